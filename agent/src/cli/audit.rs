@@ -0,0 +1,155 @@
+//! `automate audit` — reading the audit log.
+
+use std::time::Duration;
+
+use clap::Subcommand;
+
+use super::Tenant;
+use crate::db::{AuditCategory, AuditQuery, AuditRecord, AuditStore};
+use crate::prelude::*;
+use crate::services::{AppContext, AppServices};
+
+/// How often `--follow` looks for new entries.
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(clap::Args)]
+pub struct Audit {
+    #[command(flatten)]
+    tenant: Tenant,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the most recent entries, oldest first.
+    Tail {
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = 20,
+            help = "How many entries to print"
+        )]
+        lines: usize,
+
+        #[arg(
+            long,
+            help = "Only print entries in this category, such as 'workflow.run'"
+        )]
+        category: Option<String>,
+
+        #[arg(
+            long,
+            help = "Only print entries about this workflow, connection or user"
+        )]
+        subject: Option<String>,
+
+        #[arg(long, help = "Read every account's entries, ignoring --tenant")]
+        all_tenants: bool,
+
+        #[arg(short, long, help = "Keep printing new entries as they are written")]
+        follow: bool,
+    },
+}
+
+impl Audit {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let Command::Tail {
+            lines,
+            category,
+            subject,
+            all_tenants,
+            follow,
+        } = self.command;
+
+        let category = category
+            .map(|category| {
+                AuditCategory::parse(&category).ok_or_else(|| {
+                    human_errors::user(
+                        format!(
+                            "'{category}' is not a category of audit entry. Choose one of: {}.",
+                            AuditCategory::ALL
+                                .iter()
+                                .map(AuditCategory::as_str)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                        &["Leave out --category to read entries of every kind."],
+                    )
+                })
+            })
+            .transpose()?;
+
+        let query = |limit: usize| AuditQuery {
+            category,
+            subject: subject.clone(),
+            before: None,
+            limit,
+        };
+
+        // Reading across accounts is the one thing here that ignores --tenant,
+        // and only when asked for by name.
+        let scope = if all_tenants {
+            None
+        } else {
+            Some(context.tenant(self.tenant.resolve(context)?))
+        };
+
+        let mut last = None;
+        for record in read(context, scope.as_ref(), query(lines))
+            .await?
+            .into_iter()
+            .rev()
+        {
+            last = Some(record.id);
+            print(&record)?;
+        }
+
+        while follow {
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+
+            // The log can only be paged backwards, so each pass reads a generous
+            // window and keeps what is newer than the last entry printed. A burst
+            // larger than the window between two passes loses its oldest entries,
+            // which is an acceptable price for a command meant to be watched.
+            let fresh: Vec<AuditRecord> = read(context, scope.as_ref(), query(lines.max(100)))
+                .await?
+                .into_iter()
+                .filter(|record| last.is_none_or(|last| record.id > last))
+                .collect();
+
+            for record in fresh.into_iter().rev() {
+                last = Some(record.id);
+                print(&record)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads entries from one account's log, or from every account's when no scope
+/// is given.
+async fn read(
+    context: &AppContext,
+    scope: Option<&AppServices>,
+    query: AuditQuery,
+) -> Result<Vec<AuditRecord>, human_errors::Error> {
+    match scope {
+        Some(services) => services.audit().audit(query).await,
+        None => context.database().audit_all(query).await,
+    }
+}
+
+/// Prints one entry per line, so the output can be piped into tools which read
+/// JSON lines.
+fn print(record: &AuditRecord) -> Result<(), human_errors::Error> {
+    let rendered = serde_json::to_string(record).wrap_system_err(
+        "We could not write an audit entry out as JSON.",
+        &["Please report this issue to the dev team on GitHub."],
+    )?;
+
+    println!("{rendered}");
+    Ok(())
+}
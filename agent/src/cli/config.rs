@@ -0,0 +1,123 @@
+//! `automate config` — checking a configuration before deploying it.
+
+use std::cell::RefCell;
+use std::path::Path;
+
+use clap::Subcommand;
+
+use crate::prelude::*;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check that the configuration file can be loaded, without starting
+    /// anything or touching the database.
+    Check,
+}
+
+impl Command {
+    pub fn run(&self, path: &Path) -> Result<(), human_errors::Error> {
+        match self {
+            Command::Check => check(path),
+        }
+    }
+}
+
+fn check(path: &Path) -> Result<(), human_errors::Error> {
+    let config = Config::load(path)?;
+    let mut problems: Vec<String> = Vec::new();
+
+    // The loader leaves an expression naming an unset variable in place rather
+    // than failing, so that an optional setting can go unset. That is exactly
+    // the mistake worth catching before a deployment rather than after it.
+    for name in unset_variables(path)? {
+        problems.push(format!(
+            "The environment variable '{name}' is referenced but not set."
+        ));
+    }
+
+    let auth = &config.web.auth;
+    if let Some(key) = auth
+        .secret_key
+        .as_deref()
+        .filter(|key| !key.trim().is_empty())
+        && let Err(err) = crate::crypto::SecretKey::from_encoded(key)
+    {
+        problems.push(format!(
+            "The 'secret_key' under [web.auth] is not usable: {}",
+            err.description()
+        ));
+    }
+
+    for key in &auth.previous_secret_keys {
+        if let Err(err) = crate::crypto::SecretKey::from_encoded(key) {
+            problems.push(format!(
+                "One of the 'previous_secret_keys' under [web.auth] is not usable: {}",
+                err.description()
+            ));
+        }
+    }
+
+    if let Some(base_url) = &config.web.base_url
+        && let Err(err) = reqwest::Url::parse(base_url)
+    {
+        problems.push(format!("The 'base_url' under [web] is not a URL: {err}"));
+    }
+
+    if let Some(oidc) = config.web.oidc()
+        && let Err(err) = reqwest::Url::parse(&oidc.endpoint)
+    {
+        problems.push(format!(
+            "The 'endpoint' under [web.auth.oidc] is not a URL: {err}"
+        ));
+    }
+
+    if auth.multi_tenant && config.web.oidc().is_none() {
+        problems.push(
+            "'multi_tenant' is switched on, but without an identity provider everybody is the same account."
+                .to_string(),
+        );
+    }
+
+    if problems.is_empty() {
+        eprintln!("The configuration in '{}' is valid.", path.display());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        eprintln!("- {problem}");
+    }
+
+    Err(human_errors::user(
+        format!(
+            "The configuration in '{}' loaded, but {} of its settings will not work.",
+            path.display(),
+            problems.len()
+        ),
+        &["Correct the settings listed above and run this check again."],
+    ))
+}
+
+/// The environment variables a configuration file refers to which are not set.
+fn unset_variables(path: &Path) -> Result<Vec<String>, human_errors::Error> {
+    let contents = std::fs::read_to_string(path).wrap_user_err(
+        format!("We could not read your config file '{}'.", path.display()),
+        &["Ensure the file exists and is readable."],
+    )?;
+
+    let unset = RefCell::new(Vec::new());
+    crate::parsers::interpolate(&contents, |expr| {
+        // One with functions after it has somewhere to go when the variable is
+        // unset, which is usually why they were written.
+        let expression = crate::parsers::Expression::parse(expr)?;
+        if let Some(name) = expression.path().and_then(|path| path.strip_prefix("env."))
+            && expression.is_bare()
+            && std::env::var(name).is_err()
+        {
+            unset.borrow_mut().push(name.to_string());
+        }
+
+        Ok(String::new())
+    })?;
+
+    Ok(unset.into_inner())
+}
@@ -0,0 +1,42 @@
+//! `automate connections` — the links an account has made to other services.
+
+use clap::Subcommand;
+
+use super::Tenant;
+use crate::connections::ConnectionStore;
+use crate::services::AppContext;
+
+#[derive(clap::Args)]
+pub struct Connections {
+    #[command(flatten)]
+    tenant: Tenant,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List an account's connections, without their credentials.
+    List,
+}
+
+impl Connections {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let tenant = self.tenant.resolve(context)?;
+        let store = ConnectionStore::new(context.tenant(tenant.clone()), tenant);
+
+        match self.command {
+            Command::List => {
+                let connections: Vec<_> = store
+                    .list()
+                    .await?
+                    .iter()
+                    .map(crate::connections::Connection::to_summary)
+                    .collect();
+
+                super::print(&connections)
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Administrative commands.
+//!
+//! Everything the web UI can do requires a browser session, and with an
+//! identity provider configured that means an interactive sign-in. That is the
+//! right gate for people and the wrong one for somebody scripting changes to an
+//! installation they operate, who already holds something stronger than a
+//! session: the configuration file and the database it points at.
+//!
+//! These commands work on that database directly, without starting the web
+//! server or the job host. They are safe to run beside a running agent, which
+//! picks up what they change the same way it picks up a change made through
+//! the API — the database is the only thing the two share.
+//!
+//! # Tenants
+//!
+//! Every command that reads or changes somebody's records acts for one account,
+//! named with `--tenant` and defaulting to the installation's local account,
+//! which is the one a single-user installation keeps everything in. There is no
+//! way to act on several accounts at once except where a command says so, for
+//! the same reason the API has none: a typo should not be able to reach every
+//! user's records.
+//!
+//! # Output
+//!
+//! Commands that describe something print the same JSON the REST API returns
+//! for it, so that a script written against one works against the other.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use clap::Subcommand;
+use tracing_batteries::Session;
+
+use crate::prelude::*;
+use crate::services::AppContext;
+
+mod audit;
+mod config;
+mod connections;
+mod queue;
+mod run;
+mod users;
+mod workflows;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export, import and check workflows.
+    Workflows(workflows::Workflows),
+
+    /// Inspect the connections an account has made.
+    Connections(connections::Connections),
+
+    /// Manage the accounts that have signed in.
+    #[command(subcommand)]
+    Users(users::Command),
+
+    /// Inspect and clear queued work.
+    Queue(queue::Queue),
+
+    /// Run one workflow here and now, reporting what it collected and published.
+    Run(run::Run),
+
+    /// Read the audit log.
+    Audit(audit::Audit),
+
+    /// Check the configuration file.
+    #[command(subcommand)]
+    Config(config::Command),
+}
+
+impl Command {
+    /// Runs the command against the installation the configuration describes.
+    pub async fn run(
+        self,
+        config: &Path,
+        session: Arc<Session>,
+    ) -> Result<(), human_errors::Error> {
+        // Checking a configuration must not create the database it names, so it
+        // is handled before anything is opened.
+        if let Self::Config(command) = &self {
+            return command.run(config);
+        }
+
+        let context = AppContext::open(Config::load(config)?, session).await?;
+
+        match self {
+            Self::Workflows(command) => command.run(&context).await,
+            Self::Connections(command) => command.run(&context).await,
+            Self::Users(command) => command.run(&context).await,
+            Self::Queue(command) => command.run(&context).await,
+            Self::Run(command) => command.run(&context).await,
+            Self::Audit(command) => command.run(&context).await,
+            Self::Config(_) => unreachable!("handled before the database was opened"),
+        }
+    }
+}
+
+/// The account a command acts for.
+#[derive(clap::Args)]
+pub struct Tenant {
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "The account to act for, defaulting to the installation's local account"
+    )]
+    tenant: Option<String>,
+}
+
+impl Tenant {
+    pub fn resolve(&self, context: &AppContext) -> Result<TenantId, human_errors::Error> {
+        match self.tenant.as_deref() {
+            None => Ok(context.config().web.local_tenant()),
+            Some(name) => TenantId::new(name).map_err(|err| {
+                human_errors::user(
+                    format!("'{name}' is not an account name we could use: {err}"),
+                    &["Leave out --tenant to act for the installation's local account."],
+                )
+            }),
+        }
+    }
+}
+
+/// Writes a value to standard output as JSON.
+pub fn print(value: &impl Serialize) -> Result<(), human_errors::Error> {
+    let rendered = serde_json::to_string_pretty(value).wrap_system_err(
+        "We could not write the result of this command out as JSON.",
+        &["Please report this issue to the dev team on GitHub."],
+    )?;
+
+    println!("{rendered}");
+    Ok(())
+}
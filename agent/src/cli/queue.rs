@@ -0,0 +1,74 @@
+//! `automate queue` — work waiting to run.
+
+use clap::Subcommand;
+
+use super::Tenant;
+use crate::prelude::*;
+use crate::services::AppContext;
+
+#[derive(clap::Args)]
+pub struct Queue {
+    #[command(flatten)]
+    tenant: Tenant,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List an account's queued messages, soonest first.
+    Inspect {
+        #[arg(short, long, help = "Only show messages in this partition")]
+        partition: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            default_value_t = 100,
+            help = "The most messages to read from each partition"
+        )]
+        limit: usize,
+    },
+
+    /// Remove a queued message, whether or not it is being processed.
+    Purge {
+        #[arg(help = "The partition the message is queued in")]
+        partition: String,
+
+        #[arg(help = "The message's key, as shown by 'queue inspect'")]
+        key: String,
+    },
+}
+
+impl Queue {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let services = context.tenant(self.tenant.resolve(context)?);
+
+        match self.command {
+            Command::Inspect { partition, limit } => {
+                let partitions = match partition {
+                    Some(partition) => vec![partition],
+                    None => services.queue().partitions().await?,
+                };
+
+                let now = chrono::Utc::now();
+                let mut messages: Vec<automate_api::QueueMessage> = Vec::new();
+
+                for partition in partitions {
+                    let peeked = services
+                        .queue()
+                        .peek::<_, serde_json::Value>(partition.clone(), limit)
+                        .await?;
+
+                    messages.extend(peeked.into_iter().map(|msg| msg.describe(&partition, now)));
+                }
+
+                messages.sort_by_key(|msg| msg.scheduled_at);
+
+                super::print(&messages)
+            }
+            Command::Purge { partition, key } => services.queue().purge(partition, key).await,
+        }
+    }
+}
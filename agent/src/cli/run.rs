@@ -0,0 +1,264 @@
+//! `automate run` — running one workflow by hand to see what it does.
+//!
+//! Finding out why a filter lets the wrong things through otherwise means
+//! saving it, waiting for (or forcing) a run, and reading the logs afterwards.
+//! This runs the workflow's handler once, in this process, and reports every
+//! item it collected, what the filter made of each one, and the tasks that came
+//! out the other end.
+//!
+//! The run is watched through [`crate::preview`], which is what keeps it from
+//! touching the queue: the jobs it dispatches are captured and, unless this is
+//! a dry run, handed straight to their handlers here rather than left for a
+//! running agent to pick up. Collector watermarks stay where they were unless
+//! `--save-state` is given, so running a workflow to look at it does not decide
+//! what its next scheduled run skips.
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use automate_api::WorkflowId;
+
+use super::Tenant;
+use crate::connections::CONNECTIONS_PARTITION;
+use crate::db::SqliteDatabase;
+use crate::prelude::*;
+use crate::preview::{Dispatched, Observation, Options};
+use crate::services::AppContext;
+use crate::workflow_store::{WorkflowRecord, WorkflowStore};
+use crate::workflows::WorkflowType;
+
+/// How many captured jobs a single run will go on to execute.
+///
+/// A publisher that dispatches more work is normal; one that keeps doing so is
+/// a loop, and this is here so that it ends in a report rather than a hung
+/// terminal.
+const MAX_FOLLOW_ON_JOBS: usize = 500;
+
+#[derive(clap::Args)]
+pub struct Run {
+    #[command(flatten)]
+    tenant: Tenant,
+
+    #[arg(
+        short,
+        long,
+        help = "The workflow to run: its identifier, or a TOML file describing exactly one workflow ('-' reads standard input)"
+    )]
+    workflow: String,
+
+    #[arg(
+        long,
+        help = "Report the tasks the run would publish without publishing them"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Keep the collectors' progress, so the next scheduled run skips what this one saw"
+    )]
+    save_state: bool,
+}
+
+/// What a run collected and did, as printed at the end of it.
+#[derive(Serialize)]
+struct Report {
+    workflow: WorkflowId,
+
+    #[serde(rename = "type")]
+    type_id: String,
+
+    #[serde(flatten)]
+    observation: Observation,
+
+    /// How many of the dispatched jobs were run here. Always zero for a dry
+    /// run, and short of the dispatched count by any delayed jobs, which are
+    /// reported rather than waited for.
+    published: usize,
+
+    /// Whether the collectors' watermarks were written back.
+    state_saved: bool,
+}
+
+impl Run {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let tenant = self.tenant.resolve(context)?;
+
+        let (context, record) = match self.workflow.parse::<WorkflowId>() {
+            Ok(id) => {
+                let store = WorkflowStore::new(context.tenant(tenant.clone()));
+                (context.clone(), store.get(id).await?)
+            }
+            Err(_) => {
+                if self.save_state {
+                    return Err(human_errors::user(
+                        "A workflow run from a file has no saved progress to keep.",
+                        &[
+                            "Leave out --save-state, or import the file and run the workflow by its identifier.",
+                        ],
+                    ));
+                }
+
+                sandbox(context, &tenant, Path::new(&self.workflow)).await?
+            }
+        };
+
+        let workflow = runnable(&record.type_id)?;
+        let services = context.tenant(tenant);
+        let options = Options {
+            keep_state: self.save_state,
+            live: !self.dry_run,
+            ..Default::default()
+        };
+
+        let (result, mut observation) = crate::preview::execute(
+            &services,
+            options.clone(),
+            workflow.partition(),
+            &record.config,
+            Some(record.id.to_string()),
+        )
+        .await;
+
+        // A run that failed partway is still worth reporting: what it collected
+        // before it failed is usually the clue to why.
+        let mut published = 0;
+        let mut failure = result.err();
+
+        if !self.dry_run && failure.is_none() {
+            let mut pending: VecDeque<Dispatched> =
+                observation.dispatched.iter().cloned().collect();
+
+            while let Some(job) = pending.pop_front() {
+                // A delayed job is one its author meant to wait for, such as a
+                // retry or a later clean-up, so running it now would be doing
+                // something different from what the agent would do.
+                if job.delay_seconds.is_some() {
+                    continue;
+                }
+
+                if published >= MAX_FOLLOW_ON_JOBS {
+                    warn!(
+                        "Stopped after running {published} dispatched jobs; the rest are reported but were not run."
+                    );
+                    break;
+                }
+
+                let (result, follow_on) = crate::preview::execute(
+                    &services,
+                    options.clone(),
+                    &job.partition,
+                    &job.payload,
+                    job.key.clone(),
+                )
+                .await;
+
+                published += 1;
+                pending.extend(follow_on.dispatched.iter().cloned());
+                observation.merge(follow_on);
+
+                if let Err(err) = result {
+                    failure = Some(err);
+                    break;
+                }
+            }
+        }
+
+        // The report ends up in terminals and CI logs, and a dispatched job can
+        // carry the credential its handler acts with, so its payload is blanked
+        // by field name the same way a preview's is. The jobs have already run
+        // with the real thing by now.
+        for job in &mut observation.dispatched {
+            job.payload = crate::runs::redact(&job.payload, &[]);
+        }
+
+        super::print(&Report {
+            workflow: record.id,
+            type_id: record.type_id,
+            observation,
+            published,
+            state_saved: self.save_state,
+        })?;
+
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Looks up a workflow type, refusing the ones that need something to run on.
+fn runnable(type_id: &str) -> Result<&'static dyn WorkflowType, human_errors::Error> {
+    let workflow = crate::workflows::lookup(type_id)?;
+
+    match workflow.descriptor().trigger {
+        automate_api::WorkflowTrigger::Cron { .. } => Ok(workflow),
+        automate_api::WorkflowTrigger::Event { .. } => Err(human_errors::user(
+            format!(
+                "A {type_id} workflow only runs when another workflow emits an event it subscribes to, so there is nothing to run it with here."
+            ),
+            &[
+                "Run the workflow it follows instead, and read what this one did in its run history.",
+            ],
+        )),
+        _ => Err(human_errors::user(
+            format!(
+                "A {type_id} workflow only runs when a webhook delivery arrives, so there is nothing to run it with here."
+            ),
+            &["Send it a delivery instead, and read what it did in the workflow's run history."],
+        )),
+    }
+}
+
+/// Builds a throwaway database holding the one workflow a file describes.
+///
+/// The account's connections are copied across as they are stored. They stay
+/// sealed the whole way, and open in the copy because it is filed under the
+/// same account name with the same keys; nothing else of the account's comes
+/// along.
+async fn sandbox(
+    context: &AppContext,
+    tenant: &TenantId,
+    file: &Path,
+) -> Result<(AppContext, WorkflowRecord), human_errors::Error> {
+    let document = super::workflows::read(file)?;
+    let entries = crate::workflow_toml::read(&document)?.unwrap_or_default();
+
+    let [(id, draft)]: [_; 1] = entries.try_into().map_err(|entries: Vec<_>| {
+        human_errors::user(
+            format!(
+                "'{}' describes {} workflows, and only one can be run at a time.",
+                file.display(),
+                entries.len()
+            ),
+            &["Keep only the workflow you want to try in the file, or pass a stored workflow's identifier instead."],
+        )
+    })?;
+
+    // Checked before anything is saved, because saving a webhook workflow
+    // fails for reasons that have nothing to do with why it cannot be run.
+    runnable(&draft.type_id)?;
+
+    let sandbox = context.with_database(SqliteDatabase::open_in_memory().await?);
+    let services = sandbox.tenant(tenant.clone());
+
+    let connections = context
+        .tenant(tenant.clone())
+        .kv()
+        .list::<serde_json::Value>(CONNECTIONS_PARTITION)
+        .await?;
+    for (key, connection) in connections {
+        services
+            .kv()
+            .set(CONNECTIONS_PARTITION, key, connection)
+            .await?;
+    }
+
+    let store = WorkflowStore::new(services);
+    let workflow = match id {
+        Some(id) => store.upsert(id, draft).await?,
+        None => store.create(draft).await?,
+    };
+
+    let record = store.get(workflow.id).await?;
+    Ok((sandbox, record))
+}
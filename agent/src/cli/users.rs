@@ -0,0 +1,92 @@
+//! `automate users` — the accounts that have signed in.
+//!
+//! The registry belongs to the installation rather than to any one account, so
+//! these commands take no `--tenant`.
+
+use clap::Subcommand;
+
+use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditStore};
+use crate::prelude::*;
+use crate::services::AppContext;
+use crate::users::UserRegistry;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List every account that has signed in.
+    List,
+
+    /// Make an account an administrator, whatever the configured filter says.
+    Promote {
+        #[arg(help = "The account to promote, which must have signed in at least once")]
+        username: String,
+
+        #[arg(long, help = "Withdraw a promotion made here instead of granting one")]
+        revoke: bool,
+    },
+}
+
+impl Command {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let registry = UserRegistry::new(context.tenant(TenantId::system()));
+
+        match self {
+            Command::List => {
+                let accounts: Vec<_> = registry
+                    .list()
+                    .await?
+                    .iter()
+                    .map(crate::users::User::to_account)
+                    .collect();
+
+                super::print(&accounts)
+            }
+            Command::Promote { username, revoke } => {
+                let username = TenantId::new(&username).map_err(|err| {
+                    human_errors::user(
+                        format!("'{username}' is not an account name we could use: {err}"),
+                        &["Run 'automate users list' to see the accounts that have signed in."],
+                    )
+                })?;
+
+                let Some(user) = registry.set_promoted(&username, !revoke).await? else {
+                    return Err(human_errors::user(
+                        format!("Nobody has signed in as '{username}'."),
+                        &[
+                            "Have them sign in once first, so that the account exists to be promoted.",
+                            "Run 'automate users list' to see the accounts that have signed in.",
+                        ],
+                    ));
+                };
+
+                let (action, message) = if revoke {
+                    (
+                        "demoted",
+                        "Withdrew administrator rights from the command line.",
+                    )
+                } else {
+                    (
+                        "promoted",
+                        "Granted administrator rights from the command line.",
+                    )
+                };
+
+                // Recorded against the system tenant, where the registry lives,
+                // alongside the rest of the installation's administration.
+                let entry =
+                    AuditEntry::new(AuditCategory::Administration, action, AuditOutcome::Success)
+                        .subject(&username)
+                        .message(message);
+                if let Err(err) = context
+                    .tenant(TenantId::system())
+                    .audit()
+                    .record(entry)
+                    .await
+                {
+                    warn!(error = %err, "Failed to record a change of administrator in the audit log.");
+                }
+
+                super::print(&user.to_account())
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! `automate workflows` — moving workflows in and out of files.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+
+use super::Tenant;
+use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditStore};
+use crate::prelude::*;
+use crate::services::{AppContext, AppServices};
+use crate::workflow_store::WorkflowStore;
+
+#[derive(clap::Args)]
+pub struct Workflows {
+    #[command(flatten)]
+    tenant: Tenant,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write an account's workflows out as TOML.
+    Export {
+        #[arg(
+            short,
+            long,
+            help = "Where to write the file, defaulting to standard output"
+        )]
+        output: Option<PathBuf>,
+    },
+
+    /// Apply a TOML file to an account's workflows.
+    Import {
+        #[arg(help = "The file to apply, or '-' to read standard input")]
+        file: PathBuf,
+
+        #[arg(long, help = "Delete workflows which the file does not mention")]
+        prune: bool,
+    },
+
+    /// Check a TOML file without applying it.
+    Validate {
+        #[arg(help = "The file to check, or '-' to read standard input")]
+        file: PathBuf,
+    },
+}
+
+impl Workflows {
+    pub async fn run(self, context: &AppContext) -> Result<(), human_errors::Error> {
+        let services = context.tenant(self.tenant.resolve(context)?);
+        let store =
+            WorkflowStore::new(services.clone()).with_index(context.tenant(TenantId::system()));
+
+        match self.command {
+            Command::Export { output } => {
+                let document = crate::workflow_toml::export(&store.records().await?)?;
+
+                match output {
+                    Some(path) => std::fs::write(&path, document).wrap_user_err(
+                        format!("We could not write the workflows to '{}'.", path.display()),
+                        &["Check that the directory exists and that you may write to it."],
+                    )?,
+                    None => print!("{document}"),
+                }
+
+                Ok(())
+            }
+            Command::Import { file, prune } => {
+                let document = read(&file)?;
+                let summary = crate::workflow_toml::import(&store, &document, prune).await?;
+
+                // Arming the schedules here rather than leaving it to a running
+                // agent is what lets an import take effect without a restart.
+                crate::jobs::CronJob::reconcile(&services).await?;
+                record_import(&services, &summary).await;
+
+                super::print(&summary)
+            }
+            Command::Validate { file } => {
+                let document = read(&file)?;
+                let count = crate::workflow_toml::validate(&store, &document)?;
+
+                eprintln!("The file describes {count} workflows, all of which could be applied.");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads a file named on the command line, where `-` means standard input.
+pub fn read(file: &Path) -> Result<String, human_errors::Error> {
+    if file.as_os_str() == "-" {
+        let mut document = String::new();
+        std::io::stdin()
+            .read_to_string(&mut document)
+            .wrap_user_err(
+                "We could not read the workflows from standard input.",
+                &["Check that what you are piping in is text, as produced by exporting your workflows."],
+            )?;
+
+        return Ok(document);
+    }
+
+    std::fs::read_to_string(file).wrap_user_err(
+        format!("We could not read the workflows from '{}'.", file.display()),
+        &["Check that the file exists and that you may read it."],
+    )
+}
+
+async fn record_import(services: &AppServices, summary: &crate::workflow_toml::ImportSummary) {
+    let entry = AuditEntry::new(
+        AuditCategory::WorkflowConfig,
+        "imported",
+        AuditOutcome::Success,
+    )
+    .message(format!(
+        "Applied a workflow file from the command line: {} created, {} updated, {} deleted.",
+        summary.created, summary.updated, summary.deleted
+    ));
+
+    if let Err(err) = services.audit().record(entry).await {
+        warn!(error = %err, "Failed to record a workflow import in the audit log.");
+    }
+}
@@ -70,7 +70,7 @@ impl DifferentialCollector for CalendarCollector {
             .http_client()
             .get(&self.url)
             .header("Accept", "text/calendar")
-            .send()
+            .send_counted("calendar")
             .await
             .wrap_user_err(
                 "We were unable to fetch your calendar.",
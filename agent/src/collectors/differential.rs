@@ -91,7 +91,11 @@ where
             }
         }
 
-        services.kv().set(partition, key, items).await?;
+        // Held back while a run is only being previewed, for the same reason as
+        // an incremental collector's watermark.
+        if crate::preview::keeps_state() {
+            services.kv().set(partition, key, items).await?;
+        }
 
         Ok(output)
     }
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use tracing_batteries::prelude::*;
 
 use crate::filter::Filterable;
+use crate::metrics::SendCounted;
 
 use super::{Collector, IncrementalCollector};
 
@@ -47,7 +48,7 @@ impl GitHubNotificationsCollector {
     ) -> Result<Option<GitHubSubjectInformation>, human_errors::Error> {
         if let Some(url) = &subject.url {
             let response = self.request(services, reqwest::Method::GET, url)
-                .send().await.wrap_user_err("We were unable to fetch GitHub notification subject state from GitHub.", &[
+                .send_counted("github").await.wrap_user_err("We were unable to fetch GitHub notification subject state from GitHub.", &[
                     "Make sure that your network connection is working properly.",
                     "Check https://www.githubstatus.com/ for any ongoing issues with GitHub's services.",
                 ])?;
@@ -109,13 +110,19 @@ impl GitHubNotificationsCollector {
         thread_id: &str,
         services: &(impl crate::services::Services + Send + Sync + 'static),
     ) -> Result<(), human_errors::Error> {
+        if crate::preview::suppress(|| {
+            format!("Mark the GitHub notification thread {thread_id} as done.")
+        }) {
+            return Ok(());
+        }
+
         let response = self
             .request(
                 services,
                 reqwest::Method::DELETE,
                 format!("{}/notifications/threads/{}", self.api_url, thread_id),
             )
-            .send().await.wrap_user_err("We were unable to mark the GitHub notification as read.", &[
+            .send_counted("github").await.wrap_user_err("We were unable to mark the GitHub notification as read.", &[
                 "Make sure that your network connection is working properly.",
                 "Check https://www.githubstatus.com/ for any ongoing issues with GitHub's services.",
             ])?;
@@ -204,7 +211,7 @@ impl IncrementalCollector for GitHubNotificationsCollector {
     ) -> Result<(Vec<Self::Item>, Self::Watermark), human_errors::Error> {
         let response = self.request(services, reqwest::Method::GET, format!("{}/notifications", self.api_url))
             .header("If-Modified-Since", watermark.as_deref().unwrap_or("Thu, 01 Jan 1970 00:00:00 GMT"))
-            .send().await.wrap_user_err("We were unable to fetch GitHub notifications from GitHub.", &[
+            .send_counted("github").await.wrap_user_err("We were unable to fetch GitHub notifications from GitHub.", &[
                 "Make sure that your network connection is working properly.",
                 "Check https://www.githubstatus.com/ for any ongoing issues with GitHub's services.",
             ])?;
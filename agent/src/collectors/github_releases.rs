@@ -4,8 +4,9 @@ use serde::{Deserialize, Serialize};
 use tracing_batteries::prelude::*;
 
 use crate::filter::Filterable;
+use crate::metrics::SendCounted;
 
-use super::{Collector, IncrementalCollector};
+use super::{Backfill, Collector, IncrementalCollector};
 
 /// The watermark persisted between GitHub Releases collector runs.
 ///
@@ -30,10 +31,11 @@ pub struct GitHubReleasesCollector {
     api_url: String,
     repo: String,
     api_key: Option<String>,
+    backfill: Backfill,
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GitHubReleaseItem {
     pub tag_name: String,
     pub target_commitish: String,
@@ -72,6 +74,7 @@ impl GitHubReleasesCollector {
             api_url: "https://api.github.com".into(),
             repo: repo.to_string(),
             api_key: None,
+            backfill: Backfill::All,
         }
     }
 
@@ -80,15 +83,23 @@ impl GitHubReleasesCollector {
             api_url: "https://api.github.com".into(),
             repo: repo.to_string(),
             api_key: Some(api_key.into()),
+            backfill: Backfill::All,
         }
     }
 
+    /// Limits what the first run against the repository returns.
+    pub fn with_backfill(mut self, backfill: Backfill) -> Self {
+        self.backfill = backfill;
+        self
+    }
+
     #[cfg(test)]
     pub fn new_with_url(url: impl ToString, repo: impl ToString) -> Self {
         Self {
             api_url: url.to_string(),
             repo: repo.to_string(),
             api_key: None,
+            backfill: Backfill::All,
         }
     }
 
@@ -102,6 +113,7 @@ impl GitHubReleasesCollector {
             api_url: url.to_string(),
             repo: repo.to_string(),
             api_key: Some(api_key.into()),
+            backfill: Backfill::All,
         }
     }
 }
@@ -130,6 +142,14 @@ impl IncrementalCollector for GitHubReleasesCollector {
         std::borrow::Cow::Owned(format!("{}/repos/{}/releases", self.api_url, self.repo))
     }
 
+    fn backfill(&self) -> Backfill {
+        self.backfill
+    }
+
+    fn published(&self, item: &Self::Item) -> Option<chrono::DateTime<Utc>> {
+        Some(item.published_at)
+    }
+
     #[instrument(
         "collectors.github_releases.fetch_since",
         skip(self, services),
@@ -161,7 +181,7 @@ impl IncrementalCollector for GitHubReleasesCollector {
         }
 
         let response = request
-            .send().await.wrap_user_err("We were unable to fetch GitHub releases from GitHub.", &[
+            .send_counted("github").await.wrap_user_err("We were unable to fetch GitHub releases from GitHub.", &[
                 "Make sure that your network connection is working properly.",
                 "Check https://www.githubstatus.com/ for any ongoing issues with GitHub's services.",
             ])?;
@@ -1,4 +1,8 @@
 use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use tracing_batteries::prelude::*;
 
 use crate::db::StateKey;
@@ -17,6 +21,23 @@ pub trait IncrementalCollector: Collector {
         StateKey::new(self.partition(), self.key())
     }
 
+    /// What to hand back from a run that has no watermark to start from.
+    ///
+    /// Everything, unless the collector was told otherwise, because that is
+    /// what every collector did before there was a choice.
+    fn backfill(&self) -> Backfill {
+        Backfill::All
+    }
+
+    /// When an item appeared, for the policies that pick items by age.
+    ///
+    /// A collector that cannot say is only ever asked for everything or
+    /// nothing; an item without a date counts as older than any with one.
+    fn published(&self, item: &Self::Item) -> Option<DateTime<Utc>> {
+        let _ = item;
+        None
+    }
+
     async fn fetch_since(
         &self,
         watermark: Option<Self::Watermark>,
@@ -32,10 +53,287 @@ pub trait IncrementalCollector: Collector {
         let key = self.key();
 
         let current_watermark = services.kv().get(partition, key.clone()).await?;
+        let first_run = current_watermark.is_none();
+
+        let (mut new_items, new_watermark) = self.fetch_since(current_watermark, services).await?;
+
+        // The watermark is still taken from everything that was fetched, so the
+        // items a policy holds back are treated as seen rather than offered
+        // again next time.
+        if first_run {
+            new_items = self
+                .backfill()
+                .apply(new_items, |item| self.published(item));
+        }
 
-        let (new_items, new_watermark) = self.fetch_since(current_watermark, services).await?;
-        services.kv().set(partition, key, new_watermark).await?;
+        // Held back while a run is only being previewed, so that looking at what
+        // a workflow would collect does not also decide what its next real run
+        // skips.
+        if crate::preview::keeps_state() {
+            services.kv().set(partition, key, new_watermark).await?;
+        }
 
         Ok(new_items)
     }
 }
+
+/// What a collector hands back the first time it runs.
+///
+/// A feed that keeps fifty entries files fifty tasks on its first run unless
+/// told otherwise, which is rarely what somebody adding it wanted. The policy
+/// only decides what that first run returns: the watermark it leaves behind is
+/// the same either way, so every later run picks up from the newest item
+/// regardless of how many of the older ones were let through.
+///
+/// Resetting a workflow clears its watermark, which makes the next run a first
+/// run again, so a reset follows the same policy rather than re-filing the
+/// whole backlog.
+///
+/// Stored as a `mode` with the `count` or `since` it needs beside it, which is
+/// the shape a form can collect, and checked when it is read so that asking for
+/// the newest entries without saying how many is refused when the workflow is
+/// saved rather than when it first runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BackfillFields", into = "BackfillFields")]
+pub enum Backfill {
+    /// Everything currently there.
+    #[default]
+    All,
+
+    /// Nothing: only what appears after the first run.
+    Skip,
+
+    /// The given number of the most recently published items.
+    Newest(usize),
+
+    /// Whatever was published on or after the given day, in UTC.
+    Since(NaiveDate),
+}
+
+impl Backfill {
+    /// Narrows a first run's items to the ones this policy lets through,
+    /// keeping the order they were collected in.
+    pub fn apply<T>(
+        self,
+        items: Vec<T>,
+        published: impl Fn(&T) -> Option<DateTime<Utc>>,
+    ) -> Vec<T> {
+        match self {
+            Backfill::All => items,
+            Backfill::Skip => Vec::new(),
+            Backfill::Newest(count) => {
+                let mut order: Vec<usize> = (0..items.len()).collect();
+                order.sort_by_key(|&index| Reverse(published(&items[index])));
+                let keep: HashSet<usize> = order.into_iter().take(count).collect();
+
+                items
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| keep.contains(index))
+                    .map(|(_, item)| item)
+                    .collect()
+            }
+            Backfill::Since(day) => {
+                let since = day.and_time(chrono::NaiveTime::MIN).and_utc();
+
+                items
+                    .into_iter()
+                    .filter(|item| published(item).is_some_and(|at| at >= since))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// How a [`Backfill`] is stored and collected.
+#[derive(Default, Serialize, Deserialize)]
+struct BackfillFields {
+    #[serde(default)]
+    mode: BackfillMode,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    since: Option<NaiveDate>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BackfillMode {
+    #[default]
+    All,
+    Skip,
+    Newest,
+    Since,
+}
+
+impl TryFrom<BackfillFields> for Backfill {
+    type Error = String;
+
+    fn try_from(fields: BackfillFields) -> Result<Self, Self::Error> {
+        match fields.mode {
+            BackfillMode::All => Ok(Backfill::All),
+            BackfillMode::Skip => Ok(Backfill::Skip),
+            BackfillMode::Newest => fields.count.map(Backfill::Newest).ok_or_else(|| {
+                "a backfill of the newest entries needs a count of how many to take".to_string()
+            }),
+            BackfillMode::Since => fields.since.map(Backfill::Since).ok_or_else(|| {
+                "a backfill since a date needs the date, written as YYYY-MM-DD".to_string()
+            }),
+        }
+    }
+}
+
+impl From<Backfill> for BackfillFields {
+    fn from(backfill: Backfill) -> Self {
+        match backfill {
+            Backfill::All => BackfillFields::default(),
+            Backfill::Skip => BackfillFields {
+                mode: BackfillMode::Skip,
+                ..Default::default()
+            },
+            Backfill::Newest(count) => BackfillFields {
+                mode: BackfillMode::Newest,
+                count: Some(count),
+                ..Default::default()
+            },
+            Backfill::Since(since) => BackfillFields {
+                mode: BackfillMode::Since,
+                since: Some(since),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// The fields that configure a workflow's [`Backfill`], for the workflow types
+/// whose collector supports one.
+///
+/// Expects the configuration to hold it as `backfill`, the same way
+/// [`crate::todoist_target_fields!`] expects a `todoist`.
+#[macro_export]
+macro_rules! backfill_fields {
+    ($ty:ty) => {{
+        let mode = automate_api::FieldDescriptor::new(
+            format!("{}.mode", $crate::config_path!($ty: backfill)),
+            "First run",
+            automate_api::FieldKind::Select {
+                options: vec![
+                    automate_api::OptionItem::new("all", "Everything currently there"),
+                    automate_api::OptionItem::new("skip", "Nothing, only what comes next"),
+                    automate_api::OptionItem::new("newest", "Only the newest few"),
+                    automate_api::OptionItem::new("since", "Everything since a date"),
+                ],
+            },
+        )
+        .with_help(
+            "What the first run files, and the next run after a reset. Later runs only ever file what is new.",
+        )
+        .with_default("all");
+
+        let count = automate_api::FieldDescriptor::new(
+            format!("{}.count", $crate::config_path!($ty: backfill)),
+            "How many",
+            automate_api::FieldKind::Number {
+                min: Some(1.0),
+                max: None,
+                step: Some(1.0),
+            },
+        )
+        .with_help("How many of the newest to file, when only the newest few are wanted.");
+
+        let since = automate_api::FieldDescriptor::new(
+            format!("{}.since", $crate::config_path!($ty: backfill)),
+            "Since",
+            automate_api::FieldKind::Text {
+                placeholder: Some("2024-01-31".into()),
+            },
+        )
+        .with_help("The earliest day to file from, when filing everything since a date.");
+
+        [mode, count, since]
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(2024, 4, day)
+            .unwrap()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+    }
+
+    fn items() -> Vec<(&'static str, Option<DateTime<Utc>>)> {
+        vec![
+            ("third", Some(day(3))),
+            ("undated", None),
+            ("first", Some(day(1))),
+            ("second", Some(day(2))),
+        ]
+    }
+
+    fn names(items: Vec<(&'static str, Option<DateTime<Utc>>)>) -> Vec<&'static str> {
+        items.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn the_newest_items_are_kept_in_the_order_they_were_collected() {
+        let kept = Backfill::Newest(2).apply(items(), |(_, at)| *at);
+        assert_eq!(
+            names(kept),
+            vec!["third", "second"],
+            "the two most recent items should be kept, and not reordered by the choice",
+        );
+    }
+
+    #[test]
+    fn an_undated_item_is_never_one_of_the_newest() {
+        let kept = Backfill::Newest(3).apply(items(), |(_, at)| *at);
+        assert_eq!(
+            names(kept),
+            vec!["third", "first", "second"],
+            "an item that cannot say when it appeared should lose to every item that can",
+        );
+    }
+
+    #[test]
+    fn items_since_a_day_include_that_day() {
+        let kept = Backfill::Since(day(2).date_naive()).apply(items(), |(_, at)| *at);
+        assert_eq!(
+            names(kept),
+            vec!["third", "second"],
+            "an item published at the start of the chosen day should be kept",
+        );
+    }
+
+    #[test]
+    fn skipping_keeps_nothing_and_all_keeps_everything() {
+        assert!(Backfill::Skip.apply(items(), |(_, at)| *at).is_empty());
+        assert_eq!(Backfill::All.apply(items(), |(_, at)| *at).len(), 4);
+    }
+
+    #[test]
+    fn a_policy_is_stored_as_the_fields_a_form_collects() {
+        let stored = serde_json::to_value(Backfill::Newest(5)).unwrap();
+        assert_eq!(stored, serde_json::json!({ "mode": "newest", "count": 5 }));
+
+        let read: Backfill =
+            serde_json::from_value(serde_json::json!({ "mode": "since", "since": "2024-04-02" }))
+                .unwrap();
+        assert_eq!(read, Backfill::Since(day(2).date_naive()));
+    }
+
+    #[test]
+    fn a_policy_missing_what_it_needs_is_refused() {
+        let err = serde_json::from_value::<Backfill>(serde_json::json!({ "mode": "newest" }))
+            .expect_err("the newest entries without a count should not be accepted");
+        assert!(
+            err.to_string().contains("count"),
+            "the error should say what is missing: {err}",
+        );
+    }
+}
@@ -11,7 +11,7 @@ mod youtube;
 
 #[allow(dead_code)]
 pub use differential::{Diff, DifferentialCollector};
-pub use incremental::IncrementalCollector;
+pub use incremental::{Backfill, IncrementalCollector};
 
 pub use calendar::CalendarCollector;
 pub use github_notifications::{
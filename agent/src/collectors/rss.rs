@@ -1,7 +1,7 @@
 use crate::prelude::*;
 use std::borrow::Cow;
 
-use crate::collectors::{Collector, incremental::IncrementalCollector};
+use crate::collectors::{Backfill, Collector, incremental::IncrementalCollector};
 use chrono::{DateTime, Utc};
 use feed_rs::{model::Entry, parser::parse};
 
@@ -25,14 +25,22 @@ pub struct RssWatermark {
 
 pub struct RssCollector {
     pub feed_url: String,
+    backfill: Backfill,
 }
 
 impl RssCollector {
     pub fn new(feed_url: impl ToString) -> Self {
         Self {
             feed_url: feed_url.to_string(),
+            backfill: Backfill::All,
         }
     }
+
+    /// Limits what the first run against this feed returns.
+    pub fn with_backfill(mut self, backfill: Backfill) -> Self {
+        self.backfill = backfill;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -59,6 +67,14 @@ impl IncrementalCollector for RssCollector {
         Cow::Owned(self.feed_url.clone())
     }
 
+    fn backfill(&self) -> Backfill {
+        self.backfill
+    }
+
+    fn published(&self, item: &Self::Item) -> Option<DateTime<Utc>> {
+        item.published
+    }
+
     #[instrument("collectors.rss.fetch_since", skip(self, services), err(Display))]
     async fn fetch_since(
         &self,
@@ -77,7 +93,7 @@ impl IncrementalCollector for RssCollector {
             request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
         }
 
-        let response = request.send().await.wrap_user_err(
+        let response = request.send_counted("rss").await.wrap_user_err(
             format!("Failed to fetch RSS feed from URL '{}'.", self.feed_url),
             &[
                 "Check that the URL is correct and that the server is reachable.",
@@ -183,9 +199,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         let (items, _) = collector.fetch_since(None, &services).await.unwrap();
@@ -198,6 +212,72 @@ mod tests {
         assert_eq!(items[3].title.as_ref().unwrap().content, "Cursive Letters");
     }
 
+    #[tokio::test]
+    async fn a_first_run_that_skips_the_backlog_still_remembers_where_the_feed_is() {
+        let mock_server = MockServer::start().await;
+        let test_data = crate::testing::get_test_file_contents("xkcd.rss.xml");
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(test_data))
+            .mount(&mock_server)
+            .await;
+
+        let collector = RssCollector::new(mock_server.uri()).with_backfill(Backfill::Skip);
+        let services = crate::testing::mock_services().await.unwrap();
+
+        let items = collector.fetch(&services).await.unwrap();
+        assert!(
+            items.is_empty(),
+            "a workflow told to skip the backlog should file nothing on its first run",
+        );
+
+        let watermark: Option<RssWatermark> = services
+            .kv()
+            .get(collector.partition(), collector.key())
+            .await
+            .unwrap();
+        assert_eq!(
+            watermark.map(|watermark| watermark.published.date_naive()),
+            chrono::NaiveDate::from_ymd_opt(2024, 4, 3),
+            "the newest entry should be remembered, or the skipped ones come back next run",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_reset_collector_follows_its_backfill_policy_again() {
+        let mock_server = MockServer::start().await;
+        let test_data = crate::testing::get_test_file_contents("xkcd.rss.xml");
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(test_data))
+            .mount(&mock_server)
+            .await;
+
+        let collector = RssCollector::new(mock_server.uri()).with_backfill(Backfill::Newest(1));
+        let services = crate::testing::mock_services().await.unwrap();
+
+        assert_eq!(collector.fetch(&services).await.unwrap().len(), 1);
+        assert!(
+            collector.fetch(&services).await.unwrap().is_empty(),
+            "a second run has a watermark, so nothing in the feed is new to it",
+        );
+
+        let state = collector.state();
+        services
+            .kv()
+            .remove(state.partition, state.key)
+            .await
+            .unwrap();
+
+        let items = collector.fetch(&services).await.unwrap();
+        assert_eq!(
+            items.len(),
+            1,
+            "the run after a reset is a first run, and should take the newest entry only",
+        );
+        assert_eq!(items[0].title.as_ref().unwrap().content, "Eclipse Clouds");
+    }
+
     #[tokio::test]
     async fn test_rss_collector_fetch_with_watermark() {
         let mock_server = MockServer::start().await;
@@ -208,9 +288,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         // Watermark set to filter items on or before April 1, 2024
@@ -241,9 +319,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         let (items, watermark) = collector.fetch_since(None, &services).await.unwrap();
@@ -271,9 +347,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         let previous = DateTime::parse_from_rfc2822("Mon, 01 Apr 2024 04:00:00 -0000")
@@ -316,9 +390,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         let (items, watermark) = collector.fetch_since(None, &services).await.unwrap();
@@ -354,9 +426,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let collector = RssCollector {
-            feed_url: mock_server.uri(),
-        };
+        let collector = RssCollector::new(mock_server.uri());
         let services = crate::testing::mock_services().await.unwrap();
 
         let previous = DateTime::parse_from_rfc2822("Mon, 01 Apr 2024 04:00:00 -0000")
@@ -4,11 +4,13 @@ use crate::{
 };
 use chrono::{DateTime, Utc};
 use feed_rs::model::Entry;
+use serde::{Deserialize, Serialize};
 use tracing_batteries::prelude::*;
 
 pub struct XkcdCollector(RssCollector);
 
 #[allow(dead_code)]
+#[derive(Serialize, Deserialize)]
 pub struct XkcdItem {
     pub title: String,
     pub url: String,
@@ -53,6 +55,11 @@ impl XkcdCollector {
         Self(RssCollector::new("https://xkcd.com/rss.xml"))
     }
 
+    /// Limits what the first run against the feed returns.
+    pub fn with_backfill(self, backfill: crate::collectors::Backfill) -> Self {
+        Self(self.0.with_backfill(backfill))
+    }
+
     /// Where the feed watermark this collector shares with the RSS collector is
     /// kept.
     pub fn state(&self) -> crate::db::StateKey {
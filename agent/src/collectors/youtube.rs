@@ -4,11 +4,13 @@ use crate::{
 };
 use chrono::{DateTime, Utc};
 use feed_rs::model::Entry;
+use serde::{Deserialize, Serialize};
 use tracing_batteries::prelude::*;
 
 pub struct YouTubeCollector(RssCollector);
 
 #[allow(dead_code)]
+#[derive(Serialize, Deserialize)]
 pub struct YouTubeItem {
     pub channel: String,
     pub title: String,
@@ -35,6 +37,11 @@ impl YouTubeCollector {
         )))
     }
 
+    /// Limits what the first run against the feed returns.
+    pub fn with_backfill(self, backfill: crate::collectors::Backfill) -> Self {
+        Self(self.0.with_backfill(backfill))
+    }
+
     /// Where the feed watermark this collector shares with the RSS collector is
     /// kept.
     pub fn state(&self) -> crate::db::StateKey {
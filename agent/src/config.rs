@@ -18,6 +18,12 @@ pub struct Config {
     pub web: WebConfig,
     #[serde(default)]
     pub audit: AuditConfig,
+    #[serde(default)]
+    pub deliveries: DeliveryConfig,
+    #[serde(default)]
+    pub runs: RunConfig,
+    #[serde(default)]
+    pub mail: Option<MailConfig>,
 }
 
 impl Config {
@@ -55,19 +61,29 @@ impl Config {
             ],
         )?;
 
-        // Interpolate environment variables before parsing TOML
+        // Interpolate environment variables before parsing TOML. The expressions
+        // are the same ones a workflow's templates use, so a setting can carry
+        // its own fallback with `${{ env.NAME | default("...") }}`.
         let contents = crate::parsers::interpolate(&contents, |expr| {
-            let expr = expr.trim();
-            if let Some(var_name) = expr.strip_prefix("env.") {
-                Ok(std::env::var(var_name).unwrap_or_else(|_| format!("${{{{ {} }}}}", expr)))
-            } else {
-                Err(human_errors::user(
-                    format!("Unknown interpolation expression: '{}'", expr),
+            let expression = crate::parsers::Expression::parse(expr)?;
+
+            let Some(var_name) = expression.path().and_then(|path| path.strip_prefix("env."))
+            else {
+                return Err(human_errors::user(
+                    format!("Unknown interpolation expression: '{}'", expr.trim()),
                     &[
                         "Currently, only 'env.VARIABLE_NAME' expressions are supported.",
                         "Use '\\${{ ... }}' to escape literal text that looks like an expression.",
                     ],
-                ))
+                ));
+            };
+
+            match std::env::var(var_name) {
+                Ok(value) => Ok(expression.render(|_| Some(serde_json::Value::String(value)))),
+                // Left in place, as it always has been, unless the expression says
+                // what to use instead.
+                Err(_) if expression.is_bare() => Ok(format!("${{{{ {} }}}}", expr.trim())),
+                Err(_) => Ok(expression.render(|_| None)),
             }
         })?;
 
@@ -135,6 +151,175 @@ impl AuditConfig {
     }
 }
 
+/// How many of a webhook workflow's deliveries are kept for inspection.
+///
+/// Much less than the audit log keeps, because these are whole requests from
+/// somebody else's service rather than one-line notes: they are there to set a
+/// sender up against and to look into last night's failure, not to be a
+/// history. Both limits apply, for the same reason they both apply to the log.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeliveryConfig {
+    /// The most deliveries any one workflow keeps. Zero keeps none.
+    #[serde(default = "default_deliveries_keep")]
+    pub keep: usize,
+
+    /// How long a delivery is kept, in days.
+    #[serde(default = "default_deliveries_retain_days")]
+    pub retain_days: u32,
+
+    /// How long a sender's delivery id is remembered, in hours, so that the
+    /// same delivery sent again within it is dropped. Zero remembers none.
+    #[serde(default = "default_deliveries_dedupe_hours")]
+    pub dedupe_hours: u32,
+
+    /// How many deliveries a minute a webhook workflow accepts when its owner
+    /// has not said. Zero accepts as many as arrive.
+    #[serde(default = "default_deliveries_per_minute")]
+    pub per_minute: u32,
+}
+
+fn default_deliveries_keep() -> usize {
+    20
+}
+
+fn default_deliveries_retain_days() -> u32 {
+    7
+}
+
+/// A day, which is longer than any of the built-in senders keeps retrying for.
+fn default_deliveries_dedupe_hours() -> u32 {
+    24
+}
+
+/// One a second, sustained. Far more than any monitoring tool sends a single
+/// workflow outside an incident, and few enough that a leaked address costs
+/// somebody a thousand tasks an hour rather than as many as a loop can post.
+fn default_deliveries_per_minute() -> u32 {
+    60
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            keep: default_deliveries_keep(),
+            retain_days: default_deliveries_retain_days(),
+            dedupe_hours: default_deliveries_dedupe_hours(),
+            per_minute: default_deliveries_per_minute(),
+        }
+    }
+}
+
+impl DeliveryConfig {
+    pub fn retain_for(&self) -> chrono::Duration {
+        chrono::Duration::days(self.retain_days as i64)
+    }
+
+    pub fn dedupe_for(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.dedupe_hours as i64)
+    }
+}
+
+/// How many of a workflow's runs are kept as its history.
+///
+/// A run is a few lines and a capped input rather than a whole request, so
+/// more are kept than deliveries are, but both limits still apply: the history
+/// is for seeing the pattern of the last day or two, and the audit log, not
+/// this, is where what changed is kept for months.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    /// The most runs any one workflow keeps. Zero keeps none beyond the
+    /// latest and the latest failure, which every workflow keeps.
+    #[serde(default = "default_runs_keep")]
+    pub keep: usize,
+
+    /// How long a run is kept, in days.
+    #[serde(default = "default_runs_retain_days")]
+    pub retain_days: u32,
+}
+
+/// Enough for a workflow running every quarter of an hour to show a day of
+/// runs, and one running daily to show a quarter.
+fn default_runs_keep() -> usize {
+    100
+}
+
+fn default_runs_retain_days() -> u32 {
+    14
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            keep: default_runs_keep(),
+            retain_days: default_runs_retain_days(),
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn retain_for(&self) -> chrono::Duration {
+        chrono::Duration::days(self.retain_days as i64)
+    }
+}
+
+/// The mail server incident notifications are sent through.
+///
+/// Optional, because most installations already have somewhere better to be
+/// told than an inbox: without it, an email destination is refused when it is
+/// saved, and one saved before it was removed is reported when it is next used
+/// rather than silently dropped.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MailConfig {
+    /// The SMTP server's host name.
+    pub host: String,
+
+    /// The port to connect to, defaulting to the usual one for `security`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+
+    /// How the connection to the server is protected.
+    #[serde(default)]
+    pub security: MailSecurity,
+
+    /// The account to sign in as, if the server wants one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Who messages are sent from, such as `Automate <automate@example.com>`.
+    pub from: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MailSecurity {
+    /// Connects in the clear and upgrades with STARTTLS, refusing a server
+    /// that will not.
+    #[default]
+    Starttls,
+
+    /// Connects over TLS from the start.
+    Tls,
+
+    /// Never encrypts. Only for a relay on the same host or network.
+    None,
+}
+
+impl MailConfig {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.security {
+            MailSecurity::Starttls => 587,
+            MailSecurity::Tls => 465,
+            MailSecurity::None => 25,
+        })
+    }
+}
+
 #[derive(Default, Clone, Deserialize)]
 pub struct ConnectionConfigs {
     #[serde(default)]
@@ -173,6 +358,14 @@ pub struct WebConfig {
     /// headers, otherwise a client could spoof them.
     #[serde(default)]
     pub trust_proxy: bool,
+
+    /// Who may read `/metrics`. Denies access by default.
+    ///
+    /// Its own filter rather than the user or admin ACL, because what scrapes
+    /// it is a Prometheus server rather than a person: it carries no token to
+    /// sign in with, and is usually recognised by its address or a header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_acl: Option<Filter>,
 }
 
 impl Default for WebConfig {
@@ -188,6 +381,7 @@ impl Default for WebConfig {
             auth: AuthConfig::default(),
             base_url: None,
             trust_proxy: false,
+            metrics_acl: None,
         }
     }
 }
@@ -204,10 +398,26 @@ impl WebConfig {
         self.auth.admin_acl.as_ref().unwrap_or(&DEFAULT_ADMIN_ACL)
     }
 
+    /// The filter deciding who may scrape the installation's metrics.
+    pub fn metrics_acl(&self) -> &Filter {
+        self.metrics_acl.as_ref().unwrap_or(&DEFAULT_ADMIN_ACL)
+    }
+
     /// The identity provider to authenticate against, if any.
     pub fn oidc(&self) -> Option<&OidcConfig> {
         self.auth.oidc.as_ref()
     }
+
+    /// The account that owns everything nobody signed in for: every request
+    /// when there is no identity provider, and everything from before one was
+    /// configured.
+    pub fn local_tenant(&self) -> TenantId {
+        self.auth
+            .local_user
+            .as_deref()
+            .and_then(|name| TenantId::new(name).ok())
+            .unwrap_or_else(TenantId::local)
+    }
 }
 
 /// Identity, access control and credential protection.
@@ -469,6 +679,7 @@ mod tests {
 
         assert_eq!(config.web.user_acl().to_string(), "false");
         assert_eq!(config.web.admin_acl().to_string(), "false");
+        assert_eq!(config.web.metrics_acl().to_string(), "false");
         assert!(config.web.oidc().is_none());
         assert!(!config.web.auth.multi_tenant);
     }
@@ -514,6 +725,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn a_mail_server_is_reached_on_the_usual_port_for_its_security() {
+        let config = parse(
+            r#"
+            [mail]
+            host = "smtp.example.com"
+            security = "tls"
+            from = "automate@example.com"
+        "#,
+        );
+
+        let mail = config.mail.expect("the mail section should be read");
+        assert_eq!(mail.security, MailSecurity::Tls);
+        assert_eq!(mail.port(), 465);
+        assert!(Config::default().mail.is_none());
+    }
+
     #[test]
     fn the_username_claim_is_optional_and_unset_by_default() {
         let config = parse(
@@ -595,4 +823,38 @@ mod tests {
         std::fs::remove_file(&config_file).ok();
         std::fs::remove_file(&env_file).ok();
     }
+
+    #[test]
+    fn an_unset_variable_can_fall_back_to_a_default() {
+        let config_file =
+            std::env::temp_dir().join(format!("test_config_{}.toml", uuid::Uuid::new_v4()));
+
+        let mut file = std::fs::File::create(&config_file).unwrap();
+        writeln!(file, "[connections.todoist.app]").unwrap();
+        writeln!(
+            file,
+            "client_id = \"${{{{ env.AUTOMATE_TEST_UNSET_CLIENT_ID | default('automate') }}}}\""
+        )
+        .unwrap();
+        writeln!(
+            file,
+            "client_secret = \"${{{{ env.AUTOMATE_TEST_UNSET_CLIENT_SECRET }}}}\""
+        )
+        .unwrap();
+        drop(file);
+
+        let config = Config::load(&config_file).unwrap();
+        let app = config.connections.todoist.app.unwrap();
+
+        assert_eq!(
+            app.client_id, "automate",
+            "an expression that says what to use instead should use it",
+        );
+        assert_eq!(
+            app.client_secret, "${{ env.AUTOMATE_TEST_UNSET_CLIENT_SECRET }}",
+            "a bare variable that is unset should be left in place, as it always has been",
+        );
+
+        std::fs::remove_file(&config_file).ok();
+    }
 }
@@ -96,7 +96,17 @@ async fn sweep(context: &AppContext, registry: &Registry) -> Result<(), human_er
                 continue;
             };
 
-            match integration.refresh(connection, &services).await {
+            let outcome = integration.refresh(connection, &services).await;
+            crate::metrics::global().refresh(
+                &connection.provider,
+                match &outcome {
+                    Ok(RefreshOutcome::Current) => "current",
+                    Ok(RefreshOutcome::NeedsReauthorization) => "needs-reauthorization",
+                    Err(_) => "failed",
+                },
+            );
+
+            match outcome {
                 Ok(RefreshOutcome::Current) => reviewed += 1,
                 Ok(RefreshOutcome::NeedsReauthorization) => {
                     expired += 1;
@@ -376,6 +376,8 @@ impl<S: Services> ConnectionStore<S> {
             return Ok(None);
         };
 
+        let was = connection.status;
+
         connection.kind = secret.kind();
         connection.expires_at = secret.expires_at();
         connection.status = ConnectionStatus::Ok;
@@ -386,6 +388,7 @@ impl<S: Services> ConnectionStore<S> {
             .seal_json(&secret, self.context(id))?;
 
         self.put(&connection).await?;
+        self.announce(&connection, was).await;
 
         Ok(Some(connection))
     }
@@ -432,10 +435,13 @@ impl<S: Services> ConnectionStore<S> {
             return Ok(None);
         };
 
+        let was = connection.status;
+
         connection.status = status;
         connection.updated_at = Utc::now();
 
         self.put(&connection).await?;
+        self.announce(&connection, was).await;
 
         Ok(Some(connection))
     }
@@ -487,6 +493,45 @@ impl<S: Services> ConnectionStore<S> {
         Ok(true)
     }
 
+    /// Tells the account when one of its connections stops working or starts
+    /// again.
+    ///
+    /// Here rather than with whoever noticed, because every provider's way of
+    /// finding out ends in one of the two writes that call this, and a
+    /// connection whose provider was left out would be exactly the one nobody
+    /// heard about. Only a change is announced, so a connection reported broken
+    /// by every run that tries it is still one incident.
+    async fn announce(&self, connection: &Connection, was: ConnectionStatus) {
+        let incident = crate::notifications::Incident::Connection {
+            id: connection.id,
+            provider: &connection.provider,
+            name: &connection.name,
+        };
+
+        match (was.is_usable(), connection.status) {
+            (true, ConnectionStatus::NeedsReauthorization) => {
+                crate::notifications::raise(
+                    &self.services,
+                    incident,
+                    "needs to be reconnected: the service no longer accepts its authorization.",
+                )
+                .await
+            }
+            (true, ConnectionStatus::Error) => {
+                crate::notifications::raise(
+                    &self.services,
+                    incident,
+                    "stopped working the last time it was used.",
+                )
+                .await
+            }
+            (false, ConnectionStatus::Ok) => {
+                crate::notifications::resolve(&self.services, incident, "is working again.").await
+            }
+            _ => {}
+        }
+    }
+
     async fn put(&self, connection: &Connection) -> Result<(), human_errors::Error> {
         self.services
             .kv()
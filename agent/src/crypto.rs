@@ -58,7 +58,7 @@ use base64::Engine;
 use sha2::{Digest, Sha256};
 use zeroize::Zeroize;
 
-use automate_api::{ConnectionId, WorkflowId};
+use automate_api::{ConnectionId, DeliveryId, WorkflowId};
 use human_errors::ResultExt;
 
 use crate::prelude::*;
@@ -253,6 +253,22 @@ pub enum SecretContext<'a> {
         tenant: &'a str,
         workflow: WorkflowId,
     },
+
+    /// A secret an account keeps for its workflows' templates, bound to its
+    /// name so that one cannot be read back under another's.
+    Variable { tenant: &'a str, name: &'a str },
+
+    /// The credentials a kept webhook delivery arrived with, bound to the
+    /// delivery so that they can only be sent again with the body they signed.
+    Delivery {
+        tenant: &'a str,
+        workflow: WorkflowId,
+        delivery: DeliveryId,
+    },
+
+    /// A value sealed only to be opened again straight away, to show the
+    /// active key still works; nothing sealed under it is ever stored.
+    Probe,
 }
 
 impl fmt::Display for SecretContext<'_> {
@@ -264,6 +280,17 @@ impl fmt::Display for SecretContext<'_> {
             Self::WebhookSecret { tenant, workflow } => {
                 write!(f, "automate/v1/webhook-secret/{tenant}/{workflow}")
             }
+            Self::Variable { tenant, name } => {
+                write!(f, "automate/v1/variable/{tenant}/{name}")
+            }
+            Self::Delivery {
+                tenant,
+                workflow,
+                delivery,
+            } => {
+                write!(f, "automate/v1/delivery/{tenant}/{workflow}/{delivery}")
+            }
+            Self::Probe => write!(f, "automate/v1/probe"),
         }
     }
 }
@@ -495,6 +522,16 @@ impl SecretStore {
         )
     }
 
+    /// Seals a throwaway value with the active key and opens it again.
+    ///
+    /// A store that loaded is not necessarily one that works: this is what the
+    /// readiness check leans on to say that credentials can still be read and
+    /// written, without touching anybody's.
+    pub fn check(&self) -> Result<(), human_errors::Error> {
+        let sealed = self.seal(b"automate", SecretContext::Probe)?;
+        self.open(&sealed, SecretContext::Probe).map(|_| ())
+    }
+
     fn key_for(&self, kid: &str) -> Result<&SecretKey, human_errors::Error> {
         self.keys
             .iter()
@@ -321,6 +321,17 @@ pub(super) async fn audit(
 /// limit lets a busy webhook fill the disk within the window, and a count limit
 /// lets a quiet installation keep entries indefinitely. The count is applied per
 /// tenant so that one noisy user cannot evict everybody else's history.
+/// How many entries the log holds, across every tenant.
+pub(super) async fn count(connection: &Connection) -> Result<u64, errors::Error> {
+    connection
+        .call(|c| {
+            let count: i64 = c.query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))?;
+            Ok::<_, tokio_rusqlite::Error>(count.max(0) as u64)
+        })
+        .await
+        .or_system_err(super::ADVICE_DB_ERROR)
+}
+
 pub(super) async fn prune(
     connection: &Connection,
     retain_for: chrono::Duration,
@@ -81,6 +81,25 @@ pub trait KeyValueStore {
         value: T,
     ) -> Result<bool, errors::Error>;
 
+    /// Changes a stored value in place, returning whatever `change` does, or
+    /// `None` when there is no value to change.
+    ///
+    /// A [`KeyValueStore::get`] followed by a [`KeyValueStore::set`] loses
+    /// whatever another writer did in between, which is fine for somebody
+    /// saving a form and wrong for a counter that several jobs take from at
+    /// once. Here the read, the change and the write are one step, so two
+    /// callers see each other's changes rather than the same starting point.
+    async fn update<T, R, F>(
+        &self,
+        partition: impl Into<Cow<'static, str>> + Send,
+        key: impl Into<Cow<'static, str>> + Send,
+        change: F,
+    ) -> Result<Option<R>, errors::Error>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static;
+
     async fn remove(
         &self,
         partition: impl Into<Cow<'static, str>> + Send,
@@ -198,6 +217,39 @@ pub struct PeekedMessage<T> {
     pub idempotency_key: Option<String>,
 }
 
+impl PeekedMessage<serde_json::Value> {
+    /// Describes this message the way the administrative views present it.
+    pub fn describe(
+        self,
+        partition: impl ToString,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> automate_api::QueueMessage {
+        let status = if self.reserved_by.is_some() {
+            automate_api::QueueStatus::Reserved
+        } else if self.hidden_until > now {
+            automate_api::QueueStatus::Delayed
+        } else {
+            automate_api::QueueStatus::Pending
+        };
+
+        let hidden_until = matches!(
+            status,
+            automate_api::QueueStatus::Reserved | automate_api::QueueStatus::Delayed
+        )
+        .then_some(self.hidden_until);
+
+        automate_api::QueueMessage {
+            partition: partition.to_string(),
+            key: self.key,
+            payload: self.payload,
+            status,
+            scheduled_at: self.scheduled_at,
+            hidden_until,
+            traceparent: self.traceparent,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[async_trait::async_trait]
 pub trait Cache {
@@ -281,7 +281,12 @@ impl SqliteDatabase {
         Ok(db)
     }
 
-    #[cfg(test)]
+    /// Opens a private database that disappears when the last handle to it is
+    /// dropped.
+    ///
+    /// Besides the tests, this is what `automate run` works against when it is
+    /// given a file rather than a stored workflow, so that trying one out
+    /// leaves nothing behind in the real database.
     pub async fn open_in_memory() -> Result<Self, errors::Error> {
         let connection = Connection::open_in_memory().await.or_system_err(&[
             "Make sure that there is enough memory available to create an in-memory database.",
@@ -342,6 +347,37 @@ impl SqliteDatabase {
         self.initialize().await
     }
 
+    /// The migration a database this build creates ends up at.
+    pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+    /// Asks the database for nothing, to find out whether it still answers.
+    pub async fn ping(&self) -> Result<(), errors::Error> {
+        self.connection
+            .call(|c| c.query_one("SELECT 1", [], |r| r.get::<_, i64>(0)))
+            .await
+            .wrap_system_err("The database did not answer.", ADVICE_DB_ERROR)
+            .map(|_| ())
+    }
+
+    /// The latest migration this database has had applied.
+    ///
+    /// Compared against [`Self::SCHEMA_VERSION`] to notice a database that has
+    /// fallen behind the build reading it, which should only happen if another
+    /// instance is still running an older release against the same file.
+    pub async fn schema_version(&self) -> Result<usize, errors::Error> {
+        self.connection
+            .call(|c| {
+                c.query_one("SELECT COALESCE(MAX(id), 0) FROM migrations", [], |r| {
+                    r.get(0)
+                })
+            })
+            .await
+            .wrap_system_err(
+                "Failed to determine the latest database migration version.",
+                ADVICE_DB_ERROR,
+            )
+    }
+
     async fn initialize(&mut self) -> Result<(), errors::Error> {
         self.connection
             .call(|c| {
@@ -358,18 +394,7 @@ impl SqliteDatabase {
                 ADVICE_DB_ERROR,
             )?;
 
-        let latest_migration: usize = self
-            .connection
-            .call(|c| {
-                c.query_one("SELECT COALESCE(MAX(id), 0) FROM migrations", [], |r| {
-                    r.get(0)
-                })
-            })
-            .await
-            .wrap_system_err(
-                "Failed to determine the latest database migration version.",
-                ADVICE_DB_ERROR,
-            )?;
+        let latest_migration = self.schema_version().await?;
 
         for (i, migration) in MIGRATIONS.iter().enumerate().skip(latest_migration) {
             self.connection
@@ -442,10 +467,15 @@ impl SqliteDatabase {
     /// That is fair in the sense of first-come-first-served, but it is not fair
     /// between tenants: somebody with a great many due messages will delay
     /// everyone else's. Per-tenant limits keep that bounded for now.
-    #[instrument("db.sqlite.dequeue_any_global", skip(self, reserve_for), fields(otel.kind=?OpenTelemetrySpanKind::Consumer), err(Display))]
+    ///
+    /// Waits for a message rather than returning empty-handed, calling
+    /// `on_idle` each time it finds nothing due, so that whoever is waiting can
+    /// still tell a quiet queue from a consumer that has stopped asking.
+    #[instrument("db.sqlite.dequeue_any_global", skip(self, reserve_for, on_idle), fields(otel.kind=?OpenTelemetrySpanKind::Consumer), err(Display))]
     pub async fn dequeue_any_global(
         &self,
         reserve_for: chrono::Duration,
+        on_idle: impl Fn() + Send,
     ) -> Result<(TenantId, super::QueueMessage<serde_json::Value>), errors::Error> {
         loop {
             let reservation_id = uuid::Uuid::new_v4().to_string();
@@ -509,6 +539,7 @@ impl SqliteDatabase {
             if let Some(message) = message {
                 return Ok(message);
             } else {
+                on_idle();
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
             }
         }
@@ -526,6 +557,64 @@ impl SqliteDatabase {
         super::audit::audit(&self.connection, None, query).await
     }
 
+    /// How many entries the audit log holds, across every tenant.
+    pub async fn audit_log_size(&self) -> Result<u64, errors::Error> {
+        super::audit::count(&self.connection).await
+    }
+
+    /// How many messages each partition holds, in each state, across every
+    /// tenant.
+    ///
+    /// For the installation's metrics, which care how far behind the job host
+    /// is rather than whose work it is behind on. The states are decided the
+    /// way [`super::PeekedMessage::describe`] decides them for the queue page,
+    /// so the two never disagree about what is stuck.
+    pub async fn queue_depth(
+        &self,
+    ) -> Result<Vec<(String, automate_api::QueueStatus, u64)>, errors::Error> {
+        let now = chrono::Utc::now();
+
+        self.connection
+            .call(move |c| {
+                let mut stmt = c
+                    .prepare(
+                        "SELECT partition, \
+                            CASE WHEN reservedBy IS NOT NULL THEN 'reserved' \
+                                 WHEN hiddenUntil > ?1 THEN 'delayed' \
+                                 ELSE 'pending' END AS status, \
+                            COUNT(*) \
+                         FROM queues GROUP BY partition, status ORDER BY partition, status",
+                    )
+                    .or_system_err(ADVICE_DB_ERROR)?;
+
+                let iter = stmt
+                    .query_map([now], |row| {
+                        let partition: String = row.get(0)?;
+                        let status: String = row.get(1)?;
+                        let count: i64 = row.get(2)?;
+                        Ok((partition, status, count.max(0) as u64))
+                    })
+                    .or_system_err(ADVICE_DB_ERROR)?;
+
+                iter.collect::<Result<Vec<_>, _>>()
+                    .or_system_err(ADVICE_DB_ERROR)
+            })
+            .await
+            .or_system_err(ADVICE_DB_ERROR)
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|(partition, status, count)| {
+                        let status = match status.as_str() {
+                            "reserved" => automate_api::QueueStatus::Reserved,
+                            "delayed" => automate_api::QueueStatus::Delayed,
+                            _ => automate_api::QueueStatus::Pending,
+                        };
+                        (partition, status, count)
+                    })
+                    .collect()
+            })
+    }
+
     /// Trims the audit log back to the configured retention.
     #[allow(dead_code)]
     pub async fn prune_audit_log(
@@ -702,6 +791,66 @@ impl KeyValueStore for TenantDb {
         Ok(inserted > 0)
     }
 
+    #[instrument("db.sqlite.update", skip(self, partition, key, change), fields(otel.kind=?OpenTelemetrySpanKind::Client), err(Display))]
+    async fn update<T, R, F>(
+        &self,
+        partition: impl Into<Cow<'static, str>> + Send,
+        key: impl Into<Cow<'static, str>> + Send,
+        change: F,
+    ) -> std::result::Result<Option<R>, errors::Error>
+    where
+        T: serde::Serialize + DeserializeOwned + Send + 'static,
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let partition = partition.into().into_owned();
+        let key = key.into().into_owned();
+        let tenant = self.tenant.to_string();
+
+        self.connection
+            .call(move |c| {
+                // Immediate, so that the write lock is taken before the read
+                // rather than after it: another connection cannot change the
+                // value between the two.
+                let tx = c
+                    .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+                    .or_system_err(ADVICE_DB_ERROR)?;
+
+                let Some(value) = tx
+                    .query_one(
+                        "SELECT value FROM kv WHERE tenant = ?1 AND partition = ?2 AND key = ?3",
+                        (&tenant, &partition, &key),
+                        |r| r.get::<_, String>(0),
+                    )
+                    .optional()
+                    .or_system_err(ADVICE_DB_ERROR)?
+                else {
+                    return Ok(None);
+                };
+
+                let mut current: T = serde_json::from_str(&value).wrap_system_err(
+                    "Failed to read a value from the key/value store.",
+                    ADVICE_REPORT_DEV,
+                )?;
+                let outcome = change(&mut current);
+                let serialized = serde_json::to_string(&current).wrap_system_err(
+                    "Failed to serialize value for storage in the key/value store.",
+                    ADVICE_REPORT_DEV,
+                )?;
+
+                tx.execute(
+                    "UPDATE kv SET value = ?4 WHERE tenant = ?1 AND partition = ?2 AND key = ?3",
+                    (&tenant, &partition, &key, serialized),
+                )
+                .or_system_err(ADVICE_DB_ERROR)?;
+                tx.commit().or_system_err(ADVICE_DB_ERROR)?;
+
+                Result::<_, human_errors::Error>::Ok(Some(outcome))
+            })
+            .await
+            .or_system_err(ADVICE_DB_ERROR)
+    }
+
     #[instrument("db.sqlite.remove", skip(self, partition, key), fields(otel.kind=?OpenTelemetrySpanKind::Client), err(Display))]
     async fn remove(
         &self,
@@ -1449,7 +1598,7 @@ mod tests {
         let mut seen = Vec::new();
         for _ in 0..2 {
             let (tenant, message) = db
-                .dequeue_any_global(chrono::Duration::minutes(1))
+                .dequeue_any_global(chrono::Duration::minutes(1), || {})
                 .await
                 .unwrap();
             seen.push((tenant, message.payload.as_str().unwrap().to_string()));
@@ -1481,7 +1630,7 @@ mod tests {
             .unwrap();
 
         let (tenant, message) = db
-            .dequeue_any_global(chrono::Duration::minutes(5))
+            .dequeue_any_global(chrono::Duration::minutes(5), || {})
             .await
             .unwrap();
 
@@ -1791,6 +1940,43 @@ mod tests {
         assert_eq!(value, Some(test_value));
     }
 
+    #[tokio::test]
+    async fn concurrent_updates_each_see_the_last_ones_change() {
+        let db = SqliteDatabase::open_in_memory()
+            .await
+            .unwrap()
+            .tenant(TenantId::local());
+
+        db.set("counters", "left", 5u32).await.unwrap();
+
+        let takers = (0..8).map(|_| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                db.update("counters", "left", |left: &mut u32| {
+                    let took = *left > 0;
+                    *left = left.saturating_sub(1);
+                    took
+                })
+                .await
+                .unwrap()
+            })
+        });
+        let taken = futures::future::join_all(takers)
+            .await
+            .into_iter()
+            .filter(|took| took.as_ref().unwrap() == &Some(true))
+            .count();
+
+        assert_eq!(taken, 5, "each unit should be taken exactly once");
+        assert_eq!(db.get::<u32>("counters", "left").await.unwrap(), Some(0));
+        assert_eq!(
+            db.update("counters", "missing", |left: &mut u32| *left)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_queue_basic() {
         let db = SqliteDatabase::open_in_memory()
@@ -2379,4 +2565,71 @@ mod tests {
             Some("pending".into())
         );
     }
+
+    #[tokio::test]
+    async fn a_database_left_at_an_older_migration_reports_how_far_it_got() {
+        let mut db = SqliteDatabase::open_in_memory_at_migration(3)
+            .await
+            .unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), 3);
+
+        db.upgrade().await.unwrap();
+        assert_eq!(
+            db.schema_version().await.unwrap(),
+            SqliteDatabase::SCHEMA_VERSION
+        );
+        db.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_queue_depth_counts_every_tenants_messages_by_partition_and_state() {
+        let db = SqliteDatabase::open_in_memory().await.unwrap();
+
+        db.tenant(alice())
+            .enqueue("rss/todoist", "due", None, None)
+            .await
+            .unwrap();
+        db.tenant(bob())
+            .enqueue("rss/todoist", "also due", None, None)
+            .await
+            .unwrap();
+        db.tenant(bob())
+            .enqueue(
+                "rss/todoist",
+                "later",
+                None,
+                Some(chrono::Duration::hours(1)),
+            )
+            .await
+            .unwrap();
+        db.tenant(alice())
+            .enqueue("webhooks/generic", "running", None, None)
+            .await
+            .unwrap();
+        db.tenant(alice())
+            .dequeue::<_, String>("webhooks/generic", chrono::Duration::minutes(5))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.queue_depth().await.unwrap(),
+            vec![
+                (
+                    "rss/todoist".to_string(),
+                    automate_api::QueueStatus::Delayed,
+                    1
+                ),
+                (
+                    "rss/todoist".to_string(),
+                    automate_api::QueueStatus::Pending,
+                    2
+                ),
+                (
+                    "webhooks/generic".to_string(),
+                    automate_api::QueueStatus::Reserved,
+                    1
+                ),
+            ]
+        );
+    }
 }
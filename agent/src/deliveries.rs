@@ -0,0 +1,679 @@
+//! The deliveries a webhook workflow was recently sent.
+//!
+//! # Why this is not the run record
+//!
+//! [`crate::runs`] keeps one record per workflow, overwritten in place, so that
+//! a busy workflow costs what a quiet one does. That answers "is it working",
+//! and keeps the one input needed to show why not. It does not answer the
+//! question somebody setting up a sender has, which is what the last few
+//! deliveries looked like side by side: which headers Grafana sends, whether
+//! Terraform's signature checked out, which of them the filter dropped and
+//! what was filed for the rest.
+//!
+//! So a webhook workflow also keeps its last few deliveries here, each as its
+//! own entry in a partition of its own. The bound is what keeps this from being
+//! the history the run record replaced: a handful of entries per workflow, for
+//! a handful of days (see [`crate::config::DeliveryConfig`]), trimmed as each
+//! new one arrives and once a day for workflows that have stopped receiving
+//! any.
+//!
+//! # What is kept
+//!
+//! The request as it arrived, less its credentials: headers and query
+//! parameters whose names look sensitive are blanked as [`crate::runs`] blanks
+//! them, the account's secrets are scrubbed wherever they appear, and a body
+//! larger than anybody would read on a page is kept as a prefix. Alongside it
+//! is what the workflow made of it, which is captured by running the handler
+//! under a [passive](crate::preview::Options::passive) observer: the same
+//! account a preview gives, of a run that really happened.
+//!
+//! What the redaction took out is not thrown away. It is sealed beside the
+//! delivery, bound to it (see [`crate::crypto::SecretContext::Delivery`]), so
+//! that a delivery sent again for real carries the signature it arrived with
+//! and is checked as it was then; see [`DeliveryStore::request`]. Nothing the
+//! API reads ever includes it.
+
+use std::collections::BTreeMap;
+
+use automate_api::{DeliveryId, DeliveryRecord, DeliverySummary, RunOutcome, TenantId, WorkflowId};
+use chrono::{DateTime, Utc};
+use human_errors::Error;
+
+use crate::crypto::{Sealed, SecretContext};
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+use crate::preview::Observation;
+use crate::runs::{REDACTED, is_sensitive, scrub, scrub_value};
+use crate::services::AppContext;
+
+/// The prefix of the partitions holding each workflow's deliveries.
+///
+/// One partition per workflow rather than one shared one, so that listing a
+/// workflow's deliveries reads only its own, and forgetting them is a matter of
+/// emptying one partition.
+pub const DELIVERIES_PARTITION: &str = "deliveries";
+
+/// The most of a delivery's body we will keep.
+///
+/// Enough for every delivery any of the built-in senders makes, and for the
+/// start of anything larger, which is what identifies it.
+const MAX_BODY_BYTES: usize = 16 * 1024;
+
+/// How many identifiers to try before giving up on finding a free one.
+///
+/// A workflow keeps a handful of deliveries in a space of four million, so a
+/// second attempt is already rare.
+const ID_ATTEMPTS: usize = 5;
+
+fn partition(workflow: WorkflowId) -> String {
+    format!("{DELIVERIES_PARTITION}/{workflow}")
+}
+
+/// A delivery as it is stored: the record anybody may read, and what was
+/// withheld from it, sealed.
+///
+/// Every read that answers the API deserialises a bare [`DeliveryRecord`],
+/// which has no field for the sealed part, so it cannot leak out by way of a
+/// handler that forgot to drop it.
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(flatten)]
+    delivery: DeliveryRecord,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    withheld: Option<Sealed>,
+}
+
+/// The parts of a request that were changed when it was kept, as they
+/// arrived.
+#[derive(Default, Serialize, Deserialize)]
+struct Withheld {
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+
+    #[serde(default)]
+    query: Option<String>,
+
+    #[serde(default)]
+    body: Option<String>,
+}
+
+impl Withheld {
+    /// Whatever keeping `event` as `kept` changed. A body kept as a prefix is
+    /// left out: it cannot be replayed, and is the one part that may be large.
+    fn between(event: &WebhookEvent, kept: &DeliveryRecord) -> Self {
+        Self {
+            headers: event
+                .headers
+                .iter()
+                .filter(|(name, value)| {
+                    kept.headers.get(&name.to_ascii_lowercase()) != Some(*value)
+                })
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            query: (kept.query != event.query).then(|| event.query.clone()),
+            body: (kept.truncated_from.is_none() && kept.body != event.body)
+                .then(|| event.body.clone()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.query.is_none() && self.body.is_none()
+    }
+}
+
+/// Describes a delivery and what became of it, redacted and capped for
+/// keeping.
+pub fn describe(
+    event: &WebhookEvent,
+    received_at: DateTime<Utc>,
+    outcome: RunOutcome,
+    message: Option<&str>,
+    observation: Observation,
+    secrets: &[String],
+) -> DeliveryRecord {
+    let headers = event
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive(name) {
+                REDACTED.to_string()
+            } else {
+                scrub(value, secrets)
+            };
+            (name.to_ascii_lowercase(), value)
+        })
+        .collect();
+
+    let body = scrub(&event.body, secrets);
+    let (body, truncated_from) = if body.len() > MAX_BODY_BYTES {
+        let mut end = MAX_BODY_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        (body[..end].to_string(), Some(body.len()))
+    } else {
+        (body, None)
+    };
+
+    DeliveryRecord {
+        id: DeliveryId::from_entropy(rand::random()),
+        received_at,
+        finished_at: Utc::now(),
+        outcome,
+        message: message.map(|message| scrub(message, secrets)),
+        headers,
+        query: redact_query(&event.query, secrets),
+        body,
+        truncated_from,
+        signature: observation.signature,
+        items: observation
+            .items
+            .into_iter()
+            .map(|mut item| {
+                item.item = scrub(&item.item, secrets);
+                item
+            })
+            .collect(),
+        published: observation
+            .dispatched
+            .into_iter()
+            .map(|dispatched| {
+                let mut task = dispatched.describe();
+                task.title = task.title.map(|title| scrub(&title, secrets));
+                task.description = task
+                    .description
+                    .map(|description| scrub(&description, secrets));
+                task.payload = scrub_value(&task.payload, secrets);
+                task
+            })
+            .collect(),
+        effects: observation
+            .effects
+            .iter()
+            .map(|effect| scrub(effect, secrets))
+            .collect(),
+        replay_of: None,
+        repeat_of: None,
+    }
+}
+
+/// Blanks the query parameters whose names look sensitive, leaving the rest as
+/// they were sent.
+///
+/// Worked on the encoded string rather than decoded and re-encoded, so that
+/// what is shown is what the sender wrote. Only the name is decoded, to
+/// recognise `api%5Ftoken` for what it is.
+fn redact_query(query: &str, secrets: &[String]) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _))
+                if urlencoding::decode(name)
+                    .map_or_else(|_| is_sensitive(name), |name| is_sensitive(&name)) =>
+            {
+                format!("{name}={REDACTED}")
+            }
+            _ => scrub(pair, secrets),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Reads and writes the deliveries kept for one tenant's workflows.
+pub struct DeliveryStore<S> {
+    services: S,
+}
+
+impl<S: Services> DeliveryStore<S> {
+    pub fn new(services: S) -> Self {
+        Self { services }
+    }
+
+    /// A workflow's deliveries, newest first, without their bodies.
+    pub async fn list(&self, workflow: WorkflowId) -> Result<Vec<DeliverySummary>, Error> {
+        Ok(self
+            .kept(workflow)
+            .await?
+            .iter()
+            .map(DeliveryRecord::summary)
+            .collect())
+    }
+
+    /// Everything kept about one delivery.
+    pub async fn get(
+        &self,
+        workflow: WorkflowId,
+        id: DeliveryId,
+    ) -> Result<Option<DeliveryRecord>, Error> {
+        let found: Option<DeliveryRecord> = self
+            .services
+            .kv()
+            .get(partition(workflow), id.to_string())
+            .await?;
+
+        // One past its retention is treated as gone, whether or not the daily
+        // trim has reached it yet, so what can be fetched matches what is listed.
+        Ok(found.filter(|delivery| delivery.received_at >= self.cutoff()))
+    }
+
+    /// The request a kept delivery was, with what was withheld from it when it
+    /// was kept put back, for sending it again.
+    pub async fn request(
+        &self,
+        workflow: WorkflowId,
+        delivery: &DeliveryRecord,
+    ) -> Result<WebhookEvent, Error> {
+        let mut event = WebhookEvent {
+            body: delivery.body.clone(),
+            query: delivery.query.clone(),
+            headers: delivery
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        };
+
+        let stored: Option<Stored> = self
+            .services
+            .kv()
+            .get(partition(workflow), delivery.id.to_string())
+            .await?;
+        let Some(sealed) = stored.and_then(|stored| stored.withheld) else {
+            return Ok(event);
+        };
+
+        let withheld: Withheld = self.services.secrets().open_json(
+            &sealed,
+            SecretContext::Delivery {
+                tenant: self.services.tenant().as_str(),
+                workflow,
+                delivery: delivery.id,
+            },
+        )?;
+
+        for (name, value) in withheld.headers {
+            event.headers.remove(&name.to_ascii_lowercase());
+            event.headers.insert(name, value);
+        }
+        if let Some(query) = withheld.query {
+            event.query = query;
+        }
+        if let Some(body) = withheld.body {
+            event.body = body;
+        }
+
+        Ok(event)
+    }
+
+    /// Keeps a delivery, and lets go of any that it pushes past the workflow's
+    /// limits.
+    ///
+    /// `event` is the request as it arrived; whatever describing it withheld is
+    /// sealed beside the record. Returns the identifier it was kept under, or
+    /// `None` where this installation keeps no deliveries at all.
+    pub async fn record(
+        &self,
+        workflow: WorkflowId,
+        mut delivery: DeliveryRecord,
+        event: &WebhookEvent,
+    ) -> Result<Option<DeliveryId>, Error> {
+        if self.services.config().deliveries.keep == 0 {
+            self.prune(workflow).await?;
+            return Ok(None);
+        }
+
+        let withheld = Withheld::between(event, &delivery);
+
+        for _ in 0..ID_ATTEMPTS {
+            let id = delivery.id;
+
+            // Sealed afresh for each identifier tried, since it is bound to it.
+            let withheld = if withheld.is_empty() {
+                None
+            } else {
+                Some(self.services.secrets().seal_json(
+                    &withheld,
+                    SecretContext::Delivery {
+                        tenant: self.services.tenant().as_str(),
+                        workflow,
+                        delivery: id,
+                    },
+                )?)
+            };
+
+            if self
+                .services
+                .kv()
+                .insert(
+                    partition(workflow),
+                    id.to_string(),
+                    Stored {
+                        delivery: delivery.clone(),
+                        withheld,
+                    },
+                )
+                .await?
+            {
+                self.prune(workflow).await?;
+                return Ok(Some(id));
+            }
+
+            delivery.id = DeliveryId::from_entropy(rand::random());
+        }
+
+        Err(human_errors::system(
+            "We could not find a free identifier to keep a webhook delivery under.",
+            &["Please report this issue to the dev team on GitHub."],
+        ))
+    }
+
+    /// Lets go of a workflow's deliveries past its limits, returning how many
+    /// were removed.
+    pub async fn prune(&self, workflow: WorkflowId) -> Result<usize, Error> {
+        let keep = self.services.config().deliveries.keep;
+        let cutoff = self.cutoff();
+
+        let mut stored: Vec<(String, DeliveryRecord)> =
+            self.services.kv().list(partition(workflow)).await?;
+        stored.sort_by(|(_, a), (_, b)| b.received_at.cmp(&a.received_at));
+
+        let mut removed = 0;
+        for (index, (key, delivery)) in stored.into_iter().enumerate() {
+            if index >= keep || delivery.received_at < cutoff {
+                self.services.kv().remove(partition(workflow), key).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Forgets a workflow's deliveries, for when the workflow itself has gone.
+    pub async fn forget(&self, workflow: WorkflowId) -> Result<(), Error> {
+        let stored: Vec<(String, serde_json::Value)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        for (key, _) in stored {
+            self.services.kv().remove(partition(workflow), key).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The deliveries within the workflow's retention, newest first.
+    async fn kept(&self, workflow: WorkflowId) -> Result<Vec<DeliveryRecord>, Error> {
+        let cutoff = self.cutoff();
+        let stored: Vec<(String, DeliveryRecord)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        let mut kept: Vec<DeliveryRecord> = stored
+            .into_iter()
+            .map(|(_, delivery)| delivery)
+            .filter(|delivery| delivery.received_at >= cutoff)
+            .collect();
+        kept.sort_by(|a, b| b.received_at.cmp(&a.received_at));
+        kept.truncate(self.services.config().deliveries.keep);
+
+        Ok(kept)
+    }
+
+    fn cutoff(&self) -> DateTime<Utc> {
+        Utc::now() - self.services.config().deliveries.retain_for()
+    }
+}
+
+/// Trims every account's kept deliveries back to their limits, returning how
+/// many were removed.
+///
+/// A workflow trims its own deliveries as each one arrives, so this is for the
+/// ones that have stopped arriving: a sender that was set up and then switched
+/// off would otherwise leave its last few requests sitting here indefinitely.
+pub async fn prune_all(context: &AppContext) -> Result<usize, Error> {
+    let mut removed = 0;
+
+    for tenant in context.database().tenants().await? {
+        if tenant == TenantId::system() {
+            continue;
+        }
+
+        let services = context.tenant(tenant);
+        let store = DeliveryStore::new(&services);
+
+        for name in services.kv().partitions().await? {
+            // A partition whose suffix no longer parses belonged to an older
+            // identifier scheme; there is no workflow to trim it for.
+            let Some(workflow) = name
+                .strip_prefix(DELIVERIES_PARTITION)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+
+            removed += store.prune(workflow).await?;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use automate_api::{PreviewItem, SignatureVerdict};
+    use chrono::Duration;
+
+    use super::*;
+    use crate::preview::Dispatched;
+    use crate::services::ServicesContainer;
+
+    fn event(body: &str) -> WebhookEvent {
+        WebhookEvent {
+            body: body.to_string(),
+            query: "source=grafana&api_token=tok-8Hq2v".to_string(),
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("X-Signature".to_string(), "sha256=deadbeef".to_string()),
+                ("X-Forwarded-For".to_string(), "tok-8Hq2v".to_string()),
+            ]),
+        }
+    }
+
+    fn delivery(received_at: DateTime<Utc>) -> DeliveryRecord {
+        describe(
+            &event("{}"),
+            received_at,
+            RunOutcome::Succeeded,
+            None,
+            Observation::default(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn credentials_are_blanked_wherever_the_request_carried_them() {
+        let secrets = vec!["tok-8Hq2v".to_string()];
+        let kept = describe(
+            &event(r#"{"title":"Deploy with tok-8Hq2v"}"#),
+            Utc::now(),
+            RunOutcome::Succeeded,
+            None,
+            Observation::default(),
+            &secrets,
+        );
+
+        assert_eq!(kept.headers["x-signature"], REDACTED);
+        assert_eq!(
+            kept.headers["content-type"], "application/json",
+            "redaction must not take the headers that say what the delivery was",
+        );
+        assert_eq!(kept.headers["x-forwarded-for"], REDACTED);
+        assert_eq!(kept.query, format!("source=grafana&api_token={REDACTED}"));
+        assert!(!kept.body.contains("tok-8Hq2v"));
+    }
+
+    #[test]
+    fn what_the_workflow_made_of_a_delivery_is_kept_with_it() {
+        let observation = Observation {
+            items: vec![PreviewItem {
+                item: "The delivery's body".to_string(),
+                matched: true,
+                error: None,
+                clauses: Vec::new(),
+            }],
+            dispatched: vec![Dispatched {
+                partition: "todoist/create-task".to_string(),
+                payload: serde_json::json!({ "title": "Deploy finished" }),
+                key: None,
+                delay_seconds: None,
+            }],
+            effects: Vec::new(),
+            signature: Some(SignatureVerdict::Verified),
+        };
+
+        let kept = describe(
+            &event("{}"),
+            Utc::now(),
+            RunOutcome::Succeeded,
+            None,
+            observation,
+            &[],
+        );
+        let summary = kept.summary();
+
+        assert_eq!(summary.matched, 1);
+        assert_eq!(summary.published, 1);
+        assert_eq!(summary.signature, Some(SignatureVerdict::Verified));
+        assert_eq!(kept.published[0].title.as_deref(), Some("Deploy finished"));
+    }
+
+    #[test]
+    fn an_oversized_body_is_kept_as_a_prefix() {
+        let body = "é".repeat(MAX_BODY_BYTES);
+        let kept = describe(
+            &event(&body),
+            Utc::now(),
+            RunOutcome::Succeeded,
+            None,
+            Observation::default(),
+            &[],
+        );
+
+        assert!(kept.body.len() <= MAX_BODY_BYTES);
+        assert_eq!(kept.truncated_from, Some(body.len()));
+    }
+
+    #[tokio::test]
+    async fn only_the_most_recent_deliveries_are_kept() {
+        let services = ServicesContainer::new_custom_mock(|config, _| {
+            config.deliveries.keep = 2;
+        })
+        .await
+        .unwrap();
+        let store = DeliveryStore::new(&services);
+        let workflow = WorkflowId::from_entropy(1);
+        let now = Utc::now();
+
+        for minutes in [3, 2, 1] {
+            store
+                .record(
+                    workflow,
+                    delivery(now - Duration::minutes(minutes)),
+                    &event("{}"),
+                )
+                .await
+                .unwrap();
+        }
+
+        let listed = store.list(workflow).await.unwrap();
+        assert_eq!(
+            listed.len(),
+            2,
+            "the oldest delivery should have been let go"
+        );
+        assert_eq!(listed[0].received_at, now - Duration::minutes(1));
+        assert_eq!(listed[1].received_at, now - Duration::minutes(2));
+    }
+
+    #[tokio::test]
+    async fn a_delivery_past_its_retention_is_gone_even_before_it_is_trimmed() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = DeliveryStore::new(&services);
+        let workflow = WorkflowId::from_entropy(1);
+
+        // Written directly, as though it had been kept before the retention
+        // was shortened.
+        let stale = delivery(Utc::now() - Duration::days(30));
+        services
+            .kv()
+            .set(partition(workflow), stale.id.to_string(), stale.clone())
+            .await
+            .unwrap();
+
+        assert!(store.list(workflow).await.unwrap().is_empty());
+        assert!(store.get(workflow, stale.id).await.unwrap().is_none());
+
+        assert_eq!(store.prune(workflow).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn what_was_withheld_is_put_back_only_to_send_a_delivery_again() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = DeliveryStore::new(&services);
+        let workflow = WorkflowId::from_entropy(1);
+        let secrets = vec!["tok-8Hq2v".to_string()];
+        let sent = event(r#"{"title":"Deploy with tok-8Hq2v"}"#);
+
+        let mut kept = describe(
+            &sent,
+            Utc::now(),
+            RunOutcome::Succeeded,
+            None,
+            Observation::default(),
+            &secrets,
+        );
+        kept.id = store
+            .record(workflow, kept.clone(), &sent)
+            .await
+            .unwrap()
+            .expect("the delivery should have been kept");
+
+        let read = store.get(workflow, kept.id).await.unwrap().unwrap();
+        assert_eq!(read.headers["x-signature"], REDACTED);
+        assert!(
+            !serde_json::to_string(&read).unwrap().contains("deadbeef"),
+            "what the API reads must not carry the withheld credentials",
+        );
+
+        let request = store.request(workflow, &read).await.unwrap();
+        assert_eq!(request.header("x-signature"), Some("sha256=deadbeef"));
+        assert_eq!(request.query, sent.query);
+        assert_eq!(request.body, sent.body);
+        assert_eq!(
+            request.headers.len(),
+            sent.headers.len(),
+            "a header put back should replace its redacted copy, not sit beside it",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_workflows_deliveries_are_its_own() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = DeliveryStore::new(&services);
+        let (mine, theirs) = (WorkflowId::from_entropy(1), WorkflowId::from_entropy(2));
+
+        let id = store
+            .record(mine, delivery(Utc::now()), &event("{}"))
+            .await
+            .unwrap()
+            .expect("the delivery should have been kept");
+
+        assert!(store.get(mine, id).await.unwrap().is_some());
+        assert!(store.get(theirs, id).await.unwrap().is_none());
+
+        store.forget(mine).await.unwrap();
+        assert!(store.list(mine).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,611 @@
+//! Workflows triggering each other.
+//!
+//! Every workflow type starts from something outside the account — a schedule,
+//! or a delivery somebody posted — and stops at a task. That leaves no way to
+//! say "when the Grafana alert resolves, also tell the incident channel"
+//! without a workflow type written for exactly that pair, and the number of
+//! pairs grows with the square of the number of types.
+//!
+//! So a workflow can also be started by another one. A run that finishes emits
+//! an event saying how it went, a task it filed emits one naming the task, and
+//! the generic webhook can emit one of its own under whatever name its owner
+//! chooses. A workflow whose trigger is [`WorkflowTrigger::Event`] subscribes to
+//! events by name, and is handed each one that arrives as an [`EventDelivery`]
+//! on its queue partition, the way a webhook workflow is handed a
+//! [`crate::webhooks::WebhookDelivery`].
+//!
+//! # What an event reaches
+//!
+//! Only the workflows of the account whose workflow emitted it; an event is
+//! queued under the tenant it was emitted in, like everything else a run
+//! dispatches. Which of those it reaches is decided when it is emitted rather
+//! than when it is handled, so a subscriber that is paused or deleted in the
+//! meantime is settled by [`EventDelivery::config`] exactly as a delivery is.
+//!
+//! # Loops
+//!
+//! Two workflows each subscribed to the other's successful runs would pass an
+//! event back and forth for ever. An event is never handed back to the
+//! workflow that emitted it, and each event emitted while handling another
+//! carries one more hop than the event that caused it; past [`MAX_HOPS`] it is
+//! dropped with a line in the log. That bounds a loop without having to work
+//! out in advance which subscriptions would form one.
+//!
+//! Depth is not the only way to multiply runs. Ten workflows each subscribed
+//! to every workflow's successful runs would hand each of their own runs to
+//! the other nine, and each of those to eight more, so one delivery would cost
+//! thousands of runs well inside the hop limit. So a subscription that does not
+//! name the workflow it follows only hears from workflows started from outside
+//! — by a schedule or a delivery — and following an event-triggered workflow
+//! means naming it. A chain somebody draws on purpose names each link anyway.
+//!
+//! [`WorkflowTrigger::Event`]: automate_api::WorkflowTrigger::Event
+
+use automate_api::{WorkflowId, WorkflowTrigger};
+use chrono::{DateTime, Utc};
+use human_errors::Error;
+use serde_json::Value;
+
+use crate::prelude::*;
+use crate::workflow_store::WorkflowRecord;
+
+/// Emitted when a workflow run finishes without an error.
+pub const RUN_SUCCEEDED: &str = "run.succeeded";
+
+/// Emitted when a workflow run fails.
+pub const RUN_FAILED: &str = "run.failed";
+
+/// Emitted for each task a workflow run files. A workflow sending digests
+/// files its digest rather than the tasks held for it, so that is what is
+/// announced.
+pub const TASK_PUBLISHED: &str = "task.published";
+
+/// How many workflows one event may pass through before it is dropped.
+///
+/// Generous enough for any chain somebody would draw on purpose, and small
+/// enough that a loop drawn by accident costs a handful of runs rather than a
+/// queue full of them.
+pub const MAX_HOPS: u32 = 5;
+
+/// The queues a filed task is dispatched to, which is how a run's tasks are
+/// told apart from the rest of what it dispatched.
+const TASK_PARTITIONS: [&str; 2] = ["todoist/create-task", "todoist/upsert-task"];
+
+/// Something one workflow did that another may act on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowEvent {
+    /// What happened, such as [`RUN_FAILED`], or a name its owner chose.
+    pub name: String,
+
+    /// The workflow that emitted it.
+    pub workflow: WorkflowId,
+
+    /// What the event is about, which a subscriber's filter and templates
+    /// address under `payload`.
+    #[serde(default)]
+    pub payload: Value,
+
+    pub emitted_at: DateTime<Utc>,
+
+    /// How many events came before this one in the chain that caused it.
+    #[serde(default)]
+    pub hops: u32,
+}
+
+impl WorkflowEvent {
+    pub fn new(name: impl Into<String>, workflow: WorkflowId, payload: Value) -> Self {
+        Self {
+            name: name.into(),
+            workflow,
+            payload,
+            emitted_at: Utc::now(),
+            hops: 0,
+        }
+    }
+
+    /// The event as a subscriber's filter and templates see it.
+    ///
+    /// The payload is kept under its own name rather than merged in, so that a
+    /// custom event whose payload has a `name` field of its own cannot be
+    /// mistaken for a different event.
+    pub fn as_value(&self) -> Value {
+        serde_json::json!({
+            "name": self.name,
+            "workflow": self.workflow,
+            "emitted_at": self.emitted_at,
+            "payload": self.payload,
+        })
+    }
+}
+
+/// An event, together with the workflow it was handed to.
+///
+/// Every event-triggered workflow is handed one of these. Like a webhook
+/// delivery it names the record that says what to do with it, read when the
+/// job runs, so an edit made since the event was emitted applies to it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventDelivery {
+    pub workflow: WorkflowId,
+    pub event: WorkflowEvent,
+}
+
+impl std::fmt::Display for EventDelivery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event/{}/{}", self.event.name, self.workflow)
+    }
+}
+
+impl EventDelivery {
+    /// The stored configuration this event should be handled with.
+    ///
+    /// `None` means the workflow has been deleted, paused or snoozed since the
+    /// event was emitted, which is nothing to retry. A preview brings the
+    /// configuration it wants tried instead, as it does for a delivery.
+    pub async fn config<C>(
+        &self,
+        services: &(impl Services + Send + Sync + 'static),
+    ) -> Result<Option<C>, Error>
+    where
+        C: DeserializeOwned,
+    {
+        if let Some(config) = crate::preview::replay_config() {
+            return serde_json::from_value(config).map(Some).wrap_user_err(
+                "This workflow is not configured correctly, so the event could not be replayed.",
+                &["Check that every field the workflow asks for is filled in."],
+            );
+        }
+
+        let store = crate::workflow_store::WorkflowStore::new(services);
+
+        let Some(record) = store.find(self.workflow).await? else {
+            info!(workflow.id = %self.workflow, "Discarding an event for a workflow which no longer exists.");
+            crate::preview::discarded("The workflow no longer exists.");
+            return Ok(None);
+        };
+
+        if !record.enabled {
+            info!(workflow.id = %record.id, "Discarding an event for a paused workflow.");
+            crate::preview::discarded("The workflow is paused.");
+            return Ok(None);
+        }
+
+        // An event is skipped like a scheduled run rather than held like a
+        // delivery: nothing can be held for a workflow that is not a webhook,
+        // so a snooze that holds is refused before it gets here.
+        if store.check_snooze(&record, Utc::now()).await?
+            != crate::workflow_store::SnoozeVerdict::Run
+        {
+            info!(workflow.id = %record.id, "Discarding an event for a snoozed workflow.");
+            crate::preview::discarded("The workflow is snoozed.");
+            return Ok(None);
+        }
+
+        let config = serde_json::from_value(record.config).wrap_user_err(
+            "This workflow is not configured correctly, so an event could not be handled.",
+            &["Open the workflow and check that every field it asks for is filled in."],
+        )?;
+
+        Ok(Some(config))
+    }
+}
+
+/// The part of every event-triggered workflow's configuration that says which
+/// events it wants.
+///
+/// Read from the stored configuration by name rather than through each type's
+/// own configuration, so that deciding who an event reaches does not need to
+/// know every type that could subscribe. A type that subscribes to events is
+/// expected to have both fields.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Subscription {
+    #[serde(default)]
+    event: String,
+
+    /// The workflow whose events are wanted. Empty for any of them that was
+    /// not itself started by an event.
+    #[serde(default)]
+    from: String,
+}
+
+impl Subscription {
+    fn wants(&self, event: &WorkflowEvent) -> bool {
+        if self.event.trim() != event.name {
+            return false;
+        }
+
+        // Only a run that was handed an event emits one with hops to its name;
+        // see "Loops" above for why those have to be asked for by name.
+        match self.from.trim() {
+            "" => event.hops == 0,
+            from => from == event.workflow.to_string(),
+        }
+    }
+}
+
+/// The enabled workflows an event should be handed to, with the queue each is
+/// handed it on.
+async fn subscribers(
+    services: &(impl Services + Send + Sync + 'static),
+    event: &WorkflowEvent,
+) -> Result<Vec<(WorkflowId, &'static str)>, Error> {
+    let mut partitions: Vec<String> = crate::workflows::registry()
+        .values()
+        .filter_map(|workflow| match workflow.descriptor().trigger {
+            trigger @ WorkflowTrigger::Event { .. } => Some(trigger.partition()),
+            _ => None,
+        })
+        .collect();
+    partitions.sort();
+    partitions.dedup();
+
+    let mut found = Vec::new();
+    for partition in partitions {
+        let records: Vec<(String, WorkflowRecord)> = services.kv().list(partition).await?;
+
+        for (_, record) in records {
+            if !record.enabled || record.id == event.workflow {
+                continue;
+            }
+
+            let Ok(subscription) = serde_json::from_value::<Subscription>(record.config) else {
+                continue;
+            };
+
+            if !subscription.wants(event) {
+                continue;
+            }
+
+            if let Ok(workflow) = crate::workflows::lookup(&record.type_id) {
+                found.push((record.id, workflow.partition()));
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Hands an event to every workflow subscribed to it, returning how many.
+///
+/// Like [`crate::job::Job::dispatch`] this goes through
+/// [`crate::preview::intercept`], so a preview reports the events a run would
+/// emit rather than emitting them.
+pub async fn emit(
+    services: &(impl Services + Send + Sync + 'static),
+    event: WorkflowEvent,
+) -> Result<usize, Error> {
+    if event.hops >= MAX_HOPS {
+        warn!(
+            workflow.id = %event.workflow,
+            event.name = %event.name,
+            "Dropping an event which has already passed through {MAX_HOPS} workflows; check whether two workflows subscribe to each other.",
+        );
+        return Ok(0);
+    }
+
+    let subscribers = subscribers(services, &event).await?;
+
+    for (workflow, partition) in &subscribers {
+        let delivery = EventDelivery {
+            workflow: *workflow,
+            event: event.clone(),
+        };
+
+        if crate::preview::intercept(partition, &delivery, None, None)? {
+            continue;
+        }
+
+        services
+            .queue()
+            .partition(*partition)
+            .enqueue(delivery, None, None)
+            .await?;
+    }
+
+    Ok(subscribers.len())
+}
+
+/// The events a finished run gives rise to.
+///
+/// `payload` is what the run was handed and `dispatched` what it filed. A run
+/// that was handed a webhook delivery reports the delivery's body, read as the
+/// generic webhook reads it, so a subscriber can filter on what the sender
+/// said; a run handed an event reports that event, and its own events are one
+/// hop further along.
+pub fn of_run(
+    workflow: WorkflowId,
+    payload: &Value,
+    error: Option<&Error>,
+    dispatched: &[crate::preview::Dispatched],
+) -> Vec<WorkflowEvent> {
+    let (input, hops) = match serde_json::from_value::<EventDelivery>(payload.clone()) {
+        Ok(delivery) => (delivery.event.as_value(), delivery.event.hops + 1),
+        Err(_) => (
+            serde_json::from_value::<crate::webhooks::WebhookDelivery>(payload.clone())
+                .ok()
+                .and_then(|delivery| crate::webhook_body::read(&delivery.event).ok())
+                .unwrap_or_else(|| payload.clone()),
+            0,
+        ),
+    };
+
+    let mut events = vec![match error {
+        None => WorkflowEvent::new(
+            RUN_SUCCEEDED,
+            workflow,
+            serde_json::json!({ "input": input }),
+        ),
+        Some(err) => WorkflowEvent::new(
+            RUN_FAILED,
+            workflow,
+            serde_json::json!({ "input": input, "message": err.to_string() }),
+        ),
+    }];
+
+    events.extend(
+        dispatched
+            .iter()
+            .filter(|job| TASK_PARTITIONS.contains(&job.partition.as_str()))
+            .map(|job| {
+                let task = job.clone().describe();
+                WorkflowEvent::new(
+                    TASK_PUBLISHED,
+                    workflow,
+                    serde_json::json!({
+                        "title": task.title,
+                        "description": task.description,
+                        "job": task.job,
+                    }),
+                )
+            }),
+    );
+
+    for event in &mut events {
+        event.hops = hops;
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workflow_store::{WorkflowDraft, WorkflowStore};
+
+    use super::*;
+
+    async fn subscribe(
+        services: &(impl Services + Send + Sync + 'static),
+        event: &str,
+        from: Option<WorkflowId>,
+        enabled: bool,
+    ) -> WorkflowId {
+        WorkflowStore::new(services)
+            .create(WorkflowDraft {
+                type_id: "event".into(),
+                config: serde_json::json!({
+                    "name": "Follow-up",
+                    "event": event,
+                    "from": from.map(|id| id.to_string()).unwrap_or_default(),
+                    "title": "${{ name }}",
+                }),
+                schedule: None,
+                enabled,
+            })
+            .await
+            .expect("store the subscriber")
+            .id
+    }
+
+    async fn handed(
+        services: &(impl Services + Send + Sync + 'static),
+    ) -> Vec<crate::db::PeekedMessage<serde_json::Value>> {
+        services
+            .queue()
+            .peek("events/todoist", 10)
+            .await
+            .expect("peek the subscriber's queue")
+    }
+
+    #[tokio::test]
+    async fn an_event_reaches_the_workflows_subscribed_to_its_name() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let (emitter, other) = (WorkflowId::from_entropy(1), WorkflowId::from_entropy(2));
+
+        let anyone = subscribe(&services, RUN_FAILED, None, true).await;
+        let specific = subscribe(&services, RUN_FAILED, Some(emitter), true).await;
+        subscribe(&services, RUN_FAILED, Some(other), true).await;
+        subscribe(&services, RUN_SUCCEEDED, None, true).await;
+        subscribe(&services, RUN_FAILED, None, false).await;
+
+        let reached = emit(
+            &services,
+            WorkflowEvent::new(RUN_FAILED, emitter, serde_json::json!({})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reached, 2);
+
+        let mut workflows: Vec<String> = handed(&services)
+            .await
+            .iter()
+            .map(|message| message.payload["workflow"].as_str().unwrap().to_string())
+            .collect();
+        workflows.sort();
+
+        let mut expected = vec![anyone.to_string(), specific.to_string()];
+        expected.sort();
+        assert_eq!(
+            workflows, expected,
+            "only enabled subscribers to this event, from any workflow or this one, should be handed it",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_workflow_is_never_handed_its_own_event() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let subscriber = subscribe(&services, RUN_SUCCEEDED, None, true).await;
+
+        let reached = emit(
+            &services,
+            WorkflowEvent::new(RUN_SUCCEEDED, subscriber, serde_json::json!({})),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reached, 0);
+        assert!(handed(&services).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn an_event_triggered_workflow_is_only_followed_by_name() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let emitter = WorkflowId::from_entropy(1);
+
+        subscribe(&services, RUN_SUCCEEDED, None, true).await;
+        subscribe(&services, RUN_SUCCEEDED, None, true).await;
+        let named = subscribe(&services, RUN_SUCCEEDED, Some(emitter), true).await;
+
+        let mut event = WorkflowEvent::new(RUN_SUCCEEDED, emitter, serde_json::json!({}));
+        event.hops = 1;
+
+        assert_eq!(
+            emit(&services, event).await.unwrap(),
+            1,
+            "subscribers following anybody would otherwise hand each other's runs around, \
+             multiplying with every hop",
+        );
+        assert_eq!(
+            handed(&services).await[0].payload["workflow"],
+            named.to_string(),
+        );
+    }
+
+    #[tokio::test]
+    async fn an_event_that_has_passed_through_too_many_workflows_is_dropped() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let emitter = WorkflowId::from_entropy(1);
+        subscribe(&services, RUN_SUCCEEDED, Some(emitter), true).await;
+
+        let mut event = WorkflowEvent::new(RUN_SUCCEEDED, emitter, serde_json::json!({}));
+        event.hops = MAX_HOPS;
+
+        assert_eq!(emit(&services, event).await.unwrap(), 0);
+        assert!(
+            handed(&services).await.is_empty(),
+            "a chain this long is most likely a loop, and should stop here",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_snoozed_subscriber_skips_the_events_it_was_snoozed_for() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let subscriber = subscribe(&services, RUN_FAILED, None, true).await;
+        WorkflowStore::new(&services)
+            .snooze(
+                subscriber,
+                Some(automate_api::Snooze {
+                    runs: Some(1),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        let delivery = EventDelivery {
+            workflow: subscriber,
+            event: WorkflowEvent::new(
+                RUN_FAILED,
+                WorkflowId::from_entropy(1),
+                serde_json::json!({}),
+            ),
+        };
+
+        assert!(
+            delivery
+                .config::<serde_json::Value>(&services)
+                .await
+                .unwrap()
+                .is_none(),
+            "the event arriving while it is snoozed should be skipped",
+        );
+        assert!(
+            delivery
+                .config::<serde_json::Value>(&services)
+                .await
+                .unwrap()
+                .is_some(),
+            "the snooze should have been used up by the one it skipped",
+        );
+    }
+
+    #[test]
+    fn a_run_handed_an_event_emits_its_own_one_hop_further_along() {
+        let (first, second) = (WorkflowId::from_entropy(1), WorkflowId::from_entropy(2));
+        let mut cause = WorkflowEvent::new(RUN_FAILED, first, serde_json::json!({}));
+        cause.hops = 2;
+
+        let payload = serde_json::to_value(EventDelivery {
+            workflow: second,
+            event: cause,
+        })
+        .unwrap();
+
+        let events = of_run(second, &payload, None, &[]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, RUN_SUCCEEDED);
+        assert_eq!(events[0].workflow, second);
+        assert_eq!(events[0].hops, 3);
+        assert_eq!(
+            events[0].payload["input"]["name"], RUN_FAILED,
+            "the event a run was handed is what it reports as its input",
+        );
+    }
+
+    #[test]
+    fn a_run_handed_a_delivery_reports_the_body_it_was_sent() {
+        let workflow = WorkflowId::from_entropy(1);
+        let payload = serde_json::json!({
+            "workflow": workflow,
+            "event": {
+                "body": r#"{ "status": "resolved" }"#,
+                "query": "",
+                "headers": { "content-type": "application/json" },
+            },
+        });
+
+        let events = of_run(
+            workflow,
+            &payload,
+            Some(&human_errors::user("Todoist was unavailable.", &[])),
+            &[],
+        );
+
+        assert_eq!(events[0].name, RUN_FAILED);
+        assert_eq!(events[0].payload["input"]["status"], "resolved");
+        assert_eq!(events[0].payload["message"], "Todoist was unavailable.");
+        assert_eq!(events[0].hops, 0);
+    }
+
+    #[test]
+    fn each_task_a_run_filed_is_an_event_of_its_own() {
+        let dispatched = |partition: &str, title: &str| crate::preview::Dispatched {
+            partition: partition.to_string(),
+            payload: serde_json::json!({ "title": title }),
+            key: None,
+            delay_seconds: None,
+        };
+
+        let events = of_run(
+            WorkflowId::from_entropy(1),
+            &serde_json::json!({}),
+            None,
+            &[
+                dispatched("todoist/create-task", "Read the release notes"),
+                dispatched("spotify/add-to-playlist", "Not a task"),
+            ],
+        );
+
+        let names: Vec<&str> = events.iter().map(|event| event.name.as_str()).collect();
+        assert_eq!(names, [RUN_SUCCEEDED, TASK_PUBLISHED]);
+        assert_eq!(events[1].payload["title"], "Read the release notes");
+    }
+}
@@ -1,9 +1,14 @@
 //! Filtering DSL used to conditionally process jobs and gate access.
 //!
 //! The lexer, parser and interpreter all live in the external [`filt_rs`]
-//! crate, which is re-exported here wholesale. The only thing this module adds
-//! is the [`serde_json::Value`] conversion that the orphan rule prevents us
-//! from implementing directly on the [`filt_rs`] types.
+//! crate, which is re-exported here wholesale. This module adds the
+//! [`serde_json::Value`] conversion that the orphan rule prevents us from
+//! implementing directly on the [`filt_rs`] types, and [`explain`], which says
+//! how each part of a filter came out for somebody trying one out.
+
+use automate_api::FilterClause;
+use automate_api::payload_path;
+use filt_rs::{BinaryOperator, Expr, ExprVisitor, Function, Glob, LogicalOperator, UnaryOperator};
 
 pub use filt_rs::{Filter, FilterValue, Filterable};
 
@@ -30,6 +35,195 @@ pub fn json_to_filter_value(value: &serde_json::Value) -> FilterValue<'_> {
     }
 }
 
+/// Breaks a filter down at its `&&`, `||` and `!`, and evaluates each part
+/// against `target` on its own.
+///
+/// The whole filter comes first, at depth zero, and each part follows the one
+/// it belongs to. A run of the same operator is one level rather than a
+/// staircase, so `a && b && c` is three parts of one clause, which is how
+/// anybody reading it would count them.
+///
+/// Each part is evaluated in full rather than short-circuited: the point is to
+/// see which condition let an item down, and stopping at the first one that
+/// decided the answer would hide whether the others would have as well.
+pub fn explain(filter: &Filter, target: &impl Filterable) -> Vec<FilterClause> {
+    let mut breakdown = Breakdown::default();
+    filter.visit(&mut breakdown);
+
+    breakdown
+        .parts
+        .into_iter()
+        .map(|(expr, depth)| {
+            // The interpreter only evaluates whole filters, so each part is
+            // written back out and parsed again as one.
+            let result = Filter::new(source(expr, false)).and_then(|part| part.matches(target));
+
+            FilterClause {
+                expression: source(expr, true),
+                depth,
+                matched: matches!(result, Ok(true)),
+                error: result.err().map(|err| err.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Collects the parts of a filter worth reporting on, with how deeply each is
+/// nested.
+#[derive(Default)]
+struct Breakdown<'a> {
+    parts: Vec<(&'a Expr<'a>, usize)>,
+    depth: usize,
+}
+
+impl<'a> Breakdown<'a> {
+    fn descend(&mut self, children: impl IntoIterator<Item = &'a Expr<'a>>) {
+        self.depth += 1;
+        for child in children {
+            self.visit_expr(child);
+        }
+        self.depth -= 1;
+    }
+}
+
+impl<'a> ExprVisitor<'a, ()> for Breakdown<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr<'a>) {
+        self.parts.push((expr, self.depth));
+
+        match expr {
+            Expr::Logical(left, operator, right) => {
+                let mut operands = Vec::new();
+                flatten(left, *operator, &mut operands);
+                flatten(right, *operator, &mut operands);
+                self.descend(operands);
+            }
+            Expr::Unary(_, operand) => self.descend([operand.as_ref()]),
+            _ => {}
+        }
+    }
+
+    fn visit_literal(&mut self, _value: &'a FilterValue<'a>) {}
+
+    fn visit_property(&mut self, _name: &'a str) {}
+
+    fn visit_function_call(&mut self, _function: &'a dyn Function, _args: &'a [Expr<'a>]) {}
+
+    fn visit_binary(
+        &mut self,
+        _left: &'a Expr<'a>,
+        _operator: BinaryOperator,
+        _right: &'a Expr<'a>,
+    ) {
+    }
+
+    fn visit_logical(
+        &mut self,
+        _left: &'a Expr<'a>,
+        _operator: LogicalOperator,
+        _right: &'a Expr<'a>,
+    ) {
+    }
+
+    fn visit_unary(&mut self, _operator: UnaryOperator, _right: &'a Expr<'a>) {}
+
+    fn visit_like(&mut self, _left: &'a Expr<'a>, _glob: &'a Glob) {}
+}
+
+/// The operands of a run of the same logical operator, in order.
+fn flatten<'a>(expr: &'a Expr<'a>, operator: LogicalOperator, into: &mut Vec<&'a Expr<'a>>) {
+    match expr {
+        Expr::Logical(left, inner, right) if *inner == operator => {
+            flatten(left, operator, into);
+            flatten(right, operator, into);
+        }
+        other => into.push(other),
+    }
+}
+
+/// Writes an expression back out in the filter language.
+///
+/// With `decode`, a path a webhook filter hid from the parser (see
+/// [`payload_path::embed`]) is shown as it was written; without, it stays
+/// hidden so that the text can be parsed again.
+fn source(expr: &Expr<'_>, decode: bool) -> String {
+    Source { decode }.visit_expr(expr)
+}
+
+struct Source {
+    decode: bool,
+}
+
+impl Source {
+    /// An operand, in parentheses unless it is a single term.
+    fn operand<'a>(&mut self, expr: &'a Expr<'a>) -> String {
+        match expr {
+            Expr::Literal(_) | Expr::Property(_) | Expr::FunctionCall(..) => self.visit_expr(expr),
+            _ => format!("({})", self.visit_expr(expr)),
+        }
+    }
+}
+
+impl<'a> ExprVisitor<'a, String> for Source {
+    fn visit_literal(&mut self, value: &'a FilterValue<'a>) -> String {
+        value.to_string()
+    }
+
+    fn visit_property(&mut self, name: &'a str) -> String {
+        match payload_path::embedded(name) {
+            Some(path) if self.decode => path,
+            _ => name.to_string(),
+        }
+    }
+
+    fn visit_function_call(&mut self, function: &'a dyn Function, args: &'a [Expr<'a>]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| self.visit_expr(arg)).collect();
+        format!("{}({})", function.name(), args.join(", "))
+    }
+
+    fn visit_binary(
+        &mut self,
+        left: &'a Expr<'a>,
+        operator: BinaryOperator,
+        right: &'a Expr<'a>,
+    ) -> String {
+        format!("{} {operator} {}", self.operand(left), self.operand(right))
+    }
+
+    fn visit_logical(
+        &mut self,
+        left: &'a Expr<'a>,
+        operator: LogicalOperator,
+        right: &'a Expr<'a>,
+    ) -> String {
+        // Only a change of operator needs parentheses; a run of the same one
+        // reads the same however it is grouped.
+        let mut side = |expr: &'a Expr<'a>| match expr {
+            Expr::Logical(_, inner, _) if *inner != operator => {
+                format!("({})", self.visit_expr(expr))
+            }
+            _ => self.visit_expr(expr),
+        };
+
+        let left = side(left);
+        let right = side(right);
+        format!("{left} {operator} {right}")
+    }
+
+    fn visit_unary(&mut self, operator: UnaryOperator, right: &'a Expr<'a>) -> String {
+        format!("{operator}{}", self.operand(right))
+    }
+
+    fn visit_like(&mut self, left: &'a Expr<'a>, glob: &'a Glob) -> String {
+        let keyword = if glob.is_case_sensitive() {
+            "like_cs"
+        } else {
+            "like"
+        };
+
+        format!("{} {keyword} {glob}", self.operand(left))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,4 +309,54 @@ mod tests {
             FilterValue::Tuple(vec![FilterValue::from("a"), FilterValue::from("b")])
         );
     }
+
+    #[test]
+    fn explain_reports_how_each_part_of_a_filter_came_out() {
+        let obj = TestObject {
+            name: "John Doe".to_string(),
+        };
+
+        let filter =
+            Filter::new(r#"name startswith "john" && !(name == "Jane" || name like "*Smith")"#)
+                .expect("parse filter");
+
+        let clauses: Vec<_> = explain(&filter, &obj)
+            .into_iter()
+            .map(|clause| (clause.depth, clause.expression, clause.matched))
+            .collect();
+
+        assert_eq!(
+            clauses,
+            vec![
+                (
+                    0,
+                    r#"name startswith "john" && !(name == "Jane" || name like "*Smith")"#.into(),
+                    true
+                ),
+                (1, r#"name startswith "john""#.into(), true),
+                (1, r#"!(name == "Jane" || name like "*Smith")"#.into(), true),
+                (2, r#"name == "Jane" || name like "*Smith""#.into(), false),
+                (3, r#"name == "Jane""#.into(), false),
+                (3, r#"name like "*Smith""#.into(), false),
+            ],
+            "each part should be written out as it reads and evaluated on its own",
+        );
+    }
+
+    #[test]
+    fn explain_flattens_a_run_of_the_same_operator() {
+        let obj = TestObject {
+            name: "John Doe".to_string(),
+        };
+
+        let filter = Filter::new(r#"name != "" && true && trim(name) contains "doe""#)
+            .expect("parse filter");
+
+        let clauses = explain(&filter, &obj);
+
+        assert_eq!(clauses.len(), 4, "{clauses:?}");
+        assert!(clauses[1..].iter().all(|clause| clause.depth == 1));
+        assert_eq!(clauses[3].expression, r#"trim(name) contains "doe""#);
+        assert!(clauses.iter().all(|clause| clause.matched));
+    }
 }
@@ -0,0 +1,203 @@
+//! Whether this process is fit to be sent work, for an orchestrator to ask.
+//!
+//! A process can keep accepting connections long after the part of it that
+//! does anybody's work has stopped: the job host can be wedged on a lock, or
+//! the database can have gone read-only underneath it, and a TCP check will
+//! never notice. [`readiness`] looks at each of the things the agent cannot
+//! work without and says which, if any, is the problem.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use tracing_batteries::prelude::*;
+
+use crate::db::SqliteDatabase;
+use crate::services::AppContext;
+
+/// How long the job host may go without asking for work before it is taken to
+/// have stopped.
+///
+/// It asks about once a second while idle, and hands each job off rather than
+/// running it in place, so anything past a few seconds is already unusual; the
+/// margin is for a database that is busy rather than gone.
+pub const JOB_HOST_STALLED_AFTER: TimeDelta = TimeDelta::minutes(2);
+
+/// When a loop last showed that it was still going round.
+///
+/// An atomic rather than a lock because it is written on every pass of the job
+/// host and read by every probe, and neither should ever have to wait for the
+/// other.
+#[derive(Debug, Default)]
+pub struct Heartbeat {
+    /// Milliseconds since the epoch, or zero for a loop that has not started.
+    last: AtomicI64,
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        self.last
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn last(&self) -> Option<DateTime<Utc>> {
+        match self.last.load(Ordering::Relaxed) {
+            0 => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        }
+    }
+}
+
+/// One thing the agent depends on, and what was found when it was looked at.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn ok(detail: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Every check, and whether together they make the process ready.
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+/// Looks at everything the agent needs in order to do its work.
+///
+/// The details are written for an operator reading a probe's output and say
+/// what was found, never why in the database's own words: the endpoint is
+/// unauthenticated, and the errors themselves go to the log.
+pub async fn readiness(context: &AppContext) -> Readiness {
+    let database = context.database();
+
+    let checks = BTreeMap::from([
+        ("database", database_answers(database).await),
+        ("migrations", migrations_current(database).await),
+        ("job_host", job_host_running(context.job_host(), Utc::now())),
+        ("secrets", secrets_usable(context)),
+    ]);
+
+    Readiness {
+        ready: checks.values().all(|check| check.ok),
+        checks,
+    }
+}
+
+async fn database_answers(database: &SqliteDatabase) -> Check {
+    match database.ping().await {
+        Ok(()) => Check::ok("The database answered."),
+        Err(err) => {
+            warn!(error = %err, "The database did not answer a readiness check: {err}");
+            Check::failed("The database did not answer.")
+        }
+    }
+}
+
+async fn migrations_current(database: &SqliteDatabase) -> Check {
+    match database.schema_version().await {
+        Ok(version) if version >= SqliteDatabase::SCHEMA_VERSION => {
+            Check::ok(format!("The database is at migration v{version}."))
+        }
+        Ok(version) => Check::failed(format!(
+            "The database is at migration v{version}, but this build expects v{}.",
+            SqliteDatabase::SCHEMA_VERSION
+        )),
+        Err(err) => {
+            warn!(error = %err, "Failed to read the database's migration level for a readiness check: {err}");
+            Check::failed("The database's migration level could not be read.")
+        }
+    }
+}
+
+fn job_host_running(heartbeat: &Heartbeat, now: DateTime<Utc>) -> Check {
+    match heartbeat.last() {
+        None => Check::failed("The job host has not started asking for work yet."),
+        Some(last) if now - last > JOB_HOST_STALLED_AFTER => Check::failed(format!(
+            "The job host last asked for work at {}, and appears to have stopped.",
+            last.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )),
+        Some(last) => Check::ok(format!(
+            "The job host last asked for work at {}.",
+            last.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )),
+    }
+}
+
+fn secrets_usable(context: &AppContext) -> Check {
+    match context.secrets().check() {
+        Ok(()) => Check::ok("Secrets can be sealed and opened with the active key."),
+        Err(err) => {
+            warn!(error = %err, "The credential keys failed a readiness check: {err}");
+            Check::failed("Secrets could not be sealed and opened with the active key.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_job_host_that_has_never_beaten_is_not_running() {
+        let check = job_host_running(&Heartbeat::default(), Utc::now());
+        assert!(!check.ok, "{}", check.detail);
+    }
+
+    #[test]
+    fn a_job_host_is_running_until_its_heartbeat_goes_stale() {
+        let heartbeat = Heartbeat::default();
+        heartbeat.beat();
+        let beat = heartbeat.last().unwrap();
+
+        assert!(job_host_running(&heartbeat, beat + TimeDelta::seconds(5)).ok);
+        assert!(
+            !job_host_running(
+                &heartbeat,
+                beat + JOB_HOST_STALLED_AFTER + TimeDelta::seconds(1)
+            )
+            .ok
+        );
+    }
+
+    #[tokio::test]
+    async fn an_installation_whose_job_host_is_asking_for_work_is_ready() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        context.job_host().beat();
+
+        let readiness = readiness(&context).await;
+
+        assert!(readiness.ready, "{readiness:?}");
+        assert_eq!(
+            readiness.checks.keys().copied().collect::<Vec<_>>(),
+            vec!["database", "job_host", "migrations", "secrets"]
+        );
+    }
+
+    #[tokio::test]
+    async fn an_installation_whose_job_host_has_not_started_is_not_ready() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+
+        let readiness = readiness(&context).await;
+
+        assert!(!readiness.ready);
+        assert!(!readiness.checks["job_host"].ok);
+        assert!(readiness.checks["database"].ok);
+    }
+}
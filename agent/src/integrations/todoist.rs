@@ -139,7 +139,7 @@ async fn exchange(
     let response = http
         .post(token_url(app))
         .form(form)
-        .send()
+        .send_counted("todoist")
         .await
         .wrap_user_err(
             "Failed to exchange the Todoist authorization for an access token.",
@@ -321,7 +321,7 @@ impl TodoistAppIntegration {
     ) -> Result<TodoistUser, human_errors::Error> {
         http.get(user_url(app))
             .bearer_auth(access_token)
-            .send()
+            .send_counted("todoist")
             .await
             .wrap_user_err(
                 "Failed to read your Todoist account details.",
@@ -137,7 +137,7 @@ async fn exchange(
     let response = http
         .post(token_url(app))
         .form(form)
-        .send()
+        .send_counted("ynab")
         .await
         .wrap_user_err(
             "Failed to exchange the YNAB authorization for an access token.",
@@ -51,8 +51,9 @@ where
     }
 
     /// Attaches the idempotency key the message was enqueued with. Only the job
-    /// consumer sets this; a context built for a test or for an inline dispatch
-    /// has no queued message behind it and so has no key.
+    /// consumer and `automate run`, which stands in for it, set this; a context
+    /// built for a test or for an inline dispatch has no queued message behind
+    /// it and so has no key.
     pub fn with_key(mut self, key: Option<String>) -> Self {
         self.key = key;
         self
@@ -115,6 +116,12 @@ pub trait Job {
         idempotency_key: Option<Cow<'static, str>>,
         services: &impl Services,
     ) -> Result<(), human_errors::Error> {
+        // A run somebody is only watching reports what it would have queued
+        // rather than queueing it; see `crate::preview`.
+        if crate::preview::intercept(Self::partition(), &job, idempotency_key.as_deref(), None)? {
+            return Ok(());
+        }
+
         let queue = services.queue().partition(Self::partition());
 
         queue.enqueue(job, idempotency_key, None).await?;
@@ -129,6 +136,15 @@ pub trait Job {
         delay: TimeDelta,
         services: &impl Services,
     ) -> Result<(), human_errors::Error> {
+        if crate::preview::intercept(
+            Self::partition(),
+            &job,
+            idempotency_key.as_deref(),
+            Some(delay),
+        )? {
+            return Ok(());
+        }
+
         let queue = services.queue().partition(Self::partition());
 
         queue.enqueue(job, idempotency_key, Some(delay)).await?;
@@ -273,6 +289,19 @@ macro_rules! register_job {
     };
 }
 
+/// The handler registered for a queue partition, for the callers that run a job
+/// in-process rather than through the queue.
+///
+/// The [`JobHost`] builds its own table because it has to refuse duplicate
+/// registrations before it starts; anything reaching for a single handler after
+/// that can rely on there being at most one.
+pub fn handler(partition: &str) -> Option<&'static dyn JobRunnable> {
+    inventory::iter::<JobRegistration>
+        .into_iter()
+        .map(JobRegistration::handler)
+        .find(|handler| handler.partition() == partition)
+}
+
 /// The workflow a queued message belongs to, where it belongs to one.
 ///
 /// The two ways a workflow gets run label their messages differently: a
@@ -301,6 +330,55 @@ fn workflow_of(item: &QueueMessage<serde_json::Value>) -> Option<automate_api::W
         })
 }
 
+/// The delivery a job was handed, when it is a webhook delivery.
+///
+/// Every webhook workflow is handed a [`WebhookDelivery`], so the shape is what
+/// identifies one rather than the partition it arrived on.
+fn delivery_of(item: &QueueMessage<serde_json::Value>) -> Option<crate::webhooks::WebhookDelivery> {
+    serde_json::from_value(item.payload.clone()).ok()
+}
+
+/// When the work a message carries arrived, as its job is told it did.
+///
+/// The queue's own date is when the message was last enqueued. A webhook
+/// delivery that was held and released has been enqueued since it arrived, and
+/// says when it did (see [`crate::webhooks::WebhookDelivery::arrived_at`]).
+pub(crate) fn arrived_at(item: &QueueMessage<serde_json::Value>) -> DateTime<Utc> {
+    delivery_of(item)
+        .and_then(|delivery| delivery.arrived_at)
+        .unwrap_or(item.scheduled_at)
+}
+
+/// What started a run, where the payload it was handed says.
+///
+/// A delivery and an event each have a shape of their own, as [`delivery_of`]
+/// relies on. A scheduled run and one somebody asked for from the page are
+/// handed the same configuration under the same key, so `None` leaves telling
+/// those two apart to the marker the trigger endpoint leaves behind.
+fn trigger_of(payload: &serde_json::Value) -> Option<automate_api::RunTrigger> {
+    use automate_api::RunTrigger;
+
+    if let Ok(delivery) =
+        serde_json::from_value::<crate::webhooks::WebhookDelivery>(payload.clone())
+    {
+        return Some(match delivery.replay_of {
+            Some(_) => RunTrigger::Replay,
+            None => RunTrigger::Webhook,
+        });
+    }
+
+    serde_json::from_value::<crate::events::EventDelivery>(payload.clone())
+        .ok()
+        .map(|_| RunTrigger::Event)
+}
+
+/// What was seen of a workflow's run while it ran, beyond how it ended.
+#[derive(Default)]
+struct RunDetails {
+    counts: automate_api::RunCounts,
+    log: automate_api::RunLog,
+}
+
 /// The single queue consumer responsible for processing every registered job.
 ///
 /// It dequeues messages from any partition, looks up the matching handler in
@@ -400,10 +478,20 @@ impl JobHost {
             // Reap completed job tasks so the set does not grow without bound.
             while tasks.try_join_next().is_some() {}
 
+            // Beaten on every pass, and while waiting for something to be
+            // due, so that the readiness check can tell an idle host from one
+            // that has stopped going round.
+            let heartbeat = context.job_host();
+            heartbeat.beat();
+
             // The consumer works on behalf of every tenant, so it dequeues
             // from the root and immediately narrows itself to the tenant that
             // owns the message before touching any handler.
-            match context.database().dequeue_any_global(reserve_for).await {
+            match context
+                .database()
+                .dequeue_any_global(reserve_for, || heartbeat.beat())
+                .await
+            {
                 Ok((tenant, item)) => {
                     let services = context.tenant(tenant);
 
@@ -442,6 +530,10 @@ impl JobHost {
     /// needs a bound whatever is written to it — sampling decides how fast it
     /// fills, not whether it does. Runs on start-up so that an installation
     /// upgrading into this does not have to wait a day to feel it.
+    ///
+    /// Kept webhook deliveries are trimmed on the same schedule, for the
+    /// workflows that have stopped receiving any, and so are the delivery ids
+    /// remembered to recognise a sender's retries.
     async fn prune_audit_log(context: AppContext) {
         /// Often enough that the log never drifts far past its limits, and
         /// rarely enough that the delete is never the reason a write waits.
@@ -465,6 +557,37 @@ impl JobHost {
                 }
             }
 
+            match crate::deliveries::prune_all(&context).await {
+                Ok(0) => debug!("Every kept webhook delivery is within its retention."),
+                Ok(removed) => {
+                    info!("Removed {removed} kept webhook deliveries past their retention.")
+                }
+                Err(err) => {
+                    error!(error = %err, "Failed to trim the kept webhook deliveries: {err}");
+                    context.session().record_human_error(&err);
+                }
+            }
+
+            match crate::runs::prune_all(&context).await {
+                Ok(0) => debug!("Every workflow's run history is within its retention."),
+                Ok(removed) => info!("Removed {removed} workflow runs past their retention."),
+                Err(err) => {
+                    error!(error = %err, "Failed to trim the workflows' run histories: {err}");
+                    context.session().record_human_error(&err);
+                }
+            }
+
+            match crate::webhooks::repeats::prune_all(&context).await {
+                Ok(0) => debug!("Every remembered webhook delivery id is still within its window."),
+                Ok(removed) => {
+                    info!("Forgot {removed} webhook delivery ids too old to be retried.")
+                }
+                Err(err) => {
+                    error!(error = %err, "Failed to forget expired webhook delivery ids: {err}");
+                    context.session().record_human_error(&err);
+                }
+            }
+
             tokio::time::sleep(EVERY).await;
         }
     }
@@ -528,7 +651,7 @@ impl JobHost {
 
         let ctx = JobContext::new(
             services.clone(),
-            item.scheduled_at,
+            arrived_at(&item),
             item.traceparent.clone(),
             item.tracestate.clone(),
         )
@@ -538,24 +661,110 @@ impl JobHost {
         let workflow = workflow_of(&item);
         let started_at = Utc::now();
 
-        match handler
-            .handle(ctx, &item.payload)
-            .instrument(span.clone())
-            .await
-        {
+        let run = handler.handle(ctx, &item.payload).instrument(span.clone());
+
+        // A workflow's run is watched by a passive observer, which changes
+        // nothing about what it does and notes what that was: the tasks it
+        // filed are announced to the workflows following it, and a delivery is
+        // kept for the inspector alongside the request it was. What it logs is
+        // captured too, for its owner to read on the run.
+        //
+        // A replay an owner asked for is run the same way, carrying the
+        // credentials it first arrived with: its signature is checked again,
+        // and only its age is overlooked (see `crate::preview::is_redelivery`).
+        let delivery = workflow.zip(delivery_of(&item));
+        let ((result, observation), log) = match workflow {
+            Some(_) => {
+                let options = crate::preview::Options {
+                    passive: true,
+                    redelivery: delivery
+                        .as_ref()
+                        .is_some_and(|(_, delivery)| delivery.replay_of.is_some()),
+                    ..Default::default()
+                };
+                crate::run_log::capture(&span, crate::preview::observe(options, run)).await
+            }
+            None => (
+                (run.await, crate::preview::Observation::default()),
+                automate_api::RunLog::default(),
+            ),
+        };
+
+        crate::metrics::global().job(
+            name,
+            result.is_ok(),
+            (Utc::now() - started_at).to_std().unwrap_or_default(),
+        );
+
+        // Counted before the observation is handed on to be kept with the
+        // delivery, for the run history.
+        let details = RunDetails {
+            counts: automate_api::RunCounts {
+                items: observation.items.len() as u32,
+                matched: observation.items.iter().filter(|item| item.matched).count() as u32,
+                published: observation.dispatched.len() as u32,
+            },
+            log,
+        };
+
+        // A run that set its input aside did nothing worth following. Its
+        // input may be a forged body its signature check turned away, which
+        // must not reach anybody as though this workflow had vouched for it.
+        let discarded = observation.discarded.clone();
+        let events = workflow
+            .filter(|_| result.is_err() || discarded.is_none())
+            .map(|workflow| {
+                crate::events::of_run(
+                    workflow,
+                    &item.payload,
+                    result.as_ref().err(),
+                    &observation.dispatched,
+                )
+            });
+
+        if let Some((workflow, delivery)) = delivery {
+            Self::record_delivery(
+                &services,
+                workflow,
+                &delivery,
+                item.scheduled_at,
+                result.as_ref().err(),
+                observation,
+            )
+            .await;
+        }
+
+        match result {
             Ok(()) => {
-                info!("Job '{name}' completed successfully (traceparent: {traceparent}).");
+                match &discarded {
+                    Some(reason) => info!(
+                        "Job '{name}' set its input aside (traceparent: {traceparent}): {reason}"
+                    ),
+                    None => {
+                        info!("Job '{name}' completed successfully (traceparent: {traceparent}).")
+                    }
+                }
                 if let Some(workflow) = workflow {
+                    // Recorded as skipped rather than succeeded, so that a
+                    // forged delivery to a failing workflow does not make it
+                    // look recovered.
+                    let outcome = match discarded {
+                        Some(_) => AuditOutcome::Skipped,
+                        None => AuditOutcome::Success,
+                    };
+
                     Self::record_run(
                         &services,
                         workflow,
                         started_at,
-                        AuditOutcome::Success,
-                        None,
+                        outcome,
+                        discarded,
                         &item.payload,
+                        details,
                     )
                     .await;
                 }
+                Self::announce(&services, events.unwrap_or_default()).await;
                 if let Err(err) = queue.complete(name.to_string(), item).await {
                     error!(error = %err, "Failed to mark job '{name}' as completed (traceparent: {traceparent}): {err}");
                     session.record_human_error(&err);
@@ -579,9 +788,11 @@ impl JobHost {
                         AuditOutcome::Failure,
                         Some(err.to_string()),
                         &item.payload,
+                        details,
                     )
                     .await;
                 }
+                Self::announce(&services, events.unwrap_or_default()).await;
 
                 error!(error = %err, "An error occurred while processing job '{name}' (traceparent: {traceparent}): {err}");
             }
@@ -594,7 +805,7 @@ impl JobHost {
     /// this a workflow that has been failing for a week looks exactly like one
     /// that has been working.
     ///
-    /// The record is overwritten rather than appended, and the audit log hears
+    /// The run joins the workflow's bounded history, and the audit log hears
     /// about it only when the answer changes. A workflow taking thousands of
     /// deliveries a day would otherwise write thousands of rows nobody reads,
     /// and bury the entries somebody does.
@@ -605,24 +816,68 @@ impl JobHost {
         outcome: AuditOutcome,
         message: Option<String>,
         payload: &serde_json::Value,
+        details: RunDetails,
     ) {
-        use automate_api::{RunOutcome, RunReport};
+        use automate_api::{RunOutcome, RunReport, RunTrigger};
+
+        let runs = crate::runs::RunStore::new(services);
+        let trigger = match trigger_of(payload) {
+            Some(trigger) => trigger,
+            None => match runs.take_request(workflow).await {
+                Ok(true) => RunTrigger::Manual,
+                Ok(false) => RunTrigger::Schedule,
+                Err(err) => {
+                    warn!(error = %err, "Failed to tell whether a workflow run was asked for, so it will be shown as scheduled: {err}");
+                    RunTrigger::Schedule
+                }
+            },
+        };
+
+        // A template may have put one of the account's secrets into the payload
+        // a run produced, or into the error it failed with, and neither should be
+        // kept for anyone with access to the run history to read.
+        let secrets = match crate::variables::VariableStore::for_services(services)
+            .load()
+            .await
+        {
+            Ok(variables) => Some(variables.secret_values()),
+            Err(err) => {
+                warn!(error = %err, "Failed to load the secrets to scrub from a workflow run, so its input, message and log will not be kept: {err}");
+                None
+            }
+        };
+        let message = match (&secrets, message) {
+            (Some(secrets), Some(message)) => Some(crate::runs::scrub(&message, secrets)),
+            _ => None,
+        };
+        let input = secrets
+            .as_ref()
+            .and_then(|secrets| crate::runs::keepable(payload, secrets));
+        let log = match &secrets {
+            Some(secrets) => crate::run_log::redact(details.log, secrets),
+            None => Default::default(),
+        };
 
         let report = RunReport {
             started_at,
             finished_at: Utc::now(),
             outcome: match outcome {
                 AuditOutcome::Success => RunOutcome::Succeeded,
+                AuditOutcome::Skipped => RunOutcome::Discarded,
                 _ => RunOutcome::Failed,
             },
             message: message.clone(),
-            input: crate::runs::keepable(payload),
+            input,
+            // See `WebhookDelivery::replay_of`.
+            replay_of: payload
+                .get("replay_of")
+                .and_then(|id| serde_json::from_value(id.clone()).ok()),
+            trigger: Some(trigger),
+            counts: details.counts,
+            log,
         };
 
-        let transition = match crate::runs::RunStore::new(services)
-            .record(workflow, report)
-            .await
-        {
+        let transition = match runs.record(workflow, report).await {
             Ok(transition) => transition,
             Err(err) => {
                 // The run is over either way; losing the note about it is not a
@@ -632,19 +887,57 @@ impl JobHost {
             }
         };
 
+        if transition == crate::runs::Transition::Unchanged {
+            return;
+        }
+
+        // The owner is told by name, which the workflow's type works out from
+        // its configuration; one that has since been deleted is named by its
+        // identifier instead.
+        let store = crate::workflow_store::WorkflowStore::new(services);
+        let name = match store.find(workflow).await {
+            Ok(Some(record)) => store
+                .present_record(record)
+                .map(|presented| presented.name)
+                .unwrap_or_else(|_| workflow.to_string()),
+            _ => workflow.to_string(),
+        };
+        let incident = crate::notifications::Incident::Workflow {
+            id: workflow,
+            name: &name,
+        };
+
         let entry = match transition {
             crate::runs::Transition::Unchanged => return,
             crate::runs::Transition::StartedFailing => {
+                crate::notifications::raise(
+                    services,
+                    incident,
+                    &match &message {
+                        Some(message) => format!("started failing: {message}"),
+                        None => "started failing.".to_string(),
+                    },
+                )
+                .await;
+
                 AuditEntry::new(AuditCategory::WorkflowRun, "started-failing", outcome)
                     .subject(workflow)
                     .message(message.unwrap_or_else(|| "This workflow stopped working.".into()))
             }
             crate::runs::Transition::Recovered { after } => {
+                let runs = if after == 1 { "run" } else { "runs" };
+
+                crate::notifications::resolve(
+                    services,
+                    incident,
+                    &format!("recovered after {after} failed {runs}."),
+                )
+                .await;
+
                 AuditEntry::new(AuditCategory::WorkflowRun, "recovered", outcome)
                     .subject(workflow)
                     .message(format!(
-                        "This workflow is working again, after {after} failed {}.",
-                        if after == 1 { "run" } else { "runs" }
+                        "This workflow is working again, after {after} failed {runs}."
                     ))
             }
         };
@@ -654,6 +947,76 @@ impl JobHost {
         }
     }
 
+    /// Hands what a run did to the workflows following it.
+    ///
+    /// Like [`Self::record_run`], failing to is not a reason to fail the run
+    /// or retry it: the run is over, and running it again to announce it would
+    /// file its tasks twice.
+    async fn announce(services: &AppServices, events: Vec<crate::events::WorkflowEvent>) {
+        for event in events {
+            let name = event.name.clone();
+            if let Err(err) = crate::events::emit(services, event).await {
+                warn!(error = %err, event.name = %name, "Failed to hand a workflow's event to the workflows following it: {err}");
+            }
+        }
+    }
+
+    /// Keeps a delivery, and what its workflow made of it, for the inspector.
+    ///
+    /// Like [`Self::record_run`], failing to keep it is not a reason to fail
+    /// the run; the delivery has been handled either way.
+    async fn record_delivery(
+        services: &AppServices,
+        workflow: automate_api::WorkflowId,
+        delivery: &crate::webhooks::WebhookDelivery,
+        received_at: DateTime<Utc>,
+        error: Option<&human_errors::Error>,
+        observation: crate::preview::Observation,
+    ) {
+        use automate_api::RunOutcome;
+
+        if services.config().deliveries.keep == 0 {
+            return;
+        }
+
+        // A delivery is somebody else's request, and the secrets a template
+        // may have put into what it filed are ours; without them there is no
+        // telling what is safe to keep.
+        let secrets = match crate::variables::VariableStore::for_services(services)
+            .load()
+            .await
+        {
+            Ok(variables) => variables.secret_values(),
+            Err(err) => {
+                warn!(error = %err, "Failed to load the secrets to scrub from a webhook delivery, so it will not be kept: {err}");
+                return;
+            }
+        };
+
+        let (outcome, message) = match (error, &observation.discarded) {
+            (Some(err), _) => (RunOutcome::Failed, Some(err.to_string())),
+            (None, Some(reason)) => (RunOutcome::Discarded, Some(reason.clone())),
+            (None, None) => (RunOutcome::Succeeded, None),
+        };
+
+        let mut record = crate::deliveries::describe(
+            &delivery.event,
+            received_at,
+            outcome,
+            message.as_deref(),
+            observation,
+            &secrets,
+        );
+        record.replay_of = delivery.replay_of;
+
+        if let Err(err) = crate::deliveries::DeliveryStore::new(services)
+            .record(workflow, record, &delivery.event)
+            .await
+        {
+            warn!(error = %err, "Failed to keep a webhook delivery for inspection: {err}");
+        }
+    }
+
     /// Records a job failure against the supplied span following OpenTelemetry
     /// semantic conventions, attaching an `exception` event and marking the span
     /// status as an error so the failure is visible in exported traces.
@@ -773,6 +1136,7 @@ mod tests {
             outcome,
             (outcome == AuditOutcome::Failure).then(|| "it broke".to_string()),
             &payload,
+            RunDetails::default(),
         )
         .await;
     }
@@ -810,6 +1174,47 @@ mod tests {
         assert_eq!(runs[1].action, "started-failing");
     }
 
+    #[tokio::test]
+    async fn the_owner_hears_about_an_incident_once_when_it_starts_and_once_when_it_ends() {
+        let context = crate::services::AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let id = automate_api::WorkflowId::from_entropy(4);
+
+        crate::notifications::NotificationStore::new(&services)
+            .set(automate_api::NotificationDestination::Webhook {
+                url: "https://chat.example.com/hooks/abc".into(),
+            })
+            .await
+            .unwrap();
+
+        for _ in 0..20 {
+            ran(&services, id, AuditOutcome::Failure, serde_json::json!({})).await;
+        }
+        ran(&services, id, AuditOutcome::Success, serde_json::json!({})).await;
+
+        let posts = services
+            .queue()
+            .peek::<_, crate::publishers::HttpPostPayload>("http/post", 50)
+            .await
+            .unwrap();
+        let mut summaries: Vec<String> = posts
+            .iter()
+            .map(|post| {
+                let body: serde_json::Value = serde_json::from_str(&post.payload.body).unwrap();
+                body["summary"].as_str().unwrap().to_string()
+            })
+            .collect();
+        summaries.sort();
+
+        assert_eq!(
+            summaries,
+            vec![
+                format!("Workflow '{id}' recovered after 20 failed runs."),
+                format!("Workflow '{id}' started failing: it broke"),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn a_run_keeps_what_it_ran_on() {
         let context = crate::services::AppContext::new_mock(|_| {}).await.unwrap();
@@ -834,6 +1239,58 @@ mod tests {
         assert_eq!(state.last.input.unwrap()["event"]["body"], "{}");
     }
 
+    #[tokio::test]
+    async fn a_run_says_what_started_it() {
+        let context = crate::services::AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let id = automate_api::WorkflowId::from_entropy(6);
+        let delivery = serde_json::json!({
+            "workflow": id,
+            "event": { "body": "{}", "query": "", "headers": {} },
+        });
+        let mut replay = delivery.clone();
+        replay["replay_of"] = serde_json::json!(automate_api::DeliveryId::from_entropy(4));
+        let event = serde_json::to_value(crate::events::EventDelivery {
+            workflow: id,
+            event: crate::events::WorkflowEvent::new(
+                crate::events::RUN_FAILED,
+                automate_api::WorkflowId::from_entropy(99),
+                serde_json::json!({}),
+            ),
+        })
+        .unwrap();
+
+        let runs = crate::runs::RunStore::new(&services);
+        ran(&services, id, AuditOutcome::Success, serde_json::json!({})).await;
+        runs.request(id).await.unwrap();
+        ran(&services, id, AuditOutcome::Success, serde_json::json!({})).await;
+        ran(&services, id, AuditOutcome::Success, delivery).await;
+        ran(&services, id, AuditOutcome::Success, replay).await;
+        ran(&services, id, AuditOutcome::Success, event).await;
+
+        let mut triggers: Vec<_> = runs
+            .history(id, None, 10)
+            .await
+            .unwrap()
+            .runs
+            .into_iter()
+            .map(|run| run.trigger.unwrap())
+            .collect();
+        triggers.reverse();
+
+        use automate_api::RunTrigger;
+        assert_eq!(
+            triggers,
+            vec![
+                RunTrigger::Schedule,
+                RunTrigger::Manual,
+                RunTrigger::Webhook,
+                RunTrigger::Replay,
+                RunTrigger::Event,
+            ],
+        );
+    }
+
     #[derive(Serialize, Deserialize)]
     struct TestPayload {
         id: String,
@@ -1117,4 +1574,391 @@ mod tests {
         .unwrap();
         assert_eq!(retried.partition, "test/failing-retry");
     }
+
+    #[tokio::test]
+    async fn a_delivery_is_kept_with_what_its_workflow_filed_from_it() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let system = context.tenant(TenantId::system());
+
+        let workflow = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&system)
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+        services
+            .queue()
+            .enqueue(
+                partition,
+                serde_json::json!({
+                    "workflow": workflow.id,
+                    "event": {
+                        "body": r#"{"environment":"production"}"#,
+                        "query": "",
+                        "headers": { "Content-Type": "application/json" },
+                    },
+                }),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let item = services
+            .queue()
+            .dequeue_any(chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        let handler = handler(&item.partition).unwrap();
+        JobHost::process(handler, item, services.clone(), tracing::Span::none()).await;
+
+        let store = crate::deliveries::DeliveryStore::new(&services);
+        let listed = store.list(workflow.id).await.unwrap();
+        assert_eq!(listed.len(), 1);
+
+        let kept = store.get(workflow.id, listed[0].id).await.unwrap().unwrap();
+        assert_eq!(kept.body, r#"{"environment":"production"}"#);
+        assert_eq!(
+            kept.published[0].title.as_deref(),
+            Some("Deployed production")
+        );
+
+        assert_eq!(
+            services
+                .queue()
+                .peek("todoist/create-task", 10)
+                .await
+                .unwrap()
+                .len(),
+            1,
+            "watching a delivery to keep it must not stop it filing its task",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_run_keeps_what_it_logged_for_its_owner_to_read() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let system = context.tenant(TenantId::system());
+
+        let workflow = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&system)
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                }),
+                schedule: None,
+                enabled: false,
+            })
+            .await
+            .unwrap();
+
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+        services
+            .queue()
+            .enqueue(
+                partition,
+                serde_json::json!({
+                    "workflow": workflow.id,
+                    "event": { "body": "{}", "query": "", "headers": {} },
+                }),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let item = services
+            .queue()
+            .dequeue_any(chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        let handler = handler(&item.partition).unwrap();
+        JobHost::process(handler, item, services.clone(), tracing::Span::none()).await;
+
+        let state = crate::runs::RunStore::new(&services)
+            .get(workflow.id)
+            .await
+            .unwrap()
+            .expect("the run should be recorded");
+        assert_eq!(
+            state
+                .last
+                .log
+                .lines
+                .iter()
+                .map(|line| line.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Discarding a delivery for a paused workflow."],
+            "only what the run itself logged should be kept with it",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_delivery_refused_by_its_signature_is_not_announced_to_followers() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let system = context.tenant(TenantId::system());
+        let store = crate::workflow_store::WorkflowStore::new(&services).with_index(&system);
+
+        let workflow = store
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                    "signature": { "enabled": true, "secret": "s3cr3t" },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+
+        store
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "event".into(),
+                config: serde_json::json!({
+                    "name": "Follow-up",
+                    "event": crate::events::RUN_SUCCEEDED,
+                    "from": workflow.id.to_string(),
+                    "title": "${{ payload.input.environment }}",
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+
+        // Anybody who knows the address can post this; it carries no signature.
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+        services
+            .queue()
+            .enqueue(
+                partition,
+                serde_json::json!({
+                    "workflow": workflow.id,
+                    "event": {
+                        "body": r#"{"environment":"forged"}"#,
+                        "query": "",
+                        "headers": { "Content-Type": "application/json" },
+                    },
+                }),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let item = services
+            .queue()
+            .dequeue_any(chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        let handler = handler(&item.partition).unwrap();
+        JobHost::process(handler, item, services.clone(), tracing::Span::none()).await;
+
+        let followed: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("events/todoist", 10).await.unwrap();
+        assert!(
+            followed.is_empty(),
+            "a body nobody vouched for must not reach the workflows following this one",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_refused_delivery_does_not_make_a_failing_workflow_look_recovered() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let system = context.tenant(TenantId::system());
+
+        let workflow = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&system)
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                    "signature": { "enabled": true, "secret": "s3cr3t" },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+
+        ran(
+            &services,
+            workflow.id,
+            AuditOutcome::Failure,
+            serde_json::json!({}),
+        )
+        .await;
+
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+        services
+            .queue()
+            .enqueue(
+                partition,
+                serde_json::json!({
+                    "workflow": workflow.id,
+                    "event": { "body": "{}", "query": "", "headers": {} },
+                }),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let item = services
+            .queue()
+            .dequeue_any(chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        let handler = handler(&item.partition).unwrap();
+        JobHost::process(handler, item, services.clone(), tracing::Span::none()).await;
+
+        let state = crate::runs::RunStore::new(&services)
+            .get(workflow.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.last.outcome, automate_api::RunOutcome::Discarded);
+        assert_eq!(
+            state.consecutive_failures, 1,
+            "the workflow is no less broken for having turned a forgery away",
+        );
+        assert!(state.last.message.is_some(), "the run should say why");
+
+        let kept = crate::deliveries::DeliveryStore::new(&services)
+            .list(workflow.id)
+            .await
+            .unwrap();
+        assert_eq!(kept[0].outcome, automate_api::RunOutcome::Discarded);
+    }
+
+    #[tokio::test]
+    async fn a_replayed_delivery_is_checked_by_its_signature_but_not_its_age() {
+        use hmac::{Hmac, KeyInit, Mac};
+
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        let services = context.tenant(TenantId::local());
+        let system = context.tenant(TenantId::system());
+
+        // Signed with a timestamp, so that a delivery sent again days later
+        // would be turned away for its age if it had come from the sender.
+        let workflow = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&system)
+            .create(crate::workflow_store::WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                    "signature": {
+                        "enabled": true,
+                        "secret": "s3cr3t",
+                        "prefix": "v1=",
+                        "signed": "timestamp.body",
+                    },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+
+        let body = r#"{"environment":"production"}"#;
+        let signed_at = (chrono::Utc::now() - chrono::Duration::days(2)).timestamp();
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"s3cr3t").unwrap();
+        mac.update(format!("{signed_at}.{body}").as_bytes());
+        let genuine = format!(
+            "t={signed_at},v1={}",
+            hex::encode(mac.finalize().into_bytes())
+        );
+
+        let original: automate_api::DeliveryId = "brisk-harbor".parse().unwrap();
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+
+        // The second is what a replay would look like had its credentials not
+        // been kept, or had somebody made it up.
+        for signature in [genuine, "<redacted>".to_string()] {
+            services
+                .queue()
+                .enqueue(
+                    partition,
+                    crate::webhooks::WebhookDelivery {
+                        workflow: workflow.id,
+                        event: WebhookEvent {
+                            body: body.to_string(),
+                            query: String::new(),
+                            headers: [("x-signature".to_string(), signature)].into(),
+                        },
+                        replay_of: Some(original),
+                        arrived_at: None,
+                    },
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+
+            let item = services
+                .queue()
+                .dequeue_any(chrono::Duration::seconds(60))
+                .await
+                .unwrap();
+            let handler = handler(&item.partition).unwrap();
+            JobHost::process(handler, item, services.clone(), tracing::Span::none()).await;
+        }
+
+        assert_eq!(
+            services
+                .queue()
+                .peek("todoist/create-task", 10)
+                .await
+                .unwrap()
+                .len(),
+            1,
+            "only the replay still carrying its sender's signature should be filed",
+        );
+
+        let state = crate::runs::RunStore::new(&services)
+            .get(workflow.id)
+            .await
+            .unwrap()
+            .expect("the run should be recorded");
+        assert_eq!(state.last.replay_of, Some(original));
+
+        let store = crate::deliveries::DeliveryStore::new(&services);
+        let listed = store.list(workflow.id).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(
+            listed.iter().all(|kept| kept.replay_of == Some(original)),
+            "the delivery log should tell a replay apart from the sender delivering twice",
+        );
+        assert_eq!(
+            listed
+                .iter()
+                .filter(|kept| kept.signature == Some(automate_api::SignatureVerdict::Verified))
+                .count(),
+            1,
+        );
+    }
 }
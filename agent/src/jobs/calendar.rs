@@ -206,7 +206,8 @@ impl Job for CalendarWorkflow {
         for item in items.into_iter() {
             match item {
                 Diff::Added(id, item) | Diff::Modified(id, item)
-                    if job.filter.matches(&item).unwrap_or_default() =>
+                    if crate::preview::matches(&job.filter, &item, || item.summary.clone())
+                        .unwrap_or_default() =>
                 {
                     info!(
                         "Calendar item '{}' matched filter, creating Todoist task",
@@ -334,6 +334,19 @@ impl Job for CronJob {
                 }
 
                 let store = crate::workflow_store::WorkflowStore::new(&services);
+
+                // Skipped like a pause, with the schedule still armed, so the
+                // first run after the snooze ends happens without anybody
+                // having to remember to turn the workflow back on. A schedule
+                // has nothing to hold, so a snooze that holds deliveries simply
+                // skips its runs.
+                if store.check_snooze(&record, now).await?
+                    != crate::workflow_store::SnoozeVerdict::Run
+                {
+                    info!(workflow.id = %record.id, "Skipping a run of a snoozed workflow.");
+                    return Ok(());
+                }
+
                 store.mark_run(record.id, now).await?;
 
                 (
@@ -550,6 +563,47 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn a_snoozed_workflow_skips_its_runs_and_then_carries_on_by_itself() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let workflow = store.create(draft()).await.unwrap();
+        store
+            .snooze(
+                workflow.id,
+                Some(automate_api::Snooze {
+                    runs: Some(1),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+        CronJob::reconcile(&services).await.unwrap();
+
+        let armed = run_once(&services).await;
+        assert_eq!(armed.len(), 1, "a snoozed workflow's schedule stays armed");
+
+        let dispatched: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("rss/todoist", 10).await.unwrap();
+        assert!(
+            dispatched.is_empty(),
+            "the snoozed run should have been skipped"
+        );
+
+        run_once(&services).await;
+
+        let dispatched: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("rss/todoist", 10).await.unwrap();
+        assert_eq!(
+            dispatched.len(),
+            1,
+            "the run after the snooze should go ahead without anybody waking it",
+        );
+    }
+
     #[tokio::test]
     async fn a_run_records_when_it_happened() {
         let services = crate::services::ServicesContainer::new_mock()
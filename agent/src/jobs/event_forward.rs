@@ -0,0 +1,359 @@
+//! A workflow that follows another one, and posts to an address when it does
+//! something.
+//!
+//! The counterpart of [`super::event_todoist`] for the events that are better
+//! sent somewhere else than filed: "when a Grafana alert resolves, also post to
+//! our incident webhook". It subscribes and filters in exactly the same way,
+//! and hands what it renders to [`crate::publishers::HttpPost`].
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventDelivery,
+    prelude::*,
+    publishers::{HttpPost, HttpPostPayload},
+    variables::VariableStore,
+    webhook_payload::{JsonFilter, PayloadFilter, Template, render},
+};
+
+/// Which events to follow, and where to post each.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventForwardConfig {
+    /// What to call this workflow, so it can be told apart from the others in a
+    /// list of them.
+    pub name: String,
+
+    /// The name of the event to follow, such as `run.failed`.
+    pub event: String,
+
+    /// The workflow whose events to follow. Empty follows the event from any of
+    /// the account's workflows that are not themselves following events.
+    #[serde(default)]
+    pub from: String,
+
+    /// Which events are worth posting. Empty means all of them.
+    #[serde(default)]
+    pub filter: PayloadFilter,
+
+    /// Where to post, rendered against the event so that an address kept as a
+    /// secret can be written as `${{ secrets.… }}`.
+    pub url: Template,
+
+    /// What to post. Empty posts the event itself, as JSON.
+    #[serde(default)]
+    pub body: Option<Template>,
+
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+}
+
+fn default_content_type() -> String {
+    "application/json".to_string()
+}
+
+impl Display for EventForwardConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event-forward/{}", self.name)
+    }
+}
+
+#[derive(Clone)]
+pub struct EventForwardWorkflow;
+
+/// The setup notes shown while somebody is configuring one of these.
+const DOCUMENTATION: &str = r#"## What this does
+
+Follows your other workflows, and posts to an address of your choosing when
+one of them does something you asked to hear about — a chat webhook, a paging
+service, your own incident tooling. It chooses events exactly as a **Workflow
+Event** workflow does, and its notes describe the events there are and what
+each carries.
+
+## The address
+
+Paste the address you were given by the service you are posting to. Most of
+these addresses are credentials in their own right, so consider keeping it as
+a secret under `/api/v1/variables` and writing `${{ secrets.incident_hook }}`
+here instead: it is then scrubbed from the run history.
+
+A post that fails, or that the far end answers with an error, is tried again
+later.
+
+## The body
+
+Leave **Body** empty to post the event itself as JSON:
+
+```json
+{
+  "name": "run.succeeded",
+  "workflow": "copper-tiger-canyon",
+  "emitted_at": "2024-01-01T09:00:00Z",
+  "payload": { "input": { "status": "resolved", "title": "Disk full" } }
+}
+```
+
+or write a template for what the service expects, with **Content type** set
+to match:
+
+```
+{"text": "${{ payload.input.title }} has resolved"}
+```
+
+Values are inserted as they are, without escaping, so a value containing a
+quote will break a JSON body. Post the event itself when you cannot be sure of
+what the values hold.
+"#;
+
+crate::register_job!(EventForwardWorkflow);
+crate::register_workflow_type!(EventForwardWorkflow);
+
+impl crate::workflows::ConfigurableWorkflow for EventForwardWorkflow {
+    type ConfigType = EventForwardConfig;
+
+    fn type_id() -> &'static str {
+        "event_forward"
+    }
+
+    fn describe(config: &Self::ConfigType) -> String {
+        config.name.clone()
+    }
+
+    fn descriptor() -> automate_api::WorkflowTypeDescriptor {
+        use automate_api::{FieldDescriptor, FieldKind, WorkflowTrigger, WorkflowTypeDescriptor};
+
+        WorkflowTypeDescriptor {
+            id: Self::type_id().to_string(),
+            name: "Workflow Event Forwarding".to_string(),
+            description:
+                "Posts to an address when another of your workflows fails, files a task, or emits an event of its own."
+                    .to_string(),
+            documentation: DOCUMENTATION.to_string(),
+            trigger: WorkflowTrigger::Event {
+                source: "workflows".to_string(),
+            },
+            fields: vec![
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: name),
+                    "Name",
+                    FieldKind::Text {
+                        placeholder: Some("Incident channel".into()),
+                    },
+                )
+                .with_help(
+                    "Used to label this workflow, so you can tell it apart from your others.",
+                )
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: event),
+                    "Event",
+                    FieldKind::Text {
+                        placeholder: Some(crate::events::RUN_SUCCEEDED.into()),
+                    },
+                )
+                .with_help(
+                    "The event to follow: run.succeeded, run.failed, task.published, or the name a generic webhook emits.",
+                )
+                .with_default(crate::events::RUN_SUCCEEDED)
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: from),
+                    "From workflow",
+                    FieldKind::Text {
+                        placeholder: Some("copper-tiger-canyon".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. The identifier of the one workflow to follow. Leave it empty to follow the event from any of your workflows that are not themselves following an event.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: filter),
+                    "Filter",
+                    FieldKind::Filter {
+                        fields: vec![
+                            "name".into(),
+                            "workflow".into(),
+                            "payload.input".into(),
+                            "payload.message".into(),
+                            "payload.title".into(),
+                        ],
+                    },
+                )
+                .with_help(
+                    "Only post events matching this, such as payload.input.status == \"resolved\". Leave it empty to post every one.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: url),
+                    "Address",
+                    FieldKind::Text {
+                        placeholder: Some("https://hooks.example.com/incidents".into()),
+                    },
+                )
+                .with_help(
+                    "Where to post. Write ${{ secrets.name }} to use an address kept as a secret.",
+                )
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: body),
+                    "Body",
+                    FieldKind::TextArea {
+                        placeholder: Some(r#"{"text": "${{ payload.input.title }} resolved"}"#.into()),
+                    },
+                )
+                .with_help("Optional. What to post, written as a template. Leave it empty to post the event as JSON."),
+                FieldDescriptor::new(
+                    crate::config_path!(EventForwardConfig: content_type),
+                    "Content type",
+                    FieldKind::Text {
+                        placeholder: Some("application/json".into()),
+                    },
+                )
+                .with_default("application/json"),
+            ],
+        }
+    }
+}
+
+impl Job for EventForwardWorkflow {
+    type JobType = EventDelivery;
+
+    fn partition() -> &'static str {
+        "events/forward"
+    }
+
+    #[instrument("workflow.event_forward.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        let Some(config) = job.config::<EventForwardConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let event = &job.event;
+        let value = event.as_value();
+
+        if !crate::preview::matches(&config.filter, &JsonFilter(&value), || {
+            format!("The {} event from {}", event.name, event.workflow)
+        })? {
+            debug!(
+                workflow.id = %job.workflow,
+                "An event did not match this workflow's filter, so it was not posted.",
+            );
+            return Ok(());
+        }
+
+        let variables = VariableStore::for_services(services).load().await?;
+
+        let body = match &config.body {
+            Some(template) => render(template, &value, &variables)?,
+            None => value.to_string(),
+        };
+
+        HttpPost::dispatch(
+            HttpPostPayload {
+                url: render(&config.url, &value, &variables)?.trim().to_string(),
+                body,
+                content_type: config.content_type.clone(),
+            },
+            None,
+            services,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::events::WorkflowEvent;
+    use crate::workflow_store::{WorkflowDraft, WorkflowStore};
+
+    use super::*;
+
+    async fn posted(
+        config: serde_json::Value,
+        status: &str,
+    ) -> Vec<crate::db::PeekedMessage<serde_json::Value>> {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+
+        let workflow = WorkflowStore::new(&services)
+            .create(WorkflowDraft {
+                type_id: "event_forward".into(),
+                config,
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap()
+            .id;
+
+        EventForwardWorkflow
+            .handle(
+                JobContext::new(services.clone(), Utc::now(), None, None),
+                &EventDelivery {
+                    workflow,
+                    event: WorkflowEvent::new(
+                        crate::events::RUN_SUCCEEDED,
+                        automate_api::WorkflowId::from_entropy(99),
+                        serde_json::json!({ "input": { "status": status, "title": "Disk full" } }),
+                    ),
+                },
+            )
+            .await
+            .unwrap();
+
+        services.queue().peek("http/post", 10).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_resolved_alert_is_posted_with_the_body_its_template_describes() {
+        let posted = posted(
+            serde_json::json!({
+                "name": "Incident channel",
+                "event": "run.succeeded",
+                "filter": "payload.input.status == \"resolved\"",
+                "url": "https://hooks.example.com/incidents",
+                "body": r#"{"text": "${{ payload.input.title }} resolved"}"#,
+            }),
+            "resolved",
+        )
+        .await;
+
+        assert_eq!(posted.len(), 1);
+        assert_eq!(
+            posted[0].payload["url"],
+            "https://hooks.example.com/incidents"
+        );
+        assert_eq!(
+            posted[0].payload["body"],
+            r#"{"text": "Disk full resolved"}"#
+        );
+        assert_eq!(posted[0].payload["content_type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn an_event_without_a_body_template_is_posted_as_it_is() {
+        let posted = posted(
+            serde_json::json!({
+                "name": "Incident channel",
+                "event": "run.succeeded",
+                "url": "https://hooks.example.com/incidents",
+            }),
+            "firing",
+        )
+        .await;
+
+        let body: serde_json::Value =
+            serde_json::from_str(posted[0].payload["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body["name"], "run.succeeded");
+        assert_eq!(body["payload"]["input"]["status"], "firing");
+    }
+}
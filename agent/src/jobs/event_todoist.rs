@@ -0,0 +1,398 @@
+//! A workflow that follows another one, and files a task when it does something.
+//!
+//! Most of what people want from one workflow following another is a task:
+//! "tell me when the release watcher fails", "when the generic webhook hears
+//! about a deploy, remind me to check the dashboards". This subscribes to an
+//! event by name (see [`crate::events`]), filters what arrives the way the
+//! generic webhook filters a delivery, and files a task rendered from it.
+//!
+//! Like the generic webhook it models nothing about what it receives. An event
+//! is read as the JSON [`WorkflowEvent::as_value`] describes — its `name`, the
+//! `workflow` that emitted it, and its `payload` — and handed to the owner's
+//! filter and templates, which address it by path.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{EventDelivery, WorkflowEvent},
+    prelude::*,
+    publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate, TodoistTarget},
+    variables::VariableStore,
+    webhook_payload::{JsonFilter, PayloadFilter, Template, render},
+};
+
+/// Which events to follow, and the task to file for each.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventTodoistConfig {
+    /// What to call this workflow, so it can be told apart from the others in a
+    /// list of them.
+    pub name: String,
+
+    /// The name of the event to follow, such as `run.failed`.
+    pub event: String,
+
+    /// The workflow whose events to follow. Empty follows the event from any of
+    /// the account's workflows that are not themselves following events.
+    #[serde(default)]
+    pub from: String,
+
+    /// Which events are worth a task. Empty means all of them.
+    #[serde(default)]
+    pub filter: PayloadFilter,
+
+    /// The task's title, rendered against the event.
+    pub title: Template,
+
+    #[serde(default)]
+    pub description: Option<Template>,
+
+    #[serde(default)]
+    pub todoist: TodoistTarget,
+}
+
+impl Display for EventTodoistConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event/{}", self.name)
+    }
+}
+
+#[derive(Clone)]
+pub struct EventTodoistWorkflow;
+
+/// The setup notes shown while somebody is configuring one of these.
+const DOCUMENTATION: &str = r#"## What this does
+
+Follows your other workflows, and files a Todoist task when one of them does
+something you asked to hear about. It is how one workflow leads to another —
+"when my release watcher fails, remind me to look at it", or "when the
+generic webhook hears about a deploy, file a task to check the dashboards" —
+without a workflow type written for each pair.
+
+## Choosing an event
+
+Every workflow emits these:
+
+- `run.succeeded` when a run finishes, whatever it found. Its `payload.input`
+  is what the run was handed: a webhook delivery's body, read as the generic
+  webhook reads one, or the workflow's configuration for a scheduled run.
+- `run.failed` when a run fails, with `payload.input` as above and the error in
+  `payload.message`. A run that is retried fails, and emits this, each time.
+- `task.published` for each task a run files, with `payload.title` and
+  `payload.description`.
+
+A generic webhook can also emit one of its own for each delivery it files,
+under the name in its **Emit event** field, with the delivery's body as its
+`payload`.
+
+Leave **From workflow** empty to follow the event from any of your workflows
+started by a schedule or a delivery, or paste in the identifier of one, such as
+`copper-tiger-canyon`. It is shown at the top of that workflow's panel when you
+open it. A workflow never follows its own events.
+
+## Filtering and templates
+
+The filter and templates see the event as:
+
+```
+name        the event's name
+workflow    the workflow that emitted it
+emitted_at  when
+payload     what it is about
+```
+
+so a filter following a Grafana workflow's runs might be:
+
+```
+payload.input.status == "resolved"
+```
+
+and a title:
+
+```
+${{ payload.title | default("A workflow failed") }}: ${{ payload.message }}
+```
+
+Both work as they do for the generic webhook, whose notes describe paths,
+functions and `${{ vars.… }}` in full.
+
+## Chains
+
+A workflow following another's events emits events of its own, so chains work.
+Each link after the first has to be named in **From workflow**: a workflow
+following events is never heard by one that follows anybody, or a few such
+workflows would hand each other's runs around until one delivery had cost
+thousands. An event is also dropped once it has passed through five
+workflows, which is what stops two workflows following each other from
+running for ever.
+"#;
+
+crate::register_job!(EventTodoistWorkflow);
+crate::register_workflow_type!(EventTodoistWorkflow);
+
+impl crate::workflows::ConfigurableWorkflow for EventTodoistWorkflow {
+    type ConfigType = EventTodoistConfig;
+
+    fn type_id() -> &'static str {
+        "event"
+    }
+
+    fn describe(config: &Self::ConfigType) -> String {
+        config.name.clone()
+    }
+
+    fn descriptor() -> automate_api::WorkflowTypeDescriptor {
+        use automate_api::{FieldDescriptor, FieldKind, WorkflowTrigger, WorkflowTypeDescriptor};
+
+        WorkflowTypeDescriptor {
+            id: Self::type_id().to_string(),
+            name: "Workflow Event".to_string(),
+            description: "Files a task when another of your workflows fails, files a task, or emits an event of its own."
+                .to_string(),
+            documentation: DOCUMENTATION.to_string(),
+            trigger: WorkflowTrigger::Event {
+                source: "workflows".to_string(),
+            },
+            fields: [
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: name),
+                    "Name",
+                    FieldKind::Text {
+                        placeholder: Some("Failed releases".into()),
+                    },
+                )
+                .with_help(
+                    "Used to label this workflow, so you can tell it apart from your others.",
+                )
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: event),
+                    "Event",
+                    FieldKind::Text {
+                        placeholder: Some(crate::events::RUN_FAILED.into()),
+                    },
+                )
+                .with_help(
+                    "The event to follow: run.succeeded, run.failed, task.published, or the name a generic webhook emits.",
+                )
+                .with_default(crate::events::RUN_FAILED)
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: from),
+                    "From workflow",
+                    FieldKind::Text {
+                        placeholder: Some("copper-tiger-canyon".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. The identifier of the one workflow to follow. Leave it empty to follow the event from any of your workflows that are not themselves following an event.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: filter),
+                    "Filter",
+                    FieldKind::Filter {
+                        fields: vec![
+                            "name".into(),
+                            "workflow".into(),
+                            "payload.input".into(),
+                            "payload.message".into(),
+                            "payload.title".into(),
+                        ],
+                    },
+                )
+                .with_help(
+                    "Only file events matching this, such as payload.input.status == \"resolved\". Leave it empty to file every one.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: title),
+                    "Task title",
+                    FieldKind::Text {
+                        placeholder: Some("A workflow failed: ${{ payload.message }}".into()),
+                    },
+                )
+                .with_help(
+                    "What the task is called. Write ${{ some.path }} to insert a value from the event.",
+                )
+                .required(),
+                FieldDescriptor::new(
+                    crate::config_path!(EventTodoistConfig: description),
+                    "Task description",
+                    FieldKind::TextArea { placeholder: None },
+                )
+                .with_help("Optional. Written the same way as the title."),
+            ]
+            .into_iter()
+            .chain(crate::todoist_target_fields!(
+                EventTodoistConfig,
+                project = Some("Inbox"),
+                section = None::<&str>
+            ))
+            .collect(),
+        }
+    }
+}
+
+impl Job for EventTodoistWorkflow {
+    type JobType = EventDelivery;
+
+    fn partition() -> &'static str {
+        "events/todoist"
+    }
+
+    #[instrument("workflow.event_todoist.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        let Some(config) = job.config::<EventTodoistConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let event: &WorkflowEvent = &job.event;
+        let value = event.as_value();
+
+        if !crate::preview::matches(&config.filter, &JsonFilter(&value), || {
+            format!("The {} event from {}", event.name, event.workflow)
+        })? {
+            debug!(
+                workflow.id = %job.workflow,
+                "An event did not match this workflow's filter, so no task was filed.",
+            );
+            return Ok(());
+        }
+
+        let variables = VariableStore::for_services(services).load().await?;
+        let title = render(&config.title, &value, &variables)?;
+
+        let description = match &config.description {
+            Some(template) => Some(render(template, &value, &variables)?),
+            None => None,
+        };
+
+        TodoistCreateTask::dispatch(
+            TodoistCreateTaskPayload {
+                title,
+                description,
+                due: TodoistDueDate::Today,
+                config: config.todoist.clone(),
+                ..Default::default()
+            },
+            None,
+            services,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::workflow_store::{WorkflowDraft, WorkflowStore};
+
+    use super::*;
+
+    async fn store(
+        services: &(impl Services + Send + Sync + 'static),
+        config: serde_json::Value,
+        enabled: bool,
+    ) -> automate_api::WorkflowId {
+        WorkflowStore::new(services)
+            .create(WorkflowDraft {
+                type_id: "event".into(),
+                config,
+                schedule: None,
+                enabled,
+            })
+            .await
+            .expect("store the workflow")
+            .id
+    }
+
+    fn config() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Resolved alerts",
+            "event": "run.succeeded",
+            "filter": "payload.input.status == \"resolved\"",
+            "title": "${{ payload.input.title }} resolved",
+        })
+    }
+
+    fn delivery(workflow: automate_api::WorkflowId, status: &str) -> EventDelivery {
+        EventDelivery {
+            workflow,
+            event: WorkflowEvent::new(
+                crate::events::RUN_SUCCEEDED,
+                automate_api::WorkflowId::from_entropy(99),
+                serde_json::json!({ "input": { "status": status, "title": "Disk full" } }),
+            ),
+        }
+    }
+
+    async fn run(
+        services: &(impl Services + Send + Sync + Clone + 'static),
+        delivery: &EventDelivery,
+    ) {
+        EventTodoistWorkflow
+            .handle(
+                JobContext::new(services.clone(), Utc::now(), None, None),
+                delivery,
+            )
+            .await
+            .expect("handle the event");
+    }
+
+    async fn filed(
+        services: &(impl Services + Send + Sync + 'static),
+    ) -> Vec<crate::db::PeekedMessage<serde_json::Value>> {
+        services
+            .queue()
+            .peek("todoist/create-task", 10)
+            .await
+            .expect("peek the todoist queue")
+    }
+
+    #[tokio::test]
+    async fn an_event_matching_the_filter_files_a_task_rendered_from_it() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(&services, config(), true).await;
+
+        run(&services, &delivery(workflow, "firing")).await;
+        run(&services, &delivery(workflow, "resolved")).await;
+
+        let filed = filed(&services).await;
+        assert_eq!(filed.len(), 1, "only the resolved alert should be filed");
+        assert_eq!(filed[0].payload["title"], "Disk full resolved");
+    }
+
+    #[tokio::test]
+    async fn an_event_for_a_paused_workflow_files_nothing() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(&services, config(), false).await;
+
+        run(&services, &delivery(workflow, "resolved")).await;
+
+        assert!(filed(&services).await.is_empty());
+    }
+
+    #[test]
+    fn a_subscription_without_an_event_to_follow_is_refused() {
+        let workflow = crate::workflows::lookup("event").unwrap();
+
+        let mut config = config();
+        config.as_object_mut().unwrap().remove("event");
+
+        assert!(
+            workflow.validate(&config).is_err(),
+            "a workflow that follows no event would never run",
+        );
+    }
+}
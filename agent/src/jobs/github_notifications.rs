@@ -135,7 +135,9 @@ impl GitHubNotificationsWorkflow {
         let items = collector.list(&services).await?;
 
         for item in items.into_iter() {
-            match job.filter.matches(&item) {
+            match crate::preview::matches(&job.filter, &item, || {
+                format!("{}: {}", item.repository.full_name, item.subject.title)
+            }) {
                 Ok(false) => continue,
                 Err(err) => {
                     return Err(err);
@@ -160,7 +160,12 @@ impl Job for GitHubNotificationsCleanupWorkflow {
         let (notifications, _) = collector.fetch_since(None, services).await?;
 
         for notification in notifications {
-            if !job.filter.matches(&notification)? {
+            if !crate::preview::matches(&job.filter, &notification, || {
+                format!(
+                    "{}: {}",
+                    notification.repository.full_name, notification.subject.title
+                )
+            })? {
                 continue;
             }
 
@@ -3,12 +3,14 @@ use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
-use crate::publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate};
+use crate::publishers::digest::{self, Digest, DigestSource};
+use crate::publishers::{TodoistCreateTaskPayload, TodoistDueDate};
 use crate::{
-    collectors::{GitHubReleasesCollector, IncrementalCollector},
+    collectors::{Backfill, GitHubReleasesCollector, IncrementalCollector},
     db::StateKey,
     filter::Filter,
     publishers::TodoistTarget,
+    script::Script,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -18,9 +20,22 @@ pub struct GitHubReleasesConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub connection: Option<automate_api::ConnectionId>,
 
+    /// Reshapes each release before it is filtered. Empty files releases as
+    /// GitHub describes them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: Filter,
 
+    /// What the first run files, and the first after a reset.
+    #[serde(default)]
+    pub backfill: Backfill,
+
+    /// Whether releases are gathered into a periodic digest instead.
+    #[serde(default)]
+    pub digest: Digest,
+
     #[serde(default)]
     pub todoist: TodoistTarget,
 }
@@ -41,10 +56,12 @@ Watches one repository's releases and files a Todoist task for each new one,
 carrying the release notes into the task's body so you can decide whether an
 upgrade is worth your afternoon without opening GitHub.
 
-The first run files every release on the repository's first page of results —
-up to thirty of them — rather than only what appears afterwards. Every run
-after that only sees releases published since the newest one it has seen, so
-the burst happens once.
+By default the first run files every release on the repository's first page
+of results — up to thirty of them — rather than only what appears afterwards.
+Every run after that only sees releases published since the newest one it has
+seen, so the burst happens once. If you would rather skip it, set **First run**
+to file nothing, only the latest release or two, or the releases since a date;
+a reset follows the same choice.
 
 ## Naming the repository
 
@@ -78,6 +95,31 @@ several components from one place:
 tag startswith "cli/"
 ```
 
+## Reshaping releases with a script
+
+A **Script** runs against each new release before the filter does, with the
+release as `item` in the shape GitHub's API gives it: `tag_name`, `name`,
+`body`, `draft`, `prerelease`, `published_at` and `html_url`. It can tidy a
+name, trim the notes down to the section you read, or drop releases by a rule
+too involved for a filter:
+
+```
+item.body = item.body.split("\n## Dependencies")[0];
+item
+```
+
+End with `item` to file the release, or with nothing to skip it. Scripts are
+written in [Rhai](https://rhai.rs/book/); the generic webhook's notes describe
+what they can and cannot do.
+
+## Digests
+
+Following a busy repository, or a dozen quiet ones, can still be more tasks
+than you want. **Send as a digest** holds each release back and files one task
+on the **Digest schedule** listing everything released since the last one,
+linked to each release's page. **Quiet hours** holds back only what is
+released overnight, and files it with the next digest.
+
 ## Scheduling
 
 This polls the GitHub API, which is rate limited, so a failed run backs off for
@@ -150,8 +192,15 @@ impl crate::workflows::ConfigurableWorkflow for GitHubReleasesWorkflow {
                     },
                 )
                 .with_help("Only file releases matching this, such as `prerelease == false`."),
+                crate::script_field!(
+                    GitHubReleasesConfig,
+                    "release",
+                    "item.name.replace(\"Release \", \"\");\nitem"
+                ),
             ]
             .into_iter()
+            .chain(crate::backfill_fields!(GitHubReleasesConfig))
+            .chain(crate::digest_fields!(GitHubReleasesConfig))
             .chain(crate::todoist_target_fields!(
                 GitHubReleasesConfig,
                 project = Some("Software"),
@@ -194,12 +243,28 @@ impl Job for GitHubReleasesWorkflow {
             services,
         )
         .await?;
-        let collector = GitHubReleasesCollector::with_api_key(&job.repository, api_key);
+        let collector = GitHubReleasesCollector::with_api_key(&job.repository, api_key)
+            .with_backfill(job.backfill);
 
         let items = collector.list(services).await?;
+        let items = job
+            .script
+            .reshape(items, |item| format!("the release '{}'", item.tag_name))
+            .await?;
+
+        let key = ctx
+            .key()
+            .map(str::to_string)
+            .unwrap_or_else(|| job.to_string());
+        let source = DigestSource {
+            key: &key,
+            name: &job.repository,
+        };
 
         for item in items.into_iter() {
-            match job.filter.matches(&item) {
+            match crate::preview::matches(&job.filter, &item, || {
+                format!("{} ({})", item.name, item.tag_name)
+            }) {
                 Ok(false) => continue,
                 Err(err) => {
                     return Err(err);
@@ -207,7 +272,10 @@ impl Job for GitHubReleasesWorkflow {
                 _ => {}
             }
 
-            TodoistCreateTask::dispatch(
+            digest::file(
+                services,
+                &job.digest,
+                source,
                 TodoistCreateTaskPayload {
                     title: format!(
                         "[github:{}]({}): Released {} ({})",
@@ -223,8 +291,7 @@ impl Job for GitHubReleasesWorkflow {
                     config: job.todoist.clone(),
                     ..Default::default()
                 },
-                None,
-                services,
+                Some(&item.html_url),
             )
             .await?;
         }
@@ -1,5 +1,7 @@
 mod calendar;
 mod cron;
+mod event_forward;
+mod event_todoist;
 mod github_attention;
 mod github_auto_merge;
 mod github_notifications;
@@ -3,11 +3,13 @@ use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    collectors::{IncrementalCollector, RssCollector},
+    collectors::{Backfill, IncrementalCollector, RssCollector},
     db::StateKey,
     prelude::*,
     publishers::TodoistTarget,
-    publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate},
+    publishers::digest::{self, Digest, DigestSource},
+    publishers::{TodoistCreateTaskPayload, TodoistDueDate},
+    script::Script,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -16,9 +18,22 @@ pub struct RssConfig {
     pub homepage: String,
     pub url: String,
 
+    /// Reshapes each entry before it is filtered. Empty files entries as the
+    /// feed publishes them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: Filter,
 
+    /// What the first run files, and the first after a reset.
+    #[serde(default)]
+    pub backfill: Backfill,
+
+    /// Whether entries are gathered into a periodic digest instead.
+    #[serde(default)]
+    pub digest: Digest,
+
     #[serde(default = "default_todoist_config")]
     pub todoist: TodoistTarget,
 }
@@ -50,16 +65,28 @@ from HTML to Markdown, so most of the time you can decide whether to read
 something without leaving your task list.
 
 Nothing is filed twice: the publication date of the newest entry seen is
-remembered between runs, and only entries newer than that are considered. The
-first run is the exception — it files everything currently in the feed, which
-for a feed that keeps fifty entries means fifty tasks. Save it, let it run
-once, and clear out the backlog if you did not want it.
+remembered between runs, and only entries newer than that are considered.
+
+The first run has nothing to compare against, so **First run** decides what it
+files: everything currently in the feed (which for a feed that keeps fifty
+entries means fifty tasks), nothing at all, only the newest few, or everything
+published since a date. Whichever you pick, the run still remembers the newest
+entry, so the ones it passed over are not offered again. Resetting the
+workflow makes the next run a first run, and the same choice applies.
 
 That watermark is kept against the feed's address rather than against the
 workflow, so two workflows watching the same feed will not each get a copy of
 every entry; whichever runs first takes them. If you want the same feed sorted
 into two places, use one workflow and a filter rather than two workflows.
 
+## Digests
+
+A feed that publishes a dozen times a day makes for a dozen tasks. Turning on
+**Send as a digest** holds each entry back instead, and files one task listing
+all of them (with their links) on the **Digest schedule**. **Quiet hours**
+works without the digest being on: entries that arrive during them wait for the
+next digest rather than being filed overnight.
+
 ## Getting the feed address
 
 The **Feed URL** is the address of the RSS or Atom document, not the address of
@@ -84,6 +111,25 @@ comparison. Leaving it empty files every entry.
 title contains "release" || link contains "/security/"
 ```
 
+## Reshaping entries with a script
+
+A **Script** runs against each new entry before the filter does, for the things
+a filter cannot do: tidying a title every entry of a feed prefixes the same
+way, dropping entries by a rule too involved to write as a filter, or filing
+one entry as several tasks. The entry is `item`, with its `title`,
+`description`, `link` and `published` date as the feed wrote them:
+
+```
+item.title.replace("[Sponsored] ", "");
+if item.title != "" { item }
+```
+
+End with `item` to file the entry, with a list of them to file each, or with
+nothing to skip it. A `title`, `description` or `link` the script changes is
+what the filter sees and what the task is filed with. Scripts are written in
+[Rhai](https://rhai.rs/book/); the generic webhook's notes describe what they
+can and cannot do.
+
 ## Polling, not pushing
 
 This is a scheduled workflow: it asks the feed for new entries rather than
@@ -164,8 +210,15 @@ impl crate::workflows::ConfigurableWorkflow for RssWorkflow {
                     },
                 )
                 .with_help("Only file entries matching this. Leave it empty to file every entry."),
+                crate::script_field!(
+                    RssConfig,
+                    "entry",
+                    "item.title.replace(\"[Sponsored] \", \"\");\nitem"
+                ),
             ]
             .into_iter()
+            .chain(crate::backfill_fields!(RssConfig))
+            .chain(crate::digest_fields!(RssConfig))
             .chain(crate::todoist_target_fields!(
                 RssConfig,
                 project = Some("Hobbies"),
@@ -202,12 +255,60 @@ impl Job for RssWorkflow {
                 "Ensure that the feed URL is correctly formatted, it should be a fully qualified URL (including the scheme, e.g., https://).",
             ])?;
 
-        let collector = RssCollector::new(&job.url);
+        let collector = RssCollector::new(&job.url).with_backfill(job.backfill);
 
         let items = collector.list(services).await?;
 
-        for item in items.into_iter() {
-            match job.filter.matches(&RssEntryFilter(&item)) {
+        let key = ctx
+            .key()
+            .map(str::to_string)
+            .unwrap_or_else(|| job.to_string());
+        let source = DigestSource {
+            key: &key,
+            name: &job.name,
+        };
+
+        // Handed to the script together, so that one clock covers the whole
+        // feed rather than each entry having its own.
+        let entries = if job.script.is_empty() {
+            items
+        } else {
+            let results = job
+                .script
+                .run(
+                    items
+                        .iter()
+                        .map(|entry| {
+                            let title = entry
+                                .title
+                                .as_ref()
+                                .map(|t| t.content.as_str())
+                                .unwrap_or("Untitled entry");
+                            (entry_value(entry), format!("the entry '{title}'"))
+                        })
+                        .collect(),
+                )
+                .await?;
+
+            items
+                .iter()
+                .zip(results)
+                .flat_map(|(entry, values)| {
+                    values
+                        .iter()
+                        .map(|value| scripted_entry(entry, value))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+
+        for item in entries {
+            match crate::preview::matches(&job.filter, &RssEntryFilter(&item), || {
+                item.title
+                    .as_ref()
+                    .map(|t| t.content.clone())
+                    .unwrap_or_else(|| "Untitled entry".into())
+            }) {
                 Ok(false) => continue,
                 Err(err) => {
                     return Err(err);
@@ -215,7 +316,10 @@ impl Job for RssWorkflow {
                 _ => {}
             }
 
-            TodoistCreateTask::dispatch(
+            digest::file(
+                services,
+                &job.digest,
+                source,
                 TodoistCreateTaskPayload {
                     title: format!(
                         "[{}]({}): {}",
@@ -243,8 +347,7 @@ impl Job for RssWorkflow {
                     config: job.todoist.clone(),
                     ..Default::default()
                 },
-                None,
-                services,
+                Some(&item.links[0].href),
             )
             .await?;
         }
@@ -253,6 +356,59 @@ impl Job for RssWorkflow {
     }
 }
 
+/// An entry as a script sees it: the names the filter matches on, as the feed
+/// wrote them rather than lower-cased, and when it was published.
+fn entry_value(entry: &feed_rs::model::Entry) -> serde_json::Value {
+    serde_json::json!({
+        "title": entry.title.as_ref().map(|t| t.content.clone()),
+        "description": entry.summary.as_ref().map(|s| s.content.clone()),
+        "link": entry.links.first().map(|l| l.href.clone()),
+        "published": entry.published.map(|published| published.to_rfc3339()),
+    })
+}
+
+/// The entry a script returned, with whatever it did not change kept from the
+/// one it was handed.
+///
+/// Built on a copy of the original rather than from the value alone, so that
+/// an entry stays fileable (with a link to key its digest line by, and the
+/// rest of what the feed said about it) however little the script returns.
+fn scripted_entry(
+    entry: &feed_rs::model::Entry,
+    value: &serde_json::Value,
+) -> feed_rs::model::Entry {
+    let mut entry = entry.clone();
+
+    if let Some(title) = value["title"].as_str() {
+        set_text(&mut entry.title, title);
+    }
+
+    if let Some(description) = value["description"].as_str() {
+        set_text(&mut entry.summary, description);
+    }
+
+    if let (Some(link), Some(first)) = (value["link"].as_str(), entry.links.first_mut()) {
+        first.href = link.to_string();
+    }
+
+    entry
+}
+
+fn set_text(text: &mut Option<feed_rs::model::Text>, content: &str) {
+    match text {
+        Some(text) => text.content = content.to_string(),
+        None => {
+            *text = Some(feed_rs::model::Text {
+                content_type: "text/plain"
+                    .parse()
+                    .expect("text/plain is a valid media type"),
+                src: None,
+                content: content.to_string(),
+            })
+        }
+    }
+}
+
 struct RssEntryFilter<'a>(&'a feed_rs::model::Entry);
 
 impl<'a> Filterable for RssEntryFilter<'a> {
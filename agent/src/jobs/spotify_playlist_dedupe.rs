@@ -237,7 +237,7 @@ impl Job for SpotifyPlaylistDedupeWorkflow {
                 continue;
             }
 
-            if !job.filter.matches(&playlist)? {
+            if !crate::preview::matches(&job.filter, &playlist, || playlist.name.clone())? {
                 continue;
             }
 
@@ -255,6 +255,16 @@ impl Job for SpotifyPlaylistDedupeWorkflow {
                 playlist.name
             );
 
+            if crate::preview::suppress(|| {
+                format!(
+                    "Remove {} duplicate songs from the playlist '{}'.",
+                    removals.iter().map(|r| r.positions.len()).sum::<usize>(),
+                    playlist.name
+                )
+            }) {
+                continue;
+            }
+
             for batch in removals.chunks(REMOVAL_BATCH) {
                 client
                     .remove_playlist_items(&playlist.id, &playlist.snapshot_id, batch)
@@ -7,7 +7,7 @@ use crate::{
     jobs::CronJobConfig,
     prelude::*,
     publishers::TodoistTarget,
-    publishers::{TodoistClient, TodoistUpsertTaskState},
+    publishers::{TODOIST_PROVIDER, TodoistClient, TodoistUpsertTaskState},
 };
 
 #[derive(Clone, Serialize, Deserialize, Default)]
@@ -79,7 +79,12 @@ impl Job for TodoistCleanupWorkflow {
             .into_iter()
             .filter(|(key, _)| key.starts_with(&cache_key_prefix))
         {
-            match client.0.get_task(&state.id).await {
+            match client
+                .0
+                .get_task(&state.id)
+                .call_counted(TODOIST_PROVIDER)
+                .await
+            {
                 Ok(task) if task.checked || task.is_deleted || task.completed_at.is_some() => {
                     info!(
                         "Removing stale Todoist task mapping '{key}' because task '{}' has been completed or deleted.",
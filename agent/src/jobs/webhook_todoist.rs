@@ -7,7 +7,8 @@
 //! doorbell, or an internal service nobody outside their company has heard of
 //! at Automate. Writing a Rust struct is not a thing a user can do.
 //!
-//! So this workflow models nothing. The delivery is parsed as JSON and handed
+//! So this workflow models nothing. The delivery is read as JSON — or into the
+//! same shape, for a form, XML or text; see [`crate::webhook_body`] — and handed
 //! straight to the user's own filter and templates, which address it by path —
 //! see [`crate::webhook_payload`] for both halves of that. The cost is that we
 //! can offer no field suggestions and no validation of what the sender actually
@@ -39,8 +40,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
     prelude::*,
     publishers::TodoistTarget,
-    publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate},
-    webhook_payload::{JsonFilter, render},
+    publishers::digest::{self, Digest, DigestSource},
+    publishers::{TodoistCreateTaskPayload, TodoistDueDate},
+    script::Script,
+    variables::VariableStore,
+    webhook_payload::{JsonFilter, PayloadFilter, Template, render},
+    webhook_signature::SignatureCheck,
 };
 
 /// What a person tells us about the deliveries they expect.
@@ -54,17 +59,43 @@ pub struct WebhookTodoistConfig {
     /// list of them.
     pub name: String,
 
-    /// The task's title, rendered against the delivery's JSON body.
-    pub title: String,
+    /// The task's title, rendered against the delivery's body.
+    pub title: Template,
 
     /// The task's body, rendered the same way. Optional because plenty of
     /// notifications are entirely said by their title.
     #[serde(default)]
-    pub description: Option<String>,
+    pub description: Option<Template>,
+
+    /// Reshapes the delivery's body before it is filtered, for what a filter
+    /// and a template cannot compute. Empty hands the body on as it is.
+    #[serde(default)]
+    pub script: Script,
 
     /// Which deliveries are worth a task. Empty means all of them.
     #[serde(default)]
-    pub filter: Filter,
+    pub filter: PayloadFilter,
+
+    /// How the sender signs its deliveries, for a sender that does. Off by
+    /// default, in which case knowing the URL is enough to file a task.
+    #[serde(default)]
+    pub signature: SignatureCheck,
+
+    /// The header the sender names each delivery with, for a sender that keeps
+    /// it the same when it retries. Empty when it names none, in which case
+    /// every retry is handled as a delivery of its own.
+    #[serde(default)]
+    pub delivery_header: String,
+
+    /// The event to emit for each delivery the filter keeps, for the workflows
+    /// following this one (see [`crate::events`]). Empty emits none beyond
+    /// those every run does.
+    #[serde(default)]
+    pub emit: String,
+
+    /// Whether deliveries are gathered into a periodic digest instead.
+    #[serde(default)]
+    pub digest: Digest,
 
     #[serde(default)]
     pub todoist: TodoistTarget,
@@ -100,15 +131,12 @@ pub struct WebhookTodoistWorkflow;
 /// The setup notes shown while somebody is configuring one of these.
 const DOCUMENTATION: &str = r#"## What this does
 
-Gives you an address to post JSON at, and files a Todoist task for each
-delivery that arrives. Unlike the other webhook types, this one knows nothing
-about the sender: the body is parsed as JSON and handed straight to your filter
-and your templates, which address it by path. That is what makes it the right
-choice for a service Automate has never heard of — your CI, your doorbell, an
-internal tool nobody outside your company has heard of.
-
-Deliveries whose body is not JSON are discarded with a line in the log, because
-there is nothing this workflow could do with them.
+Gives you an address to post to, and files a Todoist task for each delivery
+that arrives. Unlike the other webhook types, this one knows nothing about the
+sender: the body is read and handed straight to your filter and your
+templates, which address it by path. That is what makes it the right choice
+for a service Automate has never heard of — your CI, your doorbell, an internal
+tool nobody outside your company has heard of.
 
 ## Getting the address
 
@@ -116,14 +144,76 @@ Save the workflow first. Its address is generated when it is created, and is
 shown on the workflow afterwards — there is nothing to copy in beforehand, and
 the field you would paste it into does not exist until then.
 
-The address is the only thing standing between this endpoint and anybody who
-found it, so treat it as a credential: put it wherever the sender keeps its
-secrets rather than in a checked-in config file, and rotate it if it leaks.
+Unless you check signatures, the address is the only thing standing between
+this endpoint and anybody who found it, so treat it as a credential: put it
+wherever the sender keeps its secrets rather than in a checked-in config file,
+and rotate it if it leaks.
 Rotation gives the workflow a new address and immediately stops the old one
 working, so plan to update the sender at the same time.
 
-Configure the sender to `POST` to it with a JSON body. No particular content
-type, header or signature is required.
+Configure the sender to `POST` to it. No particular header is required, and a
+signature only once you ask for one below.
+
+## What the body can be
+
+The `Content-Type` the sender declares decides how the body is read, and every
+kind ends up addressable by path in the same way:
+
+- **JSON** (`application/json`) is used as it is.
+- **A form** (`application/x-www-form-urlencoded`) becomes its fields:
+  `status=down&host=db1` is `status` and `host`. A field sent more than once
+  is a list of its values.
+- **XML** (`application/xml`, `text/xml`) becomes nested fields named after
+  its elements, starting with the outermost: `<alert severity="high"><host>db1</host></alert>`
+  is `alert.severity` and `alert.host`. Namespace prefixes are dropped. An
+  element with attributes or children as well as text keeps the text under
+  `text`, and repeated elements are a list.
+- **Text** (`text/plain`, or no type at all) is `body`. Text that happens to be
+  a JSON object or list is read as JSON, since that is what `curl -d` sends.
+
+Whatever the body, the query string is `query` and the headers are `headers`,
+so `query.host` or `headers.x_event_type` work too. Header names are lowercase
+with `-` written as `_`, and headers carrying a credential — anything naming a
+token, secret, signature, cookie or authorization — are left out. A JSON body
+with its own `query` or `headers` field keeps it.
+
+A body that does not read as what it declares, such as malformed XML, is
+discarded with a line in the log, because no retry would make it readable.
+
+## Retries
+
+A sender that does not hear back quickly enough usually sends the same delivery
+again, and each attempt would file its own task. If yours names each delivery in
+a header and keeps it the same when it retries — `X-Request-ID`,
+`X-Delivery-ID` and the like — put that header's name in **Delivery ID header**.
+A delivery whose id already arrived recently (within a day, unless your
+installation says otherwise) is then answered as accepted and dropped, and
+shows under **Deliveries** as a repeat. Leave it empty for a sender
+that names nothing, or that names each attempt afresh.
+
+## Checking signatures
+
+If your sender can sign what it posts, turn on **Check signatures**. A
+signature proves a delivery came from something holding the secret and was not
+rewritten on the way, and unlike the address it travels in a header, which
+access logs do not record. Once it is on, a delivery without a valid signature
+is refused with a line in the log, and so is every delivery while **Signing
+secret** is empty.
+
+Describe how the sender signs from its own documentation:
+
+| Sender                | Hash   | Header                | Prefix    | Signed content                  | Timestamp header            |
+| --------------------- | ------ | --------------------- | --------- | ------------------------------- | --------------------------- |
+| GitHub-style          | SHA256 | `X-Hub-Signature-256` | `sha256=` | The body alone                  |                             |
+| Stripe-style          | SHA256 | `Stripe-Signature`    | `v1=`     | The timestamp, a dot, the body  | (read from `t=`)            |
+| Slack-style           | SHA256 | `X-Slack-Signature`   | `v0=`     | `v0:`, the timestamp, the body  | `X-Slack-Request-Timestamp` |
+
+Digests can be hex or base64. When the timestamp is signed, a delivery more
+than **Signature window** minutes either side of when it arrived is refused, so
+one somebody captured cannot be sent again later; timestamps are read as whole
+seconds since 1970. Signatures are computed over the body exactly as it was
+posted, so a sender that re-serialises its JSON after signing will never
+match.
 
 ## Writing the title and description
 
@@ -134,11 +224,70 @@ delivery's body, addressed by dotted path:
 [${{ repository.name }}] deployed to ${{ deployment.environment }}
 ```
 
+A path can step into a list, too: `[0]` is its first item and `[-1]` its last,
+`[*]` is every item, and `[?field == "value"]` is the items that match, with
+`!=`, `contains`, `startswith` and `endswith` also allowed. `@` stands for the
+item itself in a list of plain values.
+
+```
+${{ commits[-1].message }}
+${{ commits[*].author.username | join(", ") }}
+${{ pull_request.labels[?name startswith "area/"].name | join(", ") }}
+```
+
 A path that is not present renders as nothing rather than failing the delivery,
 since a sender is free to omit fields and a missing one should not cost you the
 notification. Rendered output is length-capped, so a template aimed at a large
 field cannot produce an unbounded task.
 
+A value can be passed through functions, each after a `|`:
+
+```
+${{ pull_request.title | truncate(60) }} by ${{ sender.login | default("someone") }}
+${{ labels | join(", ") }} — ${{ pull_request.draft | if("draft", "ready for review") }}
+${{ created_at | date("%a %d %b") }}
+```
+
+The functions are `default("fallback")`, `upper`, `lower`, `trim`,
+`truncate(length)`, `date("format")`, `join("separator")`, `markdown_escape`,
+`length`, `eq(value)`, `ne(value)`, `contains(value)` and
+`if("when true", "when false")`. Their arguments are written out rather than
+taken from the payload. A template that does not parse — a misspelt function,
+an unclosed quote — is refused when you save the workflow, rather than failing
+each delivery later.
+
+Values your account keeps are read the same way. `${{ vars.team_label }}` is a
+variable and `${{ secrets.deploy_token }}` a secret, both set under
+`/api/v1/variables`, so a value several workflows share is changed in one
+place. A secret's value is scrubbed from the run history and the preview, but
+anything you put it in is still sent to Todoist as written.
+
+## Reshaping a delivery with a script
+
+When a template cannot say it — a total to add up, a project to pick from a
+table, one delivery listing several alerts that should each be a task — write a
+**Script**. It runs before the filter, with the delivery's body (read as
+described above) as `item`, and whatever it ends with is filtered and filed in
+the body's place:
+
+```
+let projects = #{ billing: "Finance", infra: "Operations" };
+item.project = projects[item.team] ?? "Inbox";
+item.total = item.lines.reduce(|sum, line| sum + line.amount, 0);
+item
+```
+
+End with `item`, or another map, to file one task; with a list of them, such as
+`item.alerts.filter(|alert| alert.firing)`, to file one per element; and with
+nothing, as an `if` without an `else` does, to file none. The filter and the
+templates then address what the script returned rather than the body.
+
+Scripts are written in [Rhai](https://rhai.rs/book/). They cannot read files,
+reach the network or see your variables, and are stopped if they take more
+than a fraction of a second, build a very large value, or return more than a
+hundred items, failing the delivery with a message saying which. A script that
+does not parse is refused when you save the workflow.
+
 ## Choosing which deliveries to file
 
 The filter uses the same dotted paths. There are no suggestions to offer here,
@@ -156,11 +305,37 @@ membership works:
 "bug" in issue.labels
 ```
 
+So does a path through `[*]` or `[?...]`, which is how to filter on a list of
+objects — name the field of them you care about. Lists met along the way are
+joined into one, so every file any commit in a push touched is a single list:
+
+```
+"deploy/app.yaml" in commits[*].modified
+pull_request.labels[*].name contains "needs-review"
+commits[?author.username == "dependabot"].id != []
+```
+
 Objects do not: a path that stops at an object is treated as absent, so filter
-on `issue.title` rather than on `issue`. An array of objects is likewise a list
-of nothings.
+on `issue.title` rather than on `issue`. A malformed path is refused when you
+save the workflow.
 
 Leave the filter empty to file every delivery.
+
+## Emitting an event
+
+Other workflows can follow this one. Each run emits `run.succeeded` or
+`run.failed` whatever you set here; put a name in **Emit event**, such as
+`deploy.finished`, to also emit an event of your own for each delivery the
+filter keeps. Its `payload` is the delivery's body, read as described above,
+and a **Workflow Event** workflow following that name is handed it.
+
+## Digests
+
+A sender that posts often — every build, every doorbell press — is better read
+as a summary. **Send as a digest** holds each delivery's task back and files
+one task on the **Digest schedule** listing them all; **Digest line** decides
+how each appears in it, and can use the task's `title` and `description`.
+**Quiet hours** holds back only what arrives during them.
 "#;
 
 crate::register_job!(WebhookTodoistWorkflow);
@@ -177,6 +352,11 @@ impl crate::workflows::ConfigurableWorkflow for WebhookTodoistWorkflow {
         config.name.clone()
     }
 
+    fn delivery_header(config: &Self::ConfigType) -> Option<String> {
+        let header = config.delivery_header.trim();
+        (!header.is_empty()).then(|| header.to_string())
+    }
+
     fn descriptor() -> automate_api::WorkflowTypeDescriptor {
         use automate_api::{FieldDescriptor, FieldKind, WorkflowTrigger, WorkflowTypeDescriptor};
 
@@ -220,6 +400,16 @@ impl crate::workflows::ConfigurableWorkflow for WebhookTodoistWorkflow {
                     },
                 )
                 .with_help("Optional. Written the same way as the title."),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: script),
+                    "Script",
+                    FieldKind::Script {
+                        placeholder: Some("item.total = item.amount * item.quantity;\nitem".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. Reshapes the delivery's body before it is filtered. End with item to file it, a list to file each element, or nothing to file none.",
+                ),
                 FieldDescriptor::new(
                     crate::config_path!(WebhookTodoistConfig: filter),
                     "Filter",
@@ -233,8 +423,140 @@ impl crate::workflows::ConfigurableWorkflow for WebhookTodoistWorkflow {
                 .with_help(
                     "Only file deliveries matching this. Match on the payload's own field names, addressed by path — such as action == \"opened\", or \"bug\" in issue.labels. They differ from one sender to the next, so check what yours actually posts. Leave it empty to file every delivery.",
                 ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: delivery_header),
+                    "Delivery ID header",
+                    FieldKind::Text {
+                        placeholder: Some("X-Request-ID".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. The header your sender names each delivery with and repeats when it retries. A delivery repeating a recent one is dropped rather than filed again.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: emit),
+                    "Emit event",
+                    FieldKind::Text {
+                        placeholder: Some("deploy.finished".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. A name to emit an event under for each delivery the filter keeps, carrying its body, so that a Workflow Event workflow can follow it.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.enabled),
+                    "Check signatures",
+                    FieldKind::Boolean,
+                )
+                .with_help(
+                    "Refuse deliveries that were not signed with the secret below. Turn this on whenever your sender can sign what it posts: without it, anybody who learns this workflow's URL can file tasks.",
+                )
+                .with_default(false),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.secret),
+                    "Signing secret",
+                    FieldKind::Secret {
+                        placeholder: None,
+                        // Generating one is right for an internal service that
+                        // is told its secret; a provider that issues its own
+                        // simply has it pasted over.
+                        generator: true,
+                        generator_bytes: 32,
+                    },
+                )
+                .with_help(
+                    "The secret your sender signs its deliveries with. It must be the same value on both sides, and every delivery is refused while signatures are checked and this is empty.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.algorithm),
+                    "Signature hash",
+                    FieldKind::Select {
+                        options: vec![
+                            automate_api::OptionItem::new("sha256", "HMAC-SHA256"),
+                            automate_api::OptionItem::new("sha512", "HMAC-SHA512"),
+                            automate_api::OptionItem::new("sha1", "HMAC-SHA1"),
+                        ],
+                    },
+                )
+                .with_help("The hash your sender's documentation says it signs with.")
+                .with_default("sha256"),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.header),
+                    "Signature header",
+                    FieldKind::Text {
+                        placeholder: Some("X-Hub-Signature-256".into()),
+                    },
+                )
+                .with_help("The header the signature arrives in. Its case does not matter.")
+                .with_default("X-Signature"),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.prefix),
+                    "Signature prefix",
+                    FieldKind::Text {
+                        placeholder: Some("sha256=".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. Whatever the sender writes in front of the digest, such as sha256= for GitHub, v1= for Stripe or v0= for Slack.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.encoding),
+                    "Signature encoding",
+                    FieldKind::Select {
+                        options: vec![
+                            automate_api::OptionItem::new("hex", "Hex"),
+                            automate_api::OptionItem::new("base64", "Base64"),
+                        ],
+                    },
+                )
+                .with_default("hex"),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.signed),
+                    "Signed content",
+                    FieldKind::Select {
+                        options: vec![
+                            automate_api::OptionItem::new("body", "The body alone"),
+                            automate_api::OptionItem::new(
+                                "timestamp.body",
+                                "The timestamp, a dot, then the body (Stripe)",
+                            ),
+                            automate_api::OptionItem::new(
+                                "v0:timestamp:body",
+                                "v0:, the timestamp, a colon, then the body (Slack)",
+                            ),
+                        ],
+                    },
+                )
+                .with_help(
+                    "What the signature is computed over. A sender that signs a timestamp along with the body lets a delivery somebody captured be told apart from a fresh one.",
+                )
+                .with_default("body"),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.timestamp_header),
+                    "Timestamp header",
+                    FieldKind::Text {
+                        placeholder: Some("X-Slack-Request-Timestamp".into()),
+                    },
+                )
+                .with_help(
+                    "Optional. The header a signed timestamp arrives in, for senders that keep it apart from the signature. Leave it empty to read a t= timestamp from the signature header itself.",
+                ),
+                FieldDescriptor::new(
+                    crate::config_path!(WebhookTodoistConfig: signature.tolerance),
+                    "Signature window (minutes)",
+                    FieldKind::Number {
+                        min: Some(1.0),
+                        max: None,
+                        step: Some(1.0),
+                    },
+                )
+                .with_help(
+                    "How far a signed timestamp may be from when the delivery arrived before it is refused as a replay.",
+                )
+                .with_default(5),
             ]
             .into_iter()
+            .chain(crate::digest_fields!(WebhookTodoistConfig))
             .chain(crate::todoist_target_fields!(
                 WebhookTodoistConfig,
                 project = Some("Inbox"),
@@ -264,70 +586,117 @@ impl Job for WebhookTodoistWorkflow {
 
         // Read now rather than carried in the payload, so that an edit made
         // between the delivery arriving and this running is the one that applies.
-        let Some(record) = crate::workflow_store::WorkflowStore::new(&services)
-            .find(id)
-            .await?
-        else {
-            info!(
-                workflow.id = %id,
-                "The workflow this delivery was for has been deleted, so it is discarded.",
-            );
-            return Ok(());
+        // Going through the same path as every other webhook workflow is what
+        // has a paused or snoozed one treat this delivery as they would.
+        let delivery = crate::webhooks::WebhookDelivery {
+            workflow: id,
+            event: event.clone(),
+            replay_of: None,
+            arrived_at: Some(ctx.scheduled_at()),
         };
 
-        if !record.enabled {
-            info!(
-                workflow.id = %id,
-                "The workflow this delivery was for is paused, so it is discarded.",
-            );
-            return Ok(());
-        }
-
-        let config: WebhookTodoistConfig = serde_json::from_value(record.config.clone())
-            .wrap_user_err(
-                format!("The webhook workflow '{id}' is not configured correctly."),
-                &["Open this workflow and check that every field it asks for is filled in."],
-            )?;
-
-        // A sender posting form encoding or XML has been pointed at the wrong
-        // kind of endpoint. That is worth saying out loud, but it is a
-        // misconfiguration somebody has to go and fix rather than something a
-        // retry could ever resolve, so it does not fail the delivery.
-        let Ok(payload) = serde_json::from_str::<serde_json::Value>(&event.body) else {
-            warn!(
-                workflow.id = %id,
-                "Ignoring a webhook delivery whose body is not JSON; this workflow can only read JSON payloads.",
-            );
+        let Some(config) = delivery.config::<WebhookTodoistConfig>(&services).await? else {
             return Ok(());
         };
 
-        if !config.filter.matches(&JsonFilter(&payload))? {
-            debug!(
-                workflow.id = %id,
-                "A webhook delivery did not match this workflow's filter, so no task was filed.",
-            );
-            return Ok(());
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() && config.signature.enabled {
+            if let Err(err) = config.signature.verify(
+                event,
+                (!crate::preview::is_redelivery()).then(|| ctx.scheduled_at()),
+            ) {
+                warn!(
+                    workflow.id = %id,
+                    "Refusing a webhook delivery whose signature could not be verified: {}",
+                    err
+                );
+                crate::preview::rejected(err.description());
+                return Ok(());
+            }
+
+            crate::preview::verified();
         }
 
-        let title = render(&config.title, &payload)?;
-
-        let description = match &config.description {
-            Some(template) => Some(render(template, &payload)?),
-            None => None,
+        // A body that does not read as what it claims to be — a form that is
+        // not a form, JSON that does not parse — is a sender misconfigured in a
+        // way somebody has to go and fix. That is worth saying out loud, but no
+        // retry could ever resolve it, so it does not fail the delivery.
+        let payload = match crate::webhook_body::read(event) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!(
+                    workflow.id = %id,
+                    "Ignoring a webhook delivery whose body could not be read: {}",
+                    err
+                );
+                crate::preview::discarded(err.description());
+                return Ok(());
+            }
         };
 
-        TodoistCreateTask::dispatch(
-            TodoistCreateTaskPayload {
-                title,
-                description,
-                due: TodoistDueDate::Today,
-                config: config.todoist.clone(),
-                ..Default::default()
-            },
-            None,
-            services,
-        )
-        .await?;
+        let items: Vec<_> = config
+            .script
+            .run(vec![(payload, "the delivery's body".to_string())])
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let scripted = !config.script.is_empty();
+
+        let variables = VariableStore::for_services(services).load().await?;
+
+        for (index, item) in items.into_iter().enumerate() {
+            // Each item a script returns is one the owner may want to tell apart
+            // from its siblings in a preview, where a body is just the body.
+            let describe = || {
+                if scripted {
+                    format!("Item {} from the script", index + 1)
+                } else {
+                    "The delivery's body".to_string()
+                }
+            };
+
+            if !crate::preview::matches(&config.filter, &JsonFilter(&item), describe)? {
+                debug!(
+                    workflow.id = %id,
+                    "A webhook delivery did not match this workflow's filter, so no task was filed.",
+                );
+                continue;
+            }
+
+            let title = render(&config.title, &item, &variables)?;
+
+            let description = match &config.description {
+                Some(template) => Some(render(template, &item, &variables)?),
+                None => None,
+            };
+
+            digest::file(
+                services,
+                &config.digest,
+                DigestSource {
+                    key: &id.to_string(),
+                    name: &config.name,
+                },
+                TodoistCreateTaskPayload {
+                    title,
+                    description,
+                    due: TodoistDueDate::Today,
+                    config: config.todoist.clone(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
+
+            let emit = config.emit.trim();
+            if !emit.is_empty() {
+                crate::events::emit(services, crate::events::WorkflowEvent::new(emit, id, item))
+                    .await?;
+            }
+        }
 
         Ok(())
     }
@@ -435,6 +804,36 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn a_workflow_sending_digests_holds_its_deliveries_for_the_next_one() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let mut config = config();
+        config["digest"] = serde_json::json!({ "enabled": true });
+        let workflow = store(&services, config).await;
+
+        run(&services, &delivery(workflow, body())).await.unwrap();
+        run(&services, &delivery(workflow, body())).await.unwrap();
+
+        assert!(
+            filed(&services).await.is_empty(),
+            "a delivery held for the digest should not also be filed on its own",
+        );
+
+        let pending: Vec<crate::db::PeekedMessage<serde_json::Value>> = services
+            .queue()
+            .peek("todoist/publish-digest", 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            pending.len(),
+            1,
+            "the workflow's deliveries should share one digest",
+        );
+        assert_eq!(pending[0].payload["key"], workflow.to_string());
+    }
+
     #[tokio::test]
     async fn a_delivery_the_filter_rejects_files_nothing() {
         // The filter is the only thing standing between a chatty sender and a
@@ -455,23 +854,191 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn a_body_that_is_not_json_is_discarded_rather_than_failing_the_delivery() {
-        // A sender posting form data has been pointed at the wrong endpoint.
-        // Failing here would put the message back on the queue to be retried
-        // forever, and no number of retries makes form data into JSON.
+    async fn a_script_can_turn_one_delivery_into_a_task_for_each_item_it_lists() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(
+            &services,
+            serde_json::json!({
+                "name": "Alerts",
+                "script": "let tasks = []; for alert in item.alerts { tasks.push(#{ name: alert.name, state: alert.state, host: item.host }); } tasks",
+                "filter": "state == \"firing\"",
+                "title": "${{ name }} on ${{ host }}",
+            }),
+        )
+        .await;
+
+        let body = serde_json::json!({
+            "host": "db1",
+            "alerts": [
+                { "name": "Disk full", "state": "firing" },
+                { "name": "CPU hot", "state": "resolved" },
+                { "name": "Swap in use", "state": "firing" },
+            ],
+        });
+        run(&services, &delivery(workflow, body.to_string()))
+            .await
+            .unwrap();
+
+        let mut titles: Vec<String> = filed(&services)
+            .await
+            .into_iter()
+            .map(|task| task.payload["title"].as_str().unwrap().to_string())
+            .collect();
+        titles.sort();
+        assert_eq!(
+            titles,
+            vec!["Disk full on db1", "Swap in use on db1"],
+            "each firing alert the script returned should be filtered and filed on its own",
+        );
+    }
+
+    #[test]
+    fn a_script_that_does_not_parse_is_refused_when_the_workflow_is_saved() {
+        let workflow = crate::workflows::lookup("webhook").unwrap();
+
+        let mut config = config();
+        config["script"] = serde_json::json!("item.total = ");
+
+        assert!(
+            workflow.validate(&config).is_err(),
+            "a script that cannot run should be reported on save rather than by every delivery",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_delivery_the_filter_keeps_is_handed_to_the_workflows_following_its_event() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let mut config = config();
+        config["emit"] = "deploy.finished".into();
+        let workflow = store(&services, config).await;
+
+        let follower = WorkflowStore::new(&services)
+            .create(WorkflowDraft {
+                type_id: "event".into(),
+                config: serde_json::json!({
+                    "name": "Check the dashboards",
+                    "event": "deploy.finished",
+                    "title": "Check ${{ payload.deployment.environment }}",
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap()
+            .id;
+
+        run(&services, &delivery(workflow, body())).await.unwrap();
+
+        let handed: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("events/todoist", 10).await.unwrap();
+        assert_eq!(handed.len(), 1);
+        assert_eq!(handed[0].payload["workflow"], follower.to_string());
+        assert_eq!(
+            handed[0].payload["event"]["payload"]["deployment"]["environment"], "production",
+            "the event should carry the delivery's body for the follower to read",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_workflow_checking_signatures_files_only_the_deliveries_signed_with_its_secret() {
+        use hmac::{Hmac, KeyInit, Mac};
+
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let mut config = config();
+        config["signature"] = serde_json::json!({
+            "enabled": true,
+            "secret": "shared-with-the-sender",
+            "header": "X-Hub-Signature-256",
+            "prefix": "sha256=",
+        });
+        let workflow = store(&services, config).await;
+
+        // Knowing the URL is no longer enough.
+        run(&services, &delivery(workflow, body()))
+            .await
+            .expect("a refused delivery should not fail the job");
+        assert!(
+            filed(&services).await.is_empty(),
+            "an unsigned delivery should have been refused",
+        );
+
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"shared-with-the-sender").unwrap();
+        mac.update(body().as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut signed = delivery(workflow, body());
+        signed
+            .event
+            .headers
+            .insert("x-hub-signature-256".into(), signature);
+        run(&services, &signed).await.unwrap();
+
+        assert_eq!(
+            filed(&services).await.len(),
+            1,
+            "a delivery signed with the workflow's secret should be filed",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_body_that_is_not_what_it_claims_to_be_is_discarded_rather_than_failing_the_delivery()
+    {
+        // A sender posting something other than it declares has been set up
+        // wrongly. Failing here would put the message back on the queue to be
+        // retried forever, and no number of retries makes it readable.
         let services = crate::services::ServicesContainer::new_mock()
             .await
             .unwrap();
         let workflow = store(&services, config()).await;
 
-        run(
+        let mut task = delivery(workflow, "action=deployed&environment=production");
+        task.event
+            .headers
+            .insert("Content-Type".into(), "application/json".into());
+
+        run(&services, &task)
+            .await
+            .expect("a body we cannot read should not fail the job");
+
+        assert!(filed(&services).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_form_posted_delivery_is_filtered_and_rendered_like_a_json_one() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(
             &services,
-            &delivery(workflow, "action=deployed&environment=production"),
+            serde_json::json!({
+                "name": "Doorbell",
+                "title": "${{ event }} at ${{ query.door }}",
+                "filter": "event == \"ring\"",
+            }),
         )
-        .await
-        .expect("a body we cannot read should not fail the job");
+        .await;
 
-        assert!(filed(&services).await.is_empty());
+        let mut task = delivery(workflow, "event=ring&battery=80");
+        task.event.query = "door=front".into();
+        task.event.headers.insert(
+            "Content-Type".into(),
+            "application/x-www-form-urlencoded".into(),
+        );
+
+        run(&services, &task).await.unwrap();
+
+        let filed = filed(&services).await;
+        assert_eq!(filed.len(), 1);
+        assert_eq!(
+            filed[0].payload["title"], "ring at front",
+            "form fields and the query string should both be addressable by path",
+        );
     }
 
     #[tokio::test]
@@ -5,18 +5,28 @@ use serde::{Deserialize, Serialize};
 use crate::prelude::*;
 use crate::publishers::{TodoistCreateTask, TodoistCreateTaskPayload};
 use crate::{
-    collectors::{Collector, XkcdCollector},
+    collectors::{Backfill, Collector, XkcdCollector},
     db::StateKey,
     filter::Filter,
     publishers::TodoistTarget,
+    script::Script,
     services::Services,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct XkcdConfig {
+    /// Reshapes each comic before it is filtered. Empty files comics as the
+    /// feed publishes them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: Filter,
 
+    /// What the first run files, and the first after a reset.
+    #[serde(default)]
+    pub backfill: Backfill,
+
     #[serde(default)]
     pub todoist: TodoistTarget,
 }
@@ -43,8 +53,10 @@ tasks go and how often to look. There is also no reason to have two of these:
 they would read the same feed and share the same record of what has been seen,
 so the second one would find nothing to file.
 
-The first run files every comic currently in the feed — four of them, as xkcd
-publishes it — rather than only what arrives afterwards.
+By default the first run files every comic currently in the feed — four of
+them, as xkcd publishes it — rather than only what arrives afterwards. **First
+run** can narrow that to nothing, the newest one or two, or the comics since a
+date, and a reset follows the same choice.
 
 ## Scheduling
 
@@ -62,6 +74,22 @@ image to embed:
 ```
 has_image == true
 ```
+
+## Reshaping comics with a script
+
+A **Script** runs against each new comic before the filter does, with the comic
+as `item` and its `title`, `url`, `published` date, `image_url` and
+`image_alt` text as the feed gives them. Putting the alt text in the title, so
+that it shows in the task list, is a line:
+
+```
+item.title = `${item.title}: ${item.image_alt}`;
+item
+```
+
+End with `item` to file the comic, or with nothing to skip it. Scripts are
+written in [Rhai](https://rhai.rs/book/); the generic webhook's notes describe
+what they can and cannot do.
 "#;
 
 crate::register_job!(XkcdWorkflow);
@@ -89,15 +117,23 @@ impl crate::workflows::ConfigurableWorkflow for XkcdWorkflow {
             trigger: WorkflowTrigger::Cron {
                 default_schedule: "@daily".to_string(),
             },
-            fields: [FieldDescriptor::new(
-                crate::config_path!(XkcdConfig: filter),
-                "Filter",
-                FieldKind::Filter {
-                    fields: vec!["title".into(), "url".into(), "has_image".into()],
-                },
-            )
-            .with_help("Only file comics matching this. Leave it empty to file every comic.")]
+            fields: [
+                FieldDescriptor::new(
+                    crate::config_path!(XkcdConfig: filter),
+                    "Filter",
+                    FieldKind::Filter {
+                        fields: vec!["title".into(), "url".into(), "has_image".into()],
+                    },
+                )
+                .with_help("Only file comics matching this. Leave it empty to file every comic."),
+                crate::script_field!(
+                    XkcdConfig,
+                    "comic",
+                    "item.title = `${item.title}: ${item.image_alt}`;\nitem"
+                ),
+            ]
             .into_iter()
+            .chain(crate::backfill_fields!(XkcdConfig))
             .chain(crate::todoist_target_fields!(
                 XkcdConfig,
                 project = Some("Hobbies"),
@@ -128,12 +164,16 @@ impl Job for XkcdWorkflow {
         job: &Self::JobType,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
-        let collector = XkcdCollector::new();
+        let collector = XkcdCollector::new().with_backfill(job.backfill);
 
         let items = collector.list(services).await?;
+        let items = job
+            .script
+            .reshape(items, |item| format!("the comic '{}'", item.title))
+            .await?;
 
         for item in items.into_iter() {
-            match job.filter.matches(&item) {
+            match crate::preview::matches(&job.filter, &item, || item.title.clone()) {
                 Ok(false) => continue,
                 Err(err) => {
                     return Err(err);
@@ -350,13 +350,17 @@ impl Job for YnabStocksWorkflow {
             Some(sk) => request.with_server_knowledge(sk),
             None => request,
         };
-        let (changed, new_knowledge) = request.send().await.wrap_system_err(
-            format!("Failed to fetch the accounts for YNAB budget '{budget}'."),
-            &[
-                "Check that the budget ID is correct.",
-                "Check that your YNAB API key has access to this budget.",
-            ],
-        )?;
+        let (changed, new_knowledge) = request
+            .send()
+            .call_counted(YNAB_PROVIDER)
+            .await
+            .wrap_system_err(
+                format!("Failed to fetch the accounts for YNAB budget '{budget}'."),
+                &[
+                    "Check that the budget ID is correct.",
+                    "Check that your YNAB API key has access to this budget.",
+                ],
+            )?;
 
         for account in changed {
             if account.deleted {
@@ -369,10 +373,14 @@ impl Job for YnabStocksWorkflow {
         kv.set(STATE_PARTITION, budget.to_string(), state.clone())
             .await?;
 
-        let settings = client.get_plan_settings(plan).await.wrap_system_err(
-            format!("Failed to fetch the settings for YNAB budget '{budget}'."),
-            &["Check that the budget ID is correct."],
-        )?;
+        let settings = client
+            .get_plan_settings(plan)
+            .call_counted(YNAB_PROVIDER)
+            .await
+            .wrap_system_err(
+                format!("Failed to fetch the settings for YNAB budget '{budget}'."),
+                &["Check that the budget ID is correct."],
+            )?;
         let budget_currency = budget_currency_iso_code(&settings).ok_or_else(|| {
             human_errors::system(
                 format!("The YNAB budget '{budget}' does not have a currency configured."),
@@ -452,6 +460,15 @@ impl Job for YnabStocksWorkflow {
                 .clone()
                 .unwrap_or_else(|| "Stock Market".to_string());
 
+            if crate::preview::suppress(|| {
+                format!(
+                    "Record a '{payee_name}' transaction of {shift} milliunits against '{}'.",
+                    account.name
+                )
+            }) {
+                continue;
+            }
+
             client
                 .create_transaction(
                     plan,
@@ -470,6 +487,7 @@ impl Job for YnabStocksWorkflow {
                         subtransactions: None,
                     },
                 )
+                .call_counted(YNAB_PROVIDER)
                 .await
                 .wrap_system_err(
                     format!(
@@ -3,11 +3,12 @@ use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    collectors::YouTubeCollector,
+    collectors::{Backfill, YouTubeCollector},
     db::StateKey,
     prelude::*,
     publishers::TodoistTarget,
     publishers::{TodoistCreateTask, TodoistCreateTaskPayload},
+    script::Script,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -15,9 +16,18 @@ pub struct YouTubeConfig {
     pub name: String,
     pub channel_id: String,
 
+    /// Reshapes each video before it is filtered. Empty files videos as the
+    /// channel's feed lists them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     filter: Filter,
 
+    /// What the first run files, and the first after a reset.
+    #[serde(default)]
+    pub backfill: Backfill,
+
     #[serde(default)]
     pub todoist: TodoistTarget,
 }
@@ -44,9 +54,11 @@ that posts more often than this workflow runs will lose the ones in between.
 A daily schedule is fine for most channels; a channel that uploads several
 times a day wants `@hourly`.
 
-The first run files everything the feed currently carries rather than only what
-arrives afterwards, so expect a small burst of tasks immediately after saving.
-Every run after that only sees what is new.
+Unless told otherwise, the first run files everything the feed currently
+carries rather than only what arrives afterwards, so expect a small burst of
+tasks immediately after saving. **First run** can limit that to nothing, the
+newest few uploads, or the uploads since a date; the same choice applies to the
+first run after a reset. Every run after that only sees what is new.
 
 ## Finding the channel id
 
@@ -76,6 +88,22 @@ a channel you subscribed to deliberately.
 ```
 !(title contains "#shorts")
 ```
+
+## Reshaping videos with a script
+
+A **Script** runs against each new video before the filter does, with the
+video as `item` and its `channel`, `title`, `link` and `published` date as the
+feed gives them. It suits tidying titles a channel decorates the same way every
+time, or skipping videos by a rule too involved for a filter:
+
+```
+item.title = item.title.split(" | ")[0];
+item
+```
+
+End with `item` to file the video, or with nothing to skip it. Scripts are
+written in [Rhai](https://rhai.rs/book/); the generic webhook's notes describe
+what they can and cannot do.
 "##;
 
 crate::register_job!(YouTubeWorkflow);
@@ -134,8 +162,14 @@ impl crate::workflows::ConfigurableWorkflow for YouTubeWorkflow {
                     },
                 )
                 .with_help("Only file videos matching this. Leave it empty to file every video."),
+                crate::script_field!(
+                    YouTubeConfig,
+                    "video",
+                    "item.title = item.title.split(\" | \")[0];\nitem"
+                ),
             ]
             .into_iter()
+            .chain(crate::backfill_fields!(YouTubeConfig))
             .chain(crate::todoist_target_fields!(
                 YouTubeConfig,
                 project = Some("Hobbies"),
@@ -166,12 +200,16 @@ impl Job for YouTubeWorkflow {
         job: &Self::JobType,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
-        let collector = YouTubeCollector::new(&job.channel_id);
+        let collector = YouTubeCollector::new(&job.channel_id).with_backfill(job.backfill);
 
         let items = collector.list(services).await?;
+        let items = job
+            .script
+            .reshape(items, |item| format!("the video '{}'", item.title))
+            .await?;
 
         for item in items.into_iter() {
-            match job.filter.matches(&item) {
+            match crate::preview::matches(&job.filter, &item, || item.title.clone()) {
                 Ok(false) => continue,
                 Err(err) => {
                     return Err(err);
@@ -1,23 +1,37 @@
+mod cli;
 mod collectors;
 mod config;
 mod connection_refresh;
 mod connections;
 mod crypto;
 mod db;
+mod deliveries;
+mod events;
 mod filter;
+mod health;
 mod integrations;
 mod job;
 mod jobs;
+mod metrics;
+mod notifications;
 mod parsers;
 mod prelude;
+mod preview;
 mod publishers;
+mod revisions;
+mod run_log;
 mod runs;
+mod script;
 mod serde_duration;
 mod services;
+mod templates;
 mod users;
+mod variables;
 mod web;
+mod webhook_body;
 mod webhook_index;
 mod webhook_payload;
+mod webhook_signature;
 mod webhooks;
 mod workflow_store;
 mod workflow_toml;
@@ -48,6 +62,10 @@ struct Args {
         default_value = ".env"
     )]
     env: String,
+
+    /// What to do, defaulting to running the agent.
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[tokio::main]
@@ -61,14 +79,17 @@ async fn main() {
         std::process::exit(2);
     }
 
+    // The administrative commands write their results to standard output, so
+    // logs are only sent there when running the agent itself.
     let session = Arc::new(Session::new("automate", env!("CARGO_PKG_VERSION"))
-        .with_battery(tracing_batteries::OpenTelemetry::new("").with_stdout(true))
+        .with_battery(tracing_batteries::OpenTelemetry::new("").with_stdout(args.command.is_none()))
         .with_battery(tracing_batteries::Sentry::new(
             "https://64422db58bbf92837d6484d1b8117d5a@o219072.ingest.us.sentry.io/4506753155137536",
         ))
         .with_battery(tracing_batteries::Analytics::new(
             "https://analytics.sierrasoftworks.com",
-        )));
+        ))
+        .with_battery(run_log::RunLogs));
 
     let result = run(args, session.clone()).await;
 
@@ -123,17 +144,16 @@ async fn shutdown_session(session: Arc<Session>) {
 
 #[instrument("main.run", skip(args, session), err(Display))]
 async fn run(args: Args, session: Arc<Session>) -> Result<(), human_errors::Error> {
-    let config = Config::load(args.config.unwrap_or_else(|| "config.toml".into()))?;
+    let config_path = args.config.unwrap_or_else(|| "config.toml".into());
 
-    let database_path = config.web.database.clone();
-    let db = db::SqliteDatabase::open(&database_path).await?;
-
-    // The key lives beside the database unless the operator supplies one, so
-    // that an existing installation upgrades without needing to be configured.
-    let secrets =
-        crypto::SecretStore::load(&config.web.auth, std::path::Path::new(&database_path))?;
+    if let Some(command) = args.command {
+        return command
+            .run(std::path::Path::new(&config_path), session)
+            .await;
+    }
 
-    let context = services::AppContext::new(config, db, secrets, session.clone());
+    let config = Config::load(config_path)?;
+    let context = services::AppContext::open(config, session.clone()).await?;
 
     (
         crate::web::run_web_server(context.clone()),
@@ -0,0 +1,486 @@
+//! Counting what the agent does, for Prometheus to scrape.
+//!
+//! Traces say what happened to one job; they cannot say that the queue has been
+//! growing for an hour, and that is the question an operator's alerting has to
+//! be able to ask. These are the numbers for it, served from `/metrics` in
+//! Prometheus's text format.
+//!
+//! # What is counted, and where
+//!
+//! Two kinds of number are kept. Those that describe something the agent did —
+//! a job that ran, a delivery answered, a credential renewed, a request made to
+//! a provider — are counted here, in memory, by the code that did it, and start
+//! again from zero when the process does, as Prometheus expects of a counter.
+//! Those that describe what is stored — how deep each queue is, how long the
+//! audit log has grown — are read from the database when the page is scraped,
+//! so that they are right however long the process has been up.
+//!
+//! # Why one registry for the process
+//!
+//! Requests to providers are made from deep inside clients that hold nothing
+//! but a [`reqwest::Client`], and threading a handle down to each of them would
+//! change a dozen constructors to count one number. The registry is the
+//! process's in the way the telemetry session's subscriber is, and the tests
+//! that care about what is counted build a [`Metrics`] of their own.
+//!
+//! Every label is drawn from a fixed set — a partition, a provider, a source
+//! the agent serves — and never from what a request carries, so a sender
+//! inventing addresses cannot grow the page without bound.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// The upper bounds, in seconds, of the buckets a job's duration is counted in.
+///
+/// From the quick jobs that only file a task to the ones that read a slow feed
+/// and hit their timeout, which is where a job that is struggling ends up.
+const JOB_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// The process's registry.
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// What became of a delivery to one of the webhook addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Delivery {
+    /// Queued, or knowingly dropped — a paused workflow, a sender's retry.
+    Accepted,
+    /// Turned away for something about the request.
+    Refused,
+    /// Lost to something on our side.
+    Failed,
+}
+
+impl Delivery {
+    /// Decided by the answer the sender was given, which is the one place every
+    /// path through a delivery meets.
+    pub fn of(status: actix_web::http::StatusCode) -> Self {
+        if status.is_success() {
+            Delivery::Accepted
+        } else if status.is_server_error() {
+            Delivery::Failed
+        } else {
+            Delivery::Refused
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Delivery::Accepted => "accepted",
+            Delivery::Refused => "refused",
+            Delivery::Failed => "failed",
+        }
+    }
+}
+
+/// The counters and histograms the agent keeps.
+#[derive(Default)]
+pub struct Metrics {
+    /// By partition and outcome.
+    jobs: Mutex<BTreeMap<(String, &'static str), Histogram>>,
+    /// By source and what became of the delivery.
+    deliveries: Mutex<BTreeMap<(String, Delivery), u64>>,
+    /// By provider and outcome.
+    refreshes: Mutex<BTreeMap<(String, &'static str), u64>>,
+    /// By provider and the status it answered with.
+    requests: Mutex<BTreeMap<(&'static str, String), u64>>,
+}
+
+/// What is stored, read from the database as the page is scraped.
+#[derive(Default)]
+pub struct Stored {
+    /// Messages by partition and state.
+    pub queue: Vec<(String, automate_api::QueueStatus, u64)>,
+    pub audit_entries: u64,
+}
+
+impl Metrics {
+    /// Counts a job the host ran, and how long it took.
+    pub fn job(&self, partition: &str, succeeded: bool, took: Duration) {
+        let outcome = if succeeded { "succeeded" } else { "failed" };
+
+        lock(&self.jobs)
+            .entry((partition.to_string(), outcome))
+            .or_default()
+            .observe(took.as_secs_f64());
+    }
+
+    /// Counts a delivery to a webhook address.
+    pub fn delivery(&self, source: &str, delivery: Delivery) {
+        *lock(&self.deliveries)
+            .entry((source.to_string(), delivery))
+            .or_default() += 1;
+    }
+
+    /// Counts an attempt to renew a stored credential.
+    pub fn refresh(&self, provider: &str, outcome: &'static str) {
+        *lock(&self.refreshes)
+            .entry((provider.to_string(), outcome))
+            .or_default() += 1;
+    }
+
+    /// Counts a request made to a provider, by the status it answered with, or
+    /// `error` where it never answered at all.
+    pub fn request(&self, provider: &'static str, status: Option<reqwest::StatusCode>) {
+        let status = status.map_or_else(|| "error".to_string(), |s| s.as_u16().to_string());
+
+        *lock(&self.requests).entry((provider, status)).or_default() += 1;
+    }
+
+    /// Counts a call made through a provider's own client library, which says
+    /// whether the call worked but not what the provider answered: `2xx` where
+    /// it did, and `failed` where it did not, whether or not anything answered.
+    pub fn call(&self, provider: &'static str, worked: bool) {
+        let status = if worked { "2xx" } else { "failed" };
+
+        *lock(&self.requests)
+            .entry((provider, status.to_string()))
+            .or_default() += 1;
+    }
+
+    /// Writes everything out in Prometheus's text format.
+    pub fn render(&self, stored: &Stored) -> String {
+        let mut out = String::new();
+
+        family(
+            &mut out,
+            "automate_queue_messages",
+            "gauge",
+            "Messages waiting in the job queue, by partition and state.",
+        );
+        for (partition, status, count) in &stored.queue {
+            sample(
+                &mut out,
+                "automate_queue_messages",
+                &[("partition", partition), ("status", status.as_str())],
+                *count,
+            );
+        }
+
+        family(
+            &mut out,
+            "automate_job_duration_seconds",
+            "histogram",
+            "How long each job took, by partition and outcome.",
+        );
+        for ((partition, outcome), histogram) in lock(&self.jobs).iter() {
+            let labels = [("partition", partition.as_str()), ("outcome", *outcome)];
+            histogram.render(&mut out, "automate_job_duration_seconds", &labels);
+        }
+
+        family(
+            &mut out,
+            "automate_webhook_deliveries_total",
+            "counter",
+            "Deliveries to the webhook addresses, by source and what became of them.",
+        );
+        for ((source, delivery), count) in lock(&self.deliveries).iter() {
+            sample(
+                &mut out,
+                "automate_webhook_deliveries_total",
+                &[("source", source), ("outcome", delivery.as_str())],
+                *count,
+            );
+        }
+
+        family(
+            &mut out,
+            "automate_connection_refreshes_total",
+            "counter",
+            "Attempts to renew stored credentials, by provider and outcome.",
+        );
+        for ((provider, outcome), count) in lock(&self.refreshes).iter() {
+            sample(
+                &mut out,
+                "automate_connection_refreshes_total",
+                &[("provider", provider), ("outcome", outcome)],
+                *count,
+            );
+        }
+
+        family(
+            &mut out,
+            "automate_http_requests_total",
+            "counter",
+            "Requests made to providers, by provider and the status they answered with.",
+        );
+        for ((provider, status), count) in lock(&self.requests).iter() {
+            sample(
+                &mut out,
+                "automate_http_requests_total",
+                &[("provider", provider), ("status", status)],
+                *count,
+            );
+        }
+
+        family(
+            &mut out,
+            "automate_audit_log_entries",
+            "gauge",
+            "Entries held in the audit log, across every account.",
+        );
+        sample(
+            &mut out,
+            "automate_audit_log_entries",
+            &[],
+            stored.audit_entries,
+        );
+
+        out
+    }
+}
+
+/// Sends a request and counts it against the provider it was made to.
+pub trait SendCounted {
+    fn send_counted(
+        self,
+        provider: &'static str,
+    ) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send;
+}
+
+impl SendCounted for reqwest::RequestBuilder {
+    async fn send_counted(self, provider: &'static str) -> reqwest::Result<reqwest::Response> {
+        let response = self.send().await;
+        global().request(provider, response.as_ref().ok().map(|r| r.status()));
+        response
+    }
+}
+
+/// Awaits a call made through a provider's client library and counts it against
+/// the provider it was made to.
+///
+/// The Todoist and YNAB libraries send their own requests on clients of their
+/// own, so [`SendCounted`] cannot reach them; what they hand back is all there
+/// is to count by (see [`Metrics::call`]).
+pub trait CallCounted<T, E> {
+    fn call_counted(self, provider: &'static str) -> impl Future<Output = Result<T, E>> + Send;
+}
+
+impl<F, T, E> CallCounted<T, E> for F
+where
+    F: Future<Output = Result<T, E>> + Send,
+    T: Send,
+    E: Send,
+{
+    async fn call_counted(self, provider: &'static str) -> Result<T, E> {
+        let result = self.await;
+        global().call(provider, result.is_ok());
+        result
+    }
+}
+
+/// A count of observations in buckets, as Prometheus's histograms are written.
+#[derive(Default)]
+struct Histogram {
+    /// How many observations fell at or below each of [`JOB_BUCKETS`], counted
+    /// into the first bucket they fit rather than every bucket they fit.
+    buckets: [u64; JOB_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = JOB_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &[(&str, &str)]) {
+        let bucket = format!("{name}_bucket");
+        let mut cumulative = 0;
+
+        for (bound, count) in JOB_BUCKETS.iter().zip(self.buckets) {
+            cumulative += count;
+            let le = bound.to_string();
+            sample(
+                out,
+                &bucket,
+                &[labels, &[("le", le.as_str())]].concat(),
+                cumulative,
+            );
+        }
+        sample(
+            out,
+            &bucket,
+            &[labels, &[("le", "+Inf")]].concat(),
+            self.count,
+        );
+
+        let _ = writeln!(out, "{name}_sum{} {}", label_set(labels), self.sum);
+        sample(out, &format!("{name}_count"), labels, self.count);
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: u64) {
+    let _ = writeln!(out, "{name}{} {value}", label_set(labels));
+}
+
+fn label_set(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+
+    let labels = labels
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{{labels}}}")
+}
+
+/// Escapes a label value the way the text format asks.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Takes a lock, carrying on past a panic elsewhere: a count that missed one
+/// increment is still worth serving.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_jobs_duration_is_counted_into_cumulative_buckets() {
+        let metrics = Metrics::default();
+        metrics.job("rss/todoist", true, Duration::from_millis(200));
+        metrics.job("rss/todoist", true, Duration::from_secs(3));
+        metrics.job("rss/todoist", false, Duration::from_secs(600));
+
+        let page = metrics.render(&Stored::default());
+
+        for line in [
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="succeeded",le="0.1"} 0"#,
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="succeeded",le="0.25"} 1"#,
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="succeeded",le="5"} 2"#,
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="succeeded",le="+Inf"} 2"#,
+            r#"automate_job_duration_seconds_count{partition="rss/todoist",outcome="succeeded"} 2"#,
+            r#"automate_job_duration_seconds_sum{partition="rss/todoist",outcome="succeeded"} 3.2"#,
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="failed",le="300"} 0"#,
+            r#"automate_job_duration_seconds_bucket{partition="rss/todoist",outcome="failed",le="+Inf"} 1"#,
+        ] {
+            assert!(
+                page.lines().any(|l| l == line),
+                "missing {line} from:\n{page}"
+            );
+        }
+    }
+
+    #[test]
+    fn what_is_stored_is_served_alongside_what_was_counted() {
+        let metrics = Metrics::default();
+        metrics.delivery("github", Delivery::Accepted);
+        metrics.delivery("github", Delivery::Accepted);
+        metrics.delivery("workflow", Delivery::Refused);
+        metrics.refresh("spotify", "needs-reauthorization");
+        metrics.request("todoist", Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        metrics.request("todoist", None);
+
+        let page = metrics.render(&Stored {
+            queue: vec![(
+                "rss/todoist".to_string(),
+                automate_api::QueueStatus::Pending,
+                12,
+            )],
+            audit_entries: 340,
+        });
+
+        for line in [
+            r#"automate_queue_messages{partition="rss/todoist",status="pending"} 12"#,
+            r#"automate_webhook_deliveries_total{source="github",outcome="accepted"} 2"#,
+            r#"automate_webhook_deliveries_total{source="workflow",outcome="refused"} 1"#,
+            r#"automate_connection_refreshes_total{provider="spotify",outcome="needs-reauthorization"} 1"#,
+            r#"automate_http_requests_total{provider="todoist",status="429"} 1"#,
+            r#"automate_http_requests_total{provider="todoist",status="error"} 1"#,
+            "automate_audit_log_entries 340",
+            "# TYPE automate_job_duration_seconds histogram",
+        ] {
+            assert!(
+                page.lines().any(|l| l == line),
+                "missing {line} from:\n{page}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_call_through_a_client_library_is_counted_by_whether_it_worked() {
+        let metrics = Metrics::default();
+        metrics.call("todoist", true);
+        metrics.call("todoist", true);
+        metrics.call("ynab", false);
+
+        let page = metrics.render(&Stored::default());
+
+        for line in [
+            r#"automate_http_requests_total{provider="todoist",status="2xx"} 2"#,
+            r#"automate_http_requests_total{provider="ynab",status="failed"} 1"#,
+        ] {
+            assert!(
+                page.lines().any(|l| l == line),
+                "missing {line} from:\n{page}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn a_counted_call_is_handed_back_as_it_was_made() {
+        let worked = async { Ok::<_, ()>(42) }.call_counted("example").await;
+        let failed = async { Err::<(), _>("no") }.call_counted("example").await;
+
+        assert_eq!(worked, Ok(42));
+        assert_eq!(failed, Err("no"));
+
+        let page = global().render(&Stored::default());
+        assert!(
+            page.contains(r#"automate_http_requests_total{provider="example",status="2xx"} 1"#)
+        );
+        assert!(
+            page.contains(r#"automate_http_requests_total{provider="example",status="failed"} 1"#)
+        );
+    }
+
+    #[test]
+    fn a_label_value_cannot_break_out_of_its_quotes() {
+        assert_eq!(
+            label_set(&[("source", "a\"b\\c\nd")]),
+            r#"{source="a\"b\\c\nd"}"#
+        );
+    }
+
+    #[test]
+    fn a_delivery_is_judged_by_the_answer_its_sender_was_given() {
+        use actix_web::http::StatusCode;
+
+        assert_eq!(Delivery::of(StatusCode::NO_CONTENT), Delivery::Accepted);
+        assert_eq!(
+            Delivery::of(StatusCode::TOO_MANY_REQUESTS),
+            Delivery::Refused
+        );
+        assert_eq!(Delivery::of(StatusCode::NOT_FOUND), Delivery::Refused);
+        assert_eq!(
+            Delivery::of(StatusCode::SERVICE_UNAVAILABLE),
+            Delivery::Failed
+        );
+    }
+}
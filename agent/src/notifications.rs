@@ -0,0 +1,528 @@
+//! Telling an account that something of theirs has stopped working.
+//!
+//! A workflow that starts failing at three in the morning, or a connection a
+//! provider has stopped accepting, used to be recorded in the audit log and
+//! nowhere else — which is to say, found by whoever next wondered why nothing
+//! had arrived. An account can name one [`NotificationDestination`], and each
+//! such incident is announced there when it starts and closed when it ends.
+//!
+//! # One incident, one message
+//!
+//! Callers report transitions, never states: [`raise`] when something that
+//! worked stops, [`resolve`] when it works again. A workflow failing a thousand
+//! times in a row is one incident, and at a Todoist destination it is one task,
+//! filed through the same upsert and complete pair every other reminder uses
+//! and keyed by [`Incident::key`], so raising it again updates the task rather
+//! than adding another.
+//!
+//! Both are best effort by design. The run or the status change that prompted
+//! them has already happened, and failing it because its owner could not be
+//! told would lose the thing worth telling them about.
+
+use std::fmt;
+
+use automate_api::{ConnectionId, NotificationDestination, WorkflowId};
+
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+use crate::publishers::{
+    HttpPost, HttpPostPayload, SendEmail, SendEmailPayload, TodoistCompleteTask,
+    TodoistCompleteTaskPayload, TodoistDueDate, TodoistTarget, TodoistUpsertTask,
+    TodoistUpsertTaskPayload,
+};
+
+/// The key-value partition holding an account's notification settings.
+pub const NOTIFICATIONS_PARTITION: &str = "notifications";
+
+/// The key, within that partition, of the account's destination.
+const DESTINATION_KEY: &str = "destination";
+
+/// Reads and writes where one account is told about its incidents.
+pub struct NotificationStore<S: Services> {
+    services: S,
+}
+
+impl<S: Services> NotificationStore<S> {
+    pub fn new(services: S) -> Self {
+        Self { services }
+    }
+
+    /// Where this account is told about its incidents, if anywhere.
+    pub async fn destination(&self) -> Result<Option<NotificationDestination>, Error> {
+        self.services
+            .kv()
+            .get(NOTIFICATIONS_PARTITION, DESTINATION_KEY.to_string())
+            .await
+    }
+
+    /// Replaces the destination, once it has been checked.
+    pub async fn set(&self, destination: NotificationDestination) -> Result<(), Error> {
+        validate(&destination)?;
+
+        if matches!(destination, NotificationDestination::Email { .. })
+            && self.services.config().mail.is_none()
+        {
+            return Err(human_errors::user(
+                "This installation has no mail server to send email through.",
+                &[
+                    "Ask whoever runs it to configure one under [mail], or choose Todoist or a webhook instead.",
+                ],
+            ));
+        }
+
+        self.services
+            .kv()
+            .set(
+                NOTIFICATIONS_PARTITION,
+                DESTINATION_KEY.to_string(),
+                destination,
+            )
+            .await
+    }
+
+    /// Stops telling this account about anything, reporting whether it was
+    /// being told.
+    pub async fn clear(&self) -> Result<bool, Error> {
+        let existed = self.destination().await?.is_some();
+
+        self.services
+            .kv()
+            .remove(NOTIFICATIONS_PARTITION, DESTINATION_KEY.to_string())
+            .await?;
+
+        Ok(existed)
+    }
+}
+
+/// Checks a destination before it is kept.
+///
+/// Only what can be checked without reaching out: a Todoist account that has
+/// since been disconnected, or an address that has stopped answering, is
+/// reported by the publisher when it next has something to deliver, like any
+/// workflow's would be.
+pub fn validate(destination: &NotificationDestination) -> Result<(), Error> {
+    match destination {
+        NotificationDestination::Todoist { .. } => Ok(()),
+        NotificationDestination::Webhook { url } => {
+            let parsed = reqwest::Url::parse(url).wrap_user_err(
+                format!("'{url}' is not an address we can post to."),
+                &["Give the full address, starting with https://."],
+            )?;
+
+            if matches!(parsed.scheme(), "http" | "https") {
+                Ok(())
+            } else {
+                Err(human_errors::user(
+                    format!("We only post to http and https addresses, not '{url}'."),
+                    &["Give an address starting with https://."],
+                ))
+            }
+        }
+        NotificationDestination::Email { address } => {
+            crate::publishers::email_recipient(address).map(|_| ())
+        }
+    }
+}
+
+/// The thing that has stopped working.
+#[derive(Debug, Clone, Copy)]
+pub enum Incident<'a> {
+    Workflow {
+        id: WorkflowId,
+        name: &'a str,
+    },
+    Connection {
+        id: ConnectionId,
+        provider: &'a str,
+        name: &'a str,
+    },
+}
+
+impl Incident<'_> {
+    /// Names the incident at its destination, the same however many times it
+    /// is raised and when it is resolved.
+    ///
+    /// Derived from what broke rather than from when, because that is what
+    /// makes a second failure update the first one's task instead of filing
+    /// another: there is only ever one open incident per workflow or
+    /// connection.
+    pub fn key(&self) -> String {
+        match self {
+            Self::Workflow { id, .. } => format!("incident/workflow/{id}"),
+            Self::Connection { id, .. } => format!("incident/connection/{id}"),
+        }
+    }
+
+    fn subject(&self) -> serde_json::Value {
+        match self {
+            Self::Workflow { id, name } => serde_json::json!({
+                "kind": "workflow",
+                "id": id,
+                "name": name,
+            }),
+            Self::Connection { id, provider, name } => serde_json::json!({
+                "kind": "connection",
+                "id": id,
+                "provider": provider,
+                "name": name,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Incident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Workflow { name, .. } => write!(f, "Workflow '{name}'"),
+            Self::Connection { provider, name, .. } => {
+                write!(f, "The {provider} connection '{name}'")
+            }
+        }
+    }
+}
+
+/// Announces that `incident` has started, with `summary` saying how.
+///
+/// The summary is read as the end of a sentence that starts with the incident's
+/// name, such as "started failing: the feed could not be reached".
+pub async fn raise(services: &impl Services, incident: Incident<'_>, summary: &str) {
+    let summary = format!("{incident} {summary}");
+    notify(services, incident, Change::Opened, summary).await
+}
+
+/// Announces that `incident` is over, with `summary` saying how.
+pub async fn resolve(services: &impl Services, incident: Incident<'_>, summary: &str) {
+    let summary = format!("{incident} {summary}");
+    notify(services, incident, Change::Resolved, summary).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Opened,
+    Resolved,
+}
+
+impl Change {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Opened => "opened",
+            Self::Resolved => "resolved",
+        }
+    }
+}
+
+async fn notify(services: &impl Services, incident: Incident<'_>, change: Change, summary: String) {
+    let destination = match NotificationStore::new(services).destination().await {
+        Ok(Some(destination)) => destination,
+        Ok(None) => return,
+        Err(err) => {
+            warn!(error = %err, incident = %incident.key(), "Could not read where to announce an incident, so it will not be: {err}");
+            return;
+        }
+    };
+
+    if let Err(err) = deliver(services, &destination, incident, change, summary).await {
+        warn!(error = %err, incident = %incident.key(), "Could not announce an incident to its owner: {err}");
+    }
+}
+
+async fn deliver(
+    services: &impl Services,
+    destination: &NotificationDestination,
+    incident: Incident<'_>,
+    change: Change,
+    summary: String,
+) -> Result<(), Error> {
+    let key = incident.key();
+
+    match destination {
+        NotificationDestination::Todoist {
+            connection,
+            project,
+            section,
+        } => {
+            let config = TodoistTarget {
+                connection: *connection,
+                project: project.clone(),
+                section: section.clone(),
+            };
+
+            match change {
+                // Keyed by the incident, so that a pending announcement of the
+                // same incident is replaced in the queue rather than joined.
+                Change::Opened => {
+                    TodoistUpsertTask::dispatch(
+                        TodoistUpsertTaskPayload {
+                            unique_key: key.clone(),
+                            title: format!("**automate**: {summary}"),
+                            description: None,
+                            priority: Some(3),
+                            due: TodoistDueDate::Today,
+                            duration: None,
+                            config,
+                        },
+                        Some(key.into()),
+                        services,
+                    )
+                    .await
+                }
+                Change::Resolved => {
+                    TodoistCompleteTask::dispatch(
+                        TodoistCompleteTaskPayload {
+                            unique_key: key.clone(),
+                            config,
+                        },
+                        Some(key.into()),
+                        services,
+                    )
+                    .await
+                }
+            }
+        }
+        NotificationDestination::Webhook { url } => {
+            let body = serde_json::json!({
+                "incident": key,
+                "status": change.as_str(),
+                "subject": incident.subject(),
+                "summary": summary,
+            });
+
+            HttpPost::dispatch(
+                HttpPostPayload {
+                    url: url.clone(),
+                    body: body.to_string(),
+                    content_type: "application/json".into(),
+                },
+                None,
+                services,
+            )
+            .await
+        }
+        NotificationDestination::Email { address } => {
+            let subject = match change {
+                Change::Opened => format!("[automate] {summary}"),
+                Change::Resolved => format!("[automate] Resolved: {summary}"),
+            };
+
+            SendEmail::dispatch(
+                SendEmailPayload {
+                    to: address.clone(),
+                    subject,
+                    body: format!("{summary}\n\nIncident: {key}\n"),
+                },
+                None,
+                services,
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Queue;
+
+    fn workflow() -> Incident<'static> {
+        Incident::Workflow {
+            id: WorkflowId::from_entropy(1234),
+            name: "Release notes",
+        }
+    }
+
+    async fn services_notifying(
+        destination: Option<NotificationDestination>,
+    ) -> crate::services::AppServices {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+
+        if let Some(destination) = destination {
+            NotificationStore::new(&services)
+                .set(destination)
+                .await
+                .unwrap();
+        }
+
+        services
+    }
+
+    #[tokio::test]
+    async fn an_incident_is_one_todoist_task_filed_when_it_starts_and_completed_when_it_ends() {
+        let services = services_notifying(Some(NotificationDestination::Todoist {
+            connection: None,
+            project: Some("Automate".into()),
+            section: None,
+        }))
+        .await;
+
+        raise(&services, workflow(), "started failing: the feed is gone").await;
+        resolve(&services, workflow(), "recovered after 3 failed runs.").await;
+
+        let filed = services
+            .queue()
+            .dequeue("todoist/upsert-task", chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        let filed: TodoistUpsertTaskPayload = filed.payload;
+        assert_eq!(
+            filed.title,
+            "**automate**: Workflow 'Release notes' started failing: the feed is gone"
+        );
+        assert_eq!(filed.config.project.as_deref(), Some("Automate"));
+
+        let completed = services
+            .queue()
+            .dequeue("todoist/complete-task", chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        let completed: TodoistCompleteTaskPayload = completed.payload;
+        assert_eq!(completed.unique_key, filed.unique_key);
+    }
+
+    #[tokio::test]
+    async fn a_webhook_hears_both_ends_of_an_incident_under_the_same_name() {
+        let services = services_notifying(Some(NotificationDestination::Webhook {
+            url: "https://chat.example.com/hooks/abc".into(),
+        }))
+        .await;
+
+        raise(&services, workflow(), "started failing: the feed is gone").await;
+        resolve(&services, workflow(), "recovered after 3 failed runs.").await;
+
+        let mut posts = Vec::new();
+        for _ in 0..2 {
+            let post: crate::db::QueueMessage<HttpPostPayload> = services
+                .queue()
+                .dequeue("http/post", chrono::Duration::minutes(1))
+                .await
+                .unwrap();
+            assert_eq!(post.payload.url, "https://chat.example.com/hooks/abc");
+            posts.push(serde_json::from_str::<serde_json::Value>(&post.payload.body).unwrap());
+        }
+
+        posts.sort_by_key(|post| post["status"].as_str().unwrap().to_string());
+        assert_eq!(posts[0]["status"], "opened");
+        assert_eq!(posts[1]["status"], "resolved");
+        assert_eq!(posts[0]["incident"], posts[1]["incident"]);
+        assert_eq!(posts[0]["subject"]["name"], "Release notes");
+        assert_eq!(
+            posts[1]["summary"],
+            "Workflow 'Release notes' recovered after 3 failed runs."
+        );
+    }
+
+    #[tokio::test]
+    async fn an_email_is_sent_when_an_incident_starts_and_again_when_it_ends() {
+        let services = crate::services::ServicesContainer::new_custom_mock(|config, _| {
+            config.mail = Some(crate::config::MailConfig {
+                host: "smtp.example.com".into(),
+                port: None,
+                security: Default::default(),
+                username: None,
+                password: None,
+                from: "automate@example.com".into(),
+            });
+        })
+        .await
+        .unwrap();
+        NotificationStore::new(&services)
+            .set(NotificationDestination::Email {
+                address: "ops@example.com".into(),
+            })
+            .await
+            .unwrap();
+
+        raise(&services, workflow(), "started failing: the feed is gone").await;
+        resolve(&services, workflow(), "recovered after 3 failed runs.").await;
+
+        let mut subjects = Vec::new();
+        for _ in 0..2 {
+            let message: crate::db::QueueMessage<SendEmailPayload> = services
+                .queue()
+                .dequeue("email/send", chrono::Duration::minutes(1))
+                .await
+                .unwrap();
+            assert_eq!(message.payload.to, "ops@example.com");
+            assert!(message.payload.body.contains("incident/workflow/"));
+            subjects.push(message.payload.subject);
+        }
+
+        subjects.sort();
+        assert_eq!(
+            subjects,
+            vec![
+                "[automate] Resolved: Workflow 'Release notes' recovered after 3 failed runs.",
+                "[automate] Workflow 'Release notes' started failing: the feed is gone",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn email_is_refused_where_there_is_no_mail_server() {
+        let services = services_notifying(None).await;
+
+        let err = NotificationStore::new(&services)
+            .set(NotificationDestination::Email {
+                address: "ops@example.com".into(),
+            })
+            .await
+            .unwrap_err();
+
+        assert!(err.is(human_errors::Kind::User), "{err}");
+        assert!(
+            NotificationStore::new(&services)
+                .destination()
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn an_account_with_nowhere_to_be_told_is_not_told() {
+        let services = services_notifying(None).await;
+
+        raise(&services, workflow(), "started failing.").await;
+
+        assert!(services.queue().partitions().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_web_addresses_are_kept_as_webhook_destinations() {
+        assert!(
+            validate(&NotificationDestination::Webhook {
+                url: "https://chat.example.com/hooks/abc".into()
+            })
+            .is_ok()
+        );
+        assert!(
+            validate(&NotificationDestination::Webhook {
+                url: "file:///etc/passwd".into()
+            })
+            .is_err()
+        );
+        assert!(
+            validate(&NotificationDestination::Webhook {
+                url: "chat.example.com".into()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn an_email_destination_must_be_one_address() {
+        assert!(
+            validate(&NotificationDestination::Email {
+                address: "ops@example.com".into()
+            })
+            .is_ok()
+        );
+        assert!(
+            validate(&NotificationDestination::Email {
+                address: "ops@example.com, dev@example.com".into()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,719 @@
+//! The small language spoken inside `${{ ... }}`.
+//!
+//! [`super::interpolate`] only finds the expressions; what they mean is up to
+//! whoever called it. For a long time every caller treated the whole expression
+//! as a dotted path, which is enough for `PR #${{ number }}` and nothing else:
+//! a title that should fall back to something when a field is missing, or cut a
+//! long one short, or turn a list of labels into `bug, urgent`, had nowhere to
+//! say so. This is the one place that says it, so that the configuration file
+//! and every templated workflow field agree on what an expression can do.
+//!
+//! An expression is a value followed by any number of `|`-separated functions,
+//! each applied to the result of the one before:
+//!
+//! ```text
+//! issue.title | default("Untitled") | truncate(60)
+//! labels | join(", ")
+//! draft | if("Draft", "Ready")
+//! ```
+//!
+//! # What it deliberately cannot do
+//!
+//! Templates are written by the people using the product and evaluated against
+//! payloads written by whoever can reach a webhook, so the language is kept to
+//! something that cannot loop, allocate without bound, or reach anything but
+//! the value it was handed. There are no variables, no user-defined functions
+//! and no nesting: a function's arguments are literals, never other
+//! expressions. The library below is fixed, and an expression naming anything
+//! outside it is refused when it is parsed, which for a workflow means when it
+//! is saved rather than when the first delivery arrives.
+
+use std::fmt::Write;
+
+use automate_api::PayloadPath;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+
+/// A parsed `${{ ... }}` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    head: Term,
+    functions: Vec<Function>,
+}
+
+/// Where an expression's value comes from.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Looked up by whoever evaluates the expression: a path into a payload,
+    /// or `env.NAME` in the configuration file.
+    Path(String),
+
+    /// Written out in the expression itself.
+    Literal(Value),
+}
+
+/// One of the fixed library of functions a value can be piped through.
+#[derive(Debug, Clone, PartialEq)]
+enum Function {
+    Default(Value),
+    Upper,
+    Lower,
+    Trim,
+    Truncate(usize),
+    Date(String),
+    Join(String),
+    MarkdownEscape,
+    Length,
+    Eq(Value),
+    Ne(Value),
+    Contains(Value),
+    If(Value, Value),
+}
+
+/// The advice given when an expression names a function it does not know.
+const FUNCTIONS: &str = "The functions you can use are default, upper, lower, trim, truncate, date, join, markdown_escape, length, eq, ne, contains and if.";
+
+impl Expression {
+    /// Parses the text between `${{` and `}}`.
+    ///
+    /// An empty expression is allowed and evaluates to nothing, which is how
+    /// `${{ }}` has always rendered.
+    pub fn parse(source: &str) -> Result<Self, human_errors::Error> {
+        let tokens = Lexer::new(source).tokens()?;
+        let mut tokens = tokens.into_iter().peekable();
+
+        let head = match tokens.next() {
+            None => Term::Literal(Value::Null),
+            Some(Token::Path(path)) => match path.as_str() {
+                "true" => Term::Literal(Value::Bool(true)),
+                "false" => Term::Literal(Value::Bool(false)),
+                "null" => Term::Literal(Value::Null),
+                _ => Term::Path(path),
+            },
+            Some(Token::Literal(value)) => Term::Literal(value),
+            Some(token) => return Err(unexpected(source, &token, "a value or a path")),
+        };
+
+        let mut functions = Vec::new();
+        while let Some(token) = tokens.next() {
+            if token != Token::Pipe {
+                return Err(unexpected(source, &token, "a `|` before the next function"));
+            }
+
+            let name = match tokens.next() {
+                Some(Token::Path(name)) => name,
+                Some(token) => return Err(unexpected(source, &token, "the name of a function")),
+                None => {
+                    return Err(invalid(
+                        source,
+                        "it ends with a `|` that is not followed by a function",
+                    ));
+                }
+            };
+
+            let mut args = Vec::new();
+            if tokens.peek() == Some(&Token::Open) {
+                tokens.next();
+
+                loop {
+                    match tokens.next() {
+                        Some(Token::Close) if args.is_empty() => break,
+                        Some(Token::Literal(value)) => args.push(value),
+                        Some(Token::Path(word))
+                            if matches!(word.as_str(), "true" | "false" | "null") =>
+                        {
+                            args.push(match word.as_str() {
+                                "true" => Value::Bool(true),
+                                "false" => Value::Bool(false),
+                                _ => Value::Null,
+                            })
+                        }
+                        Some(Token::Path(path)) => {
+                            return Err(invalid(
+                                source,
+                                &format!(
+                                    "`{name}` was given `{path}`, and a function's arguments have to be written out, such as \"text\" or 60"
+                                ),
+                            ));
+                        }
+                        Some(token) => return Err(unexpected(source, &token, "an argument")),
+                        None => {
+                            return Err(invalid(
+                                source,
+                                &format!("the `(` after `{name}` is never closed"),
+                            ));
+                        }
+                    }
+
+                    match tokens.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::Close) => break,
+                        Some(token) => return Err(unexpected(source, &token, "a `,` or a `)`")),
+                        None => {
+                            return Err(invalid(
+                                source,
+                                &format!("the `(` after `{name}` is never closed"),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            functions.push(Function::new(source, &name, args)?);
+        }
+
+        Ok(Self { head, functions })
+    }
+
+    /// The path this expression looks up, if it looks one up at all.
+    pub fn path(&self) -> Option<&str> {
+        match &self.head {
+            Term::Path(path) => Some(path),
+            Term::Literal(_) => None,
+        }
+    }
+
+    /// Whether this is a path and nothing more, as every expression was before
+    /// there were functions.
+    pub fn is_bare(&self) -> bool {
+        self.functions.is_empty() && self.path().is_some()
+    }
+
+    /// Works out the expression's value, asking `resolve` for the path it
+    /// names. A path that `resolve` cannot find is null, which the functions
+    /// treat as nothing at all.
+    pub fn evaluate(&self, resolve: impl FnOnce(&str) -> Option<Value>) -> Value {
+        let value = match &self.head {
+            Term::Path(path) => resolve(path).unwrap_or(Value::Null),
+            Term::Literal(value) => value.clone(),
+        };
+
+        self.functions
+            .iter()
+            .fold(value, |value, function| function.apply(value))
+    }
+
+    /// Works out the expression's value and writes it as text, the way it
+    /// appears in a template.
+    pub fn render(&self, resolve: impl FnOnce(&str) -> Option<Value>) -> String {
+        text(&self.evaluate(resolve))
+    }
+}
+
+/// The text a value stands for when it is written into a template.
+///
+/// A string is written without its quotes, nothing is written for null, and
+/// everything else is written as compact JSON.
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Whether a value counts as true for `if`: anything but null, false, zero and
+/// empty text, lists and objects.
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64().is_some_and(|number| number != 0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+/// Compares two values the way somebody writing `eq(42)` means it, so that a
+/// payload's `42.0` is the same number.
+fn same(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        _ => left == right,
+    }
+}
+
+impl Function {
+    fn new(source: &str, name: &str, args: Vec<Value>) -> Result<Self, human_errors::Error> {
+        let arity = |expected: usize| -> Result<(), human_errors::Error> {
+            if args.len() == expected {
+                Ok(())
+            } else {
+                Err(invalid(
+                    source,
+                    &format!(
+                        "`{name}` takes {expected} argument{}, and was given {}",
+                        if expected == 1 { "" } else { "s" },
+                        args.len()
+                    ),
+                ))
+            }
+        };
+
+        let text_arg = |what: &str| -> Result<String, human_errors::Error> {
+            arity(1)?;
+            match &args[0] {
+                Value::String(text) => Ok(text.clone()),
+                _ => Err(invalid(source, &format!("`{name}` takes {what} in quotes"))),
+            }
+        };
+
+        match name {
+            "default" => {
+                arity(1)?;
+                Ok(Function::Default(args[0].clone()))
+            }
+            "upper" => arity(0).map(|_| Function::Upper),
+            "lower" => arity(0).map(|_| Function::Lower),
+            "trim" => arity(0).map(|_| Function::Trim),
+            "markdown_escape" => arity(0).map(|_| Function::MarkdownEscape),
+            "length" => arity(0).map(|_| Function::Length),
+            "truncate" => {
+                arity(1)?;
+                args[0]
+                    .as_u64()
+                    .filter(|length| *length > 0)
+                    .map(|length| Function::Truncate(length as usize))
+                    .ok_or_else(|| {
+                        invalid(
+                            source,
+                            "`truncate` takes how many characters to keep, such as truncate(60)",
+                        )
+                    })
+            }
+            "date" => {
+                let format = text_arg("a format such as \"%a %d %b\"")?;
+                if chrono::format::StrftimeItems::new(&format)
+                    .any(|item| matches!(item, chrono::format::Item::Error))
+                {
+                    return Err(invalid(
+                        source,
+                        &format!("'{format}' is not a date format `date` understands"),
+                    ));
+                }
+
+                Ok(Function::Date(format))
+            }
+            "join" => text_arg("what to put between the items, such as \", \"").map(Function::Join),
+            "eq" => {
+                arity(1)?;
+                Ok(Function::Eq(args[0].clone()))
+            }
+            "ne" => {
+                arity(1)?;
+                Ok(Function::Ne(args[0].clone()))
+            }
+            "contains" => {
+                arity(1)?;
+                Ok(Function::Contains(args[0].clone()))
+            }
+            "if" => match args.len() {
+                1 => Ok(Function::If(args[0].clone(), Value::Null)),
+                2 => Ok(Function::If(args[0].clone(), args[1].clone())),
+                _ => Err(invalid(
+                    source,
+                    "`if` takes what to write when the value is true, and optionally what to write when it is not",
+                )),
+            },
+            _ => Err(human_errors::user(
+                format!(
+                    "The expression '{}' uses `{name}`, which is not a function we know.",
+                    source.trim()
+                ),
+                &[FUNCTIONS],
+            )),
+        }
+    }
+
+    fn apply(&self, value: Value) -> Value {
+        match self {
+            Function::Default(fallback) => {
+                if value.is_null() || value.as_str() == Some("") {
+                    fallback.clone()
+                } else {
+                    value
+                }
+            }
+            Function::Upper => Value::String(text(&value).to_uppercase()),
+            Function::Lower => Value::String(text(&value).to_lowercase()),
+            Function::Trim => Value::String(text(&value).trim().to_string()),
+            Function::Truncate(length) => {
+                let text = text(&value);
+                if text.chars().count() <= *length {
+                    Value::String(text)
+                } else {
+                    let mut truncated: String = text.chars().take(length - 1).collect();
+                    truncated.push('…');
+                    Value::String(truncated)
+                }
+            }
+            Function::Date(format) => match timestamp(&value) {
+                Some(at) => {
+                    let mut formatted = String::new();
+                    match write!(formatted, "{}", at.format(format)) {
+                        Ok(()) => Value::String(formatted),
+                        Err(_) => value,
+                    }
+                }
+                None => value,
+            },
+            Function::Join(separator) => match &value {
+                Value::Array(items) => {
+                    Value::String(items.iter().map(text).collect::<Vec<_>>().join(separator))
+                }
+                _ => value,
+            },
+            Function::MarkdownEscape => {
+                let text = text(&value);
+                let mut escaped = String::with_capacity(text.len());
+                for ch in text.chars() {
+                    if matches!(
+                        ch,
+                        '\\' | '`' | '*' | '_' | '~' | '[' | ']' | '(' | ')' | '#' | '>' | '|'
+                    ) {
+                        escaped.push('\\');
+                    }
+                    escaped.push(ch);
+                }
+                Value::String(escaped)
+            }
+            Function::Length => Value::from(match &value {
+                Value::Null => 0,
+                Value::String(text) => text.chars().count(),
+                Value::Array(items) => items.len(),
+                Value::Object(fields) => fields.len(),
+                other => other.to_string().chars().count(),
+            }),
+            Function::Eq(other) => Value::Bool(same(&value, other)),
+            Function::Ne(other) => Value::Bool(!same(&value, other)),
+            Function::Contains(needle) => Value::Bool(match &value {
+                Value::String(text) => needle.as_str().is_some_and(|needle| text.contains(needle)),
+                Value::Array(items) => items.iter().any(|item| same(item, needle)),
+                Value::Object(fields) => {
+                    needle.as_str().is_some_and(|key| fields.contains_key(key))
+                }
+                _ => false,
+            }),
+            Function::If(then, otherwise) => {
+                if truthy(&value) {
+                    then.clone()
+                } else {
+                    otherwise.clone()
+                }
+            }
+        }
+    }
+}
+
+/// Reads a value as a moment in time: an RFC 3339 timestamp, a `YYYY-MM-DD`
+/// day, or a number of seconds since the Unix epoch.
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .map(|at| at.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .ok()
+                    .map(|day| day.and_time(chrono::NaiveTime::MIN).and_utc())
+            }),
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Path(String),
+    Literal(Value),
+    Pipe,
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Path(path) => write!(f, "`{path}`"),
+            Token::Literal(value) => write!(f, "`{value}`"),
+            Token::Pipe => write!(f, "`|`"),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            chars: source.char_indices().peekable(),
+        }
+    }
+
+    fn tokens(mut self) -> Result<Vec<Token>, human_errors::Error> {
+        let mut tokens = Vec::new();
+
+        while let Some(&(start, ch)) = self.chars.peek() {
+            match ch {
+                ch if ch.is_whitespace() => {
+                    self.chars.next();
+                }
+                '|' => {
+                    self.chars.next();
+                    tokens.push(Token::Pipe);
+                }
+                '(' => {
+                    self.chars.next();
+                    tokens.push(Token::Open);
+                }
+                ')' => {
+                    self.chars.next();
+                    tokens.push(Token::Close);
+                }
+                ',' => {
+                    self.chars.next();
+                    tokens.push(Token::Comma);
+                }
+                '"' | '\'' => tokens.push(self.string(ch)?),
+                ch if ch.is_ascii_digit() || ch == '-' => tokens.push(self.number(start)?),
+                ch if ch.is_alphabetic() || ch == '_' => tokens.push(self.path(start)?),
+                other => {
+                    return Err(invalid(
+                        self.source,
+                        &format!("`{other}` is not something an expression can contain"),
+                    ));
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> usize {
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !keep(ch) {
+                break;
+            }
+            self.chars.next();
+        }
+
+        self.chars
+            .peek()
+            .map(|&(index, _)| index)
+            .unwrap_or(self.source.len())
+    }
+
+    /// Reads a path, carrying on through any `[...]` steps into arrays along
+    /// with the fields after them, so that `commits[*].author.name` is one
+    /// path rather than a path followed by things an expression cannot hold.
+    fn path(&mut self, start: usize) -> Result<Token, human_errors::Error> {
+        let field = |ch: char| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.');
+        let mut end = self.take_while(field);
+
+        while self.chars.peek().is_some_and(|&(_, ch)| ch == '[') {
+            let mut depth = 0;
+            let mut quote = None;
+
+            while let Some((_, ch)) = self.chars.next() {
+                match (quote, ch) {
+                    (Some(_), '\\') => {
+                        self.chars.next();
+                    }
+                    (Some(open), ch) if ch == open => quote = None,
+                    (Some(_), _) => {}
+                    (None, '"' | '\'') => quote = Some(ch),
+                    (None, '[') => depth += 1,
+                    (None, ']') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            end = self.take_while(field);
+        }
+
+        let path = &self.source[start..end];
+        if path.contains('[') {
+            PayloadPath::parse(path).map_err(|err| invalid(self.source, err.problem()))?;
+        }
+
+        Ok(Token::Path(path.to_string()))
+    }
+
+    fn string(&mut self, quote: char) -> Result<Token, human_errors::Error> {
+        self.chars.next();
+        let mut text = String::new();
+
+        while let Some((_, ch)) = self.chars.next() {
+            match ch {
+                '\\' => match self.chars.next() {
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, 't')) => text.push('\t'),
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                ch if ch == quote => return Ok(Token::Literal(Value::String(text))),
+                ch => text.push(ch),
+            }
+        }
+
+        Err(invalid(
+            self.source,
+            &format!("a {quote}quoted{quote} text is never closed"),
+        ))
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, human_errors::Error> {
+        self.chars.next();
+        let end = self.take_while(|ch| ch.is_ascii_digit() || ch == '.');
+        let literal = &self.source[start..end];
+
+        literal
+            .parse::<i64>()
+            .map(Value::from)
+            .ok()
+            .or_else(|| {
+                literal
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+            })
+            .map(Token::Literal)
+            .ok_or_else(|| invalid(self.source, &format!("`{literal}` is not a number")))
+    }
+}
+
+fn invalid(source: &str, problem: &str) -> human_errors::Error {
+    human_errors::user(
+        format!(
+            "The expression '{}' could not be read: {problem}.",
+            source.trim()
+        ),
+        &[
+            "Write a value, then any functions to pass it through, each after a `|`, such as ${{ issue.title | default(\"Untitled\") | truncate(60) }}.",
+            "Escape it as \\${{ ... }} if you meant the text literally.",
+        ],
+    )
+}
+
+fn unexpected(source: &str, token: &Token, expected: &str) -> human_errors::Error {
+    invalid(
+        source,
+        &format!("it has {token} where {expected} should be"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn payload() -> Value {
+        serde_json::json!({
+            "title": "Everything is on fire",
+            "empty": "",
+            "labels": ["bug", "urgent"],
+            "count": 3,
+            "draft": false,
+            "created_at": "2024-04-05T09:30:00Z",
+            "user": { "login": "octocat" },
+        })
+    }
+
+    fn render(expression: &str) -> String {
+        let payload = payload();
+        let expression = Expression::parse(expression).expect("parse the expression");
+        expression.render(|path| PayloadPath::parse(path).ok()?.resolve(&payload).to_value())
+    }
+
+    #[rstest]
+    #[case("title", "Everything is on fire")]
+    #[case("title | upper", "EVERYTHING IS ON FIRE")]
+    #[case("title | truncate(10)", "Everythin…")]
+    #[case("title | truncate(100)", "Everything is on fire")]
+    #[case("missing | default(\"Untitled\")", "Untitled")]
+    #[case("empty | default('Untitled')", "Untitled")]
+    #[case("title | default(\"Untitled\")", "Everything is on fire")]
+    #[case("labels | join(\", \")", "bug, urgent")]
+    #[case("labels | length", "2")]
+    #[case("created_at | date(\"%a %d %b\")", "Fri 05 Apr")]
+    #[case("draft | if(\"Draft\", \"Ready\")", "Ready")]
+    #[case("labels | contains(\"bug\") | if(\"Bug\")", "Bug")]
+    #[case("count | eq(3) | if(\"three\", \"other\")", "three")]
+    #[case("user.login | ne(\"octocat\") | if(\"someone else\")", "")]
+    #[case("\"[fire]\" | markdown_escape", "\\[fire\\]")]
+    #[case("  title   |   lower  ", "everything is on fire")]
+    #[case("labels[0]", "bug")]
+    #[case("labels[-1] | upper", "URGENT")]
+    #[case("labels[?@ startswith 'b'] | join(', ')", "bug")]
+    #[case("", "")]
+    fn functions_are_applied_in_order(#[case] expression: &str, #[case] expected: &str) {
+        assert_eq!(render(expression), expected, "rendering `{expression}`");
+    }
+
+    #[test]
+    fn a_value_that_is_not_a_date_is_left_alone() {
+        assert_eq!(
+            render("title | date(\"%Y\")"),
+            "Everything is on fire",
+            "a title is not a date, and showing it beats showing nothing",
+        );
+    }
+
+    #[rstest]
+    #[case("title | shout", "not a function we know")]
+    #[case("title | truncate", "takes 1 argument")]
+    #[case("title | truncate(\"ten\")", "how many characters")]
+    #[case("title | date(\"%Q\")", "not a date format")]
+    #[case("title | default(other.field)", "have to be written out")]
+    #[case("title |", "not followed by a function")]
+    #[case("title | upper(", "never closed")]
+    #[case("title upper", "a `|` before the next function")]
+    #[case("\"unterminated", "never closed")]
+    #[case("title + 1", "`+` is not something")]
+    #[case("labels[0 | upper", "not closed")]
+    #[case("labels[?@ ~ 'bug']", "should compare a field")]
+    fn a_malformed_expression_is_refused_with_a_reason(
+        #[case] expression: &str,
+        #[case] reason: &str,
+    ) {
+        let err = Expression::parse(expression).expect_err("the expression should be refused");
+        assert!(
+            err.to_string().contains(reason),
+            "the error for `{expression}` should say '{reason}': {err}",
+        );
+    }
+
+    #[test]
+    fn a_bare_path_is_recognised_as_one() {
+        assert!(Expression::parse(" env.API_KEY ").unwrap().is_bare());
+        assert!(
+            !Expression::parse("env.API_KEY | default(\"x\")")
+                .unwrap()
+                .is_bare()
+        );
+        assert_eq!(
+            Expression::parse("env.API_KEY | default(\"x\")")
+                .unwrap()
+                .path(),
+            Some("env.API_KEY"),
+        );
+        assert_eq!(Expression::parse("\"literal\"").unwrap().path(), None);
+    }
+}
@@ -4,6 +4,7 @@ pub use crate::db::{Cache, KeyValueStore, Queue};
 pub use crate::filter::{Filter, Filterable};
 pub use crate::job::{Job, JobContext};
 pub use crate::jobs::CronJob;
+pub use crate::metrics::{CallCounted, SendCounted};
 pub use crate::services::Services;
 pub use crate::web::OAuth2RefreshToken;
 pub use crate::webhooks::WebhookEvent;
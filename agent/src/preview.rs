@@ -0,0 +1,634 @@
+//! Watching a workflow run without letting it change anything.
+//!
+//! A workflow's handler does three kinds of thing besides reading: it files
+//! work for a publisher by dispatching a job, it remembers how far it got by
+//! moving a collector's watermark, and occasionally it acts on a service
+//! directly. Seeing what a run *would* do means holding all three back while
+//! letting everything else — fetching the feed, evaluating the filter —
+//! happen for real.
+//!
+//! # Why a task-local rather than another `Services`
+//!
+//! The obvious way to hold writes back is a [`Services`] whose queue and store
+//! record instead of writing. It does not work here: the job registry is
+//! type-erased over [`crate::services::AppServices`], so a handler can only be
+//! reached with the real thing, and the watermark is written from inside the
+//! collector traits rather than by the handler. An observer installed for the
+//! duration of one future reaches all of those places without any of them
+//! taking a new parameter, and costs a single failed lookup when nobody is
+//! watching.
+//!
+//! The places that consult it are deliberately few: [`crate::job::Job::dispatch`],
+//! the collector traits, the filter evaluation in each workflow, and the
+//! handful of direct actions that go through [`suppress`]. A handler that
+//! changes something any other way has to route it through one of these, or a
+//! preview will quietly do it for real.
+//!
+//! # Replays
+//!
+//! A webhook workflow has nothing to fetch, so it is previewed by handing it
+//! the delivery its last run kept (see [`crate::runs`]). Two things about that
+//! delivery are different from a fresh one: its signature headers were redacted
+//! before it was stored, and the configuration worth trying it against is the
+//! one in the editor rather than the one saved. [`Options::replay`] carries
+//! that configuration, and [`is_replay`] is what lets a handler skip checking a
+//! signature it can no longer see.
+//!
+//! A kept delivery its owner asks to have run for real is different again. It
+//! is about to act, so it is not taken on trust: its credentials are kept
+//! sealed beside it (see [`crate::deliveries`]) and put back before it is
+//! queued, and [`is_redelivery`] tells a handler to check them as it would a
+//! fresh delivery's, forgiving only how old the signature is.
+
+use std::sync::{Arc, Mutex};
+
+use automate_api::{PreviewItem, SignatureVerdict};
+use chrono::Utc;
+
+use crate::prelude::*;
+use crate::services::AppServices;
+use crate::webhook_payload::PayloadFilter;
+
+tokio::task_local! {
+    static OBSERVER: Observer;
+}
+
+/// What an observed run is allowed to do.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Let collectors move their watermarks, so that the next scheduled run
+    /// starts where this one finished.
+    pub keep_state: bool,
+
+    /// Let the handler act on services directly, rather than only reporting
+    /// what it would have done.
+    pub live: bool,
+
+    /// Let the run do everything it would have done unwatched, noting it as it
+    /// goes.
+    ///
+    /// This is how a real delivery is kept for the inspector (see
+    /// [`crate::deliveries`]): the handler files its tasks and moves its state
+    /// exactly as it would have, and the observation is the account of what it
+    /// did rather than of what it would have done.
+    pub passive: bool,
+
+    /// The configuration to read a replayed delivery against, when the run is
+    /// a replay.
+    ///
+    /// Its presence is also the statement that the delivery is a kept one, and
+    /// that the handler should take it as already authenticated. Nothing
+    /// outside a preview sets this, and a preview never queues what it finds.
+    pub replay: Option<serde_json::Value>,
+
+    /// Check the delivery's signature without holding its age against it,
+    /// while reading it against the workflow's saved configuration.
+    ///
+    /// This is how a kept delivery an owner asked to have run again for real is
+    /// handled (see [`crate::webhooks::WebhookDelivery::replay_of`]). The
+    /// consumer sets it for such a delivery and for nothing else.
+    pub redelivery: bool,
+
+    /// A filter to evaluate in place of the workflow's own, when somebody is
+    /// trying one out before saving it.
+    ///
+    /// Each item it is evaluated against is reported with how every part of
+    /// it came out (see [`crate::filter::explain`]). It is parsed as a webhook
+    /// filter is, so it can address a delivery by path; a workflow whose items
+    /// are not JSON simply has nothing under a bracketed path.
+    pub filter: Option<Arc<PayloadFilter>>,
+}
+
+struct Observer {
+    options: Options,
+    observation: Mutex<Observation>,
+}
+
+/// Everything an observed run did, or would have done.
+#[derive(Debug, Default, Serialize)]
+pub struct Observation {
+    /// Each item the workflow collected, and whether its filter kept it.
+    pub items: Vec<PreviewItem>,
+
+    /// The jobs the run dispatched, which were captured rather than queued.
+    pub dispatched: Vec<Dispatched>,
+
+    /// Actions the run took on a service directly, described for a person.
+    pub effects: Vec<String>,
+
+    /// What the handler made of the delivery's signature, for one that checks
+    /// one.
+    pub signature: Option<SignatureVerdict>,
+
+    /// Why the run set what it was handed aside without acting on it, if it
+    /// did.
+    ///
+    /// A handler returns `Ok(())` for a delivery it refused or had no workflow
+    /// to hand to, because there is nothing a retry could change. That makes
+    /// it look like a run that worked, which it is not: it must not be
+    /// announced to the workflows following this one, whose subscribers would
+    /// otherwise act on a body nobody vouched for, nor count as the workflow
+    /// recovering.
+    pub discarded: Option<String>,
+}
+
+impl Observation {
+    /// Folds another observation into this one, in the order they happened.
+    pub fn merge(&mut self, other: Observation) {
+        self.items.extend(other.items);
+        self.dispatched.extend(other.dispatched);
+        self.effects.extend(other.effects);
+        self.signature = other.signature.or(self.signature.take());
+        self.discarded = other.discarded.or(self.discarded.take());
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dispatched {
+    /// The queue partition the job would have been sent to, which names the
+    /// handler that would have run it.
+    pub partition: String,
+
+    pub payload: serde_json::Value,
+
+    /// The idempotency key it would have been queued under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// How long it would have waited before running, for a delayed dispatch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_seconds: Option<i64>,
+}
+
+impl Dispatched {
+    /// Describes the job as the task it would have become.
+    ///
+    /// Every publisher that files a task carries its title and description
+    /// under those names, so they are read from the payload rather than by
+    /// knowing which publisher it was for.
+    ///
+    /// The payload is shown with its sensitive-looking fields blanked. It is
+    /// what the job would have been handed, and some publishers are handed the
+    /// credential they act with — the Spotify playlist job carries the
+    /// account's `access_token` — which has no business in an editor or a log.
+    pub fn describe(self) -> automate_api::PreviewTask {
+        let text = |field: &str| {
+            self.payload
+                .get(field)
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+
+        automate_api::PreviewTask {
+            title: text("title"),
+            description: text("description"),
+            job: self.partition,
+            payload: crate::runs::redact(&self.payload, &[]),
+        }
+    }
+}
+
+/// Runs a future with an observer installed, returning its result alongside
+/// what it did.
+pub async fn observe<F: Future>(options: Options, run: F) -> (F::Output, Observation) {
+    let observer = Observer {
+        options,
+        observation: Mutex::new(Observation::default()),
+    };
+
+    OBSERVER
+        .scope(observer, async move {
+            let output = run.await;
+            let observation = OBSERVER.with(|observer| {
+                std::mem::take(&mut *observer.observation.lock().expect("observer poisoned"))
+            });
+
+            (output, observation)
+        })
+        .await
+}
+
+/// Whether anybody is watching the current run.
+pub fn is_observing() -> bool {
+    OBSERVER.try_with(|_| ()).is_ok()
+}
+
+/// Whether the current run is a preview of a kept delivery, and so cannot
+/// check the credentials it arrived with.
+pub fn is_replay() -> bool {
+    OBSERVER
+        .try_with(|observer| observer.options.replay.is_some())
+        .unwrap_or(false)
+}
+
+/// Whether the current run is a kept delivery being sent again for real, whose
+/// signature is to be checked but not its age.
+pub fn is_redelivery() -> bool {
+    OBSERVER
+        .try_with(|observer| observer.options.redelivery)
+        .unwrap_or(false)
+}
+
+/// The configuration a replayed delivery should be read against.
+pub fn replay_config() -> Option<serde_json::Value> {
+    OBSERVER
+        .try_with(|observer| observer.options.replay.clone())
+        .ok()
+        .flatten()
+}
+
+/// Runs one job's handler in this process, with an observer installed.
+///
+/// The job never goes near the queue, which is what lets a preview answer in
+/// the same request that asked for it.
+pub async fn execute(
+    services: &AppServices,
+    options: Options,
+    partition: &str,
+    payload: &serde_json::Value,
+    key: Option<String>,
+) -> (Result<(), human_errors::Error>, Observation) {
+    let Some(handler) = crate::job::handler(partition) else {
+        return (
+            Err(human_errors::system(
+                format!("Nothing is registered to run jobs from the '{partition}' queue."),
+                &["Please report this issue to the dev team on GitHub."],
+            )),
+            Observation::default(),
+        );
+    };
+
+    let context = JobContext::new(services.clone(), Utc::now(), None, None).with_key(key);
+
+    observe(options, handler.handle(context, payload)).await
+}
+
+/// Evaluates a workflow's filter against an item, noting the outcome when the
+/// run is being watched.
+///
+/// `describe` is only called when somebody is watching, so a label that is
+/// expensive to build costs nothing on a scheduled run.
+pub fn matches(
+    filter: &Filter,
+    item: &impl Filterable,
+    describe: impl FnOnce() -> String,
+) -> Result<bool, human_errors::Error> {
+    let trial = OBSERVER
+        .try_with(|observer| observer.options.filter.clone())
+        .ok()
+        .flatten();
+    let filter = trial.as_deref().map_or(filter, |trial| &**trial);
+
+    let result = filter.matches(item);
+
+    let _ = OBSERVER.try_with(|observer| {
+        let observed = PreviewItem {
+            item: describe(),
+            matched: matches!(result, Ok(true)),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            clauses: if trial.is_some() {
+                crate::filter::explain(filter, item)
+            } else {
+                Vec::new()
+            },
+        };
+
+        record(observer, |observation| observation.items.push(observed));
+    });
+
+    result
+}
+
+/// Captures a dispatch when the run is being watched, returning `true` if the
+/// caller should not queue it.
+pub fn intercept<T: Serialize>(
+    partition: &str,
+    job: &T,
+    key: Option<&str>,
+    delay: Option<chrono::TimeDelta>,
+) -> Result<bool, human_errors::Error> {
+    if !is_observing() {
+        return Ok(false);
+    }
+
+    let payload = serde_json::to_value(job).wrap_system_err(
+        "We could not describe a job this workflow dispatched.",
+        &["Please report this issue to the dev team on GitHub."],
+    )?;
+
+    let dispatched = Dispatched {
+        partition: partition.to_string(),
+        payload,
+        key: key.map(str::to_string),
+        delay_seconds: delay.map(|delay| delay.num_seconds()),
+    };
+
+    // A passive observer only takes note; the job is still queued, by the
+    // caller, as it would have been unwatched.
+    Ok(OBSERVER
+        .try_with(|observer| {
+            record(observer, |observation| {
+                observation.dispatched.push(dispatched)
+            });
+            !observer.options.passive
+        })
+        .unwrap_or(false))
+}
+
+/// Whether a collector may move its watermark.
+///
+/// Always true for an unwatched or [passive](Options::passive) run. A watched
+/// one otherwise only keeps its state when it was asked to, so that looking at
+/// what a workflow would collect does not also decide what its next real run
+/// skips.
+pub fn keeps_state() -> bool {
+    OBSERVER
+        .try_with(|observer| observer.options.keep_state || observer.options.passive)
+        .unwrap_or(true)
+}
+
+/// Notes an action a handler is about to take on a service directly, returning
+/// `true` if it should be skipped.
+///
+/// Only a watched run that is neither [live](Options::live) nor
+/// [passive](Options::passive) skips anything; the others still have the action
+/// noted, so the report says what was done.
+pub fn suppress(describe: impl FnOnce() -> String) -> bool {
+    OBSERVER
+        .try_with(|observer| {
+            let effect = describe();
+            record(observer, |observation| observation.effects.push(effect));
+            !observer.options.live && !observer.options.passive
+        })
+        .unwrap_or(false)
+}
+
+/// Notes that a delivery's signature checked out, when somebody is watching.
+pub fn verified() {
+    note_signature(SignatureVerdict::Verified);
+}
+
+/// Notes that a delivery was turned away by its signature check, and why, when
+/// somebody is watching.
+///
+/// Called at each place a handler refuses a delivery, with the reason it gives
+/// in its log line, so the inspector can say which deliveries were refused
+/// and why without anybody reading the logs.
+pub fn rejected(reason: impl ToString) {
+    let reason = reason.to_string();
+    discarded(&reason);
+    note_signature(SignatureVerdict::Rejected { reason });
+}
+
+/// Notes that the run is setting what it was handed aside, and why, when
+/// somebody is watching.
+///
+/// Called wherever a handler returns without acting on a delivery or event:
+/// one for a workflow that has been paused, snoozed or deleted, or one whose
+/// body could not be read. A refused signature is noted by [`rejected`].
+pub fn discarded(reason: impl ToString) {
+    let reason = reason.to_string();
+    let _ = OBSERVER.try_with(|observer| {
+        record(observer, |observation| {
+            observation.discarded.get_or_insert(reason);
+        });
+    });
+}
+
+fn note_signature(verdict: SignatureVerdict) {
+    let _ = OBSERVER.try_with(|observer| {
+        record(observer, |observation| {
+            observation.signature = Some(verdict)
+        });
+    });
+}
+
+fn record(observer: &Observer, f: impl FnOnce(&mut Observation)) {
+    // A panic while holding this lock would already have failed the run being
+    // observed, so there is nothing left worth protecting.
+    if let Ok(mut observation) = observer.observation.lock() {
+        f(&mut observation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    impl Filterable for Named {
+        fn get(&self, key: &str) -> crate::filter::FilterValue<'_> {
+            match key {
+                "name" => self.0.into(),
+                _ => crate::filter::FilterValue::Null,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn nothing_is_held_back_when_nobody_is_watching() {
+        assert!(!is_observing());
+        assert!(keeps_state());
+        assert!(!suppress(|| unreachable!("only described when watched")));
+        assert!(!intercept("test", &"job", None, None).unwrap());
+        assert!(!is_replay());
+        assert!(!is_redelivery());
+    }
+
+    #[tokio::test]
+    async fn a_replay_carries_the_configuration_it_should_be_read_against() {
+        let options = Options {
+            replay: Some(serde_json::json!({ "filter": "true" })),
+            ..Default::default()
+        };
+
+        let ((), observation) = observe(options, async {
+            assert!(is_replay());
+            assert_eq!(
+                replay_config(),
+                Some(serde_json::json!({ "filter": "true" })),
+                "the handler should see the configuration from the editor, not the saved one"
+            );
+        })
+        .await;
+
+        assert!(observation.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_redelivery_is_checked_and_read_against_the_saved_configuration() {
+        let options = Options {
+            passive: true,
+            redelivery: true,
+            ..Default::default()
+        };
+
+        observe(options, async {
+            assert!(
+                !is_replay(),
+                "a delivery about to act must have its signature checked"
+            );
+            assert!(is_redelivery());
+            assert_eq!(
+                replay_config(),
+                None,
+                "a redelivery should be read against the workflow as it is saved"
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn a_watched_run_reports_what_it_would_have_done() {
+        let filter = Filter::new(r#"name == "kept""#).unwrap();
+
+        let ((), observation) = observe(Options::default(), async {
+            assert!(matches(&filter, &Named("kept"), || "kept".into()).unwrap());
+            assert!(!matches(&filter, &Named("dropped"), || "dropped".into()).unwrap());
+
+            assert!(
+                intercept(
+                    "test",
+                    &serde_json::json!({ "title": "kept" }),
+                    Some("k"),
+                    None
+                )
+                .unwrap(),
+                "a dispatch during a watched run should be captured rather than queued"
+            );
+            assert!(!keeps_state());
+            assert!(suppress(|| "Mark something as done".into()));
+        })
+        .await;
+
+        assert_eq!(observation.items.len(), 2);
+        assert!(observation.items[0].matched);
+        assert!(!observation.items[1].matched);
+        assert_eq!(observation.dispatched.len(), 1);
+        assert_eq!(observation.dispatched[0].key.as_deref(), Some("k"));
+        assert_eq!(
+            observation.effects,
+            vec!["Mark something as done".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_previewed_spotify_dispatch_does_not_show_the_access_token() {
+        let job = crate::publishers::SpotifyAddToPlaylistPayload {
+            account_id: "account".into(),
+            name: "Top tracks of 2026".into(),
+            description: Some("The year in music".into()),
+            track_uris: vec!["spotify:track:1".into()],
+            access_token: OAuth2RefreshToken::new(
+                "the-access-token".into(),
+                "the-refresh-token".into(),
+                Utc::now(),
+            ),
+        };
+
+        let ((), observation) = observe(Options::default(), async {
+            assert!(intercept("spotify/add-to-playlist", &job, None, None).unwrap());
+        })
+        .await;
+
+        let task = observation.dispatched[0].clone().describe();
+        assert_eq!(task.job, "spotify/add-to-playlist");
+        assert_eq!(task.description.as_deref(), Some("The year in music"));
+        assert_eq!(task.payload["name"], "Top tracks of 2026");
+        assert_eq!(task.payload["access_token"], crate::runs::REDACTED);
+
+        let shown = task.payload.to_string();
+        assert!(
+            !shown.contains("the-access-token") && !shown.contains("the-refresh-token"),
+            "the credential the job acts with should not be shown: {shown}"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_filter_being_tried_out_is_used_in_place_of_the_workflows_own() {
+        let own = Filter::new(r#"name == "kept""#).unwrap();
+        let options = Options {
+            filter: Some(Arc::new(
+                PayloadFilter::new(r#"name == "dropped" || name == "other""#).unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        let ((), observation) = observe(options, async {
+            assert!(
+                !matches(&own, &Named("kept"), || "kept".into()).unwrap(),
+                "the filter being tried should decide, not the saved one"
+            );
+            assert!(matches(&own, &Named("dropped"), || "dropped".into()).unwrap());
+        })
+        .await;
+
+        assert!(!observation.items[0].matched);
+        assert!(observation.items[1].matched);
+
+        let clauses: Vec<_> = observation.items[1]
+            .clauses
+            .iter()
+            .map(|clause| (clause.expression.as_str(), clause.matched))
+            .collect();
+        assert_eq!(
+            clauses,
+            vec![
+                (r#"name == "dropped" || name == "other""#, true),
+                (r#"name == "dropped""#, true),
+                (r#"name == "other""#, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_live_run_acts_but_still_says_what_it_did() {
+        let options = Options {
+            keep_state: true,
+            live: true,
+            ..Default::default()
+        };
+
+        let ((), observation) = observe(options, async {
+            assert!(keeps_state());
+            assert!(!suppress(|| "Mark something as done".into()));
+        })
+        .await;
+
+        assert_eq!(observation.effects.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_passive_run_does_everything_it_would_have_done_and_says_so() {
+        let options = Options {
+            passive: true,
+            ..Default::default()
+        };
+
+        let ((), observation) = observe(options, async {
+            assert!(keeps_state());
+            assert!(!suppress(|| "Mark something as done".into()));
+            assert!(
+                !intercept("test", &serde_json::json!({ "title": "kept" }), None, None).unwrap(),
+                "a passive observer should leave the job for the caller to queue"
+            );
+            rejected("the signature did not match");
+        })
+        .await;
+
+        assert_eq!(observation.dispatched.len(), 1);
+        assert_eq!(observation.effects.len(), 1);
+        assert_eq!(
+            observation.discarded.as_deref(),
+            Some("the signature did not match"),
+            "a refused delivery was not acted on, whatever the handler returned",
+        );
+        assert_eq!(
+            observation.signature,
+            Some(SignatureVerdict::Rejected {
+                reason: "the signature did not match".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,668 @@
+//! Holding a workflow's tasks back and filing them as one periodic digest.
+//!
+//! A workflow that watches something busy files one task per item, which is
+//! right for an alert and wrong for a feed: twenty release tasks waiting on a
+//! Monday morning is how a useful workflow ends up paused. A digest keeps the
+//! items in a buffer instead, one per workflow, and files them as a single task
+//! listing all of them when the digest's own schedule comes round.
+//!
+//! # Quiet hours
+//!
+//! A workflow that files as things happen can still keep the night free. Tasks
+//! it would have filed during its quiet hours go into the same buffer and
+//! arrive with the next digest, so nothing is lost and nothing buzzes at 3am.
+//!
+//! # How the digest is published
+//!
+//! Each time an item is held, a [`TodoistPublishDigest`] job is queued for the
+//! next time the digest schedule fires, keyed by the workflow so that holding a
+//! second item replaces the first's rather than adding another. That keeps the
+//! schedule per-workflow and per-account without anything having to walk every
+//! buffer looking for ones that are due, and means a buffer whose flush was
+//! somehow lost is re-armed by the next item that lands in it.
+//!
+//! The buffer carries its own copy of what to publish, refreshed with each item,
+//! so a digest still goes out as configured if the workflow is edited to stop
+//! using one while items are waiting.
+//!
+//! # Why each item is kept on its own
+//!
+//! Deliveries to one workflow are handled alongside each other, and a buffer
+//! that was read, appended to and written back would keep whichever of two
+//! items was written last. So each held item is written under a key of its own
+//! in a partition for the workflow, named for when it arrived so that the digest
+//! lists them in order, and publishing removes exactly the items it listed. An
+//! item that lands while a digest is going out is not one of them, and waits
+//! for the next.
+
+use std::fmt::Display;
+
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::publishers::{
+    TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate, TodoistTarget,
+};
+use crate::variables::{VariableStore, Variables};
+use crate::webhook_payload::Template;
+
+/// Where each workflow's digest is kept, keyed as the workflow is. Its items
+/// are in a partition of their own beneath this one (see [`items_partition`]).
+pub const DIGEST_PARTITION: &str = "todoist/digest";
+
+/// The most items a single digest lists.
+///
+/// A digest is meant to be read, and one that has been filling up for a week
+/// behind a broken schedule should not become a task nobody can open. Anything
+/// beyond this is counted and mentioned rather than listed.
+const MAX_DIGEST_ITEMS: usize = 100;
+
+/// Whether, and how, a workflow's tasks are gathered into a digest.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Digest {
+    /// Whether every task waits for the digest, rather than only the ones that
+    /// fall in the quiet hours.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// When the digest is filed, in UTC like every other schedule.
+    #[serde(default = "default_schedule")]
+    pub schedule: croner::Cron,
+
+    /// The digest task's title, rendered with the workflow's `name` and the
+    /// `count` of items it holds.
+    #[serde(default = "default_title")]
+    pub title: Template,
+
+    /// One line of the digest's description, rendered for each item with its
+    /// `title`, `description` and `link`.
+    #[serde(default = "default_item")]
+    pub item: Template,
+
+    /// A daily window, in UTC, during which tasks are held for the next digest
+    /// even when the digest is otherwise off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: default_schedule(),
+            title: default_title(),
+            item: default_item(),
+            quiet_hours: None,
+        }
+    }
+}
+
+fn default_schedule() -> croner::Cron {
+    <croner::Cron as std::str::FromStr>::from_str(DEFAULT_SCHEDULE)
+        .expect("the default digest schedule should parse")
+}
+
+const DEFAULT_SCHEDULE: &str = "0 8 * * *";
+
+fn default_title() -> Template {
+    Template::new("${{ name }}: ${{ count }} new").expect("the default digest title should parse")
+}
+
+fn default_item() -> Template {
+    Template::new("- ${{ title }}").expect("the default digest line should parse")
+}
+
+impl Digest {
+    /// Whether a task filed at `at` should wait for the digest.
+    pub fn holds(&self, at: DateTime<Utc>) -> bool {
+        self.enabled
+            || self
+                .quiet_hours
+                .is_some_and(|quiet_hours| quiet_hours.contains(at))
+    }
+}
+
+/// A daily window such as `22:00-07:00`, which may run past midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Whether `at` falls inside the window. The start is inside it and the end
+    /// is not, so `22:00-07:00` has ended by seven.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.time();
+
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for QuietHours {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("'{value}' is not a window of quiet hours such as 22:00-07:00");
+
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M");
+
+        Ok(Self {
+            start: parse(start).map_err(|_| invalid())?,
+            end: parse(end).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl From<QuietHours> for String {
+    fn from(quiet_hours: QuietHours) -> Self {
+        format!(
+            "{}-{}",
+            quiet_hours.start.format("%H:%M"),
+            quiet_hours.end.format("%H:%M")
+        )
+    }
+}
+
+/// Which workflow a task came from, as its digest needs to know.
+#[derive(Clone, Copy)]
+pub struct DigestSource<'a> {
+    /// What the workflow's buffer is kept under. The workflow's identifier where
+    /// there is one, so that two workflows never share a digest.
+    pub key: &'a str,
+
+    /// What to call the workflow in the digest's title.
+    pub name: &'a str,
+}
+
+/// Files a task now, or holds it for the workflow's next digest.
+///
+/// `link` is offered to the digest's item template, and is otherwise unused;
+/// the task filed when nothing is held is exactly the one that was passed in.
+pub async fn file(
+    services: &(impl Services + Send + Sync + 'static),
+    digest: &Digest,
+    source: DigestSource<'_>,
+    task: TodoistCreateTaskPayload,
+    link: Option<&str>,
+) -> Result<(), human_errors::Error> {
+    let now = Utc::now();
+
+    if !digest.holds(now) {
+        return TodoistCreateTask::dispatch(task, None, services).await;
+    }
+
+    if crate::preview::suppress(|| format!("Held '{}' for the next digest.", task.title)) {
+        return Ok(());
+    }
+
+    // Written before the item, and never removed, so that an item is never
+    // found without something to publish it as.
+    services
+        .kv()
+        .set(
+            DIGEST_PARTITION,
+            source.key.to_string(),
+            DigestBuffer {
+                name: source.name.to_string(),
+                digest: digest.clone(),
+                target: task.config.clone(),
+            },
+        )
+        .await?;
+
+    services
+        .kv()
+        .set(
+            items_partition(source.key),
+            item_key(now),
+            DigestItem {
+                title: task.title,
+                description: task.description,
+                link: link.map(str::to_string),
+            },
+        )
+        .await?;
+
+    arm(services, source.key, &digest.schedule, now).await
+}
+
+/// Where the items held for the digest kept under `key` are, one to a key.
+fn items_partition(key: &str) -> String {
+    format!("{DIGEST_PARTITION}/{key}")
+}
+
+/// The key of an item held at `at`, which sorts in the order items arrived and
+/// is never shared by two of them.
+fn item_key(at: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}",
+        at.format("%Y%m%dT%H%M%S%.9fZ"),
+        uuid::Uuid::new_v4()
+    )
+}
+
+/// Queues the buffer's publication for the next time its schedule fires.
+async fn arm(
+    services: &(impl Services + Send + Sync + 'static),
+    key: &str,
+    schedule: &croner::Cron,
+    now: DateTime<Utc>,
+) -> Result<(), human_errors::Error> {
+    let next = schedule.find_next_occurrence(&now, false).wrap_user_err(
+        format!("We could not work out when the digest for '{key}' is next due."),
+        &["Edit the workflow and give its digest a schedule that fires again, such as '0 8 * * *'."],
+    )?;
+
+    TodoistPublishDigest::dispatch_delayed(
+        DigestFlush {
+            key: key.to_string(),
+        },
+        Some(key.to_string().into()),
+        next - now,
+        services,
+    )
+    .await
+}
+
+/// What a workflow's held items are published as, refreshed with each item.
+#[derive(Serialize, Deserialize)]
+struct DigestBuffer {
+    name: String,
+
+    #[serde(default)]
+    digest: Digest,
+
+    target: TodoistTarget,
+}
+
+/// One held task, as the digest's item template sees it.
+#[derive(Serialize, Deserialize)]
+struct DigestItem {
+    title: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+}
+
+impl DigestBuffer {
+    /// The single task `items` are published as, in the order they arrived.
+    fn task(
+        &self,
+        items: &[DigestItem],
+        variables: &Variables,
+    ) -> Result<TodoistCreateTaskPayload, human_errors::Error> {
+        let context = serde_json::json!({
+            "name": self.name,
+            "count": items.len(),
+        });
+
+        let omitted = items.len().saturating_sub(MAX_DIGEST_ITEMS);
+        let mut lines = items
+            .iter()
+            .take(MAX_DIGEST_ITEMS)
+            .map(|item| {
+                crate::webhook_payload::render(
+                    &self.digest.item,
+                    &serde_json::json!(item),
+                    variables,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if omitted > 0 {
+            lines.push(format!("…and {omitted} more."));
+        }
+
+        Ok(TodoistCreateTaskPayload {
+            title: crate::webhook_payload::render(&self.digest.title, &context, variables)?,
+            description: Some(lines.join("\n")),
+            due: TodoistDueDate::Today,
+            config: self.target.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Which buffer is due to be published.
+#[derive(Serialize, Deserialize)]
+pub struct DigestFlush {
+    pub key: String,
+}
+
+impl Display for DigestFlush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "digest/{}", self.key)
+    }
+}
+
+pub struct TodoistPublishDigest;
+
+crate::register_job!(TodoistPublishDigest);
+
+impl Job for TodoistPublishDigest {
+    type JobType = DigestFlush;
+
+    fn partition() -> &'static str {
+        "todoist/publish-digest"
+    }
+
+    #[instrument("publishers.todoist_digest.handle", skip(self, ctx, job), fields(job = %job), err(Display))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        let Some(buffer) = services
+            .kv()
+            .get::<DigestBuffer>(DIGEST_PARTITION, job.key.clone())
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let partition = items_partition(&job.key);
+        let mut held = services.kv().list::<DigestItem>(partition.clone()).await?;
+        if held.is_empty() {
+            return Ok(());
+        }
+        held.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let (keys, items): (Vec<_>, Vec<_>) = held.into_iter().unzip();
+        let variables = VariableStore::for_services(services).load().await?;
+        TodoistCreateTask::dispatch(buffer.task(&items, &variables)?, None, services).await?;
+
+        for key in keys {
+            services.kv().remove(partition.clone(), key).await?;
+        }
+
+        // Items held while this was being published were not listed, and are
+        // kept for the next digest.
+        if !services
+            .kv()
+            .list::<serde_json::Value>(partition)
+            .await?
+            .is_empty()
+        {
+            arm(services, &job.key, &buffer.digest.schedule, Utc::now()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The fields that configure a workflow's [`Digest`].
+///
+/// Expects the configuration to hold it as `digest`, the same way
+/// [`crate::todoist_target_fields!`] expects a `todoist`.
+#[macro_export]
+macro_rules! digest_fields {
+    ($ty:ty) => {{
+        let enabled = automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: digest.enabled),
+            "Send as a digest",
+            automate_api::FieldKind::Boolean,
+        )
+        .with_help(
+            "Gather tasks into one that lists them all, filed on the digest schedule, instead of one task each.",
+        )
+        .with_default(false);
+
+        let schedule = automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: digest.schedule),
+            "Digest schedule",
+            automate_api::FieldKind::Cron,
+        )
+        .with_help("When the digest is filed, in UTC.")
+        .with_default("0 8 * * *");
+
+        let title = automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: digest.title),
+            "Digest title",
+            automate_api::FieldKind::Text {
+                placeholder: Some("${{ name }}: ${{ count }} new".into()),
+            },
+        )
+        .with_help("Write ${{ name }} for this workflow's name and ${{ count }} for how many items it lists.")
+        .with_default("${{ name }}: ${{ count }} new");
+
+        let item = automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: digest.item),
+            "Digest line",
+            automate_api::FieldKind::Text {
+                placeholder: Some("- ${{ title }}".into()),
+            },
+        )
+        .with_help(
+            "One line per item. Write ${{ title }}, ${{ link }} or ${{ description }} for the task each item would have been.",
+        )
+        .with_default("- ${{ title }}");
+
+        let quiet_hours = automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: digest.quiet_hours),
+            "Quiet hours",
+            automate_api::FieldKind::Text {
+                placeholder: Some("22:00-07:00".into()),
+            },
+        )
+        .with_help(
+            "Optional. Tasks that would be filed during these hours, in UTC, wait for the next digest instead.",
+        );
+
+        [enabled, schedule, title, item, quiet_hours]
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2024-04-01T{time}:00Z").parse().unwrap()
+    }
+
+    fn task(title: &str) -> TodoistCreateTaskPayload {
+        TodoistCreateTaskPayload {
+            title: title.to_string(),
+            due: TodoistDueDate::Today,
+            ..Default::default()
+        }
+    }
+
+    fn source() -> DigestSource<'static> {
+        DigestSource {
+            key: "copper-tiger-canyon",
+            name: "Releases",
+        }
+    }
+
+    async fn queued(
+        services: &(impl Services + Send + Sync + 'static),
+        partition: &str,
+    ) -> Vec<crate::db::PeekedMessage<serde_json::Value>> {
+        services.queue().peek(partition, 100).await.unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_can_run_past_midnight() {
+        let quiet: QuietHours = serde_json::from_value(serde_json::json!("22:00-07:00")).unwrap();
+
+        assert!(quiet.contains(at("23:30")));
+        assert!(quiet.contains(at("03:00")));
+        assert!(
+            !quiet.contains(at("07:00")),
+            "the window should have ended by the time it says it ends",
+        );
+        assert!(!quiet.contains(at("12:00")));
+    }
+
+    #[test]
+    fn quiet_hours_that_do_not_parse_are_refused() {
+        let err = serde_json::from_value::<QuietHours>(serde_json::json!("late")).unwrap_err();
+        assert!(
+            err.to_string().contains("22:00-07:00"),
+            "the error should show what a window looks like: {err}",
+        );
+    }
+
+    #[test]
+    fn a_digest_left_off_still_holds_what_falls_in_its_quiet_hours() {
+        let digest = Digest {
+            quiet_hours: Some("22:00-07:00".to_string().try_into().unwrap()),
+            ..Default::default()
+        };
+
+        assert!(digest.holds(at("23:00")));
+        assert!(!digest.holds(at("09:00")));
+    }
+
+    #[tokio::test]
+    async fn a_task_is_filed_straight_away_when_nothing_holds_it() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+
+        file(
+            &services,
+            &Digest::default(),
+            source(),
+            task("v1.2.0"),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(queued(&services, "todoist/create-task").await.len(), 1);
+        assert!(queued(&services, "todoist/publish-digest").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn held_tasks_are_published_together_as_one() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let digest = Digest {
+            enabled: true,
+            item: Template::new("- [${{ title }}](${{ link }})").unwrap(),
+            ..Default::default()
+        };
+
+        for version in ["v1.2.0", "v1.3.0"] {
+            let link = format!("https://example.com/{version}");
+            file(&services, &digest, source(), task(version), Some(&link))
+                .await
+                .unwrap();
+        }
+
+        assert!(
+            queued(&services, "todoist/create-task").await.is_empty(),
+            "a held task should not be filed on its own",
+        );
+        assert_eq!(
+            queued(&services, "todoist/publish-digest").await.len(),
+            1,
+            "holding a second item should move the one publication, not queue another",
+        );
+
+        TodoistPublishDigest
+            .handle(
+                JobContext::new(services.clone(), Utc::now(), None, None),
+                &DigestFlush {
+                    key: source().key.to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let filed = queued(&services, "todoist/create-task").await;
+        assert_eq!(filed.len(), 1, "the digest should be a single task");
+        assert_eq!(filed[0].payload["title"], "Releases: 2 new");
+        assert_eq!(
+            filed[0].payload["description"],
+            "- [v1.2.0](https://example.com/v1.2.0)\n- [v1.3.0](https://example.com/v1.3.0)",
+        );
+
+        let remaining = services
+            .kv()
+            .list::<serde_json::Value>(items_partition(source().key))
+            .await
+            .unwrap();
+        assert!(
+            remaining.is_empty(),
+            "a published digest should not list the same items again next time",
+        );
+    }
+
+    #[tokio::test]
+    async fn items_held_at_the_same_time_are_all_published() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let digest = Digest {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let titles = (0..10).map(|n| format!("v1.{n}.0")).collect::<Vec<_>>();
+        futures::future::try_join_all(
+            titles
+                .iter()
+                .map(|title| file(&services, &digest, source(), task(title), None)),
+        )
+        .await
+        .unwrap();
+
+        TodoistPublishDigest
+            .handle(
+                JobContext::new(services.clone(), Utc::now(), None, None),
+                &DigestFlush {
+                    key: source().key.to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let filed = queued(&services, "todoist/create-task").await;
+        assert_eq!(filed[0].payload["title"], "Releases: 10 new");
+        for title in &titles {
+            assert!(
+                filed[0].payload["description"]
+                    .as_str()
+                    .unwrap()
+                    .contains(&format!("- {title}")),
+                "{title} should be listed in the digest",
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn a_digest_with_nothing_in_it_is_not_published() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+
+        TodoistPublishDigest
+            .handle(
+                JobContext::new(services.clone(), Utc::now(), None, None),
+                &DigestFlush {
+                    key: "nothing-held-here".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(queued(&services, "todoist/create-task").await.is_empty());
+    }
+}
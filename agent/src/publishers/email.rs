@@ -0,0 +1,122 @@
+//! Sending mail through the installation's own server.
+//!
+//! Like [`super::HttpPost`], this delivers to an address somebody typed in
+//! rather than to an account they linked, and it is a publisher for the same
+//! reason: a mail server that is briefly refusing connections is retried by the
+//! queue rather than costing whatever asked for the message its turn.
+//!
+//! The server is the operator's, configured once under `[mail]`, rather than
+//! one per account. Somebody choosing where to be told about a broken workflow
+//! should not need SMTP credentials to do it.
+
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::config::{MailConfig, MailSecurity};
+use crate::prelude::*;
+
+/// How long the server has to accept a message before it is given up and
+/// retried.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SendEmailPayload {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub struct SendEmail;
+
+crate::register_job!(SendEmail);
+
+impl Job for SendEmail {
+    type JobType = SendEmailPayload;
+
+    fn partition() -> &'static str {
+        "email/send"
+    }
+
+    #[instrument("publishers.email.handle", skip(self, ctx, job), err(Display))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let config = ctx.services().config();
+        let Some(mail) = config.mail.as_ref() else {
+            return Err(human_errors::user(
+                format!(
+                    "We cannot send mail to {}, because this installation has no mail server.",
+                    job.to
+                ),
+                &[
+                    "Ask whoever runs it to configure one under [mail], or choose somewhere else to be told.",
+                ],
+            ));
+        };
+
+        let message = Message::builder()
+            .from(sender(mail)?)
+            .to(recipient(&job.to)?)
+            .subject(&job.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(job.body.clone())
+            .wrap_system_err(
+                format!("We could not put together a message for {}.", job.to),
+                &["Please report this issue to us on GitHub so that we can investigate."],
+            )?;
+
+        transport(mail)?.send(message).await.wrap_user_err(
+            format!("{} did not accept a message for {}.", mail.host, job.to),
+            &[
+                "Check the [mail] settings, and that the server is up and accepts mail from this host.",
+                "We will try again, in case it was only briefly unavailable.",
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Reads an address somebody typed in as somewhere mail can be sent.
+pub fn recipient(address: &str) -> Result<Mailbox, human_errors::Error> {
+    address.trim().parse().wrap_user_err(
+        format!("'{address}' is not an email address we can send to."),
+        &["Give a single address, such as ops@example.com."],
+    )
+}
+
+fn sender(mail: &MailConfig) -> Result<Mailbox, human_errors::Error> {
+    mail.from.parse().wrap_user_err(
+        format!("'{}' is not an address we can send mail from.", mail.from),
+        &["Set [mail] from to an address, such as \"Automate <automate@example.com>\"."],
+    )
+}
+
+fn transport(mail: &MailConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, human_errors::Error> {
+    let builder = match mail.security {
+        MailSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&mail.host),
+        MailSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&mail.host),
+        MailSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            &mail.host,
+        )),
+    }
+    .wrap_user_err(
+        format!("We could not set up a secure connection to {}.", mail.host),
+        &["Check that [mail] host is the server's name rather than its address."],
+    )?;
+
+    let builder = builder.port(mail.port()).timeout(Some(TIMEOUT));
+    let builder = match (&mail.username, &mail.password) {
+        (Some(username), password) => builder.credentials(Credentials::new(
+            username.clone(),
+            password.clone().unwrap_or_default(),
+        )),
+        (None, _) => builder,
+    };
+
+    Ok(builder.build())
+}
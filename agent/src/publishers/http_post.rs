@@ -0,0 +1,83 @@
+//! Posting to somebody else's webhook.
+//!
+//! Every other publisher files something in an account the owner linked. This
+//! one sends a request to an address they typed in, which is what "also tell
+//! the incident channel" needs when the incident channel is a chat webhook or a
+//! paging service rather than a Todoist project.
+//!
+//! It is a publisher rather than a call made from the workflow for the same
+//! reason the Todoist ones are: the workflow's run is over once it has decided
+//! what to send, and a far end that is briefly down is retried by the queue
+//! rather than costing the workflow its run.
+
+use crate::prelude::*;
+
+/// How long the far end has to answer before the post is given up and retried.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct HttpPostPayload {
+    pub url: String,
+    pub body: String,
+    pub content_type: String,
+}
+
+pub struct HttpPost;
+
+crate::register_job!(HttpPost);
+
+impl Job for HttpPost {
+    type JobType = HttpPostPayload;
+
+    fn partition() -> &'static str {
+        "http/post"
+    }
+
+    #[instrument("publishers.http_post.handle", skip(self, ctx, job), err(Display))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let url = reqwest::Url::parse(&job.url).wrap_user_err(
+            format!("'{}' is not an address we can post to.", job.url),
+            &["Edit the workflow and give it the full address, starting with https://."],
+        )?;
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(human_errors::user(
+                format!("We only post to http and https addresses, not '{url}'."),
+                &["Edit the workflow and give it an address starting with https://."],
+            ));
+        }
+
+        let host = url.host_str().unwrap_or_default().to_string();
+
+        let response = ctx
+            .services()
+            .http_client()
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, &job.content_type)
+            .body(job.body.clone())
+            .timeout(TIMEOUT)
+            .send_counted("http-post")
+            .await
+            .wrap_user_err(
+                format!("We could not reach {host} to post to it."),
+                &["Check that the address is right and that the service behind it is up."],
+            )?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(human_errors::user(
+                format!("{host} refused what we posted to it, answering {status}."),
+                &[
+                    "Check that the address is right, and that the body is what the service expects.",
+                    "We will try again, in case it was only briefly unavailable.",
+                ],
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,6 @@
+pub mod digest;
+mod email;
+mod http_post;
 pub mod spotify;
 mod spotify_add_to_playlist;
 pub mod todoist;
@@ -5,6 +8,8 @@ mod todoist_complete;
 mod todoist_create;
 mod todoist_upsert;
 
+pub use email::{SendEmail, SendEmailPayload, recipient as email_recipient};
+pub use http_post::{HttpPost, HttpPostPayload};
 pub use spotify::SpotifyClient;
 pub use spotify_add_to_playlist::{SpotifyAddToPlaylist, SpotifyAddToPlaylistPayload};
 
@@ -211,10 +211,10 @@ impl SpotifyClient {
             .build()
             .or_system_err(&["Report this issue to the development team on GitHub."])?;
 
-        let resp = self
-            .client
-            .execute(req)
-            .await
+        let resp = self.client.execute(req).await;
+        crate::metrics::global().request("spotify", resp.as_ref().ok().map(|r| r.status()));
+
+        let resp = resp
             .or_user_err(&["Make sure that your internet connection is working."])?
             .error_for_status()
             .wrap_user_err(
@@ -154,7 +154,7 @@ impl TodoistClient {
                         let mut cursor = None;
 
                         loop {
-                            let response = client.get_projects(None, cursor).await.wrap_user_err(
+                            let response = client.get_projects(None, cursor).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                                 "Failed to fetch Todoist projects.",
                                 &[
                                     "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -201,10 +201,14 @@ impl TodoistClient {
                         let mut cursor = None;
 
                         loop {
-                            let response = client.get_sections(None, cursor).await.wrap_user_err(
-                                "Failed to fetch Todoist sections.",
-                                &["Check that your Todoist API token is valid."],
-                            )?;
+                            let response = client
+                                .get_sections(None, cursor)
+                                .call_counted(TODOIST_PROVIDER)
+                                .await
+                                .wrap_user_err(
+                                    "Failed to fetch Todoist sections.",
+                                    &["Check that your Todoist API token is valid."],
+                                )?;
 
                             sections.extend(response.results);
                             cursor = response.next_cursor;
@@ -249,7 +253,7 @@ impl TodoistClient {
                       let mut cursor = None;
 
                       loop {
-                        let response = client.get_projects(None, cursor).await.wrap_user_err(
+                        let response = client.get_projects(None, cursor).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                             "Failed to fetch Todoist projects.",
                             &[
                                 "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -308,7 +312,7 @@ impl TodoistClient {
                           let mut sections = Vec::new();
                           let mut cursor = None;
                           loop {
-                            let response = client.get_sections(None, cursor).await.wrap_user_err(
+                            let response = client.get_sections(None, cursor).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                                 "Failed to fetch Todoist sections.",
                                 &[
                                     "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -1,4 +1,7 @@
-use crate::{prelude::*, publishers::TodoistClient};
+use crate::{
+    prelude::*,
+    publishers::{TODOIST_PROVIDER, TodoistClient},
+};
 use serde::{Deserialize, Serialize};
 
 use super::todoist_upsert::TodoistUpsertTaskState;
@@ -41,7 +44,7 @@ impl Job for TodoistCompleteTask {
             .get::<TodoistUpsertTaskState>("todoist/task", cache_key.clone())
             .await?
         {
-            client.0.complete_task(&existing_task.id).await.wrap_user_err(
+            client.0.complete_task(&existing_task.id).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                 format!("Failed to complete Todoist task '{}'.", existing_task.id),
                 &[
                     "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -1,4 +1,7 @@
-use crate::{prelude::*, publishers::TodoistClient};
+use crate::{
+    prelude::*,
+    publishers::{TODOIST_PROVIDER, TodoistClient},
+};
 use serde::{Deserialize, Serialize};
 
 use super::TodoistDueDate;
@@ -63,6 +66,7 @@ impl Job for TodoistCreateTask {
                 priority: job.priority,
                 ..Default::default()
             })
+            .call_counted(TODOIST_PROVIDER)
             .await
             .wrap_user_err(
                 format!("Failed to create Todoist task '{}'.", job.title),
@@ -1,4 +1,7 @@
-use crate::{prelude::*, publishers::TodoistClient};
+use crate::{
+    prelude::*,
+    publishers::{TODOIST_PROVIDER, TodoistClient},
+};
 use serde::{Deserialize, Serialize};
 
 use super::TodoistDueDate;
@@ -67,7 +70,7 @@ impl Job for TodoistUpsertTask {
                 duration_unit: job.duration.map(|_| "minute".into()),
                 priority: job.priority,
                 ..Default::default()
-            }).await.wrap_user_err(
+            }).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                 format!("Failed to update Todoist task '{}'.", job.title),
                 &[
                     "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -76,7 +79,7 @@ impl Job for TodoistUpsertTask {
             )?;
 
             if task.completed_at.is_some() {
-                client.0.reopen_task(&existing_task.id).await.wrap_user_err(
+                client.0.reopen_task(&existing_task.id).call_counted(TODOIST_PROVIDER).await.wrap_user_err(
                     format!("Failed to reopen completed Todoist task '{}'.", job.title),
                     &[
                         "Check that your Todoist API token is valid and has the necessary permissions.",
@@ -125,6 +128,7 @@ impl Job for TodoistUpsertTask {
                     priority: job.priority,
                     ..Default::default()
                 })
+                .call_counted(TODOIST_PROVIDER)
                 .await
                 .wrap_user_err(
                     format!("Failed to create Todoist task '{}'.", job.title),
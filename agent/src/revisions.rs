@@ -0,0 +1,195 @@
+//! Every version of a workflow that was ever saved.
+//!
+//! A workflow record is overwritten in place when it is edited, which is what
+//! the reconciler and the webhook handler want: one record, always current. It
+//! is not what the person who broke a shared GitHub workflow's filter on a
+//! Friday afternoon wants, which is the filter as it was on Thursday. The audit
+//! log can tell them that somebody changed it and when, and nothing about what
+//! it said.
+//!
+//! So each save also writes a [`WorkflowRevision`] here, numbered from one in a
+//! partition per workflow. Unlike [`crate::deliveries`] these are not trimmed:
+//! a workflow is saved by hand, a few times over its life, and the revision
+//! somebody needs back is as likely to be the first as the last. Applying a file
+//! is routine rather than rare, which is why one that changes nothing keeps no
+//! revision at all (see [`crate::workflow_store::WorkflowStore::upsert`]).
+
+use automate_api::{WorkflowId, WorkflowRevision};
+use human_errors::Error;
+
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+
+/// The prefix of the partitions holding each workflow's revisions.
+pub const REVISIONS_PARTITION: &str = "revisions";
+
+/// How many numbers to try when two saves race for the same one.
+///
+/// Each attempt reads what is there first, so a second one only happens when
+/// another save landed in between; this bounds the loop rather than expecting
+/// to need it.
+const NUMBER_ATTEMPTS: usize = 5;
+
+fn partition(workflow: WorkflowId) -> String {
+    format!("{REVISIONS_PARTITION}/{workflow}")
+}
+
+/// Padded so that the store's own ordering of keys is the order they were
+/// saved in, which keeps a browse of the partition readable.
+fn key(number: u32) -> String {
+    format!("{number:010}")
+}
+
+/// Reads and writes the revisions kept for one tenant's workflows.
+pub struct RevisionStore<S> {
+    services: S,
+}
+
+impl<S: Services> RevisionStore<S> {
+    pub fn new(services: S) -> Self {
+        Self { services }
+    }
+
+    /// A workflow's revisions, newest first.
+    pub async fn list(&self, workflow: WorkflowId) -> Result<Vec<WorkflowRevision>, Error> {
+        let stored: Vec<(String, WorkflowRevision)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        let mut revisions: Vec<WorkflowRevision> =
+            stored.into_iter().map(|(_, revision)| revision).collect();
+        revisions.sort_by_key(|revision| std::cmp::Reverse(revision.number));
+
+        Ok(revisions)
+    }
+
+    /// One revision of a workflow, by number.
+    pub async fn get(
+        &self,
+        workflow: WorkflowId,
+        number: u32,
+    ) -> Result<Option<WorkflowRevision>, Error> {
+        self.services
+            .kv()
+            .get(partition(workflow), key(number))
+            .await
+    }
+
+    /// Keeps a revision under the next free number, which it returns.
+    ///
+    /// Whatever number the revision arrives with is replaced: the number says
+    /// where it falls in the workflow's history, which only the store knows.
+    pub async fn record(
+        &self,
+        workflow: WorkflowId,
+        mut revision: WorkflowRevision,
+    ) -> Result<u32, Error> {
+        for _ in 0..NUMBER_ATTEMPTS {
+            revision.number = self
+                .list(workflow)
+                .await?
+                .first()
+                .map_or(1, |latest| latest.number + 1);
+
+            if self
+                .services
+                .kv()
+                .insert(partition(workflow), key(revision.number), revision.clone())
+                .await?
+            {
+                return Ok(revision.number);
+            }
+        }
+
+        Err(human_errors::system(
+            "We could not find a free number to keep this version of the workflow under.",
+            &["Please try saving it again, and report this if it keeps happening."],
+        ))
+    }
+
+    /// Forgets a workflow's revisions, for when the workflow itself has gone.
+    pub async fn forget(&self, workflow: WorkflowId) -> Result<(), Error> {
+        let stored: Vec<(String, serde_json::Value)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        for (key, _) in stored {
+            self.services.kv().remove(partition(workflow), key).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn revision(filter: &str) -> WorkflowRevision {
+        WorkflowRevision {
+            number: 0,
+            config: serde_json::json!({ "filter": filter }),
+            schedule: None,
+            enabled: true,
+            saved_at: Utc::now(),
+            saved_by: Some("alice".into()),
+            restored_from: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn revisions_are_numbered_in_the_order_they_were_saved() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = RevisionStore::new(&services);
+        let workflow = WorkflowId::from_entropy(1);
+
+        for filter in ["true", "action == \"opened\"", "action == \"closed\""] {
+            store.record(workflow, revision(filter)).await.unwrap();
+        }
+
+        let numbers: Vec<u32> = store
+            .list(workflow)
+            .await
+            .unwrap()
+            .iter()
+            .map(|revision| revision.number)
+            .collect();
+        assert_eq!(numbers, [3, 2, 1], "the newest revision should come first");
+
+        let first = store.get(workflow, 1).await.unwrap().unwrap();
+        assert_eq!(first.config["filter"], "true");
+    }
+
+    #[tokio::test]
+    async fn each_workflow_counts_its_own_revisions() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = RevisionStore::new(&services);
+        let (first, second) = (WorkflowId::from_entropy(1), WorkflowId::from_entropy(2));
+
+        store.record(first, revision("true")).await.unwrap();
+        store.record(first, revision("false")).await.unwrap();
+
+        assert_eq!(
+            store.record(second, revision("true")).await.unwrap(),
+            1,
+            "another workflow's history should not advance this one's numbering",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_forgotten_workflow_has_no_history() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = RevisionStore::new(&services);
+        let workflow = WorkflowId::from_entropy(1);
+
+        store.record(workflow, revision("true")).await.unwrap();
+        store.forget(workflow).await.unwrap();
+
+        assert!(store.list(workflow).await.unwrap().is_empty());
+        assert_eq!(
+            store.record(workflow, revision("true")).await.unwrap(),
+            1,
+            "a workflow that reuses the identifier should start its own history",
+        );
+    }
+}
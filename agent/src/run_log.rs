@@ -0,0 +1,381 @@
+//! What a run logged, kept for the person whose run it was.
+//!
+//! Jobs say a good deal about what they are doing — "Discarding a delivery for
+//! a paused workflow", a Todoist request that was rate limited and will be
+//! retried — and all of it goes to the server's logs, which the owner of the
+//! workflow cannot read. This captures the lines a run logs at `info` and above
+//! while it runs, so that they can be kept with the run and shown on its row.
+//!
+//! # Why a layer on the run's span
+//!
+//! [`RunLogs`] is installed once, in the telemetry session, alongside the
+//! layers that send the same lines to the server's logs and traces. A run asks
+//! for its lines to be kept by [`capture`], which leaves a place for them on its
+//! `job.run` span, and the layer files each line under the nearest span that
+//! has one. Going by span rather than by task is what makes the capture exact:
+//! a line logged by another job running alongside on the same thread is under
+//! that job's span, and a script run on a blocking thread inside the run's span
+//! is still this run's.
+//!
+//! The session's subscriber is left as it is while a run executes, so whatever
+//! asks it for its layers — the OpenTelemetry context of a span, most often —
+//! is answered the same inside a run as outside one.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+use automate_api::{RunLog, RunLogLevel, RunLogLine};
+use chrono::Utc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Metadata, Span, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+use tracing_subscriber::{Layer, Registry};
+
+/// The most lines kept of any one run.
+///
+/// A run logs a handful of lines when it works and a few more when it does not.
+/// One logging hundreds is stuck in a loop, and what it said first is what
+/// explains it.
+const MAX_LINES: usize = 100;
+
+/// The most of any one message or field that is kept, in bytes.
+const MAX_TEXT_BYTES: usize = 2 * 1024;
+
+/// Runs `run`, the work of the run `span` stands for, to completion, returning
+/// what it logged alongside its result.
+///
+/// Nothing about what the run logs changes: every line still reaches the
+/// server's logs and traces exactly as it would have. Without [`RunLogs`] in
+/// the session nothing is kept, and the log is empty.
+pub async fn capture<F: Future>(span: &Span, run: F) -> (F::Output, RunLog) {
+    on_span(span, |span| span.extensions_mut().replace(Sink::default()));
+
+    let output = run.await;
+
+    let log = on_span(span, |span| span.extensions_mut().remove::<Sink>())
+        .flatten()
+        .map(|sink| sink.0.into_inner().unwrap_or_else(|err| err.into_inner()))
+        .unwrap_or_default();
+    (output, log)
+}
+
+/// Blanks the fields named as credentials, and the account's secrets wherever
+/// they appear, the way a run's input is redacted before it is kept.
+pub fn redact(log: RunLog, secrets: &[String]) -> RunLog {
+    RunLog {
+        lines: log
+            .lines
+            .into_iter()
+            .map(|line| RunLogLine {
+                message: crate::runs::scrub(&line.message, secrets),
+                fields: line
+                    .fields
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = if crate::runs::is_sensitive(&name) {
+                            crate::runs::REDACTED.to_string()
+                        } else {
+                            crate::runs::scrub(&value, secrets)
+                        };
+                        (name, value)
+                    })
+                    .collect(),
+                ..line
+            })
+            .collect(),
+        dropped: log.dropped,
+    }
+}
+
+/// Whether a line is one the run's owner is shown.
+fn is_kept(metadata: &Metadata<'_>) -> bool {
+    *metadata.level() <= Level::INFO
+}
+
+/// Keeps the lines logged under a span that [`capture`] is running.
+///
+/// Installed in the telemetry session whether or not telemetry is enabled,
+/// since what it keeps is shown to the owner of the run rather than sent to us.
+pub struct RunLogs;
+
+impl tracing_batteries::Battery for RunLogs {
+    fn setup(
+        &self,
+        _metadata: &tracing_batteries::Metadata,
+        _enabled: Arc<AtomicBool>,
+    ) -> Box<dyn tracing_batteries::BatteryForce> {
+        Box::new(RunLogs)
+    }
+}
+
+impl tracing_batteries::BatteryForce for RunLogs {
+    fn get_layers(&self) -> Vec<Box<dyn Layer<Registry> + Send + Sync + 'static>> {
+        vec![Box::new(RunLogs)]
+    }
+}
+
+impl<S> Layer<S> for RunLogs
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !is_kept(event.metadata()) {
+            return;
+        }
+
+        // The nearest run is the one the line belongs to, though runs are
+        // started without a parent and so are never found inside one another.
+        for span in ctx.event_scope(event).into_iter().flatten() {
+            if let Some(sink) = span.extensions().get::<Sink>() {
+                sink.keep(event);
+                return;
+            }
+        }
+    }
+}
+
+/// Runs `f` against the registry's record of `span`, if there is one.
+fn on_span<T>(span: &Span, f: impl FnOnce(SpanRef<'_, Registry>) -> T) -> Option<T> {
+    span.with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        registry.span(id).map(f)
+    })
+    .flatten()
+}
+
+/// Where the lines of one run are kept, on its span.
+#[derive(Default)]
+struct Sink(Mutex<RunLog>);
+
+impl Sink {
+    fn keep(&self, event: &Event<'_>) {
+        let mut log = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if log.lines.len() >= MAX_LINES {
+            log.dropped = log.dropped.saturating_add(1);
+            return;
+        }
+
+        let mut line = Line::default();
+        event.record(&mut line);
+
+        log.lines.push(RunLogLine {
+            at: Utc::now(),
+            level: match *event.metadata().level() {
+                Level::ERROR => RunLogLevel::Error,
+                Level::WARN => RunLogLevel::Warn,
+                _ => RunLogLevel::Info,
+            },
+            message: line.message,
+            fields: line.fields,
+        });
+    }
+}
+
+/// One event, written out as text.
+#[derive(Default)]
+struct Line {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for Line {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.keep(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.keep(field, format!("{value:?}"));
+    }
+}
+
+impl Line {
+    fn keep(&mut self, field: &Field, value: String) {
+        let value = truncated(value);
+        match field.name() {
+            "message" => self.message = value,
+            // Logged for the server's benefit, and already shown on the run.
+            name if name.starts_with("otel.") || name.starts_with("log.") => {}
+            name => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+/// Cuts `text` down to [`MAX_TEXT_BYTES`], on a character boundary.
+fn truncated(mut text: String) -> String {
+    if text.len() <= MAX_TEXT_BYTES {
+        return text;
+    }
+
+    let mut end = MAX_TEXT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push('…');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::instrument::Instrument;
+    use tracing::{Dispatch, debug, dispatcher, info, info_span, warn};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// A subscriber put together the way the telemetry session's is.
+    fn session() -> Dispatch {
+        Dispatch::new(tracing_subscriber::registry().with(RunLogs))
+    }
+
+    /// Runs `run` as a job would, under a `job.run` span of its own.
+    async fn run<F: Future>(run: F) -> (F::Output, RunLog) {
+        let span = info_span!(parent: None, "job.run");
+        capture(&span, run.instrument(span.clone())).await
+    }
+
+    #[tokio::test]
+    async fn a_run_keeps_what_it_logged_at_info_and_above() {
+        let _session = dispatcher::set_default(&session());
+        let ((), log) = run(async {
+            debug!("Reading the feed.");
+            info!(
+                workflow.id = "copper-tiger-canyon",
+                "Discarding a delivery for a paused workflow."
+            );
+            warn!(
+                attempt = 2,
+                "Todoist is rate limiting us, so the task will be retried."
+            );
+        })
+        .await;
+
+        info!("Logged after the run, and not part of it.");
+
+        assert_eq!(
+            log.lines
+                .iter()
+                .map(|line| (line.level, line.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    RunLogLevel::Info,
+                    "Discarding a delivery for a paused workflow."
+                ),
+                (
+                    RunLogLevel::Warn,
+                    "Todoist is rate limiting us, so the task will be retried."
+                ),
+            ],
+        );
+        assert_eq!(log.lines[0].fields["workflow.id"], "copper-tiger-canyon");
+        assert_eq!(log.lines[1].fields["attempt"], "2");
+    }
+
+    #[tokio::test]
+    async fn a_run_that_logs_without_end_keeps_what_it_said_first() {
+        let _session = dispatcher::set_default(&session());
+        let ((), log) = run(async {
+            for n in 0..MAX_LINES + 20 {
+                info!("Line {n}.");
+            }
+        })
+        .await;
+
+        assert_eq!(log.lines.len(), MAX_LINES);
+        assert_eq!(log.lines[0].message, "Line 0.");
+        assert_eq!(log.dropped, 20);
+    }
+
+    #[tokio::test]
+    async fn credentials_and_secrets_are_blanked_before_a_log_is_kept() {
+        let _session = dispatcher::set_default(&session());
+        let ((), log) = run(async {
+            info!(
+                authorization = "Bearer abc123",
+                url = "https://hooks.example.com/s3cr3t",
+                "Posting to https://hooks.example.com/s3cr3t.",
+            );
+        })
+        .await;
+
+        let log = redact(log, &["s3cr3t".to_string()]);
+        let line = &log.lines[0];
+        assert_eq!(
+            line.message,
+            "Posting to https://hooks.example.com/<redacted>."
+        );
+        assert_eq!(line.fields["authorization"], "<redacted>");
+        assert_eq!(line.fields["url"], "https://hooks.example.com/<redacted>");
+    }
+
+    #[tokio::test]
+    async fn a_line_another_run_logs_alongside_is_not_kept() {
+        let _session = dispatcher::set_default(&session());
+        let (((), first), ((), second)) = tokio::join!(
+            run(async {
+                info!("First, before yielding.");
+                tokio::task::yield_now().await;
+                info!("First, after yielding.");
+            }),
+            run(async {
+                info!("Second.");
+            }),
+        );
+
+        let messages = |log: &RunLog| {
+            log.lines
+                .iter()
+                .map(|line| line.message.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(&first),
+            vec!["First, before yielding.", "First, after yielding."]
+        );
+        assert_eq!(messages(&second), vec!["Second."]);
+    }
+
+    #[tokio::test]
+    async fn a_line_logged_on_a_blocking_thread_within_the_run_is_kept() {
+        let _session = dispatcher::set_default(&session());
+        let ((), log) = run(async {
+            let span = Span::current();
+            let dispatch = dispatcher::get_default(Dispatch::clone);
+            tokio::task::spawn_blocking(move || {
+                dispatcher::with_default(&dispatch, || {
+                    span.in_scope(|| info!("Logged by a script."))
+                })
+            })
+            .await
+            .unwrap();
+        })
+        .await;
+
+        assert_eq!(log.lines.len(), 1);
+        assert_eq!(log.lines[0].message, "Logged by a script.");
+    }
+
+    #[tokio::test]
+    async fn a_run_is_polled_under_the_session_s_own_subscriber() {
+        let _session = dispatcher::set_default(&session());
+        run(async {
+            assert!(dispatcher::get_default(|current| current
+                .downcast_ref::<Registry>()
+                .is_some()));
+        })
+        .await;
+    }
+
+    #[test]
+    fn a_long_line_is_cut_on_a_character_boundary() {
+        let cut = truncated("é".repeat(MAX_TEXT_BYTES));
+
+        assert!(cut.len() <= MAX_TEXT_BYTES + '…'.len_utf8());
+        assert!(cut.ends_with('…'));
+    }
+}
@@ -22,15 +22,31 @@
 //! workflow the run that failed at three in the morning has been overwritten
 //! several hundred times by breakfast, which is precisely when somebody comes
 //! looking for it.
+//!
+//! # Why there is a history as well
+//!
+//! One record says whether a workflow is working and what it last failed on.
+//! It cannot say whether it failed at three, worked at four and failed again at
+//! five — which is the difference between a sender that is down and one that
+//! is flaky. So each run is also kept as an entry of its own, in a partition
+//! per workflow, and let go of once it is past the count or the age set by
+//! [`crate::config::RunConfig`]: trimmed as each new run is recorded, and once
+//! a day for workflows that have stopped running. That bound is what keeps it
+//! from being the audit log again, and the record above is what keeps the
+//! overnight failure once the history has moved past it.
 
 use std::collections::HashMap;
 
-use automate_api::{RunOutcome, RunReport, RunState, WorkflowHealth, WorkflowId};
+use automate_api::{
+    RunHistory, RunOutcome, RunReport, RunState, TenantId, WorkflowHealth, WorkflowId,
+};
+use chrono::{DateTime, Utc};
 use human_errors::Error;
 use serde_json::Value;
 
 use crate::db::KeyValueStore;
 use crate::prelude::*;
+use crate::services::AppContext;
 
 /// The partition holding one run record per workflow.
 ///
@@ -38,6 +54,31 @@ use crate::prelude::*;
 /// is read across all of them when a list is drawn.
 pub const RUNS_PARTITION: &str = "runs";
 
+/// The partition noting which workflows somebody asked to run now, until the
+/// run they asked for is recorded.
+///
+/// A run asked for is queued exactly as a scheduled one is, under the same key
+/// so that asking twice collapses onto one run, and so the run itself cannot
+/// tell the two apart. This is how it finds out.
+const REQUESTS_PARTITION: &str = "run-requests";
+
+fn history_partition(workflow: WorkflowId) -> String {
+    format!("{RUNS_PARTITION}/{workflow}")
+}
+
+/// The key a run is kept under in its workflow's history.
+///
+/// Led by when it started, zero-padded so that keys sort as the runs did, and
+/// followed by a random suffix so that two runs starting in the same
+/// microsecond do not overwrite one another.
+fn history_key(report: &RunReport) -> String {
+    format!(
+        "{:020}-{:08x}",
+        report.started_at.timestamp_micros().max(0),
+        rand::random::<u32>()
+    )
+}
+
 /// The most of a run's input we will keep.
 ///
 /// A GitHub `push` on a large repository runs to hundreds of kilobytes, and the
@@ -49,7 +90,7 @@ const MAX_INPUT_BYTES: usize = 16 * 1024;
 /// How much of an oversized input is kept as a prefix.
 const PREVIEW_BYTES: usize = 2 * 1024;
 
-const REDACTED: &str = "<redacted>";
+pub(crate) const REDACTED: &str = "<redacted>";
 
 /// Fragments of a field name that mean its value must not be written down.
 ///
@@ -68,13 +109,19 @@ const SENSITIVE: &[&str] = &[
     "token",
 ];
 
-fn is_sensitive(key: &str) -> bool {
+pub(crate) fn is_sensitive(key: &str) -> bool {
     let key = key.to_ascii_lowercase();
     SENSITIVE.iter().any(|needle| key.contains(needle))
 }
 
-/// Replaces the values of sensitive-looking fields, at any depth.
-fn redact(value: &Value) -> Value {
+/// Replaces the values of sensitive-looking fields, at any depth, and any of
+/// the account's secrets wherever they appear in the rest.
+///
+/// Field names only catch what a sender labelled as sensitive. A secret from
+/// [`crate::variables`] is ours, and can turn up anywhere a template put it — a
+/// task title, a description, the body of a request a workflow made — so it is
+/// looked for by value instead.
+pub(crate) fn redact(value: &Value, secrets: &[String]) -> Value {
     match value {
         Value::Object(fields) => Value::Object(
             fields
@@ -83,24 +130,64 @@ fn redact(value: &Value) -> Value {
                     let value = if is_sensitive(key) {
                         Value::String(REDACTED.to_string())
                     } else {
-                        redact(value)
+                        redact(value, secrets)
                     };
                     (key.clone(), value)
                 })
                 .collect(),
         ),
-        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| redact(item, secrets)).collect())
+        }
+        Value::String(text) => Value::String(scrub(text, secrets)),
         other => other.clone(),
     }
 }
 
-/// What of a run's input is safe and small enough to keep.
-pub fn keepable(input: &Value) -> Option<Value> {
+/// Replaces every occurrence of a secret in `text`.
+pub fn scrub(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+}
+
+/// Replaces every occurrence of a secret in the strings of `value`, leaving
+/// everything else as it is.
+///
+/// For what is shown rather than kept, such as the items a preview read, where
+/// the fields [`keepable`] blanks by name are still wanted to write a filter
+/// against. What a run would have dispatched is blanked by name as well, when
+/// [`crate::preview::Dispatched::describe`] turns it into a task.
+pub fn scrub_value(value: &Value, secrets: &[String]) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), scrub_value(value, secrets)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| scrub_value(item, secrets))
+                .collect(),
+        ),
+        Value::String(text) => Value::String(scrub(text, secrets)),
+        other => other.clone(),
+    }
+}
+
+/// What of a run's input is safe and small enough to keep, given the values of
+/// the account's secrets.
+pub fn keepable(input: &Value, secrets: &[String]) -> Option<Value> {
     if input.is_null() {
         return None;
     }
 
-    let redacted = redact(input);
+    let redacted = redact(input, secrets);
 
     let Ok(encoded) = serde_json::to_string(&redacted) else {
         return None;
@@ -186,10 +273,12 @@ impl<S: Services> RunStore<S> {
             .map_or(0, |state| state.consecutive_failures);
         let failed = report.outcome == RunOutcome::Failed;
 
-        let consecutive_failures = if failed {
-            failures_before.saturating_add(1)
-        } else {
-            0
+        // A discarded run says nothing about whether the workflow works, so it
+        // neither adds to a failure streak nor ends one.
+        let consecutive_failures = match report.outcome {
+            RunOutcome::Failed => failures_before.saturating_add(1),
+            RunOutcome::Discarded => failures_before,
+            RunOutcome::Succeeded => 0,
         };
 
         let last_failure = if failed {
@@ -198,6 +287,18 @@ impl<S: Services> RunStore<S> {
             previous.and_then(|state| state.last_failure)
         };
 
+        if self.services.config().runs.keep > 0 {
+            self.services
+                .kv()
+                .set(
+                    history_partition(workflow),
+                    history_key(&report),
+                    report.clone(),
+                )
+                .await?;
+        }
+        self.prune(workflow).await?;
+
         self.services
             .kv()
             .set(
@@ -211,7 +312,7 @@ impl<S: Services> RunStore<S> {
             )
             .await?;
 
-        Ok(match (failures_before > 0, failed) {
+        Ok(match (failures_before > 0, consecutive_failures > 0) {
             (false, true) => Transition::StartedFailing,
             (true, false) => Transition::Recovered {
                 after: failures_before,
@@ -220,18 +321,175 @@ impl<S: Services> RunStore<S> {
         })
     }
 
+    /// A page of a workflow's recent runs, newest first, starting after
+    /// `before` where it is given.
+    pub async fn history(
+        &self,
+        workflow: WorkflowId,
+        before: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<RunHistory, Error> {
+        let mut runs: Vec<RunReport> = self
+            .kept(workflow)
+            .await?
+            .into_iter()
+            .filter(|run| before.is_none_or(|before| run.started_at < before))
+            .take(limit.saturating_add(1))
+            .collect();
+
+        // One more than was asked for is read to learn whether there is a page
+        // after this one, without a count of the whole history.
+        let next = if runs.len() > limit {
+            runs.truncate(limit);
+            runs.last().map(|run| run.started_at)
+        } else {
+            None
+        };
+
+        Ok(RunHistory {
+            state: self.get(workflow).await?,
+            runs,
+            next,
+        })
+    }
+
+    /// Notes that somebody asked for a workflow to run now, so that the run is
+    /// recorded as theirs rather than the schedule's.
+    pub async fn request(&self, workflow: WorkflowId) -> Result<(), Error> {
+        self.services
+            .kv()
+            .set(REQUESTS_PARTITION, workflow.to_string(), Utc::now())
+            .await
+    }
+
+    /// Whether the run about to be recorded was asked for, forgetting the
+    /// request either way.
+    ///
+    /// A scheduled run that happened to collapse onto the one asked for counts
+    /// as asked for, which is what the person who asked will be looking for.
+    pub async fn take_request(&self, workflow: WorkflowId) -> Result<bool, Error> {
+        let requested: Option<DateTime<Utc>> = self
+            .services
+            .kv()
+            .get(REQUESTS_PARTITION, workflow.to_string())
+            .await?;
+
+        if requested.is_some() {
+            self.services
+                .kv()
+                .remove(REQUESTS_PARTITION, workflow.to_string())
+                .await?;
+        }
+
+        Ok(requested.is_some())
+    }
+
+    /// Lets go of a workflow's runs past its history's limits, returning how
+    /// many were removed.
+    pub async fn prune(&self, workflow: WorkflowId) -> Result<usize, Error> {
+        let keep = self.services.config().runs.keep;
+        let cutoff = self.cutoff();
+
+        let mut stored: Vec<(String, RunReport)> =
+            self.services.kv().list(history_partition(workflow)).await?;
+        stored.sort_by(|(_, a), (_, b)| b.started_at.cmp(&a.started_at));
+
+        let mut removed = 0;
+        for (index, (key, run)) in stored.into_iter().enumerate() {
+            if index >= keep || run.started_at < cutoff {
+                self.services
+                    .kv()
+                    .remove(history_partition(workflow), key)
+                    .await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
     /// Forgets a workflow's runs, for when the workflow itself has gone.
     pub async fn forget(&self, workflow: WorkflowId) -> Result<(), Error> {
+        let stored: Vec<(String, Value)> =
+            self.services.kv().list(history_partition(workflow)).await?;
+
+        for (key, _) in stored {
+            self.services
+                .kv()
+                .remove(history_partition(workflow), key)
+                .await?;
+        }
+
+        self.services
+            .kv()
+            .remove(REQUESTS_PARTITION, workflow.to_string())
+            .await?;
+
         self.services
             .kv()
             .remove(RUNS_PARTITION, workflow.to_string())
             .await
     }
+
+    /// The runs within the workflow's history, newest first.
+    async fn kept(&self, workflow: WorkflowId) -> Result<Vec<RunReport>, Error> {
+        let cutoff = self.cutoff();
+        let stored: Vec<(String, RunReport)> =
+            self.services.kv().list(history_partition(workflow)).await?;
+
+        let mut kept: Vec<RunReport> = stored
+            .into_iter()
+            .map(|(_, run)| run)
+            .filter(|run| run.started_at >= cutoff)
+            .collect();
+        kept.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        kept.truncate(self.services.config().runs.keep);
+
+        Ok(kept)
+    }
+
+    fn cutoff(&self) -> DateTime<Utc> {
+        Utc::now() - self.services.config().runs.retain_for()
+    }
+}
+
+/// Trims every account's run histories back to their limits, returning how
+/// many runs were removed.
+///
+/// A workflow trims its own history as each run is recorded, so this is for
+/// the ones that have stopped running: a paused workflow would otherwise keep
+/// its last runs for ever.
+pub async fn prune_all(context: &AppContext) -> Result<usize, Error> {
+    let mut removed = 0;
+
+    for tenant in context.database().tenants().await? {
+        if tenant == TenantId::system() {
+            continue;
+        }
+
+        let services = context.tenant(tenant);
+        let store = RunStore::new(&services);
+
+        for name in services.kv().partitions().await? {
+            let Some(workflow) = name
+                .strip_prefix(RUNS_PARTITION)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+
+            removed += store.prune(workflow).await?;
+        }
+    }
+
+    Ok(removed)
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::Utc;
+    use automate_api::RunTrigger;
+    use chrono::Duration;
     use serde_json::json;
 
     use super::*;
@@ -245,6 +503,10 @@ mod tests {
             outcome,
             message: (outcome == RunOutcome::Failed).then(|| "it broke".to_string()),
             input,
+            replay_of: None,
+            trigger: Some(RunTrigger::Schedule),
+            counts: Default::default(),
+            log: Default::default(),
         }
     }
 
@@ -252,16 +514,19 @@ mod tests {
     fn a_signature_never_reaches_the_record() {
         // The record lands in a store the admin UI browses, so a delivery's
         // signing header would be a credential published to every operator.
-        let kept = keepable(&json!({
-            "event": {
-                "headers": {
-                    "x-hub-signature-256": "sha256=deadbeef",
-                    "authorization": "Bearer hunter2",
-                    "x-github-event": "push",
-                },
-                "body": "{\"action\":\"opened\"}",
-            }
-        }))
+        let kept = keepable(
+            &json!({
+                "event": {
+                    "headers": {
+                        "x-hub-signature-256": "sha256=deadbeef",
+                        "authorization": "Bearer hunter2",
+                        "x-github-event": "push",
+                    },
+                    "body": "{\"action\":\"opened\"}",
+                }
+            }),
+            &[],
+        )
         .unwrap();
 
         let headers = &kept["event"]["headers"];
@@ -274,9 +539,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_secret_is_scrubbed_wherever_a_template_put_it() {
+        let secrets = vec!["tok-8Hq2v".to_string()];
+        let kept = keepable(
+            &json!({
+                "title": "Deploy with tok-8Hq2v",
+                "labels": ["tok-8Hq2v", "deploy"],
+                "count": 3,
+            }),
+            &secrets,
+        )
+        .unwrap();
+
+        assert_eq!(kept["title"], json!(format!("Deploy with {REDACTED}")));
+        assert_eq!(kept["labels"], json!([REDACTED, "deploy"]));
+        assert_eq!(kept["count"], json!(3));
+        assert!(!kept.to_string().contains("tok-8Hq2v"));
+    }
+
     #[test]
     fn an_oversized_delivery_is_kept_as_a_prefix() {
-        let kept = keepable(&json!({ "body": "x".repeat(MAX_INPUT_BYTES * 2) })).unwrap();
+        let kept = keepable(&json!({ "body": "x".repeat(MAX_INPUT_BYTES * 2) }), &[]).unwrap();
 
         assert_eq!(kept["truncated"], json!(true));
         assert!(
@@ -341,6 +625,39 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn a_discarded_run_neither_ends_a_failure_streak_nor_starts_one() {
+        // A forged delivery to a failing workflow is turned away, and must not
+        // make the workflow look as though it had recovered.
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(6);
+
+        assert_eq!(
+            store
+                .record(id, report(RunOutcome::Discarded, None))
+                .await
+                .unwrap(),
+            Transition::Unchanged,
+        );
+
+        store
+            .record(id, report(RunOutcome::Failed, None))
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .record(id, report(RunOutcome::Discarded, None))
+                .await
+                .unwrap(),
+            Transition::Unchanged,
+        );
+
+        let state = store.get(id).await.unwrap().unwrap();
+        assert_eq!(state.consecutive_failures, 1);
+        assert_eq!(state.last.outcome, RunOutcome::Discarded);
+    }
+
     #[tokio::test]
     async fn a_failure_survives_the_runs_that_follow_it() {
         // The case the whole record exists for: a workflow that failed
@@ -395,4 +712,151 @@ mod tests {
             "health carries no payload, or a list of workflows carries all of them",
         );
     }
+
+    fn report_at(started_at: DateTime<Utc>, outcome: RunOutcome) -> RunReport {
+        RunReport {
+            started_at,
+            finished_at: started_at + Duration::seconds(1),
+            ..report(outcome, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn the_history_answers_which_runs_failed_and_which_did_not() {
+        // "It failed at three and again at five, but not at four" is what the
+        // single record cannot say.
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(5);
+        let now = Utc::now();
+
+        for (hours_ago, outcome) in [
+            (3, RunOutcome::Failed),
+            (2, RunOutcome::Succeeded),
+            (1, RunOutcome::Failed),
+        ] {
+            store
+                .record(id, report_at(now - Duration::hours(hours_ago), outcome))
+                .await
+                .unwrap();
+        }
+
+        let history = store.history(id, None, 10).await.unwrap();
+        assert_eq!(
+            history
+                .runs
+                .iter()
+                .map(|run| run.outcome)
+                .collect::<Vec<_>>(),
+            vec![
+                RunOutcome::Failed,
+                RunOutcome::Succeeded,
+                RunOutcome::Failed
+            ],
+            "runs should be listed newest first, each as it went",
+        );
+        assert_eq!(history.next, None);
+        assert_eq!(history.state.unwrap().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn the_history_is_paged_from_newest_to_oldest() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(6);
+        let now = Utc::now();
+
+        for minutes_ago in 1..=5 {
+            store
+                .record(
+                    id,
+                    report_at(now - Duration::minutes(minutes_ago), RunOutcome::Succeeded),
+                )
+                .await
+                .unwrap();
+        }
+
+        let first = store.history(id, None, 2).await.unwrap();
+        assert_eq!(first.runs.len(), 2);
+        assert_eq!(first.runs[0].started_at, now - Duration::minutes(1));
+        assert_eq!(first.next, Some(now - Duration::minutes(2)));
+
+        let second = store.history(id, first.next, 2).await.unwrap();
+        assert_eq!(second.runs[0].started_at, now - Duration::minutes(3));
+
+        let last = store.history(id, second.next, 2).await.unwrap();
+        assert_eq!(last.runs.len(), 1);
+        assert_eq!(last.next, None, "the oldest run should end the history");
+    }
+
+    #[tokio::test]
+    async fn the_history_lets_go_of_runs_past_its_count_and_age() {
+        let services = ServicesContainer::new_custom_mock(|config, _| {
+            config.runs.keep = 2;
+        })
+        .await
+        .unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(7);
+        let now = Utc::now();
+
+        store
+            .record(id, report_at(now - Duration::days(60), RunOutcome::Failed))
+            .await
+            .unwrap();
+        for minutes_ago in [3, 2, 1] {
+            store
+                .record(
+                    id,
+                    report_at(now - Duration::minutes(minutes_ago), RunOutcome::Succeeded),
+                )
+                .await
+                .unwrap();
+        }
+
+        let stored: Vec<(String, RunReport)> =
+            services.kv().list(history_partition(id)).await.unwrap();
+        assert_eq!(
+            stored.len(),
+            2,
+            "only the most recent runs should still be stored"
+        );
+        assert!(
+            store.get(id).await.unwrap().unwrap().last_failure.is_some(),
+            "the failure the history let go of is still the workflow's last one",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_run_asked_for_is_told_apart_from_the_schedule_once() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(8);
+
+        assert!(!store.take_request(id).await.unwrap());
+
+        store.request(id).await.unwrap();
+        assert!(store.take_request(id).await.unwrap());
+        assert!(
+            !store.take_request(id).await.unwrap(),
+            "the next scheduled run should not be mistaken for the one asked for",
+        );
+    }
+
+    #[tokio::test]
+    async fn forgetting_a_workflow_forgets_its_history_too() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let store = RunStore::new(&services);
+        let id = WorkflowId::from_entropy(9);
+
+        store
+            .record(id, report(RunOutcome::Failed, None))
+            .await
+            .unwrap();
+        store.forget(id).await.unwrap();
+
+        let history = store.history(id, None, 10).await.unwrap();
+        assert_eq!(history.state, None);
+        assert!(history.runs.is_empty());
+    }
 }
@@ -0,0 +1,701 @@
+//! Scripts that reshape an item before a workflow filters and files it.
+//!
+//! The filter DSL decides and `${{ }}` templates insert, and neither computes:
+//! there is no adding up the line items in an order, no turning one delivery
+//! listing five alerts into five tasks, no choosing a project from a lookup
+//! table. Until now the answer to any of those was a workflow type written in
+//! Rust, which is not an answer a user can act on. A [`Script`] is the step
+//! between the two — a few lines, saved with the workflow, that are handed each
+//! item as JSON and hand back what should be filtered and filed in its place.
+//!
+//! # Why Rhai
+//!
+//! Scripts are written in [Rhai](https://rhai.rs), an embedded language built
+//! to be run on behalf of somebody who is not trusted with the host. It has no
+//! file, network or process access of its own to take away, it counts every
+//! operation it performs so that a runaway loop can be stopped rather than
+//! waited out, and it can refuse to build a string or an array past a size.
+//! Its syntax is close enough to JavaScript and Rust that the scripts people
+//! will write here (arithmetic, a `switch`, a loop over an array) read the way
+//! they expect. Module imports are compiled out of the crate altogether, since
+//! the default resolver reads scripts from disk.
+//!
+//! # What a script is handed, and what it returns
+//!
+//! The item is in scope as `item`, converted from JSON: objects become maps,
+//! arrays arrays, and numbers integers or decimals as they were written. The
+//! value the script ends with (or `return`s) is what happens next:
+//!
+//! * a map, or any other single value, replaces the item;
+//! * an array replaces it with each of its elements, which is how one delivery
+//!   becomes several tasks;
+//! * nothing at all — `()`, which is what an `if` without an `else` or a bare
+//!   `return` produces — drops it, without it reaching the filter.
+//!
+//! A script that alters `item` in place therefore has to end by naming it,
+//! which the documentation of every workflow offering a script points out.
+//!
+//! Every webhook workflow offers one, as does every polling workflow that files
+//! a task per item it finds. The calendar and GitHub notifications workflows do
+//! not: each keeps a task in step with something that goes on existing
+//! upstream, completing it when the event or thread goes away, and an item a
+//! script had renamed or split would have no way back to what it came from.
+//!
+//! # Limits
+//!
+//! A script is bounded several ways at once: by a count of operations (which
+//! is what stops an infinite loop), by wall-clock time (for the operations that
+//! are individually expensive, such as sorting a large array), by how deep
+//! calls and expressions may nest, and by how large a string, array or map it
+//! may build. Hitting any of them fails the run with a message saying which,
+//! just as a filter that cannot be evaluated does. A script is compiled when
+//! the workflow is saved, so a syntax error is reported then rather than by
+//! every run.
+//!
+//! Evaluation does not yield, so it happens on the blocking pool rather than on
+//! the worker that runs the workflow's handler, and every item of one run is
+//! evaluated there in one go. The clock is two clocks: one for each item, and
+//! one for the whole run, so that a feed of a hundred entries holds a thread
+//! for a couple of seconds at most rather than a hundred times what one entry
+//! may take.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use human_errors::ResultExt;
+use rhai::{AST, Dynamic, Engine, EvalAltResult, Scope};
+use serde::{Deserialize, Serialize};
+
+/// How many operations one run of a script may take.
+///
+/// An operation is roughly one step of evaluation — a comparison, an
+/// assignment, a function call — so this allows loops over thousands of items
+/// while stopping an endless one in a few milliseconds.
+const MAX_OPERATIONS: u64 = 500_000;
+
+/// How long one run of a script may take, whatever it is doing.
+const MAX_DURATION: Duration = Duration::from_millis(250);
+
+/// How long a script may take over every item of one workflow run together.
+const MAX_RUN_DURATION: Duration = Duration::from_secs(2);
+
+/// How often, in operations, the clock is read against [`MAX_DURATION`].
+/// Reading it on every operation would cost more than most scripts do.
+const CLOCK_INTERVAL: u64 = 1_024;
+
+/// The largest string a script may build, in bytes. As large as the largest
+/// delivery a webhook accepts, so that reshaping one never trips it.
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+/// The most elements an array or entries a map a script builds may hold.
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// How deeply a script's function calls may nest.
+const MAX_CALL_LEVELS: usize = 32;
+
+/// How deeply a script's expressions may nest, at the top level and within a
+/// function.
+const MAX_EXPRESSION_DEPTH: (usize, usize) = (64, 32);
+
+/// What a script stopped for running out the whole run's time is stopped
+/// with, to tell it apart from one that took too long over its own item.
+const RUN_EXPIRED: &str = "run";
+
+/// The most items a script may turn one item into.
+///
+/// Each becomes a task, so this is less a limit on the script than on how many
+/// tasks one delivery or one entry can file.
+pub const MAX_ITEMS: usize = 100;
+
+/// A workflow field holding a script, compiled when it is read.
+///
+/// Stored as the text somebody typed, and empty by default, in which case
+/// [`Script::run`] hands the item back untouched.
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    source: String,
+    ast: Option<Arc<AST>>,
+}
+
+impl Script {
+    pub fn new(source: impl Into<String>) -> Result<Self, human_errors::Error> {
+        let source = source.into();
+
+        if source.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        let ast = engine(Instant::now(), Instant::now() + MAX_RUN_DURATION)
+            .compile(&source).map_err(|err| {
+            human_errors::user(
+                format!("The script could not be read: {err}."),
+                &["Check the script's syntax, and that every quote, bracket and brace is closed."],
+            )
+        })?;
+
+        Ok(Self {
+            source,
+            ast: Some(Arc::new(ast)),
+        })
+    }
+
+    /// The script as it was written.
+    pub fn raw(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether there is a script to run at all.
+    pub fn is_empty(&self) -> bool {
+        self.ast.is_none()
+    }
+
+    /// Runs the script against each of a run's items, and returns, for each,
+    /// the items that should take its place — none, one, or several.
+    ///
+    /// Every item comes with what to call it in an error, such as "the
+    /// delivery's body", so that a run failing on the fortieth entry of a feed
+    /// says which.
+    pub async fn run(
+        &self,
+        items: Vec<(serde_json::Value, String)>,
+    ) -> Result<Vec<Vec<serde_json::Value>>, human_errors::Error> {
+        self.run_within(items, MAX_RUN_DURATION).await
+    }
+
+    /// Runs the script against items that are read and written as JSON, and
+    /// reads each of the items it returns back as the same kind of item.
+    ///
+    /// A field the script leaves out of what it returns keeps the value the
+    /// item was handed to it with, so that `#{ title: "..." }` changes the
+    /// title rather than failing for want of everything else.
+    pub async fn reshape<T>(
+        &self,
+        items: Vec<T>,
+        describe: impl Fn(&T) -> String,
+    ) -> Result<Vec<T>, human_errors::Error>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+    {
+        if self.is_empty() {
+            return Ok(items);
+        }
+
+        let originals = items
+            .iter()
+            .map(|item| serde_json::to_value(item).map(|value| (value, describe(item))))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_system_err(
+                "The workflow's script could not be handed the items it was given.",
+                &["Please report this issue to us on GitHub so that we can investigate."],
+            )?;
+
+        self.read(originals).await
+    }
+
+    /// Runs the script against items that arrive as JSON, such as the body of a
+    /// delivery, and reads each of the items it returns as a `T`, filling in
+    /// what it leaves out as [`Script::reshape`] does.
+    ///
+    /// The script is handed the JSON as it arrived, rather than the parts of it
+    /// a workflow reads, so that it can draw on the rest.
+    pub async fn read<T>(
+        &self,
+        items: Vec<(serde_json::Value, String)>,
+    ) -> Result<Vec<T>, human_errors::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let results = self.run(items.clone()).await?;
+
+        let mut read = Vec::new();
+        for ((original, describe), results) in items.into_iter().zip(results) {
+            for result in results {
+                let merged = match (&original, result) {
+                    (serde_json::Value::Object(original), serde_json::Value::Object(changed)) => {
+                        let mut merged = original.clone();
+                        merged.extend(changed);
+                        serde_json::Value::Object(merged)
+                    }
+                    (_, result) => result,
+                };
+
+                read.push(serde_json::from_value(merged).map_err(|err| {
+                    human_errors::user(
+                        format!(
+                            "The workflow's script turned {describe} into something that cannot be filed: {err}."
+                        ),
+                        &[
+                            "Return the item, or a map with the same fields, with each field holding the same kind of value it was handed.",
+                        ],
+                    )
+                })?);
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// [`Script::run`], with `budget` for the whole run.
+    async fn run_within(
+        &self,
+        items: Vec<(serde_json::Value, String)>,
+        budget: Duration,
+    ) -> Result<Vec<Vec<serde_json::Value>>, human_errors::Error> {
+        let Some(ast) = self.ast.clone() else {
+            return Ok(items.into_iter().map(|(item, _)| vec![item]).collect());
+        };
+
+        // Carried across so that what the script prints is still recorded
+        // against the run it belongs to.
+        let span = tracing::Span::current();
+
+        tokio::task::spawn_blocking(move || {
+            span.in_scope(|| {
+                let deadline = Instant::now() + budget;
+                items
+                    .into_iter()
+                    .map(|(item, describe)| evaluate(&ast, item, &describe, deadline))
+                    .collect()
+            })
+        })
+        .await
+        .wrap_system_err(
+            "The workflow's script stopped before it could finish.",
+            &["Please report this issue to us on GitHub so that we can investigate."],
+        )?
+    }
+}
+
+/// Runs a compiled script against one item, stopping it at `deadline` if it
+/// has not already been stopped for taking too long over this one.
+fn evaluate(
+    ast: &AST,
+    item: serde_json::Value,
+    describe: &str,
+    deadline: Instant,
+) -> Result<Vec<serde_json::Value>, human_errors::Error> {
+    let input = rhai::serde::to_dynamic(&item).map_err(|err| {
+        human_errors::system(
+            format!("The workflow's script could not be handed {describe}: {err}"),
+            &["Please report this, along with what the item held."],
+        )
+    })?;
+
+    let mut scope = Scope::new();
+    scope.push_dynamic("item", input);
+
+    let result = engine(Instant::now(), deadline)
+        .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+        .map_err(|err| failed(describe, *err))?;
+
+    let results = if result.is_unit() {
+        vec![]
+    } else if result.is_array() {
+        result.cast::<rhai::Array>()
+    } else {
+        vec![result]
+    };
+
+    if results.len() > MAX_ITEMS {
+        return Err(human_errors::user(
+            format!(
+                "The workflow's script turned {describe} into {} items, and at most {MAX_ITEMS} are filed from one.",
+                results.len()
+            ),
+            &["Have the script return fewer items, or filter some of them out within it."],
+        ));
+    }
+
+    results
+        .iter()
+        .map(|result| {
+            rhai::serde::from_dynamic::<serde_json::Value>(result).map_err(|err| {
+                human_errors::user(
+                    format!("The workflow's script returned something that is not JSON: {err}."),
+                    &["Return maps, arrays, strings, numbers and booleans, rather than functions or timestamps."],
+                )
+            })
+        })
+        .collect()
+}
+
+/// The field that holds a workflow's [`Script`], for the workflow types that
+/// offer one.
+///
+/// Expects the configuration to hold it as `script`. `$item` names what the
+/// script is handed, such as "entry" or "alert", and `$placeholder` is an
+/// example of a script for this kind of item.
+#[macro_export]
+macro_rules! script_field {
+    ($ty:ty, $item:literal, $placeholder:expr) => {
+        automate_api::FieldDescriptor::new(
+            $crate::config_path!($ty: script),
+            "Script",
+            automate_api::FieldKind::Script {
+                placeholder: Some($placeholder.into()),
+            },
+        )
+        .with_help(concat!(
+            "Optional. Reshapes each ",
+            $item,
+            " before it is filed. End with item to file it, a list to file each element, or nothing to skip it.",
+        ))
+    };
+}
+
+/// The engine every script is compiled and run by, with its limits set and
+/// anything that could reach outside the script turned off.
+///
+/// `started` is when this item's run began, which its time limit counts from,
+/// and `deadline` is when the whole run's time is up.
+fn engine(started: Instant, deadline: Instant) -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPRESSION_DEPTH.0, MAX_EXPRESSION_DEPTH.1)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+
+    // `eval` compiles a script of its own at run time, which would be neither
+    // checked when the workflow is saved nor bound by the limits above.
+    engine.disable_symbol("eval");
+
+    // Printed output would otherwise go to the agent's own stdout, where the
+    // person who wrote the script would never see it.
+    engine.on_print(|text| {
+        tracing::debug!(script.output = text, "A workflow's script printed a line.")
+    });
+    engine.on_debug(|text, _, position| {
+        tracing::debug!(
+            script.output = text,
+            script.position = %position,
+            "A workflow's script printed a line.",
+        )
+    });
+
+    // Says which clock ran out, for `failed` to report.
+    engine.on_progress(move |operations| {
+        if operations % CLOCK_INTERVAL != 0 {
+            None
+        } else if Instant::now() > deadline {
+            Some(RUN_EXPIRED.into())
+        } else if started.elapsed() > MAX_DURATION {
+            Some(Dynamic::UNIT)
+        } else {
+            None
+        }
+    });
+
+    engine
+}
+
+/// Says why a script stopped, in terms of the limit it hit where it hit one.
+fn failed(item: &str, err: EvalAltResult) -> human_errors::Error {
+    let reason = match err {
+        EvalAltResult::ErrorTooManyOperations(_) => format!(
+            "it took more than {MAX_OPERATIONS} steps, which usually means a loop that never ends"
+        ),
+        EvalAltResult::ErrorTerminated(token, _)
+            if token.clone().into_string().is_ok_and(|t| t == RUN_EXPIRED) =>
+        {
+            format!(
+                "the script had already run for the {}s it is allowed across one run of the workflow",
+                MAX_RUN_DURATION.as_secs()
+            )
+        }
+        EvalAltResult::ErrorTerminated(..) => format!(
+            "it ran for longer than the {}ms a script is allowed",
+            MAX_DURATION.as_millis()
+        ),
+        EvalAltResult::ErrorDataTooLarge(what, _) => {
+            format!("it built a value larger than a script is allowed ({what})")
+        }
+        EvalAltResult::ErrorStackOverflow(_) => {
+            "its function calls nested too deeply, which usually means a function calling itself for ever"
+                .to_string()
+        }
+        err => err.to_string(),
+    };
+
+    human_errors::user(
+        format!("The workflow's script failed on {item}: {reason}."),
+        &["Open the workflow and check its script against the item it failed on."],
+    )
+}
+
+impl Serialize for Script {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.raw())
+    }
+}
+
+impl<'de> Deserialize<'de> for Script {
+    /// Compiles the script as it is read, a missing or `null` one doing
+    /// nothing.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(source) => {
+                Script::new(source).map_err(|err| serde::de::Error::custom(err.description()))
+            }
+            None => Ok(Script::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn run(script: &str, item: serde_json::Value) -> Result<Vec<serde_json::Value>, String> {
+        Script::new(script)
+            .expect("the script should compile")
+            .run(vec![(item, "the test item".to_string())])
+            .await
+            .map(|mut items| items.remove(0))
+            .map_err(|err| err.description().to_string())
+    }
+
+    #[tokio::test]
+    async fn an_empty_script_hands_the_item_back_untouched() {
+        let item = json!({ "title": "Disk full" });
+
+        assert_eq!(run("", item.clone()).await, Ok(vec![item.clone()]));
+        assert_eq!(
+            serde_json::from_value::<Script>(serde_json::Value::Null)
+                .unwrap()
+                .run(vec![(item.clone(), String::new())])
+                .await
+                .unwrap(),
+            vec![vec![item]],
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_can_compute_what_a_template_cannot() {
+        let items = run(
+            r#"
+                let projects = #{ billing: "Finance", infra: "Operations" };
+                item.total = item.lines.reduce(|sum, line| sum + line.amount * line.quantity, 0);
+                item.project = projects[item.team] ?? "Inbox";
+                item
+            "#,
+            json!({
+                "team": "billing",
+                "lines": [
+                    { "amount": 12, "quantity": 2 },
+                    { "amount": 5, "quantity": 1 },
+                ],
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["total"], 29);
+        assert_eq!(items[0]["project"], "Finance");
+    }
+
+    #[tokio::test]
+    async fn an_array_becomes_one_item_for_each_of_its_elements() {
+        let items = run(
+            r#"
+                let items = [];
+                for alert in item.alerts {
+                    items.push(#{ title: alert.name, severity: item.severity });
+                }
+                items
+            "#,
+            json!({
+                "severity": "high",
+                "alerts": [{ "name": "Disk full" }, { "name": "CPU hot" }],
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            items,
+            vec![
+                json!({ "title": "Disk full", "severity": "high" }),
+                json!({ "title": "CPU hot", "severity": "high" }),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_that_ends_with_nothing_drops_the_item() {
+        assert_eq!(
+            run(
+                r#"if item.status == "resolved" { item }"#,
+                json!({ "status": "firing" })
+            )
+            .await,
+            Ok(vec![]),
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_that_never_ends_is_stopped() {
+        let err = run("loop { }", json!({})).await.unwrap_err();
+
+        assert!(
+            err.contains("steps") || err.contains("longer than"),
+            "the error should say which limit stopped it: {err}",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_cannot_build_a_value_without_bound() {
+        let err = run(r#"let s = "x"; loop { s += s; }"#, json!({}))
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.contains("larger than"),
+            "the error should say the value grew too large: {err}",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_cannot_file_more_than_the_most_one_item_may_become() {
+        let err = run(
+            "let items = []; for i in 0..1000 { items.push(i); } items",
+            json!({}),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.contains("1000 items"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn one_clock_covers_every_item_of_a_run() {
+        let script = Script::new("let n = 0; for i in 0..100000 { n += i; } item").unwrap();
+
+        let err = script
+            .run_within(
+                vec![
+                    (json!({}), "the first entry".to_string()),
+                    (json!({}), "the second entry".to_string()),
+                ],
+                Duration::ZERO,
+            )
+            .await
+            .unwrap_err()
+            .description()
+            .to_string();
+
+        assert!(
+            err.contains("the first entry") && err.contains("across one run"),
+            "the error should say the run as a whole took too long: {err}",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_can_change_one_field_of_an_item_and_keep_the_rest() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Release {
+            name: String,
+            prerelease: bool,
+        }
+
+        let releases = Script::new(r#"if !item.prerelease { #{ name: "v" + item.name } }"#)
+            .unwrap()
+            .reshape(
+                vec![
+                    Release {
+                        name: "1.2.0".into(),
+                        prerelease: false,
+                    },
+                    Release {
+                        name: "1.3.0-rc.1".into(),
+                        prerelease: true,
+                    },
+                ],
+                |release| format!("the release '{}'", release.name),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            releases,
+            vec![Release {
+                name: "v1.2.0".into(),
+                prerelease: false,
+            }],
+        );
+    }
+
+    #[tokio::test]
+    async fn a_script_sees_all_of_a_delivery_and_returns_what_the_workflow_reads() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Alert {
+            title: String,
+        }
+
+        // `region` is not something the workflow reads, but the script can.
+        let alerts: Vec<Alert> = Script::new(r#"item.title += " in " + item.region; item"#)
+            .unwrap()
+            .read(vec![(
+                serde_json::json!({ "title": "High CPU", "region": "eu-west" }),
+                "the alert".to_string(),
+            )])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            alerts,
+            vec![Alert {
+                title: "High CPU in eu-west".into(),
+            }],
+        );
+    }
+
+    #[tokio::test]
+    async fn an_item_a_script_leaves_unfileable_fails_the_run() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Release {
+            prerelease: bool,
+        }
+
+        let err = Script::new("#{ prerelease: \"no\" }")
+            .unwrap()
+            .reshape(vec![Release { prerelease: false }], |_| {
+                "the release".into()
+            })
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.description().contains("cannot be filed"),
+            "the error should say the item could not be filed: {err}",
+        );
+    }
+
+    #[test]
+    fn a_script_cannot_reach_outside_itself() {
+        for script in [r#"import "secrets" as s; item"#, r#"eval("item")"#] {
+            assert!(
+                Script::new(script).is_err(),
+                "'{script}' should be refused when the workflow is saved",
+            );
+        }
+    }
+
+    #[test]
+    fn a_script_that_does_not_compile_is_refused_when_it_is_read() {
+        let err = serde_json::from_value::<Script>(json!("item.total = ")).unwrap_err();
+
+        assert!(
+            err.to_string().contains("could not be read"),
+            "the error should say the script could not be read: {err}",
+        );
+    }
+}
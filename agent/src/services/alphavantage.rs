@@ -223,7 +223,7 @@ impl AlphaVantageClient {
             .http_client
             .get(&url)
             .query(&query)
-            .send()
+            .send_counted("alphavantage")
             .await
             .wrap_system_err(
                 "Failed to contact the AlphaVantage API.",
@@ -251,7 +251,7 @@ impl GitHubClient {
             .bearer_auth(&self.api_key)
             .header("X-GitHub-Api-Version", "2022-11-28")
             .json(&serde_json::json!({ "query": query, "variables": variables }))
-            .send()
+            .send_counted("github")
             .await
             .wrap_user_err(
                 format!("We were unable to run the GitHub GraphQL operation '{operation}'."),
@@ -132,7 +132,7 @@ impl GitHubAppClient {
                             .bearer_auth(&jwt)
                             .header("Accept", "application/vnd.github+json")
                             .header("X-GitHub-Api-Version", "2022-11-28")
-                            .send()
+                            .send_counted("github")
                             .await
                             .wrap_user_err(
                                 "We were unable to mint a GitHub App installation token.",
@@ -204,7 +204,7 @@ impl GitHubAppClient {
                 .bearer_auth(self.jwt()?)
                 .header("Accept", "application/vnd.github+json")
                 .header("X-GitHub-Api-Version", "2022-11-28")
-                .send()
+                .send_counted("github")
                 .await
                 .wrap_user_err(
                     "We were unable to list the GitHub App's installations.",
@@ -269,7 +269,7 @@ impl GitHubAppClient {
             .bearer_auth(self.jwt()?)
             .header("Accept", "application/vnd.github+json")
             .header("X-GitHub-Api-Version", "2022-11-28")
-            .send()
+            .send_counted("github")
             .await
             .wrap_user_err(
                 "We were unable to uninstall the GitHub App.",
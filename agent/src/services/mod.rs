@@ -36,9 +36,10 @@ pub const HTTP_USER_AGENT: &str = "SierraSoftworks/automate";
 /// derived.
 ///
 /// This is the only thing that can reach across tenants, and it is deliberately
-/// held in very few places: `main`, the job consumer, and the parts of the web
-/// layer that resolve who a request is acting for. Everything downstream of
-/// those receives an [`AppServices`], which cannot widen its own scope.
+/// held in very few places: `main`, the job consumer, the administrative
+/// commands, and the parts of the web layer that resolve who a request is
+/// acting for. Everything downstream of those receives an [`AppServices`],
+/// which cannot widen its own scope.
 #[derive(Clone)]
 pub struct AppContext {
     config: Arc<Config>,
@@ -46,6 +47,18 @@ pub struct AppContext {
     secrets: Arc<SecretStore>,
     http_client: reqwest::Client,
     session: Arc<Session>,
+
+    /// How many deliveries each webhook workflow has left to accept.
+    ///
+    /// Installation-wide and in memory, because the addresses it limits are
+    /// reached before anybody is known to be acting for a tenant, and a limit
+    /// that had to be read from the database would cost a flood what it was
+    /// meant to spare; see [`crate::webhooks::limits`].
+    webhook_limiter: Arc<crate::webhooks::limits::Limiter>,
+
+    /// When the job host last asked the queue for work, for the readiness
+    /// check to tell a quiet installation from a wedged one.
+    job_host: Arc<crate::health::Heartbeat>,
 }
 
 impl AppContext {
@@ -66,6 +79,32 @@ impl AppContext {
             secrets: Arc::new(secrets),
             http_client,
             session,
+            webhook_limiter: Arc::default(),
+            job_host: Arc::default(),
+        }
+    }
+
+    /// Opens the database and credential keys a configuration describes.
+    pub async fn open(config: Config, session: Arc<Session>) -> Result<Self, human_errors::Error> {
+        let database_path = config.web.database.clone();
+        let database = crate::db::SqliteDatabase::open(&database_path).await?;
+
+        // The key lives beside the database unless the operator supplies one, so
+        // that an existing installation upgrades without needing to be
+        // configured.
+        let secrets = SecretStore::load(&config.web.auth, std::path::Path::new(&database_path))?;
+
+        Ok(Self::new(config, database, secrets, session))
+    }
+
+    /// The same installation working against a different database.
+    ///
+    /// The credential keys come along unchanged, which is what lets a record
+    /// copied out of the real database still be opened in the other one.
+    pub fn with_database(&self, database: crate::db::SqliteDatabase) -> Self {
+        Self {
+            database,
+            ..self.clone()
         }
     }
 
@@ -94,6 +133,18 @@ impl AppContext {
     pub fn session(&self) -> &Session {
         &self.session
     }
+
+    pub fn webhook_limiter(&self) -> &crate::webhooks::limits::Limiter {
+        &self.webhook_limiter
+    }
+
+    pub fn job_host(&self) -> &crate::health::Heartbeat {
+        &self.job_host
+    }
+
+    pub fn secrets(&self) -> &SecretStore {
+        &self.secrets
+    }
 }
 
 pub trait Services
@@ -233,7 +284,9 @@ impl AppContext {
         f(&mut config);
 
         let session = Arc::new(
-            Session::new("automate", "0.0.0-test").with_battery(tracing_batteries::Testing),
+            Session::new("automate", "0.0.0-test")
+                .with_battery(tracing_batteries::Testing)
+                .with_battery(crate::run_log::RunLogs),
         );
 
         Ok(AppContext::new(
@@ -266,7 +319,9 @@ impl ServicesContainer<crate::db::TenantDb> {
         f(&mut config, &database);
 
         let session = Arc::new(
-            Session::new("automate", "0.0.0-test").with_battery(tracing_batteries::Testing),
+            Session::new("automate", "0.0.0-test")
+                .with_battery(tracing_batteries::Testing)
+                .with_battery(crate::run_log::RunLogs),
         );
 
         Ok(
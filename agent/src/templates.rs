@@ -0,0 +1,809 @@
+//! Workflows that can be created again and again with different values.
+//!
+//! Ten GitHub release workflows differ in one field each, and filling in the
+//! form ten times is how a typo ends up in the seventh. A template keeps the
+//! parts that stay the same and asks for the parts that do not: its workflows'
+//! settings read `${{ params.name }}` wherever a value should differ, and using
+//! the template fills those in and creates the workflows.
+//!
+//! # Whose template it is
+//!
+//! An account keeps its own under [`TEMPLATES_PARTITION`]. An administrator can
+//! publish one for everybody, which is kept in the same partition of the system
+//! tenant and offered to every account beside their own. Both are read through
+//! a [`TemplateStore`] that knows which of the two it holds, so a published
+//! template cannot be removed through an account's store or the other way
+//! round.
+//!
+//! # What a template leaves out
+//!
+//! A linked account is never part of one. The account a workflow filed its
+//! tasks with means nothing to anybody else, and is not always the one even its
+//! owner wants the next copy to use, so every connection field is cleared when
+//! a template is saved and filled in by whoever uses it. Secrets go the same
+//! way: one agreed with a sender belongs to that sender's workflow, and a
+//! published template would otherwise hand it to every account.
+//!
+//! Placeholders are filled in once, when the workflows are created. A running
+//! workflow never sees one, which is why they are refused unless the template
+//! declares them: an undeclared `${{ params.x }}` would otherwise survive into
+//! a workflow and be rendered as nothing at every run.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::LazyLock;
+
+use automate_api::{
+    FieldKind, InstantiateTemplate, SaveAsTemplate, TemplateId, TemplateParameter, TemplateScope,
+    TemplateWorkflow, WorkflowTemplate,
+};
+use chrono::Utc;
+use human_errors::Error;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+use crate::workflow_store::{WorkflowDraft, WorkflowRecord};
+use crate::workflow_toml::SCHEDULE_KEY;
+use crate::workflows;
+
+/// The key-value partition holding an account's templates, and, in the system
+/// tenant, the installation's.
+pub const TEMPLATES_PARTITION: &str = "templates";
+
+/// How many times to retry a randomly generated identifier before giving up.
+const ID_ATTEMPTS: usize = 8;
+
+/// A placeholder, capturing the name of the parameter it reads.
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{\{\s*params\.([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}")
+        .expect("the placeholder pattern is a valid regular expression")
+});
+
+/// What a caller supplies to save a template.
+#[derive(Debug, Clone)]
+pub struct TemplateDraft {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Vec<TemplateParameter>,
+    pub workflows: Vec<TemplateWorkflow>,
+}
+
+/// Reads and writes one account's templates, or the installation's.
+pub struct TemplateStore<S: Services> {
+    services: S,
+    scope: TemplateScope,
+}
+
+impl<S: Services> TemplateStore<S> {
+    /// Wraps services scoped to the account, for [`TemplateScope::Account`], or
+    /// to the system tenant, for [`TemplateScope::Installation`].
+    pub fn new(services: S, scope: TemplateScope) -> Self {
+        Self { services, scope }
+    }
+
+    /// Every template held here, by name.
+    pub async fn list(&self) -> Result<Vec<WorkflowTemplate>, Error> {
+        let mut templates: Vec<WorkflowTemplate> = self
+            .services
+            .kv()
+            .list::<WorkflowTemplate>(TEMPLATES_PARTITION)
+            .await?
+            .into_iter()
+            .map(|(_, template)| self.claim(template))
+            .collect();
+
+        templates.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+        Ok(templates)
+    }
+
+    pub async fn find(&self, id: TemplateId) -> Result<Option<WorkflowTemplate>, Error> {
+        Ok(self
+            .services
+            .kv()
+            .get::<WorkflowTemplate>(TEMPLATES_PARTITION, id.to_string())
+            .await?
+            .map(|template| self.claim(template)))
+    }
+
+    /// Checks a draft and keeps it, choosing an identifier for it.
+    pub async fn create(&self, draft: TemplateDraft) -> Result<WorkflowTemplate, Error> {
+        let draft = prepare(draft)?;
+        let now = Utc::now();
+
+        for _ in 0..ID_ATTEMPTS {
+            let template = WorkflowTemplate {
+                id: TemplateId::from_entropy(rand::random()),
+                name: draft.name.clone(),
+                description: draft.description.clone(),
+                scope: self.scope,
+                parameters: draft.parameters.clone(),
+                workflows: draft.workflows.clone(),
+                created_at: now,
+                updated_at: now,
+            };
+
+            if self
+                .services
+                .kv()
+                .insert(
+                    TEMPLATES_PARTITION,
+                    template.id.to_string(),
+                    template.clone(),
+                )
+                .await?
+            {
+                return Ok(template);
+            }
+        }
+
+        Err(human_errors::system(
+            "We could not find an unused identifier for this template.",
+            &["Please try again, and report this if it keeps happening."],
+        ))
+    }
+
+    /// Removes a template, reporting whether there was one.
+    pub async fn delete(&self, id: TemplateId) -> Result<bool, Error> {
+        let existed = self.find(id).await?.is_some();
+
+        self.services
+            .kv()
+            .remove(TEMPLATES_PARTITION, id.to_string())
+            .await?;
+
+        Ok(existed)
+    }
+
+    /// Where a template is kept decides whose it is, whatever it says.
+    fn claim(&self, mut template: WorkflowTemplate) -> WorkflowTemplate {
+        template.scope = self.scope;
+        template
+    }
+}
+
+/// Builds a template from one of an account's workflows, asking for the named
+/// fields each time and keeping everything else as it is.
+pub fn from_workflow(
+    record: &WorkflowRecord,
+    request: SaveAsTemplate,
+) -> Result<TemplateDraft, Error> {
+    let descriptor = workflows::lookup(&record.type_id)?.descriptor();
+    let mut config = record.config.clone();
+    let mut parameters = Vec::new();
+
+    for path in request.parameters {
+        let Some(field) = descriptor.fields.iter().find(|field| field.name == path) else {
+            return Err(human_errors::user(
+                format!("This workflow has no field called '{path}' to ask for."),
+                &["Choose from the fields shown in the workflow's form."],
+            ));
+        };
+
+        if !matches!(
+            field.kind,
+            FieldKind::Text { .. } | FieldKind::TextArea { .. } | FieldKind::Url { .. }
+        ) {
+            return Err(human_errors::user(
+                format!(
+                    "'{}' cannot be asked for each time, because only text and addresses can.",
+                    field.label
+                ),
+                &[
+                    "Leave it as it is, and change it on the new workflow afterwards if you need to.",
+                ],
+            ));
+        }
+
+        let name = path.replace('.', "_");
+        let default = value_at(&config, &path)
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        insert_at(
+            &mut config,
+            &path,
+            Value::String(format!("${{{{ params.{name} }}}}")),
+        );
+
+        parameters.push(TemplateParameter {
+            name,
+            label: Some(field.label.clone()),
+            help: field.help.clone(),
+            default,
+        });
+    }
+
+    Ok(TemplateDraft {
+        name: request.name,
+        description: request.description,
+        parameters,
+        workflows: vec![TemplateWorkflow {
+            type_id: record.type_id.clone(),
+            config,
+            schedule: record.schedule.clone(),
+        }],
+    })
+}
+
+/// The workflows a template describes, with its parameters filled in and the
+/// chosen accounts linked.
+///
+/// Every draft is built before any is returned, so a template that cannot be
+/// used with these values is refused as a whole rather than half-created.
+pub fn instantiate(
+    template: &WorkflowTemplate,
+    request: &InstantiateTemplate,
+) -> Result<Vec<WorkflowDraft>, Error> {
+    if let Some(unknown) = request
+        .values
+        .keys()
+        .find(|name| !template.parameters.iter().any(|p| &p.name == *name))
+    {
+        return Err(human_errors::user(
+            format!(
+                "The template '{}' does not ask for '{unknown}'.",
+                template.name
+            ),
+            &["Check the spelling against the values the template asks for."],
+        ));
+    }
+
+    let mut values = BTreeMap::new();
+    for parameter in &template.parameters {
+        let given = request
+            .values
+            .get(&parameter.name)
+            .filter(|value| !value.trim().is_empty());
+
+        let Some(value) = given.or(parameter.default.as_ref()) else {
+            return Err(human_errors::user(
+                format!(
+                    "The template '{}' needs a value for '{}'.",
+                    template.name,
+                    parameter.label.as_deref().unwrap_or(&parameter.name)
+                ),
+                &["Fill in every value the template asks for that has no default."],
+            ));
+        };
+
+        values.insert(parameter.name.as_str(), value.as_str());
+    }
+
+    let mut linked = BTreeSet::new();
+    let mut drafts = Vec::with_capacity(template.workflows.len());
+
+    for workflow in &template.workflows {
+        let descriptor = workflows::lookup(&workflow.type_id)?.descriptor();
+
+        let mut config = workflow.config.clone();
+        fill(&mut config, &values);
+
+        for (path, connection) in &request.connections {
+            let is_connection = descriptor.fields.iter().any(|field| {
+                &field.name == path && matches!(field.kind, FieldKind::Connection { .. })
+            });
+
+            if is_connection {
+                insert_at(&mut config, path, Value::String(connection.to_string()));
+                linked.insert(path.as_str());
+            }
+        }
+
+        drafts.push(WorkflowDraft {
+            type_id: workflow.type_id.clone(),
+            config,
+            schedule: workflow.schedule.as_deref().map(|schedule| {
+                PLACEHOLDER
+                    .replace_all(schedule, |caps: &regex::Captures| {
+                        values.get(&caps[1]).copied().unwrap_or_default()
+                    })
+                    .into_owned()
+            }),
+            enabled: request.enabled,
+        });
+    }
+
+    // Said rather than ignored, because a connection that went nowhere is a
+    // workflow that will run without the account somebody thought they chose.
+    if let Some(path) = request
+        .connections
+        .keys()
+        .find(|path| !linked.contains(path.as_str()))
+    {
+        return Err(human_errors::user(
+            format!(
+                "None of the workflows in the template '{}' has an account field called '{path}'.",
+                template.name
+            ),
+            &["Choose accounts only for the fields the template's workflows have."],
+        ));
+    }
+
+    Ok(drafts)
+}
+
+/// The header of a template bundle.
+#[derive(Serialize, Deserialize)]
+struct BundleHeader {
+    name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<TemplateParameter>,
+}
+
+#[derive(Deserialize)]
+struct Bundle {
+    template: BundleHeader,
+}
+
+/// Reads a template bundle: a `[template]` section naming the template and its
+/// parameters, followed by its workflows written exactly as an export of
+/// workflows writes them.
+pub fn read_bundle(document: &str) -> Result<TemplateDraft, Error> {
+    let bundle: Bundle = toml::from_str(document).map_err(|err| {
+        human_errors::user(
+            format!("This is not a template bundle we could read: {err}"),
+            &[
+                "A bundle starts with a [template] section giving the template's name.",
+                "Its workflows follow as [[workflows.<type>]] sections, as in an export.",
+            ],
+        )
+    })?;
+
+    let mut workflows = Vec::new();
+    for (id, draft) in crate::workflow_toml::read(document)?.unwrap_or_default() {
+        if id.is_some() {
+            return Err(human_errors::user(
+                format!(
+                    "A '{}' workflow in this bundle has an id, but a template creates new workflows each time it is used.",
+                    draft.type_id
+                ),
+                &["Remove the id lines from the bundle's workflows."],
+            ));
+        }
+
+        workflows.push(TemplateWorkflow {
+            type_id: draft.type_id,
+            config: draft.config,
+            schedule: draft.schedule,
+        });
+    }
+
+    Ok(TemplateDraft {
+        name: bundle.template.name,
+        description: bundle.template.description,
+        parameters: bundle.template.parameters,
+        workflows,
+    })
+}
+
+/// Writes a template out as a bundle that [`read_bundle`] reads back.
+pub fn write_bundle(template: &WorkflowTemplate) -> Result<String, Error> {
+    let header = toml::Value::try_from(BundleHeader {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        parameters: template.parameters.clone(),
+    })
+    .wrap_system_err(
+        format!(
+            "We could not write the template '{}' out as TOML.",
+            template.name
+        ),
+        &["Please report this issue to the dev team on GitHub."],
+    )?;
+
+    let mut by_type: BTreeMap<&str, Vec<toml::Value>> = BTreeMap::new();
+    for workflow in &template.workflows {
+        let mut table = match toml::Value::try_from(&workflow.config).wrap_system_err(
+            format!(
+                "We could not write a '{}' workflow in the template '{}' out as TOML.",
+                workflow.type_id, template.name
+            ),
+            &["Please report this issue to the dev team on GitHub."],
+        )? {
+            toml::Value::Table(table) => table,
+            _ => toml::Table::new(),
+        };
+
+        if let Some(schedule) = &workflow.schedule {
+            table.insert(SCHEDULE_KEY.into(), schedule.clone().into());
+        }
+
+        by_type
+            .entry(workflow.type_id.as_str())
+            .or_default()
+            .push(toml::Value::Table(table));
+    }
+
+    let mut root = toml::Table::new();
+    root.insert("template".into(), header);
+    root.insert(
+        "workflows".into(),
+        toml::Value::Table(
+            by_type
+                .into_iter()
+                .map(|(type_id, entries)| (type_id.to_string(), toml::Value::Array(entries)))
+                .collect(),
+        ),
+    );
+
+    toml::to_string_pretty(&root).wrap_system_err(
+        format!(
+            "We could not write the template '{}' out as TOML.",
+            template.name
+        ),
+        &["Please report this issue to the dev team on GitHub."],
+    )
+}
+
+/// Checks a draft before it is kept, and clears whatever it must not carry.
+fn prepare(mut draft: TemplateDraft) -> Result<TemplateDraft, Error> {
+    draft.name = draft.name.trim().to_string();
+    if draft.name.is_empty() {
+        return Err(human_errors::user(
+            "A template needs a name.",
+            &["Give it a name that says what its workflows are for."],
+        ));
+    }
+
+    if draft.workflows.is_empty() {
+        return Err(human_errors::user(
+            format!("The template '{}' creates no workflows.", draft.name),
+            &["Add at least one [[workflows.<type>]] section."],
+        ));
+    }
+
+    let mut declared = BTreeSet::new();
+    for parameter in &draft.parameters {
+        validate_parameter_name(&parameter.name)?;
+
+        if !declared.insert(parameter.name.as_str()) {
+            return Err(human_errors::user(
+                format!(
+                    "The template '{}' asks for '{}' twice.",
+                    draft.name, parameter.name
+                ),
+                &["Give each parameter its own name."],
+            ));
+        }
+    }
+
+    let mut used = BTreeSet::new();
+    for workflow in &mut draft.workflows {
+        let descriptor = workflows::lookup(&workflow.type_id)?.descriptor();
+
+        for field in &descriptor.fields {
+            if matches!(
+                field.kind,
+                FieldKind::Connection { .. } | FieldKind::Secret { .. }
+            ) {
+                remove_at(&mut workflow.config, &field.name);
+            }
+        }
+
+        placeholders(&workflow.config, &mut used);
+        if let Some(schedule) = &workflow.schedule {
+            for caps in PLACEHOLDER.captures_iter(schedule) {
+                used.insert(caps[1].to_string());
+            }
+        }
+    }
+
+    if let Some(name) = used.iter().find(|name| !declared.contains(name.as_str())) {
+        return Err(human_errors::user(
+            format!(
+                "The template '{}' reads '${{{{ params.{name} }}}}', but does not ask for it.",
+                draft.name
+            ),
+            &["Add it to the template's parameters, or correct the spelling."],
+        ));
+    }
+
+    if let Some(name) = declared.iter().find(|name| !used.contains(**name)) {
+        return Err(human_errors::user(
+            format!(
+                "The template '{}' asks for '{name}', but none of its workflows use it.",
+                draft.name
+            ),
+            &[
+                "Read it in a workflow's settings as ${{ params.<name> }}, or remove it from the parameters.",
+            ],
+        ));
+    }
+
+    Ok(draft)
+}
+
+/// Checks that a parameter can be written in a placeholder as `params.name`.
+fn validate_parameter_name(name: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_');
+
+    if !starts_well || !chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-')) {
+        return Err(human_errors::user(
+            format!("'{name}' cannot be used as the name of a template parameter."),
+            &["Use letters, digits, '_' and '-', starting with a letter, such as 'repository'."],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Collects the name of every parameter read anywhere in `value`.
+fn placeholders(value: &Value, found: &mut BTreeSet<String>) {
+    match value {
+        Value::String(text) => {
+            for caps in PLACEHOLDER.captures_iter(text) {
+                found.insert(caps[1].to_string());
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| placeholders(item, found)),
+        Value::Object(fields) => fields.values().for_each(|item| placeholders(item, found)),
+        _ => {}
+    }
+}
+
+/// Replaces every placeholder in `value` with the value of its parameter.
+fn fill(value: &mut Value, values: &BTreeMap<&str, &str>) {
+    match value {
+        Value::String(text) => {
+            if PLACEHOLDER.is_match(text) {
+                *text = PLACEHOLDER
+                    .replace_all(text, |caps: &regex::Captures| {
+                        values.get(&caps[1]).copied().unwrap_or_default()
+                    })
+                    .into_owned();
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| fill(item, values)),
+        Value::Object(fields) => fields.values_mut().for_each(|item| fill(item, values)),
+        _ => {}
+    }
+}
+
+/// The value at a dotted path, such as `todoist.connection`.
+fn value_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, segment| value.get(segment))
+}
+
+/// Writes `value` at a dotted path, creating objects as needed.
+fn insert_at(target: &mut Value, path: &str, value: Value) {
+    let mut cursor = target;
+    let mut segments = path.split('.').peekable();
+
+    while let Some(segment) = segments.next() {
+        if !cursor.is_object() {
+            *cursor = Value::Object(Default::default());
+        }
+
+        if segments.peek().is_none() {
+            cursor[segment] = value;
+            return;
+        }
+
+        cursor = &mut cursor[segment];
+    }
+}
+
+/// Removes whatever is at a dotted path, if anything is.
+fn remove_at(target: &mut Value, path: &str) {
+    let (parent, last) = match path.rsplit_once('.') {
+        Some((parent, last)) => (
+            parent
+                .split('.')
+                .try_fold(target, |value, segment| value.get_mut(segment)),
+            last,
+        ),
+        None => (Some(target), path),
+    };
+
+    if let Some(Value::Object(fields)) = parent {
+        fields.remove(last);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ServicesContainer;
+    use automate_api::ConnectionId;
+
+    const BUNDLE: &str = r#"
+        [template]
+        name = "GitHub releases"
+
+        [[template.parameters]]
+        name = "repository"
+        label = "Repository"
+
+        [[workflows.github-releases]]
+        repository = "${{ params.repository }}"
+        connection = "abandon-ability"
+        cron = "@daily"
+
+        [workflows.github-releases.todoist]
+        project = "Software"
+        section = "${{ vars.section }}"
+    "#;
+
+    fn template(draft: TemplateDraft) -> WorkflowTemplate {
+        let draft = prepare(draft).unwrap();
+        WorkflowTemplate {
+            id: TemplateId::from_entropy(1),
+            name: draft.name,
+            description: draft.description,
+            scope: TemplateScope::Account,
+            parameters: draft.parameters,
+            workflows: draft.workflows,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn a_bundle_never_brings_somebody_elses_account_with_it() {
+        let template = template(read_bundle(BUNDLE).unwrap());
+
+        let config = &template.workflows[0].config;
+        assert!(config.get("connection").is_none(), "{config}");
+        assert_eq!(config["todoist"]["project"], "Software");
+    }
+
+    #[test]
+    fn each_use_fills_in_its_own_values_and_leaves_variables_for_the_run() {
+        let template = template(read_bundle(BUNDLE).unwrap());
+        let connection = ConnectionId::from_entropy(7);
+
+        let drafts = instantiate(
+            &template,
+            &InstantiateTemplate {
+                values: BTreeMap::from([("repository".into(), "octo/cat".into())]),
+                connections: BTreeMap::from([("connection".into(), connection)]),
+                enabled: true,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].config["repository"], "octo/cat");
+        assert_eq!(drafts[0].config["connection"], connection.to_string());
+        assert_eq!(
+            drafts[0].config["todoist"]["section"],
+            "${{ vars.section }}"
+        );
+        assert_eq!(drafts[0].schedule.as_deref(), Some("@daily"));
+    }
+
+    #[test]
+    fn a_value_the_template_needs_cannot_be_left_out() {
+        let template = template(read_bundle(BUNDLE).unwrap());
+
+        let err = instantiate(&template, &InstantiateTemplate::default()).unwrap_err();
+        assert!(err.to_string().contains("Repository"), "{err}");
+    }
+
+    #[test]
+    fn an_account_for_a_field_no_workflow_has_is_refused() {
+        let template = template(read_bundle(BUNDLE).unwrap());
+
+        let result = instantiate(
+            &template,
+            &InstantiateTemplate {
+                values: BTreeMap::from([("repository".into(), "octo/cat".into())]),
+                connections: BTreeMap::from([(
+                    "todoist.connection".into(),
+                    ConnectionId::from_entropy(7),
+                )]),
+                enabled: true,
+            },
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn a_placeholder_the_template_does_not_declare_is_refused() {
+        let document = BUNDLE.replace("params.repository", "params.repo");
+        assert!(prepare(read_bundle(&document).unwrap()).is_err());
+    }
+
+    #[test]
+    fn a_bundle_written_out_reads_back_as_the_same_template() {
+        let original = template(read_bundle(BUNDLE).unwrap());
+
+        let document = write_bundle(&original).unwrap();
+        let restored = template(read_bundle(&document).unwrap());
+
+        assert_eq!(restored.parameters, original.parameters);
+        assert_eq!(restored.workflows, original.workflows);
+    }
+
+    #[test]
+    fn a_bundle_that_names_its_workflows_is_refused() {
+        let document = BUNDLE.replace(
+            "[[workflows.github-releases]]",
+            "[[workflows.github-releases]]\nid = \"copper-tiger-canyon\"",
+        );
+
+        assert!(read_bundle(&document).is_err());
+    }
+
+    #[tokio::test]
+    async fn a_workflow_saved_as_a_template_asks_for_the_fields_chosen() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let workflows = crate::workflow_store::WorkflowStore::new(&services);
+        let created = workflows
+            .create(WorkflowDraft {
+                type_id: "rss".into(),
+                config: serde_json::json!({
+                    "name": "Citation Needed",
+                    "url": "https://example.com/rss/",
+                    "homepage": "https://example.com/",
+                    "todoist": { "connection": ConnectionId::from_entropy(3).to_string() },
+                }),
+                schedule: Some("@daily".into()),
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        let record = workflows.get(created.id).await.unwrap();
+
+        let draft = from_workflow(
+            &record,
+            SaveAsTemplate {
+                name: "Feeds".into(),
+                description: None,
+                parameters: vec!["url".into()],
+            },
+        )
+        .unwrap();
+        let saved = TemplateStore::new(&services, TemplateScope::Account)
+            .create(draft)
+            .await
+            .unwrap();
+
+        assert_eq!(saved.parameters[0].name, "url");
+        assert_eq!(
+            saved.parameters[0].default.as_deref(),
+            Some("https://example.com/rss/")
+        );
+        assert_eq!(saved.workflows[0].config["url"], "${{ params.url }}");
+        assert!(
+            saved.workflows[0].config["todoist"]
+                .get("connection")
+                .is_none()
+        );
+
+        let drafts = instantiate(
+            &saved,
+            &InstantiateTemplate {
+                values: BTreeMap::from([("url".into(), "https://example.org/feed".into())]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        workflows.create(drafts[0].clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_saved_template_is_listed_until_it_is_removed() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let account = TemplateStore::new(&services, TemplateScope::Account);
+
+        let saved = account.create(read_bundle(BUNDLE).unwrap()).await.unwrap();
+        assert_eq!(saved.scope, TemplateScope::Account);
+        assert_eq!(account.list().await.unwrap().len(), 1);
+
+        assert!(account.delete(saved.id).await.unwrap());
+        assert!(!account.delete(saved.id).await.unwrap());
+        assert!(account.list().await.unwrap().is_empty());
+    }
+}
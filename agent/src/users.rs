@@ -46,12 +46,23 @@ pub struct User {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
 
-    /// Whether the administrator filter matched them when they last signed in.
+    /// Whether the administrator filter matched them when they last signed in,
+    /// or they have been [promoted](Self::promoted).
     ///
     /// Informational only: the authoritative check happens per request.
     #[serde(default)]
     pub is_admin: bool,
 
+    /// Whether somebody with access to the installation granted this account
+    /// administrator rights directly, from the command line.
+    ///
+    /// Authoritative in the same way as `disabled`, and for the same reason: it
+    /// is how an operator bootstraps the first administrator of an installation
+    /// whose identity provider does not yet carry a claim the filter can match,
+    /// without editing the configuration file and restarting.
+    #[serde(default)]
+    pub promoted: bool,
+
     /// Whether an administrator has suspended this account.
     ///
     /// Unlike the fields above this *is* authoritative, because it is the only
@@ -156,7 +167,8 @@ impl<S: Services> UserRegistry<S> {
             username: username.clone(),
             display_name: display_name.to_string(),
             email: email.map(str::to_string),
-            is_admin,
+            is_admin: is_admin || existing.as_ref().is_some_and(|user| user.promoted),
+            promoted: existing.as_ref().is_some_and(|user| user.promoted),
             disabled: false,
             first_seen_at: existing.as_ref().map(|u| u.first_seen_at).unwrap_or(now),
             last_seen_at: now,
@@ -202,6 +214,34 @@ impl<S: Services> UserRegistry<S> {
         Ok(Some(user))
     }
 
+    /// Grants or withdraws the administrator rights an operator gave an account
+    /// directly, regardless of the configured filter.
+    ///
+    /// Withdrawing a promotion does not demote somebody the filter still
+    /// matches; it only removes the grant made here.
+    pub async fn set_promoted(
+        &self,
+        username: &TenantId,
+        promoted: bool,
+    ) -> Result<Option<User>, human_errors::Error> {
+        let Some(mut user) = self.get(username).await? else {
+            return Ok(None);
+        };
+
+        // When a grant is withdrawn the flag is left as it was, and corrected by
+        // the account's next sign-in, because only the filter knows whether it
+        // would have matched them anyway.
+        user.promoted = promoted;
+        user.is_admin |= promoted;
+
+        self.services
+            .kv()
+            .set(USERS_PARTITION, username.to_string(), user.clone())
+            .await?;
+
+        Ok(Some(user))
+    }
+
     /// Removes an account from the registry.
     ///
     /// Exposed ahead of the endpoint that will call it, because forgetting and
@@ -351,6 +391,52 @@ mod tests {
         assert!(registry.get(&alice()).await.unwrap().unwrap().is_admin);
     }
 
+    #[tokio::test]
+    async fn a_promotion_survives_sign_ins_the_filter_does_not_match() {
+        let registry = registry().await;
+
+        registry
+            .record_sign_in(&alice(), "Alice", None, false)
+            .await
+            .unwrap();
+        let promoted = registry
+            .set_promoted(&alice(), true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(promoted.promoted);
+        assert!(promoted.is_admin);
+
+        // The filter still says no, but the grant is the registry's to decide
+        // rather than the filter's, so the next sign-in must not undo it.
+        let signed_in = registry
+            .record_sign_in(&alice(), "Alice", None, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(signed_in.promoted);
+        assert!(signed_in.is_admin);
+
+        registry.set_promoted(&alice(), false).await.unwrap();
+        let demoted = registry
+            .record_sign_in(&alice(), "Alice", None, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            !demoted.is_admin,
+            "withdrawing a promotion should leave the filter's answer in charge"
+        );
+    }
+
+    #[tokio::test]
+    async fn an_account_that_never_signed_in_cannot_be_promoted() {
+        let registry = registry().await;
+
+        assert_eq!(registry.set_promoted(&alice(), true).await.unwrap(), None);
+        assert_eq!(registry.get(&alice()).await.unwrap(), None);
+    }
+
     #[tokio::test]
     async fn a_suspended_account_cannot_sign_in_or_refresh_its_record() {
         let registry = registry().await;
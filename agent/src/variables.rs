@@ -0,0 +1,420 @@
+//! Values an account keeps once and uses from any of its workflows.
+//!
+//! An internal URL prefix, the label a team files everything under, an API key
+//! for a call a workflow makes: each of those used to be typed into every
+//! workflow that needed it, and changed in every one of them when it changed.
+//! A variable is written once, under a name, and read in any template as
+//! `${{ vars.name }}`.
+//!
+//! # Secrets
+//!
+//! A variable can instead be kept as a secret, read as `${{ secrets.name }}`.
+//! The difference is everything around the template: a secret is sealed at rest
+//! with the same envelope as a connection's credential (see [`crate::crypto`]),
+//! the API never hands its value back once it has been set, and what
+//! [`crate::runs`] keeps of each run has it scrubbed out. The two live in
+//! separate namespaces so that a template says which it is reaching for, and a
+//! reviewer reading `${{ secrets.deploy_token }}` in a title can see at once
+//! that it will be published somewhere a secret probably should not be.
+//!
+//! Scrubbing finds a secret by its value, so a secret can only be written out
+//! whole. `${{ secrets.token | upper }}` would publish a value nothing
+//! recognises, and `${{ secrets.token | contains("a") | if("yes", "no") }}`
+//! would give it away one guess at a time, so a template piping a secret
+//! through any function is refused when it is saved, and rendered as
+//! [`crate::runs::REDACTED`] should one reach a run some other way.
+//!
+//! # Why a template cannot fail on one
+//!
+//! A variable that is not there renders as nothing, like a payload field that is
+//! not there. Removing one that a workflow still uses is something its next
+//! run shows plainly, and refusing to run would only cost the notification too.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use automate_api::VariableSummary;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::crypto::{Sealed, SecretContext};
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+
+/// The key-value partition holding a tenant's variables and secrets.
+pub const VARIABLES_PARTITION: &str = "variables";
+
+/// The longest name a variable may have.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// A stored variable, its value sealed if it is a secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variable {
+    pub name: String,
+
+    /// The value, in the clear or sealed. Private, so the only route to a
+    /// secret's plaintext is [`VariableStore::open`].
+    #[serde(flatten)]
+    value: StoredValue,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum StoredValue {
+    Plain { value: String },
+    Secret { sealed: Sealed },
+}
+
+impl Variable {
+    /// Whether this variable is a secret.
+    pub fn is_secret(&self) -> bool {
+        matches!(self.value, StoredValue::Secret { .. })
+    }
+
+    /// The view of this variable that is safe to send to a browser, which for a
+    /// secret leaves the value out.
+    pub fn to_summary(&self) -> VariableSummary {
+        VariableSummary {
+            name: self.name.clone(),
+            secret: self.is_secret(),
+            value: match &self.value {
+                StoredValue::Plain { value } => Some(value.clone()),
+                StoredValue::Secret { .. } => None,
+            },
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Checks that a name can be written in a template as `vars.name`.
+///
+/// Letters, digits, `_` and `-`, starting with a letter or `_`, which is what
+/// a template path reads as one field without needing anything escaped.
+pub fn validate_name(name: &str) -> Result<(), human_errors::Error> {
+    let mut chars = name.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_');
+
+    if !starts_well
+        || name.len() > MAX_NAME_LENGTH
+        || !chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-'))
+    {
+        return Err(human_errors::user(
+            format!("'{name}' cannot be used as the name of a variable."),
+            &[
+                "Use letters, digits, '_' and '-', starting with a letter, such as 'team_label'.",
+                "Keep the name to 64 characters or fewer.",
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads and writes one tenant's variables and secrets.
+pub struct VariableStore<S: Services> {
+    services: S,
+    tenant: TenantId,
+}
+
+impl<S: Services> VariableStore<S> {
+    /// Wraps services already scoped to `tenant`.
+    pub fn new(services: S, tenant: TenantId) -> Self {
+        Self { services, tenant }
+    }
+
+    /// Wraps services, taking the account from them.
+    pub fn for_services(services: S) -> Self {
+        let tenant = services.tenant().clone();
+
+        Self { services, tenant }
+    }
+
+    fn context<'a>(&'a self, name: &'a str) -> SecretContext<'a> {
+        SecretContext::Variable {
+            tenant: self.tenant.as_str(),
+            name,
+        }
+    }
+
+    /// Looks up a single variable.
+    pub async fn get(&self, name: &str) -> Result<Option<Variable>, human_errors::Error> {
+        self.services
+            .kv()
+            .get(VARIABLES_PARTITION, name.to_string())
+            .await
+    }
+
+    /// Every variable this tenant holds, by name.
+    pub async fn list(&self) -> Result<Vec<Variable>, human_errors::Error> {
+        let mut variables: Vec<Variable> = self
+            .services
+            .kv()
+            .list::<Variable>(VARIABLES_PARTITION)
+            .await?
+            .into_iter()
+            .map(|(_, variable)| variable)
+            .collect();
+
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(variables)
+    }
+
+    /// Sets a variable, sealing its value if it is a secret.
+    ///
+    /// Replaces whatever was under the name before, including a secret with a
+    /// variable or the other way round, and keeps when it was first created.
+    pub async fn set(
+        &self,
+        name: &str,
+        value: impl Into<String>,
+        secret: bool,
+    ) -> Result<Variable, human_errors::Error> {
+        validate_name(name)?;
+
+        let value = value.into();
+        let now = Utc::now();
+        let created_at = self
+            .get(name)
+            .await?
+            .map(|existing| existing.created_at)
+            .unwrap_or(now);
+
+        let variable = Variable {
+            name: name.to_string(),
+            value: if secret {
+                StoredValue::Secret {
+                    sealed: self
+                        .services
+                        .secrets()
+                        .seal(value.as_bytes(), self.context(name))?,
+                }
+            } else {
+                StoredValue::Plain { value }
+            },
+            created_at,
+            updated_at: now,
+        };
+
+        self.services
+            .kv()
+            .set(VARIABLES_PARTITION, name.to_string(), variable.clone())
+            .await?;
+
+        Ok(variable)
+    }
+
+    /// Removes a variable, reporting whether there was one to remove.
+    pub async fn delete(&self, name: &str) -> Result<bool, human_errors::Error> {
+        if self.get(name).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.services
+            .kv()
+            .remove(VARIABLES_PARTITION, name.to_string())
+            .await?;
+
+        Ok(true)
+    }
+
+    /// A variable's value, decrypting it if it is a secret.
+    pub fn open(&self, variable: &Variable) -> Result<String, human_errors::Error> {
+        match &variable.value {
+            StoredValue::Plain { value } => Ok(value.clone()),
+            StoredValue::Secret { sealed } => {
+                let plaintext = self
+                    .services
+                    .secrets()
+                    .open(sealed, self.context(&variable.name))?;
+
+                String::from_utf8(plaintext).map_err(|_| {
+                    human_errors::system(
+                        format!("The secret '{}' could not be read.", variable.name),
+                        &["Set the secret again to replace the stored value."],
+                    )
+                })
+            }
+        }
+    }
+
+    /// Every variable and secret, opened, for a run to render its templates
+    /// against.
+    pub async fn load(&self) -> Result<Variables, human_errors::Error> {
+        let mut variables = Variables::default();
+
+        for variable in self.list().await? {
+            let value = self.open(&variable)?;
+            let namespace = if variable.is_secret() {
+                &mut variables.secrets
+            } else {
+                &mut variables.vars
+            };
+
+            namespace.insert(variable.name, value);
+        }
+
+        Ok(variables)
+    }
+}
+
+/// A tenant's variables and secrets, opened, as a template reads them.
+#[derive(Clone, Default)]
+pub struct Variables {
+    vars: BTreeMap<String, String>,
+    secrets: BTreeMap<String, String>,
+}
+
+impl Variables {
+    /// Answers a path in the `vars` or `secrets` namespace.
+    ///
+    /// [`None`] when the path is in neither, so the caller can look for it
+    /// elsewhere; `Some(None)` for a name the namespace does not hold, which
+    /// renders as nothing rather than falling through to the payload.
+    pub fn lookup(&self, path: &str) -> Option<Option<Value>> {
+        let (namespace, name) = path.split_once('.')?;
+
+        let values = match namespace {
+            "vars" => &self.vars,
+            "secrets" => &self.secrets,
+            _ => return None,
+        };
+
+        Some(values.get(name).cloned().map(Value::String))
+    }
+
+    /// Whether a path reads a secret.
+    pub fn is_secret(path: &str) -> bool {
+        path.split_once('.')
+            .is_some_and(|(namespace, _)| namespace == "secrets")
+    }
+
+    /// The value of every secret, for scrubbing out of what is kept or shown.
+    pub fn secret_values(&self) -> Vec<String> {
+        self.secrets.values().cloned().collect()
+    }
+
+    #[cfg(test)]
+    pub fn with_var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_secret(mut self, name: &str, value: &str) -> Self {
+        self.secrets.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl fmt::Debug for Variables {
+    /// Names the secrets without their values, so that a stray `{:?}` cannot
+    /// write one into a log.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Variables")
+            .field("vars", &self.vars)
+            .field("secrets", &self.secrets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{AppContext, ServicesContainer};
+
+    type TestStore = VariableStore<ServicesContainer<crate::db::TenantDb>>;
+
+    fn alice() -> TenantId {
+        TenantId::new("alice").unwrap()
+    }
+
+    async fn store() -> TestStore {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        VariableStore::new(context.tenant(alice()), alice())
+    }
+
+    #[tokio::test]
+    async fn a_secret_is_sealed_at_rest_and_opened_for_a_template() {
+        let store = store().await;
+
+        store.set("team", "platform", false).await.unwrap();
+        store.set("deploy_token", "dt-Qm7Zp", true).await.unwrap();
+
+        let stored = serde_json::to_string(
+            &store
+                .services
+                .kv()
+                .list::<Value>(VARIABLES_PARTITION)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(
+            !stored.contains("dt-Qm7Zp"),
+            "a secret must not be stored in the clear: {stored}",
+        );
+        assert!(stored.contains("platform"));
+
+        let variables = store.load().await.unwrap();
+        assert_eq!(variables.lookup("vars.team"), Some(Some("platform".into())));
+        assert_eq!(
+            variables.lookup("secrets.deploy_token"),
+            Some(Some("dt-Qm7Zp".into())),
+        );
+        assert_eq!(
+            variables.lookup("vars.deploy_token"),
+            Some(None),
+            "a secret should only be readable as one",
+        );
+        assert_eq!(variables.lookup("issue.title"), None);
+        assert!(!format!("{variables:?}").contains("dt-Qm7Zp"));
+    }
+
+    #[tokio::test]
+    async fn a_secret_cannot_be_opened_under_another_name() {
+        let store = store().await;
+
+        store.set("one", "value-one", true).await.unwrap();
+        let mut moved = store.get("one").await.unwrap().unwrap();
+        moved.name = "two".into();
+
+        assert!(
+            store.open(&moved).is_err(),
+            "a sealed value copied under another name should not open",
+        );
+    }
+
+    #[tokio::test]
+    async fn setting_a_variable_again_replaces_it_and_keeps_when_it_was_created() {
+        let store = store().await;
+
+        let first = store.set("label", "ops", false).await.unwrap();
+        let second = store.set("label", "s3cr3t-value", true).await.unwrap();
+
+        assert_eq!(second.created_at, first.created_at);
+        assert!(second.is_secret());
+        assert_eq!(second.to_summary().value, None);
+        assert_eq!(store.list().await.unwrap().len(), 1);
+
+        assert!(store.delete("label").await.unwrap());
+        assert!(!store.delete("label").await.unwrap());
+    }
+
+    #[test]
+    fn a_name_must_be_one_a_template_can_read() {
+        for name in ["team", "_internal", "base-url", "API_KEY2"] {
+            assert!(validate_name(name).is_ok(), "'{name}' should be accepted");
+        }
+
+        for name in ["", "2fa", "base.url", "with space", "ünïcode"] {
+            assert!(validate_name(name).is_err(), "'{name}' should be refused");
+        }
+    }
+}
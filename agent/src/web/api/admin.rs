@@ -14,8 +14,9 @@ use actix_web::{HttpResponse, http::StatusCode, web};
 
 use super::json_error;
 use super::scope::Administrative;
-use crate::db::AuditQuery;
+use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditQuery, AuditStore};
 use crate::prelude::*;
+use crate::templates::TemplateStore;
 use crate::users::UserRegistry;
 
 /// How many audit entries a single request will return.
@@ -63,7 +64,7 @@ pub async fn list_users(context: Administrative) -> HttpResponse {
 
     let config = context.config();
     if config.web.auth.multi_tenant {
-        let local = super::local_tenant(&config);
+        let local = config.web.local_tenant();
         if !accounts.iter().any(|account| account.username == local) {
             accounts.insert(
                 0,
@@ -138,3 +139,92 @@ pub async fn audit(context: Administrative, params: web::Query<AuditParams>) ->
         Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
     }
 }
+
+/// `POST /api/v1/admin/templates` — publishes a template bundle for every
+/// account to use.
+///
+/// Published from a bundle rather than from somebody's saved template, so that
+/// what everybody is offered is a file that can be reviewed, and so that
+/// publishing does not depend on any one account keeping its copy.
+pub async fn publish_template(context: Administrative, body: String) -> HttpResponse {
+    let draft = match crate::templates::read_bundle(&body) {
+        Ok(draft) => draft,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    let system = context.tenant(TenantId::system());
+
+    match TemplateStore::new(&system, automate_api::TemplateScope::Installation)
+        .create(draft)
+        .await
+    {
+        Ok(template) => {
+            record_template(
+                &system,
+                "published",
+                template.id,
+                format!(
+                    "Published the template '{}' for every account.",
+                    template.name
+                ),
+            )
+            .await;
+
+            HttpResponse::Created().json(template)
+        }
+        Err(err) if err.is(human_errors::Kind::User) => {
+            json_error(StatusCode::BAD_REQUEST, err.description())
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `DELETE /api/v1/admin/templates/{template}` — stops offering a published
+/// template. The workflows already created from it are left as they are.
+pub async fn withdraw_template(context: Administrative, id: web::Path<String>) -> HttpResponse {
+    let id = match id.parse::<automate_api::TemplateId>() {
+        Ok(id) => id,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    let system = context.tenant(TenantId::system());
+
+    match TemplateStore::new(&system, automate_api::TemplateScope::Installation)
+        .delete(id)
+        .await
+    {
+        Ok(true) => {
+            record_template(
+                &system,
+                "withdrawn",
+                id,
+                format!("Withdrew the published template '{id}'."),
+            )
+            .await;
+
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => json_error(
+            StatusCode::NOT_FOUND,
+            format!("There is no published template called '{id}'."),
+        ),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// Recorded against the system tenant, alongside the rest of the
+/// installation's administration.
+async fn record_template(
+    system: &impl Services,
+    action: &'static str,
+    id: automate_api::TemplateId,
+    message: String,
+) {
+    let entry = AuditEntry::new(AuditCategory::Administration, action, AuditOutcome::Success)
+        .subject(id)
+        .message(message);
+
+    if let Err(err) = system.audit().record(entry).await {
+        warn!(error = %err, "Failed to record a change to a published template in the audit log.");
+    }
+}
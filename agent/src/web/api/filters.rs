@@ -0,0 +1,93 @@
+//! Trying a filter out before it is saved.
+//!
+//! The editor can check a filter's syntax and the fields it names by itself,
+//! but not whether it keeps the right things, because that depends on what the
+//! things are. This evaluates an expression against something real — a pasted
+//! payload, what a workflow collects now, or the delivery a webhook workflow
+//! last kept — and reports, for each item, how every part of the expression
+//! came out. "Why did this not come through?" is usually answered by the one
+//! condition that came out false.
+
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, http::StatusCode, web};
+use automate_api::{EvaluateFilter, FilterEvaluation, PreviewItem};
+
+use super::json_error;
+use super::scope::Scoped;
+use crate::prelude::*;
+use crate::webhook_payload::{JsonFilter, PayloadFilter};
+
+/// `POST /api/v1/filters/evaluate` — what an expression makes of a sample
+/// payload or of a workflow's items.
+///
+/// Against a workflow, the expression takes the place of the workflow's own
+/// filter for one observed run (see [`crate::preview`]), so the items are
+/// described just as a preview describes them and nothing is filed.
+pub async fn evaluate(services: Scoped, body: web::Json<EvaluateFilter>) -> HttpResponse {
+    let body = body.into_inner();
+
+    let filter = match PayloadFilter::new(body.expression) {
+        Ok(filter) => filter,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    let evaluation = match (body.payload, body.workflow) {
+        (Some(payload), None) => {
+            let target = JsonFilter(&payload);
+            let result = filter.matches(&target);
+
+            FilterEvaluation {
+                items: vec![PreviewItem {
+                    item: "The sample payload".to_string(),
+                    matched: matches!(result, Ok(true)),
+                    error: result.err().map(|err| err.to_string()),
+                    clauses: crate::filter::explain(&filter, &target),
+                }],
+                replayed: None,
+                error: None,
+            }
+        }
+        (None, Some(id)) => {
+            let stored = match services.workflows().find(id).await {
+                Ok(Some(stored)) => stored,
+                Ok(None) => {
+                    return json_error(
+                        StatusCode::NOT_FOUND,
+                        format!("There is no workflow called '{id}'."),
+                    );
+                }
+                Err(err) => {
+                    return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description());
+                }
+            };
+
+            let config = body.config.unwrap_or(stored.config);
+
+            match super::workflows::observe(
+                &services,
+                &stored.type_id,
+                config,
+                Some(id),
+                Some(Arc::new(filter)),
+            )
+            .await
+            {
+                Ok(preview) => FilterEvaluation {
+                    items: preview.items,
+                    replayed: preview.replayed,
+                    error: preview.error,
+                },
+                Err(response) => return response,
+            }
+        }
+        _ => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "Provide either a sample payload or a workflow to try the filter against, but not both.",
+            );
+        }
+    };
+
+    HttpResponse::Ok().json(evaluation)
+}
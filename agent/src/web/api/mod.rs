@@ -31,12 +31,16 @@ mod admin;
 mod audit;
 mod auth;
 mod connections;
+mod filters;
 mod kv;
+mod notifications;
 mod queue;
 pub mod scope;
+mod templates;
 #[cfg(test)]
 mod tenancy_tests;
 mod user;
+mod variables;
 mod workflows;
 
 pub use scope::Scoped;
@@ -112,6 +116,24 @@ pub fn configure() -> actix_web::Scope<
                     "/connections/{connection}/options/{source}",
                     web::get().to(connections::options),
                 )
+                .route("/filters/evaluate", web::post().to(filters::evaluate))
+                .route("/notifications", web::get().to(notifications::get))
+                .route("/notifications", web::put().to(notifications::set))
+                .route("/notifications", web::delete().to(notifications::delete))
+                .route("/variables", web::get().to(variables::list))
+                .route("/variables/{name}", web::put().to(variables::set))
+                .route("/variables/{name}", web::delete().to(variables::delete))
+                .route("/templates", web::get().to(templates::list))
+                .route("/templates/import", web::post().to(templates::import))
+                .route("/templates/{template}", web::delete().to(templates::delete))
+                .route(
+                    "/templates/{template}/bundle",
+                    web::get().to(templates::bundle),
+                )
+                .route(
+                    "/templates/{template}/instantiate",
+                    web::post().to(templates::instantiate),
+                )
                 .route("/workflow-types", web::get().to(workflows::types))
                 .route("/workflows", web::get().to(workflows::list))
                 .route("/workflows", web::post().to(workflows::create))
@@ -119,14 +141,62 @@ pub fn configure() -> actix_web::Scope<
                 // read as identifiers.
                 .route("/workflows/export", web::get().to(workflows::export))
                 .route("/workflows/import", web::post().to(workflows::import))
+                .route(
+                    "/workflows/preview",
+                    web::post().to(workflows::preview_draft),
+                )
                 .route("/workflows/{workflow}", web::get().to(workflows::get))
                 .route("/workflows/{workflow}", web::put().to(workflows::update))
                 .route("/workflows/{workflow}", web::delete().to(workflows::delete))
                 .route("/workflows/{workflow}/runs", web::get().to(workflows::runs))
+                .route(
+                    "/workflows/{workflow}/deliveries",
+                    web::get().to(workflows::deliveries),
+                )
+                .route(
+                    "/workflows/{workflow}/deliveries/{delivery}",
+                    web::get().to(workflows::delivery),
+                )
+                .route(
+                    "/workflows/{workflow}/deliveries/{delivery}/replay",
+                    web::post().to(workflows::replay),
+                )
+                .route(
+                    "/workflows/{workflow}/duplicate",
+                    web::post().to(workflows::duplicate),
+                )
+                .route(
+                    "/workflows/{workflow}/template",
+                    web::post().to(workflows::save_as_template),
+                )
                 .route(
                     "/workflows/{workflow}/rotate-webhook",
                     web::post().to(workflows::rotate_webhook),
                 )
+                .route(
+                    "/workflows/{workflow}/limits",
+                    web::put().to(workflows::set_limits),
+                )
+                .route(
+                    "/workflows/{workflow}/snooze",
+                    web::put().to(workflows::snooze),
+                )
+                .route(
+                    "/workflows/{workflow}/snooze",
+                    web::delete().to(workflows::wake),
+                )
+                .route(
+                    "/workflows/{workflow}/revisions",
+                    web::get().to(workflows::revisions),
+                )
+                .route(
+                    "/workflows/{workflow}/revisions/diff",
+                    web::get().to(workflows::revision_diff),
+                )
+                .route(
+                    "/workflows/{workflow}/revisions/{revision}/restore",
+                    web::post().to(workflows::restore_revision),
+                )
                 .route(
                     "/workflows/{workflow}/trigger",
                     web::post().to(workflows::trigger),
@@ -135,6 +205,10 @@ pub fn configure() -> actix_web::Scope<
                     "/workflows/{workflow}/reset",
                     web::post().to(workflows::reset),
                 )
+                .route(
+                    "/workflows/{workflow}/preview",
+                    web::post().to(workflows::preview),
+                )
                 // Installation-wide endpoints. These take the `Administrative`
                 // extractor, which refuses a request from anyone who is not an
                 // administrator, so the guard cannot be lost by remounting them.
@@ -144,6 +218,11 @@ pub fn configure() -> actix_web::Scope<
                     web::patch().to(admin::update_user),
                 )
                 .route("/admin/audit", web::get().to(admin::audit))
+                .route("/admin/templates", web::post().to(admin::publish_template))
+                .route(
+                    "/admin/templates/{template}",
+                    web::delete().to(admin::withdraw_template),
+                )
                 // The setup wizard is launched from the admin SPA: list the
                 // configured integrations, mint a popup authorization URL, and
                 // manage the resulting connections. All admin-gated by
@@ -292,6 +371,16 @@ pub async fn api_auth<S: Services + Send + Sync + 'static>(
                     "This account has been suspended.",
                 )));
             }
+            // An account promoted from the command line is an administrator
+            // whether or not the filter matched it, which is the point of the
+            // promotion: it is how the first administrator is appointed.
+            Ok(Some(user)) if user.promoted && !principal.is_admin() => {
+                principal = Principal::new(
+                    account.clone(),
+                    true,
+                    claims.as_ref().map(admin_user_from_claims),
+                );
+            }
             Ok(Some(_)) => {}
             Err(err) => {
                 // Failing to update the registry must not lock everybody out;
@@ -312,7 +401,7 @@ pub async fn api_auth<S: Services + Send + Sync + 'static>(
             )));
         }
 
-        match resolve_impersonation(&req, &principal, &registry, &local_tenant(&config)).await {
+        match resolve_impersonation(&req, &principal, &registry, &config.web.local_tenant()).await {
             Ok(Some(subject)) => {
                 info!(
                     admin.account = %principal.actor(),
@@ -399,20 +488,10 @@ fn account_for(
             claims,
             config.web.oidc().and_then(|o| o.username_claim.as_deref()),
         ),
-        _ => Ok(local_tenant(config)),
+        _ => Ok(config.web.local_tenant()),
     }
 }
 
-fn local_tenant(config: &crate::config::Config) -> TenantId {
-    config
-        .web
-        .auth
-        .local_user
-        .as_deref()
-        .and_then(|name| TenantId::new(name).ok())
-        .unwrap_or_else(TenantId::local)
-}
-
 /// Resolves the `X-Impersonate-User` header, if present.
 ///
 /// Returns the account to act as, `None` when the header is absent, and an error
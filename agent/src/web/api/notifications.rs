@@ -0,0 +1,66 @@
+//! Where an account is told that its workflows or connections have stopped
+//! working; see [`crate::notifications`].
+
+use actix_web::{HttpResponse, http::StatusCode, web};
+use automate_api::NotificationDestination;
+
+use super::json_error;
+use super::scope::Scoped;
+use crate::prelude::*;
+use crate::publishers::TODOIST_PROVIDER;
+
+/// `GET /api/v1/notifications` — where this account is told about incidents,
+/// or `204 No Content` when it is not told anywhere.
+pub async fn get(services: Scoped) -> HttpResponse {
+    match services.notifications().destination().await {
+        Ok(Some(destination)) => HttpResponse::Ok().json(destination),
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `PUT /api/v1/notifications` — replaces where this account is told about
+/// incidents.
+pub async fn set(services: Scoped, body: web::Json<NotificationDestination>) -> HttpResponse {
+    let destination = body.into_inner();
+
+    // Checked here, where it can be answered plainly, rather than left for the
+    // first incident to discover: that is the moment nobody is looking.
+    if let NotificationDestination::Todoist {
+        connection: Some(id),
+        ..
+    } = &destination
+    {
+        match services.connections().get(*id).await {
+            Ok(Some(connection)) if connection.provider == TODOIST_PROVIDER => {}
+            Ok(_) => {
+                return json_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("You have no Todoist connection called '{id}'."),
+                );
+            }
+            Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+        }
+    }
+
+    match services.notifications().set(destination.clone()).await {
+        Ok(()) => HttpResponse::Ok().json(destination),
+        Err(err) if err.is(human_errors::Kind::User) => {
+            json_error(StatusCode::BAD_REQUEST, err.description())
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `DELETE /api/v1/notifications` — stops telling this account about
+/// incidents.
+pub async fn delete(services: Scoped) -> HttpResponse {
+    match services.notifications().clear().await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => json_error(
+            StatusCode::NOT_FOUND,
+            "You are not being told about incidents anywhere.",
+        ),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
@@ -51,31 +51,7 @@ pub async fn list(services: Scoped) -> HttpResponse {
             }
         };
 
-        messages.extend(peeked.into_iter().map(|msg| {
-            let status = if msg.reserved_by.is_some() {
-                automate_api::QueueStatus::Reserved
-            } else if msg.hidden_until > now {
-                automate_api::QueueStatus::Delayed
-            } else {
-                automate_api::QueueStatus::Pending
-            };
-
-            let hidden_until = matches!(
-                status,
-                automate_api::QueueStatus::Reserved | automate_api::QueueStatus::Delayed
-            )
-            .then_some(msg.hidden_until);
-
-            automate_api::QueueMessage {
-                partition: partition.clone(),
-                key: msg.key,
-                payload: msg.payload,
-                status,
-                scheduled_at: msg.scheduled_at,
-                hidden_until,
-                traceparent: msg.traceparent,
-            }
-        }));
+        messages.extend(peeked.into_iter().map(|msg| msg.describe(&partition, now)));
     }
 
     messages.sort_by_key(|msg| msg.scheduled_at);
@@ -44,14 +44,21 @@ pub struct Scoped {
     /// Kept so that handlers can reach the records that belong to nobody, such
     /// as the index mapping a webhook URL to the account that owns it.
     context: AppContext,
+
+    /// The account actually making the request, which is who a change is
+    /// credited to. Not always the tenant: an administrator acting as somebody
+    /// else reaches that account's records under their own name.
+    actor: TenantId,
 }
 
 impl Scoped {
     /// This account's workflows, with the webhook address book attached so that
-    /// creating or deleting one keeps its URL in step.
+    /// creating or deleting one keeps its URL in step, and the requester named
+    /// so that each revision saved says who saved it.
     pub fn workflows(&self) -> crate::workflow_store::WorkflowStore<AppServices> {
         crate::workflow_store::WorkflowStore::new(self.services.clone())
             .with_index(self.context.tenant(TenantId::system()))
+            .with_actor(self.actor.to_string())
     }
 
     /// The account this request is acting for.
@@ -67,6 +74,32 @@ impl Scoped {
     pub fn connections(&self) -> ConnectionStore<AppServices> {
         ConnectionStore::new(self.services.clone(), self.tenant.clone())
     }
+
+    /// This account's variables and secrets.
+    pub fn variables(&self) -> crate::variables::VariableStore<AppServices> {
+        crate::variables::VariableStore::new(self.services.clone(), self.tenant.clone())
+    }
+
+    /// The templates this account has saved for itself.
+    pub fn templates(&self) -> crate::templates::TemplateStore<AppServices> {
+        crate::templates::TemplateStore::new(
+            self.services.clone(),
+            automate_api::TemplateScope::Account,
+        )
+    }
+
+    /// The templates an administrator has published for every account.
+    pub fn published_templates(&self) -> crate::templates::TemplateStore<AppServices> {
+        crate::templates::TemplateStore::new(
+            self.context.tenant(TenantId::system()),
+            automate_api::TemplateScope::Installation,
+        )
+    }
+
+    /// Where this account is told that something of theirs has stopped working.
+    pub fn notifications(&self) -> crate::notifications::NotificationStore<AppServices> {
+        crate::notifications::NotificationStore::new(self.services.clone())
+    }
 }
 
 impl Deref for Scoped {
@@ -89,6 +122,7 @@ impl FromRequest for Scoped {
                 services: context.tenant(tenant.clone()),
                 tenant,
                 context,
+                actor: principal.actor().clone(),
             }
         }))
     }
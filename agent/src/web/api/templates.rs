@@ -0,0 +1,211 @@
+//! Saving workflows as templates and creating workflows from them; see
+//! [`crate::templates`].
+//!
+//! An account sees its own templates and those an administrator has published,
+//! and may use either. It may only remove its own: a published template is
+//! withdrawn through the administrative endpoints, by whoever published it.
+
+use actix_web::{HttpResponse, http::StatusCode, web};
+use automate_api::{InstantiateTemplate, TemplateId, WorkflowTemplate};
+
+use super::json_error;
+use super::scope::Scoped;
+use super::workflows::{reconcile, record as record_workflow};
+use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditStore};
+use crate::prelude::*;
+
+/// `GET /api/v1/templates` — this account's templates, followed by those
+/// published for everybody.
+pub async fn list(services: Scoped) -> HttpResponse {
+    let mut templates = match services.templates().list().await {
+        Ok(templates) => templates,
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    match services.published_templates().list().await {
+        Ok(published) => templates.extend(published),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    HttpResponse::Ok().json(templates)
+}
+
+/// `POST /api/v1/templates/import` — keeps a template bundle as one of this
+/// account's templates.
+pub async fn import(services: Scoped, body: String) -> HttpResponse {
+    let draft = match crate::templates::read_bundle(&body) {
+        Ok(draft) => draft,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    match services.templates().create(draft).await {
+        Ok(template) => {
+            record(
+                &services,
+                "created",
+                &template,
+                format!("Imported the template '{}'.", template.name),
+            )
+            .await;
+
+            HttpResponse::Created().json(template)
+        }
+        Err(err) if err.is(human_errors::Kind::User) => {
+            json_error(StatusCode::BAD_REQUEST, err.description())
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `GET /api/v1/templates/{template}/bundle` — a template as a bundle, to be
+/// kept in version control or imported somewhere else.
+pub async fn bundle(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let template = match find(&services, &id).await {
+        Ok(template) => template,
+        Err(response) => return response,
+    };
+
+    match crate::templates::write_bundle(&template) {
+        Ok(document) => HttpResponse::Ok()
+            .content_type("application/toml; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}.toml\"", template.id),
+            ))
+            .body(document),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `DELETE /api/v1/templates/{template}` — removes one of this account's
+/// templates. The workflows created from it are left as they are.
+pub async fn delete(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let template = match find(&services, &id).await {
+        Ok(template) => template,
+        Err(response) => return response,
+    };
+
+    if template.scope == automate_api::TemplateScope::Installation {
+        return json_error(
+            StatusCode::FORBIDDEN,
+            format!(
+                "The template '{}' was published for everybody by an administrator, and only an administrator can withdraw it.",
+                template.name
+            ),
+        );
+    }
+
+    match services.templates().delete(template.id).await {
+        Ok(_) => {
+            record(
+                &services,
+                "removed",
+                &template,
+                format!("Removed the template '{}'.", template.name),
+            )
+            .await;
+
+            HttpResponse::NoContent().finish()
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `POST /api/v1/templates/{template}/instantiate` — creates the template's
+/// workflows with the given values.
+///
+/// Every workflow is checked before any is created, so a template whose second
+/// workflow will not accept these values does not leave its first behind.
+pub async fn instantiate(
+    services: Scoped,
+    id: web::Path<String>,
+    body: web::Json<InstantiateTemplate>,
+) -> HttpResponse {
+    let template = match find(&services, &id).await {
+        Ok(template) => template,
+        Err(response) => return response,
+    };
+
+    let store = services.workflows();
+
+    let drafts = match crate::templates::instantiate(&template, &body) {
+        Ok(drafts) => drafts,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    for draft in &drafts {
+        if let Err(err) = store.validate(draft) {
+            return json_error(StatusCode::BAD_REQUEST, err.description());
+        }
+    }
+
+    let mut created = Vec::with_capacity(drafts.len());
+    for draft in drafts {
+        match store.create(draft).await {
+            Ok(workflow) => {
+                record_workflow(
+                    &services,
+                    "created",
+                    workflow.id,
+                    format!(
+                        "Created the workflow '{}' from the template '{}'.",
+                        workflow.name, template.name
+                    ),
+                )
+                .await;
+                created.push(workflow);
+            }
+            Err(err) => {
+                // Only the store failing can get here, since each draft was
+                // checked above; what was created so far is real and is kept.
+                reconcile(&services).await;
+                return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description());
+            }
+        }
+    }
+
+    reconcile(&services).await;
+
+    HttpResponse::Created().json(created)
+}
+
+/// Finds a template this account can see, its own before a published one.
+async fn find(services: &Scoped, raw: &str) -> Result<WorkflowTemplate, HttpResponse> {
+    let id = raw
+        .parse::<TemplateId>()
+        .map_err(|err| json_error(StatusCode::BAD_REQUEST, err.to_string()))?;
+
+    for store in [services.templates(), services.published_templates()] {
+        match store.find(id).await {
+            Ok(Some(template)) => return Ok(template),
+            Ok(None) => {}
+            Err(err) => {
+                return Err(json_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    err.description(),
+                ));
+            }
+        }
+    }
+
+    Err(json_error(
+        StatusCode::NOT_FOUND,
+        format!("There is no template called '{id}'."),
+    ))
+}
+
+/// Records a change to one of this account's templates in its audit log.
+pub(super) async fn record(
+    services: &Scoped,
+    action: &'static str,
+    template: &WorkflowTemplate,
+    message: impl ToString,
+) {
+    let entry = AuditEntry::new(AuditCategory::WorkflowConfig, action, AuditOutcome::Success)
+        .subject(template.id)
+        .message(message);
+
+    if let Err(err) = services.audit().record(entry).await {
+        warn!(error = %err, "Failed to record a template change in the audit log.");
+    }
+}
@@ -52,7 +52,9 @@ use actix_web::http::StatusCode;
 use actix_web::http::header::AUTHORIZATION;
 use actix_web::{App, test, web};
 
-use automate_api::{AdminUser, ConnectionId, TenantId, Workflow, WorkflowId};
+use automate_api::{
+    AdminUser, ConnectionId, TemplateScope, TenantId, Workflow, WorkflowId, WorkflowTemplate,
+};
 
 use crate::filter::Filter;
 use crate::services::AppContext;
@@ -513,6 +515,174 @@ async fn one_person_cannot_edit_another_persons_connection() {
     assert_eq!(unchanged["name"], "Alice's Todoist");
 }
 
+#[actix_web::test]
+async fn a_secret_is_never_handed_back_and_never_reaches_another_account() {
+    let context = two_people().await;
+    let app = app!(context);
+
+    let created = acting_as!(
+        app,
+        ALICE,
+        test::TestRequest::put()
+            .uri("/api/v1/variables/deploy_token")
+            .set_json(serde_json::json!({ "value": "dt-Qm7Zp", "secret": true }))
+    );
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let created = String::from_utf8_lossy(&test::read_body(created).await).to_string();
+    assert!(
+        !created.contains("dt-Qm7Zp"),
+        "setting a secret should not echo it back: {created}",
+    );
+
+    let alices = text_acting_as!(
+        app,
+        ALICE,
+        test::TestRequest::get().uri("/api/v1/variables")
+    );
+    assert!(alices.contains("deploy_token"));
+    assert!(
+        !alices.contains("dt-Qm7Zp"),
+        "listing the variables should not reveal a secret's value: {alices}",
+    );
+
+    let bobs: Vec<serde_json::Value> =
+        read_acting_as!(app, BOB, test::TestRequest::get().uri("/api/v1/variables"));
+    assert!(
+        bobs.is_empty(),
+        "bob should not be shown a secret that is not his: {bobs:?}",
+    );
+
+    let removed = acting_as!(
+        app,
+        BOB,
+        test::TestRequest::delete().uri("/api/v1/variables/deploy_token")
+    );
+    assert_eq!(removed.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn incidents_are_only_ever_filed_in_the_accounts_own_todoist() {
+    let context = two_people().await;
+    let app = app!(context);
+
+    let hers = given_connection!(app, ALICE, "Alice's Todoist");
+
+    let borrowed = acting_as!(
+        app,
+        BOB,
+        test::TestRequest::put()
+            .uri("/api/v1/notifications")
+            .set_json(serde_json::json!({ "kind": "todoist", "connection": hers["id"] }))
+    );
+    assert_eq!(
+        borrowed.status(),
+        StatusCode::BAD_REQUEST,
+        "bob should not be able to have his incidents filed in alice's Todoist",
+    );
+
+    let chosen = acting_as!(
+        app,
+        ALICE,
+        test::TestRequest::put()
+            .uri("/api/v1/notifications")
+            .set_json(serde_json::json!({ "kind": "todoist", "connection": hers["id"] }))
+    );
+    assert_eq!(chosen.status(), StatusCode::OK);
+
+    let bobs = acting_as!(
+        app,
+        BOB,
+        test::TestRequest::get().uri("/api/v1/notifications")
+    );
+    assert_eq!(
+        bobs.status(),
+        StatusCode::NO_CONTENT,
+        "alice's choice of destination should not become bob's",
+    );
+}
+
+#[actix_web::test]
+async fn a_saved_template_stays_with_its_account_but_a_published_one_is_offered_to_all() {
+    let context = two_people().await;
+    let app = app!(context);
+
+    let hers = given_workflow!(app, ALICE, feed("Alice's feed"));
+    let saved: WorkflowTemplate = read_acting_as!(
+        app,
+        ALICE,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/template", hers.id))
+            .set_json(serde_json::json!({ "name": "Feeds", "parameters": ["url"] }))
+    );
+
+    let bobs: Vec<WorkflowTemplate> =
+        read_acting_as!(app, BOB, test::TestRequest::get().uri("/api/v1/templates"));
+    assert!(
+        bobs.is_empty(),
+        "bob should not be offered alice's template: {bobs:?}"
+    );
+
+    let used = acting_as!(
+        app,
+        BOB,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/templates/{}/instantiate", saved.id))
+            .set_json(serde_json::json!({ "values": { "url": "https://example.org/" } }))
+    );
+    assert_eq!(used.status(), StatusCode::NOT_FOUND);
+
+    // Published by the administrator the request is made by, acting as nobody.
+    let published = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/v1/admin/templates")
+            .set_payload(
+                r#"
+                [template]
+                name = "Blogs"
+
+                [[template.parameters]]
+                name = "feed"
+
+                [[workflows.rss]]
+                name = "A blog"
+                url = "${{ params.feed }}"
+                homepage = "https://example.com/"
+                "#,
+            )
+            .to_request(),
+    )
+    .await;
+    assert_eq!(published.status(), StatusCode::CREATED);
+    let published: WorkflowTemplate = test::read_body_json(published).await;
+
+    let bobs: Vec<WorkflowTemplate> =
+        read_acting_as!(app, BOB, test::TestRequest::get().uri("/api/v1/templates"));
+    assert_eq!(bobs.len(), 1);
+    assert_eq!(bobs[0].scope, TemplateScope::Installation);
+
+    let created: Vec<Workflow> = read_acting_as!(
+        app,
+        BOB,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/templates/{}/instantiate", published.id))
+            .set_json(serde_json::json!({ "values": { "feed": "https://example.org/rss" } }))
+    );
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].config["url"], "https://example.org/rss");
+
+    let removed = acting_as!(
+        app,
+        BOB,
+        test::TestRequest::delete().uri(&format!("/api/v1/templates/{}", published.id))
+    );
+    assert_eq!(
+        removed.status(),
+        StatusCode::FORBIDDEN,
+        "only an administrator should be able to withdraw a published template",
+    );
+}
+
 #[actix_web::test]
 async fn the_key_value_browser_shows_only_the_acting_accounts_records() {
     use crate::db::KeyValueStore;
@@ -0,0 +1,114 @@
+//! Managing the variables and secrets an account's workflows read.
+//!
+//! Like a connection's credential, a secret only ever travels inwards: it can be
+//! set and replaced, but every response here is a [`VariableSummary`], which
+//! leaves a secret's value out. Somebody who needs to know what a secret is set
+//! to should look where it came from, not here.
+//!
+//! [`VariableSummary`]: automate_api::VariableSummary
+
+use actix_web::{HttpResponse, http::StatusCode, web};
+use automate_api::VariableInput;
+
+use super::json_error;
+use super::scope::Scoped;
+use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditStore};
+use crate::prelude::*;
+
+/// `GET /api/v1/variables` — this account's variables and secrets, by name.
+pub async fn list(services: Scoped) -> HttpResponse {
+    match services.variables().list().await {
+        Ok(variables) => {
+            let summaries: Vec<_> = variables.iter().map(|v| v.to_summary()).collect();
+            HttpResponse::Ok().json(summaries)
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `PUT /api/v1/variables/{name}` — sets a variable or secret, creating it if
+/// there was none by that name.
+pub async fn set(
+    services: Scoped,
+    name: web::Path<String>,
+    body: web::Json<VariableInput>,
+) -> HttpResponse {
+    let name = name.into_inner();
+    let body = body.into_inner();
+
+    if let Err(err) = crate::variables::validate_name(&name) {
+        return json_error(StatusCode::BAD_REQUEST, err.description());
+    }
+
+    let store = services.variables();
+
+    let existed = match store.get(&name).await {
+        Ok(existing) => existing.is_some(),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    match store.set(&name, body.value, body.secret).await {
+        Ok(variable) => {
+            let kind = if variable.is_secret() {
+                "secret"
+            } else {
+                "variable"
+            };
+
+            // The value stays out of the log whichever kind it is: the log is
+            // read by more people than the variables are, and a value that was
+            // set as a variable by mistake is exactly the one not to repeat.
+            record(
+                &services,
+                if existed { "updated" } else { "created" },
+                &name,
+                format!(
+                    "The {kind} '{name}' was {}.",
+                    if existed { "replaced" } else { "created" }
+                ),
+            )
+            .await;
+
+            if existed {
+                HttpResponse::Ok().json(variable.to_summary())
+            } else {
+                HttpResponse::Created().json(variable.to_summary())
+            }
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `DELETE /api/v1/variables/{name}` — removes a variable or secret.
+pub async fn delete(services: Scoped, name: web::Path<String>) -> HttpResponse {
+    let name = name.into_inner();
+
+    match services.variables().delete(&name).await {
+        Ok(true) => {
+            record(
+                &services,
+                "removed",
+                &name,
+                format!("The variable '{name}' was removed."),
+            )
+            .await;
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => json_error(
+            StatusCode::NOT_FOUND,
+            format!("There is no variable named '{name}'."),
+        ),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// Records a change to a variable in the account's audit log.
+async fn record(services: &Scoped, action: &'static str, name: &str, message: impl ToString) {
+    let entry = AuditEntry::new(AuditCategory::Variable, action, AuditOutcome::Success)
+        .subject(name)
+        .message(message);
+
+    if let Err(err) = services.audit().record(entry).await {
+        warn!(error = %err, "Failed to record a variable change in the audit log.");
+    }
+}
@@ -13,13 +13,16 @@
 //! that is skipped is corrected by the next one rather than leaving a workflow
 //! stranded.
 
+use std::sync::Arc;
+
 use actix_web::{HttpResponse, http::StatusCode, web};
-use automate_api::{WorkflowId, WorkflowTrigger, WorkflowTypeDescriptor};
+use automate_api::{Snooze, WebhookLimits, WorkflowId, WorkflowTrigger, WorkflowTypeDescriptor};
 
 use super::json_error;
 use super::scope::Scoped;
 use crate::db::{AuditCategory, AuditEntry, AuditOutcome, AuditStore};
 use crate::prelude::*;
+use crate::webhook_payload::PayloadFilter;
 use crate::workflow_store::WorkflowDraft;
 
 /// The body of a request to create a workflow.
@@ -61,6 +64,58 @@ fn default_enabled() -> bool {
     true
 }
 
+/// The body of a request to preview a saved workflow.
+#[derive(Default, serde::Deserialize)]
+pub struct PreviewWorkflow {
+    /// The configuration to try, when it differs from the one saved. This is
+    /// what lets the editor preview a change before it is kept.
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+}
+
+/// The body of a request to preview a workflow that has not been saved.
+#[derive(serde::Deserialize)]
+pub struct PreviewDraft {
+    #[serde(rename = "type")]
+    pub type_id: String,
+
+    pub config: serde_json::Value,
+}
+
+/// How many runs a single request for a workflow's history returns.
+const DEFAULT_RUNS: usize = 25;
+const MAX_RUNS: usize = 100;
+
+/// Which page of a workflow's run history to read.
+#[derive(Default, serde::Deserialize)]
+pub struct RunsQuery {
+    /// Return runs that started before this, for paging back through them.
+    #[serde(default)]
+    pub before: Option<chrono::DateTime<chrono::Utc>>,
+
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Which two revisions to compare.
+#[derive(Default, serde::Deserialize)]
+pub struct RevisionDiffQuery {
+    #[serde(default)]
+    pub from: Option<u32>,
+
+    #[serde(default)]
+    pub to: Option<u32>,
+}
+
+/// The body of a request to replay a kept delivery.
+#[derive(Default, serde::Deserialize)]
+pub struct ReplayDelivery {
+    /// Replay it the way a preview would, reporting what it would file rather
+    /// than filing it.
+    #[serde(default)]
+    pub preview: bool,
+}
+
 /// `GET /api/v1/workflow-types` — the kinds of workflow that can be created,
 /// and the form that configures each.
 ///
@@ -105,7 +160,15 @@ pub async fn get(services: Scoped, id: web::Path<String>) -> HttpResponse {
 /// and a list of workflows carrying every one of their payloads is the problem
 /// this arrangement exists to avoid. Somebody looking into one failure asks
 /// about one workflow.
-pub async fn runs(services: Scoped, id: web::Path<String>) -> HttpResponse {
+///
+/// The runs are paged newest first: pass the `next` of one page as `before` to
+/// read the one after it. Every page carries the latest run and the latest
+/// failure as well, which outlive the history's retention.
+pub async fn runs(
+    services: Scoped,
+    id: web::Path<String>,
+    query: web::Query<RunsQuery>,
+) -> HttpResponse {
     let id = match parse_id(&id) {
         Ok(id) => id,
         Err(response) => return response,
@@ -119,15 +182,246 @@ pub async fn runs(services: Scoped, id: web::Path<String>) -> HttpResponse {
         Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
     }
 
+    let query = query.into_inner();
     match crate::runs::RunStore::new((*services).clone())
-        .get(id)
+        .history(
+            id,
+            query.before,
+            query.limit.unwrap_or(DEFAULT_RUNS).clamp(1, MAX_RUNS),
+        )
+        .await
+    {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `GET /api/v1/workflows/{workflow}/deliveries` — the deliveries this
+/// workflow was recently sent, newest first.
+///
+/// Summaries only, for the same reason [`runs`] is separate from the workflow:
+/// the bodies are the large part, and somebody reading a list reads one of them
+/// at a time.
+pub async fn deliveries(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match services.workflows().find(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    match crate::deliveries::DeliveryStore::new((*services).clone())
+        .list(id)
+        .await
+    {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `GET /api/v1/workflows/{workflow}/deliveries/{delivery}` — one delivery, as
+/// it arrived and as it was handled.
+pub async fn delivery(services: Scoped, path: web::Path<(String, String)>) -> HttpResponse {
+    let (id, delivery) = path.into_inner();
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let delivery = match delivery.parse::<automate_api::DeliveryId>() {
+        Ok(delivery) => delivery,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    match services.workflows().find(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    match crate::deliveries::DeliveryStore::new((*services).clone())
+        .get(id, delivery)
         .await
     {
-        Ok(state) => HttpResponse::Ok().json(state),
+        Ok(Some(found)) => HttpResponse::Ok().json(found),
+        Ok(None) => delivery_not_found(id, delivery),
         Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
     }
 }
 
+/// `POST /api/v1/workflows/{workflow}/deliveries/{delivery}/replay` — runs a
+/// kept delivery again, against the workflow as it is saved now.
+///
+/// Without this, trying a change to a workflow fed by an alerting system means
+/// waiting for the next real alert. The delivery is queued as the sender's was,
+/// so it is filtered, templated and filed by exactly the code a new one would
+/// be, and its run and delivery records say it was a replay.
+///
+/// The credentials withheld from what was kept are unsealed and sent with the
+/// replay, and the workflow checks its signature again as it would a new
+/// delivery's, overlooking only its age. One whose signature was turned away,
+/// or never checked by a workflow that checks them, is refused rather than run
+/// as though it had passed. Asked for as a `preview`, it is replayed the way
+/// [`preview`] replays the last one instead, files nothing, and may be any
+/// delivery that was kept.
+pub async fn replay(
+    services: Scoped,
+    path: web::Path<(String, String)>,
+    body: Option<web::Json<ReplayDelivery>>,
+) -> HttpResponse {
+    let (id, delivery) = path.into_inner();
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let delivery = match delivery.parse::<automate_api::DeliveryId>() {
+        Ok(delivery) => delivery,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+
+    let as_preview = body.is_some_and(|body| body.preview);
+
+    let stored = match services.workflows().find(id).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let workflow_type = match crate::workflows::lookup(&stored.type_id) {
+        Ok(workflow_type) => workflow_type,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    let store = crate::deliveries::DeliveryStore::new((*services).clone());
+    let kept = match store.get(id, delivery).await {
+        Ok(Some(kept)) => kept,
+        Ok(None) => return delivery_not_found(id, delivery),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    // What was kept of an oversized body identifies it but cannot be parsed,
+    // so replaying it would only report that.
+    if kept.truncated_from.is_some() {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "This delivery was too large to keep whole, so it cannot be replayed.",
+        );
+    }
+
+    if as_preview {
+        // Read as it was kept, credentials and all redacted: a preview acts on
+        // nothing, so it has nothing to prove.
+        let job = crate::webhooks::WebhookDelivery {
+            workflow: id,
+            event: WebhookEvent {
+                body: kept.body,
+                query: kept.query,
+                headers: kept.headers.into_iter().collect(),
+            },
+            replay_of: Some(delivery),
+            arrived_at: None,
+        };
+
+        let payload = match serde_json::to_value(&job) {
+            Ok(payload) => payload,
+            Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+        };
+
+        let options = crate::preview::Options {
+            replay: Some(stored.config),
+            ..Default::default()
+        };
+
+        let preview = observed(
+            &services,
+            workflow_type,
+            options,
+            &payload,
+            Some(id),
+            Some(kept.received_at),
+        )
+        .await;
+
+        return respond_with_preview(&services, &preview).await;
+    }
+
+    if matches!(
+        kept.signature,
+        Some(automate_api::SignatureVerdict::Rejected { .. })
+    ) {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "This delivery was turned away because its signature did not check out, so it will not be run as though it had. Replay it as a preview to see what it would have done.",
+        );
+    }
+
+    // Nothing was ever checked about a delivery kept without a verdict: a
+    // sender's retry dropped as a repeat, or one set aside while the workflow
+    // was snoozed. Anybody who knows the address could have sent it, so it is
+    // not run as though its signature had checked out.
+    if stored
+        .config
+        .pointer("/signature/enabled")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or_default()
+        && kept.signature != Some(automate_api::SignatureVerdict::Verified)
+    {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "This workflow checks signatures, and this delivery's was never checked, so it will not be run as though it had been. Replay it as a preview to see what it would have done.",
+        );
+    }
+
+    // The consumer would discard it, as it discards any delivery for a paused
+    // workflow, and say nothing to the person who asked.
+    if !stored.enabled {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            "This workflow is paused, so a replayed delivery would be discarded. Resume it first, or replay the delivery as a preview.",
+        );
+    }
+
+    // Sent with the credentials it arrived with, which the handler checks
+    // again; see `crate::preview::is_redelivery`.
+    let job = match store.request(id, &kept).await {
+        Ok(event) => crate::webhooks::WebhookDelivery {
+            workflow: id,
+            event,
+            replay_of: Some(delivery),
+            arrived_at: None,
+        },
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    // Not keyed: replaying the same delivery twice is two requests to run it,
+    // where a sender retrying is one delivery arriving twice.
+    if let Err(err) = services
+        .queue()
+        .enqueue(workflow_type.partition(), job, None, None)
+        .await
+    {
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+    }
+
+    record(
+        &services,
+        "replayed",
+        id,
+        format!(
+            "Replayed the delivery '{delivery}' received at {}.",
+            kept.received_at.to_rfc3339()
+        ),
+    )
+    .await;
+
+    HttpResponse::NoContent().finish()
+}
+
 /// `POST /api/v1/workflows` — configures a new workflow.
 pub async fn create(services: Scoped, body: web::Json<CreateWorkflow>) -> HttpResponse {
     let body = body.into_inner();
@@ -241,6 +535,105 @@ pub async fn delete(services: Scoped, id: web::Path<String>) -> HttpResponse {
     }
 }
 
+/// `POST /api/v1/workflows/{workflow}/duplicate` — creates a copy of a
+/// workflow, paused.
+///
+/// Paused because the copy is almost always about to be edited: a duplicated
+/// feed left running would file everything the original already filed, under
+/// the original's settings, before anybody had changed the one thing that was
+/// meant to differ. A webhook workflow's copy has its own address, since an
+/// address names exactly one workflow.
+pub async fn duplicate(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let store = services.workflows();
+
+    let original = match store.find(id).await {
+        Ok(Some(original)) => original,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let name = store
+        .present_record(original.clone())
+        .map(|workflow| workflow.name)
+        .unwrap_or_else(|_| id.to_string());
+
+    let draft = WorkflowDraft {
+        type_id: original.type_id,
+        config: original.config,
+        schedule: original.schedule,
+        enabled: false,
+    };
+
+    match store.create(draft).await {
+        Ok(workflow) => {
+            reconcile(&services).await;
+            record(
+                &services,
+                "created",
+                workflow.id,
+                format!(
+                    "Created the workflow '{}' as a copy of '{name}'.",
+                    workflow.name
+                ),
+            )
+            .await;
+
+            HttpResponse::Created().json(workflow)
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `POST /api/v1/workflows/{workflow}/template` — keeps a workflow as a
+/// template this account can use again.
+pub async fn save_as_template(
+    services: Scoped,
+    id: web::Path<String>,
+    body: web::Json<automate_api::SaveAsTemplate>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let record = match services.workflows().find(id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let draft = match crate::templates::from_workflow(&record, body.into_inner()) {
+        Ok(draft) => draft,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    match services.templates().create(draft).await {
+        Ok(template) => {
+            super::templates::record(
+                &services,
+                "created",
+                &template,
+                format!(
+                    "Saved the workflow '{id}' as the template '{}'.",
+                    template.name
+                ),
+            )
+            .await;
+
+            HttpResponse::Created().json(template)
+        }
+        Err(err) if err.is(human_errors::Kind::User) => {
+            json_error(StatusCode::BAD_REQUEST, err.description())
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
 /// `POST /api/v1/workflows/{workflow}/rotate-webhook` — issues a new address.
 ///
 /// The way a leaked URL is dealt with. The old one stops working immediately,
@@ -276,57 +669,349 @@ pub async fn rotate_webhook(services: Scoped, id: web::Path<String>) -> HttpResp
     }
 }
 
-/// `POST /api/v1/workflows/{workflow}/trigger` — runs a scheduled workflow now.
-///
-/// Dispatches exactly the message the schedule would have, so a run asked for
-/// here and a run that came round on its own are the same run. Anything this
-/// path did differently would be a difference nobody could see until it
-/// mattered.
+/// `PUT /api/v1/workflows/{workflow}/limits` — replaces what a webhook
+/// workflow's address accepts, and from whom.
 ///
-/// The schedule is deliberately left alone: running now is not a reason for the
-/// next run to move, and re-arming here would make "run now" quietly skip one.
-pub async fn trigger(services: Scoped, id: web::Path<String>) -> HttpResponse {
+/// The whole set is replaced rather than merged, so a limit left out of the
+/// body goes back to the installation's default.
+pub async fn set_limits(
+    services: Scoped,
+    id: web::Path<String>,
+    body: web::Json<WebhookLimits>,
+) -> HttpResponse {
     let id = match parse_id(&id) {
         Ok(id) => id,
         Err(response) => return response,
     };
 
-    let store = services.workflows();
+    match services.workflows().set_limits(id, body.into_inner()).await {
+        Ok(workflow) => {
+            record(
+                &services,
+                "limits-changed",
+                id,
+                format!(
+                    "Changed what the webhook address of '{}' accepts.",
+                    workflow.name
+                ),
+            )
+            .await;
 
-    let stored = match store.find(id).await {
-        Ok(Some(stored)) => stored,
-        Ok(None) => return not_found(id),
-        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
-    };
+            HttpResponse::Ok().json(workflow)
+        }
+        Err(err) => json_error(StatusCode::BAD_REQUEST, err.description()),
+    }
+}
 
-    let workflow_type = match crate::workflows::lookup(&stored.type_id) {
-        Ok(workflow_type) => workflow_type,
-        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+/// `PUT /api/v1/workflows/{workflow}/snooze` — quietens a workflow until a
+/// time, or for a number of runs, after which it carries on by itself.
+///
+/// Replaces any snooze already in place, so extending a maintenance window is
+/// the same request as starting one.
+pub async fn snooze(
+    services: Scoped,
+    id: web::Path<String>,
+    body: web::Json<Snooze>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
     };
 
-    // A webhook workflow runs on the delivery it was sent. There is no delivery
-    // here, and inventing one would run the workflow against a payload nobody
-    // sent it.
-    if !matches!(
-        workflow_type.descriptor().trigger,
-        WorkflowTrigger::Cron { .. }
-    ) {
-        return json_error(
-            StatusCode::BAD_REQUEST,
-            "This workflow runs when its webhook is called, so there is nothing to run on demand.",
-        );
-    }
+    let snooze = body.into_inner();
 
-    // Not refused for a paused workflow. Pausing stops the schedule; asking for
-    // a run is an explicit instruction, and being able to try one is most of the
-    // reason to pause a workflow you are still working on.
-    //
-    // Keyed by the workflow, as the schedule keys it, so asking twice while one
-    // is still waiting collapses onto the run already queued instead of running
-    // the same work twice.
-    if let Err(err) = services
-        .queue()
-        .enqueue(
+    match services.workflows().snooze(id, Some(snooze.clone())).await {
+        Ok(workflow) => {
+            let length = match (snooze.until, snooze.runs) {
+                (Some(until), None) => format!("until {}", until.to_rfc3339()),
+                (None, Some(1)) => "for its next run".to_string(),
+                (None, Some(runs)) => format!("for its next {runs} runs"),
+                (Some(until), Some(runs)) => {
+                    format!("until {} or for {runs} runs", until.to_rfc3339())
+                }
+                (None, None) => unreachable!("the store refuses a snooze that never ends"),
+            };
+
+            record(
+                &services,
+                "snoozed",
+                id,
+                format!("Snoozed '{}' {length}.", workflow.name),
+            )
+            .await;
+
+            HttpResponse::Ok().json(workflow)
+        }
+        Err(err) if err.is(human_errors::Kind::User) => {
+            json_error(StatusCode::BAD_REQUEST, err.description())
+        }
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `DELETE /api/v1/workflows/{workflow}/snooze` — wakes a snoozed workflow
+/// early, handing it whatever deliveries it was holding.
+pub async fn wake(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let store = services.workflows();
+
+    let existing = match store.find(id).await {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let workflow = match store.snooze(id, None).await {
+        Ok(workflow) => workflow,
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    // After the snooze is cleared, so that what is released is handled rather
+    // than held all over again.
+    let released =
+        match crate::webhooks::WebhookDelivery::release(&*services, id, &existing.type_id).await {
+            Ok(released) => released,
+            Err(err) => {
+                return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description());
+            }
+        };
+
+    if existing.snooze.is_some() {
+        record(
+            &services,
+            "woken",
+            id,
+            match released {
+                0 => format!("Woke '{}' before its snooze ended.", workflow.name),
+                1 => format!(
+                    "Woke '{}' before its snooze ended, releasing 1 held delivery.",
+                    workflow.name
+                ),
+                n => format!(
+                    "Woke '{}' before its snooze ended, releasing {n} held deliveries.",
+                    workflow.name
+                ),
+            },
+        )
+        .await;
+    }
+
+    HttpResponse::Ok().json(workflow)
+}
+
+/// `GET /api/v1/workflows/{workflow}/revisions` — every saved version of this
+/// workflow, newest first.
+pub async fn revisions(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match services.workflows().find(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    match crate::revisions::RevisionStore::new((*services).clone())
+        .list(id)
+        .await
+    {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+}
+
+/// `GET /api/v1/workflows/{workflow}/revisions/diff?from=&to=` — what changed
+/// between two of this workflow's revisions.
+///
+/// Both ends are optional, so that the common question needs neither: `to`
+/// is the latest revision unless named, and `from` the one saved before it.
+pub async fn revision_diff(
+    services: Scoped,
+    id: web::Path<String>,
+    query: web::Query<RevisionDiffQuery>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match services.workflows().find(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    let revisions = match crate::revisions::RevisionStore::new((*services).clone())
+        .list(id)
+        .await
+    {
+        Ok(revisions) => revisions,
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    // Newest first, so the latest is the first and the one before any given
+    // revision is the first that is older than it.
+    let to = match query.to {
+        Some(number) => revisions.iter().find(|r| r.number == number),
+        None => revisions.first(),
+    };
+    let Some(to) = to else {
+        return revision_not_found(id, query.to);
+    };
+
+    let from = match query.from {
+        Some(number) => revisions.iter().find(|r| r.number == number),
+        None => revisions.iter().find(|r| r.number < to.number),
+    };
+    let Some(from) = from else {
+        return match query.from {
+            Some(_) => revision_not_found(id, query.from),
+            None => json_error(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Revision {} is the first version of the workflow '{id}', so there is nothing before it to compare it with.",
+                    to.number
+                ),
+            ),
+        };
+    };
+
+    HttpResponse::Ok().json(automate_api::diff_revisions(from, to))
+}
+
+/// `POST /api/v1/workflows/{workflow}/revisions/{revision}/restore` — puts a
+/// workflow back as one of its revisions had it.
+///
+/// The undo for an edit that broke something. It is saved as an edit would
+/// be, so it becomes the newest revision and is itself one restore from being
+/// undone.
+pub async fn restore_revision(services: Scoped, path: web::Path<(String, String)>) -> HttpResponse {
+    let (id, number) = path.into_inner();
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let number = match number.parse::<u32>() {
+        Ok(number) => number,
+        Err(_) => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                format!("'{number}' is not a revision number."),
+            );
+        }
+    };
+
+    let store = services.workflows();
+
+    match store.find(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    match crate::revisions::RevisionStore::new((*services).clone())
+        .get(id, number)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return revision_not_found(id, Some(number)),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    }
+
+    match store.restore(id, number).await {
+        Ok(workflow) => {
+            reconcile(&services).await;
+            record(
+                &services,
+                "restored",
+                workflow.id,
+                format!(
+                    "Restored the workflow '{}' to revision {number}.",
+                    workflow.name
+                ),
+            )
+            .await;
+
+            HttpResponse::Ok().json(workflow)
+        }
+        // A revision whose configuration the workflow's type no longer accepts
+        // is refused as an edit to it would be.
+        Err(err) => json_error(StatusCode::BAD_REQUEST, err.description()),
+    }
+}
+
+/// `POST /api/v1/workflows/{workflow}/trigger` — runs a scheduled workflow now.
+///
+/// Dispatches exactly the message the schedule would have, so a run asked for
+/// here and a run that came round on its own are the same run. Anything this
+/// path did differently would be a difference nobody could see until it
+/// mattered.
+///
+/// The schedule is deliberately left alone: running now is not a reason for the
+/// next run to move, and re-arming here would make "run now" quietly skip one.
+pub async fn trigger(services: Scoped, id: web::Path<String>) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let store = services.workflows();
+
+    let stored = match store.find(id).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let workflow_type = match crate::workflows::lookup(&stored.type_id) {
+        Ok(workflow_type) => workflow_type,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err.description()),
+    };
+
+    // A webhook workflow runs on the delivery it was sent, and an event
+    // workflow on the event it was handed. There is neither here, and inventing
+    // one would run the workflow against something nobody sent it.
+    match workflow_type.descriptor().trigger {
+        WorkflowTrigger::Cron { .. } => {}
+        WorkflowTrigger::Event { .. } => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "This workflow runs when another workflow emits an event it follows, so there is nothing to run on demand.",
+            );
+        }
+        WorkflowTrigger::Webhook { .. } | WorkflowTrigger::RoutedWebhook { .. } => {
+            return json_error(
+                StatusCode::BAD_REQUEST,
+                "This workflow runs when its webhook is called, so there is nothing to run on demand.",
+            );
+        }
+    }
+
+    // Not refused for a paused workflow. Pausing stops the schedule; asking for
+    // a run is an explicit instruction, and being able to try one is most of the
+    // reason to pause a workflow you are still working on.
+    //
+    // Noted before the run is queued, so that it cannot be recorded before the
+    // note is there to say it was asked for. A run that then collapses onto a
+    // scheduled one already waiting is shown as asked for, which it also was.
+    let runs = crate::runs::RunStore::new((*services).clone());
+    if let Err(err) = runs.request(id).await {
+        warn!(error = %err, "Failed to note that a workflow was run on demand, so its run will be shown as scheduled.");
+    }
+
+    // Keyed by the workflow, as the schedule keys it, so asking twice while one
+    // is still waiting collapses onto the run already queued instead of running
+    // the same work twice.
+    if let Err(err) = services
+        .queue()
+        .enqueue(
             workflow_type.partition(),
             stored.config,
             Some(id.to_string().into()),
@@ -334,6 +1019,8 @@ pub async fn trigger(services: Scoped, id: web::Path<String>) -> HttpResponse {
         )
         .await
     {
+        // Otherwise the next scheduled run would be taken for this one.
+        let _ = runs.take_request(id).await;
         return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
     }
 
@@ -352,6 +1039,194 @@ pub async fn trigger(services: Scoped, id: web::Path<String>) -> HttpResponse {
     HttpResponse::NoContent().finish()
 }
 
+/// `POST /api/v1/workflows/{workflow}/preview` — what a run would collect and
+/// file, without filing it.
+///
+/// A scheduled workflow is run for real up to the point where it would change
+/// something: the feed is fetched and the filter evaluated, but the watermark
+/// stays put and the tasks are reported rather than queued (see
+/// [`crate::preview`]). A webhook workflow has nothing to fetch, so it is
+/// replayed against the delivery its last run kept instead.
+pub async fn preview(
+    services: Scoped,
+    id: web::Path<String>,
+    body: Option<web::Json<PreviewWorkflow>>,
+) -> HttpResponse {
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let stored = match services.workflows().find(id).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return not_found(id),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    let config = body
+        .and_then(|body| body.into_inner().config)
+        .unwrap_or(stored.config);
+
+    run_preview(&services, &stored.type_id, config, Some(id)).await
+}
+
+/// `POST /api/v1/workflows/preview` — previews a workflow before it is created.
+///
+/// The reason this exists: a new feed's first run files everything the feed
+/// still carries unless its backfill says otherwise, so without it the filter
+/// is tested by saving it.
+pub async fn preview_draft(services: Scoped, body: web::Json<PreviewDraft>) -> HttpResponse {
+    let body = body.into_inner();
+
+    run_preview(&services, &body.type_id, body.config, None).await
+}
+
+async fn run_preview(
+    services: &Scoped,
+    type_id: &str,
+    config: serde_json::Value,
+    id: Option<WorkflowId>,
+) -> HttpResponse {
+    let preview = match observe(services, type_id, config, id, None).await {
+        Ok(preview) => preview,
+        Err(response) => return response,
+    };
+
+    respond_with_preview(services, &preview).await
+}
+
+async fn respond_with_preview(
+    services: &Scoped,
+    preview: &automate_api::WorkflowPreview,
+) -> HttpResponse {
+    // The preview is rendered with the account's secrets like a real run, and
+    // what it shows is a response the API would otherwise never have given.
+    let secrets = match services.variables().load().await {
+        Ok(variables) => variables.secret_values(),
+        Err(err) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, err.description()),
+    };
+
+    match serde_json::to_value(preview) {
+        Ok(preview) => HttpResponse::Ok().json(crate::runs::scrub_value(&preview, &secrets)),
+        Err(err) => json_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+/// Runs a workflow under observation, as a preview does, and reports what it
+/// did.
+///
+/// `filter` replaces the workflow's own filter for the run, which is how
+/// [`super::filters::evaluate`] tries an expression against real items.
+pub(super) async fn observe(
+    services: &Scoped,
+    type_id: &str,
+    config: serde_json::Value,
+    id: Option<WorkflowId>,
+    filter: Option<Arc<PayloadFilter>>,
+) -> Result<automate_api::WorkflowPreview, HttpResponse> {
+    let workflow_type = match crate::workflows::lookup(type_id) {
+        Ok(workflow_type) => workflow_type,
+        Err(err) => return Err(json_error(StatusCode::BAD_REQUEST, err.description())),
+    };
+
+    // Checked up front so that a half-filled form gets the same message it
+    // would get on saving, rather than whatever the handler makes of it.
+    if let Err(err) = workflow_type.validate(&config) {
+        return Err(json_error(StatusCode::BAD_REQUEST, err.description()));
+    }
+
+    let (payload, options, replayed) = match workflow_type.descriptor().trigger {
+        WorkflowTrigger::Cron { .. } => (
+            config,
+            crate::preview::Options {
+                filter,
+                ..Default::default()
+            },
+            None,
+        ),
+        WorkflowTrigger::Webhook { .. }
+        | WorkflowTrigger::RoutedWebhook { .. }
+        | WorkflowTrigger::Event { .. } => {
+            let Some(id) = id else {
+                return Err(json_error(
+                    StatusCode::BAD_REQUEST,
+                    "A workflow started by a webhook or an event is previewed against the last one it received, and a new one has not received any yet. Save it, let it receive one, then preview it.",
+                ));
+            };
+
+            let last = match crate::runs::RunStore::new((**services).clone())
+                .get(id)
+                .await
+            {
+                Ok(Some(state)) => state.last,
+                Ok(None) => {
+                    return Err(json_error(
+                        StatusCode::BAD_REQUEST,
+                        "This workflow has not received a delivery or an event yet, so there is nothing to preview it against.",
+                    ));
+                }
+                Err(err) => {
+                    return Err(json_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        err.description(),
+                    ));
+                }
+            };
+
+            // An oversized delivery is kept as a prefix that identifies it but
+            // cannot be parsed, so replaying it would only report that.
+            let Some(input) = last.input.filter(|input| input.get("truncated").is_none()) else {
+                return Err(json_error(
+                    StatusCode::BAD_REQUEST,
+                    "The last delivery this workflow received was too large to keep whole, so it cannot be replayed.",
+                ));
+            };
+
+            let options = crate::preview::Options {
+                replay: Some(config),
+                filter,
+                ..Default::default()
+            };
+
+            (input, options, Some(last.started_at))
+        }
+    };
+
+    Ok(observed(services, workflow_type, options, &payload, id, replayed).await)
+}
+
+/// Runs a workflow's handler on one payload under an observer, and reports
+/// what it found.
+async fn observed(
+    services: &Scoped,
+    workflow_type: &dyn crate::workflows::WorkflowType,
+    options: crate::preview::Options,
+    payload: &serde_json::Value,
+    id: Option<WorkflowId>,
+    replayed: Option<chrono::DateTime<chrono::Utc>>,
+) -> automate_api::WorkflowPreview {
+    let (result, observation) = crate::preview::execute(
+        services,
+        options,
+        workflow_type.partition(),
+        payload,
+        id.map(|id| id.to_string()),
+    )
+    .await;
+
+    automate_api::WorkflowPreview {
+        items: observation.items,
+        tasks: observation
+            .dispatched
+            .into_iter()
+            .map(|dispatched| dispatched.describe())
+            .collect(),
+        effects: observation.effects,
+        replayed,
+        error: result.err().map(|err| err.description().to_string()),
+    }
+}
+
 /// What a reset cleared, so the browser can say so rather than guess.
 #[derive(serde::Serialize)]
 pub struct ResetSummary {
@@ -367,6 +1242,11 @@ pub struct ResetSummary {
 /// where its own state lives, so the operator does not have to know that an RSS
 /// watermark is keyed by feed URL under `rss/feed`.
 ///
+/// What the next run files is decided by the workflow's backfill policy, the
+/// same as for a workflow that has never run: clearing the watermark is all it
+/// takes to make the next run a first run, so nothing here needs to know about
+/// it. See [`crate::collectors::Backfill`].
+///
 /// Deliberately does not run the workflow afterwards. Clearing a watermark and
 /// running are separate decisions — the usual reason to reset is to fix
 /// something before the next scheduled run, and re-filing a year of backlog as
@@ -494,7 +1374,7 @@ pub async fn import(
 /// A failure here is logged rather than returned: the record has already been
 /// written, so reporting an error would describe a change that did happen as one
 /// that did not. The next reconciliation puts the schedule right.
-async fn reconcile(services: &Scoped) {
+pub(super) async fn reconcile(services: &Scoped) {
     if let Err(err) = crate::jobs::CronJob::reconcile(&**services).await {
         warn!(
             error = %err,
@@ -503,7 +1383,12 @@ async fn reconcile(services: &Scoped) {
     }
 }
 
-async fn record(services: &Scoped, action: &'static str, id: WorkflowId, message: impl ToString) {
+pub(super) async fn record(
+    services: &Scoped,
+    action: &'static str,
+    id: WorkflowId,
+    message: impl ToString,
+) {
     let entry = AuditEntry::new(AuditCategory::WorkflowConfig, action, AuditOutcome::Success)
         .subject(id)
         .message(message);
@@ -526,13 +1411,38 @@ fn not_found(id: WorkflowId) -> HttpResponse {
     )
 }
 
+/// Most often a delivery that has been let go to make room for newer ones,
+/// which is worth saying, since it was in the list a moment ago.
+fn delivery_not_found(id: WorkflowId, delivery: automate_api::DeliveryId) -> HttpResponse {
+    json_error(
+        StatusCode::NOT_FOUND,
+        format!(
+            "The workflow '{id}' has no delivery called '{delivery}'. Only its most recent deliveries are kept, so it may have been replaced by a newer one."
+        ),
+    )
+}
+
+/// A workflow with no revisions at all has never been saved since they were
+/// kept, which is the likelier reason for an empty history than a bad number.
+fn revision_not_found(id: WorkflowId, number: Option<u32>) -> HttpResponse {
+    json_error(
+        StatusCode::NOT_FOUND,
+        match number {
+            Some(number) => format!("The workflow '{id}' has no revision {number}."),
+            None => format!(
+                "The workflow '{id}' has no revisions yet. One is kept each time it is saved."
+            ),
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use actix_web::http::StatusCode;
     use actix_web::{App, test, web};
     use automate_api::{TenantId, Workflow, WorkflowTypeDescriptor};
 
-    use crate::db::{KeyValueStore, Queue};
+    use crate::db::{AuditStore, KeyValueStore, Queue};
     use crate::filter::Filter;
     use crate::prelude::Services;
     use crate::services::AppContext;
@@ -596,189 +1506,796 @@ mod tests {
         let req = test::TestRequest::get()
             .uri("/api/v1/workflow-types")
             .to_request();
-        let types: Vec<WorkflowTypeDescriptor> = test::call_and_read_body_json(&app, req).await;
-
-        let rss = types
-            .iter()
-            .find(|descriptor| descriptor.id == "rss")
-            .expect("the RSS workflow type should be offered");
+        let types: Vec<WorkflowTypeDescriptor> = test::call_and_read_body_json(&app, req).await;
+
+        let rss = types
+            .iter()
+            .find(|descriptor| descriptor.id == "rss")
+            .expect("the RSS workflow type should be offered");
+
+        assert!(
+            !rss.fields.is_empty(),
+            "a type with no fields would render as a form nobody could fill in",
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_created_workflow_comes_back_in_the_list() {
+        let app = app!(context().await);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let created: Workflow = test::read_body_json(resp).await;
+        assert_eq!(created.name, "Citation Needed");
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/workflows")
+            .to_request();
+        let listed: Vec<Workflow> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, created.id);
+    }
+
+    #[actix_web::test]
+    async fn a_duplicated_workflow_is_a_paused_copy_with_its_own_identity() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let original: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/duplicate", original.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let copy: Workflow = test::read_body_json(resp).await;
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.config, original.config);
+        assert!(
+            !copy.enabled,
+            "a copy should not run until somebody has changed what it was copied to change",
+        );
+
+        // Armed like any paused workflow's, so that resuming the copy is all it
+        // takes to start it; the run skips it until then.
+        assert_eq!(armed(&context).await.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn creating_a_workflow_arms_its_schedule() {
+        // The endpoint does not arm anything itself; it asks the reconciler to
+        // make the schedules match, which is the same path startup takes.
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let armed = armed(&context).await;
+        assert_eq!(armed.len(), 1);
+        assert_eq!(armed[0].payload["workflow"], created.id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn deleting_a_workflow_disarms_its_schedule() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/v1/workflows/{}", created.id))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT,
+        );
+
+        assert!(
+            armed(&context).await.is_empty(),
+            "deleting a workflow should stop it running without waiting for a restart",
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_workflow_can_be_run_on_demand() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT,
+        );
+
+        // The same message the schedule would have dispatched, in the same
+        // partition, so a run asked for and a run that came round are one thing.
+        let dispatched = queued(&context, "rss/todoist").await;
+        assert_eq!(dispatched.len(), 1);
+        assert_eq!(dispatched[0].payload["url"], "https://example.com/rss/");
+    }
+
+    #[actix_web::test]
+    async fn running_a_workflow_on_demand_leaves_its_schedule_where_it_was() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let before = armed(&context).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        // Running now is not a reason for the next run to move. Re-arming here
+        // would make asking for a run quietly skip the one that was coming.
+        let after = armed(&context).await;
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].hidden_until, before[0].hidden_until);
+    }
+
+    #[actix_web::test]
+    async fn a_paused_workflow_can_still_be_run_on_demand() {
+        // Pausing stops the schedule. Asking for a run is an explicit
+        // instruction, and being able to try one is most of the reason to pause
+        // a workflow you are still working on.
+        let context = context().await;
+        let app = app!(context);
+
+        let mut body = valid_body();
+        body["enabled"] = serde_json::json!(false);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(body)
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT,
+        );
+
+        assert_eq!(queued(&context, "rss/todoist").await.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn a_webhook_workflow_cannot_be_run_on_demand() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(serde_json::json!({
+                "type": "webhook",
+                "config": {
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                },
+            }))
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        // It runs on the delivery it was sent, and there is no delivery here.
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST,
+        );
+    }
+
+    /// A webhook workflow that files the deployment it was told about.
+    fn webhook_body() -> serde_json::Value {
+        serde_json::json!({
+            "type": "webhook",
+            "config": {
+                "name": "Deployments",
+                "title": "Deployed ${{ environment }}",
+                "todoist": { "connection": null },
+            },
+        })
+    }
+
+    #[actix_web::test]
+    async fn a_snooze_that_holds_deliveries_hands_them_over_when_the_workflow_is_woken() {
+        let context = context().await;
+        let app = app!(context);
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(webhook_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let until = chrono::Utc::now() + chrono::Duration::hours(2);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/v1/workflows/{}/snooze", created.id))
+            .set_json(serde_json::json!({ "until": until, "deliveries": "hold" }))
+            .to_request();
+        let snoozed: Workflow = test::call_and_read_body_json(&app, req).await;
+        assert!(
+            snoozed
+                .snooze
+                .and_then(|snooze| snooze.until)
+                .is_some_and(|snoozed_until| (snoozed_until - until).num_seconds() == 0)
+        );
+
+        let delivery = crate::webhooks::WebhookDelivery {
+            workflow: created.id,
+            event: crate::webhooks::WebhookEvent {
+                body: r#"{ "environment": "production" }"#.into(),
+                query: String::new(),
+                headers: Default::default(),
+            },
+            replay_of: None,
+            arrived_at: None,
+        };
+        let config: Option<serde_json::Value> = delivery
+            .config(&context.tenant(TenantId::local()))
+            .await
+            .unwrap();
+        assert!(
+            config.is_none(),
+            "a snoozed workflow should not handle it now"
+        );
+
+        let held = queued(&context, "webhooks/generic").await;
+        assert_eq!(
+            held.len(),
+            1,
+            "the delivery should be kept rather than dropped"
+        );
+        assert!(held[0].hidden_until > chrono::Utc::now() + chrono::Duration::hours(1));
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/v1/workflows/{}/snooze", created.id))
+            .to_request();
+        let woken: Workflow = test::call_and_read_body_json(&app, req).await;
+        assert!(woken.snooze.is_none());
+
+        let released = queued(&context, "webhooks/generic").await;
+        assert_eq!(released.len(), 1);
+        assert!(
+            released[0].hidden_until <= chrono::Utc::now(),
+            "waking the workflow early should not leave what it held waiting for the old end",
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_scheduled_workflow_has_no_deliveries_to_hold() {
+        let context = context().await;
+        let app = app!(context);
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/v1/workflows/{}/snooze", created.id))
+            .set_json(serde_json::json!({
+                "until": chrono::Utc::now() + chrono::Duration::hours(2),
+                "deliveries": "hold",
+            }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST,
+        );
+    }
+
+    #[actix_web::test]
+    async fn previewing_a_webhook_workflow_replays_its_last_delivery_against_the_edit() {
+        let context = context().await;
+        let app = app!(context);
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(webhook_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let now = chrono::Utc::now();
+        crate::runs::RunStore::new(context.tenant(TenantId::local()))
+            .record(
+                created.id,
+                automate_api::RunReport {
+                    started_at: now,
+                    finished_at: now,
+                    outcome: automate_api::RunOutcome::Succeeded,
+                    message: None,
+                    input: Some(serde_json::json!({
+                        "workflow": created.id,
+                        "event": {
+                            "body": r#"{"environment":"production"}"#,
+                            "query": "",
+                            "headers": {},
+                        },
+                    })),
+                    replay_of: None,
+                    trigger: None,
+                    counts: Default::default(),
+                    log: Default::default(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/preview", created.id))
+            .set_json(serde_json::json!({
+                "config": {
+                    "name": "Deployments",
+                    "title": "Shipped to ${{ environment }}",
+                    "todoist": { "connection": null },
+                },
+            }))
+            .to_request();
+        let preview: automate_api::WorkflowPreview = test::call_and_read_body_json(&app, req).await;
 
+        assert_eq!(preview.error, None);
+        assert_eq!(preview.replayed, Some(now));
+        assert_eq!(preview.tasks.len(), 1);
+        assert_eq!(
+            preview.tasks[0].title.as_deref(),
+            Some("Shipped to production"),
+            "the preview should use the configuration being edited, not the one saved",
+        );
         assert!(
-            !rss.fields.is_empty(),
-            "a type with no fields would render as a form nobody could fill in",
+            queued(&context, "todoist/create-task").await.is_empty(),
+            "a preview reports the task it would file rather than filing it",
         );
     }
 
     #[actix_web::test]
-    async fn a_created_workflow_comes_back_in_the_list() {
+    async fn a_webhook_workflow_that_has_received_nothing_has_nothing_to_preview() {
         let app = app!(context().await);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(valid_body())
+            .set_json(webhook_body())
             .to_request();
-        let resp = test::call_service(&app, req).await;
-        assert_eq!(resp.status(), StatusCode::CREATED);
-
-        let created: Workflow = test::read_body_json(resp).await;
-        assert_eq!(created.name, "Citation Needed");
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
 
-        let req = test::TestRequest::get()
-            .uri("/api/v1/workflows")
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/workflows/{}/preview", created.id))
             .to_request();
-        let listed: Vec<Workflow> = test::call_and_read_body_json(&app, req).await;
-
-        assert_eq!(listed.len(), 1);
-        assert_eq!(listed[0].id, created.id);
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST,
+        );
     }
 
     #[actix_web::test]
-    async fn creating_a_workflow_arms_its_schedule() {
-        // The endpoint does not arm anything itself; it asks the reconciler to
-        // make the schedules match, which is the same path startup takes.
+    async fn a_workflows_recent_deliveries_can_be_listed_and_read_back() {
         let context = context().await;
         let app = app!(context);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(valid_body())
+            .set_json(webhook_body())
             .to_request();
         let created: Workflow = test::call_and_read_body_json(&app, req).await;
 
-        let armed = armed(&context).await;
-        assert_eq!(armed.len(), 1);
-        assert_eq!(armed[0].payload["workflow"], created.id.to_string());
+        let event = crate::webhooks::WebhookEvent {
+            body: r#"{"environment":"production"}"#.to_string(),
+            query: String::new(),
+            headers: [("X-Signature".to_string(), "sha256=deadbeef".to_string())].into(),
+        };
+        crate::deliveries::DeliveryStore::new(context.tenant(TenantId::local()))
+            .record(
+                created.id,
+                crate::deliveries::describe(
+                    &event,
+                    chrono::Utc::now(),
+                    automate_api::RunOutcome::Succeeded,
+                    None,
+                    Default::default(),
+                    &[],
+                ),
+                &event,
+            )
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/workflows/{}/deliveries", created.id))
+            .to_request();
+        let listed: Vec<automate_api::DeliverySummary> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(listed.len(), 1);
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/workflows/{}/deliveries/{}",
+                created.id, listed[0].id
+            ))
+            .to_request();
+        let delivery: automate_api::DeliveryRecord = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(delivery.body, event.body);
+        assert_eq!(
+            delivery.headers["x-signature"], "<redacted>",
+            "a delivery's credentials should never be served back",
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/workflows/{}/deliveries/brisk-harbor",
+                created.id
+            ))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND,
+        );
     }
 
     #[actix_web::test]
-    async fn deleting_a_workflow_disarms_its_schedule() {
+    async fn a_webhook_workflows_limits_are_set_apart_from_its_settings() {
         let context = context().await;
         let app = app!(context);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(valid_body())
+            .set_json(webhook_body())
             .to_request();
         let created: Workflow = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            created.limits,
+            Some(Default::default()),
+            "a webhook workflow starts with the installation's limits",
+        );
 
-        let req = test::TestRequest::delete()
-            .uri(&format!("/api/v1/workflows/{}", created.id))
+        let uri = format!("/api/v1/workflows/{}/limits", created.id);
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .set_json(serde_json::json!({ "allow": "client_ip in [" }))
             .to_request();
         assert_eq!(
             test::call_service(&app, req).await.status(),
-            StatusCode::NO_CONTENT,
+            StatusCode::BAD_REQUEST,
+            "an allowlist that cannot be read should not be saved",
         );
 
-        assert!(
-            armed(&context).await.is_empty(),
-            "deleting a workflow should stop it running without waiting for a restart",
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .set_json(serde_json::json!({
+                "per_minute": 10,
+                "allow": r#"client_ip in ["192.0.2.10"]"#,
+            }))
+            .to_request();
+        let limited: Workflow = test::call_and_read_body_json(&app, req).await;
+        let limits = limited.limits.expect("the limits should be shown");
+        assert_eq!(limits.per_minute, Some(10));
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/v1/workflows/{}", created.id))
+            .set_json(serde_json::json!({
+                "config": created.config,
+                "enabled": true,
+            }))
+            .to_request();
+        let edited: Workflow = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            edited.limits,
+            Some(limits),
+            "editing the workflow's settings should leave its limits alone",
+        );
+    }
+
+    /// Keeps a delivery for the workflow, as the consumer would have.
+    async fn kept(
+        context: &AppContext,
+        workflow: automate_api::WorkflowId,
+        signature: Option<automate_api::SignatureVerdict>,
+    ) -> automate_api::DeliveryRecord {
+        let event = crate::webhooks::WebhookEvent {
+            body: r#"{"environment":"production"}"#.to_string(),
+            query: String::new(),
+            headers: [("X-Signature".to_string(), "sha256=deadbeef".to_string())].into(),
+        };
+        let mut delivery = crate::deliveries::describe(
+            &event,
+            chrono::Utc::now(),
+            automate_api::RunOutcome::Succeeded,
+            None,
+            Default::default(),
+            &[],
         );
+        delivery.signature = signature;
+
+        delivery.id = crate::deliveries::DeliveryStore::new(context.tenant(TenantId::local()))
+            .record(workflow, delivery.clone(), &event)
+            .await
+            .unwrap()
+            .expect("deliveries should be kept");
+
+        delivery
     }
 
     #[actix_web::test]
-    async fn a_workflow_can_be_run_on_demand() {
+    async fn a_kept_delivery_can_be_replayed_as_a_preview_or_for_real() {
         let context = context().await;
         let app = app!(context);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(valid_body())
+            .set_json(webhook_body())
             .to_request();
         let created: Workflow = test::call_and_read_body_json(&app, req).await;
 
+        let delivery = kept(
+            &context,
+            created.id,
+            Some(automate_api::SignatureVerdict::Verified),
+        )
+        .await;
+        let uri = format!(
+            "/api/v1/workflows/{}/deliveries/{}/replay",
+            created.id, delivery.id
+        );
+
         let req = test::TestRequest::post()
-            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .uri(&uri)
+            .set_json(serde_json::json!({ "preview": true }))
             .to_request();
+        let preview: automate_api::WorkflowPreview = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(preview.error, None);
+        assert_eq!(preview.replayed, Some(delivery.received_at));
+        assert_eq!(
+            preview.tasks[0].title.as_deref(),
+            Some("Deployed production")
+        );
+        assert!(
+            queued(&context, "todoist/create-task").await.is_empty(),
+            "a replay as a preview should file nothing",
+        );
+
+        let req = test::TestRequest::post().uri(&uri).to_request();
         assert_eq!(
             test::call_service(&app, req).await.status(),
             StatusCode::NO_CONTENT,
         );
 
-        // The same message the schedule would have dispatched, in the same
-        // partition, so a run asked for and a run that came round are one thing.
-        let dispatched = queued(&context, "rss/todoist").await;
-        assert_eq!(dispatched.len(), 1);
-        assert_eq!(dispatched[0].payload["url"], "https://example.com/rss/");
+        let partition = crate::workflows::lookup("webhook").unwrap().partition();
+        let waiting = context
+            .tenant(TenantId::local())
+            .queue()
+            .peek::<_, serde_json::Value>(partition, 10)
+            .await
+            .unwrap();
+        assert_eq!(waiting.len(), 1);
+        assert_eq!(
+            waiting[0].payload["replay_of"],
+            delivery.id.to_string(),
+            "the run should know it is a replay, so it does not hold the signature's age against it",
+        );
+        assert_eq!(
+            waiting[0].payload["event"]["body"],
+            r#"{"environment":"production"}"#
+        );
+        assert_eq!(
+            waiting[0].payload["event"]["headers"]["X-Signature"], "sha256=deadbeef",
+            "the replay should carry the signature it arrived with, to be checked again",
+        );
     }
 
     #[actix_web::test]
-    async fn running_a_workflow_on_demand_leaves_its_schedule_where_it_was() {
+    async fn a_delivery_whose_signature_was_refused_is_only_replayed_as_a_preview() {
         let context = context().await;
         let app = app!(context);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(valid_body())
+            .set_json(webhook_body())
             .to_request();
         let created: Workflow = test::call_and_read_body_json(&app, req).await;
 
-        let before = armed(&context).await;
+        let delivery = kept(
+            &context,
+            created.id,
+            Some(automate_api::SignatureVerdict::Rejected {
+                reason: "The signature did not match.".into(),
+            }),
+        )
+        .await;
+        let uri = format!(
+            "/api/v1/workflows/{}/deliveries/{}/replay",
+            created.id, delivery.id
+        );
+
+        let req = test::TestRequest::post().uri(&uri).to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST,
+            "one the signature check refused must not be run",
+        );
 
         let req = test::TestRequest::post()
-            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .uri(&uri)
+            .set_json(serde_json::json!({ "preview": true }))
             .to_request();
-        test::call_service(&app, req).await;
-
-        // Running now is not a reason for the next run to move. Re-arming here
-        // would make asking for a run quietly skip the one that was coming.
-        let after = armed(&context).await;
-        assert_eq!(after.len(), 1);
-        assert_eq!(after[0].hidden_until, before[0].hidden_until);
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK,);
     }
 
     #[actix_web::test]
-    async fn a_paused_workflow_can_still_be_run_on_demand() {
-        // Pausing stops the schedule. Asking for a run is an explicit
-        // instruction, and being able to try one is most of the reason to pause
-        // a workflow you are still working on.
+    async fn a_delivery_never_checked_is_not_replayed_for_a_workflow_that_checks_signatures() {
         let context = context().await;
         let app = app!(context);
-
-        let mut body = valid_body();
-        body["enabled"] = serde_json::json!(false);
-
+        let mut body = webhook_body();
+        body["config"]["signature"] = serde_json::json!({ "enabled": true, "secret": "s3cr3t" });
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
             .set_json(body)
             .to_request();
         let created: Workflow = test::call_and_read_body_json(&app, req).await;
 
-        let req = test::TestRequest::post()
-            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
-            .to_request();
+        // As a sender's retry dropped before any check is kept, which anybody
+        // who knows the address could have sent with a made-up delivery id.
+        let delivery = kept(&context, created.id, None).await;
+        let uri = format!(
+            "/api/v1/workflows/{}/deliveries/{}/replay",
+            created.id, delivery.id
+        );
+
+        let req = test::TestRequest::post().uri(&uri).to_request();
         assert_eq!(
             test::call_service(&app, req).await.status(),
-            StatusCode::NO_CONTENT,
+            StatusCode::BAD_REQUEST,
+        );
+        assert!(
+            queued(
+                &context,
+                crate::workflows::lookup("webhook").unwrap().partition()
+            )
+            .await
+            .is_empty(),
         );
-
-        assert_eq!(queued(&context, "rss/todoist").await.len(), 1);
     }
 
     #[actix_web::test]
-    async fn a_webhook_workflow_cannot_be_run_on_demand() {
+    async fn a_filter_is_tried_against_a_workflows_last_delivery_clause_by_clause() {
         let context = context().await;
         let app = app!(context);
-
         let req = test::TestRequest::post()
             .uri("/api/v1/workflows")
-            .set_json(serde_json::json!({
-                "type": "webhook",
-                "config": {
-                    "name": "Deployments",
-                    "title": "Deployed ${{ environment }}",
-                    "todoist": { "connection": null },
+            .set_json(webhook_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let now = chrono::Utc::now();
+        crate::runs::RunStore::new(context.tenant(TenantId::local()))
+            .record(
+                created.id,
+                automate_api::RunReport {
+                    started_at: now,
+                    finished_at: now,
+                    outcome: automate_api::RunOutcome::Succeeded,
+                    message: None,
+                    input: Some(serde_json::json!({
+                        "workflow": created.id,
+                        "event": {
+                            "body": r#"{"environment":"staging","services":[{"name":"api"}]}"#,
+                            "query": "",
+                            "headers": {},
+                        },
+                    })),
+                    replay_of: None,
+                    trigger: None,
+                    counts: Default::default(),
+                    log: Default::default(),
                 },
+            )
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/filters/evaluate")
+            .set_json(serde_json::json!({
+                "expression": r#"environment == "production" && "api" in services[*].name"#,
+                "workflow": created.id,
             }))
             .to_request();
-        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+        let evaluation: automate_api::FilterEvaluation =
+            test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(evaluation.replayed, Some(now));
+        assert_eq!(evaluation.items.len(), 1, "{evaluation:?}");
+        assert!(!evaluation.items[0].matched);
+
+        let clauses: Vec<_> = evaluation.items[0]
+            .clauses
+            .iter()
+            .map(|clause| (clause.expression.as_str(), clause.matched))
+            .collect();
+        assert_eq!(
+            clauses,
+            vec![
+                (
+                    r#"environment == "production" && "api" in services[*].name"#,
+                    false
+                ),
+                (r#"environment == "production""#, false),
+                (r#""api" in services[*].name"#, true),
+            ],
+            "each condition should be reported as written, with its own outcome",
+        );
+        assert!(
+            queued(&context, "todoist/create-task").await.is_empty(),
+            "trying a filter out should never file anything",
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_filter_is_tried_against_a_sample_payload() {
+        let app = app!(context().await);
 
-        // It runs on the delivery it was sent, and there is no delivery here.
         let req = test::TestRequest::post()
-            .uri(&format!("/api/v1/workflows/{}/trigger", created.id))
+            .uri("/api/v1/filters/evaluate")
+            .set_json(serde_json::json!({
+                "expression": r#"action == "opened" || "bug" in labels[*].name"#,
+                "payload": { "action": "closed", "labels": [{ "name": "bug" }] },
+            }))
+            .to_request();
+        let evaluation: automate_api::FilterEvaluation =
+            test::call_and_read_body_json(&app, req).await;
+
+        assert!(evaluation.items[0].matched, "{evaluation:?}");
+        assert_eq!(evaluation.items[0].clauses.len(), 3);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/filters/evaluate")
+            .set_json(serde_json::json!({ "expression": "action ==" , "payload": {} }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST,
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_draft_is_checked_before_it_is_previewed() {
+        let app = app!(context().await);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows/preview")
+            .set_json(serde_json::json!({ "type": "rss", "config": { "name": "No feed" } }))
             .to_request();
         assert_eq!(
             test::call_service(&app, req).await.status(),
             StatusCode::BAD_REQUEST,
+            "a half-filled form should be told what is missing, as it would be on saving",
         );
     }
 
@@ -1115,4 +2632,80 @@ mod tests {
         assert_eq!(updated.schedule.as_deref(), Some("@hourly"));
         assert!(!updated.enabled);
     }
+
+    #[actix_web::test]
+    async fn an_edit_can_be_compared_with_what_it_replaced_and_undone() {
+        let context = context().await;
+        let app = app!(context);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/workflows")
+            .set_json(valid_body())
+            .to_request();
+        let created: Workflow = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/v1/workflows/{}", created.id))
+            .set_json(serde_json::json!({
+                "config": {
+                    "name": "Citation Needed",
+                    "url": "https://example.com/broken/",
+                    "homepage": "https://example.com/",
+                },
+                "schedule": "@daily",
+                "enabled": true,
+            }))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/workflows/{}/revisions", created.id))
+            .to_request();
+        let revisions: Vec<automate_api::WorkflowRevision> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(revisions.len(), 2);
+        assert!(
+            revisions[0].saved_by.is_some(),
+            "a revision saved through the API should say who saved it",
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/workflows/{}/revisions/diff", created.id))
+            .to_request();
+        let diff: automate_api::RevisionDiff = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((diff.from, diff.to), (1, 2));
+        assert_eq!(diff.changes.len(), 1, "{:?}", diff.changes);
+        assert_eq!(diff.changes[0].path, "config.url");
+
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/workflows/{}/revisions/7/restore",
+                created.id
+            ))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND,
+        );
+
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/workflows/{}/revisions/1/restore",
+                created.id
+            ))
+            .to_request();
+        let restored: Workflow = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(restored.config, created.config);
+
+        let history = context
+            .tenant(TenantId::local())
+            .audit()
+            .audit(crate::db::AuditQuery::about(created.id, 10))
+            .await
+            .unwrap();
+        assert!(
+            history.iter().any(|entry| entry.action == "restored"),
+            "restoring a revision should be in the workflow's audit trail",
+        );
+    }
 }
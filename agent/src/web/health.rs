@@ -0,0 +1,84 @@
+//! `GET /healthz` and `GET /readyz` — whether the process is up, and whether
+//! it can do its work.
+//!
+//! Both are unauthenticated, because the orchestrator asking has no session
+//! and no address an operator would want to pin in an ACL. That is why the
+//! readiness breakdown says what it found without quoting the errors behind
+//! it; see [`crate::health::readiness`].
+
+use actix_web::{HttpResponse, web};
+use serde_json::json;
+use tracing_batteries::prelude::*;
+
+use crate::services::AppContext;
+
+/// Answers whenever the server does.
+///
+/// Deliberately checks nothing else: restarting the process is the answer to a
+/// process that has stopped answering, not to a database that has, and a
+/// liveness probe that failed on the latter would turn one outage into two.
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// Reports each of the checks in [`crate::health::readiness`], and refuses
+/// with a 503 while any of them fails.
+#[instrument("web.readyz", skip(context))]
+pub async fn ready(context: web::Data<AppContext>) -> HttpResponse {
+    let readiness = crate::health::readiness(&context).await;
+
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test, web};
+
+    use crate::services::AppContext;
+
+    async fn probe(context: &AppContext, uri: &str) -> (StatusCode, serde_json::Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(context.clone()))
+                .route("/healthz", web::get().to(super::live))
+                .route("/readyz", web::get().to(super::ready)),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn a_process_whose_job_host_has_stalled_is_alive_but_not_ready() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+
+        let (status, body) = probe(&context, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+
+        let (status, body) = probe(&context, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["checks"]["job_host"]["ok"], false);
+        assert_eq!(body["checks"]["database"]["ok"], true);
+    }
+
+    #[actix_web::test]
+    async fn a_process_whose_job_host_is_asking_for_work_is_ready() {
+        let context = AppContext::new_mock(|_| {}).await.unwrap();
+        context.job_host().beat();
+
+        let (status, body) = probe(&context, "/readyz").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["checks"]["secrets"]["ok"], true);
+        assert_eq!(body["checks"]["migrations"]["ok"], true);
+    }
+}
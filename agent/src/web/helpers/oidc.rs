@@ -143,7 +143,7 @@ pub async fn discovery<S: Services>(
                     let url = format!("{fetch_endpoint}/.well-known/openid-configuration");
                     let document: OidcDiscovery = http_client
                         .get(&url)
-                        .send()
+                        .send_counted("oidc")
                         .await
                         .wrap_system_err(
                             "Failed to fetch the OIDC discovery document from the provider.",
@@ -205,7 +205,7 @@ async fn jwks<S: Services>(
                 Box::pin(async move {
                     let keys: jsonwebtoken::jwk::JwkSet = http_client
                         .get(&fetch_uri)
-                        .send()
+                        .send_counted("oidc")
                         .await
                         .wrap_system_err(
                             "Failed to fetch the OIDC signing keys (JWKS) from the provider.",
@@ -418,7 +418,7 @@ async fn token_request(
     let response: ProviderTokenResponse = http_client
         .post(token_endpoint)
         .form(params)
-        .send()
+        .send_counted("oidc")
         .await
         .wrap_system_err(
             "Failed to reach the OIDC provider's token endpoint.",
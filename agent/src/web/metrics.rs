@@ -0,0 +1,125 @@
+//! `GET /metrics` — the installation's numbers, for Prometheus.
+//!
+//! Behind a filter of its own, `[web] metrics_acl`, rather than the sign-in
+//! the API uses: a scraper has no session to present, and an operator would
+//! rather recognise it by where it scrapes from. The page names partitions and
+//! providers, never accounts, but how busy an installation is is still not
+//! something to tell the internet, so it is refused unless the filter allows.
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use tracing_batteries::prelude::*;
+
+use super::helpers::oidc::AdminRequestFilter;
+use super::helpers::request::client_ip;
+use crate::metrics::Stored;
+use crate::services::AppContext;
+
+#[instrument("web.metrics", skip(req, context))]
+pub async fn serve(req: HttpRequest, context: web::Data<AppContext>) -> HttpResponse {
+    let config = context.config();
+    let filter = AdminRequestFilter {
+        method: req.method().as_str(),
+        path: req.path(),
+        client_ip: client_ip(config.web.trust_proxy, req.headers(), req.peer_addr()),
+        headers: req.headers(),
+        claims: None,
+    };
+
+    if !config.web.metrics_acl().matches(&filter).unwrap_or(false) {
+        // Not found rather than forbidden, as for a webhook address nobody was
+        // issued: the page is not advertised to anybody who may not read it.
+        return HttpResponse::NotFound().finish();
+    }
+
+    let database = context.database();
+    let stored = match (
+        database.queue_depth().await,
+        database.audit_log_size().await,
+    ) {
+        (Ok(queue), Ok(audit_entries)) => Stored {
+            queue,
+            audit_entries,
+        },
+        (Err(err), _) | (_, Err(err)) => {
+            // A scrape that fails is itself the signal: Prometheus notes `up`
+            // as zero, which is a better answer than numbers that are missing
+            // half of what they should say.
+            error!(error = %err, "Failed to read the stored metrics: {err}");
+            context.session().record_human_error(&err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(crate::metrics::global().render(&stored))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test, web};
+    use automate_api::TenantId;
+
+    use crate::db::Queue;
+    use crate::services::AppContext;
+
+    async fn scrape(acl: &str) -> (StatusCode, String) {
+        let acl = crate::filter::Filter::new(acl).unwrap();
+        let context = AppContext::new_mock(|config| config.web.metrics_acl = Some(acl))
+            .await
+            .unwrap();
+
+        context
+            .database()
+            .tenant(TenantId::local())
+            .enqueue("rss/todoist", "due", None, None)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(context.clone()))
+                .route("/metrics", web::get().to(super::serve)),
+        )
+        .await;
+
+        let res = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/metrics")
+                .insert_header(("X-Scraper", "prometheus"))
+                .to_request(),
+        )
+        .await;
+
+        let status = res.status();
+        let body = test::read_body(res).await;
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
+
+    #[actix_web::test]
+    async fn a_scraper_the_filter_allows_reads_the_queue_depth() {
+        let (status, body) = scrape(r#"headers.x-scraper == "prometheus""#).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            body.lines()
+                .any(|l| l
+                    == r#"automate_queue_messages{partition="rss/todoist",status="pending"} 1"#),
+            "{body}"
+        );
+        assert!(
+            body.lines().any(|l| l == "automate_audit_log_entries 0"),
+            "{body}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_scraper_the_filter_does_not_allow_is_not_told_the_page_exists() {
+        let (status, body) = scrape(r#"headers.x-scraper == "somebody else""#).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.is_empty());
+    }
+}
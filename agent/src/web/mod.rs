@@ -8,8 +8,10 @@ use crate::prelude::TenantId;
 use crate::services::{AppContext, AppServices};
 
 mod api;
+mod health;
 mod helpers;
 mod integrations;
+mod metrics;
 mod oauth;
 mod principal;
 mod telemetry;
@@ -64,6 +66,9 @@ pub async fn run_web_server(context: AppContext) -> Result<(), human_errors::Err
                     "/webhooks/{source}",
                     web::post().to(webhooks::deliver_source),
                 )
+                .route("/metrics", web::get().to(metrics::serve))
+                .route("/healthz", web::get().to(health::live))
+                .route("/readyz", web::get().to(health::ready))
                 .route("/robots.txt", web::get().to(ui::robots))
                 .default_service(web::get().to(ui::serve))
         })
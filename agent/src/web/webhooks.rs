@@ -3,7 +3,10 @@ use std::collections::HashMap;
 use actix_web::{Responder, web};
 use tracing_batteries::prelude::*;
 
+use super::helpers::oidc::AdminRequestFilter;
+use super::helpers::request::client_ip;
 use crate::db::Queue;
+use crate::metrics::Delivery;
 use crate::prelude::Services;
 use crate::webhooks::{Delivered, WebhookEvent};
 
@@ -12,7 +15,8 @@ use crate::webhooks::{Delivered, WebhookEvent};
 /// The path is anonymous by necessity — a sender has no credential beyond the
 /// URL — so the body was previously read with no limit at all, which let anybody
 /// who guessed nothing at all spend the agent's memory. A megabyte is far above
-/// what any provider sends and far below what hurts.
+/// what any provider sends and far below what hurts. A workflow reached at its
+/// own address can ask for less; see [`crate::webhooks::limits`].
 const MAX_BODY: usize = 1024 * 1024;
 
 /// `POST /webhooks/{source}` — the shared address a service posts every user's
@@ -34,10 +38,24 @@ pub async fn deliver_source(
     body: web::Payload,
     context: web::Data<crate::services::AppContext>,
 ) -> impl Responder {
+    // Counted as one source rather than by what was asked for, which is
+    // whatever a stranger cared to put in the path.
     let Some(source) = crate::webhooks::source(&source) else {
+        crate::metrics::global().delivery("unknown", Delivery::Refused);
         return actix_web::HttpResponse::NotFound().finish();
     };
 
+    let response = receive_from(source, req, body, &context).await;
+    crate::metrics::global().delivery(source.id(), Delivery::of(response.status()));
+    response
+}
+
+async fn receive_from(
+    source: &'static dyn crate::webhooks::WebhookSource,
+    req: actix_web::HttpRequest,
+    body: web::Payload,
+    context: &crate::services::AppContext,
+) -> actix_web::HttpResponse {
     let body = match body.to_bytes_limited(MAX_BODY).await {
         Ok(Ok(bytes)) => String::from_utf8_lossy(&bytes).to_string(),
         Ok(Err(err)) => {
@@ -59,7 +77,7 @@ pub async fn deliver_source(
         }
     });
 
-    match crate::webhooks::route(source, &context, event).await {
+    match crate::webhooks::route(source, context, event).await {
         // 200 rather than 204: Todoist documents anything other than a 200 as a
         // failed delivery, retries it three times, and then stops sending.
         Delivered::Accepted => actix_web::HttpResponse::Ok().finish(),
@@ -82,6 +100,17 @@ pub async fn deliver(
     body: web::Payload,
     context: web::Data<crate::services::AppContext>,
 ) -> impl Responder {
+    let response = receive(req, token, body, &context).await;
+    crate::metrics::global().delivery("workflow", Delivery::of(response.status()));
+    response
+}
+
+async fn receive(
+    req: actix_web::HttpRequest,
+    token: web::Path<String>,
+    body: web::Payload,
+    context: &crate::services::AppContext,
+) -> actix_web::HttpResponse {
     // Parsed before anything is read, so a body is never taken from a caller who
     // has not presented a well-formed token.
     let Ok(token) = token.parse::<automate_api::WebhookToken>() else {
@@ -152,6 +181,33 @@ pub async fn deliver(
         }
     }
 
+    // Who is asking is checked before anything else about the request, so a
+    // sender outside the allowlist learns nothing about the workflow — not even
+    // that it is paused — and spends none of the rate meant for the real one.
+    let config = services.config();
+    let sender = AdminRequestFilter {
+        method: req.method().as_str(),
+        path: req.path(),
+        client_ip: client_ip(config.web.trust_proxy, req.headers(), req.peer_addr()),
+        headers: req.headers(),
+        claims: None,
+    };
+
+    if !crate::webhooks::limits::allows(&record.limits, &sender) {
+        refused(
+            context,
+            &services,
+            record.id,
+            format!(
+                "Refused a delivery from {}, which the workflow's allowlist does not include.",
+                sender.client_ip.as_deref().unwrap_or("an unknown address")
+            ),
+        )
+        .await;
+
+        return actix_web::HttpResponse::Forbidden().finish();
+    }
+
     if !record.enabled {
         // Accepted and dropped rather than refused: the URL is real and the
         // sender did nothing wrong, and telling them otherwise would have them
@@ -165,13 +221,45 @@ pub async fn deliver(
         return actix_web::HttpResponse::NoContent().finish();
     }
 
-    let body = match body.to_bytes_limited(MAX_BODY).await {
+    if let Err(wait) = context.webhook_limiter().admit(
+        services.tenant(),
+        record.id,
+        &record.limits,
+        &config.deliveries,
+    ) {
+        refused(
+            context,
+            &services,
+            record.id,
+            "Refused deliveries arriving faster than the workflow accepts them.".to_string(),
+        )
+        .await;
+
+        // Rounded up, so a sender that waits as long as it is told is not
+        // refused again for arriving a fraction of a second early.
+        return actix_web::HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", wait.as_secs_f64().ceil().to_string()))
+            .finish();
+    }
+
+    let max_body = crate::webhooks::limits::max_body(&record.limits, MAX_BODY);
+    let body = match body.to_bytes_limited(max_body).await {
         Ok(Ok(bytes)) => String::from_utf8_lossy(&bytes).to_string(),
         Ok(Err(err)) => {
             error!("Failed to read webhook body: {}", err);
             return actix_web::HttpResponse::BadRequest().finish();
         }
         Err(_) => {
+            refused(
+                context,
+                &services,
+                record.id,
+                format!(
+                    "Refused a delivery larger than the {max_body} bytes the workflow accepts."
+                ),
+            )
+            .await;
+
             return actix_web::HttpResponse::PayloadTooLarge().finish();
         }
     };
@@ -188,19 +276,39 @@ pub async fn deliver(
         }
     });
 
-    let partition = match crate::workflows::lookup(&record.type_id) {
-        Ok(workflow) => workflow.partition().to_string(),
+    let (partition, delivery_header) = match crate::workflows::lookup(&record.type_id) {
+        Ok(workflow) => (
+            workflow.partition().to_string(),
+            workflow.delivery_header(&record.config),
+        ),
         Err(err) => {
             error!(error = %err, "A stored workflow names a type we no longer have: {err}");
             return actix_web::HttpResponse::InternalServerError().finish();
         }
     };
 
+    // A sender retrying a delivery it already made is told it arrived, so it
+    // stops, and nothing is queued; see `crate::webhooks::repeats`.
+    let delivery_id = delivery_header
+        .and_then(|header| event.header(&header).map(str::to_string))
+        .filter(|id| !id.is_empty());
+    if let Some(id) = &delivery_id
+        && crate::webhooks::repeats::is_repeat(&services, record.id, &event, id).await
+    {
+        return actix_web::HttpResponse::NoContent().finish();
+    }
+
     if let Err(err) = services
         .queue()
         .enqueue(
             partition,
-            serde_json::json!({ "workflow": record.id, "event": event }),
+            // Dated here, since the queue's own date moves if the delivery is
+            // held for a snoozed workflow; see `WebhookDelivery::arrived_at`.
+            serde_json::json!({
+                "workflow": record.id,
+                "event": event,
+                "arrived_at": chrono::Utc::now(),
+            }),
             None,
             None,
         )
@@ -208,6 +316,17 @@ pub async fn deliver(
     {
         error!(error = %err, "Failed to enqueue a webhook delivery: {err}");
         services.session().record_human_error(&err);
+
+        // Otherwise the retry this asks for would be dropped as a repeat of a
+        // delivery that was never queued.
+        if let Some(id) = &delivery_id
+            && let Err(err) = crate::webhooks::repeats::SeenDeliveries::new(&services)
+                .unnote(record.id, id)
+                .await
+        {
+            warn!(error = %err, "Failed to forget a webhook delivery that could not be queued: {err}");
+        }
+
         return actix_web::HttpResponse::InternalServerError().finish();
     }
 
@@ -248,6 +367,28 @@ async fn audit(
     }
 }
 
+/// Notes a delivery turned away by the workflow's limits, at most once a minute.
+///
+/// These are the refusals a flood produces, so unlike a mismatched address
+/// they are rate limited themselves; see [`crate::webhooks::limits::Limiter`].
+/// The first of a run is enough to tell the owner what is happening, and the
+/// log line for each of the rest is there for whoever wants the detail.
+async fn refused(
+    context: &crate::services::AppContext,
+    services: &crate::services::AppServices,
+    workflow: automate_api::WorkflowId,
+    message: String,
+) {
+    info!(workflow.id = %workflow, "{message}");
+
+    if context
+        .webhook_limiter()
+        .should_note(services.tenant(), workflow)
+    {
+        audit(services, workflow, crate::db::AuditOutcome::Denied, message).await;
+    }
+}
+
 /// The one answer given to every delivery we will not accept.
 ///
 /// A token nobody was issued, one that has been rotated away, a workflow since
@@ -413,6 +554,153 @@ mod tests {
         );
     }
 
+    #[actix_web::test]
+    async fn a_sender_retrying_a_delivery_is_answered_but_not_queued_again() {
+        let context = context().await;
+        let services = context.tenant(TenantId::local());
+        let workflow = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&context.tenant(TenantId::system()))
+            .create(WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "delivery_header": "X-Request-ID",
+                    "todoist": { "connection": null },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        let app = app!(context);
+        let path = workflow.webhook_path.as_deref().unwrap();
+
+        for request_id in ["attempt-1", "attempt-1", "attempt-2"] {
+            let req = test::TestRequest::post()
+                .uri(path)
+                .insert_header(("Content-Type", "application/json"))
+                .insert_header(("X-Request-ID", request_id))
+                .set_payload(r#"{"environment":"production"}"#)
+                .to_request();
+            assert_eq!(
+                test::call_service(&app, req).await.status(),
+                StatusCode::NO_CONTENT,
+                "a retry should be told it arrived, or the sender will keep sending it",
+            );
+        }
+
+        let queued: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("webhooks/generic", 10).await.unwrap();
+        assert_eq!(
+            queued.len(),
+            2,
+            "the retry of the first delivery should be dropped, and the second queued",
+        );
+
+        let kept = crate::deliveries::DeliveryStore::new(&services)
+            .list(workflow.id)
+            .await
+            .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].repeat_of.as_deref(), Some("attempt-1"));
+    }
+
+    #[actix_web::test]
+    async fn a_workflows_limits_turn_away_strangers_floods_and_oversized_bodies() {
+        use crate::db::{AuditQuery, AuditStore};
+
+        let context = context().await;
+        let services = context.tenant(TenantId::local());
+        let store = crate::workflow_store::WorkflowStore::new(&services)
+            .with_index(&context.tenant(TenantId::system()));
+        let workflow = store
+            .create(WorkflowDraft {
+                type_id: "webhook".into(),
+                config: serde_json::json!({
+                    "name": "Deployments",
+                    "title": "Deployed ${{ environment }}",
+                    "todoist": { "connection": null },
+                }),
+                schedule: None,
+                enabled: true,
+            })
+            .await
+            .unwrap();
+        store
+            .set_limits(
+                workflow.id,
+                automate_api::WebhookLimits {
+                    per_minute: Some(1),
+                    burst: Some(2),
+                    max_body_bytes: Some(64),
+                    allow: Some(r#"client_ip in ["192.0.2.10"]"#.into()),
+                },
+            )
+            .await
+            .unwrap();
+        let app = app!(context);
+        let path = workflow.webhook_path.as_deref().unwrap();
+
+        let send = |from: &str, body: String| {
+            test::TestRequest::post()
+                .uri(path)
+                .peer_addr(format!("{from}:4321").parse().unwrap())
+                .insert_header(("Content-Type", "application/json"))
+                .set_payload(body)
+                .to_request()
+        };
+        let small = r#"{"environment":"production"}"#.to_string();
+
+        let res = test::call_service(&app, send("198.51.100.7", small.clone())).await;
+        assert_eq!(
+            res.status(),
+            StatusCode::FORBIDDEN,
+            "a sender outside the allowlist should be refused however it got the address",
+        );
+
+        let res = test::call_service(&app, send("192.0.2.10", small.clone())).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = test::call_service(&app, send("192.0.2.10", "x".repeat(65))).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let res = test::call_service(&app, send("192.0.2.10", small)).await;
+        assert_eq!(
+            res.status(),
+            StatusCode::TOO_MANY_REQUESTS,
+            "the burst is spent, so the rate should apply",
+        );
+        assert_eq!(
+            res.headers()
+                .get("Retry-After")
+                .and_then(|value| value.to_str().ok()),
+            Some("60"),
+            "the sender should be told when to come back",
+        );
+
+        let queued: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek("webhooks/generic", 10).await.unwrap();
+        assert_eq!(
+            queued.len(),
+            1,
+            "only the delivery within every limit should be queued"
+        );
+
+        let noted = services
+            .audit()
+            .audit(AuditQuery::about(workflow.id, 10))
+            .await
+            .unwrap();
+        assert_eq!(
+            noted.len(),
+            1,
+            "a run of refusals should be noted once rather than once a request",
+        );
+        let message = noted[0].message.as_deref().unwrap_or_default();
+        assert!(message.contains("198.51.100.7"), "{message}");
+    }
+
     #[actix_web::test]
     async fn a_github_app_delivery_routes_only_to_the_tenant_with_that_installation() {
         use hmac::{Hmac, KeyInit, Mac};
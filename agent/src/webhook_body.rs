@@ -0,0 +1,470 @@
+//! Reading a delivery's body as JSON, whatever it was posted as.
+//!
+//! The generic webhook addresses a delivery by path (see
+//! [`crate::webhook_payload`]), which presumes a JSON document to walk. Plenty of
+//! the things people want to point at it do not post JSON: appliances and
+//! older tools post HTML-form encoding, enterprise software posts XML, and a
+//! shell script with `curl -d` posts whatever text it had. Rather than teach the
+//! filter and the templates three more syntaxes, each of those is read into the
+//! same [`serde_json::Value`] shape, so `JsonFilter` and `render` work on them
+//! unchanged:
+//!
+//! * A form becomes an object of its fields. A field given more than once
+//!   becomes a list of its values, which is what `in` expects.
+//! * XML becomes nested objects named after the elements, starting from the
+//!   root: `<alert severity="high"><host>db1</host></alert>` is
+//!   `alert.severity` and `alert.host`. An element with only text is that text;
+//!   one with attributes or children as well keeps its text under `text`.
+//!   Repeated elements become a list, as repeated form fields do.
+//! * Anything else that is text is `{ "body": "…" }`.
+//!
+//! # The request around the body
+//!
+//! Some senders put what matters in the URL or the headers rather than the
+//! body — `?host=db1`, `X-Event: deploy`. Those are added alongside the body as
+//! `query` and `headers`, so `query.host` and `headers.x_event` can be filtered
+//! on too. Header names are lowercased with their hyphens made underscores,
+//! because a hyphen would read as subtraction in a filter, and headers that
+//! carry credentials are left out altogether (see [`crate::runs`] for which):
+//! a template could otherwise copy a sender's token into a task. A body that
+//! has its own `query` or `headers` field keeps it, since the sender's payload
+//! is what a path was most likely written against.
+//!
+//! # What is not trusted
+//!
+//! Only XML's own five entities and character references are expanded. A
+//! document type's entities are left as written, so a delivery cannot expand
+//! itself into something enormous or reach for a file on this machine.
+
+use quick_xml::events::{BytesStart, Event};
+use serde_json::{Map, Value};
+
+use crate::prelude::*;
+
+/// The shapes a body can be read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Json,
+    Form,
+    Xml,
+    /// Declared as text, or not declared at all. JSON posted as `text/plain`
+    /// is common enough — it is what a `curl -d` without a header sends — that
+    /// it is tried first.
+    Text,
+    /// Declared as something else, such as `application/octet-stream`. Read
+    /// as JSON, as every body was before the others were understood.
+    Other,
+}
+
+impl Kind {
+    fn of(event: &WebhookEvent) -> Self {
+        let media_type = event
+            .header("content-type")
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match media_type.as_str() {
+            "" => Self::Text,
+            "application/json" => Self::Json,
+            "application/x-www-form-urlencoded" => Self::Form,
+            "application/xml" | "text/xml" => Self::Xml,
+            other if other.ends_with("+json") => Self::Json,
+            other if other.ends_with("+xml") => Self::Xml,
+            other if other.starts_with("text/") => Self::Text,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The delivery's body as JSON, with its query string and headers beside it.
+///
+/// Fails when the body does not read as what it was declared to be — a form
+/// that is not a form is a sender pointed at the wrong place, and guessing at
+/// what it meant would file tasks nobody asked for.
+pub fn read(event: &WebhookEvent) -> Result<Value, human_errors::Error> {
+    let body = match Kind::of(event) {
+        Kind::Json | Kind::Other => serde_json::from_str(&event.body).wrap_user_err(
+            "The delivery's body is not valid JSON.",
+            &[
+                "Check that the sender posts JSON, or that it sets a Content-Type saying what it posts instead.",
+            ],
+        )?,
+        Kind::Form => Value::Object(form(&event.body)),
+        Kind::Xml => xml(&event.body)?,
+        Kind::Text => match serde_json::from_str::<Value>(&event.body) {
+            Ok(value @ (Value::Object(_) | Value::Array(_))) => value,
+            _ => serde_json::json!({ "body": event.body }),
+        },
+    };
+
+    Ok(with_request(body, event))
+}
+
+/// Adds `query` and `headers` to a body that is an object and does not already
+/// have them.
+fn with_request(mut body: Value, event: &WebhookEvent) -> Value {
+    if let Value::Object(fields) = &mut body {
+        fields
+            .entry("query")
+            .or_insert_with(|| Value::Object(form(&event.query)));
+
+        fields.entry("headers").or_insert_with(|| {
+            let mut headers = Map::new();
+            for (name, value) in &event.headers {
+                if !crate::runs::is_sensitive(name) {
+                    headers.insert(
+                        name.to_ascii_lowercase().replace('-', "_"),
+                        Value::String(value.clone()),
+                    );
+                }
+            }
+            Value::Object(headers)
+        });
+    }
+
+    body
+}
+
+/// Reads `a=1&b=two+words&a=2` as `{ "a": ["1", "2"], "b": "two words" }`.
+fn form(encoded: &str) -> Map<String, Value> {
+    fn decode(component: &str) -> String {
+        let component = component.replace('+', " ");
+        String::from_utf8_lossy(&urlencoding::decode_binary(component.as_bytes())).into_owned()
+    }
+
+    let mut fields = Map::new();
+    for pair in encoded.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        insert(&mut fields, decode(key), Value::String(decode(value)));
+    }
+
+    fields
+}
+
+/// Adds a value under a key, turning the key into a list if it already has one.
+fn insert(fields: &mut Map<String, Value>, key: String, value: Value) {
+    match fields.get_mut(&key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            fields.insert(key, value);
+        }
+    }
+}
+
+/// An element being read: its name, and what has been found inside it so far.
+struct Element {
+    name: String,
+    fields: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn open(start: &BytesStart<'_>) -> Result<Self, human_errors::Error> {
+        let mut fields = Map::new();
+
+        for attribute in start.attributes() {
+            let attribute = attribute.or_user_err(&[
+                "The delivery's XML has a malformed attribute. Check that the sender posts well-formed XML.",
+            ])?;
+
+            // Namespace declarations say how to read the names, and are not
+            // something anybody would want to filter on.
+            let qualified = attribute.key.as_ref();
+            if qualified == b"xmlns" || qualified.starts_with(b"xmlns:") {
+                continue;
+            }
+
+            let value = attribute
+                .normalized_value(quick_xml::XmlVersion::Implicit1_0)
+                .or_user_err(&[
+                    "The delivery's XML has an attribute that could not be read. Check that the sender posts well-formed XML.",
+                ])?;
+
+            insert(
+                &mut fields,
+                name(attribute.key.local_name().as_ref()),
+                Value::String(value.into_owned()),
+            );
+        }
+
+        Ok(Self {
+            name: name(start.local_name().as_ref()),
+            fields,
+            text: String::new(),
+        })
+    }
+
+    fn close(self) -> (String, Value) {
+        let text = self.text.trim();
+
+        let value = if self.fields.is_empty() {
+            Value::String(text.to_string())
+        } else {
+            let mut fields = self.fields;
+            if !text.is_empty() {
+                fields
+                    .entry("text")
+                    .or_insert_with(|| Value::String(text.to_string()));
+            }
+            Value::Object(fields)
+        };
+
+        (self.name, value)
+    }
+}
+
+/// An element or attribute's name without its namespace prefix, which a path
+/// could not address.
+fn name(local: &[u8]) -> String {
+    String::from_utf8_lossy(local).into_owned()
+}
+
+/// Reads an XML document as `{ "<root>": … }`.
+///
+/// Walked with an explicit stack rather than recursively, so that however
+/// deeply a delivery nests its elements it cannot exhaust ours.
+fn xml(document: &str) -> Result<Value, human_errors::Error> {
+    const ADVICE: &[&str] = &["Check that the sender posts well-formed XML."];
+
+    let mut reader = quick_xml::Reader::from_str(document);
+    let mut open: Vec<Element> = Vec::new();
+    let mut root: Option<(String, Value)> = None;
+
+    let mut close = |element: Element, open: &mut Vec<Element>| match open.last_mut() {
+        Some(parent) => {
+            let (name, value) = element.close();
+            insert(&mut parent.fields, name, value);
+            Ok(())
+        }
+        None if root.is_none() => {
+            root = Some(element.close());
+            Ok(())
+        }
+        None => Err(human_errors::user(
+            "The delivery's XML has more than one root element.",
+            ADVICE,
+        )),
+    };
+
+    loop {
+        let event = reader.read_event().wrap_user_err(
+            format!(
+                "The delivery's XML could not be read at byte {}.",
+                reader.error_position()
+            ),
+            ADVICE,
+        )?;
+
+        match event {
+            Event::Start(start) => open.push(Element::open(&start)?),
+            Event::Empty(start) => close(Element::open(&start)?, &mut open)?,
+            Event::End(_) => {
+                let element = open.pop().ok_or_else(|| {
+                    human_errors::user(
+                        "The delivery's XML closes an element it never opened.",
+                        ADVICE,
+                    )
+                })?;
+                close(element, &mut open)?;
+            }
+            Event::Text(text) => {
+                if let Some(element) = open.last_mut() {
+                    let text = text.xml10_content().or_user_err(ADVICE)?;
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = open.last_mut() {
+                    let text = data.decode().or_user_err(ADVICE)?;
+                    element.text.push_str(&text);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(element) = open.last_mut() {
+                    let entity = reference.decode().or_user_err(ADVICE)?;
+
+                    if let Some(ch) = reference.resolve_char_ref().or_user_err(ADVICE)? {
+                        element.text.push(ch);
+                    } else if let Some(resolved) =
+                        quick_xml::escape::resolve_predefined_entity(&entity)
+                    {
+                        element.text.push_str(resolved);
+                    } else {
+                        // Left as written; see the module's notes on trust.
+                        element.text.push('&');
+                        element.text.push_str(&entity);
+                        element.text.push(';');
+                    }
+                }
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+    }
+
+    if !open.is_empty() {
+        return Err(human_errors::user(
+            "The delivery's XML ends before every element in it is closed.",
+            ADVICE,
+        ));
+    }
+
+    let (name, value) = root
+        .ok_or_else(|| human_errors::user("The delivery's XML has no elements in it.", ADVICE))?;
+
+    let mut document = Map::new();
+    document.insert(name, value);
+    Ok(Value::Object(document))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(content_type: Option<&str>, body: &str) -> WebhookEvent {
+        WebhookEvent {
+            body: body.to_string(),
+            query: String::new(),
+            headers: content_type
+                .map(|value| HashMap::from([("Content-Type".to_string(), value.to_string())]))
+                .unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn a_json_body_is_read_as_it_always_was() {
+        let payload = read(&event(
+            Some("application/json; charset=utf-8"),
+            r#"{"action":"deployed"}"#,
+        ))
+        .unwrap();
+
+        assert_eq!(payload["action"], "deployed");
+    }
+
+    #[test]
+    fn a_form_becomes_an_object_of_its_fields() {
+        let payload = read(&event(
+            Some("application/x-www-form-urlencoded"),
+            "status=down&host=db1&message=disk+at+95%25&tag=prod&tag=eu",
+        ))
+        .unwrap();
+
+        assert_eq!(payload["status"], "down");
+        assert_eq!(payload["message"], "disk at 95%");
+        assert_eq!(
+            payload["tag"],
+            json!(["prod", "eu"]),
+            "a field given more than once should be a list of every value",
+        );
+    }
+
+    #[test]
+    fn xml_becomes_nested_objects_named_after_its_elements() {
+        let payload = read(&event(
+            Some("application/xml"),
+            r#"<?xml version="1.0"?>
+            <alert severity="high" xmlns:x="urn:example">
+                <host>db1</host>
+                <x:check id="disk">Disk &amp; inodes</x:check>
+                <tag>prod</tag>
+                <tag>eu</tag>
+                <resolved/>
+            </alert>"#,
+        ))
+        .unwrap();
+
+        assert_eq!(payload["alert"]["severity"], "high");
+        assert_eq!(payload["alert"]["host"], "db1");
+        assert_eq!(
+            payload["alert"]["check"],
+            json!({ "id": "disk", "text": "Disk & inodes" }),
+            "an element with attributes should keep its text beside them, without its namespace prefix",
+        );
+        assert_eq!(payload["alert"]["tag"], json!(["prod", "eu"]));
+        assert_eq!(payload["alert"]["resolved"], "");
+        assert!(
+            payload["alert"].get("x").is_none(),
+            "a namespace declaration is not an attribute anybody would filter on",
+        );
+    }
+
+    #[test]
+    fn xml_entities_from_a_document_type_are_not_expanded() {
+        let payload = read(&event(
+            Some("text/xml"),
+            r#"<!DOCTYPE note [<!ENTITY secret SYSTEM "file:///etc/passwd">]><note>&secret;</note>"#,
+        ))
+        .unwrap();
+
+        assert_eq!(payload["note"], "&secret;");
+    }
+
+    #[test]
+    fn xml_that_is_not_well_formed_is_refused() {
+        assert!(read(&event(Some("application/xml"), "<alert><host>db1</alert>")).is_err());
+        assert!(read(&event(Some("application/xml"), "<a/><b/>")).is_err());
+        assert!(read(&event(Some("application/xml"), "")).is_err());
+    }
+
+    #[test]
+    fn text_is_kept_whole_under_body() {
+        let payload = read(&event(Some("text/plain"), "Backup finished in 4m")).unwrap();
+        assert_eq!(payload["body"], "Backup finished in 4m");
+
+        let payload = read(&event(None, "Backup finished in 4m")).unwrap();
+        assert_eq!(
+            payload["body"], "Backup finished in 4m",
+            "a body with no declared type should be read as text rather than discarded",
+        );
+    }
+
+    #[test]
+    fn json_posted_as_text_is_still_read_as_json() {
+        let payload = read(&event(Some("text/plain"), r#"{"action":"deployed"}"#)).unwrap();
+        assert_eq!(payload["action"], "deployed");
+    }
+
+    #[test]
+    fn the_query_string_and_headers_are_addressable_beside_the_body() {
+        let mut event = event(Some("text/plain"), "ping");
+        event.query = "host=db1&env=prod".to_string();
+        event
+            .headers
+            .insert("X-Event-Type".to_string(), "deploy".to_string());
+        event
+            .headers
+            .insert("Authorization".to_string(), "Bearer hunter2".to_string());
+        event
+            .headers
+            .insert("X-Hub-Signature-256".to_string(), "sha256=abc".to_string());
+
+        let payload = read(&event).unwrap();
+
+        assert_eq!(payload["query"]["host"], "db1");
+        assert_eq!(payload["headers"]["x_event_type"], "deploy");
+        assert_eq!(payload["headers"]["content_type"], "text/plain");
+        assert!(
+            payload["headers"].get("authorization").is_none()
+                && payload["headers"].get("x_hub_signature_256").is_none(),
+            "a header carrying a credential should never be something a template can copy into a task",
+        );
+    }
+
+    #[test]
+    fn a_body_with_its_own_query_field_keeps_it() {
+        let mut event = event(Some("application/json"), r#"{"query":"SELECT 1"}"#);
+        event.query = "host=db1".to_string();
+
+        assert_eq!(read(&event).unwrap()["query"], "SELECT 1");
+    }
+}
@@ -17,7 +17,9 @@
 //!
 //! # One addressing syntax, one interpolation syntax
 //!
-//! Both halves resolve a dotted path against the payload, and [`render`] is
+//! Both halves resolve a path against the payload in the syntax
+//! [`automate_api::payload_path`] defines — dotted, with `[0]`, `[*]` and
+//! `[?field == "x"]` steps into arrays — and [`render`] is
 //! built on [`crate::parsers::interpolate`] rather than on a template engine of
 //! its own. That is deliberate: the configuration file already uses `${{ ... }}`
 //! for environment substitution, and a product which spells the same idea two
@@ -36,7 +38,14 @@
 // storage traits in `crate::db`.
 #![allow(dead_code)]
 
-use crate::filter::{FilterValue, Filterable, json_to_filter_value};
+use serde::{Deserialize, Serialize};
+
+use automate_api::payload_path::{self, PayloadPath, Resolved};
+use human_errors::ResultExt;
+
+use crate::filter::{Filter, FilterValue, Filterable, json_to_filter_value};
+use crate::parsers::Expression;
+use crate::variables::Variables;
 
 /// The largest rendered string [`render`] will return, in characters.
 ///
@@ -49,55 +58,140 @@ use crate::filter::{FilterValue, Filterable, json_to_filter_value};
 const MAX_RENDERED_LENGTH: usize = 8192;
 
 /// A [`Filterable`] view over an arbitrary JSON document, addressing it with
-/// dotted paths such as `issue.user.login`.
+/// the paths [`PayloadPath`] reads, such as `issue.user.login` or
+/// `commits[*].author.username`.
 ///
 /// # Why the whole path arrives as one key
 ///
 /// The filter DSL's lexer treats `.`-separated identifiers as a single property
 /// name, so [`Filterable::get`] is called once with `"issue.user.login"` rather
-/// than three times. There is therefore nothing clever to do here: split on `.`
-/// and walk the document, yielding [`FilterValue::Null`] the moment a segment is
-/// missing. That matches the DSL's own convention, where an unknown property is
-/// null rather than an error.
+/// than three times. A path with brackets would not survive that lexer, which
+/// reads a `[` as the start of a list, so [`PayloadFilter`] hides each one
+/// behind a property name before the filter is parsed and this decodes it
+/// again. Either way the path is resolved in one go, yielding
+/// [`FilterValue::Null`] when it leads nowhere. That matches the DSL's own
+/// convention, where an unknown property is null rather than an error.
 ///
-/// # Known limitation: object leaves are not filterable
+/// # Objects are reached through projections
 ///
 /// [`json_to_filter_value`] maps a JSON object onto [`FilterValue::Null`],
 /// because the filter DSL has no representation for a structured record — it
 /// knows about strings, numbers, booleans and tuples, and nothing else. A path
 /// which stops at an object therefore evaluates to null: `issue` on its own is
-/// not filterable, but `issue.title` is. Users must address the scalar they
-/// actually care about.
+/// not filterable, but `issue.title` is.
 ///
-/// Arrays fare better. They become [`FilterValue::Tuple`]s, which is exactly
-/// what the DSL's `in` and `contains` operators consume, so a filter can be
-/// written against a list of labels or reviewers:
+/// Arrays become [`FilterValue::Tuple`]s, which is exactly what the DSL's `in`
+/// and `contains` operators consume, and a path through `[*]` or `[?...]` does
+/// too. That is how an array of objects is filtered on: not as the objects,
+/// which would be a tuple of nulls, but as the field of them that matters.
 ///
 /// ```text
 /// "bug" in issue.labels
+/// "deploy/app.yaml" in commits[*].modified
+/// pull_request.labels[*].name contains "needs-review"
 /// ```
-///
-/// Note that an array *of objects* becomes a tuple of nulls, for the same
-/// reason as above.
 pub struct JsonFilter<'a>(pub &'a serde_json::Value);
 
 impl Filterable for JsonFilter<'_> {
     fn get(&self, key: &str) -> FilterValue<'_> {
-        match resolve(self.0, key) {
-            // Borrow the leaf rather than cloning it, so evaluating a filter
-            // over a large payload stays allocation-free for scalars.
-            Some(value) => json_to_filter_value(value),
-            None => FilterValue::Null,
+        let path = payload_path::embedded(key);
+
+        match resolve(self.0, path.as_deref().unwrap_or(key)) {
+            // Borrow the leaves rather than cloning them, so evaluating a
+            // filter over a large payload stays allocation-free for scalars.
+            Resolved::One(value) => json_to_filter_value(value),
+            Resolved::Many(values) => {
+                FilterValue::Tuple(values.into_iter().map(json_to_filter_value).collect())
+            }
+            Resolved::Missing => FilterValue::Null,
+        }
+    }
+}
+
+/// A workflow's filter over a webhook payload, which may address it with
+/// bracketed paths such as `commits[*].modified`.
+///
+/// The filter DSL would read `commits[0]` as a property followed by a list, so
+/// the filter is parsed with its paths hidden by [`payload_path::embed`], and
+/// each of them checked on the way in so that a malformed one is reported when
+/// the workflow is saved rather than treated as absent by every delivery after
+/// it. The text somebody typed is what is stored and shown back to them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PayloadFilter {
+    source: Option<String>,
+    filter: Filter,
+}
+
+impl PayloadFilter {
+    pub fn new(source: impl Into<String>) -> Result<Self, human_errors::Error> {
+        let source = source.into();
+        let embedded = payload_path::embed(&source);
+
+        for property in embedded
+            .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .filter_map(payload_path::embedded)
+        {
+            PayloadPath::parse(&property).map_err(|err| {
+                human_errors::user(
+                    err.to_string(),
+                    &[
+                        "Use [0] for the first item of a list, [-1] for the last, [*] for all of them, or [?field == \"value\"] for the ones that match.",
+                    ],
+                )
+            })?;
+        }
+
+        let filter = Filter::new(embedded.as_ref()).wrap_user_err(
+            "The filter could not be read.",
+            &["Check the filter's syntax, and that every quote and bracket is closed."],
+        )?;
+
+        Ok(Self {
+            source: Some(source),
+            filter,
+        })
+    }
+
+    /// The filter as it was written.
+    pub fn raw(&self) -> &str {
+        self.source.as_deref().unwrap_or_else(|| self.filter.raw())
+    }
+}
+
+impl std::ops::Deref for PayloadFilter {
+    type Target = Filter;
+
+    fn deref(&self) -> &Filter {
+        &self.filter
+    }
+}
+
+impl Serialize for PayloadFilter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.raw())
+    }
+}
+
+impl<'de> Deserialize<'de> for PayloadFilter {
+    /// Reads the filter as [`Filter`] does, a missing or `null` one matching
+    /// everything.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(source) => PayloadFilter::new(source)
+                .map_err(|err| serde::de::Error::custom(err.description())),
+            None => Ok(PayloadFilter::default()),
         }
     }
 }
 
 /// Expands `${{ path }}` expressions in `template` against `payload`.
 ///
-/// The expression is a dotted path into the payload, trimmed of surrounding
-/// whitespace, so `${{ issue.title }}` and `${{issue.title}}` are the same
-/// thing. Escaping works exactly as it does elsewhere in the product: a
-/// `\${{ ... }}` is emitted literally and its contents are never resolved.
+/// The expression is a dotted path into the payload, optionally piped through
+/// the functions [`crate::parsers::Expression`] provides, so
+/// `${{ issue.title }}`, `${{issue.title}}` and
+/// `${{ issue.title | truncate(60) }}` all read the same field. Escaping works
+/// exactly as it does elsewhere in the product: a `\${{ ... }}` is emitted
+/// literally and its contents are never resolved.
 ///
 /// # Why a missing path is not an error
 ///
@@ -108,13 +202,28 @@ impl Filterable for JsonFilter<'_> {
 /// render, it would fail the task, and the user would lose the notification
 /// entirely — a cosmetic gap in a title escalated into a silently dropped
 /// alert. Rendering the empty string keeps the notification, which is the part
-/// the user actually needed.
+/// the user actually needed, and `default(...)` is there for the titles that
+/// should say something instead.
 ///
 /// An explicit JSON `null` is treated the same way as an absent field, since
 /// senders are inconsistent about which they use for "no value" and the user
 /// means the same thing by both. The alternative would be to write the literal
 /// text `null` into a task title.
 ///
+/// A malformed expression, on the other hand, is an error: that is a mistake in
+/// the template rather than in the delivery, and [`Template`] is what catches it
+/// when the workflow is saved.
+///
+/// # Variables and secrets
+///
+/// `vars.` and `secrets.` read the account's own values from `variables` rather
+/// than the payload (see [`crate::variables`]), so a payload field by either
+/// name cannot be reached. A sender choosing what a template finds under
+/// `secrets.` would otherwise be able to stand in for the account's own. A
+/// secret piped through a function renders as [`crate::runs::REDACTED`], since
+/// what came out could no longer be recognised and scrubbed; [`Template`]
+/// refuses one before it gets this far.
+///
 /// # How values are rendered
 ///
 /// A string leaf is emitted without its JSON quotes, which is what a user
@@ -125,44 +234,102 @@ impl Filterable for JsonFilter<'_> {
 ///
 /// The result is truncated to [`MAX_RENDERED_LENGTH`] characters with a
 /// trailing `…` if it would otherwise be longer.
-pub fn render(template: &str, payload: &serde_json::Value) -> Result<String, human_errors::Error> {
+pub fn render(
+    template: &str,
+    payload: &serde_json::Value,
+    variables: &Variables,
+) -> Result<String, human_errors::Error> {
     let rendered = crate::parsers::interpolate(template, |expression| {
-        Ok(render_leaf(resolve(payload, expression.trim())))
+        let expression = Expression::parse(expression)?;
+        if is_worked_secret(&expression) {
+            return Ok(crate::runs::REDACTED.to_string());
+        }
+
+        Ok(expression.render(|path| {
+            variables
+                .lookup(path)
+                .unwrap_or_else(|| resolve(payload, path).to_value())
+        }))
     })?;
 
     Ok(truncate(rendered))
 }
 
-/// Walks a dotted path into a JSON document, returning [`None`] as soon as a
-/// segment is not present.
+/// A workflow field written as a template, checked when it is read.
 ///
-/// Segments address object keys only. Array elements are deliberately not
-/// indexable: the filter DSL consumes whole arrays as tuples, and a template
-/// reaching for `commits.0` would be depending on an ordering the sender never
-/// promised.
-fn resolve<'a>(payload: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
-    let mut current = payload;
+/// Stored and edited as the plain text somebody typed, but refused on the way
+/// in if one of its expressions does not parse, so that a misspelt function is
+/// reported when the workflow is saved rather than by every delivery after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Template(String);
+
+impl Template {
+    pub fn new(template: impl Into<String>) -> Result<Self, human_errors::Error> {
+        let template = template.into();
+
+        crate::parsers::interpolate(&template, |expression| {
+            let parsed = Expression::parse(expression)?;
+
+            if is_worked_secret(&parsed) {
+                return Err(human_errors::user(
+                    format!(
+                        "The expression `{}` passes a secret through a function, which could reveal it.",
+                        expression.trim()
+                    ),
+                    &[
+                        "Write a secret out whole, as ${{ secrets.name }}, or keep the value as a variable instead.",
+                    ],
+                ));
+            }
 
-    for segment in path.split('.') {
-        current = current.as_object()?.get(segment)?;
+            Ok("")
+        })?;
+
+        Ok(Self(template))
     }
+}
 
-    Some(current)
+/// Whether an expression reads a secret and then works on it, which would
+/// publish something scrubbing cannot recognise as the secret.
+fn is_worked_secret(expression: &Expression) -> bool {
+    !expression.is_bare() && expression.path().is_some_and(Variables::is_secret)
 }
 
-/// Converts a resolved leaf into the text that should appear in the output.
-fn render_leaf(value: Option<&serde_json::Value>) -> String {
-    match value {
-        // Absent and explicitly-null both mean "the sender had nothing to say".
-        None | Some(serde_json::Value::Null) => String::new(),
-        // Emitted bare, because `"Fix the bug"` in a task title is noise.
-        Some(serde_json::Value::String(text)) => text.clone(),
-        // `Display` for `Value` is compact JSON, which is what we want for
-        // numbers, booleans, objects and arrays alike.
-        Some(other) => other.to_string(),
+impl std::ops::Deref for Template {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = String;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        Template::new(template).map_err(|err| err.description().to_string())
     }
 }
 
+impl From<Template> for String {
+    fn from(template: Template) -> Self {
+        template.0
+    }
+}
+
+/// Finds what a path addresses in a JSON document, treating one that cannot be
+/// read the same as one that leads nowhere.
+///
+/// The paths a [`PayloadFilter`] or [`Template`] holds have been checked
+/// already, so a path that does not parse here is one the DSL made up, and
+/// like an absent field it is not worth failing a delivery over.
+fn resolve<'a>(payload: &'a serde_json::Value, path: &str) -> Resolved<'a> {
+    PayloadPath::parse(path)
+        .map(|path| path.resolve(payload))
+        .unwrap_or(Resolved::Missing)
+}
+
 /// Caps a rendered string at [`MAX_RENDERED_LENGTH`] characters, marking any
 /// truncation with a trailing `…` so the reader can tell something was cut.
 fn truncate(rendered: String) -> String {
@@ -293,7 +460,8 @@ mod tests {
     #[test]
     fn a_simple_expression_is_substituted() {
         assert_eq!(
-            render("Issue ${{ action }}", &payload()).expect("render template"),
+            render("Issue ${{ action }}", &payload(), &Variables::default())
+                .expect("render template"),
             "Issue opened"
         );
     }
@@ -305,7 +473,8 @@ mod tests {
         assert_eq!(
             render(
                 "${{ issue.title }} (by ${{ issue.user.login }})",
-                &payload()
+                &payload(),
+                &Variables::default()
             )
             .expect("render template"),
             "Everything is on fire (by octocat)"
@@ -318,12 +487,21 @@ mod tests {
         // sender omitted must not cost the user their notification. The
         // surrounding literal text still renders, so the task remains useful.
         assert_eq!(
-            render("Assigned to '${{ issue.assignee }}'", &payload()).expect("render template"),
+            render(
+                "Assigned to '${{ issue.assignee }}'",
+                &payload(),
+                &Variables::default()
+            )
+            .expect("render template"),
             "Assigned to ''"
         );
         assert_eq!(
-            render("[${{ repository.full_name }}] ${{ action }}", &payload())
-                .expect("render template"),
+            render(
+                "[${{ repository.full_name }}] ${{ action }}",
+                &payload(),
+                &Variables::default()
+            )
+            .expect("render template"),
             "[] opened"
         );
     }
@@ -332,7 +510,8 @@ mod tests {
     fn a_string_leaf_renders_without_its_json_quotes() {
         // Naively reaching for `serde_json::to_string` would produce
         // `"Everything is on fire"`, quotes and all, in every task title.
-        let rendered = render("${{ issue.title }}", &payload()).expect("render template");
+        let rendered = render("${{ issue.title }}", &payload(), &Variables::default())
+            .expect("render template");
 
         assert_eq!(rendered, "Everything is on fire");
         assert!(!rendered.contains('"'));
@@ -341,7 +520,12 @@ mod tests {
     #[test]
     fn non_string_scalars_render_in_their_json_form() {
         assert_eq!(
-            render("#${{ number }} draft=${{ draft }}", &payload()).expect("render template"),
+            render(
+                "#${{ number }} draft=${{ draft }}",
+                &payload(),
+                &Variables::default()
+            )
+            .expect("render template"),
             "#42 draft=false"
         );
     }
@@ -352,11 +536,13 @@ mod tests {
         // value, and showing it beats showing nothing when a user is working
         // out what a sender actually posts.
         assert_eq!(
-            render("${{ issue.labels }}", &payload()).expect("render template"),
+            render("${{ issue.labels }}", &payload(), &Variables::default())
+                .expect("render template"),
             r#"["bug","urgent"]"#
         );
         assert_eq!(
-            render("${{ issue.user }}", &payload()).expect("render template"),
+            render("${{ issue.user }}", &payload(), &Variables::default())
+                .expect("render template"),
             r#"{"login":"octocat"}"#
         );
     }
@@ -366,17 +552,20 @@ mod tests {
         // A webhook body is attacker-influenceable, so the cap is a boundary
         // worth pinning exactly rather than approximately.
         let exact = serde_json::json!({ "blob": "x".repeat(MAX_RENDERED_LENGTH) });
-        let rendered = render("${{ blob }}", &exact).expect("render template");
+        let rendered =
+            render("${{ blob }}", &exact, &Variables::default()).expect("render template");
         assert_eq!(rendered.chars().count(), MAX_RENDERED_LENGTH);
         assert!(!rendered.ends_with('…'));
 
         let oversized = serde_json::json!({ "blob": "x".repeat(MAX_RENDERED_LENGTH + 1) });
-        let rendered = render("${{ blob }}", &oversized).expect("render template");
+        let rendered =
+            render("${{ blob }}", &oversized, &Variables::default()).expect("render template");
         assert_eq!(rendered.chars().count(), MAX_RENDERED_LENGTH);
         assert!(rendered.ends_with('…'));
 
         let huge = serde_json::json!({ "blob": "x".repeat(MAX_RENDERED_LENGTH * 10) });
-        let rendered = render("${{ blob }}", &huge).expect("render template");
+        let rendered =
+            render("${{ blob }}", &huge, &Variables::default()).expect("render template");
         assert_eq!(rendered.chars().count(), MAX_RENDERED_LENGTH);
         assert!(rendered.ends_with('…'));
     }
@@ -387,7 +576,8 @@ mod tests {
         // webhook payload is exactly the sort of input that carries emoji and
         // non-Latin text.
         let payload = serde_json::json!({ "blob": "é".repeat(MAX_RENDERED_LENGTH + 100) });
-        let rendered = render("${{ blob }}", &payload).expect("render template");
+        let rendered =
+            render("${{ blob }}", &payload, &Variables::default()).expect("render template");
 
         assert_eq!(rendered.chars().count(), MAX_RENDERED_LENGTH);
         assert!(rendered.ends_with('…'));
@@ -402,17 +592,28 @@ mod tests {
         // a literal `${{ ... }}` in a title has one way to ask for it, and it
         // is the same way they ask for it in the configuration file.
         assert_eq!(
-            render(r"\${{ issue.title }}", &payload()).expect("render template"),
+            render(r"\${{ issue.title }}", &payload(), &Variables::default())
+                .expect("render template"),
             "${{ issue.title }}"
         );
         assert_eq!(
-            render(r"${{ action }} but not \${{ action }}", &payload()).expect("render template"),
+            render(
+                r"${{ action }} but not \${{ action }}",
+                &payload(),
+                &Variables::default()
+            )
+            .expect("render template"),
             "opened but not ${{ action }}"
         );
         // The escape does not need the path to exist, because it never resolves
         // one.
         assert_eq!(
-            render(r"\${{ totally.unknown }}", &payload()).expect("render template"),
+            render(
+                r"\${{ totally.unknown }}",
+                &payload(),
+                &Variables::default()
+            )
+            .expect("render template"),
             "${{ totally.unknown }}"
         );
     }
@@ -420,16 +621,208 @@ mod tests {
     #[test]
     fn a_template_without_expressions_is_returned_unchanged() {
         assert_eq!(
-            render("A fixed title", &payload()).expect("render template"),
+            render("A fixed title", &payload(), &Variables::default()).expect("render template"),
             "A fixed title"
         );
     }
 
+    #[test]
+    fn functions_apply_to_the_resolved_value() {
+        assert_eq!(
+            render(
+                "${{ issue.labels | join(\", \") }}: ${{ issue.milestone | default(\"no milestone\") | upper }}", &payload(), &Variables::default())
+            .expect("render template"),
+            "bug, urgent: NO MILESTONE"
+        );
+    }
+
+    #[test]
+    fn a_template_with_a_malformed_expression_is_refused_when_it_is_read() {
+        // A workflow's templates are read when it is saved, which is the moment
+        // somebody is there to fix a typo. Left until a delivery renders it, the
+        // same typo fails every delivery instead.
+        let err =
+            serde_json::from_value::<Template>(serde_json::json!("${{ issue.title | shout }}"))
+                .expect_err("an unknown function should be refused");
+        assert!(
+            err.to_string().contains("shout"),
+            "the error should name what it did not understand: {err}",
+        );
+
+        let template: Template = serde_json::from_value(serde_json::json!(
+            "#${{ number }} ${{ issue.title | truncate(30) }}"
+        ))
+        .expect("a well-formed template should be accepted");
+        assert_eq!(
+            render(&template, &payload(), &Variables::default()).expect("render template"),
+            "#42 Everything is on fire"
+        );
+    }
+
     #[test]
     fn an_unclosed_expression_is_still_an_error() {
         // Missing *data* is tolerated; a malformed *template* is not, because
         // that is a mistake the user made and can fix, and reporting it is the
         // only way they will find out.
-        assert!(render("${{ issue.title", &payload()).is_err());
+        assert!(render("${{ issue.title", &payload(), &Variables::default()).is_err());
+    }
+
+    /// A push event, for the paths that step into arrays of objects.
+    fn push() -> serde_json::Value {
+        serde_json::json!({
+            "ref": "refs/heads/main",
+            "commits": [
+                { "id": "a1", "author": { "username": "octocat" }, "modified": ["README.md"] },
+                {
+                    "id": "b2",
+                    "author": { "username": "hubot" },
+                    "modified": ["deploy/app.yaml", "src/main.rs"],
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn a_filter_can_reach_into_an_array_of_objects() {
+        // The case the bracketed paths exist for: a push's files and authors
+        // live in an array of commits, which the filter could only ever see as
+        // a tuple of nulls.
+        let payload = push();
+        let filter = JsonFilter(&payload);
+
+        for (source, expected) in [
+            (r#""deploy/app.yaml" in commits[*].modified"#, true),
+            (r#"commits[*].author.username contains "hubot""#, true),
+            (r#"commits[0].id == "a1" && commits[-1].id == "b2""#, true),
+            (
+                r#"commits[?author.username == "octocat"].modified contains "src/main.rs""#,
+                false,
+            ),
+            (
+                r#""bug" in ["bug", "urgent"] && ref == "refs/heads/main""#,
+                true,
+            ),
+        ] {
+            let parsed = PayloadFilter::new(source).expect("parse filter");
+            assert_eq!(
+                parsed.matches(&filter).expect("run filter"),
+                expected,
+                "evaluating `{source}`",
+            );
+        }
+    }
+
+    #[test]
+    fn a_filter_is_stored_as_it_was_written() {
+        // The DSL only ever sees the paths hidden behind generated names, which
+        // nobody should find in their configuration when they open it again.
+        let source = r#""deploy/app.yaml" in commits[*].modified"#;
+        let filter: PayloadFilter =
+            serde_json::from_value(serde_json::json!(source)).expect("parse filter");
+
+        assert_eq!(filter.raw(), source);
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            serde_json::json!(source)
+        );
+        assert!(
+            serde_json::from_value::<PayloadFilter>(serde_json::Value::Null)
+                .expect("a missing filter should be accepted")
+                .matches(&JsonFilter(&push()))
+                .expect("run filter"),
+            "a missing filter should match everything, as it always has",
+        );
+    }
+
+    #[test]
+    fn a_filter_with_a_malformed_path_is_refused_when_it_is_read() {
+        let err = serde_json::from_value::<PayloadFilter>(serde_json::json!(
+            r#"commits[?id ~ "a1"].id == "a1""#
+        ))
+        .expect_err("a path with an unknown comparison should be refused");
+        assert!(
+            err.to_string().contains("should compare a field"),
+            "the error should say what is wrong with the path: {err}",
+        );
+    }
+
+    #[test]
+    fn a_template_can_index_and_project_arrays() {
+        assert_eq!(
+            render(
+                "${{ commits[-1].id }}: ${{ commits[*].author.username | join(', ') }}",
+                &push(),
+                &Variables::default()
+            )
+            .expect("render template"),
+            "b2: octocat, hubot"
+        );
+        assert_eq!(
+            render(
+                "${{ commits[5].id | default('none') }}",
+                &push(),
+                &Variables::default()
+            )
+            .expect("render template"),
+            "none",
+            "an index past the end should be as missing as any other absent field",
+        );
+    }
+
+    #[test]
+    fn variables_and_secrets_are_read_from_the_account_rather_than_the_payload() {
+        // A sender is free to post a `secrets` field of its own, and a template
+        // reaching for the account's secret must not be handed that instead.
+        let payload = serde_json::json!({
+            "action": "opened",
+            "secrets": { "token": "from-the-sender" },
+        });
+        let variables = Variables::default()
+            .with_var("base_url", "https://ci.example.com")
+            .with_secret("token", "from-the-account");
+
+        assert_eq!(
+            render(
+                "${{ vars.base_url }}/${{ action }} ${{ secrets.token }} [${{ vars.unset }}]",
+                &payload,
+                &variables
+            )
+            .expect("render template"),
+            "https://ci.example.com/opened from-the-account []"
+        );
+    }
+
+    #[test]
+    fn a_secret_can_only_be_written_out_whole() {
+        // Scrubbing recognises a secret by its value, so anything that changes
+        // the value gets past it, and a comparison gives it away a guess at a
+        // time.
+        for template in [
+            "${{ secrets.token | upper }}",
+            "${{ secrets.token | truncate(4) }}",
+            "${{ secrets.token | markdown_escape }}",
+            "${{ secrets.token | contains(\"a\") | if(\"yes\", \"no\") }}",
+            "${{ secrets.token | eq(\"hunter2\") }}",
+        ] {
+            assert!(
+                Template::new(template).is_err(),
+                "{template} should be refused when it is saved",
+            );
+        }
+
+        Template::new("Bearer ${{ secrets.token }} for ${{ vars.team | upper }}")
+            .expect("a secret written whole, and a variable worked on, are both fine");
+
+        let variables = Variables::default().with_secret("token", "from-the-account");
+        assert_eq!(
+            render(
+                "${{ secrets.token | upper }}",
+                &serde_json::json!({}),
+                &variables
+            )
+            .expect("render template"),
+            crate::runs::REDACTED,
+            "one that was never checked still must not be rendered",
+        );
     }
 }
@@ -0,0 +1,651 @@
+//! Checking that a webhook delivery came from whoever holds its secret.
+//!
+//! Nearly every service that signs its webhooks does it the same way: an HMAC,
+//! keyed with a secret both sides were given, over the body or over the body
+//! with a timestamp in front of it, carried in a header as hex or base64. What
+//! differs is the detail — which hash, which header, whether the digest has a
+//! `sha256=` in front of it, whether the timestamp travels in the same header
+//! or its own. The modelled senders in [`crate::webhooks`] each know their own
+//! detail and use the pieces here for the part that is the same.
+//!
+//! The generic webhook workflow cannot know the detail in advance, so
+//! [`SignatureCheck`] lets its owner describe it instead. That is what makes it
+//! safe to point an internal service at one: without a signature, knowing the
+//! URL is all it takes to file a task, and the URL is the part of a request that
+//! ends up in everybody's access logs.
+//!
+//! # Replay windows
+//!
+//! A timestamp inside the signed material is what stops a delivery somebody
+//! captured from being sent again next week. The window is measured against
+//! [`crate::job::JobContext::scheduled_at`], when the delivery arrived, rather
+//! than against the clock when the job happens to run — otherwise a delivery
+//! that was retried after an outage would be refused for the outage's length.
+//!
+//! The one delivery that is let outside the window is a kept one its owner
+//! has asked to be sent again (see [`crate::preview::is_redelivery`]). It is
+//! old on purpose, so its age proves nothing, but its signature still has to
+//! match: the person asking can choose which delivery to send again, not what
+//! it says.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How far either side of the arrival time a signed timestamp may be, unless a
+/// workflow says otherwise. Five minutes is what Stripe, Slack and Tailscale
+/// all recommend, which is as close to a convention as this gets.
+pub const DEFAULT_TOLERANCE: chrono::Duration = chrono::Duration::minutes(5);
+
+/// The hash an HMAC is computed with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// Still what a surprising number of older services send. Weak as a
+    /// hash, but an HMAC built on it is not broken in the way SHA-1 itself is.
+    Sha1,
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// Whether `signature` is the HMAC of `message` keyed with `secret`.
+    ///
+    /// The comparison is made in constant time, so a wrong signature cannot be
+    /// walked one byte at a time by timing the rejections.
+    pub fn verify(self, secret: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        fn verify<M: Mac + KeyInit>(secret: &[u8], message: &[u8], signature: &[u8]) -> bool {
+            // An HMAC accepts a key of any length, so this cannot fail; a
+            // failure would be refused rather than trusted all the same.
+            let Ok(mut mac) = <M as KeyInit>::new_from_slice(secret) else {
+                return false;
+            };
+
+            mac.update(message);
+            mac.verify_slice(signature).is_ok()
+        }
+
+        match self {
+            Self::Sha1 => verify::<Hmac<sha1::Sha1>>(secret, message, signature),
+            Self::Sha256 => verify::<Hmac<sha2::Sha256>>(secret, message, signature),
+            Self::Sha512 => verify::<Hmac<sha2::Sha512>>(secret, message, signature),
+        }
+    }
+}
+
+/// How a digest is written into its header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    /// The raw bytes of a digest, or `None` if it is not written this way.
+    pub fn decode(self, digest: &str) -> Option<Vec<u8>> {
+        use base64::Engine;
+
+        let digest = digest.trim();
+        match self {
+            Self::Hex => hex::decode(digest).ok(),
+            Self::Base64 => base64::engine::general_purpose::STANDARD
+                .decode(digest)
+                .ok(),
+        }
+    }
+}
+
+/// The `key=value` pairs of a header such as `t=1663781880,v1=0a1b…`, which is
+/// how Stripe, Tailscale and Grey carry a timestamp and a signature together.
+pub fn fields(header: &str) -> impl Iterator<Item = (&str, &str)> {
+    header
+        .split(',')
+        .filter_map(|field| field.trim().split_once('='))
+}
+
+/// A timestamp given in whole seconds since the Unix epoch.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+}
+
+/// Whether a signed timestamp is close enough to when the delivery arrived.
+///
+/// Either side counts, since the sender's clock is as likely to be ahead of
+/// ours as behind it.
+pub fn is_fresh(
+    timestamp: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+    tolerance: chrono::Duration,
+) -> bool {
+    (timestamp - received_at).abs() <= tolerance
+}
+
+/// What a signature is computed over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignedContent {
+    /// The body exactly as it was posted, as GitHub, Miniflux and most others
+    /// sign it.
+    #[default]
+    #[serde(rename = "body")]
+    Body,
+
+    /// `<timestamp>.<body>`, as Stripe and Tailscale sign it.
+    #[serde(rename = "timestamp.body")]
+    TimestampDotBody,
+
+    /// `v0:<timestamp>:<body>`, as Slack signs it.
+    #[serde(rename = "v0:timestamp:body")]
+    VersionedTimestampBody,
+}
+
+impl SignedContent {
+    fn has_timestamp(self) -> bool {
+        !matches!(self, Self::Body)
+    }
+
+    fn message(self, timestamp: Option<DateTime<Utc>>, body: &str) -> String {
+        let timestamp = timestamp.map(|t| t.timestamp()).unwrap_or_default();
+
+        match self {
+            Self::Body => body.to_string(),
+            Self::TimestampDotBody => format!("{timestamp}.{body}"),
+            Self::VersionedTimestampBody => format!("v0:{timestamp}:{body}"),
+        }
+    }
+}
+
+/// How a workflow's owner has said their sender signs its deliveries.
+///
+/// Off unless it is turned on, because the generic webhook predates it and a
+/// workflow saved without one should keep accepting what it always accepted.
+/// Once on, it fails closed: an empty secret, a missing header or a digest that
+/// cannot be read all refuse the delivery rather than waving it through.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The secret the sender signs with.
+    #[serde(default)]
+    pub secret: String,
+
+    #[serde(default)]
+    pub algorithm: Algorithm,
+
+    #[serde(default)]
+    pub encoding: Encoding,
+
+    /// The header the signature arrives in, matched without regard to case.
+    #[serde(default = "default_header")]
+    pub header: String,
+
+    /// What comes before the digest in that header, such as `sha256=` or
+    /// `v1=`. The header is split at its commas and every part carrying this
+    /// prefix is tried, because Stripe sends one signature per live secret
+    /// while a secret is being rotated.
+    #[serde(default)]
+    pub prefix: String,
+
+    #[serde(default)]
+    pub signed: SignedContent,
+
+    /// The header the timestamp arrives in, for senders like Slack that keep
+    /// it apart from the signature. When empty, it is read from a `t=` part of
+    /// the signature header, as Stripe and Tailscale send it. Only consulted
+    /// when the timestamp is part of what is signed.
+    #[serde(default)]
+    pub timestamp_header: String,
+
+    /// How far from the delivery's arrival a signed timestamp may be.
+    #[serde(default = "default_tolerance", with = "crate::serde_duration::minutes")]
+    pub tolerance: chrono::Duration,
+}
+
+impl Default for SignatureCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            secret: String::new(),
+            algorithm: Algorithm::default(),
+            encoding: Encoding::default(),
+            header: default_header(),
+            prefix: String::new(),
+            signed: SignedContent::default(),
+            timestamp_header: String::new(),
+            tolerance: default_tolerance(),
+        }
+    }
+}
+
+fn default_header() -> String {
+    "X-Signature".to_string()
+}
+
+fn default_tolerance() -> chrono::Duration {
+    DEFAULT_TOLERANCE
+}
+
+impl SignatureCheck {
+    /// Checks a delivery against this description of how it was signed.
+    ///
+    /// `received_at` is when the delivery arrived — see the module's notes on
+    /// replay windows — or `None` for a kept delivery its owner has asked to be
+    /// sent again, whose signature must still match but whose age is not held
+    /// against it. Always passes while the check is turned off.
+    pub fn verify(
+        &self,
+        event: &WebhookEvent,
+        received_at: Option<DateTime<Utc>>,
+    ) -> Result<(), human_errors::Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        // An empty secret is a check somebody forgot to finish setting up, and
+        // treating it as "skip the check" would make the workflow silently
+        // unauthenticated at exactly that moment.
+        if self.secret.is_empty() {
+            return Err(human_errors::user(
+                "This workflow checks signatures but has no secret to check them with.",
+                &[
+                    "Enter the secret your sender signs its deliveries with, or turn signature checking off.",
+                ],
+            ));
+        }
+
+        let header = event.header(&self.header).ok_or_else(|| {
+            human_errors::user(
+                format!("The delivery did not carry a '{}' header.", self.header),
+                &[
+                    "Check that the sender is configured to sign its deliveries.",
+                    "Check that the signature header named on this workflow is the one the sender uses.",
+                ],
+            )
+        })?;
+
+        let timestamp = if self.signed.has_timestamp() {
+            let timestamp = self.timestamp(event, header)?;
+
+            if received_at
+                .is_some_and(|received_at| !is_fresh(timestamp, received_at, self.tolerance))
+            {
+                return Err(human_errors::user(
+                    format!(
+                        "The delivery was signed at {timestamp}, too long before or after it arrived at {received_at} to be trusted."
+                    ),
+                    &[
+                        "Ensure that the clocks on this server and on the sender are accurate.",
+                        "A delivery captured and sent again later is refused this way, which is the point of the timestamp.",
+                    ],
+                ));
+            }
+
+            Some(timestamp)
+        } else {
+            None
+        };
+
+        let message = self.signed.message(timestamp, &event.body);
+
+        let candidates: Vec<Vec<u8>> = header
+            .split(',')
+            .filter_map(|part| part.trim().strip_prefix(self.prefix.as_str()))
+            .filter_map(|digest| self.encoding.decode(digest))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(human_errors::user(
+                format!(
+                    "The '{}' header did not contain a signature this workflow could read.",
+                    self.header
+                ),
+                &[
+                    "Check that the signature prefix and encoding on this workflow match what the sender puts in the header.",
+                ],
+            ));
+        }
+
+        if candidates.iter().any(|signature| {
+            self.algorithm
+                .verify(self.secret.as_bytes(), message.as_bytes(), signature)
+        }) {
+            Ok(())
+        } else {
+            Err(human_errors::user(
+                "Webhook signature verification failed (signatures did not match).",
+                &[
+                    "Ensure that the secret on this workflow matches the one the sender signs with.",
+                    "Check that the hash and what is signed match the sender's documentation.",
+                ],
+            ))
+        }
+    }
+
+    /// The signed timestamp, from its own header or from the signature's.
+    fn timestamp(
+        &self,
+        event: &WebhookEvent,
+        signature_header: &str,
+    ) -> Result<DateTime<Utc>, human_errors::Error> {
+        let value = if self.timestamp_header.is_empty() {
+            fields(signature_header)
+                .find(|(key, _)| *key == "t")
+                .map(|(_, value)| value)
+                .ok_or_else(|| {
+                    human_errors::user(
+                        format!(
+                            "The '{}' header did not carry a 't=' timestamp.",
+                            self.header
+                        ),
+                        &[
+                            "If the sender puts its timestamp in a header of its own, name that header on this workflow.",
+                        ],
+                    )
+                })?
+        } else {
+            event.header(&self.timestamp_header).ok_or_else(|| {
+                human_errors::user(
+                    format!(
+                        "The delivery did not carry a '{}' header.",
+                        self.timestamp_header
+                    ),
+                    &["Check that the timestamp header named on this workflow is the one the sender uses."],
+                )
+            })?
+        };
+
+        parse_timestamp(value).ok_or_else(|| {
+            human_errors::user(
+                format!("The delivery's timestamp '{value}' is not a number of seconds since 1970."),
+                &["Check that the timestamp header named on this workflow is the one the sender uses."],
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use base64::Engine;
+
+    use super::*;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &str = r#"{"action":"deployed"}"#;
+
+    fn sign(algorithm: Algorithm, message: &str) -> Vec<u8> {
+        fn sign<M: Mac + KeyInit>(message: &str) -> Vec<u8> {
+            let mut mac = <M as KeyInit>::new_from_slice(SECRET.as_bytes()).unwrap();
+            mac.update(message.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+
+        match algorithm {
+            Algorithm::Sha1 => sign::<Hmac<sha1::Sha1>>(message),
+            Algorithm::Sha256 => sign::<Hmac<sha2::Sha256>>(message),
+            Algorithm::Sha512 => sign::<Hmac<sha2::Sha512>>(message),
+        }
+    }
+
+    fn event(headers: &[(&str, String)]) -> WebhookEvent {
+        WebhookEvent {
+            body: BODY.to_string(),
+            query: String::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn check() -> SignatureCheck {
+        SignatureCheck {
+            enabled: true,
+            secret: SECRET.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_workflow_that_does_not_check_signatures_accepts_anything() {
+        SignatureCheck::default()
+            .verify(&event(&[]), Some(Utc::now()))
+            .expect("the check is off unless somebody turns it on");
+    }
+
+    #[test]
+    fn a_check_with_no_secret_refuses_everything() {
+        let check = SignatureCheck {
+            secret: String::new(),
+            ..check()
+        };
+
+        let signature = hex::encode(sign(Algorithm::Sha256, BODY));
+        assert!(
+            check
+                .verify(&event(&[("X-Signature", signature)]), Some(Utc::now()))
+                .is_err(),
+            "a check somebody forgot to finish setting up should fail closed",
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(Algorithm::Sha1, Encoding::Hex)]
+    #[case(Algorithm::Sha256, Encoding::Hex)]
+    #[case(Algorithm::Sha512, Encoding::Hex)]
+    #[case(Algorithm::Sha256, Encoding::Base64)]
+    fn a_body_signed_with_the_configured_secret_is_accepted(
+        #[case] algorithm: Algorithm,
+        #[case] encoding: Encoding,
+    ) {
+        let signature = sign(algorithm, BODY);
+        let signature = match encoding {
+            Encoding::Hex => hex::encode(signature),
+            Encoding::Base64 => base64::engine::general_purpose::STANDARD.encode(signature),
+        };
+
+        let check = SignatureCheck {
+            algorithm,
+            encoding,
+            ..check()
+        };
+
+        check
+            .verify(&event(&[("x-signature", signature)]), Some(Utc::now()))
+            .expect("a signature the sender would have produced should verify");
+    }
+
+    #[test]
+    fn a_signature_made_with_another_secret_is_refused() {
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"somebody else").unwrap();
+        mac.update(BODY.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(
+            check()
+                .verify(&event(&[("X-Signature", signature)]), Some(Utc::now()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn a_prefixed_signature_is_read_after_its_prefix() {
+        // GitHub's style, which is also what plenty of internal services copy.
+        let check = SignatureCheck {
+            header: "X-Hub-Signature-256".to_string(),
+            prefix: "sha256=".to_string(),
+            ..check()
+        };
+
+        let signature = format!("sha256={}", hex::encode(sign(Algorithm::Sha256, BODY)));
+        check
+            .verify(
+                &event(&[("X-Hub-Signature-256", signature)]),
+                Some(Utc::now()),
+            )
+            .expect("the prefix should be stripped before the digest is decoded");
+
+        let bare = hex::encode(sign(Algorithm::Sha256, BODY));
+        assert!(
+            check
+                .verify(&event(&[("X-Hub-Signature-256", bare)]), Some(Utc::now()))
+                .is_err(),
+            "a digest without the prefix the sender is said to use is not the sender's",
+        );
+    }
+
+    #[test]
+    fn a_stripe_style_signature_is_checked_against_its_own_timestamp() {
+        let check = SignatureCheck {
+            header: "Stripe-Signature".to_string(),
+            prefix: "v1=".to_string(),
+            signed: SignedContent::TimestampDotBody,
+            ..check()
+        };
+
+        let now = Utc::now();
+        let signature = hex::encode(sign(
+            Algorithm::Sha256,
+            &format!("{}.{BODY}", now.timestamp()),
+        ));
+
+        // Stripe sends one signature per live secret while one is rotated, and
+        // only one of them has to match.
+        let header = format!(
+            "t={},v1={},v1={signature}",
+            now.timestamp(),
+            "00".repeat(32)
+        );
+
+        check
+            .verify(&event(&[("Stripe-Signature", header)]), Some(now))
+            .expect("any one of the signatures matching should be enough");
+    }
+
+    #[test]
+    fn a_slack_style_signature_reads_its_timestamp_from_a_header_of_its_own() {
+        let check = SignatureCheck {
+            header: "X-Slack-Signature".to_string(),
+            prefix: "v0=".to_string(),
+            signed: SignedContent::VersionedTimestampBody,
+            timestamp_header: "X-Slack-Request-Timestamp".to_string(),
+            ..check()
+        };
+
+        let now = Utc::now();
+        let signature = format!(
+            "v0={}",
+            hex::encode(sign(
+                Algorithm::Sha256,
+                &format!("v0:{}:{BODY}", now.timestamp())
+            ))
+        );
+
+        check
+            .verify(
+                &event(&[
+                    ("X-Slack-Signature", signature),
+                    ("X-Slack-Request-Timestamp", now.timestamp().to_string()),
+                ]),
+                Some(now),
+            )
+            .expect("a signature Slack would have produced should verify");
+    }
+
+    #[test]
+    fn a_timestamp_outside_the_window_is_refused_even_when_correctly_signed() {
+        let check = SignatureCheck {
+            prefix: "v1=".to_string(),
+            signed: SignedContent::TimestampDotBody,
+            ..check()
+        };
+
+        let signed_at = Utc::now() - chrono::Duration::hours(1);
+        let header = format!(
+            "t={},v1={}",
+            signed_at.timestamp(),
+            hex::encode(sign(
+                Algorithm::Sha256,
+                &format!("{}.{BODY}", signed_at.timestamp())
+            ))
+        );
+
+        assert!(
+            check
+                .verify(&event(&[("X-Signature", header.clone())]), Some(Utc::now()))
+                .is_err(),
+            "a delivery captured an hour ago and sent again should not be accepted",
+        );
+
+        // Measured from when the delivery arrived, so a retry after an outage
+        // still verifies.
+        check
+            .verify(
+                &event(&[("X-Signature", header.clone())]),
+                Some(signed_at + chrono::Duration::seconds(30)),
+            )
+            .expect("a delivery that arrived on time should verify however late it runs");
+    }
+
+    #[test]
+    fn a_redelivery_is_forgiven_its_age_but_not_its_signature() {
+        let check = SignatureCheck {
+            prefix: "v1=".to_string(),
+            signed: SignedContent::TimestampDotBody,
+            ..check()
+        };
+
+        let signed_at = Utc::now() - chrono::Duration::days(2);
+        let digest = hex::encode(sign(
+            Algorithm::Sha256,
+            &format!("{}.{BODY}", signed_at.timestamp()),
+        ));
+
+        check
+            .verify(
+                &event(&[(
+                    "X-Signature",
+                    format!("t={},v1={digest}", signed_at.timestamp()),
+                )]),
+                None,
+            )
+            .expect("a kept delivery sent again on purpose is as old as it is");
+
+        // Re-dating it does not help: the timestamp is part of what was signed.
+        assert!(
+            check
+                .verify(
+                    &event(&[(
+                        "X-Signature",
+                        format!("t={},v1={digest}", Utc::now().timestamp()),
+                    )]),
+                    None,
+                )
+                .is_err(),
+            "a redelivery must still carry the signature its sender made",
+        );
+    }
+
+    #[test]
+    fn a_settings_block_saved_without_the_newer_fields_reads_with_their_defaults() {
+        let check: SignatureCheck =
+            serde_json::from_value(serde_json::json!({ "enabled": true, "secret": SECRET }))
+                .expect("only the switch and the secret should be needed");
+
+        assert_eq!(check.algorithm, Algorithm::Sha256);
+        assert_eq!(check.encoding, Encoding::Hex);
+        assert_eq!(check.header, "X-Signature");
+        assert_eq!(check.signed, SignedContent::Body);
+        assert_eq!(check.tolerance, DEFAULT_TOLERANCE);
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
         TodoistCompleteTask, TodoistCompleteTaskPayload, TodoistUpsertTask,
         TodoistUpsertTaskPayload,
     },
+    script::Script,
     webhooks::WebhookDelivery,
 };
 
@@ -22,6 +23,11 @@ pub struct AzureMonitorWebhookConfig {
     /// action groups can tell which of them filed a task.
     pub name: String,
 
+    /// Reshapes each delivery before it is filtered. Empty files alerts as
+    /// Azure sends them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: Filter,
 
@@ -95,6 +101,25 @@ Leave the filter empty to file every alert the action group sends. Note that
 the filter is only consulted when an alert fires — a resolution always
 completes its task, so tightening the filter later cannot strand a task that is
 already open.
+
+## Reshaping alerts with a script
+
+A **Script** runs against each delivery before the filter does, with the body
+Azure sent as `item`, in the common alert schema: the `data.essentials` every
+alert carries, and the `data.alertContext` that differs by monitor service. It
+can bring something from the context into the task, or pick a severity a rule
+does not set:
+
+```
+let rule = item.data.essentials.alertRule;
+if rule.starts_with("prod-") { item.data.essentials.severity = "Sev0"; }
+item
+```
+
+End with `item` to file the alert, with a list to file each element, or with
+nothing to skip it; whatever it returns still needs the fields Azure sends.
+Scripts are written in [Rhai](https://rhai.rs/book/); the generic webhook's
+notes describe what they can and cannot do.
 "#;
 
 crate::register_job!(AzureMonitorWebhook);
@@ -155,6 +180,11 @@ impl crate::workflows::ConfigurableWorkflow for AzureMonitorWebhook {
                 .with_help(
                     "Only file alerts matching this, such as severity <= 1. Severity is the number from Sev0 to Sev4, so a smaller one is more urgent. Leave it empty to file every alert.",
                 ),
+                crate::script_field!(
+                    AzureMonitorWebhookConfig,
+                    "delivery",
+                    "item.data.essentials.alertRule.replace(\"prod-\", \"\");\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -167,31 +197,21 @@ impl crate::workflows::ConfigurableWorkflow for AzureMonitorWebhook {
     }
 }
 
-impl Job for AzureMonitorWebhook {
-    type JobType = WebhookDelivery;
-
-    fn partition() -> &'static str {
-        "webhooks/azure-monitor"
-    }
-
-    #[instrument("webhooks.azure_monitor.handle", skip(self, ctx, job), fields(job = %job))]
-    async fn handle(
-        &self,
-        ctx: JobContext<impl Services + Send + Sync + 'static>,
-        job: &Self::JobType,
+impl AzureMonitorWebhook {
+    /// Files or completes the task for one alert.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &AzureMonitorWebhookConfig,
+        event: AzureMonitorAlertEventPayload,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
 
-        // Read now rather than carried in the payload, so that an edit made
-        // between the delivery arriving and this running is the one that applies.
-        let Some(config) = job.config::<AzureMonitorWebhookConfig>(services).await? else {
-            return Ok(());
-        };
-
-        let event: AzureMonitorAlertEventPayload = job.event.json()?;
-
         match event.data.essentials.monitor_condition {
-            CommonAlertSchemaMonitorCondition::Fired if config.filter.matches(&event)? => {
+            CommonAlertSchemaMonitorCondition::Fired
+                if crate::preview::matches(&config.filter, &event, || {
+                    event.data.essentials.alert_rule.clone()
+                })? =>
+            {
                 TodoistUpsertTask::dispatch(
                     TodoistUpsertTaskPayload {
                         unique_key: event.data.essentials.alert_id.clone(),
@@ -235,6 +255,37 @@ impl Job for AzureMonitorWebhook {
     }
 }
 
+impl Job for AzureMonitorWebhook {
+    type JobType = WebhookDelivery;
+
+    fn partition() -> &'static str {
+        "webhooks/azure-monitor"
+    }
+
+    #[instrument("webhooks.azure_monitor.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Read now rather than carried in the payload, so that an edit made
+        // between the delivery arriving and this running is the one that applies.
+        let Some(config) = job.config::<AzureMonitorWebhookConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let events: Vec<AzureMonitorAlertEventPayload> =
+            job.event.scripted(&config.script, "the alert").await?;
+        for event in events {
+            Self::file(&ctx, &config, event).await?;
+        }
+
+        Ok(())
+    }
+}
+
 pub type AzureMonitorAlertEventPayload = CommonAlertSchema;
 
 #[allow(dead_code)]
@@ -400,6 +451,8 @@ mod tests {
                 query: String::new(),
                 headers: HashMap::new(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -106,6 +106,11 @@ pub struct GitHubWebhookConfig {
     #[serde(default)]
     pub attention: GitHubAttentionConfig,
 
+    /// Reshapes each delivery before auto-merge and the reminders are handed
+    /// it. Empty hands them deliveries as GitHub sends them.
+    #[serde(default)]
+    pub script: crate::script::Script,
+
     /// Where every task this workflow raises is filed.
     ///
     /// One target for the whole workflow rather than one per section, because
@@ -198,6 +203,25 @@ Comments and assignments on the same issue or pull request collapse onto one
 task, so a busy thread does not become a wall of them. Security alerts get one
 task each, because each needs its own fix.
 
+## Reshaping deliveries with a script
+
+A **Script** runs against each delivery before auto-merge and the reminders
+see it, with the body GitHub sent as `item` — the same payload its webhook
+documentation describes for each event. It can pass over deliveries no filter
+can name, such as everything from archived repositories, or rewrite what a
+reminder will say:
+
+```
+if item.repository?.archived == true { return; }
+item
+```
+
+End with `item` to handle the delivery, with a list to handle each element as
+though GitHub had sent it, or with nothing to pass over it. Installs and
+uninstalls of the App are recorded whatever the script says. Scripts are
+written in [Rhai](https://rhai.rs/book/); the generic webhook's notes describe
+what they can and cannot do.
+
 ## Where the tasks go
 
 **Todoist account**, **Project** and **Section** say where everything this
@@ -427,6 +451,11 @@ impl crate::workflows::ConfigurableWorkflow for GitHubWebhook {
             .into_iter()
             .chain(Self::auto_merge_fields())
             .chain(Self::attention_fields())
+            .chain([crate::script_field!(
+                GitHubWebhookConfig,
+                "delivery",
+                "if item.repository?.archived == true { return; }\nitem"
+            )])
             .chain(crate::todoist_target_fields!(
                 GitHubWebhookConfig,
                 project = Some("Hobbies"),
@@ -628,46 +657,76 @@ impl Job for GitHubWebhook {
 
         let mut handled = false;
 
-        if event_type == "pull_request" && config.auto_merge.enabled {
-            let pull_request: GitHubPullRequestEvent = event.json()?;
-            GitHubAutoMergeWorkflow::dispatch(
-                crate::jobs::GitHubAutoMergeTask {
-                    config: config.auto_merge.clone(),
-                    // Which installation this workflow serves, so the job mints
-                    // its token from what its owner chose rather than from what
-                    // the delivery claims to be.
-                    connection: config.connection,
-                    todoist: config.todoist.clone(),
-                    event: pull_request,
-                },
-                delivery.clone(),
-                services,
-            )
-            .await?;
-            handled = true;
-        }
+        // The workflow's script reshapes what auto-merge and the reminders are
+        // handed, and each thing it returns is treated as though GitHub had sent
+        // it. Refreshing notifications files nothing of this workflow's, so it
+        // follows the delivery itself whatever the script made of it.
+        let events = if config.script.is_empty() {
+            vec![event.clone()]
+        } else {
+            let bodies: Vec<serde_json::Value> = event
+                .scripted(&config.script, &format!("the '{event_type}' delivery"))
+                .await?;
+            bodies
+                .into_iter()
+                .map(|body| WebhookEvent {
+                    body: body.to_string(),
+                    ..event.clone()
+                })
+                .collect()
+        };
 
-        if config.attention.enabled {
-            // A payload we cannot interpret is skipped rather than raised, so that
-            // an unmodelled variant cannot poison the queue by retrying forever.
-            match GitHubAttentionEvent::parse(event_type, event) {
-                Ok(Some(attention)) => {
-                    GitHubAttentionWorkflow::dispatch(
-                        crate::jobs::GitHubAttentionTask {
-                            config: config.attention.clone(),
-                            todoist: config.todoist.clone(),
-                            event: attention,
-                        },
-                        delivery,
-                        services,
-                    )
-                    .await?;
-                    handled = true;
+        for (index, event) in events.iter().enumerate() {
+            // The first keeps the delivery's own key, so that GitHub's retries
+            // are still recognised, and the rest are told apart from it.
+            let delivery = match index {
+                0 => delivery.clone(),
+                _ => delivery
+                    .as_ref()
+                    .map(|delivery| Cow::Owned(format!("{delivery}/{index}"))),
+            };
+
+            if event_type == "pull_request" && config.auto_merge.enabled {
+                let pull_request: GitHubPullRequestEvent = event.json()?;
+                GitHubAutoMergeWorkflow::dispatch(
+                    crate::jobs::GitHubAutoMergeTask {
+                        config: config.auto_merge.clone(),
+                        // Which installation this workflow serves, so the job mints
+                        // its token from what its owner chose rather than from what
+                        // the delivery claims to be.
+                        connection: config.connection,
+                        todoist: config.todoist.clone(),
+                        event: pull_request,
+                    },
+                    delivery.clone(),
+                    services,
+                )
+                .await?;
+                handled = true;
+            }
+
+            if config.attention.enabled {
+                // A payload we cannot interpret is skipped rather than raised, so that
+                // an unmodelled variant cannot poison the queue by retrying forever.
+                match GitHubAttentionEvent::parse(event_type, event) {
+                    Ok(Some(attention)) => {
+                        GitHubAttentionWorkflow::dispatch(
+                            crate::jobs::GitHubAttentionTask {
+                                config: config.attention.clone(),
+                                todoist: config.todoist.clone(),
+                                event: attention,
+                            },
+                            delivery.clone(),
+                            services,
+                        )
+                        .await?;
+                        handled = true;
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!(
+                        "Could not interpret the '{event_type}' payload as an attention event; skipping it: {err}"
+                    ),
                 }
-                Ok(None) => {}
-                Err(err) => warn!(
-                    "Could not interpret the '{event_type}' payload as an attention event; skipping it: {err}"
-                ),
             }
         }
 
@@ -971,7 +1030,8 @@ impl GitHubAttentionEvent {
                 // with active-sounding actions (`created`, `reintroduced`,
                 // `appeared_in_branch`) for alerts which are already dismissed
                 // or fixed, so the alert's own state has the final say.
-                resolved: RESOLVING_ACTIONS.contains(&payload.action.as_str()) || !alert.is_active(),
+                resolved: RESOLVING_ACTIONS.contains(&payload.action.as_str())
+                    || !alert.is_active(),
                 repository: payload.repository.full_name.clone(),
                 repository_owner: payload.repository.owner.login.clone(),
                 repository_name: payload.repository.name.clone(),
@@ -1234,6 +1294,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -2069,6 +2131,8 @@ mod tests {
                         .into_iter()
                         .collect::<HashMap<_, _>>(),
                     },
+                    replay_of: None,
+                    arrived_at: None,
                 };
 
                 run(services, &job)
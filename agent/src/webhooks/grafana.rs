@@ -14,6 +14,7 @@ use crate::{
         TodoistCompleteTask, TodoistCompleteTaskPayload, TodoistUpsertTask,
         TodoistUpsertTaskPayload,
     },
+    script::Script,
     webhooks::WebhookDelivery,
 };
 
@@ -86,6 +87,11 @@ pub struct GrafanaWebhookConfig {
     #[serde(default)]
     pub secret: String,
 
+    /// Reshapes each delivery before it is filtered. Empty files alerts as
+    /// Grafana sends them.
+    #[serde(default)]
+    pub script: Script,
+
     /// Filter to apply to incoming alerts
     #[serde(default)]
     pub filter: Filter,
@@ -138,6 +144,131 @@ impl GrafanaWebhook {
             .find(|(key, _)| key.eq_ignore_ascii_case(name))
             .map(|(_, value)| value.as_str())
     }
+
+    /// Files or completes the task for one alert.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &GrafanaWebhookConfig,
+        event: GrafanaAlertPayload,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Apply filter to the entire alert payload
+        if !crate::preview::matches(&config.filter, &event, || event.title.clone())? {
+            info!(
+                "Grafana alert '{}' did not match filter; ignoring.",
+                event.title
+            );
+            return Ok(());
+        }
+
+        // Process based on alert status
+        match event.status {
+            GrafanaAlertStatus::Firing => {
+                // Create a unique key based on the alert rule URL or title
+                let unique_key = event
+                    .rule_url
+                    .clone()
+                    .unwrap_or_else(|| format!("grafana-alert-{}", event.title));
+
+                // Get the first alert for more details
+                let first_alert = event.alerts.first();
+                let starts_at = first_alert.and_then(|a| a.starts_at);
+                let severity = first_alert
+                    .and_then(|a| a.labels.get("severity"))
+                    .map(|s| s.as_str())
+                    .unwrap_or("unknown");
+
+                // Determine priority based on severity label
+                let priority = match severity {
+                    "critical" => 4,
+                    "error" => 3,
+                    "warning" => 2,
+                    _ => 1,
+                };
+
+                let alert_title = event
+                    .group_labels
+                    .and_then(|l| {
+                        l.get("grafana_folder")
+                            .or_else(|| l.get("alertname"))
+                            .cloned()
+                    })
+                    .or_else(|| {
+                        event
+                            .alerts
+                            .iter()
+                            .filter_map(|a| a.labels.get("rulename"))
+                            .next()
+                            .cloned()
+                    })
+                    .unwrap_or("Grafana Alert".to_string());
+                let dashboard_url = event
+                    .alerts
+                    .iter()
+                    .filter_map(|a| a.dashboard_url.clone())
+                    .next()
+                    .or_else(|| event.external_url.clone())
+                    .unwrap_or_else(|| "https://grafana.com".into());
+
+                let summary = event
+                    .alerts
+                    .iter()
+                    .filter_map(|a| a.annotations.get("summary").cloned())
+                    .collect::<Vec<String>>()
+                    .join("\n");
+
+                // Create or update the Todoist task
+                TodoistUpsertTask::dispatch(
+                    TodoistUpsertTaskPayload {
+                        unique_key: unique_key.clone(),
+                        title: format!(
+                            "[**Grafana Alert**]({dashboard_url}): {alert_title} is unhealthy"
+                        ),
+                        description: Some(summary),
+                        due: starts_at
+                            .map(crate::publishers::TodoistDueDate::DateTime)
+                            .unwrap_or_else(|| {
+                                crate::publishers::TodoistDueDate::DateTime(ctx.scheduled_at())
+                            }),
+                        priority: Some(priority),
+                        config: config.todoist.clone(),
+                        ..Default::default()
+                    },
+                    Some(
+                        event
+                            .rule_url
+                            .clone()
+                            .unwrap_or_else(|| event.title.clone())
+                            .into(),
+                    ),
+                    services,
+                )
+                .await?;
+
+                Ok(())
+            }
+            GrafanaAlertStatus::Resolved => {
+                // Complete the task when the alert is resolved
+                let unique_key = event
+                    .rule_url
+                    .clone()
+                    .unwrap_or_else(|| event.title.clone());
+
+                TodoistCompleteTask::dispatch(
+                    TodoistCompleteTaskPayload {
+                        unique_key,
+                        config: config.todoist.clone(),
+                    },
+                    None,
+                    services,
+                )
+                .await?;
+
+                Ok(())
+            }
+        }
+    }
 }
 
 /// The setup notes shown while somebody is configuring one of these.
@@ -204,6 +335,27 @@ Note that a filter which rejects a delivery rejects it in both directions: if a
 firing alert was filed and its resolution does not match the same filter, the
 task will not be completed. Filter on things that do not change between the two
 — the rule, the folder, the environment — rather than on the status.
+
+## Reshaping alerts with a script
+
+A **Script** runs against each delivery before the filter does, with the body
+Grafana sent as `item` — its `title`, `status`, `commonLabels`, and the
+`alerts` it groups, each with its own `labels` and `annotations`. It can do
+what a label cannot, such as raising anything from production to the top
+priority whatever its rule says:
+
+```
+item.alerts = item.alerts.map(|alert| {
+    if alert.labels.env == "production" { alert.labels.severity = "critical"; }
+    alert
+});
+item
+```
+
+End with `item` to file the delivery, with a list to file each element, or with
+nothing to skip it; whatever it returns still needs the fields Grafana sends.
+Scripts are written in [Rhai](https://rhai.rs/book/); the generic webhook's
+notes describe what they can and cannot do.
 "#;
 
 crate::register_job!(GrafanaWebhook);
@@ -278,6 +430,11 @@ impl crate::workflows::ConfigurableWorkflow for GrafanaWebhook {
                 .with_help(
                     "Only file alerts matching this, such as state == \"alerting\". Leave it empty to file every alert.",
                 ),
+                crate::script_field!(
+                    GrafanaWebhookConfig,
+                    "delivery",
+                    "if item.commonLabels?.env == \"staging\" { return; }\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -320,148 +477,56 @@ impl Job for GrafanaWebhook {
         // the delivery retrying forever and hiding real failures behind it. The
         // log line is the record that it happened.
 
-        // No token configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty token as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed. It also
-        // means the check cannot be neutralised by clearing the box, and it is
-        // what the GitHub and Terraform Cloud webhooks do with their own secrets.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Grafana webhook for a workflow with no authorization token configured; rejecting request."
-            );
-            return Ok(());
-        }
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No token configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty token as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed. It also
+            // means the check cannot be neutralised by clearing the box, and it is
+            // what the GitHub and Terraform Cloud webhooks do with their own secrets.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Grafana webhook for a workflow with no authorization token configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no authorization token to check the delivery against.",
+                );
+                return Ok(());
+            }
 
-        let Some(authorization) = Self::header(&job.event, "authorization") else {
-            warn!("Received a Grafana webhook without an Authorization header; rejecting request.");
-            return Ok(());
-        };
+            let Some(authorization) = Self::header(&job.event, "authorization") else {
+                warn!(
+                    "Received a Grafana webhook without an Authorization header; rejecting request."
+                );
+                crate::preview::rejected("The delivery did not carry an Authorization header.");
+                return Ok(());
+            };
+
+            if !tokens_match(&config.secret, Self::credentials(authorization)) {
+                warn!(
+                    "Received a Grafana webhook whose Authorization header did not match the configured token; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The Authorization header did not match the configured token.",
+                );
+                return Ok(());
+            }
 
-        if !tokens_match(&config.secret, Self::credentials(authorization)) {
-            warn!(
-                "Received a Grafana webhook whose Authorization header did not match the configured token; rejecting request."
-            );
-            return Ok(());
+            crate::preview::verified();
         }
 
-        let event: GrafanaAlertPayload = job.event.json()?;
-
-        // Apply filter to the entire alert payload
-        if !config.filter.matches(&event)? {
-            info!(
-                "Grafana alert '{}' did not match filter; ignoring.",
-                event.title
-            );
-            return Ok(());
+        // Each alert the workflow's script turns the delivery into is filed on
+        // its own, so one notification grouping several can become a task each.
+        let events: Vec<GrafanaAlertPayload> =
+            job.event.scripted(&config.script, "the alert").await?;
+        for event in events {
+            Self::file(&ctx, &config, event).await?;
         }
 
-        // Process based on alert status
-        match event.status {
-            GrafanaAlertStatus::Firing => {
-                // Create a unique key based on the alert rule URL or title
-                let unique_key = event
-                    .rule_url
-                    .clone()
-                    .unwrap_or_else(|| format!("grafana-alert-{}", event.title));
-
-                // Get the first alert for more details
-                let first_alert = event.alerts.first();
-                let starts_at = first_alert.and_then(|a| a.starts_at);
-                let severity = first_alert
-                    .and_then(|a| a.labels.get("severity"))
-                    .map(|s| s.as_str())
-                    .unwrap_or("unknown");
-
-                // Determine priority based on severity label
-                let priority = match severity {
-                    "critical" => 4,
-                    "error" => 3,
-                    "warning" => 2,
-                    _ => 1,
-                };
-
-                let alert_title = event
-                    .group_labels
-                    .and_then(|l| {
-                        l.get("grafana_folder")
-                            .or_else(|| l.get("alertname"))
-                            .cloned()
-                    })
-                    .or_else(|| {
-                        event
-                            .alerts
-                            .iter()
-                            .filter_map(|a| a.labels.get("rulename"))
-                            .next()
-                            .cloned()
-                    })
-                    .unwrap_or("Grafana Alert".to_string());
-                let dashboard_url = event
-                    .alerts
-                    .iter()
-                    .filter_map(|a| a.dashboard_url.clone())
-                    .next()
-                    .or_else(|| event.external_url.clone())
-                    .unwrap_or_else(|| "https://grafana.com".into());
-
-                let summary = event
-                    .alerts
-                    .iter()
-                    .filter_map(|a| a.annotations.get("summary").cloned())
-                    .collect::<Vec<String>>()
-                    .join("\n");
-
-                // Create or update the Todoist task
-                TodoistUpsertTask::dispatch(
-                    TodoistUpsertTaskPayload {
-                        unique_key: unique_key.clone(),
-                        title: format!(
-                            "[**Grafana Alert**]({dashboard_url}): {alert_title} is unhealthy"
-                        ),
-                        description: Some(summary),
-                        due: starts_at
-                            .map(crate::publishers::TodoistDueDate::DateTime)
-                            .unwrap_or_else(|| {
-                                crate::publishers::TodoistDueDate::DateTime(ctx.scheduled_at())
-                            }),
-                        priority: Some(priority),
-                        config: config.todoist.clone(),
-                        ..Default::default()
-                    },
-                    Some(
-                        event
-                            .rule_url
-                            .clone()
-                            .unwrap_or_else(|| event.title.clone())
-                            .into(),
-                    ),
-                    services,
-                )
-                .await?;
-
-                Ok(())
-            }
-            GrafanaAlertStatus::Resolved => {
-                // Complete the task when the alert is resolved
-                let unique_key = event
-                    .rule_url
-                    .clone()
-                    .unwrap_or_else(|| event.title.clone());
-
-                TodoistCompleteTask::dispatch(
-                    TodoistCompleteTaskPayload {
-                        unique_key,
-                        config: config.todoist.clone(),
-                    },
-                    None,
-                    services,
-                )
-                .await?;
-
-                Ok(())
-            }
-        }
+        Ok(())
     }
 }
 
@@ -699,6 +764,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -753,6 +820,32 @@ mod tests {
         assert_eq!(queued(&services, "todoist/complete-task").await.len(), 1);
     }
 
+    #[tokio::test]
+    async fn a_script_can_file_one_delivery_as_several_alerts() {
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(
+            &services,
+            serde_json::json!({
+                "name": "Production",
+                "secret": TOKEN,
+                "script": "let replica = item;\nreplica.ruleUrl += \"/replica\";\n[item, replica]",
+            }),
+        )
+        .await;
+
+        run(&services, &delivery(workflow, FIRING_PAYLOAD))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            queued(&services, "todoist/upsert-task").await.len(),
+            2,
+            "each alert the script returned should have a task of its own",
+        );
+    }
+
     #[tokio::test]
     async fn a_body_that_is_not_grafanas_is_refused() {
         let services = crate::services::ServicesContainer::new_mock()
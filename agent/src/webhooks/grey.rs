@@ -27,9 +27,7 @@
 use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
-use hmac::{Hmac, KeyInit, Mac};
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 
 use crate::{
     prelude::*,
@@ -37,11 +35,11 @@ use crate::{
         TodoistCompleteTask, TodoistCompleteTaskPayload, TodoistDueDate, TodoistUpsertTask,
         TodoistUpsertTaskPayload,
     },
+    script::Script,
     services::debounce::{DebounceConfig, Debouncer, Detection},
+    webhook_signature::{self, Algorithm},
 };
 
-type HmacSha256 = Hmac<Sha256>;
-
 /// The key/value partition holding each Grey monitor's debounce state
 /// ([`crate::services::debounce::DebounceState`]), keyed by [`GreyWebhookEvent::unique_key`].
 const GREY_FAILURES_PARTITION: &str = "grey/failures";
@@ -122,6 +120,11 @@ pub struct GreyWebhookConfig {
     )]
     pub noise_duration: chrono::Duration,
 
+    /// Reshapes each state change before it is filtered. Empty acts on state
+    /// changes as Grey sends them.
+    #[serde(default)]
+    pub script: Script,
+
     /// Filter applied to incoming events. The same fields Grey exposes to its own webhook filters
     /// are available here (`event`, `entity.*`, `state.*`).
     #[serde(default)]
@@ -150,6 +153,7 @@ impl Default for GreyWebhookConfig {
             alert_delay: default_alert_delay(),
             recovery_delay: default_recovery_delay(),
             noise_duration: default_noise_duration(),
+            script: Script::default(),
             filter: crate::filter::Filter::default(),
             todoist: default_todoist_config(),
         }
@@ -185,7 +189,7 @@ impl GreyWebhook {
         let mut timestamp = None;
         let mut signature = None;
 
-        for (key, value) in header.split(',').filter_map(|s| s.split_once('=')) {
+        for (key, value) in webhook_signature::fields(header) {
             match key {
                 "t" => timestamp = Some(value),
                 "v1" => signature = Some(value),
@@ -195,11 +199,7 @@ impl GreyWebhook {
 
         match (timestamp, signature) {
             (Some(timestamp), Some(signature)) => {
-                let timestamp = timestamp
-                    .parse()
-                    .ok()
-                    .and_then(|ts| DateTime::from_timestamp(ts, 0))
-                    .ok_or_else(|| {
+                let timestamp = webhook_signature::parse_timestamp(timestamp).ok_or_else(|| {
                         human_errors::user(
                             "The timestamp in the Grey-Webhook-Signature header is invalid.",
                             &[
@@ -235,16 +235,19 @@ impl GreyWebhook {
     ///
     /// The `now` parameter is the point in time against which the signature timestamp is validated.
     /// This should be the time at which the request was originally received (rather than the current
-    /// time) so that retries of a previously received request continue to validate successfully.
+    /// time) so that retries of a previously received request continue to validate successfully,
+    /// or `None` for a kept delivery sent again on purpose, whose age is not held against it.
     fn verify_signature(
         secret: &str,
         body: &str,
         signature_header: &str,
-        now: DateTime<Utc>,
+        now: Option<DateTime<Utc>>,
     ) -> Result<(), human_errors::Error> {
         let (timestamp, expected_signature) = Self::parse_signature(signature_header)?;
 
-        if (timestamp - now).abs() > chrono::Duration::minutes(5) {
+        if now.is_some_and(|now| {
+            !webhook_signature::is_fresh(timestamp, now, webhook_signature::DEFAULT_TOLERANCE)
+        }) {
             return Err(human_errors::user(
                 format!(
                     "The Grey webhook signature timestamp is too old or too far in the future (got {timestamp})"
@@ -260,34 +263,21 @@ impl GreyWebhook {
         // cannot be re-dated to slip past the window above.
         let string_to_sign = format!("{}.{}", timestamp.timestamp(), body);
 
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).wrap_user_err(
-            "Failed to create HMAC instance with the provided secret.",
-            &["Ensure that you have set a valid webhook secret on this workflow."],
-        )?;
-
-        mac.update(string_to_sign.as_bytes());
-
-        // `verify_slice` compares in constant time, so a wrong signature cannot
-        // be walked one byte at a time by timing the rejections.
-        mac.verify_slice(&expected_signature).wrap_user_err(
-            "Webhook signature verification failed (signatures did not match).".to_string(),
-            &[
-                "Ensure that the webhook secret on this workflow matches the one set on the webhook in your Grey configuration.",
-            ],
-        )?;
+        if !Algorithm::Sha256.verify(
+            secret.as_bytes(),
+            string_to_sign.as_bytes(),
+            &expected_signature,
+        ) {
+            return Err(human_errors::user(
+                "Webhook signature verification failed (signatures did not match).",
+                &[
+                    "Ensure that the webhook secret on this workflow matches the one set on the webhook in your Grey configuration.",
+                ],
+            ));
+        }
 
         Ok(())
     }
-
-    /// HTTP header names are case-insensitive, and what reaches us depends on
-    /// whatever proxy handled the request, so the lookup cannot assume a casing.
-    fn header<'a>(event: &'a WebhookEvent, name: &str) -> Option<&'a str> {
-        event
-            .headers
-            .iter()
-            .find(|(key, _)| key.eq_ignore_ascii_case(name))
-            .map(|(_, value)| value.as_str())
-    }
 }
 
 /// The setup notes shown while somebody is configuring one of these.
@@ -376,6 +366,25 @@ entity.type == "probe" && tags.environment == "production"
 Leave it empty to act on every change Grey reports. Be careful narrowing it
 after the fact: a filter that admits the unhealthy event but rejects the
 recovery leaves a task open with nothing to close it.
+
+## Reshaping state changes with a script
+
+A **Script** runs against each state change before the filter does, with the
+body Grey sent as `item`: its `event`, the `entity` with its `type`, `name` and
+`tags`, and the `state`. It can work out what a tag cannot say, such as which
+team a monitor belongs to from its name:
+
+```
+let team = item.entity.name.split("-")[0];
+item.entity.tags.team = team;
+item
+```
+
+End with `item` to act on the change, or with nothing to pass over it, with the
+same care as the filter: a script that drops a monitor's recovery but not its
+failure leaves a task with nothing to close it. Scripts are written in
+[Rhai](https://rhai.rs/book/); the generic webhook's notes describe what they
+can and cannot do.
 "#;
 
 crate::register_job!(GreyWebhook);
@@ -497,6 +506,11 @@ impl crate::workflows::ConfigurableWorkflow for GreyWebhook {
                 .with_help(
                     "Only act on the state changes matching this, such as entity.type == \"probe\". A monitor's own tags are available as tags.<name>. Leave it empty to act on every change.",
                 ),
+                crate::script_field!(
+                    GreyWebhookConfig,
+                    "state change",
+                    "item.entity.tags.team = item.entity.name.split(\"-\")[0];\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -509,74 +523,16 @@ impl crate::workflows::ConfigurableWorkflow for GreyWebhook {
     }
 }
 
-impl Job for GreyWebhook {
-    type JobType = crate::webhooks::WebhookDelivery;
-
-    fn partition() -> &'static str {
-        "webhooks/grey"
-    }
-
-    #[instrument("webhooks.grey.handle", skip(self, ctx, job), fields(job = %job))]
-    async fn handle(
-        &self,
-        ctx: JobContext<impl Services + Send + Sync + 'static>,
-        job: &Self::JobType,
+impl GreyWebhook {
+    /// Acts on one state change.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &GreyWebhookConfig,
+        event: GreyWebhookEvent,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
 
-        // Read now rather than carried in the delivery, so that an edit made
-        // between the delivery arriving and this running is the one that applies.
-        let Some(config) = job.config::<GreyWebhookConfig>(services).await? else {
-            return Ok(());
-        };
-
-        let event = &job.event;
-
-        // Everything below this point happens *before* the payload is parsed, so
-        // that a delivery we cannot attribute to Grey is never interpreted, let
-        // alone acted on.
-        //
-        // A rejection returns `Ok(())` rather than an error: nothing about a bad
-        // signature improves by trying again, so raising here would only leave
-        // the delivery retrying forever and hiding real failures behind it. The
-        // log line is the record that it happened.
-
-        // No secret configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty secret as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed. It also
-        // means the check cannot be neutralised by clearing the box, and it is
-        // what the GitHub and Terraform Cloud webhooks do with their own.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Grey webhook for a workflow with no secret configured; rejecting request."
-            );
-            return Ok(());
-        }
-
-        let Some(signature) = Self::header(event, "grey-webhook-signature") else {
-            warn!(
-                "Received a Grey webhook without a Grey-Webhook-Signature header; rejecting request."
-            );
-            return Ok(());
-        };
-
-        // Validate against the time the request was originally received (the
-        // message's scheduled time) rather than now, so that a retry of a
-        // delivery we already accepted still validates.
-        if let Err(err) =
-            Self::verify_signature(&config.secret, &event.body, signature, ctx.scheduled_at())
-        {
-            warn!(
-                "Failed to verify Grey webhook signature, rejecting request: {}",
-                err
-            );
-            return Ok(());
-        }
-
-        let event: GreyWebhookEvent = event.json()?;
-
-        if !config.filter.matches(&event)? {
+        if !crate::preview::matches(&config.filter, &event, || event.event.clone())? {
             info!(
                 "Grey event for {} '{}' did not match filter; ignoring.",
                 event.entity.entity_type, event.entity.name
@@ -714,6 +670,100 @@ impl Job for GreyWebhook {
     }
 }
 
+impl Job for GreyWebhook {
+    type JobType = crate::webhooks::WebhookDelivery;
+
+    fn partition() -> &'static str {
+        "webhooks/grey"
+    }
+
+    #[instrument("webhooks.grey.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Read now rather than carried in the delivery, so that an edit made
+        // between the delivery arriving and this running is the one that applies.
+        let Some(config) = job.config::<GreyWebhookConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let event = &job.event;
+
+        // Everything below this point happens *before* the payload is parsed, so
+        // that a delivery we cannot attribute to Grey is never interpreted, let
+        // alone acted on.
+        //
+        // A rejection returns `Ok(())` rather than an error: nothing about a bad
+        // signature improves by trying again, so raising here would only leave
+        // the delivery retrying forever and hiding real failures behind it. The
+        // log line is the record that it happened.
+
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No secret configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty secret as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed. It also
+            // means the check cannot be neutralised by clearing the box, and it is
+            // what the GitHub and Terraform Cloud webhooks do with their own.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Grey webhook for a workflow with no secret configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no secret to check the signature against.",
+                );
+                return Ok(());
+            }
+
+            let Some(signature) = event.header("grey-webhook-signature") else {
+                warn!(
+                    "Received a Grey webhook without a Grey-Webhook-Signature header; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The delivery did not carry a Grey-Webhook-Signature header.",
+                );
+                return Ok(());
+            };
+
+            // Validate against the time the request was originally received (the
+            // message's scheduled time) rather than now, so that a retry of a
+            // delivery we already accepted still validates.
+            if let Err(err) = Self::verify_signature(
+                &config.secret,
+                &event.body,
+                signature,
+                (!crate::preview::is_redelivery()).then(|| ctx.scheduled_at()),
+            ) {
+                warn!(
+                    "Failed to verify Grey webhook signature, rejecting request: {}",
+                    err
+                );
+                crate::preview::rejected(err.description());
+                return Ok(());
+            }
+
+            crate::preview::verified();
+        }
+
+        let events: Vec<GreyWebhookEvent> = job
+            .event
+            .scripted(&config.script, "the state change")
+            .await?;
+        for event in events {
+            Self::file(&ctx, &config, event).await?;
+        }
+
+        Ok(())
+    }
+}
+
 /// A Grey `probe.state_changed` / `cron.state_changed` webhook payload.
 ///
 /// This mirrors the wire shape of `grey_api::WebhookEvent` (see Grey's `docs/guide/webhooks.md`),
@@ -970,6 +1020,9 @@ impl Filterable for GreyWebhookEvent {
 mod tests {
     use std::collections::HashMap;
 
+    use hmac::{Hmac, KeyInit, Mac};
+    use sha2::Sha256;
+
     use crate::db::PeekedMessage;
     use crate::publishers::TodoistUpsertTaskState;
     use crate::services::debounce::DebounceState;
@@ -978,6 +1031,8 @@ mod tests {
 
     use super::*;
 
+    type HmacSha256 = Hmac<Sha256>;
+
     /// Builds a `probe.state_changed` body with explicit event and `since` timestamps, so tests can
     /// drive the debounce state machine deterministically.
     fn probe_event_at(name: &str, healthy: bool, timestamp: &str, since: &str) -> String {
@@ -1096,6 +1151,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -1850,8 +1907,13 @@ mod tests {
         let body = probe_event("web.prod", false);
         let now = Utc::now();
 
-        GreyWebhook::verify_signature(SECRET, &body, &sign(SECRET, now.timestamp(), &body), now)
-            .expect("a signature Grey itself would have produced should verify");
+        GreyWebhook::verify_signature(
+            SECRET,
+            &body,
+            &sign(SECRET, now.timestamp(), &body),
+            Some(now),
+        )
+        .expect("a signature Grey itself would have produced should verify");
     }
 
     #[test]
@@ -1862,7 +1924,7 @@ mod tests {
         let now = Utc::now();
         let signature = sign("somebody-elses-secret", now.timestamp(), &body);
 
-        assert!(GreyWebhook::verify_signature(SECRET, &body, &signature, now).is_err());
+        assert!(GreyWebhook::verify_signature(SECRET, &body, &signature, Some(now)).is_err());
     }
 
     #[test]
@@ -1874,7 +1936,7 @@ mod tests {
         let signature = sign(SECRET, now.timestamp(), &probe_event("web.prod", false));
         let tampered = probe_event("web.staging", false);
 
-        assert!(GreyWebhook::verify_signature(SECRET, &tampered, &signature, now).is_err());
+        assert!(GreyWebhook::verify_signature(SECRET, &tampered, &signature, Some(now)).is_err());
     }
 
     #[test]
@@ -1884,10 +1946,11 @@ mod tests {
         let body = probe_event("web.prod", false);
 
         assert!(
-            GreyWebhook::verify_signature(SECRET, &body, "not-a-signature", Utc::now()).is_err()
+            GreyWebhook::verify_signature(SECRET, &body, "not-a-signature", Some(Utc::now()))
+                .is_err()
         );
         assert!(
-            GreyWebhook::verify_signature(SECRET, &body, "t=1663781880", Utc::now()).is_err(),
+            GreyWebhook::verify_signature(SECRET, &body, "t=1663781880", Some(Utc::now())).is_err(),
             "a header with a timestamp but no digest proves nothing",
         );
     }
@@ -1903,10 +1966,10 @@ mod tests {
         let signature = sign(SECRET, received_at.timestamp(), &body);
 
         assert!(
-            GreyWebhook::verify_signature(SECRET, &body, &signature, Utc::now()).is_err(),
+            GreyWebhook::verify_signature(SECRET, &body, &signature, Some(Utc::now())).is_err(),
             "the freshness window should reject a six-hour-old signature against the current time",
         );
-        GreyWebhook::verify_signature(SECRET, &body, &signature, received_at)
+        GreyWebhook::verify_signature(SECRET, &body, &signature, Some(received_at))
             .expect("the same signature should verify against the time it was received");
     }
 
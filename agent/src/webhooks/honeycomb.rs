@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     prelude::*,
     publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate},
+    script::Script,
     webhooks::WebhookDelivery,
     webhooks::grafana::tokens_match,
 };
@@ -40,6 +41,11 @@ pub struct HoneycombWebhookConfig {
     #[serde(default)]
     pub secret: String,
 
+    /// Reshapes each delivery before it is filtered. Empty files triggers as
+    /// Honeycomb sends them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: crate::filter::Filter,
 
@@ -142,6 +148,25 @@ name startswith "prod:"
 
 Leave it empty to file every trigger that fires, which is the sensible default
 when the recipient is only attached to triggers you chose.
+
+## Reshaping triggers with a script
+
+A **Script** runs against each delivery before the filter does, with the body
+Honeycomb sent as `item`: its `name`, `status`, `summary`, `description`,
+`threshold` and links. Where the filter's surface is too small, a script can
+look at the rest — skipping a trigger whose summary names a canary, say, or
+putting the summary into the task in place of the description:
+
+```
+if item.summary.contains("canary") { return; }
+item.description = item.summary;
+item
+```
+
+End with `item` to file the trigger, with a list to file each element, or with
+nothing to skip it; whatever it returns still needs the fields Honeycomb sends.
+Scripts are written in [Rhai](https://rhai.rs/book/); the generic webhook's
+notes describe what they can and cannot do.
 "#;
 
 crate::register_job!(HoneycombWebhook);
@@ -206,6 +231,11 @@ impl crate::workflows::ConfigurableWorkflow for HoneycombWebhook {
                 .with_help(
                     "Only file triggers matching this, such as name == \"Slow requests\". Leave it empty to file every trigger that fires.",
                 ),
+                crate::script_field!(
+                    HoneycombWebhookConfig,
+                    "delivery",
+                    "item.description = item.summary;\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -218,73 +248,21 @@ impl crate::workflows::ConfigurableWorkflow for HoneycombWebhook {
     }
 }
 
-impl Job for HoneycombWebhook {
-    type JobType = WebhookDelivery;
-
-    fn partition() -> &'static str {
-        "webhooks/honeycomb"
-    }
-
-    #[instrument("webhooks.honeycomb.handle", skip(self, ctx, job), fields(job = %job))]
-    async fn handle(
-        &self,
-        ctx: JobContext<impl Services + Send + Sync + 'static>,
-        job: &Self::JobType,
+impl HoneycombWebhook {
+    /// Files the task for one trigger.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &HoneycombWebhookConfig,
+        event: HoneycombAlertEventPayload,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
 
-        // Read now rather than carried in the payload, so that an edit made
-        // between the delivery arriving and this running is the one that applies.
-        let Some(config) = job.config::<HoneycombWebhookConfig>(services).await? else {
-            return Ok(());
-        };
-
-        // Everything below this point happens *before* the payload is parsed, so
-        // that a delivery we cannot attribute to Honeycomb is never interpreted,
-        // let alone acted on. The payload carries a `shared_secret` of its own,
-        // which is deliberately not what is checked: believing the body about
-        // whether the body can be believed proves nothing.
-        //
-        // A rejection returns `Ok(())` rather than an error: nothing about a
-        // wrong secret improves by trying again, so raising here would only leave
-        // the delivery retrying forever and hiding real failures behind it. The
-        // log line is the record that it happened.
-
-        // No secret configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty secret as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed. It also
-        // means the check cannot be neutralised by clearing the box, and it is
-        // what the GitHub and Terraform Cloud webhooks do with their own.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Honeycomb webhook for a workflow with no shared secret configured; rejecting request."
-            );
-            return Ok(());
-        }
-
-        let Some(presented) = Self::header(&job.event, "x-honeycomb-webhook-token") else {
-            warn!(
-                "Received a Honeycomb webhook without an X-Honeycomb-Webhook-Token header; rejecting request."
-            );
-            return Ok(());
-        };
-
-        if !tokens_match(&config.secret, presented) {
-            warn!(
-                "Received a Honeycomb webhook whose X-Honeycomb-Webhook-Token did not match the configured secret; rejecting request."
-            );
-            return Ok(());
-        }
-
-        let event: HoneycombAlertEventPayload = job.event.json()?;
-
         if !event.status.eq_ignore_ascii_case("triggered") {
             info!("Ignoring non-triggered Honeycomb alert: {}", event.status);
             return Ok(());
         }
 
-        if !config.filter.matches(&event)? {
+        if !crate::preview::matches(&config.filter, &event, || event.name.clone())? {
             info!(
                 "Honeycomb alert '{}' did not match filter; ignoring.",
                 event.name
@@ -317,6 +295,91 @@ impl Job for HoneycombWebhook {
     }
 }
 
+impl Job for HoneycombWebhook {
+    type JobType = WebhookDelivery;
+
+    fn partition() -> &'static str {
+        "webhooks/honeycomb"
+    }
+
+    #[instrument("webhooks.honeycomb.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Read now rather than carried in the payload, so that an edit made
+        // between the delivery arriving and this running is the one that applies.
+        let Some(config) = job.config::<HoneycombWebhookConfig>(services).await? else {
+            return Ok(());
+        };
+
+        // Everything below this point happens *before* the payload is parsed, so
+        // that a delivery we cannot attribute to Honeycomb is never interpreted,
+        // let alone acted on. The payload carries a `shared_secret` of its own,
+        // which is deliberately not what is checked: believing the body about
+        // whether the body can be believed proves nothing.
+        //
+        // A rejection returns `Ok(())` rather than an error: nothing about a
+        // wrong secret improves by trying again, so raising here would only leave
+        // the delivery retrying forever and hiding real failures behind it. The
+        // log line is the record that it happened.
+
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No secret configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty secret as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed. It also
+            // means the check cannot be neutralised by clearing the box, and it is
+            // what the GitHub and Terraform Cloud webhooks do with their own.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Honeycomb webhook for a workflow with no shared secret configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no shared secret to check the delivery against.",
+                );
+                return Ok(());
+            }
+
+            let Some(presented) = Self::header(&job.event, "x-honeycomb-webhook-token") else {
+                warn!(
+                    "Received a Honeycomb webhook without an X-Honeycomb-Webhook-Token header; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The delivery did not carry an X-Honeycomb-Webhook-Token header.",
+                );
+                return Ok(());
+            };
+
+            if !tokens_match(&config.secret, presented) {
+                warn!(
+                    "Received a Honeycomb webhook whose X-Honeycomb-Webhook-Token did not match the configured secret; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The X-Honeycomb-Webhook-Token header did not match the configured secret.",
+                );
+                return Ok(());
+            }
+
+            crate::preview::verified();
+        }
+
+        let events: Vec<HoneycombAlertEventPayload> =
+            job.event.scripted(&config.script, "the trigger").await?;
+        for event in events {
+            Self::file(&ctx, &config, event).await?;
+        }
+
+        Ok(())
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize)]
 struct HoneycombAlertEventPayload {
@@ -419,6 +482,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -0,0 +1,381 @@
+//! What a webhook workflow's address will accept.
+//!
+//! A workflow reached at `/webhooks/w/{token}` is authenticated by nothing but
+//! its address, so whoever has seen the address can post to it as often, and as
+//! much, as they like. Every delivery that gets through is a queue row and very
+//! likely a task in somebody's Todoist, which makes a leaked address an easy way
+//! to bury someone.
+//!
+//! [`WebhookLimits`] bound that. A sender outside the workflow's allowlist is
+//! turned away before anything is read; one sending faster than the workflow's
+//! rate is told to come back later; and a body larger than the workflow allows
+//! is refused part way through reading it. The rate is a token bucket, so a
+//! sender catching up after an outage can send a burst at once without being
+//! throttled for it, but cannot sustain more than the rate.
+//!
+//! The buckets are held in memory rather than in the database. A refusal that
+//! cost a write would make the limit a way to spend the disk instead of the
+//! queue, and a bucket that starts full again after a restart gives away at
+//! most one burst.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use automate_api::{TenantId, WebhookLimits, WorkflowId};
+use human_errors::Error;
+
+use crate::config::DeliveryConfig;
+use crate::prelude::*;
+
+/// How long after noting one refusal against a workflow before another is noted.
+///
+/// Each refusal is answered, but only the first of a run is written to the
+/// audit log. A flood is exactly what these limits exist for, and an entry for
+/// every request in it would move the flood into the log.
+const NOTE_EVERY: Duration = Duration::from_secs(60);
+
+/// Checks that a workflow's limits could be applied, before they are saved.
+///
+/// Zeroes are refused where they would turn the address off rather than limit
+/// it: a burst of nothing accepts nothing, and pausing the workflow is the way
+/// to say that.
+pub fn validate(limits: &WebhookLimits) -> Result<(), Error> {
+    if limits.burst == Some(0) {
+        return Err(human_errors::user(
+            "A burst of zero would refuse every delivery.",
+            &[
+                "Leave the burst unset to allow a minute's worth at once.",
+                "Pause the workflow if it should not accept deliveries at all.",
+            ],
+        ));
+    }
+
+    if limits.max_body_bytes == Some(0) {
+        return Err(human_errors::user(
+            "A largest body of zero bytes would refuse every delivery with anything in it.",
+            &["Leave it unset to accept what the installation accepts."],
+        ));
+    }
+
+    if let Some(allow) = &limits.allow {
+        Filter::new(allow).wrap_user_err(
+            "The list of who may deliver could not be read.",
+            &[
+                "This is a filter, as admin_acl is, such as 'client_ip in [\"192.0.2.10\"]'.",
+                "Check that every quote and bracket is closed.",
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Whether a request is one the workflow's allowlist lets through.
+///
+/// Fails closed. The allowlist is something its owner chose to add, so a filter
+/// that cannot be evaluated against a request refuses it rather than quietly
+/// letting everybody in.
+pub fn allows(limits: &WebhookLimits, request: &impl Filterable) -> bool {
+    let Some(allow) = &limits.allow else {
+        return true;
+    };
+
+    match Filter::new(allow) {
+        Ok(filter) => filter.matches(request).unwrap_or(false),
+        Err(err) => {
+            warn!(error = %err, "A stored webhook allowlist no longer parses, so its deliveries are refused: {err}");
+            false
+        }
+    }
+}
+
+/// The largest body a workflow accepts, given the most the installation does.
+pub fn max_body(limits: &WebhookLimits, ceiling: usize) -> usize {
+    limits
+        .max_body_bytes
+        .map(|bytes| (bytes as usize).min(ceiling))
+        .unwrap_or(ceiling)
+}
+
+/// One workflow's share of deliveries.
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    noted_at: Option<Instant>,
+}
+
+/// The token buckets of every workflow that has been sent something since the
+/// agent started.
+///
+/// One entry per workflow that has received a delivery at a valid address, so
+/// the map grows with the number of workflows rather than with the number of
+/// requests. It is not pruned when a workflow is deleted: the entry is a few
+/// bytes, and a workflow that later draws the same identifier inherits nothing
+/// worse than a partly spent bucket.
+#[derive(Default)]
+pub struct Limiter {
+    buckets: Mutex<HashMap<(TenantId, WorkflowId), Bucket>>,
+}
+
+impl Limiter {
+    /// Takes a delivery from a workflow's bucket, or says how long until there
+    /// will be one to take.
+    pub fn admit(
+        &self,
+        tenant: &TenantId,
+        workflow: WorkflowId,
+        limits: &WebhookLimits,
+        config: &DeliveryConfig,
+    ) -> Result<(), Duration> {
+        self.admit_at(tenant, workflow, limits, config, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        tenant: &TenantId,
+        workflow: WorkflowId,
+        limits: &WebhookLimits,
+        config: &DeliveryConfig,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let per_minute = limits.per_minute.unwrap_or(config.per_minute);
+        if per_minute == 0 {
+            return Ok(());
+        }
+
+        let burst = limits.burst.unwrap_or(per_minute).max(1) as f64;
+        let per_second = per_minute as f64 / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let bucket = buckets
+            .entry((tenant.clone(), workflow))
+            .or_insert_with(|| Bucket {
+                tokens: burst,
+                refilled_at: now,
+                noted_at: None,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * per_second).min(burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    /// Whether a refusal should be written down, which is once a minute for
+    /// each workflow however many there are; see [`NOTE_EVERY`].
+    pub fn should_note(&self, tenant: &TenantId, workflow: WorkflowId) -> bool {
+        self.should_note_at(tenant, workflow, Instant::now())
+    }
+
+    fn should_note_at(&self, tenant: &TenantId, workflow: WorkflowId, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let bucket = buckets
+            .entry((tenant.clone(), workflow))
+            .or_insert_with(|| Bucket {
+                // Refusing a sender before it has been given any deliveries
+                // does not spend them; the bucket is filled on first use.
+                tokens: f64::INFINITY,
+                refilled_at: now,
+                noted_at: None,
+            });
+
+        match bucket.noted_at {
+            Some(noted) if now.saturating_duration_since(noted) < NOTE_EVERY => false,
+            _ => {
+                bucket.noted_at = Some(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterValue;
+
+    struct Sender(&'static str);
+
+    impl Filterable for Sender {
+        fn get(&self, key: &str) -> FilterValue<'_> {
+            match key {
+                "client_ip" => self.0.into(),
+                _ => FilterValue::Null,
+            }
+        }
+    }
+
+    fn workflow() -> WorkflowId {
+        WorkflowId::from_entropy(7)
+    }
+
+    #[test]
+    fn a_burst_is_accepted_and_then_the_rate_applies() {
+        let limiter = Limiter::default();
+        let limits = WebhookLimits {
+            per_minute: Some(6),
+            burst: Some(2),
+            ..Default::default()
+        };
+        let config = DeliveryConfig::default();
+        let start = Instant::now();
+
+        for _ in 0..2 {
+            assert_eq!(
+                limiter.admit_at(&TenantId::local(), workflow(), &limits, &config, start),
+                Ok(())
+            );
+        }
+
+        let wait = limiter
+            .admit_at(&TenantId::local(), workflow(), &limits, &config, start)
+            .expect_err("a third delivery at once should be more than the burst allows");
+        assert_eq!(wait.as_secs(), 10, "six a minute is one every ten seconds");
+
+        assert_eq!(
+            limiter.admit_at(
+                &TenantId::local(),
+                workflow(),
+                &limits,
+                &config,
+                start + Duration::from_secs(10)
+            ),
+            Ok(()),
+            "the bucket should have refilled by one",
+        );
+        assert_eq!(
+            limiter.admit_at(
+                &TenantId::local(),
+                WorkflowId::from_entropy(8),
+                &limits,
+                &config,
+                start
+            ),
+            Ok(()),
+            "each workflow should have a bucket of its own",
+        );
+    }
+
+    #[test]
+    fn the_installation_default_applies_unless_the_workflow_says_otherwise() {
+        let limiter = Limiter::default();
+        let config = DeliveryConfig {
+            per_minute: 1,
+            ..Default::default()
+        };
+        let start = Instant::now();
+
+        let defaults = WebhookLimits::default();
+        assert!(
+            limiter
+                .admit_at(&TenantId::local(), workflow(), &defaults, &config, start)
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .admit_at(&TenantId::local(), workflow(), &defaults, &config, start)
+                .is_err()
+        );
+
+        let unlimited = WebhookLimits {
+            per_minute: Some(0),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            assert_eq!(
+                limiter.admit_at(&TenantId::local(), workflow(), &unlimited, &config, start),
+                Ok(()),
+                "a rate of zero should accept everything",
+            );
+        }
+    }
+
+    #[test]
+    fn refusals_are_noted_once_a_minute() {
+        let limiter = Limiter::default();
+        let start = Instant::now();
+
+        assert!(limiter.should_note_at(&TenantId::local(), workflow(), start));
+        assert!(!limiter.should_note_at(
+            &TenantId::local(),
+            workflow(),
+            start + Duration::from_secs(30)
+        ));
+        assert!(limiter.should_note_at(
+            &TenantId::local(),
+            workflow(),
+            start + Duration::from_secs(61)
+        ));
+    }
+
+    #[test]
+    fn the_allowlist_is_a_filter_over_the_sender() {
+        let limits = WebhookLimits {
+            allow: Some(r#"client_ip in ["192.0.2.10", "192.0.2.11"]"#.into()),
+            ..Default::default()
+        };
+
+        assert!(allows(&limits, &Sender("192.0.2.10")));
+        assert!(!allows(&limits, &Sender("198.51.100.7")));
+        assert!(
+            allows(&WebhookLimits::default(), &Sender("198.51.100.7")),
+            "without an allowlist anybody holding the address may deliver",
+        );
+    }
+
+    #[test]
+    fn limits_that_would_refuse_everything_or_cannot_be_read_are_not_saved() {
+        assert!(validate(&WebhookLimits::default()).is_ok());
+
+        for limits in [
+            WebhookLimits {
+                burst: Some(0),
+                ..Default::default()
+            },
+            WebhookLimits {
+                max_body_bytes: Some(0),
+                ..Default::default()
+            },
+            WebhookLimits {
+                allow: Some("client_ip in [".into()),
+                ..Default::default()
+            },
+        ] {
+            assert!(validate(&limits).is_err(), "{limits:?} should be refused");
+        }
+    }
+
+    #[test]
+    fn a_workflow_can_lower_the_body_limit_but_not_raise_it() {
+        let ceiling = 1024;
+
+        assert_eq!(max_body(&WebhookLimits::default(), ceiling), 1024);
+        assert_eq!(
+            max_body(
+                &WebhookLimits {
+                    max_body_bytes: Some(100),
+                    ..Default::default()
+                },
+                ceiling
+            ),
+            100
+        );
+        assert_eq!(
+            max_body(
+                &WebhookLimits {
+                    max_body_bytes: Some(1_000_000),
+                    ..Default::default()
+                },
+                ceiling
+            ),
+            1024
+        );
+    }
+}
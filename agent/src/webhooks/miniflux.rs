@@ -10,17 +10,16 @@
 
 use std::fmt::Display;
 
-use hmac::{Hmac, KeyInit, Mac};
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 
 use crate::{
     prelude::*,
-    publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate, TodoistTarget},
+    publishers::digest::{self, Digest, DigestSource},
+    publishers::{TodoistCreateTaskPayload, TodoistDueDate, TodoistTarget},
+    script::Script,
+    webhook_signature::Algorithm,
 };
 
-type HmacSha256 = Hmac<Sha256>;
-
 /// What one person asked us to do with the entries their Miniflux reports.
 ///
 /// This carries the secret Miniflux generated, because the address on its own
@@ -52,6 +51,15 @@ pub struct MinifluxWebhookConfig {
     #[serde(default)]
     pub saved_entries: MinifluxEventConfig,
 
+    /// Reshapes each entry, discovered or saved, before it is filtered. Empty
+    /// files entries as Miniflux sends them.
+    #[serde(default)]
+    pub script: Script,
+
+    /// Whether entries are gathered into a periodic digest instead.
+    #[serde(default)]
+    pub digest: Digest,
+
     #[serde(default = "default_todoist_config")]
     pub todoist: TodoistTarget,
 }
@@ -151,6 +159,30 @@ feed contains "security" || title contains "release"
 
 Leave a filter empty to file every entry that event carries.
 
+## Reshaping entries with a script
+
+A **Script** runs against each entry, discovered or saved, before its filter
+does. The entry is `item`, with the `title`, `url`, `content`, `author` and
+`tags` Miniflux sent, so it can tidy a title, bring a tag into it, or drop an
+entry by a rule too involved for a filter:
+
+```
+if item.tags.contains("sponsored") { return; }
+item.title.replace("[Sponsored] ", "");
+item
+```
+
+End with `item` to file the entry, with a list of them to file each, or with
+nothing to skip it. Scripts are written in [Rhai](https://rhai.rs/book/); the
+generic webhook's notes describe what they can and cannot do.
+
+## Digests
+
+**Send as a digest** holds entries back rather than filing each as it arrives,
+and files one task listing them all, with their links, on the **Digest
+schedule**. **Quiet hours** holds back only what arrives during them, so a feed
+refresh at 3am waits for the morning instead of making your phone buzz.
+
 ## No retries
 
 Miniflux does not retry a delivery that fails, so an entry missed while this
@@ -174,33 +206,27 @@ impl MinifluxWebhook {
             "Ensure that you are only sending Miniflux webhooks to this endpoint.",
         ])?;
 
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).wrap_user_err(
-            "Failed to create HMAC instance with the provided secret.",
-            &["Ensure that you have set a valid webhook secret on this workflow."],
-        )?;
-
-        mac.update(body.as_bytes());
-
-        // `verify_slice` compares in constant time, so a wrong signature cannot
-        // be walked one byte at a time by timing the rejections.
-        mac.verify_slice(&expected_signature).wrap_user_err(
-            "Webhook signature verification failed (signatures did not match).".to_string(),
-            &[
-                "Ensure that the webhook secret on this workflow matches the one shown under Settings → Integrations → Webhook in Miniflux.",
-            ],
-        )?;
+        if !Algorithm::Sha256.verify(secret.as_bytes(), body.as_bytes(), &expected_signature) {
+            return Err(human_errors::user(
+                "Webhook signature verification failed (signatures did not match).",
+                &[
+                    "Ensure that the webhook secret on this workflow matches the one shown under Settings → Integrations → Webhook in Miniflux.",
+                ],
+            ));
+        }
 
         Ok(())
     }
 
     /// Files one entry, if the event's filter wants it.
     async fn file(
+        workflow: &str,
         config: &MinifluxWebhookConfig,
         event: &MinifluxEventConfig,
         entry: MinifluxEntryRef<'_>,
         services: &(impl Services + Send + Sync + 'static),
     ) -> Result<(), human_errors::Error> {
-        if !event.filter.matches(&entry)? {
+        if !crate::preview::matches(&event.filter, &entry, || entry.entry.title.clone())? {
             info!(
                 "Miniflux entry '{}' did not match filter; ignoring.",
                 entry.entry.title
@@ -217,7 +243,13 @@ impl MinifluxWebhook {
             .ok()
             .or_else(|| entry.feed().and_then(|feed| feed.site_url.parse().ok()));
 
-        TodoistCreateTask::dispatch(
+        digest::file(
+            services,
+            &config.digest,
+            DigestSource {
+                key: workflow,
+                name: &config.name,
+            },
             TodoistCreateTaskPayload {
                 title: format!(
                     "[{}]({}): {}",
@@ -237,8 +269,7 @@ impl MinifluxWebhook {
                 config: config.todoist.clone(),
                 ..Default::default()
             },
-            None,
-            services,
+            Some(&entry.entry.url),
         )
         .await
     }
@@ -333,8 +364,14 @@ impl crate::workflows::ConfigurableWorkflow for MinifluxWebhook {
                 .with_help(
                     "Only file the saved entries matching this. Leave it empty to file every entry you save.",
                 ),
+                crate::script_field!(
+                    MinifluxWebhookConfig,
+                    "entry",
+                    "item.title.replace(\"[Sponsored] \", \"\");\nitem"
+                ),
             ]
             .into_iter()
+            .chain(crate::digest_fields!(MinifluxWebhookConfig))
             .chain(crate::todoist_target_fields!(
                 MinifluxWebhookConfig,
                 project = Some("Hobbies"),
@@ -378,6 +415,7 @@ impl Job for MinifluxWebhook {
         };
 
         let event = &job.event;
+        let workflow = job.workflow.to_string();
 
         // Everything below this point happens *before* the payload is parsed, so
         // that a delivery we cannot attribute to Miniflux is never interpreted,
@@ -388,30 +426,44 @@ impl Job for MinifluxWebhook {
         // the delivery retrying forever and hiding real failures behind it. The
         // log line is the record that it happened.
 
-        // No secret configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty secret as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Miniflux webhook for a workflow with no secret configured; rejecting request."
-            );
-            return Ok(());
-        }
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No secret configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty secret as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Miniflux webhook for a workflow with no secret configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no secret to check the signature against.",
+                );
+                return Ok(());
+            }
 
-        let Some(signature) = event.header("x-miniflux-signature") else {
-            warn!(
-                "Received a Miniflux webhook without an X-Miniflux-Signature header; rejecting request."
-            );
-            return Ok(());
-        };
+            let Some(signature) = event.header("x-miniflux-signature") else {
+                warn!(
+                    "Received a Miniflux webhook without an X-Miniflux-Signature header; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The delivery did not carry a X-Miniflux-Signature header.",
+                );
+                return Ok(());
+            };
+
+            if let Err(err) = Self::verify_signature(&config.secret, &event.body, signature) {
+                warn!(
+                    "Failed to verify Miniflux webhook signature, rejecting request: {}",
+                    err
+                );
+                crate::preview::rejected(err.description());
+                return Ok(());
+            }
 
-        if let Err(err) = Self::verify_signature(&config.secret, &event.body, signature) {
-            warn!(
-                "Failed to verify Miniflux webhook signature, rejecting request: {}",
-                err
-            );
-            return Ok(());
+            crate::preview::verified();
         }
 
         match event.json::<MinifluxEvent>()? {
@@ -421,8 +473,14 @@ impl Job for MinifluxWebhook {
                     return Ok(());
                 }
 
+                let entries = config
+                    .script
+                    .reshape(entries, |entry| format!("the entry '{}'", entry.title))
+                    .await?;
+
                 for entry in &entries {
                     Self::file(
+                        &workflow,
                         &config,
                         &config.new_entries,
                         MinifluxEntryRef {
@@ -440,16 +498,21 @@ impl Job for MinifluxWebhook {
                     return Ok(());
                 }
 
-                Self::file(
-                    &config,
-                    &config.saved_entries,
-                    MinifluxEntryRef {
-                        entry: &entry,
-                        feed: None,
-                    },
-                    services,
-                )
-                .await?;
+                let entries = config
+                    .script
+                    .reshape(vec![entry], |entry| format!("the entry '{}'", entry.title))
+                    .await?;
+
+                for entry in &entries {
+                    Self::file(
+                        &workflow,
+                        &config,
+                        &config.saved_entries,
+                        MinifluxEntryRef { entry, feed: None },
+                        services,
+                    )
+                    .await?;
+                }
             }
             MinifluxEvent::Unknown => {
                 info!("Ignoring a Miniflux event of a type we do not handle.");
@@ -481,7 +544,7 @@ enum MinifluxEvent {
     Unknown,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 struct MinifluxFeed {
     #[serde(default)]
     title: String,
@@ -489,7 +552,7 @@ struct MinifluxFeed {
     site_url: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 struct MinifluxEntry {
     #[serde(default)]
     title: String,
@@ -558,6 +621,8 @@ mod tests {
     use std::collections::HashMap;
 
     use chrono::Utc;
+    use hmac::{Hmac, KeyInit, Mac};
+    use sha2::Sha256;
 
     use crate::{
         webhooks::{WebhookDelivery, WebhookEvent},
@@ -566,6 +631,8 @@ mod tests {
 
     use super::*;
 
+    type HmacSha256 = Hmac<Sha256>;
+
     /// A delivery as Miniflux sends one after a feed refresh.
     const NEW_ENTRIES: &str = r#"{"event_type":"new_entries","feed":{"id":8,"user_id":1,"feed_url":"https://example.org/feed.xml","site_url":"https://example.org","title":"Example website","checked_at":"2023-09-10T12:48:43.428196-07:00"},"entries":[{"id":231,"user_id":1,"feed_id":8,"status":"unread","hash":"1163a9","title":"Example","url":"https://example.org/article","comments_url":"","published_at":"2023-08-17T19:29:22Z","created_at":"2023-09-10T12:48:43.428196-07:00","changed_at":"2023-09-10T12:48:43.428196-07:00","content":"<p>Some HTML content with a <a href=\"/relative\">relative link</a></p>","author":"Alice","share_code":"","starred":false,"reading_time":1,"enclosures":[],"tags":["Some category"]}]}"#;
 
@@ -626,6 +693,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
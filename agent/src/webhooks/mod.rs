@@ -1,14 +1,18 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::prelude::*;
+use crate::workflow_store::SnoozeVerdict;
 
 mod azure_monitor;
 mod github;
 mod grafana;
 mod grey;
 mod honeycomb;
+pub mod limits;
 mod miniflux;
+pub mod repeats;
 mod routing;
 mod sentry;
 mod tailscale;
@@ -41,6 +45,28 @@ impl std::fmt::Display for WebhookEvent {
 pub struct WebhookDelivery {
     pub workflow: automate_api::WorkflowId,
     pub event: WebhookEvent,
+
+    /// The kept delivery this one is a replay of, when an owner asked for one
+    /// to be run again.
+    ///
+    /// A replay carries the credentials it first arrived with, put back from
+    /// where they were sealed, and its signature is checked again without its
+    /// age being held against it. Only the replay endpoint sets this, and it
+    /// refuses a delivery whose signature was turned away or never checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<automate_api::DeliveryId>,
+
+    /// When the sender posted the delivery, which is what its signature's age
+    /// is measured against and what a task's due date is taken from.
+    ///
+    /// The queue dates a message by when it was last enqueued, and a delivery
+    /// held while its workflow was snoozed is enqueued again when it is held
+    /// and again when it is released. The job host hands this to the workflow
+    /// as [`JobContext::scheduled_at`] instead, so that neither makes the
+    /// delivery look newer than it is. Only the webhook endpoint sets it; a
+    /// delivery without one is dated by the queue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrived_at: Option<DateTime<Utc>>,
 }
 
 impl std::fmt::Display for WebhookDelivery {
@@ -55,6 +81,10 @@ impl WebhookDelivery {
     /// `None` means the workflow has been deleted or paused since the delivery
     /// arrived, which is not a failure — there is simply nothing to do, and
     /// retrying would not change that.
+    ///
+    /// A preview replaying this delivery supplies the configuration itself,
+    /// which is how an edit is tried out before it is saved — and how a paused
+    /// workflow is previewed at all.
     pub async fn config<C>(
         &self,
         services: &(impl Services + Send + Sync + 'static),
@@ -62,18 +92,49 @@ impl WebhookDelivery {
     where
         C: serde::de::DeserializeOwned,
     {
+        if let Some(config) = crate::preview::replay_config() {
+            return serde_json::from_value(config).map(Some).wrap_user_err(
+                "This workflow is not configured correctly, so the delivery could not be replayed.",
+                &["Check that every field the workflow asks for is filled in."],
+            );
+        }
+
         let store = crate::workflow_store::WorkflowStore::new(services);
 
         let Some(record) = store.find(self.workflow).await? else {
             info!(workflow.id = %self.workflow, "Discarding a delivery for a workflow which no longer exists.");
+            crate::preview::discarded("The workflow no longer exists.");
             return Ok(None);
         };
 
         if !record.enabled {
-            debug!(workflow.id = %record.id, "Discarding a delivery for a paused workflow.");
+            // At `info` so that it is kept with the run: it is the whole answer
+            // to "why did this delivery file nothing?".
+            info!(workflow.id = %record.id, "Discarding a delivery for a paused workflow.");
+            crate::preview::discarded("The workflow is paused.");
             return Ok(None);
         }
 
+        match store.check_snooze(&record, Utc::now()).await? {
+            SnoozeVerdict::Run => {}
+            SnoozeVerdict::Skip => {
+                info!(workflow.id = %record.id, "Discarding a delivery for a snoozed workflow.");
+                crate::preview::discarded("The workflow is snoozed.");
+                return Ok(None);
+            }
+            SnoozeVerdict::Hold { until } => {
+                self.hold(services, &record.type_id, until).await?;
+                info!(
+                    workflow.id = %record.id,
+                    "Holding a delivery for a snoozed workflow until {until}.",
+                );
+                crate::preview::discarded(format!(
+                    "The workflow is snoozed, so the delivery is held until {until}."
+                ));
+                return Ok(None);
+            }
+        }
+
         let config = serde_json::from_value(record.config).wrap_user_err(
             "This workflow is not configured correctly, so a delivery could not be handled.",
             &["Open the workflow and check that every field it asks for is filled in."],
@@ -81,6 +142,82 @@ impl WebhookDelivery {
 
         Ok(Some(config))
     }
+
+    /// Puts this delivery back on its workflow's queue, out of sight until
+    /// `until`.
+    ///
+    /// Kept in the queue rather than somewhere of its own because the queue
+    /// already knows how to wait: when the snooze ends the delivery simply comes
+    /// due, with nothing having to remember to release it. If the workflow has
+    /// been snoozed again by then it is held again.
+    async fn hold(
+        &self,
+        services: &(impl Services + Send + Sync + 'static),
+        type_id: &str,
+        until: DateTime<Utc>,
+    ) -> Result<(), human_errors::Error> {
+        let partition = crate::workflows::lookup(type_id)?.partition().to_string();
+        let key = format!("{}{}", held_prefix(self.workflow), uuid::Uuid::new_v4());
+
+        services
+            .queue()
+            .enqueue(
+                partition,
+                self.clone(),
+                Some(key.into()),
+                Some((until - Utc::now()).max(chrono::Duration::zero())),
+            )
+            .await
+    }
+
+    /// Hands a workflow's held deliveries to it now, for when it is woken
+    /// before its snooze would have ended. Returns how many were released.
+    ///
+    /// Enqueueing under the same key replaces each one's wait with none, so a
+    /// delivery released twice is still handled once. The queue dates each one
+    /// afresh as it does, which is why a delivery carries
+    /// [`WebhookDelivery::arrived_at`] rather than relying on that.
+    pub async fn release(
+        services: &(impl Services + Send + Sync + 'static),
+        workflow: automate_api::WorkflowId,
+        type_id: &str,
+    ) -> Result<usize, human_errors::Error> {
+        let partition = crate::workflows::lookup(type_id)?.partition().to_string();
+        let prefix = held_prefix(workflow);
+
+        let held: Vec<crate::db::PeekedMessage<serde_json::Value>> =
+            services.queue().peek(partition.clone(), MAX_HELD).await?;
+
+        let mut released = 0;
+        for message in held {
+            if !message.key.starts_with(&prefix) {
+                continue;
+            }
+
+            services
+                .queue()
+                .enqueue(
+                    partition.clone(),
+                    message.payload,
+                    Some(message.key.into()),
+                    None,
+                )
+                .await?;
+            released += 1;
+        }
+
+        Ok(released)
+    }
+}
+
+/// The most queued deliveries looked through when releasing a workflow's held
+/// ones, which bounds a single request rather than what can be held: anything
+/// past it is still released when its snooze would have ended.
+const MAX_HELD: usize = 1000;
+
+/// How the queue keys of one workflow's held deliveries begin.
+fn held_prefix(workflow: automate_api::WorkflowId) -> String {
+    format!("held/{workflow}/")
 }
 
 impl WebhookEvent {
@@ -91,6 +228,24 @@ impl WebhookEvent {
         )
     }
 
+    /// The payloads a workflow should handle for this delivery: its body, run
+    /// through the workflow's script if it has one, read as `T`.
+    ///
+    /// `describe` names the delivery in an error, such as "the alert".
+    pub async fn scripted<T: DeserializeOwned>(
+        &self,
+        script: &crate::script::Script,
+        describe: &str,
+    ) -> Result<Vec<T>, human_errors::Error> {
+        if script.is_empty() {
+            return Ok(vec![self.json()?]);
+        }
+
+        script
+            .read(vec![(self.json()?, describe.to_string())])
+            .await
+    }
+
     /// A header, matched without regard to case as HTTP requires (RFC 7230).
     pub fn header(&self, name: &str) -> Option<&str> {
         self.headers
@@ -0,0 +1,319 @@
+//! Dropping the deliveries a sender sends more than once.
+//!
+//! Senders retry when they do not hear back in time, and a timeout is not a
+//! failure: the first attempt may well have been queued before the sender gave
+//! up on it. Each retry would otherwise be queued as a delivery of its own, and
+//! a workflow that only ever creates — a task per Sentry issue, a task per
+//! Tailscale event — files the same thing again for each.
+//!
+//! Most senders name each delivery in a header that they hold constant across
+//! their own retries (GitHub's `X-GitHub-Delivery`, Sentry's `Request-ID`), and
+//! that is what is remembered here: per workflow, by the sender's id, for
+//! [`crate::config::DeliveryConfig::dedupe_hours`]. A delivery carrying an id
+//! already seen in that window is answered as accepted, so the sender stops
+//! retrying, and noted in the workflow's delivery log rather than queued.
+//!
+//! The check is made before the delivery is queued rather than when it runs,
+//! because by then a retry that arrived while the first was still waiting has
+//! replaced it in the queue and reset its reservation.
+
+use automate_api::{RunOutcome, TenantId, WorkflowId};
+use chrono::{DateTime, Utc};
+use human_errors::Error;
+
+use crate::db::KeyValueStore;
+use crate::prelude::*;
+use crate::services::AppContext;
+
+/// The prefix of the partitions holding the ids each workflow has seen.
+///
+/// Not under `webhooks/`, where each source keeps its workflows' configuration
+/// and a source could one day be called anything.
+pub const SEEN_PARTITION: &str = "seen-deliveries";
+
+/// The longest sender id worth keeping. The built-in senders use UUIDs; an id
+/// longer than this is more likely a header being misused than a real one.
+const MAX_ID_LEN: usize = 200;
+
+fn partition(workflow: WorkflowId) -> String {
+    format!("{SEEN_PARTITION}/{workflow}")
+}
+
+/// The ids one tenant's workflows have recently been sent, and when each was
+/// first seen.
+pub struct SeenDeliveries<S> {
+    services: S,
+}
+
+impl<S: Services> SeenDeliveries<S> {
+    pub fn new(services: S) -> Self {
+        Self { services }
+    }
+
+    /// Notes that a delivery with this id has arrived, returning when it first
+    /// arrived if this is a repeat of one still remembered.
+    ///
+    /// The note is written before the answer is given, so two attempts arriving
+    /// together cannot both be taken for the first.
+    pub async fn note(
+        &self,
+        workflow: WorkflowId,
+        id: &str,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let window = self.services.config().deliveries.dedupe_for();
+        if window <= chrono::Duration::zero() || id.is_empty() || id.len() > MAX_ID_LEN {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let kv = self.services.kv();
+
+        if kv.insert(partition(workflow), id.to_string(), now).await? {
+            return Ok(None);
+        }
+
+        match kv
+            .get::<DateTime<Utc>>(partition(workflow), id.to_string())
+            .await?
+        {
+            Some(first) if first > now - window => Ok(Some(first)),
+            // Seen, but long enough ago that the sender cannot still be
+            // retrying it, so this is a new delivery reusing an old id.
+            _ => {
+                kv.set(partition(workflow), id.to_string(), now).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Forgets that a delivery arrived, for when it could not be queued and the
+    /// sender's retry is the only way it will be.
+    pub async fn unnote(&self, workflow: WorkflowId, id: &str) -> Result<(), Error> {
+        self.services
+            .kv()
+            .remove(partition(workflow), id.to_string())
+            .await
+    }
+
+    /// Forgets the ids seen longer ago than anybody retries, returning how many
+    /// were removed.
+    pub async fn prune(&self, workflow: WorkflowId) -> Result<usize, Error> {
+        let cutoff = Utc::now() - self.services.config().deliveries.dedupe_for();
+        let seen: Vec<(String, DateTime<Utc>)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        let mut removed = 0;
+        for (id, first) in seen {
+            if first <= cutoff {
+                self.services.kv().remove(partition(workflow), id).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Forgets every id a workflow has seen, for when the workflow has gone.
+    pub async fn forget(&self, workflow: WorkflowId) -> Result<(), Error> {
+        let seen: Vec<(String, serde_json::Value)> =
+            self.services.kv().list(partition(workflow)).await?;
+
+        for (id, _) in seen {
+            self.services.kv().remove(partition(workflow), id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a delivery repeats one the workflow was already sent, noting it if
+/// not.
+///
+/// A repeat is kept in the workflow's delivery log, so that somebody wondering
+/// why a retry filed nothing can see that it arrived and why it was dropped.
+///
+/// Fails open. Failing to read or write the note is logged and the delivery is
+/// treated as new: a duplicate task is a nuisance, where a dropped delivery is
+/// one nobody will know to look for.
+pub async fn is_repeat(
+    services: &(impl Services + Send + Sync + 'static),
+    workflow: WorkflowId,
+    event: &WebhookEvent,
+    id: &str,
+) -> bool {
+    let first = match SeenDeliveries::new(services).note(workflow, id).await {
+        Ok(Some(first)) => first,
+        Ok(None) => return false,
+        Err(err) => {
+            warn!(error = %err, workflow.id = %workflow, "Failed to check whether a webhook delivery was a repeat, so it is handled as a new one: {err}");
+            return false;
+        }
+    };
+
+    info!(workflow.id = %workflow, delivery.id = %id, "Dropping a webhook delivery the sender had already sent.");
+
+    if services.config().deliveries.keep == 0 {
+        return true;
+    }
+
+    let secrets = match crate::variables::VariableStore::for_services(services)
+        .load()
+        .await
+    {
+        Ok(variables) => variables.secret_values(),
+        Err(err) => {
+            warn!(error = %err, "Failed to load the secrets to scrub from a webhook delivery, so it will not be kept: {err}");
+            return true;
+        }
+    };
+
+    let mut record = crate::deliveries::describe(
+        event,
+        Utc::now(),
+        RunOutcome::Discarded,
+        Some(&format!(
+            "Dropped, because the sender already sent the delivery '{id}' at {}.",
+            first.to_rfc3339()
+        )),
+        Default::default(),
+        &secrets,
+    );
+    record.repeat_of = Some(id.to_string());
+
+    if let Err(err) = crate::deliveries::DeliveryStore::new(services)
+        .record(workflow, record, event)
+        .await
+    {
+        warn!(error = %err, "Failed to keep a repeated webhook delivery for inspection: {err}");
+    }
+
+    true
+}
+
+/// Forgets every account's expired sender ids, returning how many were removed.
+///
+/// Each note outlives its use by design, since nothing else looks at it once
+/// its window has passed; this is what stops a busy workflow's partition from
+/// growing by one entry per delivery for ever.
+pub async fn prune_all(context: &AppContext) -> Result<usize, Error> {
+    let mut removed = 0;
+
+    for tenant in context.database().tenants().await? {
+        if tenant == TenantId::system() {
+            continue;
+        }
+
+        let services = context.tenant(tenant);
+        let store = SeenDeliveries::new(&services);
+
+        for name in services.kv().partitions().await? {
+            let Some(workflow) = name
+                .strip_prefix(SEEN_PARTITION)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+
+            removed += store.prune(workflow).await?;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::services::ServicesContainer;
+
+    fn workflow() -> WorkflowId {
+        WorkflowId::from_entropy(7)
+    }
+
+    fn event() -> WebhookEvent {
+        WebhookEvent {
+            body: r#"{"action":"created"}"#.to_string(),
+            query: String::new(),
+            headers: HashMap::from([(
+                "Request-ID".to_string(),
+                "0f4c8a3e-7b1d-4d5e-9a2f-3c6b8e1d4f70".to_string(),
+            )]),
+        }
+    }
+
+    #[tokio::test]
+    async fn the_first_delivery_of_an_id_is_new_and_a_retry_of_it_is_not() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let seen = SeenDeliveries::new(&services);
+
+        assert_eq!(seen.note(workflow(), "abc").await.unwrap(), None);
+        assert!(
+            seen.note(workflow(), "abc").await.unwrap().is_some(),
+            "the sender retrying the same delivery should be recognised",
+        );
+        assert_eq!(
+            seen.note(WorkflowId::from_entropy(8), "abc").await.unwrap(),
+            None,
+            "each workflow is sent its own copy, so each should see it once",
+        );
+    }
+
+    #[tokio::test]
+    async fn an_id_seen_longer_ago_than_the_window_is_new_again() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        services
+            .kv()
+            .set(
+                partition(workflow()),
+                "abc",
+                Utc::now() - chrono::Duration::hours(25),
+            )
+            .await
+            .unwrap();
+
+        let seen = SeenDeliveries::new(&services);
+        assert_eq!(seen.note(workflow(), "abc").await.unwrap(), None);
+        assert!(
+            seen.note(workflow(), "abc").await.unwrap().is_some(),
+            "reusing the id should start its window again",
+        );
+
+        assert_eq!(seen.prune(workflow()).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_remembered_when_the_window_is_zero() {
+        let services = ServicesContainer::new_custom_mock(|config, _| {
+            config.deliveries.dedupe_hours = 0;
+        })
+        .await
+        .unwrap();
+        let seen = SeenDeliveries::new(&services);
+
+        assert_eq!(seen.note(workflow(), "abc").await.unwrap(), None);
+        assert_eq!(seen.note(workflow(), "abc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_repeat_is_kept_in_the_delivery_log_and_says_what_it_repeated() {
+        let services = ServicesContainer::new_mock().await.unwrap();
+        let id = "0f4c8a3e-7b1d-4d5e-9a2f-3c6b8e1d4f70";
+
+        assert!(!is_repeat(&services, workflow(), &event(), id).await);
+        assert!(is_repeat(&services, workflow(), &event(), id).await);
+
+        let kept = crate::deliveries::DeliveryStore::new(&services)
+            .list(workflow())
+            .await
+            .unwrap();
+        assert_eq!(
+            kept.len(),
+            1,
+            "only the drop is logged here; the first is logged when it runs",
+        );
+        assert_eq!(kept[0].repeat_of.as_deref(), Some(id));
+    }
+}
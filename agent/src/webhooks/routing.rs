@@ -27,6 +27,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
 
 use automate_api::{ConnectionId, TenantId};
+use chrono::Utc;
 
 use super::{WebhookDelivery, WebhookEvent};
 use crate::connections::ConnectionStore;
@@ -288,6 +289,15 @@ pub async fn route(
                 continue;
             }
 
+            // Checked per workflow, since each is sent its own copy: a retry
+            // is dropped for the workflows that queued the first attempt, and
+            // still reaches one that was only connected since.
+            if let Some(delivery) = &delivery
+                && super::repeats::is_repeat(&services, workflow.id, &event, delivery).await
+            {
+                continue;
+            }
+
             let idempotency_key = delivery
                 .as_ref()
                 .map(|delivery| Cow::Owned(format!("{delivery}/{}", workflow.id)));
@@ -299,6 +309,8 @@ pub async fn route(
                     WebhookDelivery {
                         workflow: workflow.id,
                         event: event.clone(),
+                        replay_of: None,
+                        arrived_at: Some(Utc::now()),
                     },
                     idempotency_key,
                     None,
@@ -306,6 +318,17 @@ pub async fn route(
                 .await
             {
                 error!(error = %err, workflow.id = %workflow.id, "Failed to enqueue a webhook delivery: {err}");
+
+                // The sender will retry, and the retry must not be taken for a
+                // repeat of this.
+                if let Some(delivery) = &delivery
+                    && let Err(err) = super::repeats::SeenDeliveries::new(&services)
+                        .unnote(workflow.id, delivery)
+                        .await
+                {
+                    warn!(error = %err, "Failed to forget a webhook delivery that could not be queued: {err}");
+                }
+
                 return Delivered::Failed;
             }
         }
@@ -6,6 +6,7 @@ use serde_json::Value;
 use crate::{
     prelude::*,
     publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate},
+    script::Script,
 };
 
 /// What one person asked us to do with the deliveries Sentry sends them.
@@ -23,6 +24,11 @@ pub struct SentryWebhookConfig {
     /// list of them.
     pub name: String,
 
+    /// Reshapes each delivery before it is filtered. Empty files issues as
+    /// Sentry sends them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: crate::filter::Filter,
 
@@ -105,6 +111,23 @@ issue_level in ["fatal", "error"]
 ```
 
 Leave it empty to file everything Sentry sends here.
+
+## Reshaping issues with a script
+
+A **Script** runs against each delivery before the filter does, with the body
+Sentry sent as `item`, in whichever of the two shapes above it arrived. It can
+do what a filter cannot, such as raising the level of anything from a project
+you care most about, or tidying the titles one noisy library produces:
+
+```
+if item.project_slug == "checkout" { item.level = "fatal"; }
+item
+```
+
+End with `item` to file the issue, with a list to file each element, or with
+nothing to skip it; whatever it returns still needs the fields Sentry sends.
+Scripts are written in [Rhai](https://rhai.rs/book/); the generic webhook's
+notes describe what they can and cannot do.
 "#;
 
 crate::register_job!(SentryAlertsWebhook);
@@ -121,6 +144,12 @@ impl crate::workflows::ConfigurableWorkflow for SentryAlertsWebhook {
         config.name.clone()
     }
 
+    /// Sentry's integration platform sends one per request, and resends it
+    /// with the request when it retries.
+    fn delivery_header(_config: &Self::ConfigType) -> Option<String> {
+        Some("request-id".to_string())
+    }
+
     fn descriptor() -> automate_api::WorkflowTypeDescriptor {
         use automate_api::{FieldDescriptor, FieldKind, WorkflowTrigger, WorkflowTypeDescriptor};
 
@@ -167,6 +196,11 @@ impl crate::workflows::ConfigurableWorkflow for SentryAlertsWebhook {
                 .with_help(
                     "Only file the issues matching this, such as issue_level in [\"fatal\", \"error\"]. Leave it empty to file every issue Sentry sends.",
                 ),
+                crate::script_field!(
+                    SentryWebhookConfig,
+                    "delivery",
+                    "if item.project_slug == \"checkout\" { item.level = \"fatal\"; }\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -179,29 +213,15 @@ impl crate::workflows::ConfigurableWorkflow for SentryAlertsWebhook {
     }
 }
 
-impl Job for SentryAlertsWebhook {
-    type JobType = crate::webhooks::WebhookDelivery;
-
-    fn partition() -> &'static str {
-        "webhooks/sentry"
-    }
-
-    #[instrument("webhooks.sentry.handle", skip(self, ctx, job), fields(job = %job))]
-    async fn handle(
-        &self,
-        ctx: JobContext<impl Services + Send + Sync + 'static>,
-        job: &Self::JobType,
+impl SentryAlertsWebhook {
+    /// Files the task for one issue or alert.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &SentryWebhookConfig,
+        notification: SentryNotification,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
 
-        // Read now rather than carried in the delivery, so that an edit made
-        // between the delivery arriving and this running is the one that applies.
-        let Some(config) = job.config::<SentryWebhookConfig>(services).await? else {
-            return Ok(());
-        };
-
-        let notification: SentryNotification = job.event.json()?;
-
         match notification {
             SentryNotification::Integration(integration) => {
                 // Only process created issues (new errors)
@@ -210,7 +230,9 @@ impl Job for SentryAlertsWebhook {
                     return Ok(());
                 }
 
-                if !config.filter.matches(&integration)? {
+                if !crate::preview::matches(&config.filter, &integration, || {
+                    integration.data.issue.title.clone()
+                })? {
                     info!(
                         "Sentry issue '{}' did not match filter; ignoring.",
                         integration.data.issue.title
@@ -235,7 +257,7 @@ impl Job for SentryAlertsWebhook {
                 .await?;
             }
             SentryNotification::Alert(alert) => {
-                if !config.filter.matches(&alert)? {
+                if !crate::preview::matches(&config.filter, &alert, || alert.title())? {
                     info!(
                         "Sentry alert '{}' did not match filter; ignoring.",
                         alert.title()
@@ -268,6 +290,39 @@ impl Job for SentryAlertsWebhook {
     }
 }
 
+impl Job for SentryAlertsWebhook {
+    type JobType = crate::webhooks::WebhookDelivery;
+
+    fn partition() -> &'static str {
+        "webhooks/sentry"
+    }
+
+    #[instrument("webhooks.sentry.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Read now rather than carried in the delivery, so that an edit made
+        // between the delivery arriving and this running is the one that applies.
+        let Some(config) = job.config::<SentryWebhookConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let notifications: Vec<SentryNotification> = job
+            .event
+            .scripted(&config.script, "the notification")
+            .await?;
+        for notification in notifications {
+            Self::file(&ctx, &config, notification).await?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Represents the two different Sentry webhook payload formats:
 /// - Integration Platform webhooks (with `action`, `actor`, `data`)
 /// - Issue Alert webhooks (with `id`, `project`, `level`, `url`, `event`)
@@ -500,6 +555,8 @@ mod tests {
                 query: String::new(),
                 headers: HashMap::new(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
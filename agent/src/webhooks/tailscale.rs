@@ -1,17 +1,15 @@
 use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
-use hmac::{Hmac, KeyInit, Mac};
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 
 use crate::{
     prelude::*,
     publishers::{TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate},
+    script::Script,
+    webhook_signature::{self, Algorithm},
 };
 
-type HmacSha256 = Hmac<Sha256>;
-
 /// What one person asked us to do with the events their tailnet reports.
 ///
 /// This carries a shared secret, because the address on its own does not do the
@@ -37,6 +35,11 @@ pub struct TailscaleWebhookConfig {
     #[serde(default)]
     pub secret: String,
 
+    /// Reshapes each event before it is filtered. Empty files events as
+    /// Tailscale sends them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default)]
     pub filter: crate::filter::Filter,
 
@@ -68,7 +71,7 @@ impl TailscaleWebhook {
         let mut timestamp = None;
         let mut signature = None;
 
-        for (key, value) in header.split(',').filter_map(|s| s.split_once('=')) {
+        for (key, value) in webhook_signature::fields(header) {
             match key {
                 "t" => timestamp = Some(value),
                 "v1" => signature = Some(value),
@@ -78,11 +81,7 @@ impl TailscaleWebhook {
 
         match (timestamp, signature) {
             (Some(timestamp), Some(signature)) => {
-                let timestamp = timestamp
-                    .parse()
-                    .ok()
-                    .and_then(|ts| DateTime::from_timestamp(ts, 0))
-                    .ok_or_else(|| {
+                let timestamp = webhook_signature::parse_timestamp(timestamp).ok_or_else(|| {
                         human_errors::user(
                             "The timestamp in the Tailscale-Webhook-Signature header is invalid.",
                             &[
@@ -120,16 +119,20 @@ impl TailscaleWebhook {
     /// The `now` parameter is the point in time against which the signature
     /// timestamp is validated. This should be the time at which the request was
     /// originally received (rather than the current time) so that retries of a
-    /// previously received request continue to validate successfully.
+    /// previously received request continue to validate successfully, or `None`
+    /// for a kept delivery sent again on purpose, whose age is not held against
+    /// it.
     fn verify_signature(
         secret: &str,
         body: &str,
         signature_header: &str,
-        now: DateTime<Utc>,
+        now: Option<DateTime<Utc>>,
     ) -> Result<(), human_errors::Error> {
         let (timestamp, expected_signature) = Self::parse_signature(signature_header)?;
 
-        if (timestamp - now).abs() > chrono::Duration::minutes(5) {
+        if now.is_some_and(|now| {
+            !webhook_signature::is_fresh(timestamp, now, webhook_signature::DEFAULT_TOLERANCE)
+        }) {
             return Err(human_errors::user(
                 format!(
                     "The Tailscale webhook signature timestamp is too old or too far in the future (got {timestamp})"
@@ -145,34 +148,21 @@ impl TailscaleWebhook {
         // cannot be re-dated to slip past the window above.
         let string_to_sign = format!("{}.{}", timestamp.timestamp(), body);
 
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).wrap_user_err(
-            "Failed to create HMAC instance with the provided secret.",
-            &["Ensure that you have set a valid webhook secret on this workflow."],
-        )?;
-
-        mac.update(string_to_sign.as_bytes());
-
-        // `verify_slice` compares in constant time, so a wrong signature cannot
-        // be walked one byte at a time by timing the rejections.
-        mac.verify_slice(&expected_signature).wrap_user_err(
-            "Webhook signature verification failed (signatures did not match).".to_string(),
-            &[
-                "Ensure that the webhook secret on this workflow matches the one shown against the endpoint at https://login.tailscale.com/admin/settings/webhooks",
-            ],
-        )?;
+        if !Algorithm::Sha256.verify(
+            secret.as_bytes(),
+            string_to_sign.as_bytes(),
+            &expected_signature,
+        ) {
+            return Err(human_errors::user(
+                "Webhook signature verification failed (signatures did not match).",
+                &[
+                    "Ensure that the webhook secret on this workflow matches the one shown against the endpoint at https://login.tailscale.com/admin/settings/webhooks",
+                ],
+            ));
+        }
 
         Ok(())
     }
-
-    /// HTTP header names are case-insensitive, and what reaches us depends on
-    /// whatever proxy handled the request, so the lookup cannot assume a casing.
-    fn header<'a>(event: &'a WebhookEvent, name: &str) -> Option<&'a str> {
-        event
-            .headers
-            .iter()
-            .find(|(key, _)| key.eq_ignore_ascii_case(name))
-            .map(|(_, value)| value.as_str())
-    }
 }
 
 /// The setup notes shown while somebody is configuring one of these.
@@ -241,6 +231,25 @@ type in ["nodeNeedsApproval", "userNeedsApproval", "nodeKeyExpired"]
 Leave it empty to file every event this webhook is subscribed to. Tailscale
 sends a `test` event when you create the endpoint, which is a quick way to
 confirm the address is right before you narrow the filter.
+
+## Reshaping events with a script
+
+A **Script** runs against each event in a delivery before the filter does, with
+the event as `item`: its `type`, `tailnet`, `message`, `timestamp` and the
+`data` Tailscale attaches, which differs by type. It can bring something from
+that data into the task's title, which a filter cannot:
+
+```
+if item.type == "nodeNeedsApproval" {
+    item.message = `${item.message}: ${item.data.deviceName}`;
+}
+item
+```
+
+End with `item` to file the event, with a list to file each element, or with
+nothing to skip it; whatever it returns still needs the fields Tailscale sends.
+Scripts are written in [Rhai](https://rhai.rs/book/); the generic webhook's
+notes describe what they can and cannot do.
 "#;
 
 crate::register_job!(TailscaleWebhook);
@@ -306,6 +315,11 @@ impl crate::workflows::ConfigurableWorkflow for TailscaleWebhook {
                 .with_help(
                     "Only file the events matching this, such as type == \"nodeNeedsApproval\". Leave it empty to file every event this webhook is subscribed to.",
                 ),
+                crate::script_field!(
+                    TailscaleWebhookConfig,
+                    "event",
+                    "if item.type == \"test\" { return; }\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -350,45 +364,82 @@ impl Job for TailscaleWebhook {
         // the delivery retrying forever and hiding real failures behind it. The
         // log line is the record that it happened.
 
-        // No secret configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty secret as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed. It also
-        // means the check cannot be neutralised by clearing the box, and it is
-        // what the GitHub and Terraform Cloud webhooks do with their own.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Tailscale webhook for a workflow with no secret configured; rejecting request."
-            );
-            return Ok(());
-        }
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No secret configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty secret as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed. It also
+            // means the check cannot be neutralised by clearing the box, and it is
+            // what the GitHub and Terraform Cloud webhooks do with their own.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Tailscale webhook for a workflow with no secret configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no secret to check the signature against.",
+                );
+                return Ok(());
+            }
 
-        let Some(signature) = Self::header(event, "tailscale-webhook-signature") else {
-            warn!(
-                "Received a Tailscale webhook without a Tailscale-Webhook-Signature header; rejecting request."
-            );
-            return Ok(());
-        };
+            let Some(signature) = event.header("tailscale-webhook-signature") else {
+                warn!(
+                    "Received a Tailscale webhook without a Tailscale-Webhook-Signature header; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The delivery did not carry a Tailscale-Webhook-Signature header.",
+                );
+                return Ok(());
+            };
+
+            // Validate against the time the request was originally received (the
+            // message's scheduled time) rather than now, so that a retry of a
+            // delivery we already accepted still validates.
+            if let Err(err) = Self::verify_signature(
+                &config.secret,
+                &event.body,
+                signature,
+                (!crate::preview::is_redelivery()).then(|| ctx.scheduled_at()),
+            ) {
+                warn!(
+                    "Failed to verify Tailscale webhook signature, rejecting request: {}",
+                    err
+                );
+                crate::preview::rejected(err.description());
+                return Ok(());
+            }
 
-        // Validate against the time the request was originally received (the
-        // message's scheduled time) rather than now, so that a retry of a
-        // delivery we already accepted still validates.
-        if let Err(err) =
-            Self::verify_signature(&config.secret, &event.body, signature, ctx.scheduled_at())
-        {
-            warn!(
-                "Failed to verify Tailscale webhook signature, rejecting request: {}",
-                err
-            );
-            return Ok(());
+            crate::preview::verified();
         }
 
         // Tailscale delivers webhook events as a JSON array, even when only a
         // single event is included. https://tailscale.com/kb/1213/webhooks
-        let events: Vec<TailscaleAlertEventPayload> = event.json()?;
+        // The workflow's script is handed each of them rather than the array.
+        let events: Vec<TailscaleAlertEventPayload> = if config.script.is_empty() {
+            event.json()?
+        } else {
+            let events: Vec<serde_json::Value> = event.json()?;
+            config
+                .script
+                .read(
+                    events
+                        .into_iter()
+                        .map(|event| {
+                            let describe = format!(
+                                "the '{}' event",
+                                event["type"].as_str().unwrap_or_default()
+                            );
+                            (event, describe)
+                        })
+                        .collect(),
+                )
+                .await?
+        };
 
         for event in events {
-            if !config.filter.matches(&event)? {
+            if !crate::preview::matches(&config.filter, &event, || event.message.clone())? {
                 info!(
                     "Tailscale event '{}' did not match filter; ignoring.",
                     event._type
@@ -471,6 +522,8 @@ mod tests {
     use std::collections::HashMap;
 
     use chrono::Utc;
+    use hmac::{Hmac, KeyInit, Mac};
+    use sha2::Sha256;
 
     use crate::{
         webhooks::{WebhookDelivery, WebhookEvent},
@@ -479,6 +532,8 @@ mod tests {
 
     use super::*;
 
+    type HmacSha256 = Hmac<Sha256>;
+
     /// A delivery as Tailscale sends it: an array, even for a single event.
     const POLICY_UPDATE: &str = r#"[{"timestamp":"2026-06-19T21:12:52.923385657Z","version":1,"type":"policyUpdate","tailnet":"example.org.github","message":"Tailnet policy file updated","data":{"url":"https://login.tailscale.com/admin/acls"}}]"#;
 
@@ -549,6 +604,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
@@ -724,7 +781,7 @@ mod tests {
             SECRET,
             POLICY_UPDATE,
             &sign(SECRET, now.timestamp(), POLICY_UPDATE),
-            now,
+            Some(now),
         )
         .expect("a signature Tailscale itself would have produced should verify");
     }
@@ -737,7 +794,8 @@ mod tests {
         let signature = sign("somebody-elses-secret", now.timestamp(), POLICY_UPDATE);
 
         assert!(
-            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, now).is_err()
+            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, Some(now))
+                .is_err()
         );
     }
 
@@ -750,7 +808,9 @@ mod tests {
         let signature = sign(SECRET, now.timestamp(), POLICY_UPDATE);
         let tampered = POLICY_UPDATE.replace("policyUpdate", "nodeNeedsApproval");
 
-        assert!(TailscaleWebhook::verify_signature(SECRET, &tampered, &signature, now).is_err());
+        assert!(
+            TailscaleWebhook::verify_signature(SECRET, &tampered, &signature, Some(now)).is_err()
+        );
     }
 
     #[test]
@@ -760,11 +820,12 @@ mod tests {
         let now = Utc::now();
 
         assert!(
-            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, "not-a-signature", now)
+            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, "not-a-signature", Some(now))
                 .is_err()
         );
         assert!(
-            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, "t=1663781880", now).is_err(),
+            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, "t=1663781880", Some(now))
+                .is_err(),
             "a header with a timestamp but no digest proves nothing",
         );
         assert!(
@@ -772,7 +833,7 @@ mod tests {
                 SECRET,
                 POLICY_UPDATE,
                 "v1=0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
-                now,
+                Some(now),
             )
             .is_err(),
             "a digest with no timestamp cannot be checked for freshness",
@@ -789,11 +850,11 @@ mod tests {
         let signature = sign(SECRET, received_at.timestamp(), POLICY_UPDATE);
 
         assert!(
-            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, Utc::now())
+            TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, Some(Utc::now()))
                 .is_err(),
             "the freshness window should reject a six-hour-old signature against the current time",
         );
-        TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, received_at)
+        TailscaleWebhook::verify_signature(SECRET, POLICY_UPDATE, &signature, Some(received_at))
             .expect("the same signature should verify against the time it was received");
     }
 
@@ -909,4 +970,78 @@ mod tests {
 
         assert_eq!(filed(&services).await.len(), 1);
     }
+
+    #[tokio::test]
+    async fn a_held_delivery_released_early_is_judged_by_when_it_arrived() {
+        // Released deliveries go back on the queue, which dates them afresh. A
+        // signature checked against that date rather than the arrival would be
+        // turned away for having waited out the snooze it was held for.
+        let services = crate::services::ServicesContainer::new_mock()
+            .await
+            .unwrap();
+        let workflow = store(&services, config()).await;
+        let workflows = WorkflowStore::new(&services);
+        workflows
+            .snooze(
+                workflow,
+                Some(automate_api::Snooze {
+                    until: Some(Utc::now() + chrono::Duration::hours(8)),
+                    deliveries: automate_api::SnoozedDeliveries::Hold,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        let arrived = Utc::now() - chrono::Duration::hours(1);
+        let signature = sign(SECRET, arrived.timestamp(), POLICY_UPDATE);
+        let held = WebhookDelivery {
+            arrived_at: Some(arrived),
+            ..delivery_with(
+                workflow,
+                POLICY_UPDATE,
+                &[("Tailscale-Webhook-Signature", &signature)],
+            )
+        };
+        TailscaleWebhook
+            .handle(
+                JobContext::new(services.clone(), arrived, None, None),
+                &held,
+            )
+            .await
+            .unwrap();
+        assert!(filed(&services).await.is_empty());
+
+        workflows.snooze(workflow, None).await.unwrap();
+        assert_eq!(
+            WebhookDelivery::release(&services, workflow, "tailscale")
+                .await
+                .unwrap(),
+            1
+        );
+
+        let released = services
+            .queue()
+            .dequeue::<_, serde_json::Value>(
+                TailscaleWebhook::partition(),
+                chrono::Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+        let delivery: WebhookDelivery = serde_json::from_value(released.payload.clone()).unwrap();
+        TailscaleWebhook
+            .handle(
+                JobContext::new(
+                    services.clone(),
+                    crate::job::arrived_at(&released),
+                    None,
+                    None,
+                ),
+                &delivery,
+            )
+            .await
+            .expect("a delivery that was fresh when it arrived should be accepted once released");
+
+        assert_eq!(filed(&services).await.len(), 1);
+    }
 }
@@ -1,13 +1,11 @@
-use hmac::{Hmac, KeyInit, Mac};
 use serde::{Deserialize, Serialize};
-use sha2::Sha512;
 
 use crate::prelude::*;
 use crate::publishers::TodoistTarget;
+use crate::script::Script;
+use crate::webhook_signature::Algorithm;
 use crate::webhooks::WebhookDelivery;
 
-type HmacSha512 = Hmac<Sha512>;
-
 /// What one person asked us to do with their Terraform Cloud notifications.
 ///
 /// This keeps its signing token. The per-workflow address answers "did somebody
@@ -30,6 +28,11 @@ pub struct TerraformWebhookConfig {
     #[serde(default)]
     pub secret: String,
 
+    /// Reshapes each notification before it is filed. Empty files
+    /// notifications as Terraform Cloud sends them.
+    #[serde(default)]
+    pub script: Script,
+
     #[serde(default = "default_todoist_config")]
     pub todoist: TodoistTarget,
 }
@@ -105,6 +108,25 @@ There is no filter on this type: every notification Terraform is configured to
 send becomes a task, so choose the triggers on the Terraform side rather than
 here. The Todoist fields decide which account, project and section they land
 in.
+
+## Reshaping notifications with a script
+
+A **Script** runs against each notification before it is filed, with the body
+Terraform sent as `item`: its `workspace_name`, `run_message`, `run_url` and
+the `notifications` it carries, each with a `trigger` and `message`. It is
+where to pass over what the notification configuration cannot, such as runs in
+a workspace you only want to hear about when they fail:
+
+```
+let quiet = item.workspace_name?.starts_with("sandbox-") ?? false;
+if quiet && item.notifications.all(|n| n.trigger == "run:completed") { return; }
+item
+```
+
+End with `item` to file the notification, with a list to file each element, or
+with nothing to skip it; whatever it returns still needs the fields Terraform
+sends. Scripts are written in [Rhai](https://rhai.rs/book/); the generic
+webhook's notes describe what they can and cannot do.
 "#;
 
 impl TerraformWebhook {
@@ -126,34 +148,17 @@ impl TerraformWebhook {
             "Ensure that you are only sending Terraform Cloud notifications to this endpoint.",
         ])?;
 
-        let mut mac = HmacSha512::new_from_slice(secret.as_bytes()).wrap_user_err(
-            "Failed to create HMAC instance with the provided token.",
-            &["Ensure that you have set a valid HMAC token on this workflow."],
-        )?;
-
-        mac.update(body.as_bytes());
-
-        // `verify_slice` compares in constant time, so a wrong signature cannot
-        // be walked one byte at a time by timing the rejections.
-        mac.verify_slice(&expected_signature).wrap_user_err(
-            "Webhook signature verification failed (signatures did not match).".to_string(),
-            &[
-                "Ensure that the HMAC token on this workflow matches the one set on the notification configuration in Terraform Cloud.",
-            ],
-        )?;
+        if !Algorithm::Sha512.verify(secret.as_bytes(), body.as_bytes(), &expected_signature) {
+            return Err(human_errors::user(
+                "Webhook signature verification failed (signatures did not match).",
+                &[
+                    "Ensure that the HMAC token on this workflow matches the one set on the notification configuration in Terraform Cloud.",
+                ],
+            ));
+        }
 
         Ok(())
     }
-
-    /// HTTP header names are case-insensitive, and what reaches us depends on
-    /// whatever proxy handled the request, so the lookup cannot assume a casing.
-    fn header<'a>(event: &'a WebhookEvent, name: &str) -> Option<&'a str> {
-        event
-            .headers
-            .iter()
-            .find(|(key, _)| key.eq_ignore_ascii_case(name))
-            .map(|(_, value)| value.as_str())
-    }
 }
 
 crate::register_job!(TerraformWebhook);
@@ -208,6 +213,11 @@ impl crate::workflows::ConfigurableWorkflow for TerraformWebhook {
                 .with_help(
                     "The HMAC token you set on the notification configuration in Terraform Cloud. It signs the body of each notification, which is what proves Terraform sent it and that nothing altered it on the way. Notifications are ignored while this is empty.",
                 ),
+                crate::script_field!(
+                    TerraformWebhookConfig,
+                    "notification",
+                    "if item.workspace_name == \"sandbox\" { return; }\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -220,68 +230,15 @@ impl crate::workflows::ConfigurableWorkflow for TerraformWebhook {
     }
 }
 
-impl Job for TerraformWebhook {
-    type JobType = WebhookDelivery;
-
-    fn partition() -> &'static str {
-        "webhooks/terraform"
-    }
-
-    #[instrument("webhooks.terraform.handle", skip(self, ctx, job), fields(job = %job))]
-    async fn handle(
-        &self,
-        ctx: JobContext<impl Services + Send + Sync + 'static>,
-        job: &Self::JobType,
+impl TerraformWebhook {
+    /// Files the task for one notification.
+    async fn file(
+        ctx: &JobContext<impl Services + Send + Sync + 'static>,
+        config: &TerraformWebhookConfig,
+        payload: NotificationPayload,
     ) -> Result<(), human_errors::Error> {
         let services = ctx.services();
 
-        // Read now rather than carried in the payload, so that an edit made
-        // between the delivery arriving and this running is the one that applies.
-        let Some(config) = job.config::<TerraformWebhookConfig>(services).await? else {
-            return Ok(());
-        };
-
-        let event = &job.event;
-
-        // Everything below this point happens *before* the payload is parsed, so
-        // that a delivery we cannot attribute to Terraform is never interpreted,
-        // let alone acted on.
-        //
-        // A rejection returns `Ok(())` rather than an error: nothing about a bad
-        // signature improves by trying again, so raising here would only leave
-        // the delivery retrying forever and hiding real failures behind it. The
-        // log line is the record that it happened.
-
-        // No token configured means we refuse, rather than accept anything. The
-        // alternative — treating an empty token as "skip the check" — would make
-        // a workflow silently unauthenticated exactly when somebody forgot to
-        // finish setting it up, and a forgotten field should fail closed. It
-        // also means the token cannot be neutralised by clearing it, and it is
-        // what the GitHub webhook does with its own secret.
-        if config.secret.is_empty() {
-            warn!(
-                "Received a Terraform Cloud notification for a workflow with no HMAC token configured; rejecting request."
-            );
-            return Ok(());
-        }
-
-        let Some(signature) = Self::header(event, "x-tfe-notification-signature") else {
-            warn!(
-                "Received a Terraform Cloud notification without an X-TFE-Notification-Signature header; rejecting request."
-            );
-            return Ok(());
-        };
-
-        if let Err(err) = Self::verify_signature(&config.secret, &event.body, signature) {
-            warn!(
-                "Failed to verify Terraform Cloud notification signature, rejecting request: {}",
-                err
-            );
-            return Ok(());
-        }
-
-        let payload: NotificationPayload = event.json()?;
-
         match &payload {
             NotificationPayload::Standard {
                 organization_name,
@@ -351,6 +308,92 @@ impl Job for TerraformWebhook {
     }
 }
 
+impl Job for TerraformWebhook {
+    type JobType = WebhookDelivery;
+
+    fn partition() -> &'static str {
+        "webhooks/terraform"
+    }
+
+    #[instrument("webhooks.terraform.handle", skip(self, ctx, job), fields(job = %job))]
+    async fn handle(
+        &self,
+        ctx: JobContext<impl Services + Send + Sync + 'static>,
+        job: &Self::JobType,
+    ) -> Result<(), human_errors::Error> {
+        let services = ctx.services();
+
+        // Read now rather than carried in the payload, so that an edit made
+        // between the delivery arriving and this running is the one that applies.
+        let Some(config) = job.config::<TerraformWebhookConfig>(services).await? else {
+            return Ok(());
+        };
+
+        let event = &job.event;
+
+        // Everything below this point happens *before* the payload is parsed, so
+        // that a delivery we cannot attribute to Terraform is never interpreted,
+        // let alone acted on.
+        //
+        // A rejection returns `Ok(())` rather than an error: nothing about a bad
+        // signature improves by trying again, so raising here would only leave
+        // the delivery retrying forever and hiding real failures behind it. The
+        // log line is the record that it happened.
+
+        // A preview of a kept delivery cannot see the credentials that were
+        // redacted from it; see `crate::preview::is_replay`. One sent again for
+        // real has them back, and is checked like any other.
+        if !crate::preview::is_replay() {
+            // No token configured means we refuse, rather than accept anything. The
+            // alternative — treating an empty token as "skip the check" — would make
+            // a workflow silently unauthenticated exactly when somebody forgot to
+            // finish setting it up, and a forgotten field should fail closed. It
+            // also means the token cannot be neutralised by clearing it, and it is
+            // what the GitHub webhook does with its own secret.
+            if config.secret.is_empty() {
+                warn!(
+                    "Received a Terraform Cloud notification for a workflow with no HMAC token configured; rejecting request."
+                );
+                crate::preview::rejected(
+                    "This workflow has no HMAC token to check the signature against.",
+                );
+                return Ok(());
+            }
+
+            let Some(signature) = event.header("x-tfe-notification-signature") else {
+                warn!(
+                    "Received a Terraform Cloud notification without an X-TFE-Notification-Signature header; rejecting request."
+                );
+                crate::preview::rejected(
+                    "The delivery did not carry a X-TFE-Notification-Signature header.",
+                );
+                return Ok(());
+            };
+
+            if let Err(err) = Self::verify_signature(&config.secret, &event.body, signature) {
+                warn!(
+                    "Failed to verify Terraform Cloud notification signature, rejecting request: {}",
+                    err
+                );
+                crate::preview::rejected(err.description());
+                return Ok(());
+            }
+
+            crate::preview::verified();
+        }
+
+        let payloads: Vec<NotificationPayload> = job
+            .event
+            .scripted(&config.script, "the notification")
+            .await?;
+        for payload in payloads {
+            Self::file(&ctx, &config, payload).await?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct NotificationVersion<const V: u8>;
 
@@ -481,10 +524,15 @@ pub enum VerificationTrigger {
 mod tests {
     use std::collections::HashMap;
 
+    use hmac::{Hmac, KeyInit, Mac};
+    use sha2::Sha512;
+
     use super::*;
     use crate::webhooks::WebhookEvent;
     use crate::workflow_store::{WorkflowDraft, WorkflowStore};
 
+    type HmacSha512 = Hmac<Sha512>;
+
     const RUN_NOTIFICATION: &str = r#"
     {
         "payload_version": 1,
@@ -598,6 +646,8 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect::<HashMap<_, _>>(),
             },
+            replay_of: None,
+            arrived_at: None,
         }
     }
 
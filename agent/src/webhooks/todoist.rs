@@ -43,8 +43,10 @@ use crate::prelude::*;
 use crate::publishers::{
     TodoistCreateTask, TodoistCreateTaskPayload, TodoistDueDate, TodoistTarget,
 };
+use crate::script::Script;
 use crate::services::AppServices;
-use crate::webhook_payload::{JsonFilter, render};
+use crate::variables::VariableStore;
+use crate::webhook_payload::{JsonFilter, PayloadFilter, Template, render};
 use crate::webhooks::{WebhookDelivery, WebhookSource};
 
 type HmacSha256 = Hmac<Sha256>;
@@ -68,16 +70,21 @@ pub struct TodoistWebhookConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub connection: Option<ConnectionId>,
 
+    /// Reshapes each event before it is filtered. Empty files events as
+    /// Todoist sends them.
+    #[serde(default)]
+    pub script: Script,
+
     /// Which events are worth acting on. Matched against the delivery by path.
     #[serde(default)]
-    pub filter: Filter,
+    pub filter: PayloadFilter,
 
     /// The task's title, rendered against the delivery.
-    pub title: String,
+    pub title: Template,
 
     /// The task's body, rendered the same way.
     #[serde(default)]
-    pub description: Option<String>,
+    pub description: Option<Template>,
 
     /// Where the resulting task is filed. Not necessarily the account the event
     /// came from — filing a follow-up into a shared account is a reasonable
@@ -338,6 +345,11 @@ impl crate::workflows::ConfigurableWorkflow for TodoistWebhook {
                     },
                 )
                 .with_help("Optional. Written the same way as the title."),
+                crate::script_field!(
+                    TodoistWebhookConfig,
+                    "event",
+                    "if item.event_data.labels.contains(\"someday\") { return; }\nitem"
+                ),
             ]
             .into_iter()
             .chain(crate::todoist_target_fields!(
@@ -382,40 +394,64 @@ impl Job for TodoistWebhook {
             return Ok(());
         };
 
-        if !config.filter.matches(&JsonFilter(&payload))? {
-            debug!(
-                workflow.id = %job.workflow,
-                "A Todoist event did not match this workflow's filter, so nothing was filed.",
-            );
-            return Ok(());
-        }
+        let items: Vec<_> = config
+            .script
+            .run(vec![(payload, "the event".to_string())])
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let scripted = !config.script.is_empty();
+
+        let variables = VariableStore::for_services(services).load().await?;
+
+        for (index, item) in items.into_iter().enumerate() {
+            if !crate::preview::matches(&config.filter, &JsonFilter(&item), || {
+                if scripted {
+                    format!("Item {} from the script", index + 1)
+                } else {
+                    item.get("event_name")
+                        .and_then(|name| name.as_str())
+                        .unwrap_or("A Todoist event")
+                        .to_string()
+                }
+            })? {
+                debug!(
+                    workflow.id = %job.workflow,
+                    "A Todoist event did not match this workflow's filter, so nothing was filed.",
+                );
+                continue;
+            }
 
-        let title = render(&config.title, &payload)?;
+            let title = render(&config.title, &item, &variables)?;
 
-        let description = match &config.description {
-            Some(template) => Some(render(template, &payload)?),
-            None => None,
-        };
+            let description = match &config.description {
+                Some(template) => Some(render(template, &item, &variables)?),
+                None => None,
+            };
 
-        // Todoist holds its delivery id constant across its own retries, which
-        // makes it the natural idempotency key for the task this fans out to.
-        let idempotency_key = job
-            .event
-            .header(DELIVERY_HEADER)
-            .map(|value| Cow::Owned(format!("{value}/{}", job.workflow)));
-
-        TodoistCreateTask::dispatch(
-            TodoistCreateTaskPayload {
-                title,
-                description,
-                due: TodoistDueDate::Today,
-                config: config.todoist.clone(),
-                ..Default::default()
-            },
-            idempotency_key,
-            services,
-        )
-        .await?;
+            // Todoist holds its delivery id constant across its own retries,
+            // which makes it the natural idempotency key for the task this fans
+            // out to. The first item keeps it as it is, and the rest are told
+            // apart from it.
+            let idempotency_key = job.event.header(DELIVERY_HEADER).map(|value| match index {
+                0 => Cow::Owned(format!("{value}/{}", job.workflow)),
+                _ => Cow::Owned(format!("{value}/{}/{index}", job.workflow)),
+            });
+
+            TodoistCreateTask::dispatch(
+                TodoistCreateTaskPayload {
+                    title,
+                    description,
+                    due: TodoistDueDate::Today,
+                    config: config.todoist.clone(),
+                    ..Default::default()
+                },
+                idempotency_key,
+                services,
+            )
+            .await?;
+        }
 
         Ok(())
     }
@@ -455,6 +491,14 @@ The filter is matched against the event by dotted path. Every delivery carries:
 event_name == "item:completed" && "errand" in event_data.labels
 ```
 
+Lists can be stepped into as they can in a generic webhook's filter, with
+`[0]`, `[*]` or `[?field == "value"]`:
+
+```
+event_name == "note:added" && event_data.file_attachment.file_type startswith "image/"
+event_name == "item:updated" && event_data.labels[0] == "next"
+```
+
 `event_data` differs from one event to the next, so send yourself one — complete
 a task, add a comment — and look at what arrived before writing anything
 elaborate.
@@ -478,6 +522,40 @@ Follow up: ${{ event_data.content }}
 A path that is not present renders as nothing rather than failing the delivery,
 since events differ in which fields they carry. Rendered output is length-capped,
 so a template aimed at a large field cannot produce an unbounded task.
+
+A value can be passed through functions, each after a `|`, to supply a fallback
+or tidy it up:
+
+```
+Follow up: ${{ event_data.content | truncate(60) }} (${{ event_data.due.date | date("%a %d %b") | default("no date") }})
+```
+
+The functions are `default`, `upper`, `lower`, `trim`, `truncate`, `date`,
+`join`, `markdown_escape`, `length`, `eq`, `ne`, `contains` and `if`. A
+template using one that does not exist is refused when you save it.
+
+`${{ vars.name }}` and `${{ secrets.name }}` insert a variable or secret your
+account keeps, set under `/api/v1/variables`, rather than a value from the
+event.
+
+## Reshaping events with a script
+
+When the filter and templates cannot say it, write a **Script**. It runs
+before the filter, with the event as `item`, and whatever it ends with is
+filtered and filed in its place — which is how a completed task's labels can
+choose the words its follow-up starts with, for a title of
+`${{ verb }} ${{ event_data.content }}`:
+
+```
+let verbs = #{ errand: "Pick up", phone: "Ring back" };
+item.verb = verbs[item.event_data.labels.get(0) ?? ""] ?? "Follow up:";
+item
+```
+
+End with `item` to file the event, with a list to file each element, or with
+nothing to skip it. The filter and templates then address what the script
+returned. Scripts are written in [Rhai](https://rhai.rs/book/); the generic
+webhook's notes describe what they can and cannot do.
 "#;
 
 #[cfg(test)]
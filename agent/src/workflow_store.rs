@@ -23,12 +23,16 @@
 
 use chrono::{DateTime, Utc};
 
-use automate_api::{Workflow, WorkflowId, WorkflowTrigger};
+use automate_api::{
+    Snooze, SnoozedDeliveries, WebhookLimits, Workflow, WorkflowId, WorkflowRevision,
+    WorkflowTrigger,
+};
 
 use human_errors::Error;
 
 use crate::db::KeyValueStore;
 use crate::prelude::*;
+use crate::revisions::RevisionStore;
 use crate::workflows;
 
 /// How many times to retry a randomly generated identifier before giving up.
@@ -75,12 +79,68 @@ pub struct WorkflowRecord {
     /// record will not open there.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub webhook: Option<crate::crypto::Sealed>,
+
+    /// What that URL accepts, and from whom.
+    ///
+    /// Set on its own rather than through a [`WorkflowDraft`], like the token
+    /// it qualifies: both are about the address rather than the work, and an
+    /// edit to the workflow's settings keeps them as they were.
+    #[serde(default, skip_serializing_if = "WebhookLimits::is_default")]
+    pub limits: WebhookLimits,
+
+    /// A pause that ends by itself, on top of [`WorkflowRecord::enabled`].
+    ///
+    /// Set on its own, like the limits, so that editing a workflow during a
+    /// maintenance window does not wake it up early. Left in place once it has
+    /// ended until the next run notices, which clears it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snooze: Option<Snooze>,
+}
+
+/// What a snooze makes of a run that is due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeVerdict {
+    /// Nothing is snoozed, or the snooze has ended: run as usual.
+    Run,
+
+    /// Skip this run. A snooze measured in runs has counted it.
+    Skip,
+
+    /// Keep this delivery until the snooze ends, then handle it.
+    Hold { until: DateTime<Utc> },
 }
 
 fn default_enabled() -> bool {
     true
 }
 
+/// What `snooze` makes of a run due at `now`, taking the run from a snooze
+/// measured in runs and clearing one that has ended.
+fn snooze_verdict(snooze: &mut Option<Snooze>, now: DateTime<Utc>) -> SnoozeVerdict {
+    let Some(current) = snooze else {
+        return SnoozeVerdict::Run;
+    };
+
+    if !current.is_active(now) {
+        *snooze = None;
+        return SnoozeVerdict::Run;
+    }
+
+    if let (SnoozedDeliveries::Hold, Some(until)) = (current.deliveries, current.until) {
+        return SnoozeVerdict::Hold { until };
+    }
+
+    // The last run it was asked to skip ends it, so that the next one does not
+    // have to come along to find it over.
+    match current.runs {
+        Some(runs) if runs > 1 => current.runs = Some(runs - 1),
+        Some(_) => *snooze = None,
+        None => {}
+    }
+
+    SnoozeVerdict::Skip
+}
+
 /// What a caller supplies to create or replace a workflow.
 ///
 /// Deliberately not [`WorkflowRecord`]: a caller has no business setting the
@@ -107,6 +167,13 @@ pub struct WorkflowStore<S> {
     /// rather than quietly leaving a live URL pointing at nothing — see
     /// [`WorkflowStore::index`].
     system: Option<S>,
+
+    /// Who is making the changes, written against each revision they save.
+    ///
+    /// Absent for the callers that act on nobody's behalf, such as applying a
+    /// file from the command line, whose revisions are saved by nobody in
+    /// particular rather than refused.
+    actor: Option<String>,
 }
 
 impl<S: Services> WorkflowStore<S> {
@@ -114,6 +181,7 @@ impl<S: Services> WorkflowStore<S> {
         Self {
             services,
             system: None,
+            actor: None,
         }
     }
 
@@ -123,6 +191,12 @@ impl<S: Services> WorkflowStore<S> {
         self
     }
 
+    /// Names the account the changes made through this store are credited to.
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
     /// The token index, or an explanation of why this store cannot touch one.
     ///
     /// Refusing loudly matters more here than convenience: a delete that
@@ -189,10 +263,18 @@ impl<S: Services> WorkflowStore<S> {
 
                 Ok(Some(schedule))
             }
-            WorkflowTrigger::Webhook { .. } | WorkflowTrigger::RoutedWebhook { .. } => Ok(None),
+            WorkflowTrigger::Webhook { .. }
+            | WorkflowTrigger::RoutedWebhook { .. }
+            | WorkflowTrigger::Event { .. } => Ok(None),
         }
     }
 
+    /// Checks a draft against the rules it would be held to when saved, without
+    /// saving it.
+    pub fn validate(&self, draft: &WorkflowDraft) -> Result<(), Error> {
+        Self::vet(draft).map(|_| ())
+    }
+
     /// Stores a new workflow, choosing an identifier for it.
     pub async fn create(&self, draft: WorkflowDraft) -> Result<Workflow, Error> {
         let schedule = Self::vet(&draft)?;
@@ -230,6 +312,8 @@ impl<S: Services> WorkflowStore<S> {
                 updated_at: now,
                 last_run: None,
                 webhook: sealed,
+                limits: WebhookLimits::default(),
+                snooze: None,
             };
 
             if self
@@ -252,6 +336,7 @@ impl<S: Services> WorkflowStore<S> {
                         .await?;
                 }
 
+                self.remember(None, &record, None).await;
                 return self.present(record);
             }
         }
@@ -330,6 +415,11 @@ impl<S: Services> WorkflowStore<S> {
     /// rather than a fresh copy of it. Creation timestamps are preserved where
     /// the workflow already existed, so re-applying a file does not make
     /// everything look newly made.
+    ///
+    /// Files are applied far more often than workflows are edited by hand, and
+    /// usually say what they said last time. A revision is only kept when the
+    /// file changed something, so that the history stays a record of edits
+    /// rather than of imports (see [`crate::revisions`]).
     pub async fn upsert(&self, id: WorkflowId, draft: WorkflowDraft) -> Result<Workflow, Error> {
         let schedule = Self::vet(&draft)?;
         let partition = Self::partition_for(&draft.type_id)?;
@@ -377,6 +467,13 @@ impl<S: Services> WorkflowStore<S> {
             updated_at: now,
             last_run: existing.as_ref().and_then(|e| e.last_run),
             webhook,
+            // A file does not carry these, so applying one leaves them as they
+            // are, as it leaves the address they belong to.
+            limits: existing
+                .as_ref()
+                .map(|e| e.limits.clone())
+                .unwrap_or_default(),
+            snooze: existing.as_ref().and_then(|e| e.snooze.clone()),
         };
 
         self.services
@@ -396,6 +493,9 @@ impl<S: Services> WorkflowStore<S> {
                 .await?;
         }
 
+        if !self.is_unchanged(existing.as_ref(), &record).await {
+            self.remember(existing.as_ref(), &record, None).await;
+        }
         self.present(record)
     }
 
@@ -434,6 +534,43 @@ impl<S: Services> WorkflowStore<S> {
     /// while replacing everything the identifier referred to, which is a new
     /// workflow wearing an old name.
     pub async fn update(&self, id: WorkflowId, draft: WorkflowDraft) -> Result<Workflow, Error> {
+        self.replace(id, draft, None).await
+    }
+
+    /// Puts a workflow back as one of its revisions had it.
+    ///
+    /// Saved as a new revision rather than by rewinding the history, so that
+    /// restoring the wrong one is undone the same way, by restoring whichever
+    /// was current before it. The address and its limits are left alone, as an
+    /// edit leaves them, because they were never part of a revision.
+    pub async fn restore(&self, id: WorkflowId, number: u32) -> Result<Workflow, Error> {
+        let existing = self.get(id).await?;
+        let revision = RevisionStore::new(&self.services)
+            .get(id, number)
+            .await?
+            .ok_or_else(|| {
+                human_errors::user(
+                    format!("This workflow has no revision {number} to restore."),
+                    &["List the workflow's revisions to see which ones it has."],
+                )
+            })?;
+
+        let draft = WorkflowDraft {
+            type_id: existing.type_id,
+            config: revision.config,
+            schedule: revision.schedule,
+            enabled: revision.enabled,
+        };
+
+        self.replace(id, draft, Some(number)).await
+    }
+
+    async fn replace(
+        &self,
+        id: WorkflowId,
+        draft: WorkflowDraft,
+        restored_from: Option<u32>,
+    ) -> Result<Workflow, Error> {
         let existing = self.get(id).await?;
 
         if existing.type_id != draft.type_id {
@@ -455,7 +592,7 @@ impl<S: Services> WorkflowStore<S> {
             updated_at: Utc::now(),
             // Kept as it is: editing a workflow must not silently change the
             // address somebody has already configured a service to call.
-            ..existing
+            ..existing.clone()
         };
 
         self.services
@@ -467,9 +604,98 @@ impl<S: Services> WorkflowStore<S> {
             )
             .await?;
 
+        self.remember(Some(&existing), &record, restored_from).await;
         self.present(record)
     }
 
+    /// Whether a workflow that has just been saved says what its latest revision
+    /// does or, where none was kept, what it said before it was saved.
+    ///
+    /// Revisions that cannot be read are taken to differ, so that
+    /// [`WorkflowStore::remember`] has the chance to report why.
+    async fn is_unchanged(
+        &self,
+        previous: Option<&WorkflowRecord>,
+        record: &WorkflowRecord,
+    ) -> bool {
+        let revisions = RevisionStore::new(&self.services).list(record.id).await;
+
+        match revisions.as_deref() {
+            Ok([latest, ..]) => {
+                latest.config == record.config
+                    && latest.schedule == record.schedule
+                    && latest.enabled == record.enabled
+            }
+            Ok([]) => previous.is_some_and(|previous| {
+                previous.config == record.config
+                    && previous.schedule == record.schedule
+                    && previous.enabled == record.enabled
+            }),
+            Err(_) => false,
+        }
+    }
+
+    /// Keeps a revision of a workflow that has just been saved.
+    ///
+    /// A workflow saved before revisions were kept has no history, so the
+    /// version being replaced is kept first: it is the one somebody undoing
+    /// this save will want. Failing to keep either is logged rather than
+    /// returned, because the save itself has already happened and reporting it
+    /// as failed would have it made again.
+    async fn remember(
+        &self,
+        previous: Option<&WorkflowRecord>,
+        record: &WorkflowRecord,
+        restored_from: Option<u32>,
+    ) {
+        let store = RevisionStore::new(&self.services);
+
+        let result: Result<u32, Error> = async {
+            if let Some(previous) = previous
+                && store.list(record.id).await?.is_empty()
+            {
+                store
+                    .record(
+                        previous.id,
+                        WorkflowRevision {
+                            number: 0,
+                            config: previous.config.clone(),
+                            schedule: previous.schedule.clone(),
+                            enabled: previous.enabled,
+                            saved_at: previous.updated_at,
+                            saved_by: None,
+                            restored_from: None,
+                        },
+                    )
+                    .await?;
+            }
+
+            store
+                .record(
+                    record.id,
+                    WorkflowRevision {
+                        number: 0,
+                        config: record.config.clone(),
+                        schedule: record.schedule.clone(),
+                        enabled: record.enabled,
+                        saved_at: record.updated_at,
+                        saved_by: self.actor.clone(),
+                        restored_from,
+                    },
+                )
+                .await
+        }
+        .await;
+
+        if let Err(err) = result {
+            warn!(
+                workflow.id = %record.id,
+                error = %err,
+                "Could not keep a revision of a workflow that was saved: {err}",
+            );
+        }
+    }
+
     /// Records that a workflow just ran.
     ///
     /// Missing records are ignored rather than reported: a run that finished
@@ -520,10 +746,20 @@ impl<S: Services> WorkflowStore<S> {
             .remove(Self::partition_for(&existing.type_id)?, id.to_string())
             .await?;
 
-        // The runs go with it. Leaving them would have a workflow created later
-        // inherit a stranger's failure, since identifiers are drawn at random
-        // from a space small enough to be reused.
-        crate::runs::RunStore::new(&self.services).forget(id).await
+        // The runs, deliveries and revisions go with it. Leaving them would have a
+        // workflow created later inherit a stranger's failure, since
+        // identifiers are drawn at random from a space small enough to be
+        // reused.
+        crate::runs::RunStore::new(&self.services)
+            .forget(id)
+            .await?;
+        crate::deliveries::DeliveryStore::new(&self.services)
+            .forget(id)
+            .await?;
+        crate::webhooks::repeats::SeenDeliveries::new(&self.services)
+            .forget(id)
+            .await?;
+        RevisionStore::new(&self.services).forget(id).await
     }
 
     /// Replaces a workflow's webhook token, so a leaked URL stops working.
@@ -592,6 +828,152 @@ impl<S: Services> WorkflowStore<S> {
         Ok(token)
     }
 
+    /// Replaces what a workflow's webhook URL will accept.
+    pub async fn set_limits(
+        &self,
+        id: WorkflowId,
+        limits: WebhookLimits,
+    ) -> Result<Workflow, Error> {
+        let existing = self.get(id).await?;
+
+        if !Self::is_webhook(&existing.type_id)? {
+            return Err(human_errors::user(
+                format!("The workflow '{id}' is not one that is triggered by a webhook."),
+                &["Only webhook workflows have an address to limit."],
+            ));
+        }
+
+        crate::webhooks::limits::validate(&limits)?;
+
+        let record = WorkflowRecord {
+            limits,
+            updated_at: Utc::now(),
+            ..existing
+        };
+
+        self.services
+            .kv()
+            .set(
+                Self::partition_for(&record.type_id)?,
+                id.to_string(),
+                record.clone(),
+            )
+            .await?;
+
+        self.present(record)
+    }
+
+    /// Snoozes a workflow, or wakes it with `None`.
+    ///
+    /// Waking a workflow does not release the deliveries it held; that is the
+    /// queue's business rather than the record's, and is left to the caller
+    /// (see [`crate::webhooks::WebhookDelivery::release`]).
+    pub async fn snooze(&self, id: WorkflowId, snooze: Option<Snooze>) -> Result<Workflow, Error> {
+        let existing = self.get(id).await?;
+
+        if let Some(snooze) = &snooze {
+            let now = Utc::now();
+
+            if snooze.until.is_none() && snooze.runs.is_none() {
+                return Err(human_errors::user(
+                    "A snooze needs to say when it ends.",
+                    &[
+                        "Give a time to snooze until, or a number of runs to skip.",
+                        "To stop a workflow until further notice, pause it instead.",
+                    ],
+                ));
+            }
+
+            if snooze.until.is_some_and(|until| until <= now) {
+                return Err(human_errors::user(
+                    "A snooze has to end in the future.",
+                    &["Choose a time that has not already passed."],
+                ));
+            }
+
+            if snooze.runs == Some(0) {
+                return Err(human_errors::user(
+                    "A snooze has to skip at least one run.",
+                    &["Give a number of runs greater than zero."],
+                ));
+            }
+
+            if snooze.deliveries == SnoozedDeliveries::Hold {
+                let trigger = workflows::lookup(&existing.type_id)?.descriptor().trigger;
+                if !matches!(
+                    trigger,
+                    WorkflowTrigger::Webhook { .. } | WorkflowTrigger::RoutedWebhook { .. }
+                ) {
+                    return Err(human_errors::user(
+                        format!("The workflow '{id}' does not receive deliveries to hold."),
+                        &["Only webhook workflows can hold what arrives while they are snoozed."],
+                    ));
+                }
+
+                if snooze.until.is_none() || snooze.runs.is_some() {
+                    return Err(human_errors::user(
+                        "Only a snooze with an end time can hold deliveries.",
+                        &[
+                            "A snooze measured in runs counts the deliveries it skips, so give a time to snooze until instead.",
+                        ],
+                    ));
+                }
+            }
+        }
+
+        let record = WorkflowRecord {
+            snooze,
+            updated_at: Utc::now(),
+            ..existing
+        };
+
+        self.services
+            .kv()
+            .set(
+                Self::partition_for(&record.type_id)?,
+                id.to_string(),
+                record.clone(),
+            )
+            .await?;
+
+        self.present(record)
+    }
+
+    /// Decides what a workflow's snooze makes of a run that is due at `now`,
+    /// counting the run against a snooze measured in runs.
+    ///
+    /// A snooze that has ended is cleared here rather than by anything watching
+    /// the clock: the run that finds it over is the first moment it matters.
+    pub async fn check_snooze(
+        &self,
+        record: &WorkflowRecord,
+        now: DateTime<Utc>,
+    ) -> Result<SnoozeVerdict, Error> {
+        if record.snooze.is_none() {
+            return Ok(SnoozeVerdict::Run);
+        }
+
+        // Decided against the stored record rather than `record`, which may be
+        // older than an edit made while this run was waiting, and in the same
+        // step as the count is taken, so that two deliveries arriving together
+        // cannot both be skipped as the same run.
+        let verdict = self
+            .services
+            .kv()
+            .update(
+                Self::partition_for(&record.type_id)?,
+                record.id.to_string(),
+                move |existing: &mut WorkflowRecord| snooze_verdict(&mut existing.snooze, now),
+            )
+            .await?;
+
+        Ok(match verdict {
+            Some(verdict) => verdict,
+            // Deleted since it was read, which leaves nothing to count against.
+            None => snooze_verdict(&mut record.snooze.clone(), now),
+        })
+    }
+
     /// The token in a workflow's webhook URL, for showing its owner.
     pub fn webhook_token(
         &self,
@@ -622,11 +1004,14 @@ impl<S: Services> WorkflowStore<S> {
     fn present(&self, record: WorkflowRecord) -> Result<Workflow, Error> {
         let workflow = workflows::lookup(&record.type_id)?;
 
+        let now = Utc::now();
+        let snooze = record.snooze.filter(|snooze| snooze.is_active(now));
+
         let next_run = record
             .enabled
             .then_some(record.schedule.as_deref())
             .flatten()
-            .and_then(next_occurrence);
+            .and_then(|schedule| next_occurrence(schedule, snooze.as_ref()));
 
         // Shown as a path rather than a whole URL, because the agent does not
         // reliably know what address it is reached on from outside — a reverse
@@ -639,10 +1024,12 @@ impl<S: Services> WorkflowStore<S> {
         } else {
             None
         };
+        let limits = webhook_path.is_some().then(|| record.limits.clone());
 
         Ok(Workflow {
             id: record.id,
             webhook_path,
+            limits,
             name: workflow.describe(&record.config)?,
             resettable: !workflow.state(&record.config)?.is_empty(),
             type_id: record.type_id,
@@ -653,6 +1040,7 @@ impl<S: Services> WorkflowStore<S> {
             updated_at: record.updated_at,
             last_run: record.last_run,
             next_run,
+            snooze,
             // Attached by the callers that draw a list, which read every
             // workflow's health in one go rather than one at a time.
             health: None,
@@ -660,22 +1048,43 @@ impl<S: Services> WorkflowStore<S> {
     }
 }
 
-/// When a schedule next fires, or `None` if it never will again.
+/// When a schedule next runs, or `None` if it never will again.
+///
+/// A snoozed workflow's schedule keeps firing, and each run it skips is one
+/// nobody should be told to expect; so this is the first run after the snooze
+/// ends, whether that is its time passing or its runs being used up.
 ///
 /// A schedule is checked when it is saved, so one that will not parse here has
 /// been edited underneath us; that is worth a line in the log but not worth
 /// refusing to show the workflow.
-pub fn next_occurrence(schedule: &str) -> Option<DateTime<Utc>> {
-    match <croner::Cron as std::str::FromStr>::from_str(schedule) {
-        Ok(cron) => cron.find_next_occurrence(&Utc::now(), false).ok(),
+fn next_occurrence(schedule: &str, snooze: Option<&Snooze>) -> Option<DateTime<Utc>> {
+    let cron = match <croner::Cron as std::str::FromStr>::from_str(schedule) {
+        Ok(cron) => cron,
         Err(err) => {
             warn!(
                 workflow.schedule = schedule,
                 "Ignoring a stored schedule which no longer parses: {err}",
             );
-            None
+            return None;
         }
-    }
+    };
+
+    let next = cron.find_next_occurrence(&Utc::now(), false).ok()?;
+    let Some(snooze) = snooze else {
+        return Some(next);
+    };
+
+    // A run due at the very moment the snooze ends is not skipped, since the
+    // snooze is over by then.
+    let after_time = snooze
+        .until
+        .and_then(|until| cron.find_next_occurrence(&until, true).ok());
+
+    let after_runs = snooze.runs.and_then(|runs| {
+        (0..runs).try_fold(next, |at, _| cron.find_next_occurrence(&at, false).ok())
+    });
+
+    after_time.into_iter().chain(after_runs).min()
 }
 
 #[cfg(test)]
@@ -783,6 +1192,120 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn a_snoozed_workflow_expects_to_run_only_once_its_snooze_is_over() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(Some("@hourly"))).await.unwrap();
+        let until = Utc::now() + chrono::Duration::hours(3);
+
+        let snoozed = store
+            .snooze(
+                created.id,
+                Some(Snooze {
+                    until: Some(until),
+                    ..Snooze::default()
+                }),
+            )
+            .await
+            .unwrap();
+        assert!(snoozed.next_run.is_some_and(|next| next >= until));
+
+        let counted = store
+            .snooze(
+                created.id,
+                Some(Snooze {
+                    runs: Some(2),
+                    ..Snooze::default()
+                }),
+            )
+            .await
+            .unwrap();
+        assert!(
+            counted
+                .next_run
+                .is_some_and(|next| next > Utc::now() + chrono::Duration::hours(2)),
+            "the two runs it skips are not runs anybody should expect",
+        );
+    }
+
+    #[tokio::test]
+    async fn a_snooze_measured_in_runs_counts_down_and_then_lets_them_through() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(Some("@hourly"))).await.unwrap();
+        store
+            .snooze(
+                created.id,
+                Some(Snooze {
+                    runs: Some(2),
+                    ..Snooze::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        let mut verdicts = Vec::new();
+        for _ in 0..3 {
+            let record = store.get(created.id).await.unwrap();
+            verdicts.push(store.check_snooze(&record, Utc::now()).await.unwrap());
+        }
+
+        assert_eq!(
+            verdicts,
+            vec![SnoozeVerdict::Skip, SnoozeVerdict::Skip, SnoozeVerdict::Run],
+        );
+        assert!(store.get(created.id).await.unwrap().snooze.is_none());
+    }
+
+    #[tokio::test]
+    async fn runs_checked_together_are_each_counted_against_the_snooze() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(Some("@hourly"))).await.unwrap();
+        store
+            .snooze(
+                created.id,
+                Some(Snooze {
+                    runs: Some(2),
+                    ..Snooze::default()
+                }),
+            )
+            .await
+            .unwrap();
+
+        // Every check starts from the same record, as deliveries that arrive
+        // together do.
+        let record = store.get(created.id).await.unwrap();
+        let verdicts =
+            futures::future::join_all((0..4).map(|_| store.check_snooze(&record, Utc::now())))
+                .await;
+
+        let skipped = verdicts
+            .into_iter()
+            .filter(|verdict| verdict.as_ref().unwrap() == &SnoozeVerdict::Skip)
+            .count();
+        assert_eq!(skipped, 2, "each skipped run should be counted once");
+        assert!(store.get(created.id).await.unwrap().snooze.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_snooze_without_an_end_is_refused() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(Some("@daily"))).await.unwrap();
+        let err = store
+            .snooze(created.id, Some(Snooze::default()))
+            .await
+            .expect_err("a snooze with no end is a pause");
+
+        assert!(err.is(human_errors::Kind::User));
+    }
+
     #[tokio::test]
     async fn a_workflow_cannot_be_turned_into_a_different_type() {
         let services = crate::testing::mock_services().await.unwrap();
@@ -806,6 +1329,126 @@ mod tests {
         assert!(format!("{err}").contains("rss"), "{err}");
     }
 
+    #[tokio::test]
+    async fn a_workflow_without_an_address_has_no_limits_to_set() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(None)).await.unwrap();
+        assert!(created.limits.is_none());
+
+        store
+            .set_limits(
+                created.id,
+                WebhookLimits {
+                    per_minute: Some(10),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect_err("a scheduled workflow is not reached by URL, so nothing could be limited");
+    }
+
+    #[tokio::test]
+    async fn a_broken_edit_is_undone_by_restoring_the_revision_before_it() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services).with_actor("alice");
+
+        let created = store.create(draft(Some("@daily"))).await.unwrap();
+        store
+            .update(
+                created.id,
+                WorkflowDraft {
+                    config: serde_json::json!({
+                        "name": "Citation Needed, but wrong",
+                        "url": "https://example.com/broken/",
+                        "homepage": "https://example.com/",
+                    }),
+                    ..draft(Some("@hourly"))
+                },
+            )
+            .await
+            .unwrap();
+
+        let restored = store.restore(created.id, 1).await.unwrap();
+        assert_eq!(restored.config, valid_config());
+        assert_eq!(restored.schedule.as_deref(), Some("@daily"));
+
+        let revisions = RevisionStore::new(&services)
+            .list(created.id)
+            .await
+            .unwrap();
+        let numbers: Vec<u32> = revisions.iter().map(|r| r.number).collect();
+        assert_eq!(
+            numbers,
+            [3, 2, 1],
+            "restoring should be saved as a revision of its own, so that it can be undone too",
+        );
+        assert_eq!(revisions[0].restored_from, Some(1));
+        assert_eq!(revisions[0].saved_by.as_deref(), Some("alice"));
+
+        store
+            .restore(created.id, 9)
+            .await
+            .expect_err("a revision that was never saved cannot be restored");
+    }
+
+    #[tokio::test]
+    async fn a_workflow_saved_before_revisions_were_kept_keeps_its_old_version_on_its_first_edit() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+
+        let created = store.create(draft(Some("@daily"))).await.unwrap();
+        RevisionStore::new(&services)
+            .forget(created.id)
+            .await
+            .unwrap();
+
+        store
+            .update(created.id, draft(Some("@hourly")))
+            .await
+            .unwrap();
+
+        let revisions = RevisionStore::new(&services)
+            .list(created.id)
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(
+            revisions[1].schedule.as_deref(),
+            Some("@daily"),
+            "the version the edit replaced is the one somebody would want back",
+        );
+        assert_eq!(revisions[1].saved_by, None);
+    }
+
+    #[tokio::test]
+    async fn applying_the_same_file_again_keeps_no_new_revision() {
+        let services = crate::testing::mock_services().await.unwrap();
+        let store = WorkflowStore::new(&services);
+        let id = WorkflowId::from_entropy(7);
+
+        store.upsert(id, draft(Some("@daily"))).await.unwrap();
+        store.upsert(id, draft(Some("@daily"))).await.unwrap();
+
+        let revisions = RevisionStore::new(&services).list(id).await.unwrap();
+        assert_eq!(
+            revisions.len(),
+            1,
+            "an import that changed nothing is not a version anybody would want back",
+        );
+
+        store.upsert(id, draft(Some("@hourly"))).await.unwrap();
+
+        let revisions = RevisionStore::new(&services).list(id).await.unwrap();
+        assert_eq!(
+            revisions.len(),
+            2,
+            "an import that changed something is kept"
+        );
+        assert_eq!(revisions[0].schedule.as_deref(), Some("@hourly"));
+    }
+
     #[tokio::test]
     async fn each_created_workflow_gets_its_own_identifier() {
         let services = crate::testing::mock_services().await.unwrap();
@@ -892,6 +1535,8 @@ mod tests {
                     updated_at: Utc::now(),
                     last_run: None,
                     webhook: None,
+                    limits: Default::default(),
+                    snooze: None,
                 },
             )
             .await
@@ -38,7 +38,7 @@ use crate::workflow_store::{WorkflowDraft, WorkflowRecord, WorkflowStore};
 /// Named here so that import and export cannot disagree about which keys are
 /// the envelope and which are the contents.
 const ID_KEY: &str = "id";
-const SCHEDULE_KEY: &str = "cron";
+pub const SCHEDULE_KEY: &str = "cron";
 const ENABLED_KEY: &str = "enabled";
 
 /// What applying a file did.
@@ -120,17 +120,10 @@ pub async fn import<S: Services>(
     document: &str,
     prune: bool,
 ) -> Result<ImportSummary, Error> {
-    let parsed: toml::Table = toml::from_str(document).map_err(|err| {
-        human_errors::user(
-            format!("This is not a file we could read: {err}"),
-            &["Check that it is valid TOML, as produced by exporting your workflows."],
-        )
-    })?;
-
     let mut summary = ImportSummary::default();
     let mut seen: HashSet<WorkflowId> = HashSet::new();
 
-    let Some(workflows) = parsed.get("workflows").and_then(|value| value.as_table()) else {
+    let Some(entries) = read(document)? else {
         // An empty file is a valid description of nothing. With `prune` that is
         // a request to delete everything, which is a thing somebody could
         // genuinely mean but almost certainly does not, so it is refused.
@@ -147,6 +140,78 @@ pub async fn import<S: Services>(
         return Ok(summary);
     };
 
+    for (id, draft) in entries {
+        match id {
+            Some(id) => {
+                let existed = store.find(id).await?.is_some();
+                store.upsert(id, draft).await?;
+                seen.insert(id);
+
+                if existed {
+                    summary.updated += 1;
+                } else {
+                    summary.created += 1;
+                }
+            }
+            None => {
+                // A workflow written by hand need not have an identifier; one
+                // is chosen for it, and appears the next time the file is
+                // exported.
+                let created = store.create(draft).await?;
+                seen.insert(created.id);
+                summary.created += 1;
+            }
+        }
+    }
+
+    if prune {
+        for record in store.records().await? {
+            if !seen.contains(&record.id) {
+                store.delete(record.id).await?;
+                summary.deleted += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Checks a TOML document without applying it, returning how many workflows it
+/// describes.
+///
+/// Every entry is held to the same rules [`import`] would apply, so a file that
+/// passes here is refused by an import only if the database changes underneath
+/// it. This is what lets a file kept in version control be checked before it is
+/// merged rather than when it is deployed.
+pub fn validate<S: Services>(store: &WorkflowStore<S>, document: &str) -> Result<usize, Error> {
+    let entries = read(document)?.unwrap_or_default();
+
+    for (_, draft) in &entries {
+        store.validate(draft)?;
+    }
+
+    Ok(entries.len())
+}
+
+/// Reads every workflow a document describes, or `None` when it has no
+/// `[workflows]` section at all.
+///
+/// The whole file is read before anything is written, so that a malformed last
+/// entry is reported rather than found after the first ones were applied.
+pub fn read(document: &str) -> Result<Option<Vec<(Option<WorkflowId>, WorkflowDraft)>>, Error> {
+    let parsed: toml::Table = toml::from_str(document).map_err(|err| {
+        human_errors::user(
+            format!("This is not a file we could read: {err}"),
+            &["Check that it is valid TOML, as produced by exporting your workflows."],
+        )
+    })?;
+
+    let Some(workflows) = parsed.get("workflows").and_then(|value| value.as_table()) else {
+        return Ok(None);
+    };
+
+    let mut read = Vec::new();
+
     for (type_id, entries) in workflows {
         let Some(entries) = entries.as_array() else {
             return Err(human_errors::user(
@@ -163,42 +228,11 @@ pub async fn import<S: Services>(
                 ));
             };
 
-            let (id, draft) = read_entry(type_id, table)?;
-
-            match id {
-                Some(id) => {
-                    let existed = store.find(id).await?.is_some();
-                    store.upsert(id, draft).await?;
-                    seen.insert(id);
-
-                    if existed {
-                        summary.updated += 1;
-                    } else {
-                        summary.created += 1;
-                    }
-                }
-                None => {
-                    // A workflow written by hand need not have an identifier;
-                    // one is chosen for it, and appears the next time the file
-                    // is exported.
-                    let created = store.create(draft).await?;
-                    seen.insert(created.id);
-                    summary.created += 1;
-                }
-            }
-        }
-    }
-
-    if prune {
-        for record in store.records().await? {
-            if !seen.contains(&record.id) {
-                store.delete(record.id).await?;
-                summary.deleted += 1;
-            }
+            read.push(read_entry(type_id, table)?);
         }
     }
 
-    Ok(summary)
+    Ok(Some(read))
 }
 
 /// Splits one entry into the identifier it claims and the workflow it describes.
@@ -425,6 +459,27 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn validating_a_file_checks_every_workflow_without_saving_any() {
+        let store = new_store().await;
+        store.create(draft("Citation Needed")).await.unwrap();
+
+        let document = export(&store.records().await.unwrap()).unwrap();
+        let empty = new_store().await;
+
+        assert_eq!(validate(&empty, &document).unwrap(), 1);
+        assert!(
+            empty.records().await.unwrap().is_empty(),
+            "validating a file must not apply it"
+        );
+
+        let broken = format!("{document}\n[[workflows.rss]]\nname = \"Missing its feed\"\n");
+        assert!(
+            validate(&empty, &broken).is_err(),
+            "a file with a workflow the agent could not run should fail validation"
+        );
+    }
+
     #[tokio::test]
     async fn a_workflow_the_agent_could_not_run_is_refused() {
         let store = new_store().await;
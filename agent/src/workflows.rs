@@ -76,6 +76,20 @@ pub trait ConfigurableWorkflow: Job {
         let _ = config;
         Vec::new()
     }
+
+    /// The header a webhook sender names each delivery with, holding it
+    /// constant across its own retries, so that a retry can be recognised and
+    /// dropped before it is queued (see [`crate::webhooks::repeats`]).
+    ///
+    /// `None` by default: most senders name nothing, and a header that merely
+    /// looks like an id — one that changes on each attempt — would let every
+    /// retry through anyway. A routed webhook declares its header on its
+    /// [`crate::webhooks::WebhookSource`] instead, since it is the source that
+    /// receives the delivery.
+    fn delivery_header(config: &Self::ConfigType) -> Option<String> {
+        let _ = config;
+        None
+    }
 }
 
 /// The type-erased view of a workflow type, so that the registry can hold every
@@ -97,6 +111,10 @@ pub trait WorkflowType: Send + Sync {
     /// Everything the instance this configuration describes remembers between
     /// runs. Clearing these is what resetting a workflow means.
     fn state(&self, config: &serde_json::Value) -> Result<Vec<StateKey>, Error>;
+
+    /// The header naming a delivery to an instance with this configuration, if
+    /// its sender sends one. A configuration that does not read has none.
+    fn delivery_header(&self, config: &serde_json::Value) -> Option<String>;
 }
 
 impl<W> WorkflowType for W
@@ -130,6 +148,12 @@ where
             &self.deserialize(config)?,
         ))
     }
+
+    fn delivery_header(&self, config: &serde_json::Value) -> Option<String> {
+        self.deserialize(config)
+            .ok()
+            .and_then(|config| <W as ConfigurableWorkflow>::delivery_header(&config))
+    }
 }
 
 /// Shared by [`WorkflowType::validate`] and [`WorkflowType::describe`], so that
@@ -499,7 +523,8 @@ mod tests {
             FieldKind::Text { placeholder }
             | FieldKind::TextArea { placeholder }
             | FieldKind::Secret { placeholder, .. }
-            | FieldKind::Url { placeholder } => placeholder.as_deref(),
+            | FieldKind::Url { placeholder }
+            | FieldKind::Script { placeholder } => placeholder.as_deref(),
             _ => None,
         }
     }
@@ -530,6 +555,8 @@ mod tests {
                 serde_json::json!(automate_api::ConnectionId::from_entropy(0).to_string())
             }
             FieldKind::Cron => serde_json::json!("@daily"),
+            // The smallest script that does anything: hands the item back.
+            FieldKind::Script { .. } => serde_json::json!("item"),
             // A filter has to parse, so the only value guaranteed to is the one
             // the type produces for itself.
             FieldKind::Filter { .. } => {
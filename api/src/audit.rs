@@ -24,6 +24,9 @@ pub enum AuditCategory {
     /// A connection to an external service was established or removed.
     Connection,
 
+    /// A variable or secret shared by an account's workflows was set or removed.
+    Variable,
+
     /// Someone signed in, or was refused.
     Authentication,
 
@@ -38,6 +41,7 @@ impl AuditCategory {
         Self::WebhookDelivery,
         Self::WorkflowConfig,
         Self::Connection,
+        Self::Variable,
         Self::Authentication,
         Self::Administration,
     ];
@@ -49,6 +53,7 @@ impl AuditCategory {
             Self::WebhookDelivery => "webhook.delivery",
             Self::WorkflowConfig => "workflow.config",
             Self::Connection => "connection",
+            Self::Variable => "variable",
             Self::Authentication => "authentication",
             Self::Administration => "administration",
         }
@@ -61,6 +66,7 @@ impl AuditCategory {
             Self::WebhookDelivery => "Webhook delivery",
             Self::WorkflowConfig => "Workflow change",
             Self::Connection => "Connection",
+            Self::Variable => "Variable",
             Self::Authentication => "Sign-in",
             Self::Administration => "Administration",
         }
@@ -72,6 +78,7 @@ impl AuditCategory {
             "webhook.delivery" => Self::WebhookDelivery,
             "workflow.config" => Self::WorkflowConfig,
             "connection" => Self::Connection,
+            "variable" => Self::Variable,
             "authentication" => Self::Authentication,
             "administration" => Self::Administration,
             _ => return None,
@@ -0,0 +1,131 @@
+//! The deliveries a webhook workflow was recently sent, kept as they arrived.
+//!
+//! A run record answers "is this workflow working", and keeps one input to
+//! show for it. Setting up a sender needs more than that: the last few
+//! deliveries side by side, with the headers they came with, whether their
+//! signature checked out, which of them the filter kept and what was filed for
+//! each. That is a different question with a different shape, so it has its own
+//! record and its own, much shorter, retention.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DeliveryId, PreviewItem, PreviewTask, RunOutcome};
+
+/// What became of a delivery's signature.
+///
+/// Absent from a delivery whose workflow did not check one, either because it
+/// has no signature to check or because the shared endpoint it arrived on
+/// checked it before it was routed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "kebab-case")]
+pub enum SignatureVerdict {
+    /// The signature was made with the workflow's secret.
+    Verified,
+
+    /// The delivery was turned away, and why.
+    Rejected { reason: String },
+}
+
+/// One delivery, as it is listed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliverySummary {
+    pub id: DeliveryId,
+    pub received_at: chrono::DateTime<chrono::Utc>,
+    pub outcome: RunOutcome,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureVerdict>,
+
+    /// How many of the items the delivery carried its filter kept.
+    #[serde(default)]
+    pub matched: usize,
+
+    /// How many tasks the delivery filed.
+    #[serde(default)]
+    pub published: usize,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<DeliveryId>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_of: Option<String>,
+}
+
+/// Everything kept about one delivery.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryRecord {
+    pub id: DeliveryId,
+
+    /// When the sender's request arrived, which may be some time before it
+    /// was handled.
+    pub received_at: chrono::DateTime<chrono::Utc>,
+
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub outcome: RunOutcome,
+
+    /// Why handling it failed, in the words the failure was reported in, or
+    /// why it was set aside.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// The request's headers, with the values of any that carry credentials
+    /// replaced.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// The request's query string, redacted as the headers are.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+
+    /// The body as it was sent, or as much of it as was kept.
+    #[serde(default)]
+    pub body: String,
+
+    /// How long the body was, when only a prefix of it was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_from: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureVerdict>,
+
+    /// Each item the delivery carried, and whether the filter kept it.
+    #[serde(default)]
+    pub items: Vec<PreviewItem>,
+
+    /// The tasks it filed.
+    #[serde(default)]
+    pub published: Vec<PreviewTask>,
+
+    /// Anything else it did, described for a person.
+    #[serde(default)]
+    pub effects: Vec<String>,
+
+    /// The delivery this one replayed, when it was an owner running a kept
+    /// delivery again rather than the sender sending a new one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<DeliveryId>,
+
+    /// The sender's own id for this delivery, when it had already sent one by
+    /// that id and this was dropped as a retry of it rather than run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_of: Option<String>,
+}
+
+impl DeliveryRecord {
+    /// The summary carried in a list, without the body and headers that make
+    /// up most of the record.
+    pub fn summary(&self) -> DeliverySummary {
+        DeliverySummary {
+            id: self.id,
+            received_at: self.received_at,
+            outcome: self.outcome,
+            signature: self.signature.clone(),
+            matched: self.items.iter().filter(|item| item.matched).count(),
+            published: self.published.len(),
+            replay_of: self.replay_of,
+            repeat_of: self.repeat_of.clone(),
+        }
+    }
+}
@@ -65,6 +65,15 @@ pub type WorkflowId = WordId<3>;
 /// The identifier of a connection, rendered as two words.
 pub type ConnectionId = WordId<2>;
 
+/// The identifier of a kept webhook delivery, rendered as two words.
+///
+/// Only unique within the workflow it was sent to, which keeps a handful of
+/// them at a time.
+pub type DeliveryId = WordId<2>;
+
+/// The identifier of a workflow template, rendered as two words.
+pub type TemplateId = WordId<2>;
+
 /// An identifier encoded as `N` words drawn from the BIP-39 English wordlist.
 ///
 /// The encoding is lossless in both directions: every value in `0..=Self::MAX`
@@ -7,25 +7,50 @@
 
 mod audit;
 mod connection;
+mod delivery;
 pub mod ids;
 mod integration;
 mod kv;
+mod notification;
+pub mod payload_path;
+mod preview;
 mod queue;
+mod revision;
 mod run;
+mod template;
 mod tenant;
 mod user;
+mod variable;
 mod webhook;
 mod wordlist;
 mod workflow;
 
 pub use audit::{AuditCategory, AuditOutcome, AuditRecord};
 pub use connection::{ConnectionKind, ConnectionStatus, ConnectionSummary, OptionItem};
-pub use ids::{ConnectionId, WordId, WordIdError, WorkflowId};
+pub use delivery::{DeliveryRecord, DeliverySummary, SignatureVerdict};
+pub use ids::{ConnectionId, DeliveryId, TemplateId, WordId, WordIdError, WorkflowId};
 pub use integration::{Connection, IntegrationInfo};
 pub use kv::KeyValueEntry;
+pub use notification::NotificationDestination;
+pub use payload_path::{PayloadPath, PayloadPathError, Resolved};
+pub use preview::{
+    EvaluateFilter, FilterClause, FilterEvaluation, PreviewItem, PreviewTask, WorkflowPreview,
+};
 pub use queue::{QueueMessage, QueueStatus};
-pub use run::{RunOutcome, RunReport, RunState, WorkflowHealth};
+pub use revision::{RevisionChange, RevisionDiff, WorkflowRevision, diff as diff_revisions};
+pub use run::{
+    RunCounts, RunHistory, RunLog, RunLogLevel, RunLogLine, RunOutcome, RunReport, RunState,
+    RunTrigger, WorkflowHealth,
+};
+pub use template::{
+    InstantiateTemplate, SaveAsTemplate, TemplateParameter, TemplateScope, TemplateWorkflow,
+    WorkflowTemplate,
+};
 pub use tenant::{TenantId, TenantIdError};
 pub use user::{Account, AdminUser};
+pub use variable::{VariableInput, VariableSummary};
 pub use webhook::{WebhookToken, WebhookTokenError};
-pub use workflow::{FieldDescriptor, FieldKind, Workflow, WorkflowTrigger, WorkflowTypeDescriptor};
+pub use workflow::{
+    FieldDescriptor, FieldKind, Snooze, SnoozedDeliveries, WebhookLimits, Workflow,
+    WorkflowTrigger, WorkflowTypeDescriptor,
+};
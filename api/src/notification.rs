@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ConnectionId;
+
+/// Where an account is told that one of its workflows or connections has
+/// stopped working, and that it has started again.
+///
+/// One per account, because what it answers is "where do I look when something
+/// of mine breaks", and somebody with two answers to that has none. Each
+/// incident is one message at the destination however many runs it spans: a
+/// task that is filed when it starts and closed when it ends, or a pair of
+/// posts saying the same.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationDestination {
+    /// A task in one of the account's linked Todoist accounts, completed when
+    /// the incident is over.
+    Todoist {
+        /// Which linked account to file in, which may be left out when there is
+        /// only one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connection: Option<ConnectionId>,
+
+        /// The project to file in, defaulting to the inbox.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        project: Option<String>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        section: Option<String>,
+    },
+
+    /// A JSON document posted to an address, once when an incident starts and
+    /// again when it ends, both naming the same incident.
+    Webhook { url: String },
+
+    /// A message sent through the installation's mail server, once when an
+    /// incident starts and again when it ends, both naming the same incident.
+    Email { address: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_destination_says_what_kind_it_is() {
+        let destination = NotificationDestination::Webhook {
+            url: "https://chat.example.com/hooks/abc".into(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&destination).unwrap(),
+            serde_json::json!({ "kind": "webhook", "url": "https://chat.example.com/hooks/abc" })
+        );
+    }
+
+    #[test]
+    fn an_email_destination_is_an_address() {
+        let destination: NotificationDestination = serde_json::from_value(
+            serde_json::json!({ "kind": "email", "address": "ops@example.com" }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            destination,
+            NotificationDestination::Email {
+                address: "ops@example.com".into()
+            }
+        );
+    }
+
+    #[test]
+    fn a_todoist_destination_needs_nothing_but_its_kind() {
+        let destination: NotificationDestination =
+            serde_json::from_value(serde_json::json!({ "kind": "todoist" })).unwrap();
+
+        assert_eq!(
+            destination,
+            NotificationDestination::Todoist {
+                connection: None,
+                project: None,
+                section: None,
+            }
+        );
+    }
+}
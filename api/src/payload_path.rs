@@ -0,0 +1,793 @@
+//! The path syntax that addresses a field inside a webhook's JSON payload.
+//!
+//! Webhook workflows that know nothing about their sender let people write
+//! filters and templates against the raw payload, addressing it by dotted path:
+//! `issue.user.login`. That stops at the first array, which is where much of
+//! what people want to filter on lives — the files a push touched are in
+//! `commits`, a pull request's labels are objects, not strings. So a path can
+//! also step into arrays:
+//!
+//! * `commits[0]` is the first commit, and `commits[-1]` the last.
+//! * `commits[*]` is every commit, and a path that carries on after it,
+//!   `commits[*].modified`, is that field of every commit.
+//! * `labels[?name == "bug"]` is every label whose `name` is `bug`. The
+//!   comparison can be `==`, `!=`, `contains`, `startswith` or `endswith`, and
+//!   compares text without regard to case, as the filter language does. `@`
+//!   stands for the item itself, for arrays of plain values:
+//!   `files[?@ startswith "deploy/"]`.
+//!
+//! A path that goes through `[*]` or `[?...]` stands for a list of values rather
+//! than one. Arrays met along the way are flattened into it, so
+//! `commits[*].modified` is every file any commit touched rather than a list of
+//! lists, which is the shape the filter language's `in` and `contains` expect.
+//!
+//! # Why this lives here
+//!
+//! The agent resolves these paths, but the editor has to understand them too:
+//! it checks filters as they are typed, and the filter language on its own reads
+//! `commits[0]` as a property followed by a list. Both sides therefore use
+//! [`embed`] to hide a path's brackets from the filter language before parsing
+//! it, and [`PayloadPath::field`] to match a path against the fields a workflow
+//! suggests.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::Value;
+
+/// A parsed path into a JSON payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// The item itself, which only a predicate's path starts with.
+    Current,
+    Key(String),
+    Index(i64),
+    All,
+    Where(Box<Predicate>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Predicate {
+    path: PayloadPath,
+    comparison: Comparison,
+    value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equals,
+    NotEquals,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+/// What a path found in a payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolved<'a> {
+    /// Nothing: a key that is not there, an index past the end, or a step into
+    /// something that is not an object or array.
+    Missing,
+
+    /// The single value a path without `[*]` or `[?...]` addresses.
+    One(&'a Value),
+
+    /// Every value a path through `[*]` or `[?...]` addresses, which may be
+    /// none at all.
+    Many(Vec<&'a Value>),
+}
+
+impl Resolved<'_> {
+    /// The value as one JSON document, a list becoming an array.
+    pub fn to_value(&self) -> Option<Value> {
+        match self {
+            Resolved::Missing => None,
+            Resolved::One(value) => Some((*value).clone()),
+            Resolved::Many(values) => Some(Value::Array(
+                values.iter().map(|value| (*value).clone()).collect(),
+            )),
+        }
+    }
+}
+
+impl PayloadPath {
+    /// Parses a path such as `commits[*].author.name`.
+    pub fn parse(path: &str) -> Result<Self, PayloadPathError> {
+        let mut parser = Parser {
+            source: path,
+            position: 0,
+        };
+
+        let parsed = parser.path(false)?;
+        if parser.position < path.len() {
+            return Err(parser.error("something follows the end of the path"));
+        }
+
+        Ok(parsed)
+    }
+
+    /// The plain dotted field this path reads, with its array steps taken out,
+    /// so that `commits[*].author.name` can be matched against a workflow that
+    /// suggests `commits.author.name`.
+    pub fn field(&self) -> String {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Key(key) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Whether the path steps into an array anywhere.
+    pub fn has_brackets(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| !matches!(segment, Segment::Key(_)))
+    }
+
+    /// Finds what this path addresses in `payload`.
+    pub fn resolve<'a>(&self, payload: &'a Value) -> Resolved<'a> {
+        let mut current = Resolved::One(payload);
+
+        for segment in &self.segments {
+            current = match (segment, current) {
+                (_, Resolved::Missing) => return Resolved::Missing,
+                (Segment::Current, current) => current,
+                (Segment::Key(key), Resolved::One(value)) => match value.get(key) {
+                    Some(value) => Resolved::One(value),
+                    None => Resolved::Missing,
+                },
+                (Segment::Key(key), Resolved::Many(values)) => Resolved::Many(
+                    values
+                        .into_iter()
+                        .filter_map(|value| value.as_object()?.get(key))
+                        .collect(),
+                ),
+                (Segment::Index(index), Resolved::One(value)) => {
+                    match value.as_array().and_then(|items| nth(items, *index)) {
+                        Some(value) => Resolved::One(value),
+                        None => Resolved::Missing,
+                    }
+                }
+                (Segment::Index(index), Resolved::Many(values)) => Resolved::Many(
+                    values
+                        .into_iter()
+                        .filter_map(|value| nth(value.as_array()?, *index))
+                        .collect(),
+                ),
+                (Segment::All, current) => Resolved::Many(items(current).collect()),
+                (Segment::Where(predicate), current) => Resolved::Many(
+                    items(current)
+                        .filter(|item| predicate.matches(item))
+                        .collect(),
+                ),
+            };
+        }
+
+        // The arrays a projection ends on are flattened into it, so that
+        // `commits[*].modified` is one list of files rather than a list of
+        // lists. Doing so only at the end keeps `commits[*].modified[0]`
+        // meaning the first file of every commit.
+        match current {
+            Resolved::Many(values) => Resolved::Many(
+                values
+                    .into_iter()
+                    .flat_map(|value| match value {
+                        Value::Array(items) => items.iter().collect(),
+                        value => vec![value],
+                    })
+                    .collect(),
+            ),
+            current => current,
+        }
+    }
+}
+
+/// The items a `[*]` or `[?...]` steps into: an array's elements or an
+/// object's values, and within a projection the elements of each array in it,
+/// or the value itself where it is not one.
+fn items<'a>(current: Resolved<'a>) -> Box<dyn Iterator<Item = &'a Value> + 'a> {
+    match current {
+        Resolved::Missing => Box::new(std::iter::empty()),
+        Resolved::One(Value::Array(items)) => Box::new(items.iter()),
+        Resolved::One(Value::Object(fields)) => Box::new(fields.values()),
+        Resolved::One(_) => Box::new(std::iter::empty()),
+        Resolved::Many(values) => Box::new(values.into_iter().flat_map(|value| match value {
+            Value::Array(items) => items.iter().collect(),
+            value => vec![value],
+        })),
+    }
+}
+
+/// An array's element by position, counting back from the end for a negative
+/// index.
+fn nth(items: &[Value], index: i64) -> Option<&Value> {
+    let index = if index < 0 {
+        items.len().checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+
+    items.get(index)
+}
+
+impl Predicate {
+    fn matches(&self, item: &Value) -> bool {
+        let candidates = match self.path.resolve(item) {
+            Resolved::Missing => vec![&Value::Null],
+            Resolved::One(value) => vec![value],
+            Resolved::Many(values) => values,
+        };
+
+        let any = candidates.iter().any(|candidate| self.compare(candidate));
+        match self.comparison {
+            // Not equal to the value means none of them are, rather than any
+            // one of them not being.
+            Comparison::NotEquals => candidates.iter().all(|candidate| self.compare(candidate)),
+            _ => any,
+        }
+    }
+
+    fn compare(&self, candidate: &Value) -> bool {
+        let text = |value: &Value| -> Option<String> {
+            match value {
+                Value::String(text) => Some(text.to_lowercase()),
+                _ => None,
+            }
+        };
+
+        match self.comparison {
+            Comparison::Equals => equal(candidate, &self.value),
+            Comparison::NotEquals => !equal(candidate, &self.value),
+            Comparison::Contains => match candidate {
+                Value::Array(items) => items.iter().any(|item| equal(item, &self.value)),
+                candidate => text(candidate)
+                    .zip(text(&self.value))
+                    .is_some_and(|(candidate, needle)| candidate.contains(&needle)),
+            },
+            Comparison::StartsWith => text(candidate)
+                .zip(text(&self.value))
+                .is_some_and(|(candidate, prefix)| candidate.starts_with(&prefix)),
+            Comparison::EndsWith => text(candidate)
+                .zip(text(&self.value))
+                .is_some_and(|(candidate, suffix)| candidate.ends_with(&suffix)),
+        }
+    }
+}
+
+/// Equality as the filter language sees it: text without regard to case, and
+/// numbers by value rather than by how they were written.
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => left.to_lowercase() == right.to_lowercase(),
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        (left, right) => left == right,
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.position += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance();
+        }
+    }
+
+    fn error(&self, problem: &str) -> PayloadPathError {
+        PayloadPathError {
+            path: self.source.to_string(),
+            problem: problem.to_string(),
+        }
+    }
+
+    /// Reads a path up to whatever cannot continue it. Inside a predicate the
+    /// path may start with `@`, and ends at the whitespace before its
+    /// comparison.
+    fn path(&mut self, in_predicate: bool) -> Result<PayloadPath, PayloadPathError> {
+        let mut segments = Vec::new();
+
+        if in_predicate && self.peek() == Some('@') {
+            self.advance();
+            segments.push(Segment::Current);
+        } else if self.peek() != Some('[') {
+            segments.push(Segment::Key(self.key()?));
+        }
+
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.advance();
+                    segments.push(Segment::Key(self.key()?));
+                }
+                Some('[') => {
+                    self.advance();
+                    segments.push(self.bracket()?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(PayloadPath { segments })
+    }
+
+    fn key(&mut self) -> Result<String, PayloadPathError> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|ch| !matches!(ch, '.' | '[' | ']' | '=' | '!') && !ch.is_whitespace())
+        {
+            self.advance();
+        }
+
+        if start == self.position {
+            return Err(self.error("a field name is missing"));
+        }
+
+        Ok(self.source[start..self.position].to_string())
+    }
+
+    /// Reads what is between `[` and `]`, the `[` having been read already.
+    fn bracket(&mut self) -> Result<Segment, PayloadPathError> {
+        self.skip_whitespace();
+
+        let segment = match self.peek() {
+            Some('*') => {
+                self.advance();
+                Segment::All
+            }
+            Some('?') => {
+                self.advance();
+                self.skip_whitespace();
+                let path = self.path(true)?;
+                self.skip_whitespace();
+                let comparison = self.comparison()?;
+                self.skip_whitespace();
+                let value = self.literal()?;
+
+                Segment::Where(Box::new(Predicate {
+                    path,
+                    comparison,
+                    value,
+                }))
+            }
+            _ => {
+                let start = self.position;
+                if self.peek() == Some('-') {
+                    self.advance();
+                }
+                while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    self.advance();
+                }
+
+                let index = self.source[start..self.position].parse().map_err(|_| {
+                    self.error(
+                        "a `[` should hold a position such as 0, a `*`, or a `?` and a comparison",
+                    )
+                })?;
+                Segment::Index(index)
+            }
+        };
+
+        self.skip_whitespace();
+        if self.advance() != Some(']') {
+            return Err(self.error("a `[` is not closed with a `]`"));
+        }
+
+        Ok(segment)
+    }
+
+    fn comparison(&mut self) -> Result<Comparison, PayloadPathError> {
+        let rest = &self.source[self.position..];
+
+        for (spelling, comparison) in [
+            ("==", Comparison::Equals),
+            ("!=", Comparison::NotEquals),
+            ("contains", Comparison::Contains),
+            ("startswith", Comparison::StartsWith),
+            ("endswith", Comparison::EndsWith),
+        ] {
+            if rest.starts_with(spelling) {
+                self.position += spelling.len();
+                return Ok(comparison);
+            }
+        }
+
+        Err(self
+            .error("a `[?` should compare a field using ==, !=, contains, startswith or endswith"))
+    }
+
+    fn literal(&mut self) -> Result<Value, PayloadPathError> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.advance();
+                let mut text = String::new();
+
+                loop {
+                    match self.advance() {
+                        Some('\\') => match self.advance() {
+                            Some(escaped) => text.push(escaped),
+                            None => break,
+                        },
+                        Some(ch) if ch == quote => return Ok(Value::String(text)),
+                        Some(ch) => text.push(ch),
+                        None => break,
+                    }
+                }
+
+                Err(self.error("a quoted value is never closed"))
+            }
+            _ => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '.' | '_'))
+                {
+                    self.advance();
+                }
+
+                match &self.source[start..self.position] {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    number => number
+                        .parse::<i64>()
+                        .map(Value::from)
+                        .ok()
+                        .or_else(|| {
+                            number
+                                .parse::<f64>()
+                                .ok()
+                                .and_then(serde_json::Number::from_f64)
+                                .map(Value::Number)
+                        })
+                        .ok_or_else(|| {
+                            self.error("a comparison's value should be quoted text, a number, true, false or null")
+                        }),
+                }
+            }
+        }
+    }
+}
+
+/// A path that could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadPathError {
+    path: String,
+    problem: String,
+}
+
+impl PayloadPathError {
+    /// What is wrong with the path, without the path itself, for a caller that
+    /// reports it as part of something larger.
+    pub fn problem(&self) -> &str {
+        &self.problem
+    }
+}
+
+impl fmt::Display for PayloadPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The path '{}' could not be read: {}.",
+            self.path, self.problem
+        )
+    }
+}
+
+impl std::error::Error for PayloadPathError {}
+
+/// What an embedded path's property name starts with.
+const EMBEDDED: &str = "__path__";
+
+/// Rewrites the paths with brackets in a filter so that the filter language
+/// reads each as a single property.
+///
+/// The filter language takes a `[` to start a list, so `commits[0].id` would
+/// otherwise be a property followed by one. Each such path is replaced with a
+/// property name that spells it out in hexadecimal, which [`embedded`] turns
+/// back into the path when the filter asks for it. Text in quotes is left
+/// alone, as are the keywords that a list legitimately follows, such as `in`.
+pub fn embed(filter: &str) -> Cow<'_, str> {
+    if !filter.contains('[') {
+        return Cow::Borrowed(filter);
+    }
+
+    let mut output = String::with_capacity(filter.len());
+    let mut chars = filter.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                output.push(ch);
+                while let Some((_, ch)) = chars.next() {
+                    output.push(ch);
+                    match ch {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                output.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            'r' if raw_string_hashes(&filter[start..]).is_some() => {
+                let hashes = raw_string_hashes(&filter[start..]).unwrap_or_default();
+                let close = format!("\"{}", "#".repeat(hashes));
+                let body = start + 2 + hashes;
+                let end = filter[body..]
+                    .find(&close)
+                    .map(|offset| body + offset + close.len())
+                    .unwrap_or(filter.len());
+
+                output.push_str(&filter[start..end]);
+                while chars.peek().is_some_and(|&(index, _)| index < end) {
+                    chars.next();
+                }
+            }
+            ch if ch.is_alphabetic() || ch == '_' => {
+                let mut end = start + ch.len_utf8();
+                while let Some(&(index, ch)) = chars.peek() {
+                    if !(ch.is_alphanumeric() || matches!(ch, '_' | '.' | '-')) {
+                        break;
+                    }
+                    end = index + ch.len_utf8();
+                    chars.next();
+                }
+
+                let word = &filter[start..end];
+                if chars.peek().map(|&(_, ch)| ch) != Some('[') || is_keyword(word) {
+                    output.push_str(word);
+                    continue;
+                }
+
+                // Carries on through brackets and the fields after them, such
+                // as the `[*].author.name` of `commits[*].author.name`.
+                loop {
+                    match chars.peek().map(|&(_, ch)| ch) {
+                        Some('[') => {
+                            let mut depth = 0;
+                            let mut quote = None;
+                            while let Some((index, ch)) = chars.next() {
+                                end = index + ch.len_utf8();
+                                match (quote, ch) {
+                                    (Some(_), '\\') => {
+                                        if let Some((index, escaped)) = chars.next() {
+                                            end = index + escaped.len_utf8();
+                                        }
+                                    }
+                                    (Some(open), ch) if ch == open => quote = None,
+                                    (Some(_), _) => {}
+                                    (None, '"' | '\'') => quote = Some(ch),
+                                    (None, '[') => depth += 1,
+                                    (None, ']') => {
+                                        depth -= 1;
+                                        if depth == 0 {
+                                            break;
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        Some('.') => {
+                            chars.next();
+                            while let Some(&(index, ch)) = chars.peek() {
+                                if !(ch.is_alphanumeric() || matches!(ch, '_' | '-')) {
+                                    break;
+                                }
+                                end = index + ch.len_utf8();
+                                chars.next();
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+
+                output.push_str(EMBEDDED);
+                for byte in filter[start..end].bytes() {
+                    output.push_str(&format!("{byte:02x}"));
+                }
+            }
+            ch => output.push(ch),
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+/// The path a property name produced by [`embed`] stands for, if it is one.
+pub fn embedded(property: &str) -> Option<String> {
+    let hex = property.strip_prefix(EMBEDDED)?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
+/// How many `#`s open a raw string at the start of `source`, if one starts
+/// there, following the filter language's own `r"..."` and `r#"..."#`.
+fn raw_string_hashes(source: &str) -> Option<usize> {
+    let rest = source.strip_prefix('r')?;
+    let hashes = rest.chars().take_while(|ch| *ch == '#').count();
+    rest[hashes..].starts_with('"').then_some(hashes)
+}
+
+/// The filter language's words that a list may follow directly.
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "in" | "in_cs"
+            | "contains"
+            | "contains_cs"
+            | "startswith"
+            | "startswith_cs"
+            | "endswith"
+            | "endswith_cs"
+            | "like"
+            | "like_cs"
+            | "matches"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push() -> Value {
+        serde_json::json!({
+            "ref": "refs/heads/main",
+            "commits": [
+                {
+                    "id": "a1",
+                    "message": "Tidy the README",
+                    "author": { "username": "octocat" },
+                    "modified": ["README.md"],
+                },
+                {
+                    "id": "b2",
+                    "message": "Roll out the new config",
+                    "author": { "username": "hubot" },
+                    "modified": ["deploy/app.yaml", "src/main.rs"],
+                },
+            ],
+            "labels": [{ "name": "Bug" }, { "name": "urgent" }],
+        })
+    }
+
+    fn resolve(path: &str) -> Option<Value> {
+        PayloadPath::parse(path)
+            .expect("the path should parse")
+            .resolve(&push())
+            .to_value()
+    }
+
+    #[test]
+    fn an_index_picks_one_item_and_a_negative_one_counts_from_the_end() {
+        assert_eq!(
+            resolve("commits[0].message"),
+            Some("Tidy the README".into())
+        );
+        assert_eq!(resolve("commits[-1].id"), Some("b2".into()));
+        assert_eq!(resolve("commits[2].id"), None, "there is no third commit");
+    }
+
+    #[test]
+    fn a_wildcard_collects_a_field_from_every_item_and_flattens_lists() {
+        assert_eq!(
+            resolve("commits[*].author.username"),
+            Some(serde_json::json!(["octocat", "hubot"])),
+        );
+        assert_eq!(
+            resolve("commits[*].modified"),
+            Some(serde_json::json!([
+                "README.md",
+                "deploy/app.yaml",
+                "src/main.rs"
+            ])),
+            "the files of every commit should be one list, so `in` and `contains` can search it",
+        );
+    }
+
+    #[test]
+    fn a_predicate_keeps_the_items_that_match_it() {
+        assert_eq!(
+            resolve("commits[?author.username == \"hubot\"].id"),
+            Some(serde_json::json!(["b2"])),
+        );
+        assert_eq!(
+            resolve("labels[?name == 'bug'].name"),
+            Some(serde_json::json!(["Bug"])),
+            "text should be compared without regard to case, as the filter language does",
+        );
+        assert_eq!(
+            resolve("commits[*].modified[?@ startswith \"deploy/\"]"),
+            Some(serde_json::json!(["deploy/app.yaml"])),
+        );
+        assert_eq!(
+            resolve("commits[?modified contains \"src/main.rs\"].id"),
+            Some(serde_json::json!(["b2"])),
+        );
+        assert_eq!(
+            resolve("commits[?author.username != \"octocat\"].id"),
+            Some(serde_json::json!(["b2"])),
+        );
+    }
+
+    #[test]
+    fn a_path_is_matched_to_a_suggested_field_without_its_brackets() {
+        let path = PayloadPath::parse("commits[?id == \"a1\"].author.username").unwrap();
+        assert_eq!(path.field(), "commits.author.username");
+        assert!(path.has_brackets());
+        assert!(!PayloadPath::parse("issue.title").unwrap().has_brackets());
+    }
+
+    #[test]
+    fn a_malformed_path_says_what_is_wrong_with_it() {
+        for (path, problem) in [
+            ("commits[", "should hold a position"),
+            ("commits[0", "not closed"),
+            ("commits[?id ~ 1]", "should compare a field"),
+            ("commits[?id == \"a1]", "never closed"),
+            ("commits..id", "field name is missing"),
+        ] {
+            let err = PayloadPath::parse(path).expect_err(path);
+            assert!(
+                err.to_string().contains(problem),
+                "the error for '{path}' should say '{problem}': {err}",
+            );
+        }
+    }
+
+    #[test]
+    fn a_filter_keeps_its_quotes_and_lists_but_hides_its_paths() {
+        let filter = r#""deploy/app.yaml" in commits[*].modified && action in ["a[0]", "b"] && ref == "x[1]""#;
+        let embedded_filter = embed(filter);
+
+        assert!(
+            embedded_filter.contains(r#"in ["a[0]", "b"]"#),
+            "a list after `in` is the filter language's own: {embedded_filter}",
+        );
+        assert!(embedded_filter.ends_with(r#"ref == "x[1]""#));
+
+        let property = embedded_filter
+            .split_whitespace()
+            .find(|word| word.starts_with(EMBEDDED))
+            .expect("the path should have been embedded");
+        assert_eq!(embedded(property).as_deref(), Some("commits[*].modified"));
+    }
+
+    #[test]
+    fn a_filter_without_brackets_is_left_as_it_is() {
+        assert!(matches!(embed("issue.title == \"x\""), Cow::Borrowed(_)));
+        assert_eq!(embedded("issue.title"), None);
+    }
+}
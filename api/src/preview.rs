@@ -0,0 +1,136 @@
+//! What a workflow would do, without doing it.
+//!
+//! The first run of a feed files everything the feed still carries, so saving a
+//! workflow used to be the moment somebody found out whether its filter was
+//! right. A preview runs the collector and the filter against what is there now
+//! and reports the outcome, leaving the watermark where it was and filing
+//! nothing.
+
+use serde::{Deserialize, Serialize};
+
+use crate::WorkflowId;
+
+/// The outcome of previewing a workflow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowPreview {
+    /// Everything the workflow looked at, in the order it looked.
+    #[serde(default)]
+    pub items: Vec<PreviewItem>,
+
+    /// The tasks a real run would have filed.
+    #[serde(default)]
+    pub tasks: Vec<PreviewTask>,
+
+    /// Anything else a real run would have done, described for a person.
+    #[serde(default)]
+    pub effects: Vec<String>,
+
+    /// For a webhook workflow, when the delivery it was replayed against
+    /// originally arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replayed: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Why the run stopped early, if it did. What it found before then is
+    /// still reported, since that is usually the clue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One thing a workflow collected, and what its filter made of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewItem {
+    /// How the workflow would describe the item to a person.
+    pub item: String,
+
+    /// Whether the filter kept it.
+    pub matched: bool,
+
+    /// Why the filter could not be evaluated, where it could not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// How each part of the filter came out, when a filter is being tried out
+    /// rather than previewed as part of the workflow.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clauses: Vec<FilterClause>,
+}
+
+/// One part of a filter, and whether it held for an item.
+///
+/// A filter is broken down at its `&&`, `||` and `!`, so that a filter which
+/// dropped something it should have kept says which of its conditions did it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterClause {
+    /// The part, written out as the filter language would read it.
+    pub expression: String,
+
+    /// How many parts it is nested inside, with the whole filter at zero.
+    pub depth: usize,
+
+    /// Whether it held.
+    pub matched: bool,
+
+    /// Why it could not be evaluated, where it could not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A filter expression to try out, and what to try it against.
+///
+/// Exactly one of `payload` and `workflow` is expected. A payload is matched
+/// directly, as a webhook workflow would match it; a workflow is run as it
+/// would be previewed, with this expression in place of its own filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluateFilter {
+    pub expression: String,
+
+    /// A sample JSON body, addressed by path as a webhook workflow's filter
+    /// addresses a delivery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+
+    /// A saved workflow whose items to try the expression against: what it
+    /// collects now, or for a webhook workflow, the last delivery it kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<WorkflowId>,
+
+    /// The configuration to run `workflow` with, when it differs from the
+    /// saved one. The filter in it is ignored in favour of `expression`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<serde_json::Value>,
+}
+
+/// What a filter expression made of each item it was tried against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterEvaluation {
+    /// Each item, whether the expression kept it, and how each of its parts
+    /// came out.
+    #[serde(default)]
+    pub items: Vec<PreviewItem>,
+
+    /// For a webhook workflow, when the delivery it was tried against
+    /// originally arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replayed: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Why the workflow stopped before every item was tried, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A task a real run would have filed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewTask {
+    /// The job that would have filed it, e.g. `todoist/create-task`.
+    pub job: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Everything the job would have been handed, for the fields a title and
+    /// description do not cover.
+    pub payload: serde_json::Value,
+}
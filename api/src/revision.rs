@@ -0,0 +1,228 @@
+//! What a workflow used to be.
+//!
+//! Saving a workflow replaces it, and the audit log only says that it changed.
+//! That is enough to know when a filter stopped matching and not enough to put
+//! it back, so every saved version of a workflow is kept as a revision: what it
+//! was configured to do, when it ran, whether it was on, and who saved it.
+//!
+//! Two revisions can be compared with [`diff`], which walks both configurations
+//! and names each setting that differs. It lives here rather than in the agent
+//! so that the answer is the same whichever side works it out.
+
+use serde::{Deserialize, Serialize};
+
+/// One saved version of a workflow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRevision {
+    /// Counts up from one, in the order the versions were saved.
+    pub number: u32,
+
+    pub config: serde_json::Value,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+
+    pub enabled: bool,
+
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+
+    /// The account that saved it, where one was signed in. A workflow applied
+    /// from a file on the command line was saved by nobody in particular.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_by: Option<String>,
+
+    /// The revision this one put back, when it was saved by restoring one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_from: Option<u32>,
+}
+
+/// How one revision differs from another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevisionDiff {
+    pub from: u32,
+    pub to: u32,
+
+    /// Each setting that differs, the configuration's first and then the
+    /// schedule and whether it is on. Empty when the two are the same, as a save
+    /// that changed nothing is.
+    pub changes: Vec<RevisionChange>,
+}
+
+/// One setting that differs between two revisions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevisionChange {
+    /// Where the setting is, written as a filter would reach it:
+    /// `config.filter`, `config.labels[1]`, `schedule` or `enabled`.
+    pub path: String,
+
+    /// What it was. Absent when the later revision added it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<serde_json::Value>,
+
+    /// What it became. Absent when the later revision removed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<serde_json::Value>,
+}
+
+/// Compares two revisions, setting by setting.
+///
+/// Objects are compared key by key and lists item by item, so a changed filter
+/// is one change rather than the whole configuration being different. A value
+/// that changed kind — a string that became a list — is reported as it stands
+/// rather than taken apart.
+pub fn diff(from: &WorkflowRevision, to: &WorkflowRevision) -> RevisionDiff {
+    let mut changes = Vec::new();
+
+    walk("config", &from.config, &to.config, &mut changes);
+
+    if from.schedule != to.schedule {
+        changes.push(RevisionChange {
+            path: "schedule".into(),
+            before: from.schedule.clone().map(Into::into),
+            after: to.schedule.clone().map(Into::into),
+        });
+    }
+
+    if from.enabled != to.enabled {
+        changes.push(RevisionChange {
+            path: "enabled".into(),
+            before: Some(from.enabled.into()),
+            after: Some(to.enabled.into()),
+        });
+    }
+
+    RevisionDiff {
+        from: from.number,
+        to: to.number,
+        changes,
+    }
+}
+
+fn walk(
+    path: &str,
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    changes: &mut Vec<RevisionChange>,
+) {
+    use serde_json::Value;
+
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, was) in before {
+                let path = format!("{path}.{key}");
+                match after.get(key) {
+                    Some(now) => walk(&path, was, now, changes),
+                    None => changes.push(RevisionChange {
+                        path,
+                        before: Some(was.clone()),
+                        after: None,
+                    }),
+                }
+            }
+
+            for (key, now) in after {
+                if !before.contains_key(key) {
+                    changes.push(RevisionChange {
+                        path: format!("{path}.{key}"),
+                        before: None,
+                        after: Some(now.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for index in 0..before.len().max(after.len()) {
+                let path = format!("{path}[{index}]");
+                match (before.get(index), after.get(index)) {
+                    (Some(was), Some(now)) => walk(&path, was, now, changes),
+                    (was, now) => changes.push(RevisionChange {
+                        path,
+                        before: was.cloned(),
+                        after: now.cloned(),
+                    }),
+                }
+            }
+        }
+        (before, after) if before != after => changes.push(RevisionChange {
+            path: path.to_string(),
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(number: u32, config: serde_json::Value) -> WorkflowRevision {
+        WorkflowRevision {
+            number,
+            config,
+            schedule: None,
+            enabled: true,
+            saved_at: chrono::Utc::now(),
+            saved_by: None,
+            restored_from: None,
+        }
+    }
+
+    #[test]
+    fn a_changed_setting_is_named_by_where_it_is() {
+        let from = revision(
+            1,
+            serde_json::json!({
+                "filter": "action == \"opened\"",
+                "labels": ["bug", "triage"],
+                "todoist": { "project": "Inbox" },
+            }),
+        );
+        let to = WorkflowRevision {
+            schedule: Some("@hourly".into()),
+            ..revision(
+                2,
+                serde_json::json!({
+                    "filter": "action == \"closed\"",
+                    "labels": ["bug"],
+                    "todoist": { "project": "Inbox", "priority": 4 },
+                }),
+            )
+        };
+
+        let diff = diff(&from, &to);
+        assert_eq!((diff.from, diff.to), (1, 2));
+
+        let paths: Vec<&str> = diff.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "config.filter",
+                "config.labels[1]",
+                "config.todoist.priority",
+                "schedule",
+            ]
+        );
+
+        assert_eq!(diff.changes[1].before, Some("triage".into()));
+        assert_eq!(
+            diff.changes[1].after, None,
+            "a removed item has nothing after it"
+        );
+        assert_eq!(
+            diff.changes[2].before, None,
+            "an added setting has nothing before it"
+        );
+    }
+
+    #[test]
+    fn identical_revisions_have_no_changes() {
+        let config = serde_json::json!({ "filter": "true" });
+
+        assert!(
+            diff(&revision(1, config.clone()), &revision(2, config))
+                .changes
+                .is_empty()
+        );
+    }
+}
@@ -5,21 +5,30 @@
 //! which produces thousands of deliveries a day and buries the handful of
 //! entries somebody actually wanted to read.
 //!
-//! So a run is no longer history. Each workflow keeps one record of how it is
-//! getting on, overwritten in place, and the audit log hears about a run only
-//! when the answer changes.
+//! So a run is no longer an audit entry. Each workflow keeps one record of how
+//! it is getting on, overwritten in place, and the audit log hears about a run
+//! only when the answer changes. Beside it is a short history of the workflow's
+//! own recent runs, bounded by count and by age, which is what answers "it
+//! failed at three and again at five, but not at four".
+
+use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
 /// How a run turned out.
 ///
-/// Only two answers, because the record exists to say whether the workflow is
-/// working. A run that found nothing to do worked.
+/// The record exists to say whether the workflow is working, so a run that
+/// found nothing to do worked. One that was handed something it would not act
+/// on says neither: a delivery whose signature was refused, or one for a
+/// paused or snoozed workflow, is [`Discarded`](Self::Discarded), and leaves
+/// the failure streak as it was. Counting it as working would let anybody who
+/// knows a failing workflow's address make it look recovered.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum RunOutcome {
     Succeeded,
     Failed,
+    Discarded,
 }
 
 impl RunOutcome {
@@ -27,10 +36,144 @@ impl RunOutcome {
         match self {
             Self::Succeeded => "succeeded",
             Self::Failed => "failed",
+            Self::Discarded => "discarded",
+        }
+    }
+
+    /// How the outcome is described to a person.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "Succeeded",
+            Self::Failed => "Failed",
+            Self::Discarded => "Set aside",
+        }
+    }
+}
+
+/// What started a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunTrigger {
+    /// The workflow's schedule came round.
+    Schedule,
+
+    /// Somebody asked for a run outside the schedule.
+    Manual,
+
+    /// A sender delivered something to the workflow.
+    Webhook,
+
+    /// An owner asked for a kept delivery to be run again.
+    Replay,
+
+    /// Another workflow emitted an event this one follows.
+    Event,
+}
+
+impl RunTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Schedule => "schedule",
+            Self::Manual => "manual",
+            Self::Webhook => "webhook",
+            Self::Replay => "replay",
+            Self::Event => "event",
+        }
+    }
+
+    /// How the trigger is described to a person.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Schedule => "Scheduled",
+            Self::Manual => "Run now",
+            Self::Webhook => "Webhook",
+            Self::Replay => "Replay",
+            Self::Event => "Event",
         }
     }
 }
 
+/// How many items a run looked at, and what became of them.
+///
+/// Counted from what the run did rather than reported by it, so a workflow
+/// without a filter reports no items even when it filed tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunCounts {
+    /// The items its filter was asked about.
+    #[serde(default)]
+    pub items: u32,
+
+    /// How many of those the filter kept.
+    #[serde(default)]
+    pub matched: u32,
+
+    /// The tasks and other jobs it handed on.
+    #[serde(default)]
+    pub published: u32,
+}
+
+impl RunCounts {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How serious a line in a run's log is.
+///
+/// Only the levels an owner is shown. Anything quieter is for whoever runs the
+/// server, and stays in its logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunLogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl RunLogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// One thing a run logged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunLogLine {
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub level: RunLogLevel,
+    pub message: String,
+
+    /// The values logged beside the message, such as the delivery's id or the
+    /// error that caused it, written out as text.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+/// What a run logged, up to the most that is kept of it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunLog {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<RunLogLine>,
+
+    /// How many lines were logged after the most that is kept, and dropped.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub dropped: u32,
+}
+
+impl RunLog {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.dropped == 0
+    }
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
 /// One run, with enough of what it ran on to work out why it went the way it
 /// did.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -39,7 +182,8 @@ pub struct RunReport {
     pub finished_at: chrono::DateTime<chrono::Utc>,
     pub outcome: RunOutcome,
 
-    /// Why it failed, in the words the failure was reported in.
+    /// Why it failed, in the words the failure was reported in, or why what
+    /// it was handed was set aside.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
 
@@ -50,12 +194,40 @@ pub struct RunReport {
     /// else's data sitting in a store the admin UI can browse.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub input: Option<serde_json::Value>,
+
+    /// The kept delivery this run replayed, when an owner asked for one to be
+    /// run again rather than a sender sending it.
+    ///
+    /// Worth saying on the run itself: a replay that files a task is otherwise
+    /// indistinguishable from the sender having delivered twice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<crate::DeliveryId>,
+
+    /// What started it. Absent from runs recorded before this was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<RunTrigger>,
+
+    #[serde(default, skip_serializing_if = "RunCounts::is_empty")]
+    pub counts: RunCounts,
+
+    /// What the run logged along the way, such as a delivery it discarded or a
+    /// request it had to retry.
+    #[serde(default, skip_serializing_if = "RunLog::is_empty")]
+    pub log: RunLog,
 }
 
-/// Everything kept about one workflow's runs.
+impl RunReport {
+    /// How long the run took.
+    pub fn duration(&self) -> chrono::Duration {
+        self.finished_at - self.started_at
+    }
+}
+
+/// How one workflow is getting on, kept apart from its history.
 ///
 /// Three fields rather than a list, so that a workflow which runs a thousand
-/// times a day costs the same as one which runs twice.
+/// times a day costs the same to summarise as one which runs twice, and so
+/// that its last failure outlives a history that has moved on from it.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RunState {
     /// The most recent run, whatever became of it.
@@ -69,7 +241,8 @@ pub struct RunState {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_failure: Option<RunReport>,
 
-    /// How many runs have failed in a row. Zeroed by a run that works.
+    /// How many runs have failed in a row. Zeroed by a run that works, and
+    /// left alone by one that was discarded.
     #[serde(default)]
     pub consecutive_failures: u32,
 }
@@ -99,6 +272,24 @@ impl RunState {
     }
 }
 
+/// A page of a workflow's recent runs, as `/workflows/{id}/runs` returns it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunHistory {
+    /// How the workflow is getting on, with its most recent failure, which
+    /// the history may have let go of on a busy workflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<RunState>,
+
+    /// Runs, newest first.
+    #[serde(default)]
+    pub runs: Vec<RunReport>,
+
+    /// Where the next, older, page starts: pass it back as `before`. Absent
+    /// on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 /// How a workflow is getting on, as shown beside it in a list.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorkflowHealth {
@@ -117,3 +308,39 @@ pub struct WorkflowHealth {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_failure_at: Option<chrono::DateTime<chrono::Utc>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_run_recorded_before_triggers_and_counts_were_still_reads() {
+        let stored = serde_json::json!({
+            "started_at": "2024-01-01T03:00:00Z",
+            "finished_at": "2024-01-01T03:00:02Z",
+            "outcome": "failed",
+        });
+
+        let report: RunReport = serde_json::from_value(stored).unwrap();
+        assert_eq!(report.trigger, None);
+        assert_eq!(report.counts, RunCounts::default());
+        assert!(report.log.is_empty());
+        assert_eq!(report.duration(), chrono::Duration::seconds(2));
+    }
+
+    #[test]
+    fn as_str_matches_the_wire_form_of_every_trigger() {
+        for trigger in [
+            RunTrigger::Schedule,
+            RunTrigger::Manual,
+            RunTrigger::Webhook,
+            RunTrigger::Replay,
+            RunTrigger::Event,
+        ] {
+            assert_eq!(
+                serde_json::to_value(trigger).unwrap(),
+                serde_json::json!(trigger.as_str()),
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{ConnectionId, TemplateId};
+
+/// A set of workflows that can be created again and again with different
+/// values, such as "watch this project's GitHub releases".
+///
+/// The workflows' settings may read `${{ params.name }}` wherever a value
+/// should differ between one use and the next. Those are filled in when the
+/// template is used and never seen by a running workflow, which is why they
+/// live in their own namespace beside `vars` and `secrets` rather than in it.
+///
+/// A template never names a linked account. Each person who uses one picks
+/// their own, because the account that filed the original workflow's tasks is
+/// not one anybody else can file into.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowTemplate {
+    pub id: TemplateId,
+
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Whether this template is the account's own or was published by an
+    /// administrator for everybody.
+    pub scope: TemplateScope,
+
+    /// The values asked for each time the template is used.
+    #[serde(default)]
+    pub parameters: Vec<TemplateParameter>,
+
+    /// The workflows created each time.
+    pub workflows: Vec<TemplateWorkflow>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Who a template belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateScope {
+    /// Saved by this account, and seen by nobody else.
+    #[default]
+    Account,
+
+    /// Published by an administrator, and offered to every account.
+    Installation,
+}
+
+/// One value a template asks for, read in its workflows as
+/// `${{ params.name }}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateParameter {
+    pub name: String,
+
+    /// What to call the value in a form, defaulting to its name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+
+    /// Used when no value is given. A parameter without one must be given a
+    /// value every time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// A workflow as a template describes it: what it would be created with, less
+/// anything that only makes sense for one account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateWorkflow {
+    #[serde(rename = "type")]
+    pub type_id: String,
+
+    pub config: serde_json::Value,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+}
+
+/// The body of a request to create workflows from a template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstantiateTemplate {
+    /// A value for each of the template's parameters, by name.
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+
+    /// The linked account to use for each connection field, by the field's
+    /// path, such as `todoist.connection`. A field left out is left unset, as
+    /// it would be by a form nobody had touched.
+    #[serde(default)]
+    pub connections: BTreeMap<String, ConnectionId>,
+
+    /// Whether the new workflows should start running straight away.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// The body of a request to keep a workflow as a template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveAsTemplate {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The paths of the fields to ask for each time, such as `url`. Each
+    /// becomes a parameter whose default is the workflow's current value.
+    #[serde(default)]
+    pub parameters: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn using_a_template_starts_its_workflows_unless_told_otherwise() {
+        let request: InstantiateTemplate =
+            serde_json::from_value(serde_json::json!({ "values": { "repo": "octo/cat" } }))
+                .unwrap();
+
+        assert!(request.enabled);
+        assert_eq!(request.values["repo"], "octo/cat");
+        assert!(request.connections.is_empty());
+    }
+}
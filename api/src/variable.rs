@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A value an account keeps for its workflows' templates to share, as
+/// described to the browser.
+///
+/// Read in a template as `${{ vars.name }}`, or as `${{ secrets.name }}` when
+/// it is a secret. A secret's value is never sent back once it has been set:
+/// [`VariableSummary::value`] is absent for one, and there is no endpoint that
+/// would return it, so replacing a secret is the only way to change it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableSummary {
+    pub name: String,
+
+    /// Whether this is a secret, sealed at rest and redacted from what is kept
+    /// of each run.
+    pub secret: bool,
+
+    /// The value, for a variable that is not a secret.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The body of a request to set a variable or secret.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableInput {
+    pub value: String,
+
+    /// Whether to keep the value as a secret. A variable's kind can be changed
+    /// by setting it again.
+    #[serde(default)]
+    pub secret: bool,
+}
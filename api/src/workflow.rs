@@ -62,6 +62,14 @@ pub enum WorkflowTrigger {
         /// The provider whose shared endpoint receives the payload.
         source: String,
     },
+
+    /// Runs when another of the same account's workflows emits an event this
+    /// workflow subscribes to, such as a run failing or a task being filed.
+    Event {
+        /// What emits the events this accepts. Every event is emitted by a
+        /// workflow today, so this is `workflows`.
+        source: String,
+    },
 }
 
 impl WorkflowTrigger {
@@ -72,6 +80,7 @@ impl WorkflowTrigger {
             Self::Webhook { source } | Self::RoutedWebhook { source } => {
                 format!("webhooks/{source}")
             }
+            Self::Event { source } => format!("events/{source}"),
         }
     }
 }
@@ -186,6 +195,16 @@ pub enum FieldKind {
     /// fluently.
     Cron,
 
+    /// A script that reshapes each item before it is filtered.
+    ///
+    /// Separate from [`FieldKind::TextArea`] because it is code: it wants a
+    /// monospaced face and room for more than a sentence, and an editor that
+    /// knows not to correct its spelling.
+    Script {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        placeholder: Option<String>,
+    },
+
     /// A filter expression over the items the workflow collected.
     Filter {
         /// The names this workflow's items expose, so the editor can suggest
@@ -209,6 +228,7 @@ impl FieldKind {
             Self::Options { .. } => "options",
             Self::Connection { .. } => "connection",
             Self::Cron => "cron",
+            Self::Script { .. } => "script",
             Self::Filter { .. } => "filter",
         }
     }
@@ -368,6 +388,15 @@ pub struct Workflow {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub webhook_path: Option<String>,
 
+    /// What a webhook-triggered workflow accepts at that path, and from whom.
+    ///
+    /// Present exactly when [`Workflow::webhook_path`] is. Kept apart from
+    /// [`Workflow::config`] because it is about the address rather than the work:
+    /// every type reached by URL is equally exposed by one leaking, and none of
+    /// their handlers has any use for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<WebhookLimits>,
+
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 
@@ -375,10 +404,15 @@ pub struct Workflow {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_run: Option<chrono::DateTime<chrono::Utc>>,
 
-    /// When this is next expected to run, for triggers that can say.
+    /// When this is next expected to run, for triggers that can say. A snoozed
+    /// workflow's is the first run after its snooze ends.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub next_run: Option<chrono::DateTime<chrono::Utc>>,
 
+    /// A pause that ends by itself, present only while it lasts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snooze: Option<Snooze>,
+
     /// How the most recent runs have gone, once there has been one.
     ///
     /// A summary rather than the runs themselves: a list of workflows carrying
@@ -403,10 +437,131 @@ fn default_enabled() -> bool {
     true
 }
 
+/// What a webhook workflow's address will accept.
+///
+/// The address is the only credential a sender presents, so somebody who has
+/// seen it can send as much as they like. These bound what that costs: how
+/// often a delivery is accepted, how large one may be, and where it may come
+/// from. Each is optional, and an unset one takes the installation's default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookLimits {
+    /// How many deliveries a minute are accepted, on average. Zero accepts as
+    /// many as arrive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_minute: Option<u32>,
+
+    /// How many may arrive together before the rate applies. Unset allows a
+    /// minute's worth, which is what a sender catching up after an outage sends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+
+    /// The largest body accepted, in bytes. It can lower the installation's own
+    /// limit but not raise it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u32>,
+
+    /// Who may deliver, as a filter over the request's `client_ip`, `method`,
+    /// `path` and `headers.*` — the fields `admin_acl` is written against.
+    /// Unset accepts anybody holding the address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<String>,
+}
+
+impl WebhookLimits {
+    /// Whether nothing has been set, so every limit is the installation's.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A pause that ends by itself.
+///
+/// [`Workflow::enabled`] is for a workflow somebody has stopped until further
+/// notice. This is for the other kind of quiet — a maintenance window, a noisy
+/// release — where the thing most likely to go wrong is forgetting to turn the
+/// workflow back on afterwards. It sits on top of `enabled` rather than
+/// replacing it, so a snooze ending never wakes a workflow somebody paused.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snooze {
+    /// When the workflow wakes up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// How many more runs are skipped before it wakes up. A workflow given both
+    /// this and [`Snooze::until`] wakes at whichever comes first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runs: Option<u32>,
+
+    /// What becomes of the deliveries a webhook workflow receives meanwhile.
+    #[serde(default, skip_serializing_if = "SnoozedDeliveries::is_drop")]
+    pub deliveries: SnoozedDeliveries,
+}
+
+impl Snooze {
+    /// Whether this still keeps the workflow quiet at `now`.
+    pub fn is_active(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        (self.until.is_some() || self.runs.is_some())
+            && self.until.is_none_or(|until| until > now)
+            && self.runs != Some(0)
+    }
+}
+
+/// What a snoozed webhook workflow does with a delivery.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnoozedDeliveries {
+    /// Discards it, as a paused workflow does. An alert raised during planned
+    /// maintenance is usually one nobody wants filed afterwards either.
+    #[default]
+    Drop,
+
+    /// Keeps it, and handles it once the snooze ends. Only a snooze with an
+    /// end time can hold deliveries, because one measured in runs is measured
+    /// in the deliveries themselves.
+    Hold,
+}
+
+impl SnoozedDeliveries {
+    fn is_drop(&self) -> bool {
+        *self == Self::Drop
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn a_snooze_ends_at_its_time_or_after_its_runs_whichever_is_first() {
+        let now = chrono::Utc::now();
+        let later = now + chrono::Duration::hours(2);
+
+        let timed = Snooze {
+            until: Some(later),
+            ..Snooze::default()
+        };
+        assert!(timed.is_active(now));
+        assert!(
+            !timed.is_active(later),
+            "it has ended at the moment it names"
+        );
+
+        let counted = Snooze {
+            until: Some(later),
+            runs: Some(0),
+            ..Snooze::default()
+        };
+        assert!(
+            !counted.is_active(now),
+            "its runs are used up before its time"
+        );
+
+        assert!(
+            !Snooze::default().is_active(now),
+            "a snooze with no end is a pause, which is what enabled is for",
+        );
+    }
+
     #[test]
     fn a_trigger_names_the_partition_holding_its_configurations() {
         assert_eq!(
@@ -430,6 +585,13 @@ mod tests {
             .partition(),
             "webhooks/github"
         );
+        assert_eq!(
+            WorkflowTrigger::Event {
+                source: "workflows".into()
+            }
+            .partition(),
+            "events/workflows"
+        );
     }
 
     #[test]
@@ -479,6 +641,7 @@ mod tests {
                 connection_kind: None,
             },
             FieldKind::Cron,
+            FieldKind::Script { placeholder: None },
             FieldKind::Filter { fields: vec![] },
         ];
 
@@ -624,16 +787,37 @@ mod tests {
             config: serde_json::json!({}),
             schedule: Some("@daily".into()),
             webhook_path: None,
+            limits: None,
             resettable: true,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
             last_run: None,
             next_run: None,
+            snooze: None,
             health: None,
         };
 
         let json = serde_json::to_value(&workflow).unwrap();
         assert_eq!(json["type"], "rss");
         assert!(json.get("type_id").is_none());
+        assert!(
+            json.get("limits").is_none(),
+            "a workflow without an address has nothing to limit",
+        );
+    }
+
+    #[test]
+    fn webhook_limits_only_write_what_was_set() {
+        let limits = WebhookLimits {
+            per_minute: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&limits).unwrap(),
+            serde_json::json!({ "per_minute": 10 })
+        );
+        assert!(WebhookLimits::default().is_default());
+        assert!(!limits.is_default());
     }
 }
@@ -13,9 +13,13 @@
 //! a demo branch of its own — and why a page cannot accidentally leave one out.
 
 use automate_api::{
-    Account, AdminUser, AuditRecord, Connection, ConnectionSummary, IntegrationInfo, KeyValueEntry,
-    OptionItem, QueueMessage, RunState, Workflow, WorkflowTypeDescriptor,
+    Account, AdminUser, AuditRecord, Connection, ConnectionSummary, DeliveryRecord,
+    DeliverySummary, EvaluateFilter, FilterEvaluation, InstantiateTemplate, IntegrationInfo,
+    KeyValueEntry, NotificationDestination, OptionItem, QueueMessage, RevisionDiff, RunHistory,
+    SaveAsTemplate, Snooze, WebhookLimits, Workflow, WorkflowPreview, WorkflowRevision,
+    WorkflowTemplate, WorkflowTypeDescriptor,
 };
+use chrono::{DateTime, SecondsFormat, Utc};
 use gloo_net::http::{Request, Response};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -374,6 +378,35 @@ pub async fn delete_service_connection(id: &str) -> Result<(), ApiError> {
     delete(&format!("/connections/{}", urlencode(id))).await
 }
 
+/// Where this account is told that something of theirs has stopped working, if
+/// anywhere.
+pub async fn notification_destination() -> Result<Option<NotificationDestination>, ApiError> {
+    demo!(Ok(fixtures::notification_destination()));
+
+    let resp = send::<()>(Verb::Get, "/notifications", None).await?;
+    if resp.status() == 204 {
+        return Ok(None);
+    }
+
+    json_response(resp).await.map(Some)
+}
+
+/// Replaces where this account is told that something has stopped working.
+pub async fn set_notification_destination(
+    destination: &NotificationDestination,
+) -> Result<NotificationDestination, ApiError> {
+    demo!(fixtures::set_notification_destination(Some(destination.clone())); Ok(destination.clone()));
+
+    json_response(send(Verb::Put, "/notifications", Some(destination)).await?).await
+}
+
+/// Stops telling this account about anything.
+pub async fn clear_notification_destination() -> Result<(), ApiError> {
+    demo!(fixtures::set_notification_destination(None); Ok(()));
+
+    delete("/notifications").await
+}
+
 /// The kinds of workflow that can be created, and the form that configures each.
 ///
 /// Fetched rather than compiled in, so a workflow type added to the agent is one
@@ -458,6 +491,69 @@ pub async fn rotate_webhook(id: &str) -> Result<Workflow, ApiError> {
     .await
 }
 
+/// Replaces what a webhook workflow's address accepts, and from whom.
+pub async fn set_webhook_limits(id: &str, limits: &WebhookLimits) -> Result<Workflow, ApiError> {
+    demo!(fixtures::set_webhook_limits(id, limits).ok_or(not_found("workflow")));
+
+    json_response(
+        send(
+            Verb::Put,
+            &format!("/workflows/{}/limits", urlencode(id)),
+            Some(limits),
+        )
+        .await?,
+    )
+    .await
+}
+
+/// Every saved version of a workflow, newest first.
+pub async fn workflow_revisions(id: &str) -> Result<Vec<WorkflowRevision>, ApiError> {
+    demo!(fixtures::workflow_revisions(id).ok_or(not_found("workflow")));
+
+    get_json(&format!("/workflows/{}/revisions", urlencode(id))).await
+}
+
+/// What changed between two of a workflow's revisions.
+///
+/// `to` is the latest revision when left out, and `from` the one saved before
+/// `to`, so asking with neither is asking what the last save changed.
+pub async fn revision_diff(
+    id: &str,
+    from: Option<u32>,
+    to: Option<u32>,
+) -> Result<RevisionDiff, ApiError> {
+    demo!(fixtures::revision_diff(id, from, to).ok_or(not_found("revision")));
+
+    let mut path = format!("/workflows/{}/revisions/diff", urlencode(id));
+    let mut separator = '?';
+
+    if let Some(from) = from {
+        path.push_str(&format!("{separator}from={from}"));
+        separator = '&';
+    }
+
+    if let Some(to) = to {
+        path.push_str(&format!("{separator}to={to}"));
+    }
+
+    get_json(&path).await
+}
+
+/// Puts a workflow back as one of its revisions had it.
+pub async fn restore_revision(id: &str, number: u32) -> Result<Workflow, ApiError> {
+    demo!(fixtures::restore_revision(id, number).ok_or(not_found("revision")));
+
+    json_response(
+        send(
+            Verb::Post,
+            &format!("/workflows/{}/revisions/{number}/restore", urlencode(id)),
+            None::<&()>,
+        )
+        .await?,
+    )
+    .await
+}
+
 /// Removes a workflow.
 pub async fn delete_workflow(id: &str) -> Result<(), ApiError> {
     demo!(fixtures::delete_workflow(id); Ok(()));
@@ -465,15 +561,129 @@ pub async fn delete_workflow(id: &str) -> Result<(), ApiError> {
     delete(&format!("/workflows/{}", urlencode(id))).await
 }
 
-/// How a workflow's most recent runs went, and what they ran on.
+/// How a workflow's recent runs went, and what they ran on, newest first.
+///
+/// Paged: pass the `next` of one page as `before` to read the one after it.
+/// The payloads live here rather than on the workflow itself so that drawing a
+/// list does not fetch every one of them.
+pub async fn workflow_runs(
+    id: &str,
+    before: Option<DateTime<Utc>>,
+) -> Result<RunHistory, ApiError> {
+    demo!(Ok(fixtures::workflow_runs(id, before)));
+
+    let mut path = format!("/workflows/{}/runs", urlencode(id));
+    if let Some(before) = before {
+        path.push_str(&format!(
+            "?before={}",
+            urlencode(&before.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        ));
+    }
+
+    get_json(&path).await
+}
+
+/// The deliveries a webhook workflow was recently sent, newest first, without
+/// their bodies.
+pub async fn workflow_deliveries(id: &str) -> Result<Vec<DeliverySummary>, ApiError> {
+    demo!(Ok(fixtures::workflow_deliveries(id)));
+
+    get_json(&format!("/workflows/{}/deliveries", urlencode(id))).await
+}
+
+/// One delivery a webhook workflow was sent, as it arrived and as it was
+/// handled.
+pub async fn workflow_delivery(id: &str, delivery: &str) -> Result<DeliveryRecord, ApiError> {
+    demo!(fixtures::workflow_delivery(id, delivery).ok_or(not_found("delivery")));
+
+    get_json(&format!(
+        "/workflows/{}/deliveries/{}",
+        urlencode(id),
+        urlencode(delivery)
+    ))
+    .await
+}
+
+/// Runs a kept delivery again, against the workflow as it is saved now.
+pub async fn replay_delivery(id: &str, delivery: &str) -> Result<(), ApiError> {
+    demo!(
+        fixtures::workflow_delivery(id, delivery)
+            .map(|_| ())
+            .ok_or(not_found("delivery"))
+    );
+
+    let resp = send::<()>(
+        Verb::Post,
+        &format!(
+            "/workflows/{}/deliveries/{}/replay",
+            urlencode(id),
+            urlencode(delivery)
+        ),
+        None,
+    )
+    .await?;
+
+    if resp.ok() {
+        Ok(())
+    } else {
+        Err(error_from_response(resp).await)
+    }
+}
+
+/// What replaying a kept delivery would file, without filing it.
+pub async fn preview_delivery_replay(
+    id: &str,
+    delivery: &str,
+) -> Result<WorkflowPreview, ApiError> {
+    demo!(fixtures::preview_delivery_replay(id, delivery).ok_or(not_found("delivery")));
+
+    json_response(
+        send(
+            Verb::Post,
+            &format!(
+                "/workflows/{}/deliveries/{}/replay",
+                urlencode(id),
+                urlencode(delivery)
+            ),
+            Some(&serde_json::json!({ "preview": true })),
+        )
+        .await?,
+    )
+    .await
+}
+
+/// What a run of this configuration would collect and file, without filing it.
 ///
-/// `None` where the workflow has never run. The payloads live here rather than
-/// on the workflow itself so that drawing a list does not fetch every one of
-/// them.
-pub async fn workflow_runs(id: &str) -> Result<Option<RunState>, ApiError> {
-    demo!(Ok(fixtures::workflow_runs(id)));
+/// `id` names the workflow being edited, and is absent for one that has not
+/// been saved; the configuration is sent either way, so that what is previewed
+/// is what is in the form rather than what was last saved.
+pub async fn preview_workflow(
+    id: Option<&str>,
+    type_id: &str,
+    config: &serde_json::Value,
+) -> Result<WorkflowPreview, ApiError> {
+    demo!(Ok(fixtures::preview_workflow(type_id, config)));
+
+    let (path, body) = match id {
+        Some(id) => (
+            format!("/workflows/{}/preview", urlencode(id)),
+            serde_json::json!({ "config": config }),
+        ),
+        None => (
+            "/workflows/preview".to_string(),
+            serde_json::json!({ "type": type_id, "config": config }),
+        ),
+    };
+
+    json_response(send(Verb::Post, &path, Some(&body)).await?).await
+}
 
-    get_json(&format!("/workflows/{}/runs", urlencode(id))).await
+/// What a filter expression makes of a sample payload or of a workflow's items,
+/// broken down into each of its conditions.
+pub async fn evaluate_filter(request: &EvaluateFilter) -> Result<FilterEvaluation, ApiError> {
+    demo!(Ok(fixtures::evaluate_filter(&request.expression)));
+
+    json_response(send(Verb::Post, "/filters/evaluate", Some(request)).await?).await
 }
 
 /// This account's own history, most recent first.
@@ -548,6 +758,109 @@ struct ResetSummary {
     cleared: usize,
 }
 
+/// Creates a copy of a workflow, which starts paused so that it does not file
+/// everything the original already has.
+pub async fn duplicate_workflow(id: &str) -> Result<Workflow, ApiError> {
+    demo!(fixtures::duplicate_workflow(id).ok_or(not_found("workflow")));
+
+    json_response(
+        send(
+            Verb::Post,
+            &format!("/workflows/{}/duplicate", urlencode(id)),
+            None::<&()>,
+        )
+        .await?,
+    )
+    .await
+}
+
+/// Snoozes a workflow until a time or for a number of runs, after which it
+/// carries on by itself.
+pub async fn snooze_workflow(id: &str, snooze: &Snooze) -> Result<Workflow, ApiError> {
+    demo!(
+        fixtures::snooze_workflow(id, snooze)
+            .ok_or(not_found("workflow"))
+            .and_then(|result| result.map_err(ApiError::Server))
+    );
+
+    json_response(
+        send(
+            Verb::Put,
+            &format!("/workflows/{}/snooze", urlencode(id)),
+            Some(snooze),
+        )
+        .await?,
+    )
+    .await
+}
+
+/// Ends a workflow's snooze early, handing over any deliveries it was holding.
+pub async fn wake_workflow(id: &str) -> Result<Workflow, ApiError> {
+    demo!(fixtures::wake_workflow(id).ok_or(not_found("workflow")));
+
+    json_response(
+        send::<()>(
+            Verb::Delete,
+            &format!("/workflows/{}/snooze", urlencode(id)),
+            None,
+        )
+        .await?,
+    )
+    .await
+}
+
+/// Keeps a workflow as a template, asking for the chosen fields each time it is
+/// used.
+pub async fn save_workflow_as_template(
+    id: &str,
+    request: &SaveAsTemplate,
+) -> Result<WorkflowTemplate, ApiError> {
+    demo!(fixtures::save_workflow_as_template(id, request).ok_or(not_found("workflow")));
+
+    json_response(
+        send(
+            Verb::Post,
+            &format!("/workflows/{}/template", urlencode(id)),
+            Some(request),
+        )
+        .await?,
+    )
+    .await
+}
+
+/// This account's templates, followed by those published for everybody.
+pub async fn list_templates() -> Result<Vec<WorkflowTemplate>, ApiError> {
+    demo!(Ok(fixtures::templates()));
+
+    get_json("/templates").await
+}
+
+/// Removes one of this account's templates, leaving the workflows created from
+/// it alone.
+pub async fn delete_template(id: &str) -> Result<(), ApiError> {
+    demo!(fixtures::delete_template(id); Ok(()));
+
+    delete(&format!("/templates/{}", urlencode(id))).await
+}
+
+/// Creates a template's workflows with the given values.
+pub async fn instantiate_template(
+    id: &str,
+    request: &InstantiateTemplate,
+) -> Result<Vec<Workflow>, ApiError> {
+    demo!(fixtures::instantiate_template(id, request).map_err(ApiError::Server));
+
+    json_response(
+        send(
+            Verb::Post,
+            &format!("/templates/{}/instantiate", urlencode(id)),
+            Some(request),
+        )
+        .await?,
+    )
+    .await
+}
+
 /// The choices a picker should offer, fetched through a linked account.
 pub async fn list_connection_options(
     connection: &str,
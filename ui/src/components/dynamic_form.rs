@@ -101,6 +101,11 @@ pub struct DynamicFormProps {
 
     #[prop_or_default]
     pub disabled: bool,
+
+    /// The saved workflow being edited, if there is one, which a filter field
+    /// offers to be tried against.
+    #[prop_or_default]
+    pub workflow: Option<AttrValue>,
 }
 
 #[function_component(DynamicForm)]
@@ -116,6 +121,7 @@ pub fn dynamic_form(props: &DynamicFormProps) -> Html {
                 options={props.options.get(&descriptor.name).cloned().unwrap_or_default()}
                 error={props.errors.get(&descriptor.name).cloned()}
                 disabled={props.disabled}
+                workflow={props.workflow.clone()}
             />
         }
     });
@@ -132,6 +138,7 @@ struct DynamicFieldProps {
     options: Vec<OptionItem>,
     error: Option<String>,
     disabled: bool,
+    workflow: Option<AttrValue>,
 }
 
 #[function_component(DynamicField)]
@@ -205,6 +212,22 @@ fn dynamic_field(props: &DynamicFieldProps) -> Html {
             />
         },
 
+        // Code rather than prose, so it gets a monospaced face and room for
+        // more than the three lines a description is given.
+        FieldKind::Script { placeholder } => html! {
+            <TextArea
+                id={id.clone()}
+                value={value.clone()}
+                onchange={text_update}
+                onblur={text_blur}
+                placeholder={placeholder.clone().map(AttrValue::from)}
+                rows={8}
+                monospace={true}
+                disabled={props.disabled}
+                invalid={invalid}
+            />
+        },
+
         FieldKind::Secret {
             placeholder,
             generator,
@@ -372,6 +395,8 @@ fn dynamic_field(props: &DynamicFieldProps) -> Html {
                 fields={fields.clone()}
                 disabled={props.disabled}
                 invalid={invalid}
+                workflow={props.workflow.clone()}
+                config={Some(props.config.clone())}
             />
         },
     };
@@ -1,14 +1,23 @@
 //! A filter expression editor with immediate syntax and field diagnostics.
+//!
+//! Syntax and field names are checked here as the filter is typed. Whether it
+//! keeps the right things can only be answered by the agent, which can fetch
+//! what a workflow sees, so the editor also offers to try the filter out there
+//! (see [`FilterTrial`]).
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
 
+use automate_api::payload_path::{self, PayloadPath};
+use automate_api::{EvaluateFilter, FilterEvaluation, WorkflowId};
 use filt_rs::{
     BinaryOperator, Expr, ExprVisitor, Filter, FilterValue, Function, Glob, LogicalOperator,
     UnaryOperator,
 };
 use yew::prelude::*;
 
-use crate::components::TextArea;
+use crate::api;
+use crate::components::{Alert, AlertKind, Button, StatusPill, StatusTone, TextArea};
 
 #[derive(Properties, PartialEq)]
 pub struct FilterInputProps {
@@ -29,6 +38,16 @@ pub struct FilterInputProps {
     /// Whether the agent reported a problem with this field.
     #[prop_or_default]
     pub invalid: bool,
+
+    /// The saved workflow this filter belongs to, so that it can be tried
+    /// against what that workflow collects or was last sent.
+    #[prop_or_default]
+    pub workflow: Option<AttrValue>,
+
+    /// The rest of the form the filter is part of, so that a workflow is tried
+    /// with the settings being edited rather than the saved ones.
+    #[prop_or_default]
+    pub config: Option<Rc<serde_json::Value>>,
 }
 
 #[function_component(FilterInput)]
@@ -88,6 +107,204 @@ pub fn filter_input(props: &FilterInputProps) -> Html {
                     <code>{ props.fields.join(", ") }</code>
                 </p>
             }
+            if !props.disabled && !has_syntax_error && !props.value.trim().is_empty() {
+                <FilterTrial
+                    id={props.id.clone()}
+                    expression={props.value.clone()}
+                    workflow={props.workflow.clone()}
+                    config={props.config.clone()}
+                />
+            }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct FilterTrialProps {
+    id: AttrValue,
+    expression: AttrValue,
+    workflow: Option<AttrValue>,
+    config: Option<Rc<serde_json::Value>>,
+}
+
+/// Tries the filter against something real and shows how each of its
+/// conditions came out.
+///
+/// Folded away until it is wanted, since most edits to a filter are small and
+/// the syntax check above is all they need. A workflow that has been saved can
+/// be tried as it stands — what a feed carries now, or the delivery a webhook
+/// workflow last kept — and anything can be tried against a pasted payload.
+#[function_component(FilterTrial)]
+fn filter_trial(props: &FilterTrialProps) -> Html {
+    let sample = use_state(String::new);
+    let outcome = use_state(|| None::<Result<FilterEvaluation, String>>);
+    let busy = use_state(|| false);
+
+    let run = {
+        let (outcome, busy) = (outcome.clone(), busy.clone());
+
+        Callback::from(move |request: EvaluateFilter| {
+            let (outcome, busy) = (outcome.clone(), busy.clone());
+
+            busy.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let found = api::evaluate_filter(&request).await;
+                outcome.set(Some(found.map_err(|err| err.to_string())));
+                busy.set(false);
+            });
+        })
+    };
+
+    let workflow = props
+        .workflow
+        .as_ref()
+        .and_then(|workflow| workflow.parse::<WorkflowId>().ok());
+
+    let on_workflow = {
+        let (run, expression, config) = (
+            run.clone(),
+            props.expression.to_string(),
+            props.config.clone(),
+        );
+
+        Callback::from(move |_| {
+            run.emit(EvaluateFilter {
+                expression: expression.clone(),
+                payload: None,
+                workflow,
+                config: config.as_deref().cloned(),
+            })
+        })
+    };
+
+    let on_sample = {
+        let (run, outcome, expression, sample) = (
+            run.clone(),
+            outcome.clone(),
+            props.expression.to_string(),
+            sample.clone(),
+        );
+
+        Callback::from(move |_| match serde_json::from_str(&sample) {
+            Ok(payload) => run.emit(EvaluateFilter {
+                expression: expression.clone(),
+                payload: Some(payload),
+                workflow: None,
+                config: None,
+            }),
+            Err(err) => outcome.set(Some(Err(format!("The sample is not valid JSON: {err}.")))),
+        })
+    };
+
+    let on_sample_change = {
+        let sample = sample.clone();
+        Callback::from(move |value: String| sample.set(value))
+    };
+
+    html! {
+        <details class="filter-input__trial">
+            <summary>{ "Try it out" }</summary>
+
+            if workflow.is_some() {
+                <p class="filter-input__hint">
+                    { "Runs this workflow as a preview would, with this filter in place of its own. Nothing is filed." }
+                </p>
+                <div class="filter-input__actions">
+                    <Button onclick={on_workflow} busy={*busy} small={true}>
+                        { "Try against this workflow" }
+                    </Button>
+                </div>
+            }
+
+            <TextArea
+                id={format!("{}-sample", props.id)}
+                value={(*sample).clone()}
+                onchange={on_sample_change}
+                placeholder={Some(AttrValue::from(r#"{ "action": "opened", "labels": [{ "name": "bug" }] }"#))}
+                rows={4}
+                monospace={true}
+            />
+            <div class="filter-input__actions">
+                <Button onclick={on_sample} busy={*busy} small={true}>
+                    { "Try against this payload" }
+                </Button>
+            </div>
+
+            {
+                match &*outcome {
+                    Some(Ok(evaluation)) => html! { <TrialOutcome evaluation={evaluation.clone()} /> },
+                    Some(Err(message)) => html! {
+                        <Alert
+                            kind={AlertKind::Error}
+                            title="We could not try this filter."
+                            message={message.clone()}
+                        />
+                    },
+                    None => html! {},
+                }
+            }
+        </details>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TrialOutcomeProps {
+    evaluation: FilterEvaluation,
+}
+
+/// Each item the filter was tried against, with every condition indented
+/// under the one it is part of and marked with how it came out.
+#[function_component(TrialOutcome)]
+fn trial_outcome(props: &TrialOutcomeProps) -> Html {
+    let evaluation = &props.evaluation;
+
+    html! {
+        <div class="filter-input__outcome">
+            if let Some(message) = &evaluation.error {
+                <Alert
+                    kind={AlertKind::Warning}
+                    title="The workflow stopped partway."
+                    message={message.clone()}
+                />
+            }
+
+            if evaluation.items.is_empty() {
+                <p class="filter-input__hint">{ "There was nothing to try the filter against." }</p>
+            }
+
+            { for evaluation.items.iter().map(|item| {
+                let (tone, label) = match (&item.error, item.matched) {
+                    (Some(_), _) => (StatusTone::Error, "Unreadable"),
+                    (None, true) => (StatusTone::Ok, "Matched"),
+                    (None, false) => (StatusTone::Neutral, "Skipped"),
+                };
+
+                html! {
+                    <div class="filter-input__item">
+                        <div class="filter-input__item-heading">
+                            <StatusPill {tone} {label} title={item.error.clone().map(AttrValue::from)} />
+                            <span>{ &item.item }</span>
+                        </div>
+                        <ul class="filter-input__clauses">
+                            { for item.clauses.iter().map(|clause| html! {
+                                <li
+                                    class={classes!(
+                                        "filter-input__clause",
+                                        if clause.matched { "filter-input__clause--held" } else { "filter-input__clause--failed" },
+                                    )}
+                                    style={format!("padding-left: {}rem", clause.depth as f32 * 1.25)}
+                                    title={clause.error.clone()}
+                                >
+                                    <span class="filter-input__clause-mark" aria-label={if clause.matched { "held" } else { "did not hold" }}>
+                                        { if clause.matched { "✓" } else { "✗" } }
+                                    </span>
+                                    <code>{ &clause.expression }</code>
+                                </li>
+                            }) }
+                        </ul>
+                    </div>
+                }
+            }) }
         </div>
     }
 }
@@ -115,7 +332,10 @@ fn analyze_filter(expression: &str, supported_fields: &[String]) -> FilterAnalys
         return FilterAnalysis::Empty;
     }
 
-    let filter = match Filter::new(expression) {
+    // A path that steps into an array, such as `commits[*].modified`, would be
+    // read by the filter language as a property followed by a list, so it is
+    // hidden the same way the agent hides it before the filter is parsed.
+    let filter = match Filter::new(payload_path::embed(expression).as_ref()) {
         Ok(filter) => filter,
         Err(error) => return FilterAnalysis::Invalid(error.to_string()),
     };
@@ -123,17 +343,31 @@ fn analyze_filter(expression: &str, supported_fields: &[String]) -> FilterAnalys
     let mut collector = PropertyCollector::default();
     filter.visit(&mut collector);
 
-    let used_fields: Vec<String> = collector
-        .properties
-        .into_iter()
-        .map(str::to_owned)
-        .collect();
+    // Each field as it was written, beside the plain dotted field it reads, so
+    // that `labels[*].name` counts as the `labels.name` a workflow suggests.
+    let mut fields = BTreeMap::new();
+    for property in collector.properties {
+        let (written, field) = match payload_path::embedded(property) {
+            Some(path) => match PayloadPath::parse(&path) {
+                Ok(parsed) => {
+                    let field = parsed.field();
+                    (path, field)
+                }
+                Err(error) => return FilterAnalysis::Invalid(error.to_string()),
+            },
+            None => (property.to_owned(), property.to_owned()),
+        };
+
+        fields.insert(written, field);
+    }
+
     let supported_fields: BTreeSet<&str> = supported_fields.iter().map(String::as_str).collect();
-    let unsupported_fields = used_fields
+    let unsupported_fields = fields
         .iter()
-        .filter(|field| !supported_fields.contains(field.as_str()))
-        .cloned()
+        .filter(|(_, field)| !supported_fields.contains(field.as_str()))
+        .map(|(written, _)| written.clone())
         .collect();
+    let used_fields = fields.into_keys().collect();
 
     FilterAnalysis::Valid {
         used_fields,
@@ -224,4 +458,33 @@ mod tests {
         ));
         assert_eq!(analyze_filter("  ", &[]), FilterAnalysis::Empty);
     }
+
+    #[test]
+    fn understands_paths_that_step_into_arrays() {
+        let supported = vec!["commits.modified".to_string(), "ref".to_string()];
+
+        assert_eq!(
+            analyze_filter(
+                r#""deploy/app.yaml" in commits[*].modified && commits[0].id != "" && ref in ["a[0]"]"#,
+                &supported,
+            ),
+            FilterAnalysis::Valid {
+                used_fields: vec![
+                    "commits[*].modified".to_string(),
+                    "commits[0].id".to_string(),
+                    "ref".to_string(),
+                ],
+                unsupported_fields: vec!["commits[0].id".to_string()],
+            },
+            "a path should be shown as it was written, and matched without its brackets",
+        );
+    }
+
+    #[test]
+    fn reports_a_malformed_path_as_invalid() {
+        assert!(matches!(
+            analyze_filter(r#"labels[?name ~ "bug"] contains "bug""#, &[]),
+            FilterAnalysis::Invalid(error) if error.contains("should compare a field")
+        ));
+    }
 }
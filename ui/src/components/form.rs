@@ -188,7 +188,8 @@ pub struct TextAreaProps {
     pub invalid: bool,
 
     /// Renders in a monospaced face, for values whose alignment carries meaning
-    /// such as a filter expression.
+    /// such as a filter expression. Such a value is code rather than prose, so
+    /// the browser is also asked not to check its spelling.
     #[prop_or_default]
     pub monospace: bool,
 }
@@ -218,6 +219,7 @@ pub fn text_area(props: &TextAreaProps) -> Html {
                 props.invalid.then_some("field__input--invalid"),
             )}
             rows={props.rows.to_string()}
+            spellcheck={if props.monospace { "false" } else { "true" }}
             value={props.value.clone()}
             placeholder={props.placeholder.clone()}
             disabled={props.disabled}
@@ -19,12 +19,16 @@ mod impersonation;
 mod json_highlight;
 mod layout;
 mod menu_button;
+mod notification_settings;
 mod page_title;
 mod partition_browser;
 mod refresh_button;
 mod secret_input;
+mod snooze_form;
 mod status_pill;
 mod webhook_address;
+mod webhook_limits;
+mod workflow_templates;
 
 pub use admin_shell::{AdminShell, PageActions};
 pub use alert::{Alert, AlertKind};
@@ -45,9 +49,13 @@ pub use impersonation::{ActAsMenu, ImpersonationBanner, ImpersonationNotice};
 pub use json_highlight::JsonHighlight;
 pub use layout::Layout;
 pub use menu_button::{MenuButton, MenuButtonOption};
+pub use notification_settings::NotificationSettings;
 pub use page_title::PageTitle;
 pub use partition_browser::{BrowserEntry, BrowserPartition, PartitionBrowser};
 pub use refresh_button::RefreshButton;
 pub use secret_input::SecretInput;
+pub use snooze_form::SnoozeForm;
 pub use status_pill::{StatusPill, StatusTone};
 pub use webhook_address::WebhookAddress;
+pub use webhook_limits::WebhookLimitsEditor;
+pub use workflow_templates::{SaveTemplateForm, WorkflowTemplates};
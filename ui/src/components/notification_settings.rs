@@ -0,0 +1,373 @@
+//! Where this account is told that something of theirs has stopped working.
+//!
+//! Folded away to a line saying where incidents currently go, like the limits
+//! beneath a webhook address: it is set once, when somebody first wants to hear
+//! about a failure, and then left alone.
+
+use automate_api::{ConnectionId, ConnectionSummary, NotificationDestination};
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{Button, ButtonKind, Field, Select, SelectOption, TextInput};
+
+#[derive(Properties, PartialEq)]
+pub struct NotificationSettingsProps {
+    /// The Todoist accounts incidents could be filed in.
+    pub todoist: Vec<ConnectionSummary>,
+}
+
+/// What is being edited, kept as text until it is saved.
+#[derive(Clone, Default, PartialEq)]
+struct Draft {
+    kind: Option<String>,
+    connection: Option<String>,
+    project: String,
+    url: String,
+    address: String,
+}
+
+impl Draft {
+    fn from(destination: Option<&NotificationDestination>) -> Self {
+        match destination {
+            None => Self::default(),
+            Some(NotificationDestination::Todoist {
+                connection,
+                project,
+                ..
+            }) => Self {
+                kind: Some("todoist".into()),
+                connection: connection.map(|id| id.to_string()),
+                project: project.clone().unwrap_or_default(),
+                ..Self::default()
+            },
+            Some(NotificationDestination::Webhook { url }) => Self {
+                kind: Some("webhook".into()),
+                url: url.clone(),
+                ..Self::default()
+            },
+            Some(NotificationDestination::Email { address }) => Self {
+                kind: Some("email".into()),
+                address: address.clone(),
+                ..Self::default()
+            },
+        }
+    }
+
+    /// The destination this describes, or `None` to be told nowhere.
+    fn destination(&self) -> Result<Option<NotificationDestination>, String> {
+        match self.kind.as_deref() {
+            Some("todoist") => Ok(Some(NotificationDestination::Todoist {
+                connection: match &self.connection {
+                    Some(id) => Some(
+                        id.parse::<ConnectionId>()
+                            .map_err(|_| "Choose one of your Todoist accounts.".to_string())?,
+                    ),
+                    None => None,
+                },
+                project: Some(self.project.trim().to_string()).filter(|p| !p.is_empty()),
+                section: None,
+            })),
+            Some("webhook") if self.url.trim().is_empty() => {
+                Err("Give the address to post to.".into())
+            }
+            Some("webhook") => Ok(Some(NotificationDestination::Webhook {
+                url: self.url.trim().to_string(),
+            })),
+            Some("email") if self.address.trim().is_empty() => {
+                Err("Give the address to send to.".into())
+            }
+            Some("email") => Ok(Some(NotificationDestination::Email {
+                address: self.address.trim().to_string(),
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[function_component(NotificationSettings)]
+pub fn notification_settings(props: &NotificationSettingsProps) -> Html {
+    let current = use_state(|| None::<Option<NotificationDestination>>);
+    let editing = use_state(|| false);
+    let draft = use_state(Draft::default);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    {
+        let (current, error) = (current.clone(), error.clone());
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::notification_destination().await {
+                    Ok(destination) => current.set(Some(destination)),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        });
+    }
+
+    let on_edit = {
+        let (editing, draft, current) = (editing.clone(), draft.clone(), current.clone());
+        Callback::from(move |_| {
+            draft.set(Draft::from(current.as_ref().and_then(Option::as_ref)));
+            editing.set(true);
+        })
+    };
+
+    let on_cancel = {
+        let (editing, error) = (editing.clone(), error.clone());
+        Callback::from(move |_| {
+            error.set(None);
+            editing.set(false);
+        })
+    };
+
+    let on_save = {
+        let (draft, current, busy, error, editing) = (
+            draft.clone(),
+            current.clone(),
+            busy.clone(),
+            error.clone(),
+            editing.clone(),
+        );
+
+        Callback::from(move |_| {
+            let destination = match draft.destination() {
+                Ok(destination) => destination,
+                Err(message) => {
+                    error.set(Some(message));
+                    return;
+                }
+            };
+
+            let (current, busy, error, editing) = (
+                current.clone(),
+                busy.clone(),
+                error.clone(),
+                editing.clone(),
+            );
+            let was_set = current.as_ref().is_some_and(Option::is_some);
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                let saved = match &destination {
+                    Some(destination) => api::set_notification_destination(destination)
+                        .await
+                        .map(Some),
+                    None if was_set => api::clear_notification_destination().await.map(|_| None),
+                    None => Ok(None),
+                };
+
+                match saved {
+                    Ok(destination) => {
+                        current.set(Some(destination));
+                        editing.set(false);
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_kind = {
+        let draft = draft.clone();
+        Callback::from(move |value: Option<String>| {
+            draft.set(Draft {
+                kind: value,
+                ..(*draft).clone()
+            });
+        })
+    };
+    let on_connection = {
+        let draft = draft.clone();
+        Callback::from(move |value: Option<String>| {
+            draft.set(Draft {
+                connection: value,
+                ..(*draft).clone()
+            });
+        })
+    };
+    let on_project = {
+        let draft = draft.clone();
+        Callback::from(move |value: String| {
+            draft.set(Draft {
+                project: value,
+                ..(*draft).clone()
+            });
+        })
+    };
+    let on_url = {
+        let draft = draft.clone();
+        Callback::from(move |value: String| {
+            draft.set(Draft {
+                url: value,
+                ..(*draft).clone()
+            });
+        })
+    };
+
+    let on_address = {
+        let draft = draft.clone();
+        Callback::from(move |value: String| {
+            draft.set(Draft {
+                address: value,
+                ..(*draft).clone()
+            });
+        })
+    };
+
+    let kinds = vec![
+        SelectOption::new("todoist", "A task in Todoist"),
+        SelectOption::new("webhook", "A post to a webhook"),
+        SelectOption::new("email", "An email"),
+    ];
+    let accounts: Vec<SelectOption> = props
+        .todoist
+        .iter()
+        .map(|connection| SelectOption::new(connection.id.to_string(), connection.name.clone()))
+        .collect();
+
+    html! {
+        <div class="notification-settings">
+            if *editing {
+                <Field
+                    label="Tell me by"
+                    id="notifications-kind"
+                    help="Each incident is one message, sent when it starts and closed when it ends."
+                >
+                    <Select
+                        id="notifications-kind"
+                        value={draft.kind.clone().map(AttrValue::from)}
+                        onchange={on_kind}
+                        options={kinds}
+                        placeholder="Nothing"
+                        clearable=true
+                        disabled={*busy}
+                    />
+                </Field>
+
+                if draft.kind.as_deref() == Some("todoist") {
+                    <Field
+                        label="Todoist account"
+                        id="notifications-connection"
+                        help="Leave unset to use your only Todoist account."
+                    >
+                        <Select
+                            id="notifications-connection"
+                            value={draft.connection.clone().map(AttrValue::from)}
+                            onchange={on_connection}
+                            options={accounts}
+                            placeholder="Your only Todoist account"
+                            clearable=true
+                            disabled={*busy}
+                        />
+                    </Field>
+
+                    <Field label="Project" id="notifications-project">
+                        <TextInput
+                            id="notifications-project"
+                            value={draft.project.clone()}
+                            onchange={on_project}
+                            placeholder="Inbox"
+                            disabled={*busy}
+                        />
+                    </Field>
+                }
+
+                if draft.kind.as_deref() == Some("webhook") {
+                    <Field
+                        label="Address"
+                        id="notifications-url"
+                        required=true
+                        help="Receives a JSON document naming the incident, whether it was opened or resolved, and what happened."
+                    >
+                        <TextInput
+                            id="notifications-url"
+                            value={draft.url.clone()}
+                            onchange={on_url}
+                            placeholder="https://"
+                            disabled={*busy}
+                        />
+                    </Field>
+                }
+
+                if draft.kind.as_deref() == Some("email") {
+                    <Field
+                        label="Email address"
+                        id="notifications-address"
+                        required=true
+                        help="Sent through this installation's mail server, which whoever runs it has to have set up."
+                    >
+                        <TextInput
+                            id="notifications-address"
+                            value={draft.address.clone()}
+                            onchange={on_address}
+                            placeholder="you@example.com"
+                            disabled={*busy}
+                        />
+                    </Field>
+                }
+
+                <div class="notification-settings__actions">
+                    <Button kind={ButtonKind::Primary} onclick={on_save} busy={*busy}>
+                        { "Save" }
+                    </Button>
+                    <Button kind={ButtonKind::Subtle} onclick={on_cancel} disabled={*busy}>
+                        { "Cancel" }
+                    </Button>
+                </div>
+
+                if let Some(message) = (*error).clone() {
+                    <p class="notification-settings__error">{ message }</p>
+                }
+            } else {
+                <p class="notification-settings__summary">
+                    {
+                        match &*current {
+                            None => "Loading…".to_string(),
+                            Some(destination) => summary(destination.as_ref(), &props.todoist),
+                        }
+                    }
+                    { " " }
+                    <button class="notification-settings__edit" onclick={on_edit}>
+                        { "Change" }
+                    </button>
+                </p>
+
+                if let Some(message) = (*error).clone() {
+                    <p class="notification-settings__error">{ message }</p>
+                }
+            }
+        </div>
+    }
+}
+
+/// Where incidents go, in one line.
+fn summary(destination: Option<&NotificationDestination>, todoist: &[ConnectionSummary]) -> String {
+    match destination {
+        None => "When a workflow or connection stops working, you are not told anywhere.".into(),
+        Some(NotificationDestination::Todoist {
+            connection,
+            project,
+            ..
+        }) => {
+            let account = connection
+                .and_then(|id| todoist.iter().find(|c| c.id == id))
+                .map(|c| format!(" of {}", c.name))
+                .unwrap_or_default();
+
+            format!(
+                "When a workflow or connection stops working, a task is filed in the {} project{account} in Todoist.",
+                project.as_deref().unwrap_or("Inbox")
+            )
+        }
+        Some(NotificationDestination::Webhook { url }) => {
+            format!("When a workflow or connection stops working, it is posted to {url}.")
+        }
+        Some(NotificationDestination::Email { address }) => {
+            format!("When a workflow or connection stops working, an email is sent to {address}.")
+        }
+    }
+}
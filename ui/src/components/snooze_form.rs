@@ -0,0 +1,193 @@
+//! Silencing a workflow for a while without turning it off.
+//!
+//! Pausing is for "stop this", and is undone by somebody remembering to. A
+//! snooze is for "not now" — a maintenance window, a noisy afternoon — and ends
+//! by itself, so the form asks how long rather than whether.
+
+use automate_api::{Snooze, SnoozedDeliveries, Workflow};
+use chrono::{Duration, Utc};
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{Button, ButtonKind, Field, NumberInput, Select, SelectOption};
+
+#[derive(Properties, PartialEq)]
+pub struct SnoozeFormProps {
+    pub workflow: Workflow,
+
+    /// Whether the workflow is sent deliveries, which are the only thing a
+    /// snooze can hold on to; a missed scheduled run is simply missed.
+    #[prop_or_default]
+    pub receives_deliveries: bool,
+
+    /// Called with the workflow as the agent now describes it.
+    pub on_snoozed: Callback<Workflow>,
+    pub oncancel: Callback<()>,
+}
+
+/// The lengths offered, in hours. Longer than a day is a pause by another name.
+const PERIODS: [(i64, &str); 4] = [
+    (1, "An hour"),
+    (2, "Two hours"),
+    (8, "Eight hours"),
+    (24, "A day"),
+];
+
+#[function_component(SnoozeForm)]
+pub fn snooze_form(props: &SnoozeFormProps) -> Html {
+    // Either a number of hours, or "runs" to count runs instead.
+    let length = use_state(|| Some("2".to_string()));
+    let runs = use_state(|| Some(1_i64));
+    let hold = use_state(|| false);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let counting_runs = length.as_deref() == Some("runs");
+
+    let on_length = {
+        let length = length.clone();
+        Callback::from(move |value: Option<String>| length.set(value))
+    };
+    let on_runs = {
+        let runs = runs.clone();
+        Callback::from(move |value: Option<i64>| runs.set(value))
+    };
+    let on_deliveries = {
+        let hold = hold.clone();
+        Callback::from(move |value: Option<String>| hold.set(value.as_deref() == Some("hold")))
+    };
+
+    let on_save = {
+        let (id, length, runs, hold, busy, error, on_snoozed) = (
+            props.workflow.id.to_string(),
+            length.clone(),
+            runs.clone(),
+            hold.clone(),
+            busy.clone(),
+            error.clone(),
+            props.on_snoozed.clone(),
+        );
+        let receives_deliveries = props.receives_deliveries;
+
+        Callback::from(move |_| {
+            let snooze = match length.as_deref() {
+                Some("runs") => match *runs {
+                    Some(count) if count > 0 => Snooze {
+                        runs: u32::try_from(count).ok(),
+                        ..Snooze::default()
+                    },
+                    _ => {
+                        error.set(Some("Say how many runs to skip.".into()));
+                        return;
+                    }
+                },
+                Some(hours) => match hours.parse::<i64>() {
+                    Ok(hours) => Snooze {
+                        until: Some(Utc::now() + Duration::hours(hours)),
+                        deliveries: if receives_deliveries && *hold {
+                            SnoozedDeliveries::Hold
+                        } else {
+                            SnoozedDeliveries::Drop
+                        },
+                        ..Snooze::default()
+                    },
+                    Err(_) => return,
+                },
+                None => {
+                    error.set(Some("Say how long to snooze for.".into()));
+                    return;
+                }
+            };
+
+            let (id, busy, error, on_snoozed) =
+                (id.clone(), busy.clone(), error.clone(), on_snoozed.clone());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::snooze_workflow(&id, &snooze).await {
+                    Ok(workflow) => on_snoozed.emit(workflow),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_cancel = {
+        let oncancel = props.oncancel.clone();
+        Callback::from(move |_| oncancel.emit(()))
+    };
+
+    let length_id = format!("snooze-length-{}", props.workflow.id);
+    let runs_id = format!("snooze-runs-{}", props.workflow.id);
+    let deliveries_id = format!("snooze-deliveries-{}", props.workflow.id);
+
+    let mut lengths: Vec<SelectOption> = PERIODS
+        .iter()
+        .map(|(hours, label)| SelectOption::new(hours.to_string(), *label))
+        .collect();
+    lengths.push(SelectOption::new("runs", "A number of runs"));
+
+    html! {
+        <div class="workflow__snooze">
+            <Field
+                label="Snooze for"
+                id={length_id.clone()}
+                help="The workflow carries on by itself afterwards, or when you wake it."
+            >
+                <Select
+                    id={length_id}
+                    value={(*length).clone().map(AttrValue::from)}
+                    onchange={on_length}
+                    options={lengths}
+                    disabled={*busy}
+                />
+            </Field>
+
+            if counting_runs {
+                <Field label="Runs to skip" id={runs_id.clone()} required=true>
+                    <NumberInput
+                        id={runs_id}
+                        value={*runs}
+                        onchange={on_runs}
+                        min={Some(1)}
+                        disabled={*busy}
+                    />
+                </Field>
+            } else if props.receives_deliveries {
+                <Field
+                    label="Deliveries meanwhile"
+                    id={deliveries_id.clone()}
+                    help="Kept deliveries are handled when the snooze ends, or as soon as you wake the workflow."
+                >
+                    <Select
+                        id={deliveries_id}
+                        value={Some(AttrValue::from(if *hold { "hold" } else { "drop" }))}
+                        onchange={on_deliveries}
+                        options={vec![
+                            SelectOption::new("drop", "Ignore them"),
+                            SelectOption::new("hold", "Keep them for later"),
+                        ]}
+                        disabled={*busy}
+                    />
+                </Field>
+            }
+
+            <div class="workflow__confirm-actions">
+                <Button kind={ButtonKind::Primary} onclick={on_save} busy={*busy}>
+                    { "Snooze" }
+                </Button>
+                <Button kind={ButtonKind::Subtle} onclick={on_cancel} disabled={*busy}>
+                    { "Cancel" }
+                </Button>
+            </div>
+
+            if let Some(message) = (*error).clone() {
+                <p class="workflow__snooze-error">{ message }</p>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,216 @@
+//! What a webhook-triggered workflow's address will accept.
+//!
+//! Sits beneath the address it qualifies, folded away to a line saying what it
+//! currently allows. Most workflows never need more than the installation's
+//! defaults, and the ones that do are usually being set up against a sender
+//! whose addresses and volume their owner can look up.
+
+use automate_api::WebhookLimits;
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{Button, ButtonKind, Field, NumberInput, TextInput};
+
+#[derive(Properties, PartialEq)]
+pub struct WebhookLimitsEditorProps {
+    /// The workflow these limits belong to.
+    pub workflow: AttrValue,
+
+    /// The limits the agent last reported.
+    pub limits: WebhookLimits,
+
+    /// Invoked once new limits have been saved.
+    pub on_saved: Callback<()>,
+}
+
+#[function_component(WebhookLimitsEditor)]
+pub fn webhook_limits_editor(props: &WebhookLimitsEditorProps) -> Html {
+    let editing = use_state(|| false);
+    let draft = use_state(|| props.limits.clone());
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let on_edit = {
+        let (editing, draft, limits) = (editing.clone(), draft.clone(), props.limits.clone());
+        Callback::from(move |_| {
+            draft.set(limits.clone());
+            editing.set(true);
+        })
+    };
+
+    let on_cancel = {
+        let (editing, error) = (editing.clone(), error.clone());
+        Callback::from(move |_| {
+            error.set(None);
+            editing.set(false);
+        })
+    };
+
+    let on_save = {
+        let (workflow, draft, busy, error, editing, on_saved) = (
+            props.workflow.to_string(),
+            draft.clone(),
+            busy.clone(),
+            error.clone(),
+            editing.clone(),
+            props.on_saved.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (workflow, limits, busy, error, editing, on_saved) = (
+                workflow.clone(),
+                (*draft).clone(),
+                busy.clone(),
+                error.clone(),
+                editing.clone(),
+                on_saved.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::set_webhook_limits(&workflow, &limits).await {
+                    Ok(_) => {
+                        editing.set(false);
+                        on_saved.emit(());
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let id = |name: &str| format!("webhook-limits-{}-{name}", props.workflow);
+
+    let count = |update: fn(&mut WebhookLimits, Option<u32>)| {
+        let draft = draft.clone();
+        Callback::from(move |value: Option<i64>| {
+            let mut next = (*draft).clone();
+            update(&mut next, value.and_then(|value| u32::try_from(value).ok()));
+            draft.set(next);
+        })
+    };
+
+    let on_allow = {
+        let draft = draft.clone();
+        Callback::from(move |value: String| {
+            let mut next = (*draft).clone();
+            next.allow = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            draft.set(next);
+        })
+    };
+
+    html! {
+        <div class="webhook-limits">
+            if *editing {
+                <Field
+                    label="Deliveries a minute"
+                    id={id("per-minute")}
+                    help="How many are accepted a minute, on average. Leave empty for the installation's default, or 0 for no limit."
+                >
+                    <NumberInput
+                        id={id("per-minute")}
+                        value={draft.per_minute.map(i64::from)}
+                        onchange={count(|limits, value| limits.per_minute = value)}
+                        min={Some(0)}
+                        placeholder="The installation's default"
+                        disabled={*busy}
+                    />
+                </Field>
+
+                <Field
+                    label="Burst"
+                    id={id("burst")}
+                    help="How many may arrive at once before the rate applies. Leave empty for a minute's worth."
+                >
+                    <NumberInput
+                        id={id("burst")}
+                        value={draft.burst.map(i64::from)}
+                        onchange={count(|limits, value| limits.burst = value)}
+                        min={Some(1)}
+                        placeholder="A minute's worth"
+                        disabled={*busy}
+                    />
+                </Field>
+
+                <Field
+                    label="Largest body, in KB"
+                    id={id("max-body")}
+                    help="Deliveries larger than this are refused. It cannot be raised above the installation's own limit."
+                >
+                    <NumberInput
+                        id={id("max-body")}
+                        value={draft.max_body_bytes.map(|bytes| i64::from(bytes.div_ceil(1024)))}
+                        onchange={count(|limits, value| {
+                            limits.max_body_bytes = value.map(|kb| kb.saturating_mul(1024));
+                        })}
+                        min={Some(1)}
+                        placeholder="The installation's limit"
+                        disabled={*busy}
+                    />
+                </Field>
+
+                <Field
+                    label="Only accept from"
+                    id={id("allow")}
+                    help={r#"A filter over client_ip and headers.*, such as client_ip in ["192.0.2.10"]. Leave empty to accept anybody holding the address."#}
+                >
+                    <TextInput
+                        id={id("allow")}
+                        value={draft.allow.clone().unwrap_or_default()}
+                        onchange={on_allow}
+                        placeholder="Anybody"
+                        disabled={*busy}
+                    />
+                </Field>
+
+                <div class="webhook-limits__actions">
+                    <Button kind={ButtonKind::Primary} onclick={on_save} busy={*busy}>
+                        { "Save limits" }
+                    </Button>
+                    <Button kind={ButtonKind::Subtle} onclick={on_cancel} disabled={*busy}>
+                        { "Cancel" }
+                    </Button>
+                </div>
+
+                if let Some(message) = (*error).clone() {
+                    <p class="webhook-limits__error">{ message }</p>
+                }
+            } else {
+                <p class="webhook-limits__summary">
+                    { summary(&props.limits) }
+                    { " " }
+                    <button class="webhook-limits__edit" onclick={on_edit}>
+                        { "Change" }
+                    </button>
+                </p>
+            }
+        </div>
+    }
+}
+
+/// What the limits allow, in one line.
+fn summary(limits: &WebhookLimits) -> String {
+    let rate = match limits.per_minute {
+        None => "the installation's rate".to_string(),
+        Some(0) => "any number of deliveries".to_string(),
+        Some(1) => "1 delivery a minute".to_string(),
+        Some(n) => format!("{n} deliveries a minute"),
+    };
+
+    let size = limits
+        .max_body_bytes
+        .map(|bytes| format!(" of up to {} KB", bytes.div_ceil(1024)))
+        .unwrap_or_default();
+
+    let from = if limits.allow.is_some() {
+        "from the senders it allows"
+    } else {
+        "from anybody holding the address"
+    };
+
+    format!("Accepts {rate}{size}, {from}.")
+}
@@ -0,0 +1,544 @@
+//! Saving a workflow as a template, and creating workflows from one.
+//!
+//! A template asks for a few values each time it is used — a feed's address, a
+//! repository's name — and for the linked accounts its workflows file into,
+//! which it never carries itself. Everything else it brings with it, so that
+//! the tenth feed is a line of typing rather than the whole form again.
+
+use std::collections::BTreeMap;
+
+use automate_api::{
+    ConnectionId, ConnectionSummary, FieldKind, InstantiateTemplate, SaveAsTemplate, TemplateScope,
+    Workflow, WorkflowTemplate, WorkflowTypeDescriptor,
+};
+use yew::prelude::*;
+
+use crate::api;
+use crate::components::{
+    Button, ButtonKind, Field, Select, SelectOption, StatusPill, StatusTone, Switch, TextInput,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct WorkflowTemplatesProps {
+    pub types: Vec<WorkflowTypeDescriptor>,
+    pub connections: Vec<ConnectionSummary>,
+
+    /// Changes whenever the page reloads, so a template saved from one of its
+    /// rows shows up here without this list having to be told about it.
+    pub generation: u32,
+
+    /// Called once a template's workflows have been created.
+    pub on_created: Callback<()>,
+}
+
+#[function_component(WorkflowTemplates)]
+pub fn workflow_templates(props: &WorkflowTemplatesProps) -> Html {
+    let templates = use_state(|| None::<Vec<WorkflowTemplate>>);
+    let error = use_state(|| None::<String>);
+    let reload = use_state(|| 0u32);
+
+    {
+        let (templates, error) = (templates.clone(), error.clone());
+        use_effect_with((props.generation, *reload), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::list_templates().await {
+                    Ok(found) => {
+                        templates.set(Some(found));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        });
+    }
+
+    let on_removed = {
+        let reload = reload.clone();
+        Callback::from(move |_| reload.set(*reload + 1))
+    };
+
+    html! {
+        <div class="workflow-templates">
+            if let Some(message) = (*error).clone() {
+                <p class="workflow-templates__error">{ message }</p>
+            }
+
+            {
+                match &*templates {
+                    None => html! { <p class="workflow-templates__empty">{ "Loading…" }</p> },
+                    Some(templates) if templates.is_empty() => html! {
+                        <p class="workflow-templates__empty">
+                            { "Save a workflow as a template from its menu to set up more like it \
+                               without filling in the form again." }
+                        </p>
+                    },
+                    Some(templates) => html! {
+                        <ul class="workflow-templates__list">
+                            { for templates.iter().map(|template| html! {
+                                <TemplateEntry
+                                    key={template.id.to_string()}
+                                    template={template.clone()}
+                                    types={props.types.clone()}
+                                    connections={props.connections.clone()}
+                                    on_created={props.on_created.clone()}
+                                    on_removed={on_removed.clone()}
+                                />
+                            }) }
+                        </ul>
+                    },
+                }
+            }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TemplateEntryProps {
+    template: WorkflowTemplate,
+    types: Vec<WorkflowTypeDescriptor>,
+    connections: Vec<ConnectionSummary>,
+    on_created: Callback<()>,
+    on_removed: Callback<()>,
+}
+
+/// A linked account the template's workflows need, asked for once however many
+/// of them share the field.
+#[derive(Clone, PartialEq)]
+struct AccountField {
+    path: String,
+    label: String,
+    provider: String,
+}
+
+#[function_component(TemplateEntry)]
+fn template_entry(props: &TemplateEntryProps) -> Html {
+    let using = use_state(|| false);
+    let values = use_state(BTreeMap::<String, String>::new);
+    let accounts = use_state(BTreeMap::<String, String>::new);
+    let enabled = use_state(|| true);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+    let notice = use_state(|| None::<String>);
+    let template = &props.template;
+
+    let fields = account_fields(template, &props.types);
+
+    let on_use = {
+        let (using, notice) = (using.clone(), notice.clone());
+        Callback::from(move |_| {
+            notice.set(None);
+            using.set(!*using);
+        })
+    };
+
+    let on_create = {
+        let (id, values, accounts, enabled, busy, error, notice, using, on_created) = (
+            template.id.to_string(),
+            values.clone(),
+            accounts.clone(),
+            enabled.clone(),
+            busy.clone(),
+            error.clone(),
+            notice.clone(),
+            using.clone(),
+            props.on_created.clone(),
+        );
+
+        Callback::from(move |_| {
+            let mut connections = BTreeMap::new();
+            for (path, id) in accounts.iter() {
+                match id.parse::<ConnectionId>() {
+                    Ok(id) => {
+                        connections.insert(path.clone(), id);
+                    }
+                    Err(_) => {
+                        error.set(Some("Choose one of your linked accounts.".into()));
+                        return;
+                    }
+                }
+            }
+
+            let request = InstantiateTemplate {
+                values: (*values).clone(),
+                connections,
+                enabled: *enabled,
+            };
+
+            let (id, busy, error, notice, using, values, on_created) = (
+                id.clone(),
+                busy.clone(),
+                error.clone(),
+                notice.clone(),
+                using.clone(),
+                values.clone(),
+                on_created.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::instantiate_template(&id, &request).await {
+                    Ok(created) => {
+                        values.set(BTreeMap::new());
+                        using.set(false);
+                        notice.set(Some(match created.len() {
+                            1 => "Created 1 workflow.".to_string(),
+                            other => format!("Created {other} workflows."),
+                        }));
+                        on_created.emit(());
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_remove = {
+        let (id, busy, error, on_removed) = (
+            template.id.to_string(),
+            busy.clone(),
+            error.clone(),
+            props.on_removed.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (id, busy, error, on_removed) =
+                (id.clone(), busy.clone(), error.clone(), on_removed.clone());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+
+                match api::delete_template(&id).await {
+                    Ok(()) => on_removed.emit(()),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_enabled = {
+        let enabled = enabled.clone();
+        Callback::from(move |value: bool| enabled.set(value))
+    };
+
+    let kinds: Vec<&str> = template
+        .workflows
+        .iter()
+        .map(|workflow| {
+            props
+                .types
+                .iter()
+                .find(|descriptor| descriptor.id == workflow.type_id)
+                .map_or(workflow.type_id.as_str(), |descriptor| {
+                    descriptor.name.as_str()
+                })
+        })
+        .collect();
+
+    html! {
+        <li class="workflow-template">
+            <div class="workflow-template__summary">
+                <div class="workflow-template__detail">
+                    <span class="workflow-template__name">{ &template.name }</span>
+                    <span class="workflow-template__meta">{ kinds.join(" · ") }</span>
+                    if let Some(description) = &template.description {
+                        <span class="workflow-template__description">{ description }</span>
+                    }
+                </div>
+
+                if template.scope == TemplateScope::Installation {
+                    <StatusPill
+                        tone={StatusTone::Neutral}
+                        label="Shared"
+                        title="Published for everybody by an administrator."
+                    />
+                }
+
+                <Button small=true onclick={on_use} disabled={*busy}>
+                    { if *using { "Close" } else { "Use" } }
+                </Button>
+
+                if template.scope == TemplateScope::Account {
+                    <Button
+                        small=true
+                        kind={ButtonKind::Subtle}
+                        onclick={on_remove}
+                        disabled={*busy}
+                        title="Removes the template. Workflows created from it are kept."
+                    >
+                        { "Remove" }
+                    </Button>
+                }
+            </div>
+
+            if let Some(message) = (*notice).clone() {
+                <p class="workflow__notice" role="status">{ message }</p>
+            }
+
+            if *using {
+                <div class="workflow-template__form">
+                    { for template.parameters.iter().map(|parameter| {
+                        let id = format!("template-{}-{}", template.id, parameter.name);
+                        let onchange = {
+                            let (values, name) = (values.clone(), parameter.name.clone());
+                            Callback::from(move |value: String| {
+                                let mut next = (*values).clone();
+                                next.insert(name.clone(), value);
+                                values.set(next);
+                            })
+                        };
+
+                        html! {
+                            <Field
+                                label={parameter.label.clone().unwrap_or_else(|| parameter.name.clone())}
+                                id={id.clone()}
+                                required={parameter.default.is_none()}
+                                help={parameter.help.clone().map(AttrValue::from)}
+                            >
+                                <TextInput
+                                    {id}
+                                    value={values.get(&parameter.name).cloned().unwrap_or_default()}
+                                    {onchange}
+                                    placeholder={parameter.default.clone().map(AttrValue::from)}
+                                    disabled={*busy}
+                                />
+                            </Field>
+                        }
+                    }) }
+
+                    { for fields.iter().map(|field| {
+                        let id = format!("template-{}-{}", template.id, field.path);
+                        let options: Vec<SelectOption> = props
+                            .connections
+                            .iter()
+                            .filter(|connection| connection.provider == field.provider)
+                            .map(|connection| {
+                                SelectOption::new(connection.id.to_string(), connection.name.clone())
+                            })
+                            .collect();
+                        let onchange = {
+                            let (accounts, path) = (accounts.clone(), field.path.clone());
+                            Callback::from(move |value: Option<String>| {
+                                let mut next = (*accounts).clone();
+                                match value {
+                                    Some(value) => next.insert(path.clone(), value),
+                                    None => next.remove(&path),
+                                };
+                                accounts.set(next);
+                            })
+                        };
+
+                        html! {
+                            <Field label={field.label.clone()} id={id.clone()}>
+                                <Select
+                                    {id}
+                                    value={accounts.get(&field.path).cloned().map(AttrValue::from)}
+                                    {onchange}
+                                    {options}
+                                    placeholder="Choose one of your accounts"
+                                    clearable=true
+                                    disabled={*busy}
+                                />
+                            </Field>
+                        }
+                    }) }
+
+                    <Switch
+                        id={format!("template-{}-enabled", template.id)}
+                        checked={*enabled}
+                        onchange={on_enabled}
+                        label="Start running straight away"
+                        disabled={*busy}
+                    />
+
+                    <div class="workflow-template__actions">
+                        <Button kind={ButtonKind::Primary} onclick={on_create} busy={*busy}>
+                            { match template.workflows.len() {
+                                1 => "Create workflow".to_string(),
+                                other => format!("Create {other} workflows"),
+                            } }
+                        </Button>
+                    </div>
+                </div>
+            }
+
+            if let Some(message) = (*error).clone() {
+                <p class="workflow-templates__error">{ message }</p>
+            }
+        </li>
+    }
+}
+
+/// The linked accounts a template's workflows file into, by the path of the
+/// field each is chosen in.
+fn account_fields(
+    template: &WorkflowTemplate,
+    types: &[WorkflowTypeDescriptor],
+) -> Vec<AccountField> {
+    let mut fields: Vec<AccountField> = Vec::new();
+
+    for workflow in &template.workflows {
+        let Some(descriptor) = types.iter().find(|t| t.id == workflow.type_id) else {
+            continue;
+        };
+
+        for field in &descriptor.fields {
+            if let FieldKind::Connection { provider, .. } = &field.kind
+                && !fields.iter().any(|known| known.path == field.name)
+            {
+                fields.push(AccountField {
+                    path: field.name.clone(),
+                    label: field.label.clone(),
+                    provider: provider.clone(),
+                });
+            }
+        }
+    }
+
+    fields
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SaveTemplateFormProps {
+    pub workflow: Workflow,
+    pub descriptor: WorkflowTypeDescriptor,
+
+    /// Called with the new template's name once it has been saved.
+    pub on_saved: Callback<String>,
+    pub oncancel: Callback<()>,
+}
+
+/// Keeps a workflow as a template, asking which of its fields should be asked
+/// for each time it is used.
+///
+/// Only the fields that hold free text are offered. They are the ones that
+/// differ from one feed or repository to the next; a priority or a yes-or-no is
+/// far more often the thing the template is meant to keep the same.
+#[function_component(SaveTemplateForm)]
+pub fn save_template_form(props: &SaveTemplateFormProps) -> Html {
+    let name = use_state(|| props.workflow.name.clone());
+    let chosen = use_state(Vec::<String>::new);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let candidates: Vec<_> = props
+        .descriptor
+        .fields
+        .iter()
+        .filter(|field| {
+            matches!(
+                field.kind,
+                FieldKind::Text { .. } | FieldKind::TextArea { .. } | FieldKind::Url { .. }
+            )
+        })
+        .collect();
+
+    let on_name = {
+        let name = name.clone();
+        Callback::from(move |value: String| name.set(value))
+    };
+
+    let on_save = {
+        let (id, name, chosen, busy, error, on_saved) = (
+            props.workflow.id.to_string(),
+            name.clone(),
+            chosen.clone(),
+            busy.clone(),
+            error.clone(),
+            props.on_saved.clone(),
+        );
+
+        Callback::from(move |_| {
+            let request = SaveAsTemplate {
+                name: name.trim().to_string(),
+                description: None,
+                parameters: (*chosen).clone(),
+            };
+
+            let (id, busy, error, on_saved) =
+                (id.clone(), busy.clone(), error.clone(), on_saved.clone());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::save_workflow_as_template(&id, &request).await {
+                    Ok(template) => on_saved.emit(template.name),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_cancel = {
+        let oncancel = props.oncancel.clone();
+        Callback::from(move |_| oncancel.emit(()))
+    };
+
+    let name_id = format!("template-name-{}", props.workflow.id);
+
+    html! {
+        <div class="workflow-template__form">
+            <Field label="Template name" id={name_id.clone()} required=true>
+                <TextInput
+                    id={name_id}
+                    value={(*name).clone()}
+                    onchange={on_name}
+                    disabled={*busy}
+                />
+            </Field>
+
+            if !candidates.is_empty() {
+                <p class="workflow-template__hint">
+                    { "Ask for these each time the template is used. The linked accounts are \
+                       always asked for, and secrets are never kept." }
+                </p>
+
+                { for candidates.into_iter().map(|field| {
+                    let path = field.name.clone();
+                    let onchange = {
+                        let (chosen, path) = (chosen.clone(), path.clone());
+                        Callback::from(move |on: bool| {
+                            let mut next: Vec<String> =
+                                chosen.iter().filter(|p| **p != path).cloned().collect();
+                            if on {
+                                next.push(path.clone());
+                            }
+                            chosen.set(next);
+                        })
+                    };
+
+                    html! {
+                        <Switch
+                            id={format!("template-{}-{}", props.workflow.id, path)}
+                            checked={chosen.contains(&path)}
+                            {onchange}
+                            label={AttrValue::from(field.label.clone())}
+                            disabled={*busy}
+                        />
+                    }
+                }) }
+            }
+
+            <div class="workflow-template__actions">
+                <Button kind={ButtonKind::Primary} onclick={on_save} busy={*busy}>
+                    { "Save template" }
+                </Button>
+                <Button kind={ButtonKind::Subtle} onclick={on_cancel} disabled={*busy}>
+                    { "Cancel" }
+                </Button>
+            </div>
+
+            if let Some(message) = (*error).clone() {
+                <p class="workflow-templates__error">{ message }</p>
+            }
+        </div>
+    }
+}
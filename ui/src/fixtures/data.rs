@@ -9,9 +9,13 @@
 
 use automate_api::{
     Account, AdminUser, AuditCategory, AuditOutcome, AuditRecord, Connection, ConnectionId,
-    ConnectionKind, ConnectionStatus, ConnectionSummary, FieldDescriptor, FieldKind,
-    IntegrationInfo, KeyValueEntry, OptionItem, QueueMessage, QueueStatus, RunOutcome, RunReport,
-    RunState, TenantId, Workflow, WorkflowId, WorkflowTrigger, WorkflowTypeDescriptor,
+    ConnectionKind, ConnectionStatus, ConnectionSummary, DeliveryId, DeliveryRecord,
+    FieldDescriptor, FieldKind, FilterClause, FilterEvaluation, IntegrationInfo, KeyValueEntry,
+    NotificationDestination, OptionItem, PreviewItem, PreviewTask, QueueMessage, QueueStatus,
+    RunCounts, RunLog, RunLogLevel, RunLogLine, RunOutcome, RunReport, RunState, RunTrigger,
+    SignatureVerdict, Snooze, SnoozedDeliveries, TemplateId, TemplateParameter, TemplateScope,
+    TemplateWorkflow, TenantId, Workflow, WorkflowId, WorkflowPreview, WorkflowRevision,
+    WorkflowTemplate, WorkflowTrigger, WorkflowTypeDescriptor,
 };
 use chrono::{Duration, Utc};
 use serde_json::json;
@@ -179,6 +183,16 @@ pub fn queue_messages() -> Vec<QueueMessage> {
 
 /// Sample linked accounts, one per credential shape and one per status, so the
 /// connections page shows every badge it can draw.
+/// Incidents filed in the demo's Todoist account, so the settings open on a
+/// destination rather than on nothing.
+pub fn notification_destination() -> Option<NotificationDestination> {
+    Some(NotificationDestination::Todoist {
+        connection: Some(ConnectionId::from_entropy(1)),
+        project: Some("Automate".to_string()),
+        section: None,
+    })
+}
+
 pub fn service_connections() -> Vec<ConnectionSummary> {
     let now = Utc::now();
     vec![
@@ -271,6 +285,28 @@ pub fn workflow_types() -> Vec<WorkflowTypeDescriptor> {
                     },
                 )
                 .with_help("Leave empty to take every item in the feed."),
+                FieldDescriptor::new(
+                    "script",
+                    "Script",
+                    FieldKind::Script {
+                        placeholder: Some("item.title.replace(\"[Sponsored] \", \"\");\nitem".to_string()),
+                    },
+                )
+                .with_help("Optional. Reshapes each entry before it is filtered."),
+                FieldDescriptor::new(
+                    "backfill.mode",
+                    "First run",
+                    FieldKind::Select {
+                        options: vec![
+                            OptionItem::new("all", "Everything currently there"),
+                            OptionItem::new("skip", "Nothing, only what comes next"),
+                            OptionItem::new("newest", "Only the newest few"),
+                            OptionItem::new("since", "Everything since a date"),
+                        ],
+                    },
+                )
+                .with_help("What the first run files, and the next run after a reset.")
+                .with_default("all"),
                 FieldDescriptor::new(
                     "todoist.connection",
                     "Todoist account",
@@ -449,6 +485,62 @@ pub fn workflow_types() -> Vec<WorkflowTypeDescriptor> {
                 ),
             ],
         },
+        WorkflowTypeDescriptor {
+            id: "event".to_string(),
+            name: "Workflow Event".to_string(),
+            description: "Files a task when another of your workflows fails, files a task, or emits an event of its own.".to_string(),
+            documentation: String::new(),
+            trigger: WorkflowTrigger::Event {
+                source: "workflows".to_string(),
+            },
+            fields: vec![
+                FieldDescriptor::new(
+                    "name",
+                    "Name",
+                    FieldKind::Text {
+                        placeholder: Some("Failed releases".to_string()),
+                    },
+                )
+                .required(),
+                FieldDescriptor::new(
+                    "event",
+                    "Event",
+                    FieldKind::Text {
+                        placeholder: Some("run.failed".to_string()),
+                    },
+                )
+                .with_default("run.failed")
+                .required(),
+                FieldDescriptor::new(
+                    "from",
+                    "From workflow",
+                    FieldKind::Text {
+                        placeholder: Some("copper-tiger-canyon".to_string()),
+                    },
+                )
+                .with_help("Optional. Leave it empty to follow the event from any of your workflows."),
+                FieldDescriptor::new(
+                    "filter",
+                    "Filter",
+                    FieldKind::Filter {
+                        fields: vec![
+                            "name".to_string(),
+                            "workflow".to_string(),
+                            "payload.input".to_string(),
+                            "payload.message".to_string(),
+                        ],
+                    },
+                ),
+                FieldDescriptor::new(
+                    "title",
+                    "Task title",
+                    FieldKind::Text {
+                        placeholder: Some("A workflow failed: ${{ payload.message }}".to_string()),
+                    },
+                )
+                .required(),
+            ],
+        },
     ]
 }
 
@@ -478,11 +570,13 @@ pub fn workflows() -> Vec<Workflow> {
             }),
             schedule: Some("0 */6 * * *".to_string()),
             webhook_path: None,
+            limits: None,
             resettable: true,
             created_at: now - Duration::days(90),
             updated_at: now - Duration::days(3),
             last_run: Some(now - Duration::hours(2)),
             next_run: Some(now + Duration::hours(4)),
+            snooze: None,
             health: health(WorkflowId::from_entropy(1)),
         },
         Workflow {
@@ -500,11 +594,13 @@ pub fn workflows() -> Vec<Workflow> {
             }),
             schedule: Some("*/15 * * * *".to_string()),
             webhook_path: None,
+            limits: None,
             resettable: true,
             created_at: now - Duration::days(45),
             updated_at: now - Duration::days(1),
             last_run: Some(now - Duration::days(1)),
             next_run: None,
+            snooze: None,
             health: health(WorkflowId::from_entropy(2)),
         },
         Workflow {
@@ -519,16 +615,135 @@ pub fn workflows() -> Vec<Workflow> {
             }),
             schedule: None,
             webhook_path: Some("/webhooks/github/8f14e45fceea167a5a36dedd4bea2543".to_string()),
+            limits: Some(automate_api::WebhookLimits {
+                per_minute: Some(30),
+                allow: Some(r#"client_ip startswith "140.82.""#.to_string()),
+                ..Default::default()
+            }),
             resettable: false,
             created_at: now - Duration::days(7),
             updated_at: now - Duration::days(7),
             last_run: Some(now - Duration::minutes(20)),
             next_run: None,
+            // Quiet for a maintenance window, holding what arrives meanwhile.
+            snooze: Some(Snooze {
+                until: Some(now + Duration::minutes(90)),
+                runs: None,
+                deliveries: SnoozedDeliveries::Hold,
+            }),
             health: health(WorkflowId::from_entropy(3)),
         },
+        Workflow {
+            id: WorkflowId::from_entropy(4),
+            type_id: "event".to_string(),
+            name: "Failed releases".to_string(),
+            enabled: true,
+            config: json!({
+                "name": "Failed releases",
+                "event": "run.failed",
+                "from": WorkflowId::from_entropy(3).to_string(),
+                "title": "The release webhook failed: ${{ payload.message }}"
+            }),
+            schedule: None,
+            webhook_path: None,
+            limits: None,
+            resettable: false,
+            created_at: now - Duration::days(2),
+            updated_at: now - Duration::days(2),
+            last_run: None,
+            next_run: None,
+            snooze: None,
+            health: health(WorkflowId::from_entropy(4)),
+        },
     ]
 }
 
+/// The versions each workflow was saved as, oldest first.
+///
+/// Every workflow starts with the version it was created as, which is its
+/// current one unless it has been edited since. Two have: the feed's filter
+/// was narrowed to releases, and the notifications were paused, so the history
+/// tab has a change of each kind to compare.
+/// The templates an account starts with: one an administrator has published,
+/// so the list shows the kind nobody here can remove.
+pub fn templates() -> Vec<WorkflowTemplate> {
+    let now = Utc::now();
+
+    vec![WorkflowTemplate {
+        id: TemplateId::from_entropy(1),
+        name: "Project release notes".to_string(),
+        description: Some(
+            "Files a task for each post on a project's blog that mentions a release.".to_string(),
+        ),
+        scope: TemplateScope::Installation,
+        parameters: vec![TemplateParameter {
+            name: "feed".to_string(),
+            label: Some("Blog feed".to_string()),
+            help: Some("The address of the project's RSS or Atom feed.".to_string()),
+            default: None,
+        }],
+        workflows: vec![TemplateWorkflow {
+            type_id: "rss".to_string(),
+            config: json!({
+                "feed": { "url": "${{ params.feed }}" },
+                "filter": "title contains \"release\"",
+                "todoist": { "priority": 2 },
+                "include_summary": true
+            }),
+            schedule: Some("0 */6 * * *".to_string()),
+        }],
+        created_at: now - Duration::days(30),
+        updated_at: now - Duration::days(30),
+    }]
+}
+
+pub fn workflow_revisions() -> Vec<(WorkflowId, Vec<WorkflowRevision>)> {
+    let saved = |number: u32, workflow: &Workflow, at, by: Option<&str>| WorkflowRevision {
+        number,
+        config: workflow.config.clone(),
+        schedule: workflow.schedule.clone(),
+        enabled: workflow.enabled,
+        saved_at: at,
+        saved_by: by.map(str::to_string),
+        restored_from: None,
+    };
+
+    workflows()
+        .iter()
+        .map(|workflow| {
+            let current = saved(2, workflow, workflow.updated_at, Some("demo"));
+
+            let history = match workflow.type_id.as_str() {
+                "rss" => {
+                    let mut config = workflow.config.clone();
+                    config["todoist"]["priority"] = json!(1);
+                    if let Some(config) = config.as_object_mut() {
+                        config.remove("filter");
+                    }
+
+                    vec![
+                        WorkflowRevision {
+                            config,
+                            ..saved(1, workflow, workflow.created_at, Some("demo"))
+                        },
+                        current,
+                    ]
+                }
+                "github_notifications" => vec![
+                    WorkflowRevision {
+                        enabled: true,
+                        ..saved(1, workflow, workflow.created_at, Some("demo"))
+                    },
+                    current,
+                ],
+                _ => vec![saved(1, workflow, workflow.created_at, Some("demo"))],
+            };
+
+            (workflow.id, history)
+        })
+        .collect()
+}
+
 /// A history of the things worth keeping: a workflow whose health changed, a
 /// delivery that was turned away, and the changes somebody made by hand.
 ///
@@ -639,80 +854,375 @@ pub fn audit() -> Vec<AuditRecord> {
 
 /// What each workflow's last runs looked like.
 ///
-/// Between them: one that is failing and shows the payload it failed on, one
-/// that is working but failed earlier in the night, and one that has never run.
+/// Derived from [`workflow_history`] the way the agent keeps it, so the panel's
+/// summary and its chart cannot disagree.
 pub fn workflow_runs(workflow: &str) -> Option<RunState> {
+    let history = workflow_history(workflow);
+    let last = history.first()?.clone();
+
+    Some(RunState {
+        last,
+        last_failure: history
+            .iter()
+            .find(|run| run.outcome == RunOutcome::Failed)
+            .cloned(),
+        consecutive_failures: history
+            .iter()
+            .filter(|run| run.outcome != RunOutcome::Discarded)
+            .take_while(|run| run.outcome == RunOutcome::Failed)
+            .count() as u32,
+    })
+}
+
+/// Each workflow's recent runs, newest first.
+///
+/// Between them: a feed that has been failing for the last three runs after a
+/// day of working, with the payload it failed on; a webhook that is working but
+/// failed earlier in the night, with a replay and a run somebody asked for; and
+/// one that has never run.
+pub fn workflow_history(workflow: &str) -> Vec<RunReport> {
     let now = Utc::now();
 
     let report = |finished: chrono::DateTime<Utc>,
+                  took: i64,
                   outcome,
                   message: Option<&str>,
                   input: Option<serde_json::Value>| RunReport {
-        started_at: finished - Duration::milliseconds(840),
+        started_at: finished - Duration::milliseconds(took),
         finished_at: finished,
         outcome,
         message: message.map(ToString::to_string),
         input,
+        replay_of: None,
+        trigger: Some(RunTrigger::Schedule),
+        counts: RunCounts::default(),
+        log: RunLog::default(),
     };
 
-    let rss = report(
-        now - Duration::minutes(12),
-        RunOutcome::Failed,
-        Some(
-            "The feed at https://blog.sierrasoftworks.com/feed.xml did not respond within 30 seconds.",
-        ),
-        Some(json!({
-            "feed": { "url": "https://blog.sierrasoftworks.com/feed.xml" },
-            "include_summary": true,
-        })),
-    );
+    let feed = json!({
+        "feed": { "url": "https://blog.sierrasoftworks.com/feed.xml" },
+        "include_summary": true,
+    });
 
     // A delivery, redacted the way the agent redacts one before storing it.
-    let delivery = |action: &str, finished, outcome, message: Option<&str>| {
-        report(
-            finished,
-            outcome,
-            message,
-            Some(json!({
-                "workflow": WorkflowId::from_entropy(3).to_string(),
-                "event": {
-                    "headers": {
-                        "x-github-event": "release",
-                        "x-hub-signature-256": "<redacted>",
-                        "content-type": "application/json",
-                    },
-                    "body": format!("{{\"action\":\"{action}\",\"release\":{{\"tag_name\":\"v2.0.2\"}}}}"),
+    let delivery = |action: &str| {
+        json!({
+            "workflow": WorkflowId::from_entropy(3).to_string(),
+            "event": {
+                "headers": {
+                    "x-github-event": "release",
+                    "x-hub-signature-256": "<redacted>",
+                    "content-type": "application/json",
                 },
-            })),
-        )
+                "body": format!("{{\"action\":\"{action}\",\"release\":{{\"tag_name\":\"v2.0.2\"}}}}"),
+            },
+        })
     };
 
     match workflow {
-        id if id == WorkflowId::from_entropy(1).to_string() => Some(RunState {
-            last: rss.clone(),
-            last_failure: Some(rss),
-            consecutive_failures: 3,
-        }),
-        id if id == WorkflowId::from_entropy(3).to_string() => Some(RunState {
-            last: delivery(
+        // Every quarter of an hour, timing out on the last three.
+        id if id == WorkflowId::from_entropy(1).to_string() => (0..40)
+            .map(|n: i64| {
+                let finished = now - Duration::minutes(12 + 15 * n);
+                if n < 3 {
+                    let mut run = report(
+                        finished,
+                        30_000,
+                        RunOutcome::Failed,
+                        Some(
+                            "The feed at https://blog.sierrasoftworks.com/feed.xml did not respond within 30 seconds.",
+                        ),
+                        Some(feed.clone()),
+                    );
+                    run.log = RunLog {
+                        lines: vec![RunLogLine {
+                            at: finished - Duration::seconds(15),
+                            level: RunLogLevel::Warn,
+                            message: "The feed is taking a while to answer, so we are still waiting for it."
+                                .into(),
+                            fields: [(
+                                "feed.url".to_string(),
+                                "https://blog.sierrasoftworks.com/feed.xml".to_string(),
+                            )]
+                            .into(),
+                        }],
+                        dropped: 0,
+                    };
+                    return run;
+                }
+
+                let mut run = report(
+                    finished,
+                    600 + (n * 137) % 900,
+                    RunOutcome::Succeeded,
+                    None,
+                    Some(feed.clone()),
+                );
+                let published = u32::from(n % 9 == 4);
+                run.counts = RunCounts {
+                    items: 10,
+                    matched: published,
+                    published,
+                };
+                if n == 7 {
+                    run.trigger = Some(RunTrigger::Manual);
+                }
+                run
+            })
+            .collect(),
+        id if id == WorkflowId::from_entropy(3).to_string() => {
+            let filed = RunCounts {
+                items: 1,
+                matched: 1,
+                published: 1,
+            };
+
+            let mut runs: Vec<RunReport> = (0..14)
+                .map(|n: i64| {
+                    let mut run = report(
+                        now - Duration::hours(1) - Duration::minutes(37 * n),
+                        180 + (n * 53) % 400,
+                        RunOutcome::Succeeded,
+                        None,
+                        Some(delivery(if n % 3 == 0 { "published" } else { "created" })),
+                    );
+                    run.trigger = Some(RunTrigger::Webhook);
+                    run.counts = if n % 3 == 0 {
+                        filed
+                    } else {
+                        RunCounts {
+                            items: 1,
+                            ..Default::default()
+                        }
+                    };
+                    run
+                })
+                .collect();
+
+            runs[3].trigger = Some(RunTrigger::Replay);
+            runs[3].replay_of = Some(DeliveryId::from_entropy(12));
+
+            let failure = &mut runs[13];
+            failure.outcome = RunOutcome::Failed;
+            failure.message = Some("Todoist refused the request: 403 Forbidden.".into());
+            failure.input = Some(delivery("published"));
+            failure.counts = filed;
+
+            runs
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The deliveries each webhook workflow was recently sent, newest first.
+///
+/// Between them: a release that was filed, one the filter skipped, one turned
+/// away because its signature did not match, which is the usual reason a sender
+/// insists it is delivering and nothing arrives, a retry of the filed one that
+/// was dropped, and a replay of the skipped one once the filter had been
+/// changed to take it.
+pub fn workflow_deliveries(workflow: &str) -> Vec<DeliveryRecord> {
+    if workflow != WorkflowId::from_entropy(3).to_string() {
+        return Vec::new();
+    }
+
+    let now = Utc::now();
+
+    let delivery = |entropy: u64,
+                    received: chrono::DateTime<Utc>,
+                    action: &str,
+                    signature: SignatureVerdict| {
+        let body = format!(
+            "{{\"action\":\"{action}\",\"release\":{{\"tag_name\":\"v2.0.2\",\"name\":\"Automate v2.0.2\"}},\"repository\":{{\"full_name\":\"SierraSoftworks/automate\"}}}}"
+        );
+
+        DeliveryRecord {
+            id: DeliveryId::from_entropy(entropy),
+            received_at: received,
+            finished_at: received + Duration::milliseconds(310),
+            outcome: RunOutcome::Succeeded,
+            message: None,
+            headers: [
+                ("content-type", "application/json"),
+                ("user-agent", "GitHub-Hookshot/a1b2c3d"),
+                ("x-github-delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"),
+                ("x-github-event", "release"),
+                ("x-hub-signature-256", "<redacted>"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+            query: String::new(),
+            body,
+            truncated_from: None,
+            signature: Some(signature),
+            items: Vec::new(),
+            published: Vec::new(),
+            effects: Vec::new(),
+            replay_of: None,
+            repeat_of: None,
+        }
+    };
+
+    let item = |action: &str, matched| PreviewItem {
+        item: format!("SierraSoftworks/automate v2.0.2 ({action})"),
+        matched,
+        error: None,
+        clauses: Vec::new(),
+    };
+
+    vec![
+        // The delivery below that the filter skipped, run again once the
+        // filter had been widened to take drafts as well. A replay has no
+        // signature left to check, so it carries no verdict.
+        DeliveryRecord {
+            items: vec![item("created", true)],
+            published: vec![PreviewTask {
+                job: "todoist/create-task".to_string(),
+                title: Some("Automate v2.0.2 was drafted".to_string()),
+                description: Some(
+                    "https://github.com/SierraSoftworks/automate/releases/tag/v2.0.2".to_string(),
+                ),
+                payload: json!({
+                    "title": "Automate v2.0.2 was drafted",
+                    "description": "https://github.com/SierraSoftworks/automate/releases/tag/v2.0.2",
+                    "project": "Releases",
+                }),
+            }],
+            signature: None,
+            replay_of: Some(DeliveryId::from_entropy(12)),
+            ..delivery(
+                14,
+                now - Duration::minutes(20),
+                "created",
+                SignatureVerdict::Verified,
+            )
+        },
+        // GitHub retrying the release below after a slow response. Dropped
+        // before it was queued, so the filter never saw it.
+        DeliveryRecord {
+            message: Some(format!(
+                "Dropped, because the sender already sent the delivery '72d3162e-cc78-11e3-81ab-4c9367dc0958' at {}.",
+                (now - Duration::hours(1)).to_rfc3339()
+            )),
+            signature: None,
+            repeat_of: Some("72d3162e-cc78-11e3-81ab-4c9367dc0958".to_string()),
+            ..delivery(
+                15,
+                now - Duration::hours(1) + Duration::seconds(10),
                 "published",
+                SignatureVerdict::Verified,
+            )
+        },
+        DeliveryRecord {
+            items: vec![item("published", true)],
+            published: vec![PreviewTask {
+                job: "todoist/create-task".to_string(),
+                title: Some("Automate v2.0.2 was released".to_string()),
+                description: Some(
+                    "https://github.com/SierraSoftworks/automate/releases/tag/v2.0.2".to_string(),
+                ),
+                payload: json!({
+                    "title": "Automate v2.0.2 was released",
+                    "description": "https://github.com/SierraSoftworks/automate/releases/tag/v2.0.2",
+                    "project": "Releases",
+                }),
+            }],
+            ..delivery(
+                11,
                 now - Duration::hours(1),
-                RunOutcome::Succeeded,
-                None,
-            ),
-            last_failure: Some(delivery(
+                "published",
+                SignatureVerdict::Verified,
+            )
+        },
+        DeliveryRecord {
+            items: vec![item("created", false)],
+            ..delivery(
+                12,
+                now - Duration::hours(1) - Duration::seconds(4),
                 "created",
-                now - Duration::hours(9),
-                RunOutcome::Failed,
-                Some("Todoist refused the request: 403 Forbidden."),
-            )),
-            consecutive_failures: 0,
-        }),
-        _ => None,
-    }
+                SignatureVerdict::Verified,
+            )
+        },
+        delivery(
+            13,
+            now - Duration::hours(26),
+            "published",
+            SignatureVerdict::Rejected {
+                reason: "The signature did not match the body it was sent with.".to_string(),
+            },
+        ),
+    ]
 }
 
 /// The integrations demo mode pretends the agent has configured.
+/// What previewing a workflow finds.
+///
+/// The same three entries whatever is asked, one of which the filter drops and
+/// one of which it could not read, so the panel shows every state an item can
+/// be in. The tasks are titled from the configuration, so an edit made before
+/// previewing visibly reaches the answer.
+pub fn workflow_preview(config: &serde_json::Value) -> WorkflowPreview {
+    let name = config
+        .get("name")
+        .and_then(|name| name.as_str())
+        .unwrap_or("Demo workflow");
+
+    let item = |item: &str, matched: bool, error: Option<&str>| PreviewItem {
+        item: item.to_string(),
+        matched,
+        error: error.map(str::to_string),
+        clauses: Vec::new(),
+    };
+
+    let task = |title: String| PreviewTask {
+        job: "todoist/create-task".to_string(),
+        description: Some("Filed by a preview in demo mode.".to_string()),
+        payload: json!({ "title": title }),
+        title: Some(title),
+    };
+
+    WorkflowPreview {
+        items: vec![
+            item("Release notes for v2.4", true, None),
+            item("Quarterly roadmap", false, None),
+            item("Untitled entry", false, Some("'published' is not a date")),
+        ],
+        tasks: vec![task(format!("[{name}] Release notes for v2.4"))],
+        effects: Vec::new(),
+        replayed: None,
+        error: None,
+    }
+}
+
+/// What a filter being tried out makes of the demo's items.
+///
+/// Demo mode cannot evaluate the expression, so it reports the whole of it as
+/// having kept one item and dropped another, which is enough to review how
+/// both outcomes and their breakdown are drawn.
+pub fn filter_evaluation(expression: &str) -> FilterEvaluation {
+    let item = |item: &str, matched: bool| PreviewItem {
+        item: item.to_string(),
+        matched,
+        error: None,
+        clauses: vec![FilterClause {
+            expression: expression.to_string(),
+            depth: 0,
+            matched,
+            error: None,
+        }],
+    };
+
+    FilterEvaluation {
+        items: vec![
+            item("Release notes for v2.4", true),
+            item("Quarterly roadmap", false),
+        ],
+        replayed: None,
+        error: None,
+    }
+}
+
 pub fn integrations() -> Vec<IntegrationInfo> {
     vec![
         IntegrationInfo {
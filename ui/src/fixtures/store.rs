@@ -15,21 +15,29 @@ use std::cell::RefCell;
 
 use automate_api::{
     Account, AdminUser, AuditRecord, Connection, ConnectionId, ConnectionKind, ConnectionStatus,
-    ConnectionSummary, FieldKind, IntegrationInfo, KeyValueEntry, OptionItem, QueueMessage,
-    QueueStatus, RunState, TenantId, Workflow, WorkflowId, WorkflowTrigger, WorkflowTypeDescriptor,
+    ConnectionSummary, DeliveryRecord, DeliverySummary, FieldKind, FilterEvaluation,
+    InstantiateTemplate, IntegrationInfo, KeyValueEntry, NotificationDestination, OptionItem,
+    QueueMessage, QueueStatus, RevisionDiff, RunHistory, RunReport, SaveAsTemplate, Snooze,
+    SnoozedDeliveries, TemplateId, TemplateParameter, TemplateScope, TemplateWorkflow, TenantId,
+    WebhookLimits, Workflow, WorkflowId, WorkflowPreview, WorkflowRevision, WorkflowTemplate,
+    WorkflowTrigger, WorkflowTypeDescriptor,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 use super::data;
-use crate::components::dynamic_form::value_at;
+use crate::components::dynamic_form::{set_at, value_at};
 
 struct State {
     kv: Vec<KeyValueEntry>,
     queue: Vec<QueueMessage>,
     connections: Vec<ConnectionSummary>,
     workflows: Vec<Workflow>,
+    /// Each workflow's saved versions, oldest first.
+    revisions: Vec<(WorkflowId, Vec<WorkflowRevision>)>,
     accounts: Vec<Account>,
     integration_connections: Vec<(String, Vec<Connection>)>,
+    notifications: Option<NotificationDestination>,
+    templates: Vec<WorkflowTemplate>,
     /// Distinguishes the records created during this session from the fixtures
     /// and from each other.
     next_id: u64,
@@ -42,6 +50,7 @@ impl State {
             queue: data::queue_messages(),
             connections: data::service_connections(),
             workflows: data::workflows(),
+            revisions: data::workflow_revisions(),
             accounts: data::accounts(),
             integration_connections: data::integrations()
                 .into_iter()
@@ -50,6 +59,8 @@ impl State {
                     (integration.id, connections)
                 })
                 .collect(),
+            notifications: data::notification_destination(),
+            templates: data::templates(),
             next_id: 100,
         }
     }
@@ -58,6 +69,43 @@ impl State {
         self.next_id += 1;
         self.next_id
     }
+
+    /// Keeps a revision of a workflow that has just been saved, as the agent
+    /// does on every save.
+    fn remember(
+        &mut self,
+        workflow: &Workflow,
+        saved_by: Option<String>,
+        restored_from: Option<u32>,
+    ) {
+        let index = match self.revisions.iter().position(|(id, _)| *id == workflow.id) {
+            Some(index) => index,
+            None => {
+                self.revisions.push((workflow.id, Vec::new()));
+                self.revisions.len() - 1
+            }
+        };
+
+        let history = &mut self.revisions[index].1;
+        history.push(WorkflowRevision {
+            number: history.last().map_or(1, |latest| latest.number + 1),
+            config: workflow.config.clone(),
+            schedule: workflow.schedule.clone(),
+            enabled: workflow.enabled,
+            saved_at: workflow.updated_at,
+            saved_by,
+            restored_from,
+        });
+    }
+}
+
+/// Who the agent would credit a change to: whoever is signed in, even while
+/// acting as somebody else.
+fn saved_by() -> Option<String> {
+    let user = admin_user();
+    user.impersonated_by
+        .or(user.username)
+        .map(|username| username.to_string())
 }
 
 thread_local! {
@@ -203,6 +251,14 @@ pub fn delete_service_connection(id: &str) {
     });
 }
 
+pub fn notification_destination() -> Option<NotificationDestination> {
+    with(|state| state.notifications.clone())
+}
+
+pub fn set_notification_destination(destination: Option<NotificationDestination>) {
+    with(|state| state.notifications = destination)
+}
+
 pub fn workflow_types() -> Vec<WorkflowTypeDescriptor> {
     data::workflow_types()
 }
@@ -211,9 +267,58 @@ pub fn workflows() -> Vec<Workflow> {
     with(|state| state.workflows.clone())
 }
 
-/// How a workflow's most recent runs went, payloads included.
-pub fn workflow_runs(workflow: &str) -> Option<RunState> {
-    data::workflow_runs(workflow)
+/// A page of a workflow's recent runs, payloads included, paged as the agent
+/// pages them.
+pub fn workflow_runs(workflow: &str, before: Option<DateTime<Utc>>) -> RunHistory {
+    const PAGE: usize = 25;
+
+    let mut runs: Vec<RunReport> = data::workflow_history(workflow)
+        .into_iter()
+        .filter(|run| before.is_none_or(|before| run.started_at < before))
+        .take(PAGE + 1)
+        .collect();
+
+    let next = if runs.len() > PAGE {
+        runs.truncate(PAGE);
+        runs.last().map(|run| run.started_at)
+    } else {
+        None
+    };
+
+    RunHistory {
+        state: data::workflow_runs(workflow),
+        runs,
+        next,
+    }
+}
+
+/// A webhook workflow's recent deliveries, as the agent lists them.
+pub fn workflow_deliveries(workflow: &str) -> Vec<DeliverySummary> {
+    data::workflow_deliveries(workflow)
+        .iter()
+        .map(DeliveryRecord::summary)
+        .collect()
+}
+
+/// One of a webhook workflow's recent deliveries.
+pub fn workflow_delivery(workflow: &str, delivery: &str) -> Option<DeliveryRecord> {
+    data::workflow_deliveries(workflow)
+        .into_iter()
+        .find(|record| record.id.to_string() == delivery)
+}
+
+/// Replays a kept delivery as a preview, which in demo mode files what it
+/// filed the first time.
+pub fn preview_delivery_replay(workflow: &str, delivery: &str) -> Option<WorkflowPreview> {
+    let record = workflow_delivery(workflow, delivery)?;
+
+    Some(WorkflowPreview {
+        items: record.items,
+        tasks: record.published,
+        effects: record.effects,
+        replayed: Some(record.received_at),
+        error: None,
+    })
 }
 
 /// The audit log, narrowed the way the agent narrows it.
@@ -246,6 +351,8 @@ pub fn create_workflow(
         .into_iter()
         .find(|descriptor| descriptor.id == type_id)?;
 
+    let saved_by = saved_by();
+
     with(|state| {
         let now = Utc::now();
         let id = state.take_id();
@@ -257,6 +364,8 @@ pub fn create_workflow(
             config: config.clone(),
             schedule: schedule.map(str::to_string),
             webhook_path: webhook_path(&descriptor.trigger, id),
+            limits: matches!(descriptor.trigger, WorkflowTrigger::Webhook { .. })
+                .then(Default::default),
             // The agent derives this from the type's declared state. Here the
             // trigger is the closest honest stand-in: the workflows that poll
             // are the ones that remember where they got to.
@@ -265,12 +374,14 @@ pub fn create_workflow(
             updated_at: now,
             last_run: None,
             next_run: None,
+            snooze: None,
             // Nothing has run it yet, which is what a workflow created a moment
             // ago should look like.
             health: None,
         };
 
         state.workflows.push(workflow.clone());
+        state.remember(&workflow, saved_by, None);
         Some(workflow)
     })
 }
@@ -282,6 +393,7 @@ pub fn update_workflow(
     enabled: bool,
 ) -> Option<Workflow> {
     let types = data::workflow_types();
+    let saved_by = saved_by();
 
     with(|state| {
         let workflow = state
@@ -300,7 +412,72 @@ pub fn update_workflow(
         workflow.schedule = schedule.map(str::to_string);
         workflow.enabled = enabled;
         workflow.updated_at = Utc::now();
-        Some(workflow.clone())
+
+        let workflow = workflow.clone();
+        state.remember(&workflow, saved_by, None);
+        Some(workflow)
+    })
+}
+
+/// A workflow's saved versions, newest first.
+pub fn workflow_revisions(id: &str) -> Option<Vec<WorkflowRevision>> {
+    with(|state| {
+        let (_, history) = state
+            .revisions
+            .iter()
+            .find(|(workflow, _)| workflow.to_string() == id)?;
+
+        Some(history.iter().rev().cloned().collect())
+    })
+}
+
+/// Compares two of a workflow's revisions, choosing them as the agent does when
+/// either is left out.
+pub fn revision_diff(id: &str, from: Option<u32>, to: Option<u32>) -> Option<RevisionDiff> {
+    let revisions = workflow_revisions(id)?;
+
+    let to = match to {
+        Some(number) => revisions.iter().find(|r| r.number == number),
+        None => revisions.first(),
+    }?;
+    let from = match from {
+        Some(number) => revisions.iter().find(|r| r.number == number),
+        None => revisions.iter().find(|r| r.number < to.number),
+    }?;
+
+    Some(automate_api::diff_revisions(from, to))
+}
+
+/// Puts a workflow back as one of its revisions had it, saving that as a new
+/// revision.
+pub fn restore_revision(id: &str, number: u32) -> Option<Workflow> {
+    let revision = workflow_revisions(id)?
+        .into_iter()
+        .find(|revision| revision.number == number)?;
+    let types = data::workflow_types();
+    let saved_by = saved_by();
+
+    with(|state| {
+        let workflow = state
+            .workflows
+            .iter_mut()
+            .find(|workflow| workflow.id.to_string() == id)?;
+
+        if let Some(descriptor) = types
+            .iter()
+            .find(|descriptor| descriptor.id == workflow.type_id)
+        {
+            workflow.name = derive_name(descriptor, &revision.config);
+        }
+
+        workflow.config = revision.config;
+        workflow.schedule = revision.schedule;
+        workflow.enabled = revision.enabled;
+        workflow.updated_at = Utc::now();
+
+        let workflow = workflow.clone();
+        state.remember(&workflow, saved_by, Some(number));
+        Some(workflow)
     })
 }
 
@@ -324,12 +501,289 @@ pub fn rotate_webhook(id: &str) -> Option<Workflow> {
     })
 }
 
+pub fn set_webhook_limits(id: &str, limits: &WebhookLimits) -> Option<Workflow> {
+    with(|state| {
+        let workflow = state
+            .workflows
+            .iter_mut()
+            .find(|workflow| workflow.id.to_string() == id && workflow.limits.is_some())?;
+
+        workflow.limits = Some(limits.clone());
+        workflow.updated_at = Utc::now();
+        Some(workflow.clone())
+    })
+}
+
 pub fn delete_workflow(id: &str) {
     with(|state| {
         state
             .workflows
-            .retain(|workflow| workflow.id.to_string() != id)
+            .retain(|workflow| workflow.id.to_string() != id);
+        state
+            .revisions
+            .retain(|(workflow, _)| workflow.to_string() != id);
+    });
+}
+
+/// Copies a workflow, paused, as the agent does.
+pub fn duplicate_workflow(id: &str) -> Option<Workflow> {
+    let saved_by = saved_by();
+    let types = data::workflow_types();
+
+    with(|state| {
+        let original = state
+            .workflows
+            .iter()
+            .find(|workflow| workflow.id.to_string() == id)?
+            .clone();
+
+        let now = Utc::now();
+        let entropy = state.take_id();
+        let workflow = Workflow {
+            id: WorkflowId::from_entropy(entropy),
+            enabled: false,
+            webhook_path: types
+                .iter()
+                .find(|descriptor| descriptor.id == original.type_id)
+                .and_then(|descriptor| webhook_path(&descriptor.trigger, entropy)),
+            created_at: now,
+            updated_at: now,
+            last_run: None,
+            next_run: None,
+            snooze: None,
+            health: None,
+            ..original
+        };
+
+        state.workflows.push(workflow.clone());
+        state.remember(&workflow, saved_by, None);
+        Some(workflow)
+    })
+}
+
+/// Snoozes a workflow, refusing what the agent refuses.
+pub fn snooze_workflow(id: &str, snooze: &Snooze) -> Option<Result<Workflow, String>> {
+    let now = Utc::now();
+
+    with(|state| {
+        let workflow = state
+            .workflows
+            .iter_mut()
+            .find(|workflow| workflow.id.to_string() == id)?;
+
+        if snooze.until.is_none() && snooze.runs.is_none() {
+            return Some(Err(
+                "Say when the snooze should end, either at a time or after a number of runs."
+                    .into(),
+            ));
+        }
+
+        if snooze.until.is_some_and(|until| until <= now) {
+            return Some(Err("A snooze has to end in the future.".into()));
+        }
+
+        if snooze.deliveries == SnoozedDeliveries::Hold
+            && (snooze.until.is_none() || snooze.runs.is_some() || workflow.schedule.is_some())
+        {
+            return Some(Err(
+                "Only a webhook workflow snoozed until a time can hold its deliveries.".into(),
+            ));
+        }
+
+        workflow.snooze = Some(snooze.clone());
+        if let Some(until) = snooze.until {
+            workflow.next_run = workflow.next_run.map(|next| next.max(until));
+        }
+
+        Some(Ok(workflow.clone()))
+    })
+}
+
+/// Ends a workflow's snooze early.
+pub fn wake_workflow(id: &str) -> Option<Workflow> {
+    with(|state| {
+        let workflow = state
+            .workflows
+            .iter_mut()
+            .find(|workflow| workflow.id.to_string() == id)?;
+
+        workflow.snooze = None;
+        Some(workflow.clone())
+    })
+}
+
+/// Keeps a workflow as one of this account's templates.
+///
+/// Does what the agent does to the configuration in miniature: each chosen
+/// field becomes a placeholder whose default is its current value, and the
+/// linked accounts and secrets are left behind.
+pub fn save_workflow_as_template(id: &str, request: &SaveAsTemplate) -> Option<WorkflowTemplate> {
+    let workflow = workflows()
+        .into_iter()
+        .find(|workflow| workflow.id.to_string() == id)?;
+    let descriptor = data::workflow_types()
+        .into_iter()
+        .find(|descriptor| descriptor.id == workflow.type_id)?;
+
+    let mut config = workflow.config.clone();
+    for field in &descriptor.fields {
+        if matches!(
+            field.kind,
+            FieldKind::Connection { .. } | FieldKind::Secret { .. }
+        ) {
+            set_at(&mut config, &field.name, None);
+        }
+    }
+
+    let parameters = request
+        .parameters
+        .iter()
+        .map(|path| {
+            let name = path.replace('.', "_");
+            let default = value_at(&config, path)
+                .and_then(|value| value.as_str())
+                .map(str::to_string);
+            set_at(
+                &mut config,
+                path,
+                Some(serde_json::Value::String(format!(
+                    "${{{{ params.{name} }}}}"
+                ))),
+            );
+
+            TemplateParameter {
+                name,
+                label: descriptor
+                    .fields
+                    .iter()
+                    .find(|field| &field.name == path)
+                    .map(|field| field.label.clone()),
+                help: None,
+                default,
+            }
+        })
+        .collect();
+
+    with(|state| {
+        let now = Utc::now();
+        let template = WorkflowTemplate {
+            id: TemplateId::from_entropy(state.take_id()),
+            name: request.name.trim().to_string(),
+            description: request.description.clone(),
+            scope: TemplateScope::Account,
+            parameters,
+            workflows: vec![TemplateWorkflow {
+                type_id: workflow.type_id.clone(),
+                config,
+                schedule: workflow.schedule.clone(),
+            }],
+            created_at: now,
+            updated_at: now,
+        };
+
+        state.templates.push(template.clone());
+        Some(template)
+    })
+}
+
+/// This account's templates, then the published ones, each by name.
+pub fn templates() -> Vec<WorkflowTemplate> {
+    let mut templates = with(|state| state.templates.clone());
+    templates.sort_by(|a, b| {
+        (a.scope == TemplateScope::Installation, &a.name)
+            .cmp(&(b.scope == TemplateScope::Installation, &b.name))
     });
+    templates
+}
+
+pub fn delete_template(id: &str) {
+    with(|state| {
+        state.templates.retain(|template| {
+            template.id.to_string() != id || template.scope == TemplateScope::Installation
+        })
+    });
+}
+
+/// Creates a template's workflows, refusing as the agent would when a value it
+/// needs was left out.
+pub fn instantiate_template(
+    id: &str,
+    request: &InstantiateTemplate,
+) -> Result<Vec<Workflow>, String> {
+    let template = with(|state| {
+        state
+            .templates
+            .iter()
+            .find(|template| template.id.to_string() == id)
+            .cloned()
+    })
+    .ok_or_else(|| format!("There is no template called '{id}'."))?;
+
+    let mut values = Vec::new();
+    for parameter in &template.parameters {
+        let value = request
+            .values
+            .get(&parameter.name)
+            .filter(|value| !value.trim().is_empty())
+            .or(parameter.default.as_ref())
+            .ok_or_else(|| {
+                format!(
+                    "The template '{}' needs a value for '{}'.",
+                    template.name,
+                    parameter.label.as_deref().unwrap_or(&parameter.name)
+                )
+            })?;
+        values.push((
+            format!("${{{{ params.{} }}}}", parameter.name),
+            value.clone(),
+        ));
+    }
+
+    let mut created = Vec::new();
+    for workflow in &template.workflows {
+        let mut config = fill(&workflow.config, &values);
+        for (path, connection) in &request.connections {
+            set_at(
+                &mut config,
+                path,
+                Some(serde_json::Value::String(connection.to_string())),
+            );
+        }
+
+        if let Some(workflow) = create_workflow(
+            &workflow.type_id,
+            &config,
+            workflow.schedule.as_deref(),
+            request.enabled,
+        ) {
+            created.push(workflow);
+        }
+    }
+
+    Ok(created)
+}
+
+/// Replaces each placeholder in the strings of a configuration with its value.
+fn fill(config: &serde_json::Value, values: &[(String, String)]) -> serde_json::Value {
+    match config {
+        serde_json::Value::String(text) => serde_json::Value::String(
+            values
+                .iter()
+                .fold(text.clone(), |text, (placeholder, value)| {
+                    text.replace(placeholder, value)
+                }),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|item| fill(item, values)).collect())
+        }
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), fill(value, values)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
 }
 
 /// Runs a workflow now. There is no job host here to run it, so all this can
@@ -363,6 +817,26 @@ pub fn reset_workflow(id: &str) -> Option<usize> {
     })
 }
 
+/// Previews a workflow, which in demo mode finds the same few items every time.
+///
+/// A webhook workflow is marked as replayed against its last run, as the agent
+/// would, so the panel's wording for that case can be reviewed too.
+pub fn preview_workflow(type_id: &str, config: &serde_json::Value) -> WorkflowPreview {
+    let webhook = data::workflow_types()
+        .iter()
+        .find(|descriptor| descriptor.id == type_id)
+        .is_some_and(|descriptor| !matches!(descriptor.trigger, WorkflowTrigger::Cron { .. }));
+
+    WorkflowPreview {
+        replayed: webhook.then(|| Utc::now() - chrono::Duration::hours(1)),
+        ..data::workflow_preview(config)
+    }
+}
+
+pub fn evaluate_filter(expression: &str) -> FilterEvaluation {
+    data::filter_evaluation(expression)
+}
+
 pub fn connection_options(source: &str, parent: Option<&str>) -> Vec<OptionItem> {
     data::connection_options(source, parent)
 }
@@ -420,7 +894,9 @@ fn derive_name(descriptor: &WorkflowTypeDescriptor, config: &serde_json::Value)
 fn webhook_path(trigger: &WorkflowTrigger, id: u64) -> Option<String> {
     match trigger {
         WorkflowTrigger::Webhook { source } => Some(format!("/webhooks/{source}/{}", token(id))),
-        WorkflowTrigger::Cron { .. } | WorkflowTrigger::RoutedWebhook { .. } => None,
+        WorkflowTrigger::Cron { .. }
+        | WorkflowTrigger::RoutedWebhook { .. }
+        | WorkflowTrigger::Event { .. } => None,
     }
 }
 
@@ -11,7 +11,7 @@ use yew::prelude::*;
 use crate::api;
 use crate::components::{
     Alert, AlertKind, Button, ButtonGroup, ButtonKind, ConnectMenu, Field, MenuButtonOption,
-    PageActions, StatusPill, StatusTone, TextInput,
+    NotificationSettings, PageActions, StatusPill, StatusTone, TextInput,
 };
 use crate::search::{MatchContext, SearchContext};
 use crate::util::short_relative;
@@ -128,6 +128,13 @@ pub fn connections() -> Html {
         .map(|search| search.filter.clone())
         .unwrap_or_default();
 
+    let todoist: Vec<ConnectionSummary> = connections
+        .iter()
+        .flatten()
+        .filter(|connection| connection.provider == "todoist")
+        .cloned()
+        .collect();
+
     html! {
         <section class="connections-page">
             if let Some(message) = &*error {
@@ -194,6 +201,9 @@ pub fn connections() -> Html {
                     },
                 }
             }
+
+            <h2 class="connections-page__heading">{ "When something stops working" }</h2>
+            <NotificationSettings {todoist} />
         </section>
     }
 }
@@ -1205,6 +1205,13 @@ fn dynamic_forms() -> Html {
             },
         ),
         FieldDescriptor::new("schedule", "Cron", FieldKind::Cron),
+        FieldDescriptor::new(
+            "script",
+            "Script",
+            FieldKind::Script {
+                placeholder: Some("item.total = item.amount * item.quantity;\nitem".to_string()),
+            },
+        ),
         FieldDescriptor::new(
             "filter",
             "Filter",
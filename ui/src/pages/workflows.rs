@@ -11,8 +11,10 @@
 use std::rc::Rc;
 
 use automate_api::{
-    ConnectionSummary, FieldKind, RunOutcome, RunState, Workflow, WorkflowHealth, WorkflowTrigger,
-    WorkflowTypeDescriptor,
+    ConnectionSummary, DeliveryRecord, DeliverySummary, FieldKind, PreviewItem, PreviewTask,
+    RevisionChange, RevisionDiff, RunHistory, RunOutcome, RunReport, RunTrigger, SignatureVerdict,
+    Snooze, SnoozedDeliveries, Workflow, WorkflowHealth, WorkflowPreview, WorkflowRevision,
+    WorkflowTrigger, WorkflowTypeDescriptor,
 };
 use gloo_timers::callback::Timeout;
 use yew::prelude::*;
@@ -21,8 +23,9 @@ use crate::api;
 use crate::components::dynamic_form::{set_at, value_at};
 use crate::components::{
     Alert, AlertKind, Button, ButtonKind, Documentation, DynamicForm, FetchedOptions, Field,
-    JsonHighlight, MenuButton, MenuButtonOption, PageActions, StatusPill, StatusTone, Switch,
-    TextInput, WebhookAddress,
+    JsonHighlight, MenuButton, MenuButtonOption, PageActions, SaveTemplateForm, SnoozeForm,
+    StatusPill, StatusTone, Switch, TextInput, WebhookAddress, WebhookLimitsEditor,
+    WorkflowTemplates,
 };
 use crate::search::{MatchContext, SearchContext};
 use crate::util::{format_iso8601, short_relative};
@@ -47,6 +50,35 @@ fn announce(notice: &UseStateHandle<Option<String>>, message: String) {
     Timeout::new(4_000, move || notice.set(None)).forget();
 }
 
+/// How long a snooze lasts, in words that follow "snoozed", such as "for 2
+/// more runs" or "for 2h, keeping deliveries".
+fn snooze_summary(snooze: Option<&Snooze>) -> String {
+    let Some(snooze) = snooze else {
+        return String::new();
+    };
+
+    let mut parts = Vec::new();
+    if let Some(until) = snooze.until {
+        let left = until
+            .signed_duration_since(chrono::Utc::now())
+            .num_seconds();
+        parts.push(format!("for {}", crate::util::short_duration(left)));
+    }
+    if let Some(runs) = snooze.runs {
+        parts.push(match runs {
+            1 => "for 1 more run".to_string(),
+            runs => format!("for {runs} more runs"),
+        });
+    }
+
+    let mut summary = parts.join(" or ");
+    if snooze.deliveries == SnoozedDeliveries::Hold {
+        summary.push_str(", keeping deliveries");
+    }
+
+    summary
+}
+
 /// How a workflow's health reads on its row.
 ///
 /// Only says "working" once there has been a run to say it about. A workflow
@@ -55,9 +87,19 @@ fn announce(notice: &UseStateHandle<Option<String>>, message: String) {
 fn health_pill(health: &WorkflowHealth) -> Html {
     let failing = health.consecutive_failures > 0;
 
-    let title = match &health.message {
-        Some(message) if failing => message.clone(),
-        _ if failing => format!("Its last run failed {}.", short_relative(health.at)),
+    // A run that set its input aside leaves the workflow as it was, so its
+    // reason is not why the workflow is failing.
+    let title = match (&health.message, health.outcome) {
+        (Some(message), RunOutcome::Failed) if failing => message.clone(),
+        (_, RunOutcome::Failed) => format!("Its last run failed {}.", short_relative(health.at)),
+        _ if failing => format!(
+            "Its last failure was {}.",
+            short_relative(health.last_failure_at.unwrap_or(health.at))
+        ),
+        (_, RunOutcome::Discarded) => format!(
+            "Its last run set what it was sent aside, {}.",
+            short_relative(health.at)
+        ),
         _ => format!("Its last run worked, {}.", short_relative(health.at)),
     };
 
@@ -277,10 +319,26 @@ pub fn workflows() -> Html {
             }
 
             { body }
+
+            <h2 class="workflows__heading">{ "Templates" }</h2>
+            <WorkflowTemplates
+                types={(*types).clone()}
+                connections={(*connections).clone()}
+                generation={*reload}
+                on_created={on_changed}
+            />
         </section>
     }
 }
 
+/// Which of a workflow's records its open row is showing.
+#[derive(Clone, Copy, PartialEq)]
+enum DetailTab {
+    Runs,
+    Deliveries,
+    History,
+}
+
 #[derive(Properties, PartialEq)]
 struct WorkflowRowProps {
     workflow: Workflow,
@@ -302,15 +360,18 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
     // queued and finishes later, and a reset changes nothing the row shows, so
     // without this the page would answer both requests by looking unchanged.
     let notice = use_state(|| None::<String>);
-    // Resetting throws away what a workflow remembers, and the consequence — a
-    // backlog re-filed as though it were new — lands in somebody's task list
+    // Resetting throws away what a workflow remembers, and the consequence — by
+    // default a backlog re-filed as though it were new — lands in somebody's task list
     // rather than here, so it is worth saying out loud before it happens.
     let confirming_reset = use_state(|| false);
-    // What the row folds away: the address it receives deliveries on, and how
-    // its last runs went. The runs are fetched only once this is open, since
-    // they carry the payload each run was handed and nobody wants every row's
-    // at once.
+    let saving_template = use_state(|| false);
+    let snoozing = use_state(|| false);
+    // What the row folds away: the address it receives deliveries on, how its
+    // last runs went, the deliveries themselves, and the versions it was saved
+    // as. Each is fetched only once its tab is open, since they carry the
+    // payloads and nobody wants every row's at once.
     let expanded = use_state(|| false);
+    let tab = use_state(|| DetailTab::Runs);
     let workflow = &props.workflow;
 
     /// Saves a change to this workflow, whatever prompted it.
@@ -507,16 +568,155 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
         })
     };
 
+    // Copies the workflow. The copy starts paused, since running two of the same
+    // thing files everything twice; the reload puts it in the list to be edited.
+    let on_duplicate = {
+        let (id, busy, error, notice, on_changed) = (
+            workflow.id.to_string(),
+            busy.clone(),
+            error.clone(),
+            notice.clone(),
+            props.on_changed.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (id, busy, error, notice, on_changed) = (
+                id.clone(),
+                busy.clone(),
+                error.clone(),
+                notice.clone(),
+                on_changed.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::duplicate_workflow(&id).await {
+                    Ok(_) => {
+                        announce(
+                            &notice,
+                            "Copied. The copy is paused until you have changed what it watches."
+                                .to_string(),
+                        );
+                        on_changed.emit(());
+                    }
+                    Err(err) => {
+                        error.set(Some(("We could not copy this workflow.", err.to_string())))
+                    }
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    // Ends a snooze early. Whatever a webhook workflow held on to is handed over
+    // straight away rather than at the time the snooze would have ended.
+    let on_wake = {
+        let (id, busy, error, notice, on_changed) = (
+            workflow.id.to_string(),
+            busy.clone(),
+            error.clone(),
+            notice.clone(),
+            props.on_changed.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (id, busy, error, notice, on_changed) = (
+                id.clone(),
+                busy.clone(),
+                error.clone(),
+                notice.clone(),
+                on_changed.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::wake_workflow(&id).await {
+                    Ok(_) => {
+                        announce(
+                            &notice,
+                            "Woken. It carries on from its next run.".to_string(),
+                        );
+                        on_changed.emit(());
+                    }
+                    Err(err) => {
+                        error.set(Some(("We could not wake this workflow.", err.to_string())))
+                    }
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_snoozed = {
+        let (snoozing, notice, on_changed) =
+            (snoozing.clone(), notice.clone(), props.on_changed.clone());
+
+        Callback::from(move |workflow: Workflow| {
+            snoozing.set(false);
+            announce(
+                &notice,
+                format!("Snoozed {}.", snooze_summary(workflow.snooze.as_ref())),
+            );
+            on_changed.emit(());
+        })
+    };
+
+    let on_cancel_snooze = {
+        let snoozing = snoozing.clone();
+        Callback::from(move |_| snoozing.set(false))
+    };
+
+    let on_template_saved = {
+        let (saving_template, notice, on_changed) = (
+            saving_template.clone(),
+            notice.clone(),
+            props.on_changed.clone(),
+        );
+
+        Callback::from(move |name: String| {
+            saving_template.set(false);
+            announce(&notice, format!("Saved as the template '{name}'."));
+            on_changed.emit(());
+        })
+    };
+
+    let on_cancel_template = {
+        let saving_template = saving_template.clone();
+        Callback::from(move |_| saving_template.set(false))
+    };
+
     let on_action = {
-        let (on_trigger, on_delete, confirming_reset) = (
+        let (
+            on_trigger,
+            on_duplicate,
+            on_delete,
+            on_wake,
+            confirming_reset,
+            saving_template,
+            snoozing,
+        ) = (
             on_trigger.clone(),
+            on_duplicate.clone(),
             on_delete.clone(),
+            on_wake.clone(),
             confirming_reset.clone(),
+            saving_template.clone(),
+            snoozing.clone(),
         );
 
         Callback::from(move |action: String| match action.as_str() {
             "trigger" => on_trigger.emit(()),
+            "snooze" => snoozing.set(true),
+            "wake" => on_wake.emit(()),
             "reset" => confirming_reset.set(true),
+            "duplicate" => on_duplicate.emit(()),
+            "template" => saving_template.set(true),
             "delete" => on_delete.emit(()),
             _ => {}
         })
@@ -549,12 +749,28 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
         Callback::from(move |_| editing.set(false))
     };
 
-    let schedule = workflow
-        .schedule
-        .as_deref()
-        .and_then(crate::util::describe_cron)
-        .or_else(|| workflow.schedule.clone())
-        .unwrap_or_else(|| "when its webhook is called".to_string());
+    let follows = props
+        .descriptor
+        .as_ref()
+        .is_some_and(|descriptor| matches!(descriptor.trigger, WorkflowTrigger::Event { .. }));
+
+    let schedule = if follows {
+        match workflow
+            .config
+            .get("event")
+            .and_then(|event| event.as_str())
+        {
+            Some(event) if !event.trim().is_empty() => format!("when {} is emitted", event.trim()),
+            _ => "when another workflow emits an event".to_string(),
+        }
+    } else {
+        workflow
+            .schedule
+            .as_deref()
+            .and_then(crate::util::describe_cron)
+            .or_else(|| workflow.schedule.clone())
+            .unwrap_or_else(|| "when its webhook is called".to_string())
+    };
 
     // Everything the row can do other than the one the button itself carries
     // out. Assembled rather than written out, because which of them apply
@@ -568,34 +784,64 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
         actions.push(MenuButtonOption::new("trigger", "Run now"));
     }
 
+    // Snoozing a paused workflow would say nothing: it is already silent, and
+    // the snooze ending would not start it again.
+    if workflow.snooze.is_some() {
+        actions.push(MenuButtonOption::new("wake", "Wake"));
+    } else if workflow.enabled {
+        actions.push(MenuButtonOption::new("snooze", "Snooze"));
+    }
+
     if workflow.resettable {
         actions.push(MenuButtonOption::new("reset", "Reset state"));
     }
 
+    // Both start from the workflow's configuration, which a workflow whose type
+    // has gone no longer has a form for.
+    if props.descriptor.is_some() {
+        actions.push(MenuButtonOption::new("duplicate", "Duplicate"));
+        actions.push(MenuButtonOption::new("template", "Save as template"));
+    }
+
     actions.push(MenuButtonOption::new("delete", "Delete").destructive());
 
-    // Nothing to open for a workflow that has neither an address nor a run
-    // behind it, so it is not made to look as though there is.
-    let expandable = workflow.health.is_some() || workflow.webhook_path.is_some();
+    let receives_deliveries = props.descriptor.as_ref().is_some_and(|descriptor| {
+        matches!(
+            descriptor.trigger,
+            WorkflowTrigger::Webhook { .. } | WorkflowTrigger::RoutedWebhook { .. }
+        )
+    });
+
     let panel_id = format!("workflow-detail-{}", workflow.id);
 
+    // Deliveries only for a workflow that is sent them; every workflow has runs
+    // and a history, even if neither has anything in it yet.
+    let mut tabs = vec![(DetailTab::Runs, "Runs")];
+    if receives_deliveries {
+        tabs.push((DetailTab::Deliveries, "Deliveries"));
+    }
+    tabs.push((DetailTab::History, "History"));
+
     let title = html! {
         <>
             <span class="workflow__name">{ &workflow.name }</span>
             <span class="workflow__meta">
                 { &workflow.type_id }{ " · " }{ schedule }
+                if !workflow.enabled {
+                    { " · paused" }
+                } else if workflow.snooze.is_some() {
+                    { " · snoozed " }{ snooze_summary(workflow.snooze.as_ref()) }
+                }
                 if workflow.enabled && let Some(next) = workflow.next_run {
                     { " · next " }{ crate::util::short_relative(next) }
-                } else if !workflow.enabled {
-                    { " · paused" }
                 }
             </span>
         </>
     };
 
     html! {
-        <li class={classes!("workflow", expandable.then_some("workflow--expandable"))}>
-            <div class="workflow__summary" onclick={expandable.then_some(on_row)}>
+        <li class="workflow workflow--expandable">
+            <div class="workflow__summary" onclick={on_row}>
                 <Switch
                     id={format!("workflow-enabled-{}", workflow.id)}
                     checked={workflow.enabled}
@@ -606,32 +852,28 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
                 // A real button, so the row can be opened from the keyboard and
                 // says whether it is open. The rest of the row is a convenience
                 // on top of this rather than the only way in.
-                if expandable {
-                    <button
-                        type="button"
-                        class="workflow__detail workflow__detail--button"
-                        aria-expanded={expanded.to_string()}
-                        aria-controls={panel_id.clone()}
-                        onclick={on_expand}
+                <button
+                    type="button"
+                    class="workflow__detail workflow__detail--button"
+                    aria-expanded={expanded.to_string()}
+                    aria-controls={panel_id.clone()}
+                    onclick={on_expand}
+                >
+                    { title }
+                    <span
+                        class={classes!(
+                            "workflow__chevron",
+                            expanded.then_some("workflow__chevron--open"),
+                        )}
+                        aria-hidden="true"
                     >
-                        { title }
-                        <span
-                            class={classes!(
-                                "workflow__chevron",
-                                expanded.then_some("workflow__chevron--open"),
-                            )}
-                            aria-hidden="true"
-                        >
-                            <svg viewBox="0 0 24 24" width="12" height="12" fill="none"
-                                stroke="currentColor" stroke-width="2" stroke-linecap="round"
-                                stroke-linejoin="round">
-                                <polyline points="6 9 12 15 18 9" />
-                            </svg>
-                        </span>
-                    </button>
-                } else {
-                    <div class="workflow__detail">{ title }</div>
-                }
+                        <svg viewBox="0 0 24 24" width="12" height="12" fill="none"
+                            stroke="currentColor" stroke-width="2" stroke-linecap="round"
+                            stroke-linejoin="round">
+                            <polyline points="6 9 12 15 18 9" />
+                        </svg>
+                    </span>
+                </button>
 
                 if let Some(health) = &workflow.health {
                     { health_pill(health) }
@@ -672,9 +914,9 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
             if *confirming_reset {
                 <div class="workflow__confirm">
                     <p class="workflow__warning">
-                        { "This forgets where the workflow got to. The next run treats \
-                           everything it finds as new, so a backlog that was already dealt \
-                           with will be filed again." }
+                        { "This forgets where the workflow got to. The next run is treated \
+                           as its first, so unless its first-run setting says otherwise, a \
+                           backlog that was already dealt with will be filed again." }
                     </p>
 
                     <div class="workflow__confirm-actions">
@@ -692,8 +934,31 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
                 </div>
             }
 
+            if *snoozing {
+                <SnoozeForm
+                    workflow={workflow.clone()}
+                    {receives_deliveries}
+                    {on_snoozed}
+                    oncancel={on_cancel_snooze}
+                />
+            }
+
+            if *saving_template && let Some(descriptor) = props.descriptor.clone() {
+                <SaveTemplateForm
+                    workflow={workflow.clone()}
+                    {descriptor}
+                    on_saved={on_template_saved}
+                    oncancel={on_cancel_template}
+                />
+            }
+
             if *expanded {
                 <div class="workflow__panel" id={panel_id}>
+                    <p class="workflow__identifier">
+                        { "Known to the workflows following it as " }
+                        <code>{ workflow.id.to_string() }</code>
+                    </p>
+
                     if let Some(path) = workflow.webhook_path.clone() {
                         <WebhookAddress
                             workflow={workflow.id.to_string()}
@@ -702,9 +967,49 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
                         />
                     }
 
-                    if workflow.health.is_some() {
-                        <WorkflowRuns workflow={workflow.id.to_string()} />
+                    if let Some(limits) = workflow.limits.clone() {
+                        <WebhookLimitsEditor
+                            workflow={workflow.id.to_string()}
+                            {limits}
+                            on_saved={props.on_changed.clone()}
+                        />
                     }
+
+                    <div class="workflow__tabs" role="tablist">
+                        { for tabs.into_iter().map(|(choice, label)| html! {
+                            <button
+                                type="button"
+                                role="tab"
+                                class={classes!(
+                                    "workflow__tab",
+                                    (*tab == choice).then_some("workflow__tab--active"),
+                                )}
+                                aria-selected={(*tab == choice).to_string()}
+                                onclick={{
+                                    let tab = tab.clone();
+                                    Callback::from(move |_| tab.set(choice))
+                                }}
+                            >
+                                { label }
+                            </button>
+                        }) }
+                    </div>
+
+                    { match *tab {
+                        DetailTab::Runs => html! {
+                            <WorkflowRuns workflow={workflow.id.to_string()} />
+                        },
+                        DetailTab::Deliveries => html! {
+                            <WorkflowDeliveries workflow={workflow.id.to_string()} />
+                        },
+                        DetailTab::History => html! {
+                            <WorkflowHistory
+                                workflow={workflow.id.to_string()}
+                                updated_at={workflow.updated_at}
+                                on_restored={props.on_changed.clone()}
+                            />
+                        },
+                    } }
                 </div>
             }
 
@@ -717,6 +1022,7 @@ fn workflow_row(props: &WorkflowRowProps) -> Html {
                 <WorkflowForm
                     descriptor={descriptor}
                     connections={props.connections.clone()}
+                    workflow={Some(workflow.id.to_string())}
                     initial={WorkflowValues {
                         config: workflow.config.clone(),
                         schedule: workflow.schedule.clone(),
@@ -737,23 +1043,26 @@ struct WorkflowRunsProps {
     workflow: String,
 }
 
-/// What a workflow's most recent runs did, and what they did it to.
+/// What a workflow's recent runs did, and what they did it to.
 ///
-/// Two runs at most: the last one, and the last one that failed. A history
-/// would be the thing this arrangement replaced — but a failure that has been
-/// overwritten by three hundred successful deliveries is a failure nobody can
-/// look into, which is why the second one is kept.
+/// The last run and the last failure come first: a failure that has been
+/// pushed out of the history by three hundred successful deliveries is still
+/// the one somebody needs to look into, which is why the agent keeps it
+/// separately. Beneath them is the history itself, charted so that "it failed
+/// at three and again at five" can be seen at a glance, and listed a page at a
+/// time.
 #[function_component(WorkflowRuns)]
 fn workflow_runs(props: &WorkflowRunsProps) -> Html {
-    let state = use_state(|| None::<Option<RunState>>);
+    let history = use_state(|| None::<RunHistory>);
     let error = use_state(|| None::<String>);
+    let busy = use_state(|| false);
 
     {
-        let (id, state, error) = (props.workflow.clone(), state.clone(), error.clone());
+        let (id, history, error) = (props.workflow.clone(), history.clone(), error.clone());
         use_effect_with(props.workflow.clone(), move |_| {
             wasm_bindgen_futures::spawn_local(async move {
-                match api::workflow_runs(&id).await {
-                    Ok(found) => state.set(Some(found)),
+                match api::workflow_runs(&id, None).await {
+                    Ok(found) => history.set(Some(found)),
                     Err(err) => error.set(Some(err.to_string())),
                 }
             });
@@ -761,6 +1070,41 @@ fn workflow_runs(props: &WorkflowRunsProps) -> Html {
         });
     }
 
+    let on_older = {
+        let (id, history, error, busy) = (
+            props.workflow.clone(),
+            history.clone(),
+            error.clone(),
+            busy.clone(),
+        );
+
+        Callback::from(move |_| {
+            let Some(before) = (*history).as_ref().and_then(|history| history.next) else {
+                return;
+            };
+            let (id, history, error, busy) =
+                (id.clone(), history.clone(), error.clone(), busy.clone());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+
+                match api::workflow_runs(&id, Some(before)).await {
+                    Ok(page) => {
+                        let mut runs = (*history)
+                            .as_ref()
+                            .map(|history| history.runs.clone())
+                            .unwrap_or_default();
+                        runs.extend(page.runs);
+                        history.set(Some(RunHistory { runs, ..page }));
+                    }
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
     if let Some(message) = (*error).clone() {
         return html! {
             <Alert
@@ -771,12 +1115,16 @@ fn workflow_runs(props: &WorkflowRunsProps) -> Html {
         };
     }
 
-    let body = match &*state {
+    let body = match &*history {
         None => html! { <p class="workflow-runs__empty">{ "Loading…" }</p> },
-        Some(None) => html! {
+        Some(RunHistory { state: None, .. }) => html! {
             <p class="workflow-runs__empty">{ "This workflow has not run yet." }</p>
         },
-        Some(Some(state)) => {
+        Some(RunHistory {
+            state: Some(state),
+            runs,
+            next,
+        }) => {
             // Shown once when the last run is itself the failure, which is the
             // usual case for a workflow that is currently broken.
             let earlier_failure = state
@@ -790,6 +1138,25 @@ fn workflow_runs(props: &WorkflowRunsProps) -> Html {
                     if let Some(failure) = earlier_failure {
                         <Run label="Last failure" report={failure.clone()} />
                     }
+
+                    if !runs.is_empty() {
+                        <RunChart runs={runs.clone()} />
+                        <ul class="workflow-runs__history">
+                            { for runs.iter().map(|run| html! {
+                                <li key={run.started_at.timestamp_micros().to_string()}>
+                                    <Run report={run.clone()} />
+                                </li>
+                            }) }
+                        </ul>
+                    }
+
+                    if next.is_some() {
+                        <div>
+                            <Button kind={ButtonKind::Subtle} onclick={on_older} busy={*busy}>
+                                { "Show older runs" }
+                            </Button>
+                        </div>
+                    }
                 </>
             }
         }
@@ -798,34 +1165,136 @@ fn workflow_runs(props: &WorkflowRunsProps) -> Html {
     html! { <div class="workflow-runs">{ body }</div> }
 }
 
+#[derive(Properties, PartialEq)]
+struct RunChartProps {
+    /// Newest first, as the agent lists them.
+    runs: Vec<RunReport>,
+}
+
+/// The runs loaded so far as a bar each, oldest on the left: coloured by how
+/// they went, and as tall as they took.
+///
+/// Height is relative to the slowest run shown, so a feed that has started
+/// timing out stands out against the runs before it whatever its usual pace.
+#[function_component(RunChart)]
+fn run_chart(props: &RunChartProps) -> Html {
+    let slowest = props
+        .runs
+        .iter()
+        .map(|run| run.duration().num_milliseconds())
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    let failed = props
+        .runs
+        .iter()
+        .filter(|run| run.outcome == RunOutcome::Failed)
+        .count();
+
+    html! {
+        <div
+            class="workflow-runs__chart"
+            role="img"
+            aria-label={format!("{failed} of the last {} runs failed.", props.runs.len())}
+        >
+            { for props.runs.iter().rev().map(|run| {
+                let took = run.duration().num_milliseconds().max(0);
+                // Never drawn so short that a quick run cannot be seen or
+                // pointed at.
+                let height = (took * 100 / slowest).max(6);
+
+                html! {
+                    <span
+                        key={run.started_at.timestamp_micros().to_string()}
+                        class={classes!(
+                            "workflow-runs__bar",
+                            match run.outcome {
+                                RunOutcome::Failed => Some("workflow-runs__bar--failed"),
+                                RunOutcome::Discarded => Some("workflow-runs__bar--discarded"),
+                                RunOutcome::Succeeded => None,
+                            },
+                        )}
+                        style={format!("height: {height}%")}
+                        title={format!(
+                            "{} {}, {} · took {took}ms",
+                            run.outcome.label(),
+                            short_relative(run.finished_at),
+                            trigger_of(run).map(|trigger| trigger.label()).unwrap_or("Run"),
+                        )}
+                    />
+                }
+            }) }
+        </div>
+    }
+}
+
+/// The tone a run's outcome is shown in. A run that set its input aside is
+/// neither working nor failing, and is shown as a skipped one would be.
+fn outcome_tone(outcome: RunOutcome) -> StatusTone {
+    match outcome {
+        RunOutcome::Succeeded => StatusTone::Ok,
+        RunOutcome::Failed => StatusTone::Error,
+        RunOutcome::Discarded => StatusTone::Neutral,
+    }
+}
+
+/// What started a run, including one recorded before runs noted it: a replay
+/// always said so.
+fn trigger_of(report: &RunReport) -> Option<RunTrigger> {
+    report
+        .trigger
+        .or(report.replay_of.map(|_| RunTrigger::Replay))
+}
+
 #[derive(Properties, PartialEq)]
 struct RunProps {
-    label: &'static str,
-    report: automate_api::RunReport,
+    /// Which run this is, where it is one of the two kept apart from the
+    /// history.
+    #[prop_or_default]
+    label: Option<&'static str>,
+    report: RunReport,
 }
 
 #[function_component(Run)]
 fn run(props: &RunProps) -> Html {
     let report = &props.report;
-    let failed = report.outcome == RunOutcome::Failed;
-    let took = (report.finished_at - report.started_at)
-        .num_milliseconds()
-        .max(0);
+    let took = report.duration().num_milliseconds().max(0);
+    let counts = report.counts;
 
     html! {
         <div class="workflow-runs__run">
             <div class="workflow-runs__header">
-                <span class="workflow-runs__label">{ props.label }</span>
+                if let Some(label) = props.label {
+                    <span class="workflow-runs__label">{ label }</span>
+                }
                 <StatusPill
-                    tone={if failed { StatusTone::Error } else { StatusTone::Ok }}
-                    label={if failed { "Failed" } else { "Succeeded" }}
+                    tone={outcome_tone(report.outcome)}
+                    label={report.outcome.label()}
                 />
+                if let Some(trigger) = trigger_of(report) {
+                    <StatusPill
+                        tone={StatusTone::Neutral}
+                        label={trigger.label()}
+                        title={report.replay_of.map(|original| {
+                            AttrValue::from(format!("A replay of the delivery '{original}'."))
+                        })}
+                    />
+                }
                 <span
                     class="workflow-runs__when"
                     title={format_iso8601(report.finished_at)}
                 >
                     { short_relative(report.finished_at) }{ format!(" · took {took}ms") }
                 </span>
+                if !counts.is_empty() {
+                    <span class="workflow-runs__counts">
+                        { format!(
+                            "{} seen · {} matched · {} filed",
+                            counts.items, counts.matched, counts.published
+                        ) }
+                    </span>
+                }
             </div>
 
             if let Some(message) = &report.message {
@@ -840,73 +1309,748 @@ fn run(props: &RunProps) -> Html {
                     <JsonHighlight value={input.clone()} />
                 </details>
             }
+
+            // What the run said along the way, which is usually the answer to
+            // "why did it file nothing?" when nothing failed.
+            if !report.log.is_empty() {
+                <details class="workflow-runs__log">
+                    <summary>{ "What it logged" }</summary>
+                    <ul>
+                        { for report.log.lines.iter().map(|line| html! {
+                            <li class={classes!(
+                                "workflow-runs__line",
+                                format!("workflow-runs__line--{}", line.level.as_str()),
+                            )}>
+                                <span class="workflow-runs__level">{ line.level.as_str() }</span>
+                                <span class="workflow-runs__when" title={format_iso8601(line.at)}>
+                                    { line.at.format("%H:%M:%S").to_string() }
+                                </span>
+                                <span>{ &line.message }</span>
+                                if !line.fields.is_empty() {
+                                    <code class="workflow-runs__fields">
+                                        { line.fields
+                                            .iter()
+                                            .map(|(name, value)| format!("{name}={value}"))
+                                            .collect::<Vec<_>>()
+                                            .join(" ") }
+                                    </code>
+                                }
+                            </li>
+                        }) }
+                    </ul>
+                    if report.log.dropped > 0 {
+                        <p class="workflow-runs__empty">
+                            { format!("{} more lines were not kept.", report.log.dropped) }
+                        </p>
+                    }
+                </details>
+            }
         </div>
     }
 }
 
 #[derive(Properties, PartialEq)]
-struct AddWorkflowProps {
-    descriptor: WorkflowTypeDescriptor,
-    connections: Vec<ConnectionSummary>,
-    on_created: Callback<()>,
-    oncancel: Callback<()>,
+struct WorkflowDeliveriesProps {
+    workflow: String,
 }
 
-#[function_component(AddWorkflow)]
-fn add_workflow(props: &AddWorkflowProps) -> Html {
+/// The last few deliveries a webhook workflow was sent, newest first.
+///
+/// Listed by their summaries, and read one at a time: a delivery's body is the
+/// large part, and the question is usually about one of them — the one that
+/// should have filed something and did not.
+#[function_component(WorkflowDeliveries)]
+fn workflow_deliveries(props: &WorkflowDeliveriesProps) -> Html {
+    let deliveries = use_state(|| None::<Vec<DeliverySummary>>);
     let error = use_state(|| None::<String>);
-    let busy = use_state(|| false);
-
-    let on_submit = {
-        let (type_id, error, busy, on_created) = (
-            props.descriptor.id.clone(),
-            error.clone(),
-            busy.clone(),
-            props.on_created.clone(),
-        );
-
-        Callback::from(move |values: WorkflowValues| {
-            let (type_id, error, busy, on_created) = (
-                type_id.clone(),
-                error.clone(),
-                busy.clone(),
-                on_created.clone(),
-            );
 
+    {
+        let (id, deliveries, error) = (props.workflow.clone(), deliveries.clone(), error.clone());
+        use_effect_with(props.workflow.clone(), move |_| {
             wasm_bindgen_futures::spawn_local(async move {
-                busy.set(true);
-                error.set(None);
-
-                match api::create_workflow(
-                    &type_id,
-                    &values.config,
-                    values.schedule.as_deref(),
-                    values.enabled,
-                )
-                .await
-                {
-                    Ok(_) => on_created.emit(()),
+                match api::workflow_deliveries(&id).await {
+                    Ok(found) => deliveries.set(Some(found)),
                     Err(err) => error.set(Some(err.to_string())),
                 }
-
-                busy.set(false);
             });
-        })
-    };
-
-    html! {
-        <div class="form-modal" role="dialog" aria-modal="true" aria-labelledby="add-workflow-title">
-                    <div class="form-modal__panel">
-                        <div class="form-modal__header">
-                            <h3 id="add-workflow-title" class="form-modal__title">
-                                { format!("Add {} workflow", props.descriptor.name) }
-                            </h3>
-                        </div>
+            || ()
+        });
+    }
 
-                        <div class="form-modal__body form-modal__body--workflow">
-                            <p class="workflows__form-description">{ props.descriptor.description.clone() }</p>
+    if let Some(message) = (*error).clone() {
+        return html! {
+            <Alert
+                kind={AlertKind::Error}
+                title="We could not load this workflow's deliveries."
+                message={message}
+            />
+        };
+    }
 
-                            <Documentation markdown={props.descriptor.documentation.clone()} />
+    let body = match &*deliveries {
+        None => html! { <p class="workflow-runs__empty">{ "Loading…" }</p> },
+        Some(found) if found.is_empty() => html! {
+            <p class="workflow-runs__empty">
+                { "This workflow has not been sent anything recently. Only the last few \
+                   deliveries are kept, for a few days." }
+            </p>
+        },
+        Some(found) => html! {
+            <ul class="workflow-deliveries">
+                { for found.iter().map(|summary| html! {
+                    <Delivery
+                        key={summary.id.to_string()}
+                        workflow={props.workflow.clone()}
+                        summary={summary.clone()}
+                    />
+                }) }
+            </ul>
+        },
+    };
+
+    html! { <div class="workflow-runs">{ body }</div> }
+}
+
+#[derive(Properties, PartialEq)]
+struct DeliveryProps {
+    workflow: String,
+    summary: DeliverySummary,
+}
+
+/// One delivery, fetched in full the first time it is opened.
+#[function_component(Delivery)]
+fn delivery(props: &DeliveryProps) -> Html {
+    let record = use_state(|| None::<Result<DeliveryRecord, String>>);
+    let summary = &props.summary;
+
+    let on_toggle = {
+        let (workflow, id, record) = (
+            props.workflow.clone(),
+            summary.id.to_string(),
+            record.clone(),
+        );
+        Callback::from(move |_: Event| {
+            if record.is_some() {
+                return;
+            }
+
+            let (workflow, id, record) = (workflow.clone(), id.clone(), record.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                record.set(Some(
+                    api::workflow_delivery(&workflow, &id)
+                        .await
+                        .map_err(|err| err.to_string()),
+                ));
+            });
+        })
+    };
+
+    let signature = match &summary.signature {
+        Some(SignatureVerdict::Verified) => {
+            html! { <StatusPill tone={StatusTone::Ok} label="Signed" /> }
+        }
+        Some(SignatureVerdict::Rejected { reason }) => html! {
+            <StatusPill
+                tone={StatusTone::Error}
+                label="Refused"
+                title={Some(AttrValue::from(reason.clone()))}
+            />
+        },
+        None => html! {},
+    };
+
+    html! {
+        <li class="workflow-deliveries__delivery">
+            <details ontoggle={on_toggle}>
+                <summary class="workflow-runs__header">
+                    <span
+                        class="workflow-runs__when"
+                        title={format_iso8601(summary.received_at)}
+                    >
+                        { short_relative(summary.received_at) }
+                    </span>
+                    if summary.outcome != RunOutcome::Succeeded {
+                        <StatusPill
+                            tone={outcome_tone(summary.outcome)}
+                            label={summary.outcome.label()}
+                        />
+                    }
+                    { signature }
+                    if summary.replay_of.is_some() {
+                        <StatusPill tone={StatusTone::Neutral} label="Replay" />
+                    }
+                    if summary.repeat_of.is_some() {
+                        <StatusPill
+                            tone={StatusTone::Neutral}
+                            label="Repeat"
+                            title={Some(AttrValue::from("Dropped, because the sender had already sent it."))}
+                        />
+                    }
+                    <span class="workflow-runs__when">
+                        { format!(
+                            "{} matched · {} filed",
+                            summary.matched,
+                            summary.published,
+                        ) }
+                    </span>
+                </summary>
+
+                { match &*record {
+                    None => html! { <p class="workflow-runs__empty">{ "Loading…" }</p> },
+                    Some(Err(message)) => html! {
+                        <Alert
+                            kind={AlertKind::Error}
+                            title="We could not load this delivery."
+                            message={message.clone()}
+                        />
+                    },
+                    Some(Ok(record)) => delivery_detail(&props.workflow, record),
+                } }
+            </details>
+        </li>
+    }
+}
+
+/// A delivery as it arrived, and what the workflow made of it.
+fn delivery_detail(workflow: &str, record: &DeliveryRecord) -> Html {
+    html! {
+        <div class="workflow-preview">
+            if let Some(original) = record.replay_of {
+                <p class="workflow-preview__note">
+                    { format!("A replay of the delivery '{original}', run because it was asked for.") }
+                </p>
+            }
+
+            if let Some(message) = &record.message {
+                <p class="workflow-runs__message">{ message }</p>
+            }
+
+            if let Some(SignatureVerdict::Rejected { reason }) = &record.signature {
+                <p class="workflow-runs__message">
+                    { format!("Refused because of its signature: {reason}") }
+                </p>
+            }
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Headers" }</span>
+                <dl class="workflow-deliveries__headers">
+                    { for record.headers.iter().map(|(name, value)| html! {
+                        <>
+                            <dt>{ name }</dt>
+                            <dd>{ value }</dd>
+                        </>
+                    }) }
+                </dl>
+                if !record.query.is_empty() {
+                    <code class="workflow-deliveries__query">{ format!("?{}", record.query) }</code>
+                }
+            </div>
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Body" }</span>
+                if let Some(bytes) = record.truncated_from {
+                    <p class="workflow-preview__note">
+                        { format!("Only the start of a {bytes} byte body was kept.") }
+                    </p>
+                }
+                // Highlighted when it is JSON, which it usually is, and shown as
+                // it was sent otherwise.
+                { match serde_json::from_str::<serde_json::Value>(&record.body) {
+                    Ok(value) => html! { <JsonHighlight {value} /> },
+                    Err(_) => html! {
+                        <pre class="workflow-deliveries__body">{ &record.body }</pre>
+                    },
+                } }
+            </div>
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Items" }</span>
+                if record.items.is_empty() {
+                    <p class="workflow-preview__empty">
+                        { "Nothing reached the filter." }
+                    </p>
+                }
+                { item_list(&record.items) }
+            </div>
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Filed" }</span>
+                if record.published.is_empty() {
+                    <p class="workflow-preview__empty">{ "Nothing was filed." }</p>
+                }
+                { task_list(&record.published) }
+            </div>
+
+            if !record.effects.is_empty() {
+                <div class="workflow-preview__section">
+                    <span class="workflow-preview__label">{ "Also did" }</span>
+                    <ul class="workflow-preview__items">
+                        { for record.effects.iter().map(|effect| html! { <li>{ effect }</li> }) }
+                    </ul>
+                </div>
+            }
+
+            <DeliveryReplay
+                workflow={workflow.to_string()}
+                delivery={record.id.to_string()}
+                refused={matches!(record.signature, Some(SignatureVerdict::Rejected { .. }))}
+                truncated={record.truncated_from.is_some()}
+            />
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct DeliveryReplayProps {
+    workflow: String,
+    delivery: String,
+
+    /// Its signature was turned away, so it may only be replayed as a preview.
+    refused: bool,
+
+    /// Only the start of its body was kept, so it cannot be replayed at all.
+    truncated: bool,
+}
+
+/// Runs a kept delivery again, against the workflow as it is saved now.
+///
+/// The preview is offered first because it is what somebody changing a filter
+/// or a template wants most of the time: to see whether the alert that came in
+/// this morning would now be filed, and as what, without filing it twice.
+#[function_component(DeliveryReplay)]
+fn delivery_replay(props: &DeliveryReplayProps) -> Html {
+    let busy = use_state(|| false);
+    let preview = use_state(|| None::<WorkflowPreview>);
+    let error = use_state(|| None::<(&'static str, String)>);
+    let notice = use_state(|| None::<String>);
+
+    if props.truncated {
+        return html! {
+            <p class="workflow-preview__note">
+                { "Only part of this delivery was kept, so it cannot be replayed." }
+            </p>
+        };
+    }
+
+    let on_preview = {
+        let (workflow, delivery, busy, preview, error) = (
+            props.workflow.clone(),
+            props.delivery.clone(),
+            busy.clone(),
+            preview.clone(),
+            error.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (workflow, delivery, busy, preview, error) = (
+                workflow.clone(),
+                delivery.clone(),
+                busy.clone(),
+                preview.clone(),
+                error.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::preview_delivery_replay(&workflow, &delivery).await {
+                    Ok(found) => preview.set(Some(found)),
+                    Err(err) => {
+                        error.set(Some(("We could not preview this replay.", err.to_string())))
+                    }
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    let on_replay = {
+        let (workflow, delivery, busy, error, notice) = (
+            props.workflow.clone(),
+            props.delivery.clone(),
+            busy.clone(),
+            error.clone(),
+            notice.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (workflow, delivery, busy, error, notice) = (
+                workflow.clone(),
+                delivery.clone(),
+                busy.clone(),
+                error.clone(),
+                notice.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::replay_delivery(&workflow, &delivery).await {
+                    Ok(()) => announce(
+                        &notice,
+                        "Queued. The replay is listed here once it has run.".to_string(),
+                    ),
+                    Err(err) => error.set(Some((
+                        "We could not replay this delivery.",
+                        err.to_string(),
+                    ))),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    html! {
+        <div class="workflow-preview__section">
+            <span class="workflow-preview__label">{ "Replay" }</span>
+
+            if let Some((title, message)) = (*error).clone() {
+                <Alert kind={AlertKind::Error} title={title} message={message} />
+            }
+
+            if let Some(message) = (*notice).clone() {
+                <p class="workflow-preview__note">{ message }</p>
+            }
+
+            if props.refused {
+                <p class="workflow-preview__note">
+                    { "Its signature was turned away, so it can only be replayed as a preview." }
+                </p>
+            }
+
+            <div class="workflow__confirm-actions">
+                <Button onclick={on_preview} busy={*busy}>
+                    { "Preview replay" }
+                </Button>
+                <Button
+                    kind={ButtonKind::Subtle}
+                    onclick={on_replay}
+                    disabled={*busy || props.refused}
+                >
+                    { "Replay" }
+                </Button>
+            </div>
+
+            if let Some(found) = (*preview).clone() {
+                <Preview preview={found} />
+            }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct WorkflowHistoryProps {
+    workflow: String,
+
+    /// When the workflow was last saved, so that the list is read again after
+    /// an edit or a restore rather than going on showing the history before it.
+    updated_at: chrono::DateTime<chrono::Utc>,
+
+    /// Invoked once a revision has been restored, since the workflow itself
+    /// has changed as well as its history.
+    on_restored: Callback<()>,
+}
+
+/// The versions a workflow was saved as, newest first.
+///
+/// Each one can say what it changed from the version before it, and any but
+/// the current one can be put back. Restoring is saved as a new version rather
+/// than by rewinding, so a restore is undone the same way.
+#[function_component(WorkflowHistory)]
+fn workflow_history(props: &WorkflowHistoryProps) -> Html {
+    let revisions = use_state(|| None::<Vec<WorkflowRevision>>);
+    let error = use_state(|| None::<String>);
+
+    {
+        let (id, revisions, error) = (props.workflow.clone(), revisions.clone(), error.clone());
+        use_effect_with((props.workflow.clone(), props.updated_at), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match api::workflow_revisions(&id).await {
+                    Ok(found) => revisions.set(Some(found)),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+            || ()
+        });
+    }
+
+    if let Some(message) = (*error).clone() {
+        return html! {
+            <Alert
+                kind={AlertKind::Error}
+                title="We could not load this workflow's history."
+                message={message}
+            />
+        };
+    }
+
+    let body = match &*revisions {
+        None => html! { <p class="workflow-runs__empty">{ "Loading…" }</p> },
+        Some(found) if found.is_empty() => html! {
+            <p class="workflow-runs__empty">
+                { "This workflow has not been saved since its history started being kept. \
+                   Its next save will keep both the version it replaces and the new one." }
+            </p>
+        },
+        Some(found) => html! {
+            <ul class="workflow-deliveries">
+                { for found.iter().enumerate().map(|(index, revision)| html! {
+                    <Revision
+                        key={revision.number}
+                        workflow={props.workflow.clone()}
+                        revision={revision.clone()}
+                        current={index == 0}
+                        on_restored={props.on_restored.clone()}
+                    />
+                }) }
+            </ul>
+        },
+    };
+
+    html! { <div class="workflow-runs">{ body }</div> }
+}
+
+#[derive(Properties, PartialEq)]
+struct RevisionProps {
+    workflow: String,
+    revision: WorkflowRevision,
+
+    /// Whether this is the version the workflow is saved as now, which there is
+    /// nothing to be gained by restoring.
+    current: bool,
+
+    on_restored: Callback<()>,
+}
+
+/// One saved version, compared with the one before it the first time it is
+/// opened.
+#[function_component(Revision)]
+fn revision(props: &RevisionProps) -> Html {
+    let diff = use_state(|| None::<Result<RevisionDiff, String>>);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+    let revision = &props.revision;
+    let first = revision.number <= 1;
+
+    let on_toggle = {
+        let (workflow, number, diff) = (props.workflow.clone(), revision.number, diff.clone());
+        Callback::from(move |_: Event| {
+            if diff.is_some() || first {
+                return;
+            }
+
+            let (workflow, diff) = (workflow.clone(), diff.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                diff.set(Some(
+                    api::revision_diff(&workflow, None, Some(number))
+                        .await
+                        .map_err(|err| err.to_string()),
+                ));
+            });
+        })
+    };
+
+    let on_restore = {
+        let (workflow, number, busy, error, on_restored) = (
+            props.workflow.clone(),
+            revision.number,
+            busy.clone(),
+            error.clone(),
+            props.on_restored.clone(),
+        );
+
+        Callback::from(move |_| {
+            let (workflow, busy, error, on_restored) = (
+                workflow.clone(),
+                busy.clone(),
+                error.clone(),
+                on_restored.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::restore_revision(&workflow, number).await {
+                    Ok(_) => on_restored.emit(()),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    html! {
+        <li class="workflow-deliveries__delivery">
+            <details ontoggle={on_toggle}>
+                <summary class="workflow-runs__header">
+                    <span class="workflow-runs__label">
+                        { format!("Revision {}", revision.number) }
+                    </span>
+                    if props.current {
+                        <StatusPill tone={StatusTone::Ok} label="Current" />
+                    }
+                    if let Some(original) = revision.restored_from {
+                        <StatusPill
+                            tone={StatusTone::Neutral}
+                            label="Restored"
+                            title={Some(AttrValue::from(format!("Put back revision {original}.")))}
+                        />
+                    }
+                    <span class="workflow-runs__when" title={format_iso8601(revision.saved_at)}>
+                        { short_relative(revision.saved_at) }
+                        if let Some(by) = &revision.saved_by {
+                            { format!(" · by {by}") }
+                        }
+                    </span>
+                </summary>
+
+                <div class="workflow-preview">
+                    if first {
+                        <p class="workflow-preview__note">
+                            { "The first version kept, so there is nothing earlier to compare it with." }
+                        </p>
+                    } else {
+                        { match &*diff {
+                            None => html! { <p class="workflow-runs__empty">{ "Loading…" }</p> },
+                            Some(Err(message)) => html! {
+                                <Alert
+                                    kind={AlertKind::Error}
+                                    title="We could not compare this revision with the one before it."
+                                    message={message.clone()}
+                                />
+                            },
+                            Some(Ok(diff)) => change_list(diff),
+                        } }
+                    }
+
+                    if let Some(message) = (*error).clone() {
+                        <Alert
+                            kind={AlertKind::Error}
+                            title="We could not restore this revision."
+                            message={message}
+                        />
+                    }
+
+                    if !props.current {
+                        <div class="workflow__confirm-actions">
+                            <Button onclick={on_restore} busy={*busy}>
+                                { "Restore this version" }
+                            </Button>
+                        </div>
+                    }
+                </div>
+            </details>
+        </li>
+    }
+}
+
+/// What one revision changed from another, a setting to a line.
+fn change_list(diff: &RevisionDiff) -> Html {
+    if diff.changes.is_empty() {
+        return html! {
+            <p class="workflow-preview__empty">
+                { format!("Saved without changing anything from revision {}.", diff.from) }
+            </p>
+        };
+    }
+
+    let shown = |value: &Option<serde_json::Value>| match value {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+
+    html! {
+        <div class="workflow-preview__section">
+            <span class="workflow-preview__label">
+                { format!("Changed from revision {}", diff.from) }
+            </span>
+            <ul class="workflow-history__changes">
+                { for diff.changes.iter().map(|change: &RevisionChange| html! {
+                    <li class="workflow-history__change">
+                        <code class="workflow-history__path">{ &change.path }</code>
+                        if change.before.is_some() {
+                            <del class="workflow-history__before">{ shown(&change.before) }</del>
+                        }
+                        if change.after.is_some() {
+                            <ins class="workflow-history__after">{ shown(&change.after) }</ins>
+                        }
+                    </li>
+                }) }
+            </ul>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct AddWorkflowProps {
+    descriptor: WorkflowTypeDescriptor,
+    connections: Vec<ConnectionSummary>,
+    on_created: Callback<()>,
+    oncancel: Callback<()>,
+}
+
+#[function_component(AddWorkflow)]
+fn add_workflow(props: &AddWorkflowProps) -> Html {
+    let error = use_state(|| None::<String>);
+    let busy = use_state(|| false);
+
+    let on_submit = {
+        let (type_id, error, busy, on_created) = (
+            props.descriptor.id.clone(),
+            error.clone(),
+            busy.clone(),
+            props.on_created.clone(),
+        );
+
+        Callback::from(move |values: WorkflowValues| {
+            let (type_id, error, busy, on_created) = (
+                type_id.clone(),
+                error.clone(),
+                busy.clone(),
+                on_created.clone(),
+            );
+
+            wasm_bindgen_futures::spawn_local(async move {
+                busy.set(true);
+                error.set(None);
+
+                match api::create_workflow(
+                    &type_id,
+                    &values.config,
+                    values.schedule.as_deref(),
+                    values.enabled,
+                )
+                .await
+                {
+                    Ok(_) => on_created.emit(()),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+
+                busy.set(false);
+            });
+        })
+    };
+
+    html! {
+        <div class="form-modal" role="dialog" aria-modal="true" aria-labelledby="add-workflow-title">
+                    <div class="form-modal__panel">
+                        <div class="form-modal__header">
+                            <h3 id="add-workflow-title" class="form-modal__title">
+                                { format!("Add {} workflow", props.descriptor.name) }
+                            </h3>
+                        </div>
+
+                        <div class="form-modal__body form-modal__body--workflow">
+                            <p class="workflows__form-description">{ props.descriptor.description.clone() }</p>
+
+                            <Documentation markdown={props.descriptor.documentation.clone()} />
 
                             if let Some(message) = (*error).clone() {
                                 <Alert
@@ -941,6 +2085,12 @@ struct WorkflowFormProps {
     #[prop_or_default]
     initial: Option<WorkflowValues>,
 
+    /// The saved workflow being edited, if there is one. A webhook workflow can
+    /// only be previewed against a delivery it has already received, so without
+    /// this there is no preview to offer for one.
+    #[prop_or_default]
+    workflow: Option<String>,
+
     submit_label: AttrValue,
     busy: bool,
     onsubmit: Callback<WorkflowValues>,
@@ -1076,6 +2226,30 @@ fn workflow_form(props: &WorkflowFormProps) -> Html {
         .clone()
         .map(|cancel| Callback::from(move |_: MouseEvent| cancel.emit(())));
 
+    let preview = use_state(|| None::<Result<WorkflowPreview, String>>);
+    let previewing = use_state(|| false);
+
+    let previewable = props.workflow.is_some()
+        || matches!(props.descriptor.trigger, WorkflowTrigger::Cron { .. });
+
+    let on_preview = {
+        let (config, preview, previewing) = (config.clone(), preview.clone(), previewing.clone());
+        let (workflow, type_id) = (props.workflow.clone(), props.descriptor.id.clone());
+
+        Callback::from(move |_| {
+            let (config, preview, previewing) =
+                ((*config).clone(), preview.clone(), previewing.clone());
+            let (workflow, type_id) = (workflow.clone(), type_id.clone());
+
+            previewing.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let found = api::preview_workflow(workflow.as_deref(), &type_id, &config).await;
+                preview.set(Some(found.map_err(|err| err.to_string())));
+                previewing.set(false);
+            });
+        })
+    };
+
     html! {
         <div class="workflow-form">
             <DynamicForm
@@ -1085,6 +2259,7 @@ fn workflow_form(props: &WorkflowFormProps) -> Html {
                 connections={props.connections.clone()}
                 options={(*options).clone()}
                 disabled={props.busy}
+                workflow={props.workflow.clone().map(AttrValue::from)}
             />
 
             if matches!(props.descriptor.trigger, WorkflowTrigger::Cron { .. }) {
@@ -1125,16 +2300,141 @@ fn workflow_form(props: &WorkflowFormProps) -> Html {
                     { props.submit_label.clone() }
                 </Button>
 
+                if previewable {
+                    <Button
+                        onclick={on_preview}
+                        busy={*previewing}
+                        disabled={props.busy}
+                    >
+                        { "Preview" }
+                    </Button>
+                }
+
                 if let Some(on_cancel) = on_cancel {
                     <Button kind={ButtonKind::Subtle} onclick={on_cancel} disabled={props.busy}>
                         { "Cancel" }
                     </Button>
                 }
             </div>
+
+            {
+                match &*preview {
+                    Some(Ok(found)) => html! { <Preview preview={found.clone()} /> },
+                    Some(Err(message)) => html! {
+                        <Alert
+                            kind={AlertKind::Error}
+                            title="We could not preview this workflow."
+                            message={message.clone()}
+                        />
+                    },
+                    None => html! {},
+                }
+            }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct PreviewProps {
+    preview: WorkflowPreview,
+}
+
+/// What a run of the form's current settings would have done.
+///
+/// Every item the run looked at is listed, including the ones the filter
+/// dropped, because "why did this not come through?" is the question a preview
+/// is most often asked to answer.
+#[function_component(Preview)]
+fn preview(props: &PreviewProps) -> Html {
+    let preview = &props.preview;
+
+    html! {
+        <div class="workflow-preview">
+            if let Some(replayed) = preview.replayed {
+                <p class="workflow-preview__note" title={format_iso8601(replayed)}>
+                    { format!("Replayed against the delivery received {}.", short_relative(replayed)) }
+                </p>
+            }
+
+            if let Some(message) = &preview.error {
+                <Alert
+                    kind={AlertKind::Warning}
+                    title="The run stopped partway."
+                    message={message.clone()}
+                />
+            }
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Items" }</span>
+                if preview.items.is_empty() {
+                    <p class="workflow-preview__empty">{ "Nothing was collected." }</p>
+                }
+                { item_list(&preview.items) }
+            </div>
+
+            <div class="workflow-preview__section">
+                <span class="workflow-preview__label">{ "Tasks" }</span>
+                if preview.tasks.is_empty() {
+                    <p class="workflow-preview__empty">{ "Nothing would be filed." }</p>
+                }
+                { task_list(&preview.tasks) }
+            </div>
+
+            if !preview.effects.is_empty() {
+                <div class="workflow-preview__section">
+                    <span class="workflow-preview__label">{ "Also held back" }</span>
+                    <ul class="workflow-preview__items">
+                        { for preview.effects.iter().map(|effect| html! { <li>{ effect }</li> }) }
+                    </ul>
+                </div>
+            }
         </div>
     }
 }
 
+/// Each item a run looked at, and whether its filter kept it.
+fn item_list(items: &[PreviewItem]) -> Html {
+    html! {
+        <ul class="workflow-preview__items">
+            { for items.iter().map(|item| {
+                let (tone, label) = match (&item.error, item.matched) {
+                    (Some(_), _) => (StatusTone::Error, "Unreadable"),
+                    (None, true) => (StatusTone::Ok, "Matched"),
+                    (None, false) => (StatusTone::Neutral, "Skipped"),
+                };
+
+                html! {
+                    <li class="workflow-preview__item">
+                        <StatusPill
+                            {tone}
+                            {label}
+                            title={item.error.clone().map(AttrValue::from)}
+                        />
+                        <span>{ &item.item }</span>
+                    </li>
+                }
+            }) }
+        </ul>
+    }
+}
+
+/// The tasks a run filed, or would have, each opening onto what it was handed.
+fn task_list(tasks: &[PreviewTask]) -> Html {
+    html! {
+        { for tasks.iter().map(|task| html! {
+            <details class="workflow-preview__task">
+                <summary>
+                    { task.title.clone().unwrap_or_else(|| task.job.clone()) }
+                </summary>
+                if let Some(description) = &task.description {
+                    <p class="workflow-preview__description">{ description }</p>
+                }
+                <JsonHighlight value={task.payload.clone()} />
+            </details>
+        }) }
+    }
+}
+
 /// The configuration a new workflow of this type starts from.
 fn defaults_of(descriptor: &WorkflowTypeDescriptor) -> serde_json::Value {
     let mut config = serde_json::json!({});